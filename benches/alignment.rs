@@ -0,0 +1,51 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks encapsulation on the AVX2 backend to a public key on a
+//! 64-byte boundary against the same key one byte past it, where every
+//! vector load of the key straddles two cache lines.
+//!
+//! ```bash
+//! cargo bench --features avx2,force-backend --bench alignment
+//! ```
+
+#![allow(missing_docs)]
+
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion,
+};
+use kyberlib::acceleration::{force_backend, reset_backend};
+use kyberlib::{
+    derive, encapsulate, Acceleration, AlignedPublicKey,
+    KYBER_PUBLIC_KEY_BYTES,
+};
+use rand_core::OsRng;
+
+// Room for a key shifted by one byte from a 64-byte boundary.
+#[repr(C, align(64))]
+struct Shifted([u8; KYBER_PUBLIC_KEY_BYTES + 64]);
+
+fn alignment(c: &mut Criterion) {
+    if force_backend(Acceleration::Avx2).is_err() {
+        return;
+    }
+    let public = derive(&[3u8; 64]).unwrap().public;
+    let aligned = AlignedPublicKey::from(public);
+    let mut shifted = Shifted([0u8; KYBER_PUBLIC_KEY_BYTES + 64]);
+    shifted.0[1..=KYBER_PUBLIC_KEY_BYTES].copy_from_slice(&public);
+    let unaligned = &shifted.0[1..=KYBER_PUBLIC_KEY_BYTES];
+
+    let mut group = c.benchmark_group("avx2 encapsulate");
+    let _ = group.bench_function("aligned key", |b| {
+        b.iter(|| encapsulate(black_box(&*aligned), &mut OsRng))
+    });
+    let _ = group.bench_function("unaligned key", |b| {
+        b.iter(|| encapsulate(black_box(unaligned), &mut OsRng))
+    });
+    group.finish();
+    reset_backend();
+}
+
+criterion_group!(benches, alignment);
+
+criterion_main!(benches);
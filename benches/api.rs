@@ -1,4 +1,6 @@
+//! Benchmarks for the KEM API.
 #![cfg(feature = "benchmarking")]
+#![allow(missing_docs)]
 use criterion::{criterion_group, criterion_main, Criterion};
 use kyberlib::{
     kem::{encrypt_message, generate_key_pair},
@@ -6,12 +8,12 @@ use kyberlib::{
 };
 
 // Benchmarking key generation
-pub fn keypair(c: &mut Criterion) {
+fn keypair(c: &mut Criterion) {
     let mut _rng = rand::thread_rng(); //placeholder
     let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
     let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
     let bufs = Some(([1u8; 32].as_slice(), [255u8; 32].as_slice()));
-    c.bench_function("Keypair Generation", |b| {
+    let _ = c.bench_function("Keypair Generation", |b| {
         b.iter(|| {
             generate_key_pair(&mut pk, &mut sk, &mut _rng, bufs)
                 .unwrap();
@@ -20,13 +22,13 @@ pub fn keypair(c: &mut Criterion) {
 }
 
 // Encapsulating a single public key
-pub fn encap(c: &mut Criterion) {
+fn encap(c: &mut Criterion) {
     let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
     let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
     let pk = decode_hex(PK);
     let mut _rng = rand::thread_rng();
     let encap_buf = Some([255u8; 32].as_slice());
-    c.bench_function("Encapsulate", |b| {
+    let _ = c.bench_function("Encapsulate", |b| {
         b.iter(|| {
             encrypt_message(
                 &mut ct, &mut ss, &pk, &mut _rng, encap_buf,
@@ -37,10 +39,10 @@ pub fn encap(c: &mut Criterion) {
 }
 
 // Decapsulating a single correct ciphertext
-pub fn decap(c: &mut Criterion) {
+fn decap(c: &mut Criterion) {
     let sk = decode_hex(SK);
     let ct = decode_hex(CT);
-    c.bench_function("Decapsulate", |b| {
+    let _ = c.bench_function("Decapsulate", |b| {
         b.iter(|| {
             let _dec = decapsulate(&ct, &sk);
         })
@@ -48,10 +50,10 @@ pub fn decap(c: &mut Criterion) {
 }
 
 // Decapsulating a single incorrect ciphertext
-pub fn decap_fail(c: &mut Criterion) {
+fn decap_fail(c: &mut Criterion) {
     let sk = decode_hex(BAD_SK);
     let ct = decode_hex(CT);
-    c.bench_function("Decapsulate Failure", |b| {
+    let _ = c.bench_function("Decapsulate Failure", |b| {
         b.iter(|| {
             let _dec = decapsulate(&ct, &sk);
         })
@@ -59,7 +61,7 @@ pub fn decap_fail(c: &mut Criterion) {
 }
 
 // Decodes a hex string into a vector of bytes
-pub fn decode_hex(s: &str) -> Vec<u8> {
+fn decode_hex(s: &str) -> Vec<u8> {
     (0..s.len())
         .step_by(2)
         .map(|i| {
@@ -153,5 +155,5 @@ const BAD_SK: &str = "010c5a48053837472f7dcacc4e74709f3a29e7177e83198ee8228390d4
 #[cfg(all(feature = "kyber1024", feature = "90s"))]
 const CT: &str = "45039c00f10120df9ab21dc99e39e131db36e772d6dbf1674b01c373397c0ac184c6cf643b72cca5b1114563f2e3d77c2662ca7733fd44b44d720b83d2f88800213a1c2c1bad43bbcd8d96d1859ee68e5c31c6c897cf4986fc78ac79eeba67965d65ebb62ad54b45a1cc58a54608b82308932299baa74bff835e81b3fa420a8b7678c16fbd5bfc5d0a48e5114db91a11e205ab7c576ce8f2bdf1fe1d7eef6850709ec0fcd05f5f3e9c796e1b3dbbab0a89a7c87f0c97adf666183ceff8d99dc89ee6ba12b5aa578d297c35a7897cd2256b1975a7dfa1c58a3b764e6b5ae5ad7a6036ef725b0c3cdf6a46505d3ed6b2f22ba8d339180a0b73ffee93ede006c55911ea09a265959be22a527902315bb2eadc1286116019d28477bebe4274483829e9fe7403cda1996b2118fe7599c0ba0b5b4248f55d3d256a97c31511f1ddee17568fa8035cd05bf92d9d8708c20369cae3e79067eb01d5c6fe92f18be94e3e04f45035c29e4ffe01df1c8c4c2b65c4adbfa798fbd4dfbfc755ea2de1820077ccf90feb427436bc3a96481f70561439c79259162f5cdcb644350030e7e9f5d97951bc3a040fc54d26a8776fd89fa0bb14bc98656590257629585b69b6314ecb8ff783fe089419fe9f5e8c40d3385f444ac659b3cc2233cfdc08244f44442a5aa12d6ff3486955ca595e1f83b3193910002b8a65cd6e7a57e046d08761ad374f03133228efd4b9fc794dc5d889e080af2925138559029eab45ba269867a72eec08f32e286b0cc6155bf676503a71a82eafa63e3403f020539ab5de1b574208b05d71bb150eb7c9a8c8766137ce16d8024947cad656926199fc9f750e4bbd8cc99ff3962db5c4bb3d6862947a80f0f2c073c949b69914bfe1dd4e76949c459ee71febbab354d315426a06f99c949be3e41e690d0323da8a18534c31cdb6ee5c7d707eba044ef69251beeb619bf5fb8cabc72c5617c80b0ed77e33dc0e49f8e458facfafb13cdc51c7a50271b6e9428d2913190a35cbad304dcb7b1c24003215de05cbdd34248e57a7a014a2b7a9cb295e2f0194d701ba75f045fdd690874f4a2ebb6081eabf1a4d17ac0b4b7082bbd8852be96505e7c6c0d2bfef26d34a7540e8445144dc7f4243b89d52c59901aaa6faaaa87529925752012a3be0418c70983550733359d23babf17c994339bc411695cb8ec813fc986bf676ac17b7483047279706a7da32fc8c832ae377b09faa50abc0e9319f433f82fed700fff7d3a0b09f3b39fb417e07d07d2db595b9aa2244714633e857a676d6fc01bbdcab69d3b09fb5fb69544cc3ef1413a74336fe59bc2d6fe10a8b3360ab0a011d308d4b267119a822865ec56c6e8e94b0ffa0290fe5dbb391e68e9c06c7f5375a678f37e7f23c1e6eacca4a82a9d20bf14bfccdbc9ac6106782391f2b900d836361e16e563a282992b8ce469a4a166a97c43b13dd67e046eb4b31a63c78075e0126684b8b17cfb3eb37aec09ed44a84c796decd851567fd5aab299ee7e10be6239e70acc00c74e62471a99bf0ef0341ec7cc31e51ac1a96f372c2542c7365fbb32a983344acf738609b1bb6b1e13d8a7a69703e45c54b68586a03c19a119ed7db72d6ea4701bc04784b05607e9bf9c3e1fc4694cef58e3efdbf72e71082cbfd4b4cd957b01b92501ce2af996249a1e54601a9973bfadd8db33e6494f1a1109c9353d3fdc96d5c9c07316d5e75b275bd0603da71cd9896b9c975c91bf3cf1f91adf93eb89af7294cd6c047dd3f88299a8ce56f5aadc607bcaa10bbc8189f6beff6ff5c65b93566ac5d0e4bd6783ba9a0f9072cf5ee6729006d252a110a3bfc5a61d4be6b92f0035e9e3f1a8d725fdd8f3fe129f54567fedf8fee52053279946424a76e8d1522894096f6cc790b49456394fc79812d6bacd2eeeb9fbd1c0aac8cec4767db75ee53a828b62913532c033f7fb5adefe7e7f5e59c077c4dcf3527007fc62ebccff96e3867adf6d4f6a77293da5caffdef083fb7dd3adbd1c4a90cb8b76e8bf65b9b4c6c24b22bf7bbfd8bf6f02e105f880c74d199b5c6482c241b181e8d001aab471cd83eda8d38925702ab1fbe0dd18ba370916dd1c8bdbfa090bc5f2621fb891d94126f0627ced17cf29f74e6c04129de0132f8c19fd3221038ee651ce69f7d43df0b8a0a9e5b5a5deee26396e952cdb55e9855daf870dde03f048b3b8bdfeb435960";
 
-criterion_group!(benches, keypair);
+criterion_group!(benches, keypair, encap, decap, decap_fail);
 criterion_main!(benches);
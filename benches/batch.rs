@@ -0,0 +1,91 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks the batch operations at 64 and 1024 items against a loop
+//! over the single-item API. With the `parallel` feature the batch runs
+//! on the rayon pool, one thread per core unless `RAYON_NUM_THREADS`
+//! says otherwise:
+//!
+//! ```bash
+//! cargo bench --features batch --bench batch
+//! cargo bench --features parallel --bench batch
+//! ```
+
+#![allow(missing_docs)]
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+    Throughput,
+};
+use kyberlib::batch::{
+    decapsulate_batch, encapsulate_batch, keypair_batch,
+};
+use kyberlib::{decapsulate, encapsulate, keypair, PublicKey};
+use rand_core::OsRng;
+
+fn batch(c: &mut Criterion, n: usize) {
+    let mut group = c.benchmark_group("batch");
+    let _ = group.throughput(Throughput::Elements(n as u64));
+    let _ = group.sample_size(10);
+    let keys = keypair(&mut OsRng).unwrap();
+    let pks: Vec<PublicKey> = vec![keys.public; n];
+    let cts: Vec<_> = encapsulate_batch(&pks, &mut OsRng)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.unwrap().0)
+        .collect();
+
+    let _ = group.bench_function(
+        BenchmarkId::new("keypair loop", n),
+        |b| {
+            b.iter(|| {
+                (0..n)
+                    .map(|_| keypair(&mut OsRng).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        },
+    );
+    let _ = group.bench_function(BenchmarkId::new("keypair", n), |b| {
+        b.iter(|| keypair_batch(n, &mut OsRng).unwrap())
+    });
+    let _ = group.bench_function(
+        BenchmarkId::new("encapsulate loop", n),
+        |b| {
+            b.iter(|| {
+                pks.iter()
+                    .map(|pk| encapsulate(black_box(pk), &mut OsRng))
+                    .collect::<Vec<_>>()
+            })
+        },
+    );
+    let _ =
+        group.bench_function(BenchmarkId::new("encapsulate", n), |b| {
+            b.iter(|| {
+                encapsulate_batch(black_box(&pks), &mut OsRng).unwrap()
+            })
+        });
+    let _ = group.bench_function(
+        BenchmarkId::new("decapsulate loop", n),
+        |b| {
+            b.iter(|| {
+                cts.iter()
+                    .map(|ct| decapsulate(black_box(ct), &keys.secret))
+                    .collect::<Vec<_>>()
+            })
+        },
+    );
+    let _ = group
+        .bench_function(BenchmarkId::new("decapsulate", n), |b| {
+            b.iter(|| decapsulate_batch(black_box(&cts), &keys.secret))
+        });
+    group.finish();
+}
+
+fn sizes(c: &mut Criterion) {
+    batch(c, 64);
+    batch(c, 1024);
+}
+
+criterion_group!(benches, sizes);
+
+criterion_main!(benches);
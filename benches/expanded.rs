@@ -0,0 +1,56 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks cold-start decapsulation, from stored bytes to the first
+//! shared secret: with the secret key alone, which expands the matrix
+//! within the call, and with an expanded key cache, with and without
+//! the matrix. The warm expanded decapsulation is the lower bound.
+//!
+//! ```bash
+//! cargo bench --features expanded --bench expanded
+//! ```
+
+#![allow(missing_docs)]
+
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion,
+};
+use kyberlib::expanded::ExpandedSecretKey;
+use kyberlib::{decapsulate, encapsulate, keypair};
+use rand_core::OsRng;
+
+fn cold_start(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cold start decapsulation");
+    let keys = keypair(&mut OsRng).unwrap();
+    let (ct, _) = encapsulate(&keys.public, &mut OsRng).unwrap();
+    let expanded = ExpandedSecretKey::new(&keys.secret);
+    let cache = expanded.to_cache_bytes(true);
+    let compact_cache = expanded.to_cache_bytes(false);
+
+    let _ = group.bench_function("secret key", |b| {
+        b.iter(|| decapsulate(black_box(&ct), black_box(&keys.secret)))
+    });
+    let _ = group.bench_function("cache", |b| {
+        b.iter(|| {
+            ExpandedSecretKey::from_cache_bytes(black_box(&cache))
+                .unwrap()
+                .decapsulate(black_box(&ct))
+        })
+    });
+    let _ = group.bench_function("cache without matrix", |b| {
+        b.iter(|| {
+            ExpandedSecretKey::from_cache_bytes(black_box(
+                &compact_cache,
+            ))
+            .unwrap()
+            .decapsulate(black_box(&ct))
+        })
+    });
+    let _ = group.bench_function("warm", |b| {
+        b.iter(|| expanded.decapsulate(black_box(&ct)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cold_start);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks sampling the public matrix A from its seed, the XOF and
+//! the rejection sampling of key generation.
+//!
+//! ```bash
+//! cargo bench --features research --bench gen_matrix
+//! ```
+
+#![allow(missing_docs)]
+
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion,
+};
+use kyberlib::params::{KYBER_SECURITY_PARAMETER, KYBER_SYM_BYTES};
+use kyberlib::research::{gen_matrix_entry, Polynomial};
+
+const K: usize = KYBER_SECURITY_PARAMETER;
+
+fn gen_matrix(c: &mut Criterion) {
+    let seed = [0x5au8; KYBER_SYM_BYTES];
+    let _ = c.bench_function("matrix generation", |b| {
+        b.iter(|| {
+            let mut a = [[Polynomial::new(); K]; K];
+            for (i, row) in a.iter_mut().enumerate() {
+                for (j, entry) in row.iter_mut().enumerate() {
+                    gen_matrix_entry(
+                        entry,
+                        black_box(&seed),
+                        i,
+                        j,
+                        false,
+                    )
+                    .unwrap();
+                }
+            }
+            a
+        })
+    });
+}
+
+criterion_group!(benches, gen_matrix);
+
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks the SHAKE256 transcript hash, which runs the Keccak-f[1600]
+//! permutation 24 times for Kyber1024. Compare a run with the
+//! `armv8-sha3` feature on AArch64 against a baseline saved without it:
+//!
+//! ```bash
+//! cargo bench --bench keccak -- --save-baseline software
+//! cargo bench --features armv8-sha3 --bench keccak -- --baseline software
+//! ```
+
+#![allow(missing_docs)]
+
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion,
+};
+use kyberlib::{encapsulate, keypair, transcript_hash};
+use rand_core::OsRng;
+
+fn keccak(c: &mut Criterion) {
+    let keys = keypair(&mut OsRng).unwrap();
+    let (ct, ss) = encapsulate(&keys.public, &mut OsRng).unwrap();
+    let _ = c.bench_function("transcript hash", |b| {
+        b.iter(|| transcript_hash(black_box(&keys.public), &ct, &ss))
+    });
+}
+
+criterion_group!(benches, keccak);
+
+criterion_main!(benches);
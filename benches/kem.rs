@@ -0,0 +1,60 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks key generation, encapsulation and decapsulation on the
+//! reference backend and, where the CPU supports them, the AVX2 and
+//! AVX-512 ones, or the NEON one on AArch64, the RVV one on riscv64,
+//! and the portable SIMD one on nightly.
+//!
+//! ```bash
+//! cargo bench --features avx512,force-backend --bench kem
+//! cargo bench --features neon,force-backend --bench kem
+//! cargo bench --features rvv,force-backend --bench kem
+//! cargo +nightly bench --features portable-simd,force-backend --bench kem
+//! ```
+
+#![allow(missing_docs)]
+
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion,
+};
+use kyberlib::acceleration::{force_backend, reset_backend};
+use kyberlib::{decapsulate, encapsulate, keypair, Acceleration};
+use rand_core::OsRng;
+
+fn kem(c: &mut Criterion, backend: Acceleration) {
+    if force_backend(backend).is_err() {
+        return;
+    }
+    let mut group = c.benchmark_group(backend.name());
+    let keys = keypair(&mut OsRng).unwrap();
+    let (ct, _) = encapsulate(&keys.public, &mut OsRng).unwrap();
+    let _ = group.bench_function("keypair", |b| {
+        b.iter(|| keypair(&mut OsRng).unwrap())
+    });
+    let _ = group.bench_function("encapsulate", |b| {
+        b.iter(|| encapsulate(black_box(&keys.public), &mut OsRng))
+    });
+    let _ = group.bench_function("decapsulate", |b| {
+        b.iter(|| decapsulate(black_box(&ct), &keys.secret))
+    });
+    group.finish();
+    reset_backend();
+}
+
+fn backends(c: &mut Criterion) {
+    for backend in [
+        Acceleration::Scalar,
+        Acceleration::Avx2,
+        Acceleration::Avx512,
+        Acceleration::Neon,
+        Acceleration::Rvv,
+        Acceleration::PortableSimd,
+    ] {
+        kem(c, backend);
+    }
+}
+
+criterion_group!(benches, backends);
+
+criterion_main!(benches);
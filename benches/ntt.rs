@@ -0,0 +1,48 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks the iterative forward NTT against the recursive one.
+//!
+//! ```bash
+//! cargo bench --features research --bench ntt
+//! ```
+
+#![allow(missing_docs)]
+
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion,
+};
+use kyberlib::params::{KYBER_N, KYBER_Q};
+use kyberlib::research::Polynomial;
+
+fn input() -> Polynomial {
+    let mut coeffs = [0i16; KYBER_N];
+    for (i, c) in coeffs.iter_mut().enumerate() {
+        *c = (i * 1021 % KYBER_Q) as i16;
+    }
+    Polynomial::from_coeffs(coeffs)
+}
+
+fn ntt(c: &mut Criterion) {
+    let mut group = c.benchmark_group("NTT");
+    let poly = input();
+    let _ = group.bench_function("iterative", |b| {
+        b.iter(|| {
+            let mut p = black_box(poly);
+            p.ntt();
+            p
+        })
+    });
+    let _ = group.bench_function("recursive", |b| {
+        b.iter(|| {
+            let mut p = black_box(poly);
+            p.ntt_recursive();
+            p
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ntt);
+
+criterion_main!(benches);
@@ -0,0 +1,258 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The benchmark suite compared across pull requests and releases.
+//!
+//! Every group that the enabled features allow is run, each with its
+//! throughput set so Criterion also reports operations or coefficients
+//! per second. The IDs below are the directories under
+//! `target/criterion` that baselines are stored in, so they must not
+//! change; add new IDs rather than renaming old ones.
+//!
+//! | ID | Needs |
+//! |----|-------|
+//! | `kem/{keypair,encapsulate,decapsulate}/<level>-<backend>` | |
+//! | `levels/{keypair,encapsulate,decapsulate}/<level>` | `levels` |
+//! | `poly/{ntt,invntt,basemul,compress,tobytes}` | `research` |
+//! | `gen_matrix/<level>` | `research` |
+//! | `batch/{keypair,encapsulate,decapsulate}/64` | `batch` |
+//!
+//! `<level>` is `kyber512`, `kyber768` or `kyber1024` and `<backend>` the
+//! [`Acceleration::name`] of each backend the CPU supports.
+//!
+//! ```bash
+//! cargo bench --features levels,research,batch,force-backend --bench suite
+//! cargo run --release --example bench_summary
+//! ```
+
+use criterion::{
+    black_box, criterion_main, BenchmarkId, Criterion, Throughput,
+};
+use kyberlib::acceleration::{force_backend, reset_backend};
+use kyberlib::params::KYBER_SECURITY_PARAMETER;
+use kyberlib::{decapsulate, encapsulate, keypair, Acceleration};
+use rand_core::OsRng;
+
+// The level the crate-level API is built for.
+const LEVEL: &str = match KYBER_SECURITY_PARAMETER {
+    2 => "kyber512",
+    3 => "kyber768",
+    _ => "kyber1024",
+};
+
+fn kem(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kem");
+    let _ = group.throughput(Throughput::Elements(1));
+    for backend in [
+        Acceleration::Scalar,
+        Acceleration::Avx2,
+        Acceleration::Avx512,
+        Acceleration::Neon,
+        Acceleration::Rvv,
+        Acceleration::PortableSimd,
+    ] {
+        if force_backend(backend).is_err() {
+            continue;
+        }
+        let id = format!("{}-{}", LEVEL, backend.name());
+        let keys = keypair(&mut OsRng).unwrap();
+        let (ct, _) = encapsulate(&keys.public, &mut OsRng).unwrap();
+        let _ = group
+            .bench_function(BenchmarkId::new("keypair", &id), |b| {
+                b.iter(|| keypair(&mut OsRng).unwrap())
+            });
+        let _ = group.bench_function(
+            BenchmarkId::new("encapsulate", &id),
+            |b| {
+                b.iter(|| {
+                    encapsulate(black_box(&keys.public), &mut OsRng)
+                })
+            },
+        );
+        let _ = group.bench_function(
+            BenchmarkId::new("decapsulate", &id),
+            |b| b.iter(|| decapsulate(black_box(&ct), &keys.secret)),
+        );
+        reset_backend();
+    }
+    group.finish();
+}
+
+#[cfg(feature = "levels")]
+fn levels(c: &mut Criterion) {
+    use kyberlib::levels::{
+        self, Kyber1024, Kyber512, Kyber768, Params,
+    };
+
+    fn level<P: Params>(
+        group: &mut criterion::BenchmarkGroup<
+            '_,
+            criterion::measurement::WallTime,
+        >,
+    ) {
+        let id = P::NAME.to_lowercase();
+        let keys = levels::keypair::<P, _>(&mut OsRng).unwrap();
+        let (ct, _) = levels::encapsulate::<P, _>(
+            keys.public.as_ref(),
+            &mut OsRng,
+        )
+        .unwrap();
+        let _ = group
+            .bench_function(BenchmarkId::new("keypair", &id), |b| {
+                b.iter(|| levels::keypair::<P, _>(&mut OsRng).unwrap())
+            });
+        let _ = group.bench_function(
+            BenchmarkId::new("encapsulate", &id),
+            |b| {
+                b.iter(|| {
+                    levels::encapsulate::<P, _>(
+                        black_box(keys.public.as_ref()),
+                        &mut OsRng,
+                    )
+                })
+            },
+        );
+        let _ = group.bench_function(
+            BenchmarkId::new("decapsulate", &id),
+            |b| {
+                b.iter(|| {
+                    levels::decapsulate::<P>(
+                        black_box(ct.as_ref()),
+                        keys.secret.as_ref(),
+                    )
+                })
+            },
+        );
+    }
+
+    let mut group = c.benchmark_group("levels");
+    let _ = group.throughput(Throughput::Elements(1));
+    level::<Kyber512>(&mut group);
+    level::<Kyber768>(&mut group);
+    level::<Kyber1024>(&mut group);
+    group.finish();
+}
+
+#[cfg(feature = "research")]
+fn poly(c: &mut Criterion) {
+    use kyberlib::params::{KYBER_N, KYBER_Q};
+    use kyberlib::research::{basemul_acc, Polynomial};
+
+    let mut coeffs = [0i16; KYBER_N];
+    for (i, c) in coeffs.iter_mut().enumerate() {
+        *c = (i * 1021 % KYBER_Q) as i16;
+    }
+    let a = Polynomial::from_coeffs(coeffs);
+    let mut b = a;
+    b.ntt();
+
+    let mut group = c.benchmark_group("poly");
+    let _ = group.throughput(Throughput::Elements(KYBER_N as u64));
+    let _ = group.bench_function("ntt", |bench| {
+        bench.iter(|| {
+            let mut p = black_box(a);
+            p.ntt();
+            p
+        })
+    });
+    let _ = group.bench_function("invntt", |bench| {
+        bench.iter(|| {
+            let mut p = black_box(b);
+            p.inv_ntt();
+            p
+        })
+    });
+    let _ = group.bench_function("basemul", |bench| {
+        bench.iter(|| basemul_acc(&[black_box(b)], &[b]).unwrap())
+    });
+    let _ = group.bench_function("compress", |bench| {
+        bench.iter(|| black_box(a).compress())
+    });
+    let _ = group.bench_function("tobytes", |bench| {
+        bench.iter(|| black_box(a).to_bytes())
+    });
+    group.finish();
+}
+
+#[cfg(feature = "research")]
+fn gen_matrix(c: &mut Criterion) {
+    use kyberlib::params::KYBER_SYM_BYTES;
+    use kyberlib::research::{gen_matrix_entry, Polynomial};
+
+    const K: usize = KYBER_SECURITY_PARAMETER;
+    let seed = [0x5au8; KYBER_SYM_BYTES];
+    let mut group = c.benchmark_group("gen_matrix");
+    let _ = group.throughput(Throughput::Elements((K * K) as u64));
+    let _ = group.bench_function(LEVEL, |b| {
+        b.iter(|| {
+            let mut a = [[Polynomial::new(); K]; K];
+            for (i, row) in a.iter_mut().enumerate() {
+                for (j, entry) in row.iter_mut().enumerate() {
+                    gen_matrix_entry(
+                        entry,
+                        black_box(&seed),
+                        i,
+                        j,
+                        false,
+                    )
+                    .unwrap();
+                }
+            }
+            a
+        })
+    });
+    group.finish();
+}
+
+#[cfg(feature = "batch")]
+fn batch(c: &mut Criterion) {
+    use kyberlib::batch::{
+        decapsulate_batch, encapsulate_batch, keypair_batch,
+    };
+    use kyberlib::PublicKey;
+
+    // The IDs of the 64-item runs of the batch bench.
+    const N: usize = 64;
+    let keys = keypair(&mut OsRng).unwrap();
+    let pks: Vec<PublicKey> = vec![keys.public; N];
+    let cts: Vec<_> = encapsulate_batch(&pks, &mut OsRng)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.unwrap().0)
+        .collect();
+
+    let mut group = c.benchmark_group("batch");
+    let _ = group.throughput(Throughput::Elements(N as u64));
+    let _ = group.sample_size(10);
+    let _ = group.bench_function(BenchmarkId::new("keypair", N), |b| {
+        b.iter(|| keypair_batch(N, &mut OsRng).unwrap())
+    });
+    let _ =
+        group.bench_function(BenchmarkId::new("encapsulate", N), |b| {
+            b.iter(|| {
+                encapsulate_batch(black_box(&pks), &mut OsRng).unwrap()
+            })
+        });
+    let _ = group
+        .bench_function(BenchmarkId::new("decapsulate", N), |b| {
+            b.iter(|| decapsulate_batch(black_box(&cts), &keys.secret))
+        });
+    group.finish();
+}
+
+// `criterion_group!` takes no `#[cfg]` on its targets.
+fn benches() {
+    let mut c = Criterion::default().configure_from_args();
+    kem(&mut c);
+    #[cfg(feature = "levels")]
+    levels(&mut c);
+    #[cfg(feature = "research")]
+    {
+        poly(&mut c);
+        gen_matrix(&mut c);
+    }
+    #[cfg(feature = "batch")]
+    batch(&mut c);
+}
+
+criterion_main!(benches);
@@ -3,42 +3,41 @@
 //! Currently, it only instructs Cargo to re-run this build script if `build.rs` is changed.
 #[rustfmt::skip]
 fn main() {
-    #[cfg(not(feature = "wasm"))]
-    {
-        #[cfg(feature = "avx2")]
-        {
-            const FILES: [&str; 5] = ["basemul", "fq", "invntt", "ntt", "shuffle"];
+    // The AVX2 assembly is only linked into x86_64 builds, whatever other
+    // features are enabled alongside.
+    #[cfg(feature = "avx2")]
+    if std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("x86_64") {
+        const FILES: [&str; 5] = ["basemul", "fq", "invntt", "ntt", "shuffle"];
 
-            #[cfg(feature = "nasm")]
-            {
-                const ROOT: &str = "src/avx2/nasm/";
-                let paths = FILES.iter().map(|file| format!("{}{}.asm", ROOT, file));
+        #[cfg(feature = "nasm")]
+        {
+            const ROOT: &str = "src/avx2/nasm/";
+            let paths = FILES.iter().map(|file| format!("{}{}.asm", ROOT, file));
 
-                let mut nasm = nasm_rs::Build::new();
-                let mut linker = cc::Build::new();
+            let mut nasm = nasm_rs::Build::new();
+            let mut linker = cc::Build::new();
 
-                nasm.files(paths);
-                nasm.include(ROOT);
+            nasm.files(paths);
+            nasm.include(ROOT);
 
-                for o in nasm.compile_objects().expect("
+            for o in nasm.compile_objects().expect("
 Compiling NASM files:
 Ensure it is installed and in your path
 https://www.nasm.us/",
-                ) {
-                    linker.object(o);
-                }
-                linker.compile("kyberlib");
+            ) {
+                linker.object(o);
             }
+            linker.compile("kyberlib");
+        }
 
-            #[cfg(not(feature = "nasm"))]
-            {
-                const ROOT: &str = "src/avx2/";
-                let paths = FILES.iter().map(|file| format!("{}{}.S", ROOT, file));
-                cc::Build::new()
-                    .include(ROOT)
-                    .files(paths)
-                    .compile("kyberlib");
-            }
+        #[cfg(not(feature = "nasm"))]
+        {
+            const ROOT: &str = "src/avx2/";
+            let paths = FILES.iter().map(|file| format!("{}{}.S", ROOT, file));
+            cc::Build::new()
+                .include(ROOT)
+                .files(paths)
+                .compile("kyberlib");
         }
     }
 }
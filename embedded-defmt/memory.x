@@ -0,0 +1,7 @@
+/* nRF52840: 1 MiB of flash and 256 KiB of RAM. Adjust for your part;
+   Kyber1024 needs about 32 KiB of stack in release builds. */
+MEMORY
+{
+  FLASH : ORIGIN = 0x00000000, LENGTH = 1024K
+  RAM : ORIGIN = 0x20000000, LENGTH = 256K
+}
@@ -0,0 +1,63 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Firmware measuring the stack high-water marks of `keypair`,
+//! `encapsulate` and `decapsulate` on the device, the no_std counterpart
+//! of `tests/test_stack_probe.rs`, and logging them over defmt and RTT.
+//!
+//! ```shell
+//! DEFMT_LOG=info cargo build -p embedded-defmt --features device --release \
+//!     --target thumbv7em-none-eabihf --bin stack-probe
+//! probe-rs run --chip nRF52840_xxAA \
+//!     target/thumbv7em-none-eabihf/release/stack-probe
+//! ```
+
+#![no_std]
+#![no_main]
+#![deny(missing_docs)]
+
+use cortex_m_rt::entry;
+use defmt_rtt as _;
+use kyberlib::stack_probe::measure_in_place;
+use kyberlib::{decapsulate, derive, encapsulate};
+use panic_probe as _;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+// The deepest stack use reported, in bytes. With the 512 bytes the probe
+// keeps unpainted, this must fit in the RAM below `main`'s frame, which
+// `memory.x` gives all of the 256 KiB not used by statics.
+const MAX_DEPTH: usize = 128 * 1024;
+
+#[entry]
+fn main() -> ! {
+    // SAFETY: the stack grows down from the top of RAM and only `main`
+    // runs on it, with no interrupts enabled; the statics of this image
+    // take a few kilobytes at the bottom of RAM, which leaves the
+    // `MAX_DEPTH` plus 512 bytes below this frame unused.
+    let (keys, depth) =
+        unsafe { measure_in_place(MAX_DEPTH, || derive(&[7u8; 64])) };
+    let keys = defmt::unwrap!(keys);
+    defmt::info!("keypair: {} bytes of stack", depth);
+
+    let mut rng = ChaCha20Rng::from_seed([9u8; 32]);
+    // SAFETY: as above.
+    let (encapsulated, depth) = unsafe {
+        measure_in_place(MAX_DEPTH, || {
+            encapsulate(&keys.public, &mut rng)
+        })
+    };
+    let (ct, ss) = defmt::unwrap!(encapsulated);
+    defmt::info!("encapsulate: {} bytes of stack", depth);
+
+    // SAFETY: as above.
+    let (decapsulated, depth) = unsafe {
+        measure_in_place(MAX_DEPTH, || decapsulate(&ct, &keys.secret))
+    };
+    defmt::assert_eq!(defmt::unwrap!(decapsulated), ss);
+    defmt::info!("decapsulate: {} bytes of stack", depth);
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Firmware logging kyberlib values over defmt and RTT: the parameter
+//! set, a keypair and a ciphertext, then a decapsulation that fails
+//! because the ciphertext was truncated in transit.
+//!
+//! ```shell
+//! rustup target add thumbv7em-none-eabihf
+//! DEFMT_LOG=info cargo build -p embedded-defmt --features device --release \
+//!     --target thumbv7em-none-eabihf
+//! probe-rs run --chip nRF52840_xxAA \
+//!     target/thumbv7em-none-eabihf/release/embedded-defmt
+//! ```
+//!
+//! Without `DEFMT_LOG=info` only errors are logged. `memory.x` describes
+//! an nRF52840; adjust it for another part.
+
+#![no_std]
+#![no_main]
+#![deny(missing_docs)]
+
+use cortex_m_rt::entry;
+use defmt_rtt as _;
+use kyberlib::logging::{Params, ShortCiphertext, ShortPublicKey};
+use kyberlib::{acceleration, decapsulate, derive, encapsulate};
+use panic_probe as _;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+#[entry]
+fn main() -> ! {
+    defmt::info!("{} on the {} backend", Params, acceleration());
+
+    // Fixed seeds keep the example deterministic; a device would seed
+    // both from its hardware RNG.
+    let keys = defmt::unwrap!(derive(&[7u8; 64]));
+    defmt::info!("{}", keys);
+    defmt::info!("publishing {}", ShortPublicKey(&keys.public));
+
+    let mut rng = ChaCha20Rng::from_seed([9u8; 32]);
+    let (ct, _) = defmt::unwrap!(encapsulate(&keys.public, &mut rng));
+    let received = &ct[..ct.len() - 1];
+
+    match decapsulate(received, &keys.secret) {
+        Ok(_) => defmt::error!("a truncated ciphertext decapsulated"),
+        Err(err) => defmt::warn!(
+            "decapsulating {} failed: {}",
+            ShortCiphertext(received),
+            err
+        ),
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
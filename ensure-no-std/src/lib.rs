@@ -0,0 +1,77 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Builds kyberlib with `default-features = false` for a target with no
+//! std and no allocator, `thumbv7em-none-eabihf`:
+//!
+//! ```shell
+//! rustup target add thumbv7em-none-eabihf
+//! cargo build -p ensure-no-std --target thumbv7em-none-eabihf
+//! ```
+//!
+//! `tests/test_no_std.rs` runs this build when the target is installed.
+//! The library only has to compile: a dependency reaching for std breaks
+//! the build. The `ensure-no-alloc` binary, built with the `link-check`
+//! feature, also has to link without a global allocator, which catches a
+//! core API starting to allocate.
+
+#![no_std]
+#![deny(missing_docs)]
+
+use kyberlib::{
+    decapsulate, derive, encapsulate, CryptoRng, KyberLibError, RngCore,
+};
+
+// Replays a fixed buffer, standing in for a hardware RNG. Not random.
+struct FixedRng([u8; 32], usize);
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.0[self.1 % self.0.len()];
+            self.1 += 1;
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+fn round_trip() -> Result<bool, KyberLibError> {
+    let keys = derive(&[7u8; 64])?;
+    let mut rng = FixedRng([9u8; 32], 0);
+    let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+    Ok(decapsulate(&ct, &keys.secret)? == ss)
+}
+
+/// Runs a key generation, encapsulation and decapsulation from fixed
+/// seeds and returns 0 if the shared secrets agree.
+#[no_mangle]
+pub extern "C" fn ensure_no_std_round_trip() -> i32 {
+    match round_trip() {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(_) => 2,
+    }
+}
+
+#[cfg(target_os = "none")]
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo<'_>) -> ! {
+    loop {}
+}
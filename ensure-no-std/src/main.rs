@@ -0,0 +1,22 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A bare-metal executable using the core KEM API without a global
+//! allocator. If anything it reaches needed the heap, linking would fail
+//! with "no global memory allocator found":
+//!
+//! ```shell
+//! cargo build -p ensure-no-std --features link-check --target thumbv7em-none-eabihf
+//! ```
+
+#![no_std]
+#![no_main]
+
+/// The entry point, eventually spinning forever.
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    let status = ensure_no_std::ensure_no_std_round_trip();
+    loop {
+        core::hint::black_box(status);
+    }
+}
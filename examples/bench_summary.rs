@@ -0,0 +1,100 @@
+//! Prints the KEM results of the benchmark suite as a Markdown table for
+//! release notes, one line per level and backend.
+//!
+//! # Usage
+//!
+//! ```bash
+//! cargo bench --features levels,force-backend --bench suite
+//! cargo run --release --example bench_summary
+//! ```
+//!
+//! The results are read from `$CRITERION_HOME`, else from
+//! `$CARGO_TARGET_DIR/criterion`, else from `target/criterion`, as
+//! Criterion writes them. Each cell is the median of the last run.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+const OPS: [&str; 3] = ["keypair", "encapsulate", "decapsulate"];
+
+fn criterion_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("CRITERION_HOME") {
+        return dir.into();
+    }
+    env::var_os("CARGO_TARGET_DIR")
+        .map_or_else(|| PathBuf::from("target"), PathBuf::from)
+        .join("criterion")
+}
+
+// The median point estimate in nanoseconds of an `estimates.json`,
+// found by key rather than by parsing the whole document.
+fn median_ns(json: &str) -> Option<f64> {
+    let median = &json[json.find("\"median\"")?..];
+    let value = &median[median.find("\"point_estimate\":")? + 17..];
+    let end = value.find([',', '}'])?;
+    value[..end].trim().parse().ok()
+}
+
+// The IDs measured in `group`, e.g. `kyber1024-avx2`.
+fn ids(dir: &Path, group: &str) -> Vec<String> {
+    let mut ids: Vec<String> =
+        fs::read_dir(dir.join(group).join(OPS[0]))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().join("new").is_dir())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+    ids.sort();
+    ids
+}
+
+fn main() {
+    let dir = criterion_dir();
+    let rows: Vec<(&str, String)> = ["kem", "levels"]
+        .iter()
+        .flat_map(|g| ids(&dir, g).into_iter().map(move |id| (*g, id)))
+        .collect();
+    if rows.is_empty() {
+        eprintln!(
+            "no results in {}; run `cargo bench --features \
+             force-backend --bench suite` first",
+            dir.display()
+        );
+        std::process::exit(1);
+    }
+
+    println!(
+        "| Level | Backend | keypair | encapsulate | decapsulate |"
+    );
+    println!(
+        "|-------|---------|---------|-------------|-------------|"
+    );
+    for (group, id) in rows {
+        // The levels group runs the reference code at every level.
+        let (level, backend) = match id.split_once('-') {
+            Some((level, backend)) => (level, backend),
+            None => (id.as_str(), "levels"),
+        };
+        let cells: Vec<String> = OPS
+            .iter()
+            .map(|op| {
+                let path = dir
+                    .join(group)
+                    .join(op)
+                    .join(&id)
+                    .join("new/estimates.json");
+                fs::read_to_string(path)
+                    .ok()
+                    .and_then(|json| median_ns(&json))
+                    .map_or_else(
+                        || "-".to_string(),
+                        |ns| format!("{:.1} µs", ns / 1000.0),
+                    )
+            })
+            .collect();
+        println!("| {} | {} | {} |", level, backend, cells.join(" | "));
+    }
+}
@@ -0,0 +1,57 @@
+//! Deterministic KEM self test for `wasm32-unknown-unknown` hosts
+//! without JavaScript or WASI.
+//!
+//! Built without the `os-rng` feature, the module links no entropy
+//! backend and has no imports, so any wasm runtime can instantiate it.
+//! The keypair is derived from a fixed seed and encapsulation draws from
+//! a seeded ChaCha20 RNG; in a real plugin the seeds would come from the
+//! host, or an entropy import would be registered with
+//! `kyberlib::rng::register_entropy_source` (the `custom-entropy`
+//! feature).
+//!
+//! # Usage
+//!
+//! ```shell
+//! cargo build --release --example wasm_deterministic \
+//!     --target wasm32-unknown-unknown \
+//!     --no-default-features --features kyber1024,90s-fixslice
+//! wasmtime run --invoke kyberlib_self_test \
+//!     target/wasm32-unknown-unknown/release/examples/wasm_deterministic.wasm
+//! ```
+//!
+//! which prints `0` when the shared secrets agree.
+//!
+//! # License
+//!
+//! This example is licensed under either of the following, at your choice:
+//!
+//! - Apache License, Version 2.0 (LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0)
+//! - MIT License (LICENSE-MIT or http://opensource.org/licenses/MIT)
+
+use kyberlib::*;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+fn self_test() -> Result<bool, KyberLibError> {
+    let keys = derive(&[7u8; 64])?;
+    let mut rng = ChaCha20Rng::from_seed([9u8; 32]);
+    let (ciphertext, shared_secret) =
+        encapsulate(&keys.public, &mut rng)?;
+    let decapsulated = decapsulate(&ciphertext, &keys.secret)?;
+
+    // The same seeds give the same ciphertext.
+    let mut rng = ChaCha20Rng::from_seed([9u8; 32]);
+    let (again, _) = encapsulate(&keys.public, &mut rng)?;
+    Ok(decapsulated == shared_secret && again == ciphertext)
+}
+
+/// Returns 0 if the deterministic round trip succeeds, 1 if the shared
+/// secrets disagree and 2 on an error.
+#[no_mangle]
+pub extern "C" fn kyberlib_self_test() -> i32 {
+    match self_test() {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(_) => 2,
+    }
+}
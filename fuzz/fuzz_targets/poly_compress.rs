@@ -0,0 +1,39 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Compares the SIMD poly_compress and poly_decompress with the scalar
+//! reference. The input is read as 256 little-endian `u16`, reduced mod
+//! q, for compression, and its first KYBER_POLY_COMPRESSED_BYTES bytes
+//! as a compressed polynomial for decompression; short inputs are padded
+//! with zeros.
+//!
+//! ```bash
+//! cargo +nightly fuzz run poly_compress
+//! cargo +nightly fuzz run poly_compress --no-default-features --features portable-simd
+//! ```
+
+#![no_main]
+
+use kyberlib::fuzzing::*;
+use kyberlib::params::{KYBER_N, KYBER_POLY_COMPRESSED_BYTES, KYBER_Q};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut input = [0u8; 2 * KYBER_N];
+    let len = data.len().min(input.len());
+    input[..len].copy_from_slice(&data[..len]);
+
+    let mut coeffs = [0i16; KYBER_N];
+    for (c, b) in coeffs.iter_mut().zip(input.chunks_exact(2)) {
+        *c = (u16::from_le_bytes([b[0], b[1]]) % KYBER_Q as u16) as i16;
+    }
+    if let Some(simd) = poly_compress_simd(&coeffs) {
+        assert_eq!(simd, poly_compress_reference(&coeffs), "{:?}", coeffs);
+    }
+
+    let mut bytes = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+    bytes.copy_from_slice(&input[..KYBER_POLY_COMPRESSED_BYTES]);
+    if let Some(simd) = poly_decompress_simd(&bytes) {
+        assert_eq!(simd, poly_decompress_reference(&bytes), "{:?}", bytes);
+    }
+});
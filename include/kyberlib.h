@@ -0,0 +1,112 @@
+/* kyberlib C API, see src/capi.rs. Regenerate with cbindgen --config cbindgen.toml. */
+
+#ifndef KYBERLIB_H
+#define KYBERLIB_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Returned on success.
+ */
+#define KYBERLIB_OK 0
+
+/**
+ * Returned when a buffer pointer is null.
+ */
+#define KYBERLIB_ERROR_NULL_POINTER -1
+
+/**
+ * Returned when the operating system fails to provide random bytes.
+ */
+#define KYBERLIB_ERROR_RANDOMNESS -2
+
+/**
+ * Returned when a buffer length does not match the parameter set.
+ */
+#define KYBERLIB_ERROR_INVALID_LENGTH -3
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Size in bytes of a public key.
+ */
+size_t kyberlib_public_key_bytes(void);
+
+/**
+ * Size in bytes of a secret key.
+ */
+size_t kyberlib_secret_key_bytes(void);
+
+/**
+ * Size in bytes of a ciphertext.
+ */
+size_t kyberlib_ciphertext_bytes(void);
+
+/**
+ * Size in bytes of a shared secret.
+ */
+size_t kyberlib_shared_secret_bytes(void);
+
+/**
+ * A static, NUL-terminated description of an error code.
+ */
+const char *kyberlib_error_message(int code);
+
+/**
+ * Generates a keypair into `pk_out` and `sk_out`.
+ *
+ * On failure the secret key buffer is zeroed.
+ *
+ * # Safety
+ *
+ * `pk_out` must be null or valid for writes of `pk_len` bytes, `sk_out`
+ * null or valid for writes of `sk_len` bytes, and the buffers must not
+ * overlap.
+ */
+int kyberlib_keypair(uint8_t *pk_out, size_t pk_len, uint8_t *sk_out, size_t sk_len);
+
+/**
+ * Encapsulates to `pk`, writing the ciphertext to `ct_out` and the
+ * shared secret to `ss_out`.
+ *
+ * # Safety
+ *
+ * Each pointer must be null or valid for reads (`pk`) or writes
+ * (`ct_out`, `ss_out`) of its length, and the buffers must not overlap.
+ */
+int kyberlib_encapsulate(uint8_t *ct_out,
+                         size_t ct_len,
+                         uint8_t *ss_out,
+                         size_t ss_len,
+                         const uint8_t *pk,
+                         size_t pk_len);
+
+/**
+ * Decapsulates `ct` with `sk`, writing the shared secret to `ss_out`.
+ *
+ * A ciphertext of the right length that does not decapsulate yields a
+ * pseudo-random shared secret rather than an error.
+ *
+ * # Safety
+ *
+ * Each pointer must be null or valid for reads (`ct`, `sk`) or writes
+ * (`ss_out`) of its length, and `ss_out` must not overlap the inputs.
+ */
+int kyberlib_decapsulate(uint8_t *ss_out,
+                         size_t ss_len,
+                         const uint8_t *ct,
+                         size_t ct_len,
+                         const uint8_t *sk,
+                         size_t sk_len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KYBERLIB_H */
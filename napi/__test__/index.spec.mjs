@@ -0,0 +1,71 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+import test from 'ava'
+import { Worker } from 'node:worker_threads'
+
+import {
+  CIPHERTEXT_BYTES,
+  PUBLIC_KEY_BYTES,
+  SECRET_KEY_BYTES,
+  SHARED_SECRET_BYTES,
+  decapsulate,
+  encapsulate,
+  keypair,
+} from '../index.js'
+
+const invalidLength = {
+  name: 'KyberLibError',
+  code: 'KYBERLIB_ERROR_INVALID_LENGTH',
+}
+
+test('round trip', async (t) => {
+  const { publicKey, secretKey } = await keypair()
+  t.true(Buffer.isBuffer(publicKey))
+  t.is(publicKey.length, PUBLIC_KEY_BYTES)
+  t.is(secretKey.length, SECRET_KEY_BYTES)
+
+  const { ciphertext, sharedSecret } = await encapsulate(publicKey)
+  t.is(ciphertext.length, CIPHERTEXT_BYTES)
+  t.is(sharedSecret.length, SHARED_SECRET_BYTES)
+  t.deepEqual(await decapsulate(ciphertext, secretKey), sharedSecret)
+})
+
+test('tampered ciphertext is implicitly rejected', async (t) => {
+  const { publicKey, secretKey } = await keypair()
+  const { ciphertext, sharedSecret } = await encapsulate(publicKey)
+  ciphertext[0] ^= 1
+  t.notDeepEqual(await decapsulate(ciphertext, secretKey), sharedSecret)
+})
+
+test('mismatched lengths reject with the stable code', async (t) => {
+  const { publicKey, secretKey } = await keypair()
+  const { ciphertext } = await encapsulate(publicKey)
+
+  const error = await t.throwsAsync(encapsulate(publicKey.subarray(1)), invalidLength)
+  t.is(error.errno, -3)
+  await t.throwsAsync(decapsulate(ciphertext.subarray(1), secretKey), invalidLength)
+  await t.throwsAsync(decapsulate(ciphertext, Buffer.alloc(32)), invalidLength)
+})
+
+test('concurrent operations share the threadpool', async (t) => {
+  const { publicKey, secretKey } = await keypair()
+  const results = await Promise.all(Array.from({ length: 16 }, () => encapsulate(publicKey)))
+  for (const { ciphertext, sharedSecret } of results) {
+    t.deepEqual(await decapsulate(ciphertext, secretKey), sharedSecret)
+  }
+})
+
+test('loads in a worker thread', async (t) => {
+  const source = `
+    const { parentPort } = require('node:worker_threads')
+    const { keypair, encapsulate, decapsulate } = require(${JSON.stringify(new URL('../index.js', import.meta.url).pathname)})
+    ;(async () => {
+      const keys = await keypair()
+      const { ciphertext, sharedSecret } = await encapsulate(keys.publicKey)
+      parentPort.postMessage(sharedSecret.equals(await decapsulate(ciphertext, keys.secretKey)))
+    })()
+  `
+  const worker = new Worker(source, { eval: true })
+  t.true(await new Promise((resolve, reject) => worker.once('message', resolve).once('error', reject)))
+})
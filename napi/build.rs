@@ -0,0 +1,8 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Sets up linking against the Node-API symbols of the host process.
+
+fn main() {
+    napi_build::setup();
+}
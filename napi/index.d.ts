@@ -0,0 +1,48 @@
+/* tslint:disable */
+/* eslint-disable */
+
+/* auto-generated by NAPI-RS */
+
+/** Size in bytes of a public key. */
+export const PUBLIC_KEY_BYTES: number
+/** Size in bytes of a secret key. */
+export const SECRET_KEY_BYTES: number
+/** Size in bytes of a ciphertext. */
+export const CIPHERTEXT_BYTES: number
+/** Size in bytes of a shared secret. */
+export const SHARED_SECRET_BYTES: number
+/** A public and secret key. */
+export interface KeyPair {
+  /** The public key. */
+  publicKey: Buffer
+  /** The secret key. */
+  secretKey: Buffer
+}
+/** A ciphertext and the shared secret it encapsulates. */
+export interface Encapsulation {
+  /** The ciphertext to send to the holder of the secret key. */
+  ciphertext: Buffer
+  /** The shared secret. */
+  sharedSecret: Buffer
+}
+/**
+ * Generates a keypair from the operating system RNG.
+ *
+ * Rejects with `KYBERLIB_ERROR_RANDOMNESS` if the RNG fails.
+ */
+export function keypair(): Promise<KeyPair>
+/**
+ * Encapsulates a fresh shared secret to `publicKey`.
+ *
+ * Rejects with `KYBERLIB_ERROR_INVALID_LENGTH` if the public key has the
+ * wrong length and `KYBERLIB_ERROR_RANDOMNESS` if the RNG fails.
+ */
+export function encapsulate(publicKey: Buffer): Promise<Encapsulation>
+/**
+ * Decapsulates `ciphertext` with `secretKey`.
+ *
+ * A ciphertext of the right length that does not decapsulate yields a
+ * pseudo-random shared secret rather than an error. Rejects with
+ * `KYBERLIB_ERROR_INVALID_LENGTH` if either input has the wrong length.
+ */
+export function decapsulate(ciphertext: Buffer, secretKey: Buffer): Promise<Buffer>
@@ -0,0 +1,214 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Node.js bindings for kyberlib.
+//!
+//! Each function returns a promise and runs the Kyber arithmetic as an
+//! `AsyncTask` on the libuv threadpool, so the event loop is never blocked
+//! and worker threads can each load the addon.
+//!
+//! Input `Buffer`s are read in place on the threadpool rather than copied;
+//! they must not be modified until the promise settles. Results are copied
+//! out into fresh `Buffer`s, and the Rust copies of secret keys and shared
+//! secrets are zeroed. The `Buffer`s handed to JavaScript cannot be wiped
+//! reliably; overwrite them with `buf.fill(0)` when done as a best effort.
+//!
+//! Failures reject with an `Error` whose `name` is `KyberLibError`, whose
+//! `code` is one of the stable C API code names, e.g.
+//! `KYBERLIB_ERROR_INVALID_LENGTH`, and whose `errno` is its number.
+
+use kyberlib::{
+    KyberLibError, KYBER_CIPHERTEXT_BYTES, KYBER_PUBLIC_KEY_BYTES,
+    KYBER_SECRET_KEY_BYTES, KYBER_SHARED_SECRET_BYTES,
+};
+use napi::bindgen_prelude::*;
+use napi::{Env, JsUnknown, Task};
+use napi_derive::napi;
+use rand::rngs::OsRng;
+use zeroize::Zeroize;
+
+/// Size in bytes of a public key.
+#[napi]
+pub const PUBLIC_KEY_BYTES: u32 = KYBER_PUBLIC_KEY_BYTES as u32;
+/// Size in bytes of a secret key.
+#[napi]
+pub const SECRET_KEY_BYTES: u32 = KYBER_SECRET_KEY_BYTES as u32;
+/// Size in bytes of a ciphertext.
+#[napi]
+pub const CIPHERTEXT_BYTES: u32 = KYBER_CIPHERTEXT_BYTES as u32;
+/// Size in bytes of a shared secret.
+#[napi]
+pub const SHARED_SECRET_BYTES: u32 = KYBER_SHARED_SECRET_BYTES as u32;
+
+/// A public and secret key.
+#[napi(object)]
+pub struct KeyPair {
+    /// The public key.
+    pub public_key: Buffer,
+    /// The secret key.
+    pub secret_key: Buffer,
+}
+
+/// A ciphertext and the shared secret it encapsulates.
+#[napi(object)]
+pub struct Encapsulation {
+    /// The ciphertext to send to the holder of the secret key.
+    pub ciphertext: Buffer,
+    /// The shared secret.
+    pub shared_secret: Buffer,
+}
+
+// The stable code of the C API for a kyberlib error, see `src/capi.rs`.
+// The other variants cannot arise from these functions.
+fn error_code(err: KyberLibError) -> Option<(&'static str, i32)> {
+    match err {
+        KyberLibError::RandomBytesGeneration => {
+            Some(("KYBERLIB_ERROR_RANDOMNESS", -2))
+        }
+        KyberLibError::InvalidInput | KyberLibError::InvalidLength => {
+            Some(("KYBERLIB_ERROR_INVALID_LENGTH", -3))
+        }
+        _ => None,
+    }
+}
+
+// Builds the `KyberLibError` the promise rejects with.
+fn to_js_error(env: Env, err: KyberLibError) -> Error {
+    let build = || -> Result<JsUnknown> {
+        let reason =
+            Error::new(Status::GenericFailure, err.to_string());
+        let mut error = env.create_error(reason)?;
+        error.set_named_property(
+            "name",
+            env.create_string("KyberLibError")?,
+        )?;
+        if let Some((code, errno)) = error_code(err) {
+            error
+                .set_named_property("code", env.create_string(code)?)?;
+            error.set_named_property(
+                "errno",
+                env.create_int32(errno)?,
+            )?;
+        }
+        Ok(error.into_unknown())
+    };
+    match build() {
+        Ok(error) => Error::from(error),
+        Err(error) => error,
+    }
+}
+
+/// Generates a keypair on the threadpool.
+pub struct KeypairTask;
+
+impl Task for KeypairTask {
+    type Output =
+        core::result::Result<kyberlib::Keypair, KyberLibError>;
+    type JsValue = KeyPair;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(kyberlib::keypair(&mut OsRng))
+    }
+
+    fn resolve(
+        &mut self,
+        env: Env,
+        output: Self::Output,
+    ) -> Result<KeyPair> {
+        let mut keys = output.map_err(|err| to_js_error(env, err))?;
+        let pair = KeyPair {
+            public_key: keys.public.to_vec().into(),
+            secret_key: keys.secret.to_vec().into(),
+        };
+        keys.secret.zeroize();
+        Ok(pair)
+    }
+}
+
+/// Encapsulates to a public key on the threadpool.
+pub struct EncapsulateTask {
+    public_key: Buffer,
+}
+
+impl Task for EncapsulateTask {
+    type Output = kyberlib::Encapsulated;
+    type JsValue = Encapsulation;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(kyberlib::encapsulate(&self.public_key, &mut OsRng))
+    }
+
+    fn resolve(
+        &mut self,
+        env: Env,
+        output: Self::Output,
+    ) -> Result<Encapsulation> {
+        let (ct, mut ss) =
+            output.map_err(|err| to_js_error(env, err))?;
+        let encapsulation = Encapsulation {
+            ciphertext: ct.to_vec().into(),
+            shared_secret: ss.to_vec().into(),
+        };
+        ss.zeroize();
+        Ok(encapsulation)
+    }
+}
+
+/// Decapsulates a ciphertext on the threadpool.
+pub struct DecapsulateTask {
+    ciphertext: Buffer,
+    secret_key: Buffer,
+}
+
+impl Task for DecapsulateTask {
+    type Output = kyberlib::Decapsulated;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(kyberlib::decapsulate(&self.ciphertext, &self.secret_key))
+    }
+
+    fn resolve(
+        &mut self,
+        env: Env,
+        output: Self::Output,
+    ) -> Result<Buffer> {
+        let mut ss = output.map_err(|err| to_js_error(env, err))?;
+        let shared_secret = ss.to_vec().into();
+        ss.zeroize();
+        Ok(shared_secret)
+    }
+}
+
+/// Generates a keypair from the operating system RNG.
+///
+/// Rejects with `KYBERLIB_ERROR_RANDOMNESS` if the RNG fails.
+#[napi(ts_return_type = "Promise<KeyPair>")]
+pub fn keypair() -> AsyncTask<KeypairTask> {
+    AsyncTask::new(KeypairTask)
+}
+
+/// Encapsulates a fresh shared secret to `publicKey`.
+///
+/// Rejects with `KYBERLIB_ERROR_INVALID_LENGTH` if the public key has the
+/// wrong length and `KYBERLIB_ERROR_RANDOMNESS` if the RNG fails.
+#[napi(ts_return_type = "Promise<Encapsulation>")]
+pub fn encapsulate(public_key: Buffer) -> AsyncTask<EncapsulateTask> {
+    AsyncTask::new(EncapsulateTask { public_key })
+}
+
+/// Decapsulates `ciphertext` with `secretKey`.
+///
+/// A ciphertext of the right length that does not decapsulate yields a
+/// pseudo-random shared secret rather than an error. Rejects with
+/// `KYBERLIB_ERROR_INVALID_LENGTH` if either input has the wrong length.
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn decapsulate(
+    ciphertext: Buffer,
+    secret_key: Buffer,
+) -> AsyncTask<DecapsulateTask> {
+    AsyncTask::new(DecapsulateTask {
+        ciphertext,
+        secret_key,
+    })
+}
@@ -0,0 +1,268 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Python bindings for kyberlib.
+//!
+//! Built into the `kyberlib` extension module with maturin; see
+//! `README.md` for usage and `tests/` for the pytest suite. Every function
+//! releases the GIL while the Kyber arithmetic runs, so the module can be
+//! called from several Python threads at once.
+
+use kyberlib::{
+    encrypt_message, generate_key_pair, KYBER_90S,
+    KYBER_CIPHERTEXT_BYTES, KYBER_PUBLIC_KEY_BYTES,
+    KYBER_SECRET_KEY_BYTES, KYBER_SECURITY_PARAMETER,
+    KYBER_SHARED_SECRET_BYTES,
+};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rand::rngs::OsRng;
+use zeroize::Zeroize;
+
+create_exception!(
+    kyberlib,
+    KyberLibError,
+    PyException,
+    "Base class of the errors raised by kyberlib."
+);
+create_exception!(
+    kyberlib,
+    InvalidInputError,
+    KyberLibError,
+    "An input is of the wrong length for this security level."
+);
+create_exception!(
+    kyberlib,
+    InvalidKeyError,
+    KyberLibError,
+    "A secret and public key do not match."
+);
+create_exception!(
+    kyberlib,
+    InvalidLengthError,
+    KyberLibError,
+    "The length of an input buffer is invalid."
+);
+create_exception!(
+    kyberlib,
+    DecapsulationError,
+    KyberLibError,
+    "A ciphertext could not be decapsulated."
+);
+create_exception!(
+    kyberlib,
+    RandomBytesGenerationError,
+    KyberLibError,
+    "The operating system failed to provide random bytes."
+);
+create_exception!(
+    kyberlib,
+    UnsealingError,
+    KyberLibError,
+    "A sealed secret key could not be opened."
+);
+create_exception!(
+    kyberlib,
+    InvalidShareError,
+    KyberLibError,
+    "A secret share is corrupted, repeated or belongs to another key."
+);
+create_exception!(
+    kyberlib,
+    InvalidSignatureError,
+    KyberLibError,
+    "A signature over a key does not verify."
+);
+
+// Raises the Python exception matching a kyberlib error, with the Rust
+// error's message.
+fn to_py_err(err: kyberlib::KyberLibError) -> PyErr {
+    use kyberlib::KyberLibError::*;
+    let message = err.to_string();
+    match err {
+        InvalidInput => InvalidInputError::new_err(message),
+        InvalidKey => InvalidKeyError::new_err(message),
+        InvalidLength => InvalidLengthError::new_err(message),
+        Decapsulation => DecapsulationError::new_err(message),
+        RandomBytesGeneration => {
+            RandomBytesGenerationError::new_err(message)
+        }
+        Unsealing => UnsealingError::new_err(message),
+        InvalidShare => InvalidShareError::new_err(message),
+        InvalidSignature => InvalidSignatureError::new_err(message),
+    }
+}
+
+/// keypair() -> tuple[bytes, bytes]
+///
+/// Generates a keypair from the operating system RNG and returns
+/// `(public_key, secret_key)`.
+///
+/// Raises `RandomBytesGenerationError` if the RNG fails.
+#[pyfunction]
+fn keypair(py: Python<'_>) -> PyResult<(Py<PyBytes>, Py<PyBytes>)> {
+    let mut keys = py
+        .allow_threads(|| kyberlib::keypair(&mut OsRng))
+        .map_err(to_py_err)?;
+    let pair = (
+        PyBytes::new_bound(py, &keys.public).unbind(),
+        PyBytes::new_bound(py, &keys.secret).unbind(),
+    );
+    keys.secret.zeroize();
+    Ok(pair)
+}
+
+/// encapsulate(pk: bytes) -> tuple[bytes, bytes]
+///
+/// Encapsulates to the public key `pk` and returns
+/// `(ciphertext, shared_secret)`.
+///
+/// Raises `InvalidInputError` if `pk` has the wrong length and
+/// `RandomBytesGenerationError` if the RNG fails.
+#[pyfunction]
+fn encapsulate(
+    py: Python<'_>,
+    pk: &[u8],
+) -> PyResult<(Py<PyBytes>, Py<PyBytes>)> {
+    let (ct, mut ss) = py
+        .allow_threads(|| kyberlib::encapsulate(pk, &mut OsRng))
+        .map_err(to_py_err)?;
+    let pair = (
+        PyBytes::new_bound(py, &ct).unbind(),
+        PyBytes::new_bound(py, &ss).unbind(),
+    );
+    ss.zeroize();
+    Ok(pair)
+}
+
+/// decapsulate(ct: bytes, sk: bytes) -> bytes
+///
+/// Decapsulates the ciphertext `ct` with the secret key `sk` and returns
+/// the shared secret. A ciphertext that does not decapsulate yields a
+/// pseudo-random secret rather than an error.
+///
+/// Raises `InvalidInputError` if either input has the wrong length.
+#[pyfunction]
+fn decapsulate(
+    py: Python<'_>,
+    ct: &[u8],
+    sk: &[u8],
+) -> PyResult<Py<PyBytes>> {
+    let mut ss = py
+        .allow_threads(|| kyberlib::decapsulate(ct, sk))
+        .map_err(to_py_err)?;
+    let secret = PyBytes::new_bound(py, &ss).unbind();
+    ss.zeroize();
+    Ok(secret)
+}
+
+/// _keypair_from_buffers(buf1: bytes, buf2: bytes) -> tuple[bytes, bytes]
+///
+/// Deterministic key generation from the two 32-byte buffers of a known
+/// answer test. For testing only.
+#[pyfunction]
+fn _keypair_from_buffers(
+    py: Python<'_>,
+    buf1: &[u8],
+    buf2: &[u8],
+) -> PyResult<(Py<PyBytes>, Py<PyBytes>)> {
+    let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+    let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
+    py.allow_threads(|| {
+        generate_key_pair(
+            &mut pk,
+            &mut sk,
+            &mut OsRng,
+            Some((buf1, buf2)),
+        )
+    })
+    .map_err(to_py_err)?;
+    let pair = (
+        PyBytes::new_bound(py, &pk).unbind(),
+        PyBytes::new_bound(py, &sk).unbind(),
+    );
+    sk.zeroize();
+    Ok(pair)
+}
+
+/// _encapsulate_from_buffer(pk: bytes, buf: bytes) -> tuple[bytes, bytes]
+///
+/// Deterministic encapsulation from the 32-byte buffer of a known answer
+/// test. For testing only.
+#[pyfunction]
+fn _encapsulate_from_buffer(
+    py: Python<'_>,
+    pk: &[u8],
+    buf: &[u8],
+) -> PyResult<(Py<PyBytes>, Py<PyBytes>)> {
+    if pk.len() != KYBER_PUBLIC_KEY_BYTES {
+        return Err(to_py_err(kyberlib::KyberLibError::InvalidInput));
+    }
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    py.allow_threads(|| {
+        encrypt_message(&mut ct, &mut ss, pk, &mut OsRng, Some(buf))
+    })
+    .map_err(to_py_err)?;
+    let pair = (
+        PyBytes::new_bound(py, &ct).unbind(),
+        PyBytes::new_bound(py, &ss).unbind(),
+    );
+    ss.zeroize();
+    Ok(pair)
+}
+
+/// CRYSTALS-Kyber key encapsulation, backed by the kyberlib Rust crate.
+///
+/// Keys, ciphertexts and shared secrets are `bytes`. Python cannot wipe a
+/// `bytes` object: secret keys and shared secrets stay in memory until the
+/// interpreter reuses it, and may be copied by the garbage collector or
+/// swapped to disk. The Rust side zeroes its own copies before returning.
+#[pymodule]
+#[pyo3(name = "kyberlib")]
+fn kyberlib_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_function(wrap_pyfunction!(keypair, m)?)?;
+    m.add_function(wrap_pyfunction!(encapsulate, m)?)?;
+    m.add_function(wrap_pyfunction!(decapsulate, m)?)?;
+    m.add_function(wrap_pyfunction!(_keypair_from_buffers, m)?)?;
+    m.add_function(wrap_pyfunction!(_encapsulate_from_buffer, m)?)?;
+
+    m.add("KyberLibError", py.get_type_bound::<KyberLibError>())?;
+    m.add(
+        "InvalidInputError",
+        py.get_type_bound::<InvalidInputError>(),
+    )?;
+    m.add("InvalidKeyError", py.get_type_bound::<InvalidKeyError>())?;
+    m.add(
+        "InvalidLengthError",
+        py.get_type_bound::<InvalidLengthError>(),
+    )?;
+    m.add(
+        "DecapsulationError",
+        py.get_type_bound::<DecapsulationError>(),
+    )?;
+    m.add(
+        "RandomBytesGenerationError",
+        py.get_type_bound::<RandomBytesGenerationError>(),
+    )?;
+    m.add("UnsealingError", py.get_type_bound::<UnsealingError>())?;
+    m.add(
+        "InvalidShareError",
+        py.get_type_bound::<InvalidShareError>(),
+    )?;
+    m.add(
+        "InvalidSignatureError",
+        py.get_type_bound::<InvalidSignatureError>(),
+    )?;
+
+    m.add("PUBLIC_KEY_BYTES", KYBER_PUBLIC_KEY_BYTES)?;
+    m.add("SECRET_KEY_BYTES", KYBER_SECRET_KEY_BYTES)?;
+    m.add("CIPHERTEXT_BYTES", KYBER_CIPHERTEXT_BYTES)?;
+    m.add("SHARED_SECRET_BYTES", KYBER_SHARED_SECRET_BYTES)?;
+    m.add("SECURITY_PARAMETER", KYBER_SECURITY_PARAMETER)?;
+    m.add("KYBER_90S", KYBER_90S)?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+# Copyright © 2024 kyberlib. All rights reserved.
+# SPDX-License-Identifier: Apache-2.0 OR MIT
+
+"""Tests for the kyberlib Python bindings, run with ``pytest``."""
+
+import pathlib
+import threading
+
+import pytest
+
+import kyberlib
+
+KAT_DIR = pathlib.Path(__file__).resolve().parents[2] / "tests" / "KAT"
+KAT_NAMES = {2: "tvecs512", 3: "tvecs768", 4: "tvecs1024"}
+
+
+def load_kats():
+    """Parses the KAT file the Rust tests use, as in tests/load/mod.rs."""
+    name = KAT_NAMES[kyberlib.SECURITY_PARAMETER]
+    if kyberlib.KYBER_90S:
+        name += "-90s"
+    path = KAT_DIR / name
+    if not path.exists():
+        pytest.skip(f"{path} not found, see tests/KAT/readme.md")
+    lines = path.read_text().splitlines()
+    for i in range(0, len(lines) - 7, 8):
+        values = [line.split(": ")[-1] for line in lines[i : i + 7]]
+        yield [bytes.fromhex(value) for value in values]
+
+
+def test_round_trip():
+    pk, sk = kyberlib.keypair()
+    assert len(pk) == kyberlib.PUBLIC_KEY_BYTES
+    assert len(sk) == kyberlib.SECRET_KEY_BYTES
+    ct, ss = kyberlib.encapsulate(pk)
+    assert len(ct) == kyberlib.CIPHERTEXT_BYTES
+    assert len(ss) == kyberlib.SHARED_SECRET_BYTES
+    assert kyberlib.decapsulate(ct, sk) == ss
+
+
+def test_tampered_ciphertext_is_implicitly_rejected():
+    pk, sk = kyberlib.keypair()
+    ct, ss = kyberlib.encapsulate(pk)
+    tampered = bytes([ct[0] ^ 1]) + ct[1:]
+    assert kyberlib.decapsulate(tampered, sk) != ss
+
+
+def test_wrong_lengths_raise_typed_exceptions():
+    pk, sk = kyberlib.keypair()
+    ct, _ = kyberlib.encapsulate(pk)
+    with pytest.raises(kyberlib.InvalidInputError):
+        kyberlib.encapsulate(pk[1:])
+    with pytest.raises(kyberlib.InvalidInputError):
+        kyberlib.decapsulate(ct + b"\0", sk)
+    with pytest.raises(kyberlib.KyberLibError):
+        kyberlib.decapsulate(ct, sk[:32])
+    assert issubclass(kyberlib.InvalidInputError, kyberlib.KyberLibError)
+    assert issubclass(kyberlib.KyberLibError, Exception)
+
+
+def test_arguments_must_be_bytes():
+    pk, _ = kyberlib.keypair()
+    with pytest.raises(TypeError):
+        kyberlib.encapsulate(list(pk))
+
+
+def test_concurrent_use_from_threads():
+    pk, sk = kyberlib.keypair()
+    failures = []
+
+    def worker():
+        for _ in range(20):
+            ct, ss = kyberlib.encapsulate(pk)
+            if kyberlib.decapsulate(ct, sk) != ss:
+                failures.append(ct)
+
+    threads = [threading.Thread(target=worker) for _ in range(4)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+    assert not failures
+
+
+def test_known_answers():
+    for buf1, buf2, pk, sk, encap_buf, ct, ss in load_kats():
+        assert kyberlib._keypair_from_buffers(buf1, buf2) == (pk, sk)
+        assert kyberlib._encapsulate_from_buffer(pk, encap_buf) == (ct, ss)
+        assert kyberlib.decapsulate(ct, sk) == ss
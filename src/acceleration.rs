@@ -0,0 +1,304 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Selects and reports which implementation of the polynomial
+//! arithmetic is live.
+//!
+//! The NTT and the rest of the polynomial arithmetic have a portable
+//! reference implementation and, on x86_64 with the `avx2` feature, an
+//! AVX2 one, on AArch64 with the `neon` feature a NEON one, on wasm32
+//! with the `wasm-simd` feature a SIMD128 one, on riscv64 with the `rvv`
+//! feature an RVV one, and with the nightly-only `portable-simd` feature
+//! a `core::simd` one for any other target.
+//! The fastest one the machine supports is picked on first use, and
+//! [`acceleration`] says which, so that logs can show whether the fast
+//! path is taken on a given machine:
+//!
+//! ```
+//! use kyberlib::{acceleration, Acceleration};
+//!
+//! match acceleration() {
+//!     Acceleration::Scalar => println!("kyberlib: portable NTT"),
+//!     simd => println!("kyberlib: {} NTT", simd),
+//! }
+//! ```
+
+#[cfg(feature = "force-backend")]
+use crate::error::KyberLibError;
+use core::fmt;
+#[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Instruction set extension used by the NTT and the other polynomial
+/// arithmetic.
+///
+/// `Sse2` is reserved for a backend still to come and is not returned
+/// by this version. More variants may be added.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Acceleration {
+    /// The portable reference implementation, without SIMD.
+    Scalar,
+    /// SSE2 on x86 and x86_64.
+    Sse2,
+    /// AVX2 on x86_64.
+    Avx2,
+    /// NEON on AArch64 and ARMv7.
+    Neon,
+    /// The 128-bit SIMD proposal on WebAssembly.
+    WasmSimd,
+    /// AVX-512 (F and BW) on x86_64.
+    Avx512,
+    /// Portable SIMD through `core::simd`, on any architecture.
+    PortableSimd,
+    /// The RISC-V vector extension, version 1.0, on riscv64.
+    Rvv,
+}
+
+impl Acceleration {
+    /// A short lowercase name for logs, e.g. `"avx2"`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Acceleration::Scalar => "scalar",
+            Acceleration::Sse2 => "sse2",
+            Acceleration::Avx2 => "avx2",
+            Acceleration::Neon => "neon",
+            Acceleration::WasmSimd => "wasm-simd",
+            Acceleration::Avx512 => "avx512",
+            Acceleration::PortableSimd => "portable-simd",
+            Acceleration::Rvv => "rvv",
+        }
+    }
+}
+
+impl fmt::Display for Acceleration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Backends this crate may run, fastest first.
+const PREFERENCE: [Acceleration; 7] = [
+    Acceleration::Avx512,
+    Acceleration::Avx2,
+    Acceleration::Neon,
+    Acceleration::WasmSimd,
+    Acceleration::Rvv,
+    Acceleration::PortableSimd,
+    Acceleration::Scalar,
+];
+
+// Names a backend to run instead of the fastest, see `acceleration`.
+#[cfg(all(feature = "std", feature = "force-backend"))]
+const BACKEND_VAR: &str = "KYBERLIB_BACKEND";
+
+// The backend picked on first use and the one set by `force_backend`,
+// each as 1 + its index in `PREFERENCE`, or 0 for none yet. Threads that
+// race through the first use store the same value, so plain loads and
+// stores suffice, also on targets without compare-and-swap.
+static DETECTED: AtomicU8 = AtomicU8::new(0);
+#[cfg(feature = "force-backend")]
+static FORCED: AtomicU8 = AtomicU8::new(0);
+
+fn load(slot: &AtomicU8) -> Option<Acceleration> {
+    match slot.load(Ordering::Relaxed) {
+        0 => None,
+        i => Some(PREFERENCE[usize::from(i) - 1]),
+    }
+}
+
+fn store(slot: &AtomicU8, backend: Option<Acceleration>) {
+    let i = backend.map_or(0, |backend| {
+        1 + PREFERENCE.iter().position(|b| *b == backend).unwrap()
+    });
+    slot.store(i as u8, Ordering::Relaxed);
+}
+
+// Whether this build has the backend and the machine can run it.
+#[allow(clippy::match_like_matches_macro)] // one arm per backend built
+fn supported(backend: Acceleration) -> bool {
+    match backend {
+        Acceleration::Scalar => true,
+        #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+        Acceleration::Avx512 => crate::avx512::supported(),
+        #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+        Acceleration::Avx2 => crate::avx2::supported(),
+        #[cfg(all(
+            target_arch = "aarch64",
+            target_feature = "neon",
+            feature = "neon"
+        ))]
+        Acceleration::Neon => true,
+        #[cfg(all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+            feature = "wasm-simd"
+        ))]
+        Acceleration::WasmSimd => true,
+        #[cfg(all(target_arch = "riscv64", feature = "rvv"))]
+        Acceleration::Rvv => crate::rvv::supported(),
+        #[cfg(all(
+            feature = "portable-simd",
+            not(any(
+                all(target_arch = "x86_64", feature = "avx2"),
+                all(
+                    target_arch = "aarch64",
+                    target_feature = "neon",
+                    feature = "neon"
+                ),
+                all(
+                    target_arch = "wasm32",
+                    target_feature = "simd128",
+                    feature = "wasm-simd"
+                ),
+                all(target_arch = "riscv64", feature = "rvv")
+            ))
+        ))]
+        Acceleration::PortableSimd => true,
+        _ => false,
+    }
+}
+
+// The fastest available backend, or the one the environment names.
+fn detect() -> Acceleration {
+    #[cfg(all(feature = "std", feature = "force-backend"))]
+    if let Ok(name) = std::env::var(BACKEND_VAR) {
+        if let Some(backend) = available().find(|b| b.name() == name) {
+            return backend;
+        }
+    }
+    available().next().unwrap_or(Acceleration::Scalar)
+}
+
+/// Returns the implementation of the polynomial arithmetic that key
+/// generation, encapsulation and decapsulation run on this machine.
+///
+/// The first call, made by the first of these operations at the
+/// latest, picks the fastest backend of [`available`]: with the `avx2`
+/// feature on x86_64 AVX2 if the CPU supports AVX2, BMI2 and POPCNT,
+/// and with `avx512` AVX-512 where AVX512F and AVX512BW are also
+/// present; with the `neon` feature on AArch64 always NEON, and with
+/// the `wasm-simd` feature SIMD128 in modules built with the `simd128`
+/// target feature; with the `rvv` feature on riscv64 RVV if the kernel
+/// reports the V extension; and with the `portable-simd` feature its
+/// code on the targets none of these cover. Other builds run the
+/// reference code. The `levels` and `research` modules run the
+/// reference code regardless.
+///
+/// With the `std` and `force-backend` features, operators can override
+/// the choice by setting the `KYBERLIB_BACKEND` environment variable to
+/// the name of an available backend, such as `scalar` to rule out a
+/// SIMD path; other values are ignored. The variable is read once, on
+/// the first call. `force_backend` takes precedence over both.
+pub fn acceleration() -> Acceleration {
+    #[cfg(feature = "force-backend")]
+    if let Some(forced) = load(&FORCED) {
+        return forced;
+    }
+    if let Some(detected) = load(&DETECTED) {
+        return detected;
+    }
+    let detected = detect();
+    store(&DETECTED, Some(detected));
+    detected
+}
+
+/// The backends this build can run on this machine, fastest first,
+/// ending with the reference code, [`Acceleration::Scalar`].
+pub fn available() -> impl Iterator<Item = Acceleration> {
+    PREFERENCE.iter().copied().filter(|b| supported(*b))
+}
+
+/// Makes key generation, encapsulation and decapsulation run `backend`
+/// in every thread until [`reset_backend`], so that tests and
+/// benchmarks can compare backends in one process. An operation already
+/// running finishes on the backend it started with.
+///
+/// Any code in the process can switch the backend this way, so it is
+/// only built with the `force-backend` feature, which is meant for
+/// tests and benchmarks.
+///
+/// # Errors
+///
+/// [`KyberLibError::InvalidInput`] if `backend` is not one of
+/// [`available`]; the selection is left as it was.
+#[cfg(feature = "force-backend")]
+pub fn force_backend(
+    backend: Acceleration,
+) -> Result<(), KyberLibError> {
+    if !supported(backend) {
+        return Err(KyberLibError::InvalidInput);
+    }
+    store(&FORCED, Some(backend));
+    Ok(())
+}
+
+/// Undoes [`force_backend`], returning to the backend picked on first
+/// use.
+#[cfg(feature = "force-backend")]
+pub fn reset_backend() {
+    store(&FORCED, None);
+}
+
+// Forward NTTs run by each backend, counted in debug builds so that tests
+// can check `acceleration` against the code actually executed.
+#[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+static NTT_CALLS: [AtomicUsize; 8] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+#[inline(always)]
+pub(crate) fn count_ntt(_backend: Acceleration) {
+    #[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+    let _ =
+        NTT_CALLS[_backend as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// The number of forward NTTs `backend` has run in this process. Always
+/// zero in release builds. Not part of the public API.
+#[doc(hidden)]
+pub fn ntt_calls(backend: Acceleration) -> usize {
+    #[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+    {
+        NTT_CALLS[backend as usize].load(Ordering::Relaxed)
+    }
+    #[cfg(not(all(debug_assertions, target_has_atomic = "ptr")))]
+    {
+        let _ = backend;
+        0
+    }
+}
+
+// Matrix entries expanded by the reference code, counted in debug builds
+// so that tests can check that no operation expands A twice.
+#[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+static MATRIX_ENTRIES: AtomicUsize = AtomicUsize::new(0);
+
+#[inline(always)]
+pub(crate) fn count_matrix_entry() {
+    #[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+    let _ = MATRIX_ENTRIES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The number of matrix entries the reference code has expanded in this
+/// process. Always zero in release builds. Not part of the public API.
+#[doc(hidden)]
+pub fn matrix_entries() -> usize {
+    #[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+    {
+        MATRIX_ENTRIES.load(Ordering::Relaxed)
+    }
+    #[cfg(not(all(debug_assertions, target_has_atomic = "ptr")))]
+    {
+        0
+    }
+}
@@ -0,0 +1,695 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! An [age](https://age-encryption.org) plugin for Kyber recipients.
+//!
+//! Recipients are Kyber public keys encoded as `age1kyber1...` bech32
+//! strings and identities are the 64-byte seeds of the keypairs encoded as
+//! `AGE-PLUGIN-KYBER-1...`. To encrypt to a recipient, the age file key is
+//! wrapped in a `kyber` stanza whose body is the Kyber ciphertext followed
+//! by the file key sealed with ChaCha20-Poly1305 under
+//! `HKDF-SHA-256(salt = ciphertext, ikm = shared secret, info = "age-plugin-kyber/v1")`.
+//!
+//! The `recipient-v1` and `identity-v1` state machines of the age plugin
+//! protocol are available through [`run_recipient_v1`] and
+//! [`run_identity_v1`], which the `age-plugin-kyber` binary wires to its
+//! standard input and output. Stanzas of other types are ignored, so files
+//! can mix Kyber recipients with any other kind.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::age_plugin::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let identity = Identity::generate(&mut rng)?;
+//! let recipient: Recipient = identity.recipient().to_string().parse()?;
+//!
+//! let file_key = [7u8; FILE_KEY_BYTES];
+//! let stanza = recipient.wrap_file_key(&file_key, &mut rng)?;
+//! assert_eq!(identity.unwrap_stanza(&stanza), Some(Ok(file_key)));
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    api::{decapsulate, derive, encapsulate, Keypair},
+    error::KyberLibError,
+    kem::check_public_key,
+    kex::PublicKey,
+    params::*,
+    CryptoRng, RngCore,
+};
+use base64ct::{Base64Unpadded, Encoding};
+use bech32::{FromBase32, ToBase32, Variant};
+use chacha20poly1305::{
+    aead::AeadInPlace, ChaCha20Poly1305, KeyInit, Nonce, Tag,
+};
+use core::{convert::TryInto, fmt, str::FromStr};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Write},
+};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Human-readable part of recipient strings.
+pub const RECIPIENT_HRP: &str = "age1kyber";
+/// Human-readable part of identity strings, in lowercase.
+pub const IDENTITY_HRP: &str = "age-plugin-kyber-";
+/// Type of the stanzas produced by this plugin.
+pub const STANZA_TYPE: &str = "kyber";
+/// Label advertised to age when asked for extension labels.
+pub const LABEL: &str = "postquantum";
+/// Size in bytes of an age file key.
+pub const FILE_KEY_BYTES: usize = 16;
+/// Size in bytes of the body of a `kyber` stanza.
+pub const STANZA_BODY_BYTES: usize =
+    KYBER_CIPHERTEXT_BYTES + FILE_KEY_BYTES + 16;
+
+const KDF_INFO: &[u8] = b"age-plugin-kyber/v1";
+const SEED_BYTES: usize = 64;
+const COLUMNS: usize = 64;
+
+/// An age file key.
+pub type FileKey = [u8; FILE_KEY_BYTES];
+
+/// A stanza as exchanged in age headers and the plugin protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stanza {
+    /// The stanza type, or command name in the plugin protocol.
+    pub tag: String,
+    /// The space-separated arguments.
+    pub args: Vec<String>,
+    /// The decoded body.
+    pub body: Vec<u8>,
+}
+
+impl Stanza {
+    /// Creates a stanza.
+    pub fn new(tag: &str, args: &[&str], body: &[u8]) -> Self {
+        Self {
+            tag: tag.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            body: body.to_vec(),
+        }
+    }
+
+    /// Reads the next stanza, returning `None` at the end of the input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidData` if the stanza is malformed
+    /// and `UnexpectedEof` if the input ends inside it.
+    pub fn read<R: BufRead>(input: &mut R) -> io::Result<Option<Self>> {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let mut words = line
+            .strip_suffix('\n')
+            .and_then(|l| l.strip_prefix("-> "))
+            .ok_or_else(|| invalid("stanza must start with \"-> \""))?
+            .split(' ');
+        let tag = words.next().unwrap_or_default().to_string();
+        let args: Vec<String> = words.map(str::to_string).collect();
+        if tag.is_empty() || args.iter().any(String::is_empty) {
+            return Err(invalid("empty stanza argument"));
+        }
+
+        let mut encoded = String::new();
+        loop {
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let chunk = line
+                .strip_suffix('\n')
+                .filter(|chunk| chunk.len() <= COLUMNS)
+                .ok_or_else(|| invalid("stanza body line too long"))?;
+            encoded.push_str(chunk);
+            if chunk.len() < COLUMNS {
+                break;
+            }
+        }
+        let body = Base64Unpadded::decode_vec(&encoded)
+            .map_err(|_| invalid("stanza body is not valid base64"))?;
+        Ok(Some(Self { tag, args, body }))
+    }
+
+    /// Writes the stanza, wrapping the body at 64 columns.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of the underlying writer.
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        write!(output, "-> {}", self.tag)?;
+        for arg in &self.args {
+            write!(output, " {}", arg)?;
+        }
+        writeln!(output)?;
+        let encoded = Base64Unpadded::encode_string(&self.body);
+        let mut rest = encoded.as_str();
+        while rest.len() >= COLUMNS {
+            let (chunk, tail) = rest.split_at(COLUMNS);
+            writeln!(output, "{}", chunk)?;
+            rest = tail;
+        }
+        writeln!(output, "{}", rest)
+    }
+}
+
+/// A Kyber recipient, encoded as `age1kyber1...`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Recipient {
+    public_key: PublicKey,
+}
+
+impl Recipient {
+    /// Creates the recipient for `public_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if the key is malformed.
+    pub fn new(public_key: &PublicKey) -> Result<Self, KyberLibError> {
+        check_public_key(public_key)?;
+        Ok(Self {
+            public_key: *public_key,
+        })
+    }
+
+    /// The Kyber public key.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Wraps `file_key` in a `kyber` stanza for this recipient.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    pub fn wrap_file_key<R>(
+        &self,
+        file_key: &FileKey,
+        rng: &mut R,
+    ) -> Result<Stanza, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let (ciphertext, shared_secret) =
+            encapsulate(&self.public_key, rng)?;
+        let shared_secret = Zeroizing::new(shared_secret);
+        let key = wrapping_key(&ciphertext, &shared_secret[..])?;
+
+        let mut body = Vec::with_capacity(STANZA_BODY_BYTES);
+        body.extend_from_slice(&ciphertext);
+        body.extend_from_slice(file_key);
+        let tag = ChaCha20Poly1305::new(&(*key).into())
+            .encrypt_in_place_detached(
+                &Nonce::default(),
+                b"",
+                &mut body[KYBER_CIPHERTEXT_BYTES..],
+            )
+            .map_err(|_| KyberLibError::InvalidInput)?;
+        body.extend_from_slice(&tag);
+        Ok(Stanza::new(STANZA_TYPE, &[], &body))
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = bech32::encode(
+            RECIPIENT_HRP,
+            self.public_key.to_base32(),
+            Variant::Bech32,
+        )
+        .map_err(|_| fmt::Error)?;
+        f.write_str(&encoded)
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = KyberLibError;
+
+    /// Parses an `age1kyber1...` recipient.
+    ///
+    /// Returns `KyberLibError::InvalidInput` if the string is not valid
+    /// bech32 for [`RECIPIENT_HRP`] and `KyberLibError::InvalidKey` if it
+    /// does not hold a Kyber public key of the active security level.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = decode_bech32(s, RECIPIENT_HRP)?;
+        let public_key: PublicKey = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| KyberLibError::InvalidKey)?;
+        Self::new(&public_key)
+    }
+}
+
+/// A Kyber identity, encoded as `AGE-PLUGIN-KYBER-1...`.
+///
+/// The identity holds the seed the keypair is derived from. It is zeroized
+/// on drop and redacted from `Debug` output.
+pub struct Identity {
+    seed: [u8; SEED_BYTES],
+    keys: Keypair,
+}
+
+impl Identity {
+    /// Derives the identity from a 64-byte seed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KyberLibError` if the keypair cannot be derived.
+    pub fn from_seed(
+        seed: &[u8; SEED_BYTES],
+    ) -> Result<Self, KyberLibError> {
+        Ok(Self {
+            seed: *seed,
+            keys: derive(seed)?,
+        })
+    }
+
+    /// Generates a fresh identity.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    pub fn generate<R>(rng: &mut R) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut seed = Zeroizing::new([0u8; SEED_BYTES]);
+        rng.try_fill_bytes(&mut seed[..])
+            .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+        Self::from_seed(&seed)
+    }
+
+    /// The recipient matching this identity.
+    pub fn recipient(&self) -> Recipient {
+        Recipient {
+            public_key: self.keys.public,
+        }
+    }
+
+    /// Encodes the identity as `AGE-PLUGIN-KYBER-1...`.
+    pub fn to_encoded(&self) -> Zeroizing<String> {
+        let mut data = self.seed.to_base32();
+        let encoded =
+            bech32::encode(IDENTITY_HRP, &data, Variant::Bech32)
+                .map(|s| s.to_uppercase())
+                .unwrap_or_default();
+        data.iter_mut().for_each(|u| *u = bech32::u5::default());
+        Zeroizing::new(encoded)
+    }
+
+    /// Attempts to unwrap the file key from `stanza`.
+    ///
+    /// Returns `None` if the stanza is not a `kyber` stanza, so that
+    /// stanzas for other recipient types can be passed through.
+    ///
+    /// # Errors
+    ///
+    /// Yields `KyberLibError::InvalidInput` for a malformed `kyber` stanza
+    /// and `KyberLibError::Decapsulation` for a stanza addressed to another
+    /// recipient.
+    pub fn unwrap_stanza(
+        &self,
+        stanza: &Stanza,
+    ) -> Option<Result<FileKey, KyberLibError>> {
+        if stanza.tag != STANZA_TYPE {
+            return None;
+        }
+        if !stanza.args.is_empty()
+            || stanza.body.len() != STANZA_BODY_BYTES
+        {
+            return Some(Err(KyberLibError::InvalidInput));
+        }
+        let (ciphertext, sealed) =
+            stanza.body.split_at(KYBER_CIPHERTEXT_BYTES);
+        let (sealed, tag) = sealed.split_at(FILE_KEY_BYTES);
+        Some(self.open(ciphertext, sealed, tag))
+    }
+
+    fn open(
+        &self,
+        ciphertext: &[u8],
+        sealed: &[u8],
+        tag: &[u8],
+    ) -> Result<FileKey, KyberLibError> {
+        let shared_secret =
+            Zeroizing::new(decapsulate(ciphertext, &self.keys.secret)?);
+        let key = wrapping_key(ciphertext, &shared_secret[..])?;
+        let mut file_key = [0u8; FILE_KEY_BYTES];
+        file_key.copy_from_slice(sealed);
+        if ChaCha20Poly1305::new(&(*key).into())
+            .decrypt_in_place_detached(
+                &Nonce::default(),
+                b"",
+                &mut file_key,
+                Tag::from_slice(tag),
+            )
+            .is_err()
+        {
+            file_key.zeroize();
+            return Err(KyberLibError::Decapsulation);
+        }
+        Ok(file_key)
+    }
+}
+
+impl FromStr for Identity {
+    type Err = KyberLibError;
+
+    /// Parses an `AGE-PLUGIN-KYBER-1...` identity.
+    ///
+    /// Returns `KyberLibError::InvalidInput` if the string is not valid
+    /// bech32 for [`IDENTITY_HRP`] and `KyberLibError::InvalidKey` if it
+    /// does not hold a 64-byte seed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = Zeroizing::new(decode_bech32(s, IDENTITY_HRP)?);
+        let seed: &[u8; SEED_BYTES] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| KyberLibError::InvalidKey)?;
+        let identity = Self::from_seed(seed);
+        bytes.zeroize();
+        identity
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Identity").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Identity {
+    fn drop(&mut self) {
+        self.seed.zeroize();
+        self.keys.secret.zeroize();
+    }
+}
+
+/// Runs the `recipient-v1` state machine of the age plugin protocol.
+///
+/// Reads the recipients, identities and file keys sent by age, then
+/// answers with one `kyber` stanza per file key and recipient. Recipients
+/// or identities that do not parse are reported with `error` commands and
+/// no stanza is produced.
+///
+/// # Errors
+///
+/// Returns an I/O error if age sends malformed commands or the streams
+/// fail.
+pub fn run_recipient_v1<I, O, R>(
+    input: &mut I,
+    output: &mut O,
+    rng: &mut R,
+) -> io::Result<()>
+where
+    I: BufRead,
+    O: Write,
+    R: RngCore + CryptoRng,
+{
+    let mut recipients = Vec::new();
+    let mut identities = Vec::new();
+    let mut file_keys = Vec::new();
+    let mut labels = false;
+    while let Some(command) = next_command(input)? {
+        match command.tag.as_str() {
+            "add-recipient" => recipients.push(single_arg(&command)?),
+            "add-identity" => identities.push(single_arg(&command)?),
+            "wrap-file-key" => {
+                let key: FileKey =
+                    command.body.as_slice().try_into().map_err(
+                        |_| invalid("file key must be 16 bytes"),
+                    )?;
+                file_keys.push(Zeroizing::new(key));
+            }
+            "extension-labels" => labels = true,
+            _ => {}
+        }
+    }
+
+    let mut targets = Vec::new();
+    let mut failed = false;
+    for (index, recipient) in recipients.iter().enumerate() {
+        match recipient.parse::<Recipient>() {
+            Ok(recipient) => targets.push(recipient),
+            Err(_) => {
+                failed = true;
+                send_error(
+                    input,
+                    output,
+                    "recipient",
+                    index,
+                    "invalid recipient",
+                )?;
+            }
+        }
+    }
+    for (index, identity) in identities.iter().enumerate() {
+        match identity.parse::<Identity>() {
+            Ok(identity) => targets.push(identity.recipient()),
+            Err(_) => {
+                failed = true;
+                send_error(
+                    input,
+                    output,
+                    "identity",
+                    index,
+                    "invalid identity",
+                )?;
+            }
+        }
+    }
+
+    if !failed && labels {
+        failed = !send(
+            input,
+            output,
+            &Stanza::new("labels", &[LABEL], &[]),
+        )?;
+    }
+    if !failed {
+        'files: for (file, file_key) in file_keys.iter().enumerate() {
+            for recipient in &targets {
+                let stanza =
+                    match recipient.wrap_file_key(file_key, rng) {
+                        Ok(stanza) => stanza,
+                        Err(_) => {
+                            send_internal_error(
+                                input,
+                                output,
+                                "file key wrapping failed",
+                            )?;
+                            break 'files;
+                        }
+                    };
+                let file = file.to_string();
+                let mut args = vec![file.as_str(), stanza.tag.as_str()];
+                args.extend(stanza.args.iter().map(String::as_str));
+                let command = Stanza::new(
+                    "recipient-stanza",
+                    &args,
+                    &stanza.body,
+                );
+                if !send(input, output, &command)? {
+                    break 'files;
+                }
+            }
+        }
+    }
+    done(output)
+}
+
+/// Runs the `identity-v1` state machine of the age plugin protocol.
+///
+/// Reads the identities and the stanzas of each file sent by age, then
+/// answers with the file key of every file that has a `kyber` stanza for
+/// one of the identities. Stanzas of other types are skipped; malformed
+/// `kyber` stanzas are reported with `error stanza` commands.
+///
+/// # Errors
+///
+/// Returns an I/O error if age sends malformed commands or the streams
+/// fail.
+pub fn run_identity_v1<I, O>(
+    input: &mut I,
+    output: &mut O,
+) -> io::Result<()>
+where
+    I: BufRead,
+    O: Write,
+{
+    let mut encoded = Vec::new();
+    let mut files: BTreeMap<usize, Vec<Stanza>> = BTreeMap::new();
+    while let Some(command) = next_command(input)? {
+        match command.tag.as_str() {
+            "add-identity" => encoded.push(single_arg(&command)?),
+            "recipient-stanza" => {
+                let (file, rest) = command
+                    .args
+                    .split_first()
+                    .ok_or_else(|| invalid("missing file index"))?;
+                let file = file
+                    .parse::<usize>()
+                    .map_err(|_| invalid("invalid file index"))?;
+                let (tag, args) = rest
+                    .split_first()
+                    .ok_or_else(|| invalid("missing stanza type"))?;
+                files.entry(file).or_default().push(Stanza {
+                    tag: tag.clone(),
+                    args: args.to_vec(),
+                    body: command.body,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let mut identities = Vec::new();
+    let mut failed = false;
+    for (index, identity) in encoded.iter().enumerate() {
+        match identity.parse::<Identity>() {
+            Ok(identity) => identities.push(identity),
+            Err(_) => {
+                failed = true;
+                send_error(
+                    input,
+                    output,
+                    "identity",
+                    index,
+                    "invalid identity",
+                )?;
+            }
+        }
+    }
+
+    if !failed {
+        'files: for (file, stanzas) in &files {
+            for (index, stanza) in stanzas.iter().enumerate() {
+                for identity in &identities {
+                    match identity.unwrap_stanza(stanza) {
+                        None
+                        | Some(Err(KyberLibError::Decapsulation)) => {}
+                        Some(Ok(file_key)) => {
+                            let file_key = Zeroizing::new(file_key);
+                            let command = Stanza::new(
+                                "file-key",
+                                &[&file.to_string()],
+                                &file_key[..],
+                            );
+                            if !send(input, output, &command)? {
+                                break 'files;
+                            }
+                            continue 'files;
+                        }
+                        Some(Err(_)) => {
+                            let args =
+                                [file.to_string(), index.to_string()];
+                            let command = Stanza::new(
+                                "error",
+                                &["stanza", &args[0], &args[1]],
+                                b"malformed kyber stanza",
+                            );
+                            let _ = send(input, output, &command)?;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    done(output)
+}
+
+fn wrapping_key(
+    ciphertext: &[u8],
+    shared_secret: &[u8],
+) -> Result<Zeroizing<[u8; 32]>, KyberLibError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(ciphertext), shared_secret)
+        .expand(KDF_INFO, &mut key[..])
+        .map_err(|_| KyberLibError::InvalidLength)?;
+    Ok(key)
+}
+
+fn decode_bech32(s: &str, hrp: &str) -> Result<Vec<u8>, KyberLibError> {
+    match bech32::decode(s) {
+        Ok((decoded_hrp, data, Variant::Bech32))
+            if decoded_hrp == hrp =>
+        {
+            Vec::<u8>::from_base32(&data)
+                .map_err(|_| KyberLibError::InvalidInput)
+        }
+        _ => Err(KyberLibError::InvalidInput),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Reads the next phase 1 command, returning `None` once age sends `done`.
+fn next_command<I: BufRead>(
+    input: &mut I,
+) -> io::Result<Option<Stanza>> {
+    match Stanza::read(input)? {
+        Some(command) if command.tag == "done" => Ok(None),
+        Some(command) => Ok(Some(command)),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+fn single_arg(command: &Stanza) -> io::Result<String> {
+    match command.args.as_slice() {
+        [arg] => Ok(arg.clone()),
+        _ => Err(invalid("expected a single argument")),
+    }
+}
+
+// Sends a phase 2 command and returns whether age answered `ok`.
+fn send<I: BufRead, O: Write>(
+    input: &mut I,
+    output: &mut O,
+    command: &Stanza,
+) -> io::Result<bool> {
+    command.write(output)?;
+    output.flush()?;
+    match Stanza::read(input)? {
+        Some(reply) if reply.tag == "ok" => Ok(true),
+        Some(reply) if reply.tag == "fail" => Ok(false),
+        Some(_) => Err(invalid("expected ok or fail")),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+fn send_error<I: BufRead, O: Write>(
+    input: &mut I,
+    output: &mut O,
+    kind: &str,
+    index: usize,
+    message: &str,
+) -> io::Result<()> {
+    let index = index.to_string();
+    let command =
+        Stanza::new("error", &[kind, &index], message.as_bytes());
+    send(input, output, &command).map(|_| ())
+}
+
+fn send_internal_error<I: BufRead, O: Write>(
+    input: &mut I,
+    output: &mut O,
+    message: &str,
+) -> io::Result<()> {
+    let command =
+        Stanza::new("error", &["internal"], message.as_bytes());
+    send(input, output, &command).map(|_| ())
+}
+
+fn done<O: Write>(output: &mut O) -> io::Result<()> {
+    Stanza::new("done", &[], &[]).write(output)?;
+    output.flush()
+}
@@ -3,12 +3,18 @@
 
 use crate::{
     error::KyberLibError,
-    kem::*,
-    kex::{Decapsulated, Encapsulated, PublicKey, SecretKey},
+    fips202::shake256,
+    kem::{coefficients_in_range, *},
+    kex::{
+        AlignedPublicKey, CompactSecretKey, Decapsulated, Encapsulated,
+        PublicKey, SecretKey, SharedSecret,
+    },
     params::*,
+    rng::randombytes,
+    symmetric::hash_h,
+    verify::verify,
     CryptoRng, RngCore,
 };
-use pqc_core::zero;
 #[cfg(feature = "zeroize")]
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -22,7 +28,8 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 ///
 /// # Errors
 ///
-/// Returns a `KyberLibError` if an error occurs during key pair generation.
+/// Returns a `KyberLibError` if an error occurs during key pair generation,
+/// including `KyberLibError::WeakKey` if `rng` returns all-zero seeds.
 ///
 /// ### Example
 /// ```
@@ -32,6 +39,7 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 /// let keys = keypair(&mut rng)?;
 /// # Ok(())}
 /// ```
+#[must_use = "dropping the keypair discards the only copy of the secret key"]
 pub fn keypair<R>(rng: &mut R) -> Result<Keypair, KyberLibError>
 where
     R: RngCore + CryptoRng,
@@ -40,7 +48,8 @@ where
     let mut secret = [0u8; KYBER_SECRET_KEY_BYTES];
     generate_key_pair(&mut public, &mut secret, rng, None)?;
     let keys = Keypair { public, secret };
-    zero!(secret);
+    #[cfg(feature = "zeroize")]
+    secret.zeroize();
     Ok(keys)
 }
 
@@ -57,6 +66,7 @@ where
 /// let _ = keypairfrom(&mut public, &mut secret, &mut rng)?;
 /// # Ok(())}
 /// ```
+#[must_use = "the verified keypair is returned, not stored"]
 pub fn keypairfrom<R>(
     public: &mut [u8; KYBER_PUBLIC_KEY_BYTES],
     secret: &mut [u8; KYBER_SECRET_KEY_BYTES],
@@ -70,18 +80,14 @@ where
     let expected_shared_secret = decapsulate(&ciphertext, secret)?;
     //If it does match, return a KeyPair
     if expected_shared_secret == shared_secret {
-        let public2 = *public;
-        let secret2 = *secret;
         let key = Keypair {
-            public: public2,
-            secret: secret2,
+            public: *public,
+            secret: *secret,
         };
         #[cfg(feature = "zeroize")]
         {
             public.zeroize();
             secret.zeroize();
-            public2.zeroize();
-            secret2.zeroize();
         }
         Ok(key)
     } else {
@@ -99,9 +105,15 @@ where
 /// * `pk` - The public key as a slice of bytes.
 /// * `rng` - The random number generator implementing the `RngCore` and `CryptoRng` traits.
 ///
+/// The key is checked with [`check_public_key`] first, as FIPS 203
+/// requires. To encapsulate repeatedly to the same peer, check it once
+/// with [`ValidatePublicKey::validated`] and use
+/// [`encapsulate_validated`].
+///
 /// # Errors
 ///
-/// Returns a `KyberLibError` if the input sizes are incorrect or if an error occurs during encapsulation.
+/// Returns a `KyberLibError` if the input sizes are incorrect, if the key
+/// is not canonically encoded or if an error occurs during encapsulation.
 ///
 /// ### Example
 /// ```
@@ -112,6 +124,7 @@ where
 /// let (ciphertext, shared_secret) = encapsulate(&keys.public, &mut rng)?;
 /// # Ok(())}
 /// ```
+#[must_use = "the shared secret is lost if the result is dropped"]
 pub fn encapsulate<R>(pk: &[u8], rng: &mut R) -> Encapsulated
 where
     R: CryptoRng + RngCore,
@@ -119,12 +132,70 @@ where
     if pk.len() != KYBER_PUBLIC_KEY_BYTES {
         return Err(KyberLibError::InvalidInput);
     }
+    check_public_key(pk)?;
     let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
     let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
     encrypt_message(&mut ct, &mut ss, pk, rng, None)?;
     Ok((ct, ss))
 }
 
+/// Encapsulates a public key with the message hedged against a broken
+/// RNG, and returns the ciphertext to send and the shared secret.
+///
+/// The IND-CPA message `m` determines the shared secret. [`encapsulate`]
+/// derives it as `m = H(r)` from 32 bytes `r` of RNG output, so an RNG
+/// that repeats its output repeats the ciphertext and shared secret. This
+/// function derives
+///
+/// ```text
+/// m = H(r || H(pk) || n)
+/// ```
+///
+/// where `H` is the hash the KEM uses for `H(pk)` and `n` is a
+/// process-wide counter, incremented on every call and encoded as 8
+/// little-endian bytes. Encapsulations to the same key in one process
+/// never share `m`, whatever the RNG returns, and ones to different keys
+/// do not share it either. A predictable RNG stays predictable: hedging
+/// keeps a stuck RNG from repeating a shared secret, it does not make
+/// one secret.
+///
+/// Everything after `m` is unchanged, so the peer decapsulates with
+/// [`decapsulate`]. It is not the default, because [`encapsulate`] and
+/// the functions sharing its behaviour give the same ciphertext for the
+/// same RNG output, which tests against other implementations rely on.
+/// The seeded and known-answer paths of
+/// [`encrypt_message`](crate::kem::encrypt_message) are never hedged.
+///
+/// # Errors
+///
+/// As [`encapsulate`].
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let (ct, ss) = encapsulate_hedged(&keys.public, &mut rng)?;
+/// assert_eq!(decapsulate(&ct, keys.expose_secret())?, ss);
+/// # Ok(())}
+/// ```
+#[cfg(target_has_atomic = "ptr")]
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn encapsulate_hedged<R>(pk: &[u8], rng: &mut R) -> Encapsulated
+where
+    R: CryptoRng + RngCore,
+{
+    if pk.len() != KYBER_PUBLIC_KEY_BYTES {
+        return Err(KyberLibError::InvalidInput);
+    }
+    check_public_key(pk)?;
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    encrypt_message_hedged(&mut ct, &mut ss, pk, rng)?;
+    Ok((ct, ss))
+}
+
 /// Decapsulates ciphertext with a secret key.
 ///
 /// This function decapsulates ciphertext with a secret key and returns the shared secret.
@@ -149,6 +220,7 @@ where
 /// assert_eq!(ss1, ss2);
 /// #  Ok(())}
 /// ```
+#[must_use = "the shared secret is lost if the result is dropped"]
 pub fn decapsulate(ct: &[u8], sk: &[u8]) -> Decapsulated {
     if ct.len() != KYBER_CIPHERTEXT_BYTES
         || sk.len() != KYBER_SECRET_KEY_BYTES
@@ -160,11 +232,366 @@ pub fn decapsulate(ct: &[u8], sk: &[u8]) -> Decapsulated {
     Ok(ss)
 }
 
+/// Encapsulates a public key, returning the ciphertext and shared secret
+/// as named fields.
+///
+/// Behaves like [`encapsulate`], whose tuple is easy to destructure in the
+/// wrong order; the two convert into each other with `From`.
+///
+/// # Errors
+///
+/// Returns a `KyberLibError` if the input sizes are incorrect or if an error occurs during encapsulation.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let Encapsulation { ciphertext, shared_secret } =
+///     encapsulate_struct(&keys.public, &mut rng)?;
+/// assert_eq!(decapsulate(&ciphertext, keys.expose_secret())?, shared_secret);
+/// # Ok(())}
+/// ```
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn encapsulate_struct<R>(
+    pk: &[u8],
+    rng: &mut R,
+) -> Result<Encapsulation, KyberLibError>
+where
+    R: CryptoRng + RngCore,
+{
+    encapsulate(pk, rng).map(Encapsulation::from)
+}
+
+/// Encapsulates to a public key held in a borrowed buffer.
+///
+/// Behaves like [`encapsulate`] but reports a buffer of the wrong size as
+/// `KyberLibError::InvalidLength`, so callers passing foreign memory, e.g.
+/// over FFI, can tell a sizing mistake from other failures. The key is read
+/// in place and never copied into an owned [`PublicKey`].
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `pk` is not
+/// `KYBER_PUBLIC_KEY_BYTES` long, `KyberLibError::InvalidKey` if it is not
+/// canonically encoded and `KyberLibError::RandomBytesGeneration` if the
+/// RNG fails.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let buffer: &[u8] = &keys.public;
+/// let (ct, ss1) = encapsulate_slice(buffer, &mut rng)?;
+/// let ss2 = decapsulate_slice(&keys.secret[..], &ct)?;
+/// assert_eq!(ss1, ss2);
+/// # Ok(())}
+/// ```
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn encapsulate_slice<R>(pk: &[u8], rng: &mut R) -> Encapsulated
+where
+    R: CryptoRng + RngCore,
+{
+    if pk.len() != KYBER_PUBLIC_KEY_BYTES {
+        return Err(KyberLibError::InvalidLength);
+    }
+    check_public_key(pk)?;
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    encrypt_message(&mut ct, &mut ss, pk, rng, None)?;
+    Ok((ct, ss))
+}
+
+/// A public key that has passed [`check_public_key`].
+///
+/// Obtained from [`ValidatePublicKey::validated`] only, so holding one
+/// proves the check ran, and [`encapsulate_validated`] does not repeat
+/// it. Worth it when encapsulating to the same peer many times. The key
+/// is held as an [`AlignedPublicKey`], on a 64-byte boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidatedPublicKey(AlignedPublicKey);
+
+impl ValidatedPublicKey {
+    /// The checked key bytes.
+    #[must_use]
+    pub fn as_bytes(&self) -> &PublicKey {
+        &self.0
+    }
+
+    /// Returns the key bytes, giving up the proof of validation.
+    #[must_use]
+    pub fn into_inner(self) -> PublicKey {
+        self.0 .0
+    }
+}
+
+impl AsRef<[u8]> for ValidatedPublicKey {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+/// Validation of a [`PublicKey`] into a [`ValidatedPublicKey`].
+///
+/// `PublicKey` is a plain byte array, so validation is provided through
+/// this extension trait rather than an inherent method.
+pub trait ValidatePublicKey {
+    /// Checks the key with [`check_public_key`] once and returns it as a
+    /// [`ValidatedPublicKey`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if a coefficient is not below
+    /// `q`.
+    fn validated(self) -> Result<ValidatedPublicKey, KyberLibError>;
+}
+
+impl ValidatePublicKey for PublicKey {
+    fn validated(self) -> Result<ValidatedPublicKey, KyberLibError> {
+        check_public_key(&self)?;
+        Ok(ValidatedPublicKey(AlignedPublicKey(self)))
+    }
+}
+
+/// Access to the seed `rho` a [`PublicKey`] embeds.
+///
+/// `PublicKey` is a plain byte array, so the accessor is provided through
+/// this extension trait rather than an inherent method.
+pub trait MatrixSeed {
+    /// Returns the public seed from which the matrix A of the key is
+    /// sampled, the last `KYBER_SYM_BYTES` of the key.
+    ///
+    /// With the `research` feature, `research::gen_matrix` expands it to
+    /// the same A that key generation and encapsulation use.
+    ///
+    /// ### Example
+    /// ```
+    /// # use kyberlib::*;
+    /// let keys = keypair(&mut rand::thread_rng()).unwrap();
+    /// let rho = keys.public.matrix_seed();
+    /// assert!(keys.public.ends_with(&rho));
+    /// ```
+    fn matrix_seed(&self) -> [u8; KYBER_SYM_BYTES];
+}
+
+impl MatrixSeed for PublicKey {
+    fn matrix_seed(&self) -> [u8; KYBER_SYM_BYTES] {
+        let mut seed = [0u8; KYBER_SYM_BYTES];
+        seed.copy_from_slice(&self[KYBER_POLYVEC_BYTES..]);
+        seed
+    }
+}
+
+/// Encapsulates to a public key that was already validated.
+///
+/// Behaves like [`encapsulate`] without checking the key again, and gives
+/// the same ciphertext and shared secret for the same RNG output.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let peer = keys.public.validated()?;
+/// for _ in 0..3 {
+///     let (ct, ss) = encapsulate_validated(&peer, &mut rng)?;
+///     assert_eq!(decapsulate(&ct, keys.expose_secret())?, ss);
+/// }
+/// # Ok(())}
+/// ```
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn encapsulate_validated<R>(
+    pk: &ValidatedPublicKey,
+    rng: &mut R,
+) -> Encapsulated
+where
+    R: CryptoRng + RngCore,
+{
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    encrypt_message(&mut ct, &mut ss, pk.as_bytes(), rng, None)?;
+    Ok((ct, ss))
+}
+
+/// Decapsulates a ciphertext with a secret key held in a borrowed buffer.
+///
+/// Behaves like [`decapsulate`], with the key first, but reports buffers
+/// of the wrong size as `KyberLibError::InvalidLength`. Neither buffer is
+/// copied into an owned key or ciphertext.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `sk` is not
+/// `KYBER_SECRET_KEY_BYTES` or `ct` is not `KYBER_CIPHERTEXT_BYTES` long.
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn decapsulate_slice(sk: &[u8], ct: &[u8]) -> Decapsulated {
+    if ct.len() != KYBER_CIPHERTEXT_BYTES
+        || sk.len() != KYBER_SECRET_KEY_BYTES
+    {
+        return Err(KyberLibError::InvalidLength);
+    }
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    decrypt_message(&mut ss, ct, sk);
+    Ok(ss)
+}
+
+/// Decapsulates a ciphertext, re-encrypting under a separately held
+/// public key instead of the copy embedded in the secret key.
+///
+/// `sk` is either a full secret key, whose stored `H(pk)` must match `pk`,
+/// or just the IND-CPA secret key followed by the 32-byte rejection value
+/// `z`, for callers storing keys without the embedded public key. The
+/// shared secret is the one [`decapsulate`] yields with the full key.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if the input sizes are incorrect
+/// and `KyberLibError::InvalidKey` if `pk` does not belong to `sk`.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+/// let secret = keys.expose_secret();
+/// assert_eq!(decapsulate_with_pk(&ct, secret, &keys.public)?, ss);
+/// # Ok(())}
+/// ```
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn decapsulate_with_pk(
+    ct: &[u8],
+    sk: &[u8],
+    pk: &[u8],
+) -> Decapsulated {
+    const H_START: usize = KYBER_SECRET_KEY_BYTES - 2 * KYBER_SYM_BYTES;
+    const Z_START: usize = KYBER_SECRET_KEY_BYTES - KYBER_SYM_BYTES;
+    const CPA_Z_BYTES: usize =
+        KYBER_INDCPA_SECRET_KEY_BYTES + KYBER_SYM_BYTES;
+    if ct.len() != KYBER_CIPHERTEXT_BYTES
+        || pk.len() != KYBER_PUBLIC_KEY_BYTES
+    {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut h_pk = [0u8; KYBER_SYM_BYTES];
+    hash_h(&mut h_pk, pk, KYBER_PUBLIC_KEY_BYTES);
+    let (sk_cpa, z) = match sk.len() {
+        KYBER_SECRET_KEY_BYTES => {
+            let stored = &sk[H_START..Z_START];
+            if verify(&h_pk, stored, KYBER_SYM_BYTES) != 0 {
+                return Err(KyberLibError::InvalidKey);
+            }
+            (&sk[..KYBER_INDCPA_SECRET_KEY_BYTES], &sk[Z_START..])
+        }
+        CPA_Z_BYTES => sk.split_at(KYBER_INDCPA_SECRET_KEY_BYTES),
+        _ => return Err(KyberLibError::InvalidInput),
+    };
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    decrypt_message_with_pk(&mut ss, ct, sk_cpa, pk, &h_pk, z);
+    Ok(ss)
+}
+
+/// Decapsulates a ciphertext and reports whether it was canonically
+/// encoded.
+///
+/// The shared secret is derived exactly as by [`decapsulate`], so implicit
+/// rejection is unaffected; the flag is `true` when decompressing and
+/// recompressing the ciphertext reproduces it bit for bit. It is meant for
+/// strict interop testing. For the round-3 parameter sets every packed
+/// coefficient value round-trips, so any well-sized ciphertext is
+/// canonical; the check fails closed should that ever change.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if the input sizes are incorrect.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let (ct, ss1) = encapsulate(&keys.public, &mut rng)?;
+/// let (ss2, canonical) = decapsulate_strict(&ct, keys.expose_secret())?;
+/// assert_eq!(ss1, ss2);
+/// assert!(canonical);
+/// # Ok(())}
+/// ```
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn decapsulate_strict(
+    ct: &[u8],
+    sk: &[u8],
+) -> Result<(SharedSecret, bool), KyberLibError> {
+    let ss = decapsulate(ct, sk)?;
+    Ok((ss, ciphertext_is_canonical(ct)))
+}
+
+/// The result of encapsulating to a public key.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Encapsulation {
+    /// The ciphertext to send to the holder of the secret key.
+    pub ciphertext: [u8; KYBER_CIPHERTEXT_BYTES],
+    /// The shared secret, which decapsulating the ciphertext recovers.
+    pub shared_secret: SharedSecret,
+}
+
+/// Formats the ciphertext as a [`ShortCiphertext`](crate::logging::ShortCiphertext)
+/// and the shared secret as `<redacted>`.
+#[cfg(feature = "defmt")]
+impl defmt::Format for Encapsulation {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "Encapsulation {{ ciphertext: {}, shared_secret: <redacted> }}",
+            crate::logging::ShortCiphertext(&self.ciphertext)
+        )
+    }
+}
+
+impl From<([u8; KYBER_CIPHERTEXT_BYTES], SharedSecret)>
+    for Encapsulation
+{
+    fn from(
+        (ciphertext, shared_secret): (
+            [u8; KYBER_CIPHERTEXT_BYTES],
+            SharedSecret,
+        ),
+    ) -> Self {
+        Encapsulation {
+            ciphertext,
+            shared_secret,
+        }
+    }
+}
+
+impl From<Encapsulation>
+    for ([u8; KYBER_CIPHERTEXT_BYTES], SharedSecret)
+{
+    fn from(encapsulation: Encapsulation) -> Self {
+        (encapsulation.ciphertext, encapsulation.shared_secret)
+    }
+}
+
 /// A public/secret keypair for use with Kyber.
 ///
 /// Byte lengths of the keys are determined by the security level chosen.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "zeroize", derive(Zeroize, ZeroizeOnDrop))]
+///
+/// With the `zeroize` feature the secret key, including the implicit
+/// rejection seed `z` at its end, is overwritten with zeros on drop; the
+/// public key is left as it is. `Keypair` is `Clone` but never `Copy`, so
+/// that enabling the feature, which adds a destructor, does not break
+/// code that compiles without it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "zeroize", derive(Zeroize))]
 pub struct Keypair {
     /// The public key.
     pub public: PublicKey,
@@ -172,6 +599,29 @@ pub struct Keypair {
     pub secret: SecretKey,
 }
 
+#[cfg(feature = "zeroize")]
+impl Drop for Keypair {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Keypair {}
+
+/// Formats the public key as a [`ShortPublicKey`](crate::logging::ShortPublicKey)
+/// and the secret key as `<redacted>`.
+#[cfg(feature = "defmt")]
+impl defmt::Format for Keypair {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "Keypair {{ public: {}, secret: <redacted> }}",
+            crate::logging::ShortPublicKey(&self.public)
+        )
+    }
+}
+
 impl Keypair {
     /// Securely generates a new keypair.
     ///
@@ -193,12 +643,41 @@ impl Keypair {
     /// # assert!(empty_keys != keys);
     /// # Ok(()) }
     /// ```
+    #[must_use = "dropping the keypair discards the only copy of the secret key"]
     pub fn generate<R: CryptoRng + RngCore>(
         rng: &mut R,
     ) -> Result<Keypair, KyberLibError> {
         keypair(rng)
     }
 
+    /// Generates a new keypair from the operating system RNG, a shorthand
+    /// for [`Keypair::generate`] with `OsRng` for prototypes and examples.
+    /// A transient failure of the RNG is retried as by
+    /// [`RetryRng`](crate::rng::RetryRng).
+    ///
+    /// This is deliberately not `Default`: `default()` is expected to be
+    /// cheap and infallible, and a keypair appearing from
+    /// `..Default::default()` or `unwrap_or_default()` would hide both the
+    /// entropy draw and its failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the operating
+    /// system RNG fails.
+    ///
+    /// ### Example
+    /// ```
+    /// # use kyberlib::*;
+    /// let keys = Keypair::random()?;
+    /// assert_ne!(keys, Keypair::random()?);
+    /// # Ok::<(), KyberLibError>(())
+    /// ```
+    #[cfg(feature = "os-rng")]
+    #[must_use = "dropping the keypair discards the only copy of the secret key"]
+    pub fn random() -> Result<Keypair, KyberLibError> {
+        keypair(&mut crate::rng::RetryRng(rand::rngs::OsRng))
+    }
+
     /// Explicitly exposes the secret key
     ///```
     /// use kyberlib::*;
@@ -210,6 +689,7 @@ impl Keypair {
     /// assert!(secret.len() == KYBER_SECRET_KEY_BYTES);
     /// assert!(secret.len() != 0);
     /// ```
+    #[must_use]
     pub fn expose_secret(&self) -> &SecretKey {
         &self.secret
     }
@@ -234,9 +714,13 @@ impl Keypair {
     /// let mut public_key = keys.public;
     /// let mut secret_key = keys.secret;
     /// let keys = Keypair::import(&mut public_key, &mut secret_key, &mut rng)?;
+    /// // With the zeroize feature the arrays were zeroed, so copy them again.
+    /// let mut public_key = keys.public;
+    /// let mut secret_key = keys.secret;
     /// let _ = Keypair::import(&mut public_key, &mut secret_key, &mut rng)?;
     /// # Ok(()) }
     /// ```
+    #[must_use = "the imported keypair is returned, not stored"]
     pub fn import<R: CryptoRng + RngCore>(
         public: &mut [u8; KYBER_PUBLIC_KEY_BYTES],
         secret: &mut [u8; KYBER_SECRET_KEY_BYTES],
@@ -244,9 +728,120 @@ impl Keypair {
     ) -> Result<Keypair, KyberLibError> {
         keypairfrom(public, secret, rng)
     }
+
+    /// Serialises the keypair as a versioned blob.
+    ///
+    /// The secret key already embeds the public key, so the blob is the
+    /// one produced by [`VersionedSecretKey::to_versioned_bytes`]:
+    ///
+    /// | Bytes | Field |
+    /// |-------|-------|
+    /// | 1     | format version, currently [`KEY_FORMAT_VERSION`] |
+    /// | 3168  | secret key `sk_cpa \|\| pk \|\| H(pk) \|\| z` (Kyber1024) |
+    ///
+    /// ### Example
+    /// ```
+    /// # use kyberlib::*;
+    /// # fn main() -> Result<(), KyberLibError> {
+    /// let mut rng = rand::thread_rng();
+    /// let keys = keypair(&mut rng)?;
+    /// let bytes = keys.to_bytes();
+    /// assert_eq!(Keypair::from_bytes(&bytes)?, keys);
+    /// # Ok(()) }
+    /// ```
+    pub fn to_bytes(&self) -> [u8; VERSIONED_SECRET_KEY_BYTES] {
+        self.secret.to_versioned_bytes()
+    }
+
+    /// Parses a blob produced by [`Keypair::to_bytes`], taking the public
+    /// key from the secret key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `bytes` has the wrong
+    /// size, `KyberLibError::InvalidInput` if it records an unknown format
+    /// version and `KyberLibError::InvalidKey` if the embedded hash of the
+    /// public key does not match it.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Keypair, KyberLibError> {
+        let secret = SecretKey::from_bytes_checked(bytes)?;
+        Ok(Keypair {
+            public: public(&secret),
+            secret,
+        })
+    }
+}
+
+/// Current version of the serialised key format written by
+/// [`Keypair::to_bytes`] and [`VersionedSecretKey::to_versioned_bytes`].
+pub const KEY_FORMAT_VERSION: u8 = 1;
+/// Size in bytes of a serialised keypair or secret key.
+pub const VERSIONED_SECRET_KEY_BYTES: usize =
+    1 + KYBER_SECRET_KEY_BYTES;
+
+/// Versioned serialisation of a [`SecretKey`].
+///
+/// `SecretKey` is a plain byte array, so the versioned format is provided
+/// through this extension trait rather than inherent methods. The layout is
+/// the one documented on [`Keypair::to_bytes`]; the version byte lets a
+/// later release change how keys are stored and still recognise, or
+/// refuse, blobs written by this one.
+pub trait VersionedSecretKey: Sized {
+    /// Serialises the secret key, prefixed with [`KEY_FORMAT_VERSION`].
+    fn to_versioned_bytes(&self) -> [u8; VERSIONED_SECRET_KEY_BYTES];
+
+    /// Parses a blob produced by
+    /// [`to_versioned_bytes`](VersionedSecretKey::to_versioned_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `bytes` has the wrong
+    /// size, `KyberLibError::InvalidInput` if it records an unknown format
+    /// version and `KyberLibError::InvalidKey` if the embedded hash of the
+    /// public key does not match it.
+    fn from_bytes_checked(bytes: &[u8]) -> Result<Self, KyberLibError>;
+}
+
+impl VersionedSecretKey for SecretKey {
+    fn to_versioned_bytes(&self) -> [u8; VERSIONED_SECRET_KEY_BYTES] {
+        let mut bytes = [0u8; VERSIONED_SECRET_KEY_BYTES];
+        bytes[0] = KEY_FORMAT_VERSION;
+        bytes[1..].copy_from_slice(self);
+        bytes
+    }
+
+    fn from_bytes_checked(bytes: &[u8]) -> Result<Self, KyberLibError> {
+        const PK_START: usize = KYBER_INDCPA_SECRET_KEY_BYTES;
+        const H_START: usize =
+            KYBER_SECRET_KEY_BYTES - 2 * KYBER_SYM_BYTES;
+        if bytes.len() != VERSIONED_SECRET_KEY_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        if bytes[0] != KEY_FORMAT_VERSION {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let mut secret = [0u8; KYBER_SECRET_KEY_BYTES];
+        secret.copy_from_slice(&bytes[1..]);
+        let mut h_pk = [0u8; KYBER_SYM_BYTES];
+        hash_h(
+            &mut h_pk,
+            &secret[PK_START..H_START],
+            KYBER_PUBLIC_KEY_BYTES,
+        );
+        if verify(
+            &h_pk,
+            &secret[H_START..H_START + KYBER_SYM_BYTES],
+            KYBER_SYM_BYTES,
+        ) != 0
+        {
+            #[cfg(feature = "zeroize")]
+            secret.zeroize();
+            return Err(KyberLibError::InvalidKey);
+        }
+        Ok(secret)
+    }
 }
 
-struct DummyRng {}
+pub(crate) struct DummyRng {}
 
 impl CryptoRng for DummyRng {}
 
@@ -282,6 +877,7 @@ impl RngCore for DummyRng {
 /// # Errors
 ///
 /// Returns a `KyberLibError` if the seed length is incorrect.
+#[must_use = "dropping the keypair discards the derived secret key"]
 pub fn derive(seed: &[u8]) -> Result<Keypair, KyberLibError> {
     let mut public = [0u8; KYBER_PUBLIC_KEY_BYTES];
     let mut secret = [0u8; KYBER_SECRET_KEY_BYTES];
@@ -298,6 +894,89 @@ pub fn derive(seed: &[u8]) -> Result<Keypair, KyberLibError> {
     Ok(Keypair { public, secret })
 }
 
+/// Generates a keypair whose secret key is stored in compact form.
+///
+/// The compact secret key is the seed passed to [`derive`], so it is 64
+/// bytes instead of `KYBER_SECRET_KEY_BYTES`. The secret vector alone
+/// would not do: re-encryption during decapsulation needs the public key,
+/// which cannot be recomputed from `s` without the error vector, so the
+/// whole key is re-derived from the seed instead.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let (public, compact) = keypair_compact(&mut rng)?;
+/// let (ct, ss1) = encapsulate(&public, &mut rng)?;
+/// let ss2 = decapsulate_compact(&ct, &compact)?;
+/// assert_eq!(ss1, ss2);
+/// # Ok(())}
+/// ```
+#[must_use = "dropping the keypair discards the only copy of the secret key"]
+pub fn keypair_compact<R>(
+    rng: &mut R,
+) -> Result<(PublicKey, CompactSecretKey), KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    let mut compact = [0u8; KYBER_COMPACT_SECRET_KEY_BYTES];
+    randombytes(&mut compact, KYBER_COMPACT_SECRET_KEY_BYTES, rng)?;
+    let keys = derive(&compact)?;
+    Ok((keys.public, compact))
+}
+
+/// Expands a compact secret key into the full secret key.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if `compact` is not
+/// `KYBER_COMPACT_SECRET_KEY_BYTES` long.
+#[must_use = "dropping the result discards the expanded secret key"]
+pub fn expand_secret_key(
+    compact: &[u8],
+) -> Result<SecretKey, KyberLibError> {
+    let keys = derive(compact)?;
+    Ok(keys.secret)
+}
+
+/// Decapsulates a ciphertext with a compact secret key.
+///
+/// The full secret key is re-derived for the call and erased afterwards
+/// when the `zeroize` feature is enabled. The shared secret is the one
+/// [`decapsulate`] yields with the expanded key.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if the input sizes are incorrect.
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn decapsulate_compact(ct: &[u8], compact: &[u8]) -> Decapsulated {
+    if ct.len() != KYBER_CIPHERTEXT_BYTES
+        || compact.len() != KYBER_COMPACT_SECRET_KEY_BYTES
+    {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut public = [0u8; KYBER_PUBLIC_KEY_BYTES];
+    let mut secret = [0u8; KYBER_SECRET_KEY_BYTES];
+    generate_key_pair(
+        &mut public,
+        &mut secret,
+        &mut DummyRng {},
+        Some((
+            &compact[..KYBER_SYM_BYTES],
+            &compact[KYBER_SYM_BYTES..],
+        )),
+    )?;
+    let ss = decapsulate(ct, &secret);
+    #[cfg(feature = "zeroize")]
+    secret.zeroize();
+    ss
+}
+
 /// Extracts a public key from a private key.
 ///
 /// This function extracts the public key from a private key.
@@ -309,6 +988,7 @@ pub fn derive(seed: &[u8]) -> Result<Keypair, KyberLibError> {
 /// # Returns
 ///
 /// Returns the public key as a `PublicKey`.
+#[must_use]
 pub fn public(sk: &[u8]) -> PublicKey {
     let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
     pk.copy_from_slice(
@@ -318,3 +998,96 @@ pub fn public(sk: &[u8]) -> PublicKey {
     );
     pk
 }
+
+/// Parses a public key, checking its length and that every coefficient is
+/// in range, in const context.
+///
+/// Lets a key embedded in a firmware image be validated by the compiler
+/// rather than at boot; [`include_pubkey!`](crate::include_pubkey) wraps
+/// it for key files. The checks are those of [`check_public_key`].
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # let keys = derive(&[7u8; 64]).unwrap();
+/// # let bytes = &keys.public[..];
+/// let key = public_key_from_bytes_const(bytes)?;
+/// # assert_eq!(key, keys.public);
+/// # Ok::<(), KyberLibError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `bytes` is not
+/// `KYBER_PUBLIC_KEY_BYTES` long and `KyberLibError::InvalidKey` if a
+/// coefficient is not below `q`.
+pub const fn public_key_from_bytes_const(
+    bytes: &[u8],
+) -> Result<PublicKey, KyberLibError> {
+    if bytes.len() != KYBER_PUBLIC_KEY_BYTES {
+        return Err(KyberLibError::InvalidLength);
+    }
+    if !coefficients_in_range(bytes) {
+        return Err(KyberLibError::InvalidKey);
+    }
+    let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+    let mut i = 0;
+    while i < KYBER_PUBLIC_KEY_BYTES {
+        pk[i] = bytes[i];
+        i += 1;
+    }
+    Ok(pk)
+}
+
+/// Size in bytes of a [`transcript_hash`].
+pub const TRANSCRIPT_HASH_BYTES: usize = 32;
+
+// Domain separation prefix of the transcript hash input.
+const TRANSCRIPT_LABEL: &[u8] = b"kyberlib transcript v1";
+
+/// Hashes a KEM exchange into a value both parties share, for channel
+/// binding in the manner of a TLS exporter.
+///
+/// The hash is SHAKE256, in every mode, over
+/// `"kyberlib transcript v1" || pk || ct || ss`. Every element has a fixed
+/// size, so the concatenation is unambiguous. Because the shared secret
+/// is hashed in, only the two parties to the exchange can compute the
+/// value, and it reveals nothing about the secret itself.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+/// let initiator = transcript_hash(&keys.public, &ct, &ss);
+///
+/// let ss = decapsulate(&ct, &keys.secret)?;
+/// assert_eq!(transcript_hash(&keys.public, &ct, &ss), initiator);
+/// # Ok(())}
+/// ```
+#[must_use]
+pub fn transcript_hash(
+    pk: &PublicKey,
+    ct: &[u8; KYBER_CIPHERTEXT_BYTES],
+    ss: &SharedSecret,
+) -> [u8; TRANSCRIPT_HASH_BYTES] {
+    const LEN: usize = TRANSCRIPT_LABEL.len()
+        + KYBER_PUBLIC_KEY_BYTES
+        + KYBER_CIPHERTEXT_BYTES
+        + KYBER_SHARED_SECRET_BYTES;
+    let mut input = [0u8; LEN];
+    let (label, rest) = input.split_at_mut(TRANSCRIPT_LABEL.len());
+    let (pk_part, rest) = rest.split_at_mut(KYBER_PUBLIC_KEY_BYTES);
+    let (ct_part, ss_part) = rest.split_at_mut(KYBER_CIPHERTEXT_BYTES);
+    label.copy_from_slice(TRANSCRIPT_LABEL);
+    pk_part.copy_from_slice(pk);
+    ct_part.copy_from_slice(ct);
+    ss_part.copy_from_slice(ss);
+    let mut out = [0u8; TRANSCRIPT_HASH_BYTES];
+    shake256(&mut out, TRANSCRIPT_HASH_BYTES, &input, LEN);
+    #[cfg(feature = "zeroize")]
+    input.zeroize();
+    out
+}
@@ -1,11 +1,10 @@
-#![allow(non_snake_case, dead_code)]
-use crate::align::Eta4xBuf;
-#[cfg(feature = "90s")]
-use crate::align::IndcpaBuf;
+#![allow(non_snake_case, clippy::identity_op)]
+use super::align::NoiseBuf;
+use super::poly::Poly;
 use crate::params::KYBER_N;
-use crate::poly::*;
 use core::arch::x86_64::*;
 
+#[target_feature(enable = "avx2")]
 fn cbd2(r: &mut Poly, buf: &[__m256i]) {
     unsafe {
         let mask55: __m256i = _mm256_set1_epi32(0x55555555);
@@ -13,6 +12,7 @@ fn cbd2(r: &mut Poly, buf: &[__m256i]) {
         let mask03: __m256i = _mm256_set1_epi32(0x03030303);
         let mask0F: __m256i = _mm256_set1_epi32(0x0F0F0F0F);
         let (mut f0, mut f1, mut f2, mut f3);
+        #[allow(clippy::needless_range_loop)]
         for i in 0..(KYBER_N / 64) {
             f0 = _mm256_load_si256(&buf[i]);
 
@@ -49,6 +49,8 @@ fn cbd2(r: &mut Poly, buf: &[__m256i]) {
     }
 }
 
+#[cfg(feature = "kyber512")]
+#[target_feature(enable = "avx2")]
 fn cbd3(r: &mut Poly, buf: &[u8]) {
     unsafe {
         let (mut f0, mut f1, mut f2, mut f3);
@@ -105,27 +107,15 @@ fn cbd3(r: &mut Poly, buf: &[u8]) {
     }
 }
 
-pub fn poly_cbd_eta1(r: &mut Poly, buf: &Eta4xBuf) {
-    unsafe {
-        if cfg!(feature = "kyber512") {
-            cbd3(r, &buf.coeffs)
-        } else {
-            cbd2(r, &buf.vec)
-        }
-    }
-}
-
-#[cfg(feature = "90s")]
-pub fn poly_cbd_eta1_90s(r: &mut Poly, buf: &IndcpaBuf) {
-    unsafe {
-        if cfg!(feature = "kyber512") {
-            cbd3(r, &buf.coeffs)
-        } else {
-            cbd2(r, &buf.vec)
-        }
-    }
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_cbd_eta1(r: &mut Poly, buf: &NoiseBuf) {
+    #[cfg(feature = "kyber512")]
+    cbd3(r, unsafe { &buf.coeffs });
+    #[cfg(not(feature = "kyber512"))]
+    cbd2(r, unsafe { &buf.vec });
 }
 
-pub fn poly_cbd_eta2(r: &mut Poly, buf: &[__m256i]) {
-    cbd2(r, &buf)
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_cbd_eta2(r: &mut Poly, buf: &NoiseBuf) {
+    cbd2(r, unsafe { &buf.vec })
 }
@@ -1,7 +1,8 @@
+use super::{poly::*, polyvec::*, rejsample::*};
+use crate::reference::indcpa::rej_uniform;
 use crate::rng::randombytes;
 use crate::{
-    params::*, poly::*, polyvec::*, symmetric::*, CryptoRng,
-    KyberLibError, RngCore,
+    params::*, symmetric::*, CryptoRng, KyberLibError, RngCore,
 };
 
 /// Name:  pack_pk
@@ -13,7 +14,8 @@ use crate::{
 /// Arguments:   [u8] r:  the output serialized public key
 ///  const poly *pk:  the input public-key polynomial
 ///  const [u8] seed: the input public seed
-fn pack_pk(r: &mut [u8], pk: &mut Polyvec, seed: &[u8]) {
+#[target_feature(enable = "avx2")]
+fn pack_pk(r: &mut [u8], pk: &Polyvec, seed: &[u8]) {
     const END: usize = KYBER_SYM_BYTES + KYBER_POLYVEC_BYTES;
     polyvec_tobytes(r, pk);
     r[KYBER_POLYVEC_BYTES..END]
@@ -28,31 +30,25 @@ fn pack_pk(r: &mut [u8], pk: &mut Polyvec, seed: &[u8]) {
 /// Arguments:   - Polyvec pk:  output public-key vector of polynomials
 ///  - [u8] seed:   output seed to generate matrix A
 ///  - const [u8] packedpk: input serialized public key
+#[target_feature(enable = "avx2")]
 fn unpack_pk(pk: &mut Polyvec, seed: &mut [u8], packedpk: &[u8]) {
     const END: usize = KYBER_SYM_BYTES + KYBER_POLYVEC_BYTES;
-    polyvec_frombytes(pk, packedpk);
+    // SAFETY: a public key holds KYBER_POLYVEC_BYTES of polynomials.
+    unsafe { polyvec_frombytes(pk, packedpk) };
     seed[..KYBER_SYM_BYTES]
         .copy_from_slice(&packedpk[KYBER_POLYVEC_BYTES..END]);
 }
 
-/// Name:  pack_sk
-///
-/// Description: Serialize the secret key
-///
-/// Arguments: - [u8] r:  output serialized secret key
-///  - const Polyvec sk: input vector of polynomials (secret key)
-fn pack_sk(r: &mut [u8], sk: &mut Polyvec) {
-    polyvec_tobytes(r, sk);
-}
-
 /// Name:  unpack_sk
 ///
-/// Description: De-serialize the secret key, inverse of pack_sk
+/// Description: De-serialize the secret key, inverse of polyvec_tobytes
 ///
 /// Arguments:   - Polyvec sk: output vector of polynomials (secret key)
 ///  - const [u8] packedsk: input serialized secret key
+#[target_feature(enable = "avx2")]
 fn unpack_sk(sk: &mut Polyvec, packedsk: &[u8]) {
-    polyvec_frombytes(sk, packedsk);
+    // SAFETY: an IND-CPA secret key is KYBER_POLYVEC_BYTES long.
+    unsafe { polyvec_frombytes(sk, packedsk) };
 }
 
 /// Name:  pack_ciphertext
@@ -64,9 +60,13 @@ fn unpack_sk(sk: &mut Polyvec, packedsk: &[u8]) {
 /// Arguments:   [u8] r:  the output serialized ciphertext
 ///  const poly *pk:  the input vector of polynomials b
 ///  const [u8] seed: the input polynomial v
-fn pack_ciphertext(r: &mut [u8], b: &mut Polyvec, v: Poly) {
-    polyvec_compress(r, *b);
-    poly_compress(&mut r[KYBER_POLYVEC_COMPRESSED_BYTES..], v);
+#[target_feature(enable = "avx2")]
+fn pack_ciphertext(r: &mut [u8], b: &Polyvec, v: Poly) {
+    // SAFETY: a ciphertext holds both compressed parts.
+    unsafe {
+        polyvec_compress(r, b);
+        poly_compress(&mut r[KYBER_POLYVEC_COMPRESSED_BYTES..], v);
+    }
 }
 
 /// Name:  unpack_ciphertext
@@ -77,121 +77,78 @@ fn pack_ciphertext(r: &mut [u8], b: &mut Polyvec, v: Poly) {
 /// Arguments:   - Polyvec b:   output vector of polynomials b
 ///  - poly *v:  output polynomial v
 ///  - const [u8] c:   input serialized ciphertext
+#[target_feature(enable = "avx2")]
 fn unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8]) {
-    polyvec_decompress(b, c);
-    poly_decompress(v, &c[KYBER_POLYVEC_COMPRESSED_BYTES..]);
+    // SAFETY: as in `pack_ciphertext`.
+    unsafe {
+        polyvec_decompress(b, c);
+        poly_decompress(v, &c[KYBER_POLYVEC_COMPRESSED_BYTES..]);
+    }
 }
 
-/// Name:  rej_uniform
-///
-/// Description: Run rejection sampling on uniform random bytes to generate
-///  uniform random integers mod q
+/// Name:  sample_uniform
 ///
-/// Arguments: - i16 *r:  output buffer
-///  - usize len:   requested number of 16-bit integers (uniform mod q)
-///  - const [u8] buf:  input buffer (assumed to be uniform random bytes)
-///  - usize buflen:  length of input buffer in bytes
+/// Description: Deterministically sample the matrix entry that the
+///  reference gen_matrix_entry derives from seed, x and y, in the
+///  standard coefficient order
 ///
-/// Returns number of sampled 16-bit integers (at most len)
-fn rej_uniform(
-    r: &mut [i16],
-    len: usize,
-    buf: &[u8],
-    buflen: usize,
-) -> usize {
-    let (mut ctr, mut pos) = (0usize, 0usize);
-    let (mut val0, mut val1);
-
-    while ctr < len && pos + 3 <= buflen {
-        val0 = ((buf[pos]) as u16 | (buf[pos + 1] as u16) << 8) & 0xFFF;
-        val1 = ((buf[pos + 1] >> 4) as u16
-            | (buf[pos + 2] as u16) << 4)
-            & 0xFFF;
-        pos += 3;
-
-        if val0 < KYBER_Q as u16 {
-            r[ctr] = val0 as i16;
-            ctr += 1;
-        }
-        if ctr < len && val1 < KYBER_Q as u16 {
-            r[ctr] = val1 as i16;
-            ctr += 1;
-        }
+/// Arguments:   - Poly r: output polynomial
+///  - const [u8] seed: input seed
+///  - u8 x, u8 y: indices appended to the seed
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+pub(crate) fn sample_uniform(r: &mut Poly, seed: &[u8], x: u8, y: u8) {
+    let mut buf = [0u8; REJ_UNIFORM_AVX_BUFLEN];
+    let mut state = xof_init(seed, x, y);
+
+    xof_squeezeblocks(&mut buf, REJ_UNIFORM_AVX_NBLOCKS, &mut state);
+    // SAFETY: only the coefficients are read or written.
+    let coeffs = unsafe { &mut r.coeffs };
+    // SAFETY: the features rej_uniform_avx needs are enabled.
+    let mut ctr = unsafe { rej_uniform_avx(coeffs, &buf) };
+    let mut buflen = REJ_UNIFORM_AVX_BUFLEN;
+
+    while ctr < KYBER_N {
+        let off = buflen % 3;
+        buf.copy_within(buflen - off..buflen, 0);
+        xof_squeezeblocks(&mut buf[off..], 1, &mut state);
+        buflen = off + XOF_BLOCKBYTES;
+        ctr += rej_uniform(
+            &mut coeffs[ctr..],
+            KYBER_N - ctr,
+            &buf,
+            buflen,
+        );
     }
-    ctr
-}
-
-fn gen_a(a: &mut [Polyvec], b: &[u8]) {
-    gen_matrix(a, b, false);
-}
-
-fn gen_at(a: &mut [Polyvec], b: &[u8]) {
-    gen_matrix(a, b, true);
 }
 
 /// Name:  gen_matrix
 ///
 /// Description: Deterministically generate matrix A (or the transpose of A)
-///  from a seed. Entries of the matrix are polynomials that look
-///  uniformly random. Performs rejection sampling on output of
-///  a XOF
+///  from a seed, as the reference gen_matrix does, and permute the
+///  entries into the coefficient order of the AVX2 NTT
 ///
-/// Arguments:   - Polyvec a:   ouptput matrix A
+/// Arguments:   - Polyvec a: ouptput matrix A
 ///  - const [u8] seed: input seed
 ///  - bool transposed: boolean deciding whether A or A^T is generated
+#[target_feature(enable = "avx2,bmi2,popcnt")]
 fn gen_matrix(a: &mut [Polyvec], seed: &[u8], transposed: bool) {
-    let mut ctr;
-    const GEN_MATRIX_NBLOCKS: usize =
-        (12 * KYBER_N / 8 * (1 << 12) / KYBER_Q + XOF_BLOCKBYTES)
-            / XOF_BLOCKBYTES;
-    let mut buf = [0u8; GEN_MATRIX_NBLOCKS * XOF_BLOCKBYTES + 2];
-    let mut buflen: usize;
-    let mut off: usize;
-    let mut state = XofState::new();
-
-    for (i, item) in
-        a.iter_mut().enumerate().take(KYBER_SECURITY_PARAMETER)
-    {
-        for j in 0..KYBER_SECURITY_PARAMETER {
-            if transposed {
-                xof_absorb(&mut state, seed, i as u8, j as u8);
-            } else {
-                xof_absorb(&mut state, seed, j as u8, i as u8);
-            }
-            xof_squeezeblocks(&mut buf, GEN_MATRIX_NBLOCKS, &mut state);
-            buflen = GEN_MATRIX_NBLOCKS * XOF_BLOCKBYTES;
-            ctr = rej_uniform(
-                &mut item.vec[j].coeffs,
-                KYBER_N,
-                &buf,
-                buflen,
-            );
-
-            while ctr < KYBER_N {
-                off = buflen % 3;
-                for k in 0..off {
-                    buf[k] = buf[buflen - off + k];
-                }
-                xof_squeezeblocks(&mut buf[off..], 1, &mut state);
-                buflen = off + XOF_BLOCKBYTES;
-                ctr += rej_uniform(
-                    &mut item.vec[j].coeffs[ctr..],
-                    KYBER_N - ctr,
-                    &buf,
-                    buflen,
-                );
-            }
+    for (i, row) in a.iter_mut().enumerate() {
+        for (j, entry) in row.vec.iter_mut().enumerate() {
+            let [x, y] = matrix_index_bytes(i, j, transposed);
+            sample_uniform(entry, seed, x, y);
+            poly_nttunpack(entry);
         }
     }
 }
 
-// Name:  indcpa_keypair
-//
-// Description: Generates public and private key for the CPA-secure
-//  public-key encryption scheme underlying Kyber
-//
-// Arguments: - [u8] pk: output public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
-//  - [u8] sk: output private key (length KYBER_INDCPA_SECRET_KEY_BYTES)
+/// Name:  indcpa_keypair
+///
+/// Description: Generates public and private key for the CPA-secure
+///  public-key encryption scheme underlying Kyber
+///
+/// Arguments: - [u8] pk: output public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
+///  - [u8] sk: output private key (length KYBER_INDCPA_SECRET_KEY_BYTES)
+#[target_feature(enable = "avx2,bmi2,popcnt")]
 pub(crate) fn indcpa_keypair<R>(
     pk: &mut [u8],
     sk: &mut [u8],
@@ -217,36 +174,31 @@ where
     hash_g(&mut buf, &randbuf, KYBER_SYM_BYTES);
 
     let (publicseed, noiseseed) = buf.split_at(KYBER_SYM_BYTES);
-    gen_a(&mut a, publicseed);
+    gen_matrix(&mut a, publicseed, false);
 
-    for (i, _item) in
-        a.iter().enumerate().take(KYBER_SECURITY_PARAMETER)
-    {
-        poly_getnoise_eta1(&mut skpv.vec[i], noiseseed, nonce);
+    for poly in skpv.vec.iter_mut() {
+        poly_getnoise_eta1(poly, noiseseed, nonce);
         nonce += 1;
     }
-    for (i, _item) in
-        a.iter().enumerate().take(KYBER_SECURITY_PARAMETER)
-    {
-        poly_getnoise_eta1(&mut e.vec[i], noiseseed, nonce);
+    for poly in e.vec.iter_mut() {
+        poly_getnoise_eta1(poly, noiseseed, nonce);
         nonce += 1;
     }
 
     polyvec_ntt(&mut skpv);
+    polyvec_reduce(&mut skpv);
     polyvec_ntt(&mut e);
 
     // matrix-vector multiplication
-    for (i, _item) in
-        a.iter().enumerate().take(KYBER_SECURITY_PARAMETER)
-    {
-        polyvec_basemul_acc_montgomery(&mut pkpv.vec[i], &a[i], &skpv);
-        poly_tomont(&mut pkpv.vec[i]);
+    for (t, row) in pkpv.vec.iter_mut().zip(a.iter()) {
+        polyvec_basemul_acc_montgomery(t, row, &skpv);
+        poly_tomont(t);
     }
     polyvec_add(&mut pkpv, &e);
     polyvec_reduce(&mut pkpv);
 
-    pack_sk(sk, &mut skpv);
-    pack_pk(pk, &mut pkpv, publicseed);
+    polyvec_tobytes(sk, &skpv);
+    pack_pk(pk, &pkpv, publicseed);
     Ok(())
 }
 
@@ -255,11 +207,13 @@ where
 /// Description: Encryption function of the CPA-secure
 ///  public-key encryption scheme underlying Kyber.
 ///
-/// Arguments: - [u8] c:  output ciphertext (length KYBER_INDCPA_BYTES)
-///  - const [u8] m:  input message (length KYBER_SYM_BYTES)
+/// Arguments:
+///  - const [u8] c:    output ciphertext (length KYBER_INDCPA_BYTES)
+///  - const [u8] m:    input message (length KYBER_SYM_BYTES)
 ///  - const [u8] pk:   input public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
 ///  - const [u8] coin: input random coins used as seed (length KYBER_SYM_BYTES)
-///      to deterministically generate all randomness
+///    to deterministically generate all randomness
+#[target_feature(enable = "avx2,bmi2,popcnt")]
 pub(crate) fn indcpa_enc(
     c: &mut [u8],
     m: &[u8],
@@ -279,19 +233,16 @@ pub(crate) fn indcpa_enc(
     let mut nonce = 0u8;
 
     unpack_pk(&mut pkpv, &mut seed, pk);
-    poly_frommsg(&mut k, m);
-    gen_at(&mut at, &seed);
+    // SAFETY: the message is KYBER_SYM_BYTES long.
+    unsafe { poly_frommsg(&mut k, m) };
+    gen_matrix(&mut at, &seed, true);
 
-    for (i, _item) in
-        a.iter().enumerate().take(KYBER_SECURITY_PARAMETER)
-    {
-        poly_getnoise_eta1(&mut sp.vec[i], coins, nonce);
+    for poly in sp.vec.iter_mut() {
+        poly_getnoise_eta1(poly, coins, nonce);
         nonce += 1;
     }
-    for (i, _item) in
-        a.iter().enumerate().take(KYBER_SECURITY_PARAMETER)
-    {
-        poly_getnoise_eta2(&mut ep.vec[i], coins, nonce);
+    for poly in ep.vec.iter_mut() {
+        poly_getnoise_eta2(poly, coins, nonce);
         nonce += 1;
     }
     poly_getnoise_eta2(&mut epp, coins, nonce);
@@ -299,10 +250,8 @@ pub(crate) fn indcpa_enc(
     polyvec_ntt(&mut sp);
 
     // matrix-vector multiplication
-    for (i, _item) in
-        a.iter().enumerate().take(KYBER_SECURITY_PARAMETER)
-    {
-        polyvec_basemul_acc_montgomery(&mut b.vec[i], &at[i], &sp);
+    for (t, row) in b.vec.iter_mut().zip(at.iter()) {
+        polyvec_basemul_acc_montgomery(t, row, &sp);
     }
 
     polyvec_basemul_acc_montgomery(&mut v, &pkpv, &sp);
@@ -315,7 +264,7 @@ pub(crate) fn indcpa_enc(
     polyvec_reduce(&mut b);
     poly_reduce(&mut v);
 
-    pack_ciphertext(c, &mut b, v);
+    pack_ciphertext(c, &b, v);
 }
 
 /// Name:  indcpa_dec
@@ -323,9 +272,11 @@ pub(crate) fn indcpa_enc(
 /// Description: Decryption function of the CPA-secure
 ///  public-key encryption scheme underlying Kyber.
 ///
-/// Arguments:   - [u8] m:  output decrypted message (of length KYBER_SYM_BYTES)
-///  - const [u8] c:  input ciphertext (of length KYBER_INDCPA_BYTES)
-///  - const [u8] sk: input secret key (of length KYBER_INDCPA_SECRET_KEY_BYTES)
+/// Arguments:
+///  - const [u8] m:    output decrypted message (of length KYBER_SYM_BYTES)
+///  - const [u8] c:    input ciphertext (of length KYBER_INDCPA_BYTES)
+///  - const [u8] sk:   input secret key (of length KYBER_INDCPA_SECRET_KEY_BYTES)
+#[target_feature(enable = "avx2,bmi2,popcnt")]
 pub(crate) fn indcpa_dec(m: &mut [u8], c: &[u8], sk: &[u8]) {
     let (mut b, mut skpv) = (Polyvec::new(), Polyvec::new());
     let (mut v, mut mp) = (Poly::new(), Poly::new());
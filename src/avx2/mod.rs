@@ -1,12 +1,175 @@
-pub mod aes256ctr;
-pub mod align;
-pub mod cbd;
-pub mod consts;
-pub mod fips202;
-pub mod fips202x4;
-pub mod indcpa;
-pub mod keccak4x;
-pub mod poly;
-pub mod polyvec;
-pub mod rejsample;
-pub mod verify;
+//! AVX2 implementation of the IND-CPA layer, selected at run time.
+//!
+//! The NTT, inverse NTT and base multiplication are the assembly of the
+//! Kyber AVX2 implementation; CBD sampling, rejection sampling and the
+//! compression and serialization kernels use `core::arch::x86_64`
+//! intrinsics. Hashing, the XOF and the PRF stay in the shared
+//! `symmetric` module, so that both backends consume the same byte
+//! streams.
+//!
+//! Every function here is compiled with AVX2 enabled regardless of the
+//! target features of the build. [`supported`] checks once whether the
+//! CPU has AVX2, BMI2 and POPCNT, the equivalent of
+//! `is_x86_feature_detected!` that also works without `std`, and the
+//! `acceleration` module only selects the backend if it does, running
+//! the reference implementation otherwise. Both produce the same keys,
+//! ciphertexts and messages bit for bit.
+
+use crate::params::KYBER_COMPRESSION_OVERRIDDEN;
+
+mod align;
+mod cbd;
+mod consts;
+pub(crate) mod indcpa;
+pub(crate) mod poly;
+pub(crate) mod polyvec;
+mod rejsample;
+
+// The macro expansion trips lints this crate denies.
+#[allow(deprecated_in_future, unexpected_cfgs)]
+mod detect {
+    cpufeatures::new!(cpuid, "avx2", "bmi2", "popcnt");
+
+    pub(super) fn supported() -> bool {
+        cpuid::get()
+    }
+}
+
+/// Whether the CPU can run the AVX2 backend. Never with the `kyber_du`
+/// or `kyber_dv` overrides, which only the reference compression code
+/// supports.
+pub(crate) fn supported() -> bool {
+    !KYBER_COMPRESSION_OVERRIDDEN && detect::supported()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{poly, rejsample::*, *};
+    use crate::{params::*, reference};
+    use rand::{rngs::OsRng, RngCore};
+
+    const ROUNDS: usize = 64;
+
+    fn random<const N: usize>() -> [u8; N] {
+        let mut buf = [0u8; N];
+        OsRng.fill_bytes(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn polynomials_are_cache_line_aligned() {
+        assert_eq!(align_of::<poly::Poly>(), 64);
+        assert_eq!(size_of::<poly::Poly>(), 2 * KYBER_N);
+        let v = polyvec::Polyvec::new();
+        for p in v.vec.iter() {
+            assert_eq!(unsafe { p.coeffs.as_ptr() } as usize % 64, 0);
+        }
+    }
+
+    #[test]
+    fn indcpa_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        let mut rng = OsRng;
+        for _ in 0..ROUNDS {
+            let (d, z) = (random::<KYBER_SYM_BYTES>(), [0u8; 32]);
+            let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+            let mut sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+            let mut ref_pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+            let mut ref_sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+            unsafe {
+                indcpa::indcpa_keypair(
+                    &mut pk,
+                    &mut sk,
+                    Some((&d, &z)),
+                    &mut rng,
+                )
+                .unwrap();
+            }
+            reference::indcpa::indcpa_keypair(
+                &mut ref_pk,
+                &mut ref_sk,
+                Some((&d, &z)),
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(pk, ref_pk);
+            assert_eq!(sk, ref_sk);
+
+            let (m, coins) = (
+                random::<KYBER_SYM_BYTES>(),
+                random::<KYBER_SYM_BYTES>(),
+            );
+            let mut ct = [0u8; KYBER_INDCPA_BYTES];
+            let mut ref_ct = [0u8; KYBER_INDCPA_BYTES];
+            unsafe { indcpa::indcpa_enc(&mut ct, &m, &pk, &coins) };
+            reference::indcpa::indcpa_enc(&mut ref_ct, &m, &pk, &coins);
+            assert_eq!(ct, ref_ct);
+
+            let mut out = [0u8; KYBER_SYM_BYTES];
+            let mut ref_out = [0u8; KYBER_SYM_BYTES];
+            unsafe { indcpa::indcpa_dec(&mut out, &ct, &sk) };
+            reference::indcpa::indcpa_dec(&mut ref_out, &ct, &sk);
+            assert_eq!(out, m);
+            assert_eq!(out, ref_out);
+
+            // A tampered ciphertext decrypts to the same wrong message.
+            ct[0] ^= 1;
+            unsafe { indcpa::indcpa_dec(&mut out, &ct, &sk) };
+            reference::indcpa::indcpa_dec(&mut ref_out, &ct, &sk);
+            assert_eq!(out, ref_out);
+        }
+    }
+
+    #[test]
+    fn rejection_sampling_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        for round in 0..ROUNDS {
+            let mut buf = random::<REJ_UNIFORM_AVX_BUFLEN>();
+            // Force rejections, and some in every 16 byte lane.
+            if round % 2 == 1 {
+                for byte in buf.iter_mut().step_by(5) {
+                    *byte = 0xff;
+                }
+            }
+            let mut r = [0i16; KYBER_N];
+            let mut ref_r = [0i16; KYBER_N];
+            let ctr = unsafe { rej_uniform_avx(&mut r, &buf) };
+            let ref_ctr = reference::indcpa::rej_uniform(
+                &mut ref_r,
+                KYBER_N,
+                &buf,
+                REJ_UNIFORM_AVX_BUFLEN,
+            );
+            assert_eq!(ctr, ref_ctr);
+            assert_eq!(r[..ctr], ref_r[..ctr]);
+        }
+    }
+
+    #[test]
+    fn noise_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        for nonce in 0..ROUNDS as u8 {
+            let seed = random::<KYBER_SYM_BYTES>();
+            let mut r = poly::Poly::new();
+            let mut ref_r = reference::poly::Poly::new();
+
+            unsafe { poly::poly_getnoise_eta1(&mut r, &seed, nonce) };
+            reference::poly::poly_getnoise_eta1(
+                &mut ref_r, &seed, nonce,
+            );
+            assert_eq!(unsafe { r.coeffs }, ref_r.coeffs);
+
+            unsafe { poly::poly_getnoise_eta2(&mut r, &seed, nonce) };
+            reference::poly::poly_getnoise_eta2(
+                &mut ref_r, &seed, nonce,
+            );
+            assert_eq!(unsafe { r.coeffs }, ref_r.coeffs);
+        }
+    }
+}
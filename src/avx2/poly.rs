@@ -1,24 +1,22 @@
-#![allow(unused_imports)]
-use crate::{
-    align::*, cbd::*, consts::*, fips202::*, fips202x4::*, params::*,
-    symmetric::*,
-};
+#![allow(clippy::identity_op)]
+use super::{align::*, cbd::*, consts::*};
+use crate::{params::*, symmetric::*};
 use core::arch::x86_64::*;
 
-pub const NOISE_NBLOCKS: usize =
-    (KYBER_ETA1 * KYBER_N / 4 + SHAKE256_RATE - 1) / SHAKE256_RATE;
-
+// Aligned to 64 bytes rather than the 32 of __m256i, so that the
+// AVX-512 kernels, which share this type, can use aligned loads and
+// stores. The size stays 512 bytes.
 #[derive(Clone)]
-#[repr(C)]
-pub union Poly {
-    pub coeffs: [i16; KYBER_N],
-    pub vec: [__m256i; (KYBER_N + 15) / 16],
+#[repr(C, align(64))]
+pub(crate) union Poly {
+    pub(crate) coeffs: [i16; KYBER_N],
+    pub(crate) vec: [__m256i; KYBER_N.div_ceil(16)],
 }
 
 impl Copy for Poly {}
 
 impl Poly {
-    pub fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Poly {
             coeffs: [0i16; KYBER_N],
         }
@@ -46,7 +44,8 @@ extern "C" {
 }
 
 #[cfg(any(feature = "kyber512", not(feature = "kyber1024")))]
-pub unsafe fn poly_compress(r: &mut [u8], a: Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_compress(r: &mut [u8], a: Poly) {
     let (mut f0, mut f1, mut f2, mut f3);
     let v: __m256i =
         _mm256_load_si256(QDATA.vec[_16XV / 16..].as_ptr());
@@ -86,7 +85,8 @@ pub unsafe fn poly_compress(r: &mut [u8], a: Poly) {
 }
 
 #[cfg(any(feature = "kyber512", not(feature = "kyber1024")))]
-pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_decompress(r: &mut Poly, a: &[u8]) {
     let (mut t, mut f);
     let q: __m256i =
         _mm256_load_si256(QDATA.vec[_16XQ / 16..].as_ptr());
@@ -109,7 +109,8 @@ pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8]) {
 }
 
 #[cfg(feature = "kyber1024")]
-pub unsafe fn poly_compress(r: &mut [u8], a: Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_compress(r: &mut [u8], a: Poly) {
     let (mut f0, mut f1);
     let (mut t0, mut t1);
     let mut tmp;
@@ -152,7 +153,8 @@ pub unsafe fn poly_compress(r: &mut [u8], a: Poly) {
 }
 
 #[cfg(feature = "kyber1024")]
-pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_decompress(r: &mut Poly, a: &[u8]) {
     let (mut t, mut f, mut ti);
 
     let q = _mm256_load_si256(&QDATA.vec[_16XQ / 16]);
@@ -178,11 +180,12 @@ pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8]) {
         f = _mm256_and_si256(f, mask);
         f = _mm256_mullo_epi16(f, shift);
         f = _mm256_mulhrs_epi16(f, q);
-        _mm256_store_si256(r.vec[i..].as_mut_ptr() as *mut __m256i, f);
+        _mm256_store_si256(r.vec[i..].as_mut_ptr(), f);
     }
 }
 
-pub fn poly_frombytes(r: &mut Poly, a: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_frombytes(r: &mut Poly, a: &[u8]) {
     unsafe {
         nttfrombytes_avx(
             r.coeffs.as_mut_ptr(),
@@ -192,7 +195,8 @@ pub fn poly_frombytes(r: &mut Poly, a: &[u8]) {
     }
 }
 
-pub fn poly_tobytes(r: &mut [u8], a: Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_tobytes(r: &mut [u8], a: Poly) {
     let mut buf = [0u8; KYBER_POLY_BYTES];
     unsafe {
         ntttobytes_avx(buf.as_mut_ptr(), &a.coeffs, &QDATA.coeffs);
@@ -200,7 +204,8 @@ pub fn poly_tobytes(r: &mut [u8], a: Poly) {
     r[..KYBER_POLY_BYTES].copy_from_slice(&buf[..]);
 }
 
-pub unsafe fn poly_frommsg(r: &mut Poly, msg: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_frommsg(r: &mut Poly, msg: &[u8]) {
     let shift = _mm256_broadcastsi128_si256(_mm_set_epi32(0, 1, 2, 3));
     let idx = _mm256_broadcastsi128_si256(_mm_set_epi8(
         15, 14, 11, 10, 7, 6, 3, 2, 13, 12, 9, 8, 5, 4, 1, 0,
@@ -243,7 +248,8 @@ pub unsafe fn poly_frommsg(r: &mut Poly, msg: &[u8]) {
     frommsg64(3, _mm256_shuffle_epi32(f, 255));
 }
 
-pub fn poly_tomsg(msg: &mut [u8], a: Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_tomsg(msg: &mut [u8], a: Poly) {
     unsafe {
         let (mut f0, mut f1, mut g0, mut g1);
         let hq: __m256i = _mm256_set1_epi16((KYBER_Q - 1) as i16 / 2);
@@ -268,43 +274,85 @@ pub fn poly_tomsg(msg: &mut [u8], a: Poly) {
     }
 }
 
-pub fn poly_ntt(r: &mut Poly) {
+/// Name:  poly_getnoise_eta1
+///
+/// Description: Sample a polynomial deterministically from a seed and a nonce,
+///  with output polynomial close to centered binomial distribution
+///  with parameter KYBER_ETA1
+///
+/// Arguments:   - poly *r:     output polynomial
+///  - const [u8] seed: input seed (pointing to array of length KYBER_SYM_BYTES bytes)
+///  - u8 nonce:       one-byte input nonce
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_getnoise_eta1(r: &mut Poly, seed: &[u8], nonce: u8) {
+    const LENGTH: usize = KYBER_ETA1 * KYBER_N / 4;
+    let mut buf = NoiseBuf::new();
+    prf(unsafe { &mut buf.coeffs[..LENGTH] }, LENGTH, seed, nonce);
+    poly_cbd_eta1(r, &buf);
+}
+
+/// Name:  poly_getnoise_eta2
+///
+/// Description: Sample a polynomial deterministically from a seed and a nonce,
+///  with output polynomial close to centered binomial distribution
+///  with parameter KYBER_ETA2
+///
+/// Arguments:   - poly *r:     output polynomial
+///  - const [u8] seed: input seed (pointing to array of length KYBER_SYM_BYTES bytes)
+///  - u8 nonce:       one-byte input nonce
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_getnoise_eta2(r: &mut Poly, seed: &[u8], nonce: u8) {
+    const LENGTH: usize = KYBER_ETA2 * KYBER_N / 4;
+    let mut buf = NoiseBuf::new();
+    prf(unsafe { &mut buf.coeffs[..LENGTH] }, LENGTH, seed, nonce);
+    poly_cbd_eta2(r, &buf);
+}
+
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_ntt(r: &mut Poly) {
+    crate::acceleration::count_ntt(crate::Acceleration::Avx2);
     unsafe {
         ntt_avx(&mut r.coeffs, &QDATA.coeffs);
     }
 }
 
-pub fn poly_invntt_tomont(r: &mut Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_invntt_tomont(r: &mut Poly) {
     unsafe {
         invntt_avx(&mut r.coeffs, &QDATA.coeffs);
     }
 }
 
-pub fn poly_nttunpack(r: &mut Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_nttunpack(r: &mut Poly) {
     unsafe {
         nttunpack_avx(&mut r.coeffs, &QDATA.coeffs);
     }
 }
 
-pub fn poly_basemul(r: &mut Poly, a: &Poly, b: &Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_basemul(r: &mut Poly, a: &Poly, b: &Poly) {
     unsafe {
         basemul_avx(&mut r.coeffs, &a.coeffs, &b.coeffs, &QDATA.coeffs);
     }
 }
 
-pub fn poly_tomont(r: &mut Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_tomont(r: &mut Poly) {
     unsafe {
         tomont_avx(&mut r.coeffs, &QDATA.coeffs);
     }
 }
 
-pub fn poly_reduce(r: &mut Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_reduce(r: &mut Poly) {
     unsafe {
         reduce_avx(&mut r.coeffs, &QDATA.coeffs);
     }
 }
 
-pub fn poly_add(r: &mut Poly, b: &Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_add(r: &mut Poly, b: &Poly) {
     let (mut f0, mut f1);
     for i in 0..(KYBER_N / 16) {
         unsafe {
@@ -316,7 +364,8 @@ pub fn poly_add(r: &mut Poly, b: &Poly) {
     }
 }
 
-pub fn poly_sub(r: &mut Poly, a: &Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_sub(r: &mut Poly, a: &Poly) {
     let (mut f0, mut f1);
     for i in 0..(KYBER_N / 16) {
         unsafe {
@@ -1,22 +1,25 @@
-use crate::{consts::*, params::*, poly::*};
+#![allow(clippy::identity_op)]
+use super::{consts::*, poly::*};
+use crate::params::*;
 use core::arch::x86_64::*;
 
 #[derive(Clone)]
-pub struct Polyvec {
-    pub vec: [Poly; KYBER_SECURITY_PARAMETER],
+pub(crate) struct Polyvec {
+    pub(crate) vec: [Poly; KYBER_SECURITY_PARAMETER],
 }
 
 impl Copy for Polyvec {}
 
 impl Polyvec {
-    pub fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Polyvec {
             vec: [Poly::new(); KYBER_SECURITY_PARAMETER],
         }
     }
 }
 
-pub unsafe fn poly_compress10(r: &mut [u8], a: &Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_compress10(r: &mut [u8], a: &Poly) {
     let (mut f0, mut f1, mut f2);
     let (mut t0, mut t1);
 
@@ -61,7 +64,8 @@ pub unsafe fn poly_compress10(r: &mut [u8], a: &Poly) {
     }
 }
 
-pub unsafe fn poly_decompress10(r: &mut Poly, a: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_decompress10(r: &mut Poly, a: &[u8]) {
     let mut f;
     let q = _mm256_set1_epi32(
         ((KYBER_Q as i32) << 16) + 4 * KYBER_Q as i32,
@@ -84,7 +88,8 @@ pub unsafe fn poly_decompress10(r: &mut Poly, a: &[u8]) {
     }
 }
 
-pub unsafe fn poly_compress11(r: &mut [u8], a: &Poly) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_compress11(r: &mut [u8], a: &Poly) {
     let (mut f0, mut f1, mut f2);
     let (mut t0, mut t1);
     let v = _mm256_load_si256(QDATA.vec[_16XV / 16..].as_ptr());
@@ -135,7 +140,8 @@ pub unsafe fn poly_compress11(r: &mut [u8], a: &Poly) {
     }
 }
 
-pub unsafe fn poly_decompress11(r: &mut Poly, a: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn poly_decompress11(r: &mut Poly, a: &[u8]) {
     let mut f;
 
     let q = _mm256_load_si256(QDATA.vec[_16XQ / 16..].as_ptr());
@@ -164,7 +170,8 @@ pub unsafe fn poly_decompress11(r: &mut Poly, a: &[u8]) {
     }
 }
 
-pub unsafe fn polyvec_compress(r: &mut [u8], a: &Polyvec) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn polyvec_compress(r: &mut [u8], a: &Polyvec) {
     if cfg!(feature = "kyber1024") {
         for i in 0..KYBER_SECURITY_PARAMETER {
             poly_compress11(&mut r[352 * i..], &a.vec[i]);
@@ -176,7 +183,8 @@ pub unsafe fn polyvec_compress(r: &mut [u8], a: &Polyvec) {
     }
 }
 
-pub unsafe fn polyvec_decompress(r: &mut Polyvec, a: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn polyvec_decompress(r: &mut Polyvec, a: &[u8]) {
     if cfg!(feature = "kyber1024") {
         for i in 0..KYBER_SECURITY_PARAMETER {
             poly_decompress11(&mut r.vec[i], &a[352 * i..]);
@@ -188,13 +196,15 @@ pub unsafe fn polyvec_decompress(r: &mut Polyvec, a: &[u8]) {
     }
 }
 
-pub fn polyvec_tobytes(r: &mut [u8], a: &Polyvec) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn polyvec_tobytes(r: &mut [u8], a: &Polyvec) {
     for i in 0..KYBER_SECURITY_PARAMETER {
         poly_tobytes(&mut r[i * KYBER_POLY_BYTES..], a.vec[i]);
     }
 }
 
-pub unsafe fn polyvec_frombytes(r: &mut Polyvec, a: &[u8]) {
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn polyvec_frombytes(r: &mut Polyvec, a: &[u8]) {
     for i in 0..KYBER_SECURITY_PARAMETER {
         poly_frombytes(&mut r.vec[i], &a[i * KYBER_POLY_BYTES..]);
     }
@@ -205,7 +215,8 @@ pub unsafe fn polyvec_frombytes(r: &mut Polyvec, a: &[u8]) {
 /// Description: Apply forward NTT to all elements of a vector of polynomials
 ///
 /// Arguments:   - Polyvec r: in/output vector of polynomials
-pub fn polyvec_ntt(r: &mut Polyvec) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn polyvec_ntt(r: &mut Polyvec) {
     for i in 0..KYBER_SECURITY_PARAMETER {
         poly_ntt(&mut r.vec[i]);
     }
@@ -216,7 +227,8 @@ pub fn polyvec_ntt(r: &mut Polyvec) {
 /// Description: Apply inverse NTT to all elements of a vector of polynomials
 ///
 /// Arguments:   - Polyvec r: in/output vector of polynomials
-pub fn polyvec_invntt_tomont(r: &mut Polyvec) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn polyvec_invntt_tomont(r: &mut Polyvec) {
     for i in 0..KYBER_SECURITY_PARAMETER {
         poly_invntt_tomont(&mut r.vec[i]);
     }
@@ -229,7 +241,8 @@ pub fn polyvec_invntt_tomont(r: &mut Polyvec) {
 /// Arguments: - poly *r:  output polynomial
 ///  - const Polyvec a: first input vector of polynomials
 ///  - const Polyvec b: second input vector of polynomials
-pub fn polyvec_basemul_acc_montgomery(
+#[target_feature(enable = "avx2")]
+pub(crate) fn polyvec_basemul_acc_montgomery(
     r: &mut Poly,
     a: &Polyvec,
     b: &Polyvec,
@@ -249,7 +262,8 @@ pub fn polyvec_basemul_acc_montgomery(
 ///  for details of the Barrett reduction see comments in reduce.c
 ///
 /// Arguments:   - poly *r:   input/output polynomial
-pub fn polyvec_reduce(r: &mut Polyvec) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn polyvec_reduce(r: &mut Polyvec) {
     for i in 0..KYBER_SECURITY_PARAMETER {
         poly_reduce(&mut r.vec[i]);
     }
@@ -262,7 +276,8 @@ pub fn polyvec_reduce(r: &mut Polyvec) {
 /// Arguments: - Polyvec r:   output vector of polynomials
 ///  - const Polyvec a: first input vector of polynomials
 ///  - const Polyvec b: second input vector of polynomials
-pub fn polyvec_add(r: &mut Polyvec, b: &Polyvec) {
+#[target_feature(enable = "avx2")]
+pub(crate) fn polyvec_add(r: &mut Polyvec, b: &Polyvec) {
     for i in 0..KYBER_SECURITY_PARAMETER {
         poly_add(&mut r.vec[i], &b.vec[i]);
     }
@@ -1,21 +1,30 @@
-use crate::{consts::*, params::*, symmetric::*};
+#![allow(clippy::identity_op)]
+use super::consts::*;
+use crate::{params::*, symmetric::*};
 use core::arch::x86_64::*;
 
-pub const REJ_UNIFORM_AVX_NBLOCKS: usize =
+pub(crate) const REJ_UNIFORM_AVX_NBLOCKS: usize =
     (12 * KYBER_N / 8 * (1 << 12) / KYBER_Q + XOF_BLOCKBYTES)
         / XOF_BLOCKBYTES;
-const REJ_UNIFORM_AVX_BUFLEN: usize =
+pub(crate) const REJ_UNIFORM_AVX_BUFLEN: usize =
     REJ_UNIFORM_AVX_NBLOCKS * XOF_BLOCKBYTES;
 
-pub unsafe fn _mm256_cmpge_epu16(a: __m256i, b: __m256i) -> __m256i {
-    _mm256_cmpeq_epi16(_mm256_max_epu16(a, b), a)
-}
-
-pub unsafe fn _mm_cmpge_epu16(a: __m128i, b: __m128i) -> __m128i {
-    _mm_cmpeq_epi16(_mm_max_epu16(a, b), a)
-}
-
-pub unsafe fn rej_uniform_avx(r: &mut [i16], buf: &[u8]) -> usize {
+/// Name:  rej_uniform_avx
+///
+/// Description: Run rejection sampling on uniform random bytes to generate
+///  uniform random integers mod q, accepting the same values in the same
+///  order as the scalar rej_uniform
+///
+/// Arguments: - i16 *r:  output buffer of KYBER_N integers
+///  - const [u8] buf:  input buffer (assumed to be uniform random bytes)
+///
+/// Returns number of sampled 16-bit integers (at most KYBER_N), having
+/// consumed the whole 3-byte groups of buf if fewer
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+pub(crate) unsafe fn rej_uniform_avx(
+    r: &mut [i16; KYBER_N],
+    buf: &[u8; REJ_UNIFORM_AVX_BUFLEN],
+) -> usize {
     let mut ctr = 0;
     let mut pos = 0;
     let mut good: usize;
@@ -30,7 +39,7 @@ pub unsafe fn rej_uniform_avx(r: &mut [i16], buf: &[u8]) -> usize {
         15, 14, 14, 13, 12, 11, 11, 10, 9, 8, 8, 7, 6, 5, 5, 4, 11, 10,
         10, 9, 8, 7, 7, 6, 5, 4, 4, 3, 2, 1, 1, 0,
     );
-    while ctr <= KYBER_N - 32 && pos <= REJ_UNIFORM_AVX_BUFLEN - 48 {
+    while ctr <= KYBER_N - 32 && pos <= REJ_UNIFORM_AVX_BUFLEN - 56 {
         f0 = _mm256_loadu_si256(buf[pos..].as_ptr() as *const __m256i);
         f1 = _mm256_loadu_si256(
             buf[pos + 24..].as_ptr() as *const __m256i
@@ -101,7 +110,7 @@ pub unsafe fn rej_uniform_avx(r: &mut [i16], buf: &[u8]) -> usize {
         ctr += _popcnt32(((good >> 24) & 0xFF) as i32) as usize;
     }
 
-    while ctr <= KYBER_N - 8 && pos <= REJ_UNIFORM_AVX_BUFLEN - 12 {
+    while ctr <= KYBER_N - 8 && pos <= REJ_UNIFORM_AVX_BUFLEN - 16 {
         f = _mm_loadu_si128(buf[pos..].as_ptr() as *const __m128i);
         f = _mm_shuffle_epi8(f, _mm256_castsi256_si128(idx8));
         t = _mm_srli_epi16(f, 4);
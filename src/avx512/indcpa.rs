@@ -0,0 +1,104 @@
+use super::poly::*;
+use crate::avx2::indcpa::sample_uniform;
+use crate::avx2::poly::{
+    poly_add, poly_getnoise_eta1, poly_getnoise_eta2, poly_sub,
+    poly_tomont, Poly,
+};
+use crate::avx2::polyvec::{polyvec_add, Polyvec};
+use crate::avx2::{poly as avx2_poly, polyvec as avx2_polyvec};
+use crate::params::*;
+
+crate::simd::indcpa_layer!(
+    #[target_feature(enable = "avx2,bmi2,popcnt,avx512f,avx512bw")]
+);
+
+/// Name:  pack_ciphertext
+///
+/// Description: Reduce b and v to the 0..=q range the AVX2 compression
+///  expects, then serialize the ciphertext as concatenation of the
+///  compressed and serialized vector of polynomials b
+///  and the compressed and serialized polynomial v
+///
+/// Arguments:   [u8] r:  the output serialized ciphertext
+///  - Polyvec b: the input vector of polynomials b
+///  - poly *v:   the input polynomial v
+#[target_feature(enable = "avx2,avx512f,avx512bw")]
+fn pack_ciphertext(r: &mut [u8], b: &mut Polyvec, v: &mut Poly) {
+    avx2_polyvec::polyvec_reduce(b);
+    avx2_poly::poly_reduce(v);
+    // SAFETY: a ciphertext holds both compressed parts.
+    unsafe {
+        avx2_polyvec::polyvec_compress(r, b);
+        avx2_poly::poly_compress(
+            &mut r[KYBER_POLYVEC_COMPRESSED_BYTES..],
+            *v,
+        );
+    }
+}
+
+/// Name:  unpack_ciphertext
+///
+/// Description: De-serialize and decompress ciphertext from a byte array;
+///  approximate inverse of pack_ciphertext
+///
+/// Arguments:   - Polyvec b:   output vector of polynomials b
+///  - poly *v:  output polynomial v
+///  - const [u8] c:   input serialized ciphertext
+#[target_feature(enable = "avx2,avx512f,avx512bw")]
+fn unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8]) {
+    // SAFETY: as in `pack_ciphertext`.
+    unsafe {
+        avx2_polyvec::polyvec_decompress(b, c);
+        avx2_poly::poly_decompress(
+            v,
+            &c[KYBER_POLYVEC_COMPRESSED_BYTES..],
+        );
+    }
+}
+
+/// Name:  gen_matrix
+///
+/// Description: Deterministically generate matrix A (or the transpose of A)
+///  from a seed, in the standard coefficient order of the reference
+///
+/// Arguments:   - Polyvec a: ouptput matrix A
+///  - const [u8] seed: input seed
+///  - bool transposed: boolean deciding whether A or A^T is generated
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+fn gen_matrix(a: &mut [Polyvec], seed: &[u8], transposed: bool) {
+    for (i, row) in a.iter_mut().enumerate() {
+        for (j, entry) in row.vec.iter_mut().enumerate() {
+            if transposed {
+                sample_uniform(entry, seed, i as u8, j as u8);
+            } else {
+                sample_uniform(entry, seed, j as u8, i as u8);
+            }
+        }
+    }
+}
+
+/// Name:  poly_frommsg
+///
+/// Description: The AVX2 poly_frommsg, for the KYBER_SYM_BYTES messages
+///  of indcpa_enc
+///
+/// Arguments:   - poly *r:  output polynomial
+///  - const [u8] msg: input message
+#[target_feature(enable = "avx2")]
+fn poly_frommsg(r: &mut Poly, msg: &[u8]) {
+    // SAFETY: the message is KYBER_SYM_BYTES long.
+    unsafe { avx2_poly::poly_frommsg(r, msg) }
+}
+
+/// Name:  decode_message
+///
+/// Description: Reduce mp to the 0..=q range the AVX2 message decoding
+///  expects and convert it to a message
+///
+/// Arguments:   - [u8] m: output message (of length KYBER_SYM_BYTES)
+///  - poly *mp: input/output polynomial
+#[target_feature(enable = "avx2")]
+fn decode_message(m: &mut [u8], mp: &mut Poly) {
+    avx2_poly::poly_reduce(mp);
+    avx2_poly::poly_tomsg(m, *mp);
+}
@@ -0,0 +1,132 @@
+//! AVX-512 implementation of the IND-CPA layer, selected at run time.
+//!
+//! The NTT, inverse NTT, base multiplication and the 12-bit
+//! (de)serialization of polynomials work on 512-bit vectors of 32
+//! coefficients; everything else is shared with the AVX2 backend. Only
+//! AVX-512F and AVX-512BW are used, so no IFMA or VBMI support is needed.
+//! Unlike the AVX2 kernels, these keep the coefficients in the standard
+//! order of the reference implementation, and produce the same keys,
+//! ciphertexts and messages bit for bit.
+//!
+//! [`supported`] checks once whether the CPU has AVX-512F and AVX-512BW,
+//! with the operating system saving the 512-bit registers, as well as
+//! what the AVX2 backend needs; without them the `acceleration` module
+//! selects the AVX2 backend, or the reference implementation in turn.
+//!
+//! Frequency throttling: on Skylake-SP and Cascade Lake servers, heavy
+//! 512-bit integer multiplication lowers the clock of the core for some
+//! milliseconds after the last such instruction, which also slows down
+//! unrelated code running on that core. Ice Lake and later cores
+//! throttle far less. The feature is therefore opt-in, and with the
+//! `force-backend` feature `acceleration::force_backend` switches a
+//! process back to AVX2 at run time.
+
+use crate::avx2;
+
+pub(crate) mod indcpa;
+mod poly;
+
+// The macro expansion trips lints this crate denies.
+#[allow(deprecated_in_future, unexpected_cfgs)]
+mod detect {
+    cpufeatures::new!(cpuid, "avx512f", "avx512bw");
+
+    pub(super) fn supported() -> bool {
+        cpuid::get()
+    }
+}
+
+/// Whether the CPU can run the AVX-512 backend, which shares kernels
+/// with the AVX2 one and so needs its features too.
+pub(crate) fn supported() -> bool {
+    detect::supported() && avx2::supported()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::differential;
+    use crate::{reference, reference::poly::Poly};
+
+    // The AVX2 layout of a reference polynomial; both hold the
+    // coefficients in the standard order.
+    fn to_avx2(r: &Poly) -> avx2::poly::Poly {
+        let mut a = avx2::poly::Poly::new();
+        a.coeffs = r.coeffs;
+        a
+    }
+
+    fn to_avx2_vec(
+        r: &reference::polyvec::Polyvec,
+    ) -> avx2::polyvec::Polyvec {
+        let mut a = avx2::polyvec::Polyvec::new();
+        for (a, r) in a.vec.iter_mut().zip(r.vec.iter()) {
+            *a = to_avx2(r);
+        }
+        a
+    }
+
+    // Runs an AVX-512 kernel on the AVX2 layout of `r`.
+    fn on_avx2(r: &mut Poly, f: impl FnOnce(&mut avx2::poly::Poly)) {
+        let mut a = to_avx2(r);
+        f(&mut a);
+        r.coeffs = unsafe { a.coeffs };
+    }
+
+    #[test]
+    fn ntt_matches_reference() {
+        if !supported() {
+            return;
+        }
+        differential::ntt(
+            |r| on_avx2(r, |a| unsafe { poly::poly_ntt(a) }),
+            |r| on_avx2(r, |a| unsafe { poly::poly_invntt_tomont(a) }),
+        );
+    }
+
+    #[test]
+    fn basemul_matches_reference() {
+        if !supported() {
+            return;
+        }
+        differential::basemul(|r, a, b| {
+            let (a, b) = (to_avx2_vec(a), to_avx2_vec(b));
+            on_avx2(r, |r| unsafe {
+                poly::polyvec_basemul_acc_montgomery(r, &a, &b)
+            });
+        });
+    }
+
+    #[test]
+    fn serialization_matches_reference() {
+        if !supported() {
+            return;
+        }
+        differential::serialization(
+            |bytes, r| unsafe {
+                poly::poly_tobytes(bytes, &to_avx2(r))
+            },
+            |r, bytes| {
+                on_avx2(r, |a| unsafe {
+                    poly::poly_frombytes(a, bytes)
+                })
+            },
+        );
+    }
+
+    #[test]
+    fn indcpa_matches_reference() {
+        if !supported() {
+            return;
+        }
+        differential::indcpa(
+            |pk, sk, seed, rng| unsafe {
+                indcpa::indcpa_keypair(pk, sk, seed, rng)
+            },
+            |c, m, pk, coins| unsafe {
+                indcpa::indcpa_enc(c, m, pk, coins)
+            },
+            |m, c, sk| unsafe { indcpa::indcpa_dec(m, c, sk) },
+        );
+    }
+}
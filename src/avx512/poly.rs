@@ -0,0 +1,465 @@
+use crate::acceleration::{count_ntt, Acceleration};
+use crate::avx2::{poly::Poly, polyvec::Polyvec};
+use crate::params::*;
+use crate::reference::ntt::ZETAS;
+use core::arch::x86_64::*;
+
+const Q: i16 = KYBER_Q as i16;
+const QINV: i16 = -3327; // q^(-1) mod 2^16
+const V: i16 = 20159; // ((1 << 26) + q/2) / q
+const F: i16 = 1441; // mont^2/128
+
+// Permutations and zetas of an NTT layer whose butterflies lie within a
+// window of two vectors, i.e. 64 coefficients, for len 16, 8, 4 and 2.
+struct Layer {
+    // Lanes of the window holding the first and the second operand of
+    // each butterfly, as indices for _mm512_permutex2var_epi16
+    lo: [i16; 32],
+    hi: [i16; 32],
+    // The inverse permutation, back to the two vectors of the window
+    out0: [i16; 32],
+    out1: [i16; 32],
+    // Zeta of each butterfly for the four windows, forward and inverse
+    zetas: [[i16; 32]; 4],
+    inv_zetas: [[i16; 32]; 4],
+}
+
+const fn layer(len: usize) -> Layer {
+    let mut l = Layer {
+        lo: [0; 32],
+        hi: [0; 32],
+        out0: [0; 32],
+        out1: [0; 32],
+        zetas: [[0; 32]; 4],
+        inv_zetas: [[0; 32]; 4],
+    };
+    let mut m = 0;
+    while m < 32 {
+        let first = (m / len) * 2 * len + m % len;
+        l.lo[m] = first as i16;
+        l.hi[m] = (first + len) as i16;
+        let mut w = 0;
+        while w < 4 {
+            let block = w * (32 / len) + m / len;
+            l.zetas[w][m] = ZETAS[128 / len + block];
+            l.inv_zetas[w][m] = ZETAS[256 / len - 1 - block];
+            w += 1;
+        }
+        m += 1;
+    }
+    let mut j = 0;
+    while j < 64 {
+        let m = (j / (2 * len)) * len + j % len;
+        let idx = if j % (2 * len) < len { m } else { 32 + m } as i16;
+        if j < 32 {
+            l.out0[j] = idx;
+        } else {
+            l.out1[j - 32] = idx;
+        }
+        j += 1;
+    }
+    l
+}
+
+static LAYERS: [Layer; 4] = [layer(16), layer(8), layer(4), layer(2)];
+
+// The zeta of each pair of coefficients in the NTT domain, in the odd
+// lane of the pair: the basemul of pairs 2i and 2i + 1 reduces modulo
+// X^2 - zeta and X^2 + zeta.
+static BASEMUL_ZETAS: [[i16; 32]; KYBER_N / 32] = {
+    let mut zetas = [[0i16; 32]; KYBER_N / 32];
+    let mut p = 0;
+    while p < KYBER_N / 2 {
+        let zeta = ZETAS[64 + p / 2];
+        zetas[p / 16][2 * (p % 16) + 1] =
+            if p % 2 == 0 { zeta } else { -zeta };
+        p += 1;
+    }
+    zetas
+};
+
+// Moves the three bytes of every 32-bit lane to the bottom of its
+// 128-bit lane, and back.
+static PACK12: [i8; 64] = {
+    const LANE: [i8; 16] =
+        [0, 1, 2, 4, 5, 6, 8, 9, 10, 12, 13, 14, -1, -1, -1, -1];
+    let mut idx = [0i8; 64];
+    let mut i = 0;
+    while i < 64 {
+        idx[i] = LANE[i % 16];
+        i += 1;
+    }
+    idx
+};
+static UNPACK12: [i8; 64] = {
+    const LANE: [i8; 16] =
+        [0, 1, 2, -1, 3, 4, 5, -1, 6, 7, 8, -1, 9, 10, 11, -1];
+    let mut idx = [0i8; 64];
+    let mut i = 0;
+    while i < 64 {
+        idx[i] = LANE[i % 16];
+        i += 1;
+    }
+    idx
+};
+
+// 48 of the 64 bytes of a vector: 32 coefficients of 12 bits.
+const BYTES48: __mmask64 = (1 << 48) - 1;
+
+#[target_feature(enable = "avx512f,avx512bw")]
+fn load<T>(a: &[T; 32]) -> __m512i {
+    // SAFETY: a is 64 bytes long.
+    unsafe { _mm512_loadu_si512(a.as_ptr().cast()) }
+}
+
+#[target_feature(enable = "avx512f,avx512bw")]
+fn load64(a: &[i8; 64]) -> __m512i {
+    // SAFETY: a is 64 bytes long.
+    unsafe { _mm512_loadu_si512(a.as_ptr().cast()) }
+}
+
+// 32 coefficients of a polynomial, one vector, on a 64-byte boundary.
+#[repr(C, align(64))]
+struct Chunk([i16; 32]);
+
+// Polynomials are 64-byte aligned, which `chunks` relies on.
+const _: () = assert!(align_of::<Poly>() >= 64);
+
+#[target_feature(enable = "avx512f,avx512bw")]
+fn load_chunk(a: &Chunk) -> __m512i {
+    // SAFETY: a is 64 bytes long and 64-byte aligned.
+    unsafe { _mm512_load_si512(a.0.as_ptr().cast()) }
+}
+
+#[target_feature(enable = "avx512f,avx512bw")]
+fn store(r: &mut Chunk, a: __m512i) {
+    // SAFETY: r is 64 bytes long and 64-byte aligned.
+    unsafe { _mm512_store_si512(r.0.as_mut_ptr().cast(), a) }
+}
+
+// The 32-coefficient chunks of a polynomial.
+fn chunks(r: &Poly) -> &[Chunk; KYBER_N / 32] {
+    // SAFETY: the coefficients are KYBER_N contiguous i16 values
+    // starting on a 64-byte boundary, and any bit pattern is a valid
+    // coefficient.
+    unsafe { &*r.coeffs.as_ptr().cast() }
+}
+
+fn chunks_mut(r: &mut Poly) -> &mut [Chunk; KYBER_N / 32] {
+    // SAFETY: as in `chunks`.
+    unsafe { &mut *r.coeffs.as_mut_ptr().cast() }
+}
+
+// montgomery_reduce(a * b) in every lane, exactly as the reference
+// computes it.
+#[target_feature(enable = "avx512f,avx512bw")]
+fn fqmul(a: __m512i, b: __m512i) -> __m512i {
+    let lo = _mm512_mullo_epi16(a, b);
+    let hi = _mm512_mulhi_epi16(a, b);
+    let u = _mm512_mullo_epi16(lo, _mm512_set1_epi16(QINV));
+    _mm512_sub_epi16(hi, _mm512_mulhi_epi16(u, _mm512_set1_epi16(Q)))
+}
+
+// montgomery_reduce of every 32-bit lane, in the low half of the lane.
+#[target_feature(enable = "avx512f,avx512bw")]
+fn montgomery_reduce32(a: __m512i) -> __m512i {
+    let u = _mm512_mullo_epi32(a, _mm512_set1_epi32(QINV as i32));
+    let u = _mm512_srai_epi32::<16>(_mm512_slli_epi32::<16>(u));
+    let t = _mm512_sub_epi32(
+        a,
+        _mm512_mullo_epi32(u, _mm512_set1_epi32(Q as i32)),
+    );
+    _mm512_srai_epi32::<16>(t)
+}
+
+// barrett_reduce in every lane: round(a * v / 2^26) is computed as
+// (floor(a * v / 2^16) + 2^9) >> 10, which is the same number.
+#[target_feature(enable = "avx512f,avx512bw")]
+fn barrett_reduce(a: __m512i) -> __m512i {
+    let t = _mm512_mulhi_epi16(a, _mm512_set1_epi16(V));
+    let t = _mm512_add_epi16(t, _mm512_set1_epi16(1 << 9));
+    let t = _mm512_srai_epi16::<10>(t);
+    _mm512_sub_epi16(a, _mm512_mullo_epi16(t, _mm512_set1_epi16(Q)))
+}
+
+/// Name:  poly_ntt
+///
+/// Description: Computes the NTT of a polynomial in place like the
+///  reference poly_ntt, with identical output; inputs in normal order,
+///  output in bitreversed order
+///
+/// Arguments:   - Poly r: in/output polynomial
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn poly_ntt(r: &mut Poly) {
+    count_ntt(Acceleration::Avx512);
+    let mut v = [_mm512_setzero_si512(); KYBER_N / 32];
+    for (v, c) in v.iter_mut().zip(chunks(r)) {
+        *v = load_chunk(c);
+    }
+
+    // len 128, 64 and 32: butterflies between whole vectors
+    let mut k = 1;
+    for dist in [4, 2, 1] {
+        for start in (0..v.len()).step_by(2 * dist) {
+            let zeta = _mm512_set1_epi16(ZETAS[k]);
+            k += 1;
+            for j in start..start + dist {
+                let t = fqmul(zeta, v[j + dist]);
+                v[j + dist] = _mm512_sub_epi16(v[j], t);
+                v[j] = _mm512_add_epi16(v[j], t);
+            }
+        }
+    }
+
+    // len 16 down to 2: butterflies within windows of two vectors
+    for layer in LAYERS.iter() {
+        let (lo, hi) = (load(&layer.lo), load(&layer.hi));
+        let (out0, out1) = (load(&layer.out0), load(&layer.out1));
+        for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+            let a = _mm512_permutex2var_epi16(pair[0], lo, pair[1]);
+            let b = _mm512_permutex2var_epi16(pair[0], hi, pair[1]);
+            let t = fqmul(load(&layer.zetas[w]), b);
+            let (a, b) =
+                (_mm512_add_epi16(a, t), _mm512_sub_epi16(a, t));
+            pair[0] = _mm512_permutex2var_epi16(a, out0, b);
+            pair[1] = _mm512_permutex2var_epi16(a, out1, b);
+        }
+    }
+
+    for (c, v) in chunks_mut(r).iter_mut().zip(v) {
+        store(c, barrett_reduce(v));
+    }
+}
+
+/// Name:  poly_invntt_tomont
+///
+/// Description: Computes the inverse NTT of a polynomial in place and
+///  multiplies by the Montgomery factor like the reference
+///  poly_invntt_tomont, with identical output; inputs in bitreversed
+///  order, output in normal order
+///
+/// Arguments:   - Poly r: in/output polynomial
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn poly_invntt_tomont(r: &mut Poly) {
+    let mut v = [_mm512_setzero_si512(); KYBER_N / 32];
+    for (v, c) in v.iter_mut().zip(chunks(r)) {
+        *v = load_chunk(c);
+    }
+
+    // len 2 up to 16: butterflies within windows of two vectors
+    for layer in LAYERS.iter().rev() {
+        let (lo, hi) = (load(&layer.lo), load(&layer.hi));
+        let (out0, out1) = (load(&layer.out0), load(&layer.out1));
+        for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+            let a = _mm512_permutex2var_epi16(pair[0], lo, pair[1]);
+            let b = _mm512_permutex2var_epi16(pair[0], hi, pair[1]);
+            let t = _mm512_sub_epi16(b, a);
+            let a = barrett_reduce(_mm512_add_epi16(a, b));
+            let b = fqmul(load(&layer.inv_zetas[w]), t);
+            pair[0] = _mm512_permutex2var_epi16(a, out0, b);
+            pair[1] = _mm512_permutex2var_epi16(a, out1, b);
+        }
+    }
+
+    // len 32, 64 and 128: butterflies between whole vectors
+    for dist in [1, 2, 4] {
+        let first = 256 / (32 * dist) - 1;
+        for (g, start) in (0..v.len()).step_by(2 * dist).enumerate() {
+            let zeta = _mm512_set1_epi16(ZETAS[first - g]);
+            for j in start..start + dist {
+                let t = _mm512_sub_epi16(v[j + dist], v[j]);
+                v[j] =
+                    barrett_reduce(_mm512_add_epi16(v[j], v[j + dist]));
+                v[j + dist] = fqmul(zeta, t);
+            }
+        }
+    }
+
+    let f = _mm512_set1_epi16(F);
+    for (c, v) in chunks_mut(r).iter_mut().zip(v) {
+        store(c, fqmul(v, f));
+    }
+}
+
+/// Name:  polyvec_basemul_acc_montgomery
+///
+/// Description: Pointwise multiply elements of a and b and accumulate
+///  into r, with the lazy i32 accumulate of the reference and identical
+///  output: _mm512_madd_epi16 forms both products of every output
+///  coefficient of a pair and their sum in one step
+///
+/// Arguments: - poly *r:  output polynomial
+///  - const Polyvec a: first input vector of polynomials
+///  - const Polyvec b: second input vector of polynomials
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn polyvec_basemul_acc_montgomery(
+    r: &mut Poly,
+    a: &Polyvec,
+    b: &Polyvec,
+) {
+    let mut even = [_mm512_setzero_si512(); KYBER_N / 32];
+    let mut odd = [_mm512_setzero_si512(); KYBER_N / 32];
+    for (a, b) in a.vec.iter().zip(b.vec.iter()) {
+        for (i, (a, b)) in chunks(a).iter().zip(chunks(b)).enumerate() {
+            let (a, b) = (load_chunk(a), load_chunk(b));
+            // (b0, b1 * zeta) and (b1, b0) in each pair of lanes
+            let bz = fqmul(b, load(&BASEMUL_ZETAS[i]));
+            let b_even = _mm512_mask_blend_epi16(0xAAAA_AAAA, b, bz);
+            let b_odd = _mm512_rol_epi32::<16>(b);
+            even[i] =
+                _mm512_add_epi32(even[i], _mm512_madd_epi16(a, b_even));
+            odd[i] =
+                _mm512_add_epi32(odd[i], _mm512_madd_epi16(a, b_odd));
+        }
+    }
+    let low = _mm512_set1_epi32(0xFFFF);
+    for (c, (even, odd)) in
+        chunks_mut(r).iter_mut().zip(even.iter().zip(odd))
+    {
+        let even = _mm512_and_si512(montgomery_reduce32(*even), low);
+        let odd = _mm512_slli_epi32::<16>(montgomery_reduce32(odd));
+        store(c, barrett_reduce(_mm512_or_si512(even, odd)));
+    }
+}
+
+/// Name:  poly_reduce
+///
+/// Description: Applies the reference Barrett reduction to all
+///  coefficients of a polynomial, giving the centered representatives
+///
+/// Arguments:   - poly *r:   input/output polynomial
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn poly_reduce(r: &mut Poly) {
+    for c in chunks_mut(r).iter_mut() {
+        store(c, barrett_reduce(load_chunk(c)));
+    }
+}
+
+/// Name:  poly_tobytes
+///
+/// Description: Serialization of a polynomial like the reference
+///  poly_tobytes; coefficients must be in {-q+1,...,q-1}. Panics if r is
+///  shorter than KYBER_POLY_BYTES, before anything is written
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLY_BYTES bytes)
+///  - const poly *a:  input polynomial
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn poly_tobytes(r: &mut [u8], a: &Poly) {
+    let r = &mut r[..KYBER_POLY_BYTES];
+    let shuffle = load64(&PACK12);
+    let dwords = _mm512_setr_epi32(
+        0, 1, 2, 4, 5, 6, 8, 9, 10, 12, 13, 14, 0, 0, 0, 0,
+    );
+    for (out, c) in r.chunks_exact_mut(48).zip(chunks(a)) {
+        let t = load_chunk(c);
+        // map to positive standard representatives
+        let neg = _mm512_srai_epi16::<15>(t);
+        let t = _mm512_add_epi16(
+            t,
+            _mm512_and_si512(neg, _mm512_set1_epi16(Q)),
+        );
+        // t0 + 2^12 t1 in every 32-bit lane, then three bytes per lane
+        let t = _mm512_madd_epi16(t, _mm512_set1_epi32(0x1000_0001));
+        let t = _mm512_shuffle_epi8(t, shuffle);
+        let t = _mm512_permutexvar_epi32(dwords, t);
+        // SAFETY: out is 48 bytes long and only 48 bytes are written.
+        unsafe {
+            _mm512_mask_storeu_epi8(out.as_mut_ptr().cast(), BYTES48, t)
+        };
+    }
+}
+
+/// Name:  poly_frombytes
+///
+/// Description: De-serialization of a polynomial like the reference
+///  poly_frombytes. Panics if a is shorter than KYBER_POLY_BYTES
+///
+/// Arguments:   - poly *r:  output polynomial
+///  - const [u8] a: input byte array (of KYBER_POLY_BYTES bytes)
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn poly_frombytes(r: &mut Poly, a: &[u8]) {
+    let a = &a[..KYBER_POLY_BYTES];
+    let shuffle = load64(&UNPACK12);
+    let dwords = _mm512_setr_epi32(
+        0, 1, 2, 0, 3, 4, 5, 0, 6, 7, 8, 0, 9, 10, 11, 0,
+    );
+    let mask = _mm512_set1_epi32(0xFFF);
+    for (c, bytes) in chunks_mut(r).iter_mut().zip(a.chunks_exact(48)) {
+        // SAFETY: bytes is 48 bytes long and only 48 bytes are read.
+        let t = unsafe {
+            _mm512_maskz_loadu_epi8(BYTES48, bytes.as_ptr().cast())
+        };
+        let t = _mm512_permutexvar_epi32(dwords, t);
+        let t = _mm512_shuffle_epi8(t, shuffle);
+        let t0 = _mm512_and_si512(t, mask);
+        let t1 = _mm512_and_si512(
+            _mm512_slli_epi32::<4>(t),
+            _mm512_slli_epi32::<16>(mask),
+        );
+        store(c, _mm512_or_si512(t0, t1));
+    }
+}
+
+/// Name:  polyvec_ntt
+///
+/// Description: Apply forward NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn polyvec_ntt(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_ntt(poly);
+    }
+}
+
+/// Name:  polyvec_invntt_tomont
+///
+/// Description: Apply inverse NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn polyvec_invntt_tomont(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_invntt_tomont(poly);
+    }
+}
+
+/// Name:  polyvec_reduce
+///
+/// Description: Applies the reference Barrett reduction to each
+///  coefficient of each element of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: input/output vector of polynomials
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn polyvec_reduce(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_reduce(poly);
+    }
+}
+
+/// Name:  polyvec_tobytes
+///
+/// Description: Serialize vector of polynomials
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLYVEC_BYTES)
+///  - const Polyvec a: input vector of polynomials
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn polyvec_tobytes(r: &mut [u8], a: &Polyvec) {
+    for (r, poly) in r.chunks_mut(KYBER_POLY_BYTES).zip(a.vec.iter()) {
+        poly_tobytes(r, poly);
+    }
+}
+
+/// Name:  polyvec_frombytes
+///
+/// Description: De-serialize vector of polynomials;
+///  inverse of polyvec_tobytes
+///
+/// Arguments:   - Polyvec r: output vector of polynomials
+///  - const [u8] a: input byte array (of length KYBER_POLYVEC_BYTES)
+#[target_feature(enable = "avx512f,avx512bw")]
+pub(crate) fn polyvec_frombytes(r: &mut Polyvec, a: &[u8]) {
+    for (poly, a) in r.vec.iter_mut().zip(a.chunks(KYBER_POLY_BYTES)) {
+        poly_frombytes(poly, a);
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Key generation, encapsulation and decapsulation over many items.
+//!
+//! Each item runs the same code as the single-item API on its own stack,
+//! so items share no scratch memory. With the `parallel` feature the
+//! items are spread over the global rayon pool; without it they run one
+//! after the other. The output is in input order either way, and the
+//! two produce the same bytes.
+//!
+//! An RNG cannot be shared between threads, so the randomized functions
+//! draw one 32-byte base seed from the caller's RNG and hand it to the
+//! `_from_seed` variant. Item `i` then gets its randomness from SHAKE256
+//! over a label, the base seed and `i` as a little-endian `u64`:
+//!
+//! | Function | Label | Output |
+//! |----------|-------|--------|
+//! | [`keypair_batch_from_seed`] | `kyberlib batch keypair v1` | 64-byte seed for [`derive`] |
+//! | [`encapsulate_batch_from_seed`] | `kyberlib batch encapsulate v1` | 32-byte encapsulation randomness |
+//!
+//! [`item_keypair_seed`] exposes the first, so a single key of a batch
+//! can be rebuilt from the base seed and its index alone. The base seed
+//! must be secret and used for one batch only, like any key generation
+//! seed.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::batch::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let peers = [keys.public; 3];
+//!
+//! let encapsulated = encapsulate_batch(&peers, &mut rng)?;
+//! let mut cts = Vec::new();
+//! for item in &encapsulated {
+//!     cts.push(item.as_ref().unwrap().0);
+//! }
+//! let secrets = decapsulate_batch(&cts, &keys.secret)?;
+//! for (item, ss) in encapsulated.iter().zip(&secrets) {
+//!     assert_eq!(&item.as_ref().unwrap().1, ss);
+//! }
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::{decapsulate, derive, DummyRng, Keypair},
+    error::KyberLibError,
+    fips202::{
+        shake256_absorb, shake256_finalize, shake256_init,
+        shake256_squeeze,
+    },
+    kem::{check_public_key, encrypt_message},
+    kex::{Encapsulated, PublicKey, SharedSecret},
+    params::*,
+    rng::randombytes,
+    symmetric::KeccakState,
+    CryptoRng, RngCore,
+};
+use alloc::vec::Vec;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size in bytes of the base seed of a batch.
+pub const BATCH_SEED_BYTES: usize = 32;
+
+const KEYPAIR_LABEL: &[u8] = b"kyberlib batch keypair v1";
+const ENCAPSULATE_LABEL: &[u8] = b"kyberlib batch encapsulate v1";
+
+// SHAKE256(label || seed || index) into `out`. The label and the seed
+// have fixed lengths per call site, so the encoding is injective.
+fn item_randomness(
+    out: &mut [u8],
+    label: &[u8],
+    seed: &[u8; BATCH_SEED_BYTES],
+    index: usize,
+) {
+    let mut state = KeccakState::new();
+    shake256_init(&mut state);
+    shake256_absorb(&mut state, label);
+    shake256_absorb(&mut state, seed);
+    shake256_absorb(&mut state, &(index as u64).to_le_bytes());
+    shake256_finalize(&mut state);
+    shake256_squeeze(out, out.len(), &mut state);
+    #[cfg(feature = "zeroize")]
+    state.s.zeroize();
+}
+
+// Runs `f` on each index in 0..n, in parallel with the `parallel`
+// feature, and returns the results in index order.
+#[cfg(feature = "parallel")]
+fn map_items<T, F>(n: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync + Send,
+{
+    use rayon::prelude::*;
+    (0..n).into_par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn map_items<T, F>(n: usize, f: F) -> Vec<T>
+where
+    F: Fn(usize) -> T,
+{
+    map_items_serial(n, f)
+}
+
+#[cfg(any(test, not(feature = "parallel")))]
+fn map_items_serial<T, F>(n: usize, f: F) -> Vec<T>
+where
+    F: Fn(usize) -> T,
+{
+    (0..n).map(f).collect()
+}
+
+/// Returns the [`derive`] seed of key `index` of
+/// [`keypair_batch_from_seed`] with base seed `seed`.
+pub fn item_keypair_seed(
+    seed: &[u8; BATCH_SEED_BYTES],
+    index: usize,
+) -> [u8; KYBER_COMPACT_SECRET_KEY_BYTES] {
+    let mut out = [0u8; KYBER_COMPACT_SECRET_KEY_BYTES];
+    item_randomness(&mut out, KEYPAIR_LABEL, seed, index);
+    out
+}
+
+fn keypair_item(
+    seed: &[u8; BATCH_SEED_BYTES],
+    index: usize,
+) -> Result<Keypair, KyberLibError> {
+    #[allow(unused_mut)]
+    let mut item_seed = item_keypair_seed(seed, index);
+    let keys = derive(&item_seed);
+    #[cfg(feature = "zeroize")]
+    item_seed.zeroize();
+    keys
+}
+
+fn encapsulate_item(
+    pk: &PublicKey,
+    seed: &[u8; BATCH_SEED_BYTES],
+    index: usize,
+) -> Encapsulated {
+    check_public_key(pk)?;
+    let mut coins = [0u8; KYBER_SYM_BYTES];
+    item_randomness(&mut coins, ENCAPSULATE_LABEL, seed, index);
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let result = encrypt_message(
+        &mut ct,
+        &mut ss,
+        pk,
+        &mut DummyRng {},
+        Some(&coins),
+    );
+    #[cfg(feature = "zeroize")]
+    coins.zeroize();
+    result.map(|()| (ct, ss))
+}
+
+fn base_seed<R>(
+    rng: &mut R,
+) -> Result<[u8; BATCH_SEED_BYTES], KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    let mut seed = [0u8; BATCH_SEED_BYTES];
+    randombytes(&mut seed, BATCH_SEED_BYTES, rng)?;
+    Ok(seed)
+}
+
+/// Generates `n` keypairs from one base seed drawn from `rng`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+#[must_use = "dropping the keypairs discards the only copies of the secret keys"]
+pub fn keypair_batch<R>(
+    n: usize,
+    rng: &mut R,
+) -> Result<Vec<Keypair>, KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    #[allow(unused_mut)]
+    let mut seed = base_seed(rng)?;
+    let keys = keypair_batch_from_seed(&seed, n);
+    #[cfg(feature = "zeroize")]
+    seed.zeroize();
+    keys
+}
+
+/// Deterministically generates `n` keypairs from `seed`, key `i` being
+/// [`derive`] of [`item_keypair_seed`]`(seed, i)`.
+///
+/// # Errors
+///
+/// Does not fail at present; the `Result` matches [`derive`].
+#[must_use = "dropping the keypairs discards the derived secret keys"]
+pub fn keypair_batch_from_seed(
+    seed: &[u8; BATCH_SEED_BYTES],
+    n: usize,
+) -> Result<Vec<Keypair>, KyberLibError> {
+    map_items(n, |i| keypair_item(seed, i))
+        .into_iter()
+        .collect()
+}
+
+/// Encapsulates to each key in `pks` with randomness derived from one
+/// base seed drawn from `rng`.
+///
+/// Item `i` of the result is the encapsulation to `pks[i]`, or the error
+/// [`encapsulate`](crate::encapsulate) returns for it, so one bad key
+/// does not fail the rest.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+#[must_use = "the shared secrets are lost if the result is dropped"]
+pub fn encapsulate_batch<R>(
+    pks: &[PublicKey],
+    rng: &mut R,
+) -> Result<Vec<Encapsulated>, KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    #[allow(unused_mut)]
+    let mut seed = base_seed(rng)?;
+    let encapsulated = encapsulate_batch_from_seed(pks, &seed);
+    #[cfg(feature = "zeroize")]
+    seed.zeroize();
+    Ok(encapsulated)
+}
+
+/// Deterministically encapsulates to each key in `pks`, as
+/// [`encapsulate_batch`] does after drawing `seed`.
+#[must_use = "the shared secrets are lost if the result is dropped"]
+pub fn encapsulate_batch_from_seed(
+    pks: &[PublicKey],
+    seed: &[u8; BATCH_SEED_BYTES],
+) -> Vec<Encapsulated> {
+    map_items(pks.len(), |i| encapsulate_item(&pks[i], seed, i))
+}
+
+/// Decapsulates each ciphertext in `cts` with the secret key `sk`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if `sk` is not
+/// `KYBER_SECRET_KEY_BYTES` long.
+#[must_use = "the shared secrets are lost if the result is dropped"]
+pub fn decapsulate_batch(
+    cts: &[[u8; KYBER_CIPHERTEXT_BYTES]],
+    sk: &[u8],
+) -> Result<Vec<SharedSecret>, KyberLibError> {
+    if sk.len() != KYBER_SECRET_KEY_BYTES {
+        return Err(KyberLibError::InvalidInput);
+    }
+    map_items(cts.len(), |i| decapsulate(&cts[i], sk))
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whichever way map_items runs, it matches the serial path.
+    #[test]
+    fn items_match_serial_path() {
+        let seed = [7u8; BATCH_SEED_BYTES];
+        let keys = keypair_batch_from_seed(&seed, 9).unwrap();
+        let serial =
+            map_items_serial(9, |i| keypair_item(&seed, i).unwrap());
+        assert_eq!(keys, serial);
+
+        let pks: Vec<PublicKey> =
+            keys.iter().map(|k| k.public).collect();
+        let encapsulated = encapsulate_batch_from_seed(&pks, &seed);
+        let serial = map_items_serial(pks.len(), |i| {
+            encapsulate_item(&pks[i], &seed, i)
+        });
+        assert_eq!(encapsulated, serial);
+    }
+
+    #[test]
+    fn item_randomness_separates_labels_and_indices() {
+        let seed = [1u8; BATCH_SEED_BYTES];
+        let mut a = [0u8; 32];
+        let mut b = [0u8; 32];
+        item_randomness(&mut a, KEYPAIR_LABEL, &seed, 0);
+        item_randomness(&mut b, ENCAPSULATE_LABEL, &seed, 0);
+        assert_ne!(a, b);
+        item_randomness(&mut b, KEYPAIR_LABEL, &seed, 1);
+        assert_ne!(a, b);
+        assert_eq!(a[..], item_keypair_seed(&seed, 0)[..32]);
+    }
+}
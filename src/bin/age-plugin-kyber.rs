@@ -0,0 +1,52 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! `age-plugin-kyber`, an age plugin for Kyber recipients.
+//!
+//! Run without arguments to generate a new identity. age invokes the
+//! plugin with `--age-plugin=recipient-v1` or `--age-plugin=identity-v1`.
+
+use kyberlib::age_plugin::{
+    run_identity_v1, run_recipient_v1, Identity,
+};
+use rand::rngs::OsRng;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result =
+        match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["--age-plugin=recipient-v1"] => run_recipient_v1(
+                &mut stdin.lock(),
+                &mut stdout.lock(),
+                &mut OsRng,
+            ),
+            ["--age-plugin=identity-v1"] => {
+                run_identity_v1(&mut stdin.lock(), &mut stdout.lock())
+            }
+            [] => generate(&mut stdout.lock()),
+            _ => {
+                eprintln!(
+                "usage: age-plugin-kyber [--age-plugin=STATE_MACHINE]"
+            );
+                return ExitCode::FAILURE;
+            }
+        };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("age-plugin-kyber: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn generate<W: Write>(output: &mut W) -> io::Result<()> {
+    let identity =
+        Identity::generate(&mut OsRng).map_err(io::Error::other)?;
+    writeln!(output, "# recipient: {}", identity.recipient())?;
+    writeln!(output, "{}", identity.to_encoded().as_str())
+}
@@ -0,0 +1,201 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Heap-allocated keys for async code.
+//!
+//! [`Keypair`] holds both keys by value, 4.7 KB for Kyber1024, so every
+//! future that keeps one across an `.await` carries those bytes in its
+//! state, as does every future awaiting that one. [`BoxedKeypair`] and
+//! [`BoxedSecretKey`] keep the keys on the heap instead, leaving a pointer
+//! per key in the future, and overwrite the secret key with zeros when
+//! dropped whether or not the `zeroize` feature is enabled.
+//!
+//! Prefer them when keys live across `.await` points in request handlers
+//! or other tasks, when many futures holding keys are in flight at once,
+//! or when debug builds come close to the stack size of the runtime's
+//! worker threads. Synchronous code and `no_std` targets without an
+//! allocator keep using [`Keypair`], which needs no allocation.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::boxed::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = BoxedKeypair::generate(&mut rng)?;
+//! let (ct, ss) = encapsulate(&*keys.public, &mut rng)?;
+//! assert_eq!(decapsulate(&ct, &*keys.secret)?, ss);
+//!
+//! // An existing keypair moves to the heap with `boxed`.
+//! let keys = keypair(&mut rng)?.boxed();
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::{
+        keypairfrom, public, Keypair, VersionedSecretKey,
+        VERSIONED_SECRET_KEY_BYTES,
+    },
+    error::KyberLibError,
+    kem::generate_key_pair,
+    kex::{PublicKey, SecretKey},
+    params::*,
+    CryptoRng, RngCore,
+};
+use alloc::boxed::Box;
+use core::fmt;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A [`SecretKey`] on the heap, zeroed on drop.
+///
+/// It dereferences to the array, so every function taking `&[u8]` or
+/// `&SecretKey` accepts `&*key`. `Debug` does not print the key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BoxedSecretKey(Box<SecretKey>);
+
+impl BoxedSecretKey {
+    fn zeroed() -> Self {
+        Self(Box::new([0u8; KYBER_SECRET_KEY_BYTES]))
+    }
+}
+
+impl core::ops::Deref for BoxedSecretKey {
+    type Target = SecretKey;
+
+    fn deref(&self) -> &SecretKey {
+        &self.0
+    }
+}
+
+impl core::ops::DerefMut for BoxedSecretKey {
+    fn deref_mut(&mut self) -> &mut SecretKey {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for BoxedSecretKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+/// Copies the key to the heap and zeroes the copy passed in.
+impl From<SecretKey> for BoxedSecretKey {
+    fn from(mut secret: SecretKey) -> Self {
+        let boxed = Self(Box::new(secret));
+        secret.zeroize();
+        boxed
+    }
+}
+
+impl fmt::Debug for BoxedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BoxedSecretKey(<redacted>)")
+    }
+}
+
+impl Drop for BoxedSecretKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for BoxedSecretKey {}
+
+/// A [`Keypair`] with both keys on the heap.
+///
+/// It offers the methods of [`Keypair`], and its size is that of two
+/// pointers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoxedKeypair {
+    /// The public key.
+    pub public: Box<PublicKey>,
+    /// The secret key.
+    pub secret: BoxedSecretKey,
+}
+
+impl BoxedKeypair {
+    /// Securely generates a new keypair, writing the keys straight to the
+    /// heap.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails
+    /// and `KyberLibError::WeakKey` if it returns zeros.
+    #[must_use = "dropping the keypair discards the only copy of the secret key"]
+    pub fn generate<R: CryptoRng + RngCore>(
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError> {
+        let mut public = Box::new([0u8; KYBER_PUBLIC_KEY_BYTES]);
+        let mut secret = BoxedSecretKey::zeroed();
+        generate_key_pair(&mut public[..], &mut secret[..], rng, None)?;
+        Ok(Self { public, secret })
+    }
+
+    /// Explicitly exposes the secret key.
+    #[must_use]
+    pub fn expose_secret(&self) -> &SecretKey {
+        &self.secret
+    }
+
+    /// Imports a keypair from existing public and secret key arrays, as
+    /// [`Keypair::import`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if the keys do not match.
+    #[must_use = "the imported keypair is returned, not stored"]
+    pub fn import<R: CryptoRng + RngCore>(
+        public: &mut [u8; KYBER_PUBLIC_KEY_BYTES],
+        secret: &mut [u8; KYBER_SECRET_KEY_BYTES],
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError> {
+        keypairfrom(public, secret, rng).map(Self::from)
+    }
+
+    /// Serialises the keypair in the format of [`Keypair::to_bytes`].
+    pub fn to_bytes(&self) -> [u8; VERSIONED_SECRET_KEY_BYTES] {
+        self.secret.to_versioned_bytes()
+    }
+
+    /// Parses a blob produced by [`BoxedKeypair::to_bytes`] or
+    /// [`Keypair::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`Keypair::from_bytes`] does.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KyberLibError> {
+        let secret =
+            BoxedSecretKey::from(SecretKey::from_bytes_checked(bytes)?);
+        Ok(Self {
+            public: Box::new(public(&*secret)),
+            secret,
+        })
+    }
+}
+
+/// Moves the keys to the heap and zeroes the secret key of `keys`.
+impl From<Keypair> for BoxedKeypair {
+    fn from(mut keys: Keypair) -> Self {
+        let boxed = Self {
+            public: Box::new(keys.public),
+            secret: BoxedSecretKey::from(keys.secret),
+        };
+        keys.secret.zeroize();
+        boxed
+    }
+}
+
+impl Keypair {
+    /// Moves the keypair to the heap, for holding it across `.await`
+    /// points; see the [`boxed`](crate::boxed) module.
+    ///
+    /// The secret key in `self` is zeroed; clones made before the call are
+    /// not.
+    #[must_use = "dropping the keypair discards the secret key"]
+    pub fn boxed(self) -> BoxedKeypair {
+        BoxedKeypair::from(self)
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! CMS `KEMRecipientInfo` (RFC 9629) for Kyber recipients.
+//!
+//! The sender encapsulates to the recipient's public key, derives a
+//! key-encryption key with HKDF-SHA-256 and wraps the content-encryption
+//! key (CEK) with AES-256 key wrap. The resulting structure is meant to be
+//! placed in an `OtherRecipientInfo` with type [`ID_ORI_KEM`] by an existing
+//! CMS library, which also handles the content encryption itself.
+//!
+//! The `kem` algorithm identifier is the ML-KEM OID for the active security
+//! level. Note that this crate implements the CRYSTALS-Kyber submission
+//! rather than FIPS 203, so its ciphertexts only decapsulate with kyberlib;
+//! structures produced by other ML-KEM implementations parse, but their
+//! CEK cannot be recovered here.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::cms::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let cek = [7u8; 32];
+//!
+//! let info = KemRecipientInfo::new(&keys.public, b"recipient", &cek, None, &mut rng)?;
+//! let der = info.to_der();
+//!
+//! let parsed = KemRecipientInfo::from_der(&der)?;
+//! assert_eq!(&parsed.unwrap_cek(&keys.secret)?[..], &cek[..]);
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::{decapsulate, encapsulate},
+    error::KyberLibError,
+    kex::{PublicKey, SecretKey},
+    params::*,
+    CryptoRng, RngCore,
+};
+use aes_kw::KekAes256;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+/// `id-ori-kem` (1.2.840.113549.1.9.16.13.3), the `OtherRecipientInfo`
+/// type for a `KEMRecipientInfo`, as DER content bytes.
+pub const ID_ORI_KEM: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x0d, 0x03,
+];
+/// `id-alg-hkdf-with-sha256` (1.2.840.113549.1.9.16.3.28) as DER content
+/// bytes.
+pub const ID_ALG_HKDF_WITH_SHA256: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x03, 0x1c,
+];
+/// `id-aes256-wrap` (2.16.840.1.101.3.4.1.45) as DER content bytes.
+pub const ID_AES256_WRAP: &[u8] =
+    &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2d];
+/// `id-alg-ml-kem-512` (2.16.840.1.101.3.4.4.1) as DER content bytes.
+pub const ID_ALG_ML_KEM_512: &[u8] =
+    &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x04, 0x01];
+/// `id-alg-ml-kem-768` (2.16.840.1.101.3.4.4.2) as DER content bytes.
+pub const ID_ALG_ML_KEM_768: &[u8] =
+    &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x04, 0x02];
+/// `id-alg-ml-kem-1024` (2.16.840.1.101.3.4.4.3) as DER content bytes.
+pub const ID_ALG_ML_KEM_1024: &[u8] =
+    &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x04, 0x03];
+
+/// KEM algorithm identifier for the active security level.
+pub const KEM_OID: &[u8] = match KYBER_SECURITY_PARAMETER {
+    2 => ID_ALG_ML_KEM_512,
+    3 => ID_ALG_ML_KEM_768,
+    _ => ID_ALG_ML_KEM_1024,
+};
+
+/// Length in bytes of the AES-256 key-encryption key.
+pub const KEK_BYTES: usize = 32;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SKI: u8 = 0x80;
+const TAG_UKM: u8 = 0xa0;
+
+/// A `KEMRecipientInfo` addressed by subject key identifier.
+///
+/// Only the `subjectKeyIdentifier` form of `RecipientIdentifier` is
+/// supported. Parsing is strict: non-minimal lengths, unknown algorithms,
+/// algorithm parameters, a `kekLength` other than 32 and trailing data are
+/// all rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KemRecipientInfo {
+    subject_key_id: Vec<u8>,
+    kem_ciphertext: [u8; KYBER_CIPHERTEXT_BYTES],
+    ukm: Option<Vec<u8>>,
+    encrypted_key: Vec<u8>,
+}
+
+impl KemRecipientInfo {
+    /// Wraps `cek` for the holder of `recipient_pk`.
+    ///
+    /// # Arguments
+    ///
+    /// * `recipient_pk` - The recipient's public key.
+    /// * `subject_key_id` - Identifier of the recipient's certificate.
+    /// * `cek` - Content-encryption key, a multiple of 8 bytes and at least 16.
+    /// * `ukm` - Optional user keying material mixed into the KDF.
+    /// * `rng` - The random number generator used for encapsulation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `cek` cannot be wrapped and
+    /// `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    pub fn new<R>(
+        recipient_pk: &PublicKey,
+        subject_key_id: &[u8],
+        cek: &[u8],
+        ukm: Option<&[u8]>,
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if cek.len() < 16 || !cek.len().is_multiple_of(8) {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let (kem_ciphertext, mut shared_secret) =
+            encapsulate(recipient_pk, rng)?;
+        let kek = derive_kek(&shared_secret, ukm);
+        shared_secret.zeroize();
+        let mut kek = kek?;
+
+        let mut encrypted_key = alloc::vec![0u8; cek.len() + 8];
+        let wrapped =
+            KekAes256::from(*kek).wrap(cek, &mut encrypted_key);
+        kek.zeroize();
+        wrapped.map_err(|_| KyberLibError::InvalidLength)?;
+
+        Ok(Self {
+            subject_key_id: subject_key_id.to_vec(),
+            kem_ciphertext,
+            ukm: ukm.map(<[u8]>::to_vec),
+            encrypted_key,
+        })
+    }
+
+    /// Recovers the content-encryption key with the recipient's secret key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::Decapsulation` if the wrapped key fails its
+    /// integrity check, i.e. `secret` is not the recipient's key or the
+    /// structure was modified.
+    pub fn unwrap_cek(
+        &self,
+        secret: &SecretKey,
+    ) -> Result<Zeroizing<Vec<u8>>, KyberLibError> {
+        let mut shared_secret =
+            decapsulate(&self.kem_ciphertext, secret)?;
+        let kek = derive_kek(&shared_secret, self.ukm.as_deref());
+        shared_secret.zeroize();
+        let mut kek = kek?;
+
+        let mut cek = Zeroizing::new(
+            alloc::vec![0u8; self.encrypted_key.len() - 8],
+        );
+        let unwrapped =
+            KekAes256::from(*kek).unwrap(&self.encrypted_key, &mut cek);
+        kek.zeroize();
+        unwrapped.map_err(|_| KyberLibError::Decapsulation)?;
+        Ok(cek)
+    }
+
+    /// The subject key identifier of the recipient.
+    pub fn subject_key_id(&self) -> &[u8] {
+        &self.subject_key_id
+    }
+
+    /// The KEM ciphertext.
+    pub fn kem_ciphertext(&self) -> &[u8; KYBER_CIPHERTEXT_BYTES] {
+        &self.kem_ciphertext
+    }
+
+    /// The user keying material, if any.
+    pub fn ukm(&self) -> Option<&[u8]> {
+        self.ukm.as_deref()
+    }
+
+    /// The wrapped content-encryption key.
+    pub fn encrypted_key(&self) -> &[u8] {
+        &self.encrypted_key
+    }
+
+    /// Encodes the structure as DER.
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        write_tlv(&mut body, TAG_INTEGER, &[0]);
+        write_tlv(&mut body, TAG_SKI, &self.subject_key_id);
+        write_algorithm(&mut body, KEM_OID);
+        write_tlv(&mut body, TAG_OCTET_STRING, &self.kem_ciphertext);
+        write_algorithm(&mut body, ID_ALG_HKDF_WITH_SHA256);
+        write_tlv(&mut body, TAG_INTEGER, &[KEK_BYTES as u8]);
+        if let Some(ukm) = &self.ukm {
+            write_ukm(&mut body, ukm);
+        }
+        write_algorithm(&mut body, ID_AES256_WRAP);
+        write_tlv(&mut body, TAG_OCTET_STRING, &self.encrypted_key);
+
+        let mut der = Vec::with_capacity(body.len() + 4);
+        write_tlv(&mut der, TAG_SEQUENCE, &body);
+        der
+    }
+
+    /// Parses a DER-encoded structure.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if `der` is not a strictly
+    /// encoded `KEMRecipientInfo` for the active security level.
+    pub fn from_der(der: &[u8]) -> Result<Self, KyberLibError> {
+        let mut outer = Reader(der);
+        let mut body = Reader(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+
+        if body.read(TAG_INTEGER)? != [0] {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let subject_key_id = body.read(TAG_SKI)?.to_vec();
+        body.read_algorithm(KEM_OID)?;
+        let kem_ciphertext = body
+            .read(TAG_OCTET_STRING)?
+            .try_into()
+            .map_err(|_| KyberLibError::InvalidInput)?;
+        body.read_algorithm(ID_ALG_HKDF_WITH_SHA256)?;
+        if body.read(TAG_INTEGER)? != [KEK_BYTES as u8] {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let ukm = if body.peek() == Some(TAG_UKM) {
+            let mut explicit = Reader(body.read(TAG_UKM)?);
+            let ukm = explicit.read(TAG_OCTET_STRING)?.to_vec();
+            explicit.finish()?;
+            Some(ukm)
+        } else {
+            None
+        };
+        body.read_algorithm(ID_AES256_WRAP)?;
+        let encrypted_key = body.read(TAG_OCTET_STRING)?.to_vec();
+        body.finish()?;
+        if encrypted_key.len() < 24
+            || !encrypted_key.len().is_multiple_of(8)
+        {
+            return Err(KyberLibError::InvalidInput);
+        }
+
+        Ok(Self {
+            subject_key_id,
+            kem_ciphertext,
+            ukm,
+            encrypted_key,
+        })
+    }
+}
+
+// HKDF-SHA-256 over the shared secret with the DER of
+// CMSORIforKEMOtherInfo as info and no salt.
+fn derive_kek(
+    shared_secret: &[u8],
+    ukm: Option<&[u8]>,
+) -> Result<Zeroizing<[u8; KEK_BYTES]>, KyberLibError> {
+    let mut info_body = Vec::new();
+    write_algorithm(&mut info_body, ID_AES256_WRAP);
+    write_tlv(&mut info_body, TAG_INTEGER, &[KEK_BYTES as u8]);
+    if let Some(ukm) = ukm {
+        write_ukm(&mut info_body, ukm);
+    }
+    let mut info = Vec::with_capacity(info_body.len() + 2);
+    write_tlv(&mut info, TAG_SEQUENCE, &info_body);
+
+    let mut kek = Zeroizing::new([0u8; KEK_BYTES]);
+    Hkdf::<Sha256>::new(None, shared_secret)
+        .expand(&info, &mut *kek)
+        .map_err(|_| KyberLibError::InvalidLength)?;
+    Ok(kek)
+}
+
+fn write_tlv(out: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    out.push(tag);
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len <= 0xff {
+        out.extend_from_slice(&[0x81, len as u8]);
+    } else {
+        out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]);
+    }
+    out.extend_from_slice(content);
+}
+
+fn write_algorithm(out: &mut Vec<u8>, oid: &[u8]) {
+    let mut body = Vec::with_capacity(oid.len() + 2);
+    write_tlv(&mut body, TAG_OID, oid);
+    write_tlv(out, TAG_SEQUENCE, &body);
+}
+
+fn write_ukm(out: &mut Vec<u8>, ukm: &[u8]) {
+    let mut explicit = Vec::with_capacity(ukm.len() + 4);
+    write_tlv(&mut explicit, TAG_OCTET_STRING, ukm);
+    write_tlv(out, TAG_UKM, &explicit);
+}
+
+// Minimal DER reader over a byte slice, accepting only definite, minimally
+// encoded lengths below 64 KiB.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    fn read(&mut self, tag: u8) -> Result<&'a [u8], KyberLibError> {
+        let err = KyberLibError::InvalidInput;
+        let (&actual, rest) = self.0.split_first().ok_or(err)?;
+        let (&first, rest) = rest.split_first().ok_or(err)?;
+        if actual != tag {
+            return Err(err);
+        }
+        let (len, rest) = match first {
+            0x00..=0x7f => (usize::from(first), rest),
+            0x81 => match rest {
+                [l, rest @ ..] if *l >= 0x80 => (usize::from(*l), rest),
+                _ => return Err(err),
+            },
+            0x82 => match rest {
+                [h, l, rest @ ..] if *h != 0 => {
+                    (usize::from(*h) << 8 | usize::from(*l), rest)
+                }
+                _ => return Err(err),
+            },
+            _ => return Err(err),
+        };
+        if rest.len() < len {
+            return Err(err);
+        }
+        let (content, rest) = rest.split_at(len);
+        self.0 = rest;
+        Ok(content)
+    }
+
+    fn read_algorithm(
+        &mut self,
+        oid: &[u8],
+    ) -> Result<(), KyberLibError> {
+        let mut algorithm = Reader(self.read(TAG_SEQUENCE)?);
+        if algorithm.read(TAG_OID)? != oid {
+            return Err(KyberLibError::InvalidInput);
+        }
+        // Parameters must be absent for every algorithm used here.
+        algorithm.finish()
+    }
+
+    fn finish(&self) -> Result<(), KyberLibError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(KyberLibError::InvalidInput)
+        }
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Constant-time hex and base64 codecs for secret material.
+//!
+//! Table-based or `match`-based decoders branch and index memory on each
+//! input character, and usually return at the first invalid one, so the
+//! time they take leaks the characters of a secret key, shared secret or
+//! seed being encoded or decoded. The codecs here map between characters
+//! and values with arithmetic on masks only, in the manner of libsodium
+//! and the `ct-codecs` crate, and a decoder checks every character before
+//! reporting whether any was invalid. Only the lengths of the input and
+//! output, and whether the input was valid as a whole, are revealed.
+//!
+//! The `serde` support uses these for [`Keypair`](crate::Keypair) and for
+//! `serialization::secret` fields. Public keys and ciphertexts keep the
+//! faster codecs, as there is nothing to hide in them.
+//!
+//! Functions write into a caller-provided buffer, so they work without an
+//! allocator, and return the written part of it. On invalid input the
+//! buffer is cleared.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::codec;
+//! let secret = [0xde, 0xad, 0xbe, 0xef];
+//! let mut buf = [0u8; 8];
+//! let hex = codec::hex_encode(&secret, &mut buf).unwrap();
+//! assert_eq!(hex, "deadbeef");
+//!
+//! let mut out = [0u8; 4];
+//! assert_eq!(codec::hex_decode("DEADbeef", &mut out).unwrap(), secret);
+//! assert!(codec::hex_decode("deadbeeg", &mut out).is_err());
+//! ```
+
+use crate::error::KyberLibError;
+
+// 0xff if `lo <= c <= hi` and 0 otherwise, without a branch on `c`: both
+// differences are negative only inside the range, and the arithmetic
+// shift spreads the sign bit of their conjunction over the low byte.
+fn in_range(c: u8, lo: u8, hi: u8) -> u8 {
+    let c = i16::from(c);
+    (((i16::from(lo) - 1 - c) & (c - i16::from(hi) - 1)) >> 8) as u8
+}
+
+// The lowercase hex digit of a nibble.
+fn hex_char(n: u8) -> u8 {
+    let letter = in_range(n, 10, 15);
+    n + b'0' + (letter & (b'a' - b'0' - 10))
+}
+
+// The value of a hex digit of either case, and 0xff if it is one.
+fn hex_value(c: u8) -> (u8, u8) {
+    let digit = in_range(c, b'0', b'9');
+    let lower = in_range(c, b'a', b'f');
+    let upper = in_range(c, b'A', b'F');
+    let value = (digit & c.wrapping_sub(b'0'))
+        | (lower & c.wrapping_sub(b'a' - 10))
+        | (upper & c.wrapping_sub(b'A' - 10));
+    (value, digit | lower | upper)
+}
+
+// The characters for 62 and 63 are the only difference between the
+// standard and URL-safe alphabets. The variant is public, so branching on
+// it is fine.
+#[derive(Clone, Copy)]
+struct Variant {
+    url: bool,
+    pad: bool,
+}
+
+const STANDARD: Variant = Variant {
+    url: false,
+    pad: true,
+};
+
+const URL_SAFE: Variant = Variant {
+    url: true,
+    pad: false,
+};
+
+impl Variant {
+    fn extra(self) -> (u8, u8) {
+        if self.url {
+            (b'-', b'_')
+        } else {
+            (b'+', b'/')
+        }
+    }
+
+    fn encoded_len(self, len: usize) -> usize {
+        if self.pad {
+            base64_encoded_len(len)
+        } else {
+            base64url_encoded_len(len)
+        }
+    }
+}
+
+// The base64 character of a 6-bit value.
+fn base64_char(x: u8, variant: Variant) -> u8 {
+    let (c62, c63) = variant.extra();
+    (in_range(x, 0, 25) & x.wrapping_add(b'A'))
+        | (in_range(x, 26, 51) & x.wrapping_add(b'a' - 26))
+        | (in_range(x, 52, 61) & x.wrapping_sub(52 - b'0'))
+        | (in_range(x, 62, 62) & c62)
+        | (in_range(x, 63, 63) & c63)
+}
+
+// The value of a base64 character, and 0xff if it is one.
+fn base64_value(c: u8, variant: Variant) -> (u8, u8) {
+    let (c62, c63) = variant.extra();
+    let upper = in_range(c, b'A', b'Z');
+    let lower = in_range(c, b'a', b'z');
+    let digit = in_range(c, b'0', b'9');
+    let is62 = in_range(c, c62, c62);
+    let is63 = in_range(c, c63, c63);
+    let value = (upper & c.wrapping_sub(b'A'))
+        | (lower & c.wrapping_sub(b'a' - 26))
+        | (digit & c.wrapping_add(52 - b'0'))
+        | (is62 & 62)
+        | (is63 & 63);
+    (value, upper | lower | digit | is62 | is63)
+}
+
+// Every output character is ASCII, so the UTF-8 check takes the same path
+// whatever the bytes are.
+fn ascii(encoded: &[u8]) -> &str {
+    core::str::from_utf8(encoded).expect("codec output is ASCII")
+}
+
+// `valid` is the conjunction of the per-character masks, so this is the
+// first and only branch on the input characters.
+fn checked(dst: &mut [u8], valid: u8) -> Result<&[u8], KyberLibError> {
+    if valid != 0xff {
+        dst.fill(0);
+        return Err(KyberLibError::InvalidEncoding);
+    }
+    Ok(dst)
+}
+
+/// Returns the length of the hex encoding of `len` bytes.
+pub const fn hex_encoded_len(len: usize) -> usize {
+    2 * len
+}
+
+/// Writes `src` to `dst` as lowercase hex in constant time, and returns
+/// the written part of `dst`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `dst` is shorter than
+/// [`hex_encoded_len`] of the input.
+pub fn hex_encode<'a>(
+    src: &[u8],
+    dst: &'a mut [u8],
+) -> Result<&'a str, KyberLibError> {
+    let dst = dst
+        .get_mut(..hex_encoded_len(src.len()))
+        .ok_or(KyberLibError::InvalidLength)?;
+    for (byte, pair) in src.iter().zip(dst.chunks_exact_mut(2)) {
+        pair[0] = hex_char(byte >> 4);
+        pair[1] = hex_char(byte & 0x0f);
+    }
+    Ok(ascii(dst))
+}
+
+/// Decodes hex of either case from `src` into `dst` in constant time, and
+/// returns the written part of `dst`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidEncoding` if `src` has an odd length or
+/// a character that is not a hex digit, after reading all of it, and
+/// `KyberLibError::InvalidLength` if `dst` is shorter than half of `src`.
+pub fn hex_decode(
+    src: impl AsRef<[u8]>,
+    dst: &mut [u8],
+) -> Result<&[u8], KyberLibError> {
+    let src = src.as_ref();
+    if !src.len().is_multiple_of(2) {
+        return Err(KyberLibError::InvalidEncoding);
+    }
+    let dst = dst
+        .get_mut(..src.len() / 2)
+        .ok_or(KyberLibError::InvalidLength)?;
+    let mut valid = 0xff;
+    for (byte, pair) in dst.iter_mut().zip(src.chunks_exact(2)) {
+        let (hi, hi_valid) = hex_value(pair[0]);
+        let (lo, lo_valid) = hex_value(pair[1]);
+        *byte = hi << 4 | lo;
+        valid &= hi_valid & lo_valid;
+    }
+    checked(dst, valid)
+}
+
+/// Returns the length of the padded standard base64 encoding of `len`
+/// bytes.
+pub const fn base64_encoded_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Returns the length of the unpadded URL-safe base64 encoding of `len`
+/// bytes.
+pub const fn base64url_encoded_len(len: usize) -> usize {
+    (len * 4).div_ceil(3)
+}
+
+/// Writes `src` to `dst` as padded base64 with the standard alphabet (RFC
+/// 4648, section 4) in constant time, and returns the written part of
+/// `dst`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `dst` is shorter than
+/// [`base64_encoded_len`] of the input.
+pub fn base64_encode<'a>(
+    src: &[u8],
+    dst: &'a mut [u8],
+) -> Result<&'a str, KyberLibError> {
+    encode_base64(src, dst, STANDARD)
+}
+
+/// Decodes padded standard base64 from `src` into `dst` in constant time,
+/// and returns the written part of `dst`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidEncoding` if `src` is not canonical
+/// padded base64, after reading all of it, and
+/// `KyberLibError::InvalidLength` if `dst` is too short for the decoded
+/// bytes.
+pub fn base64_decode(
+    src: impl AsRef<[u8]>,
+    dst: &mut [u8],
+) -> Result<&[u8], KyberLibError> {
+    decode_base64(src.as_ref(), dst, STANDARD)
+}
+
+/// Writes `src` to `dst` as unpadded base64 with the URL-safe alphabet
+/// (RFC 4648, section 5) in constant time, and returns the written part
+/// of `dst`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `dst` is shorter than
+/// [`base64url_encoded_len`] of the input.
+pub fn base64url_encode<'a>(
+    src: &[u8],
+    dst: &'a mut [u8],
+) -> Result<&'a str, KyberLibError> {
+    encode_base64(src, dst, URL_SAFE)
+}
+
+/// Decodes unpadded URL-safe base64 from `src` into `dst` in constant
+/// time, and returns the written part of `dst`.
+///
+/// # Errors
+///
+/// As [`base64_decode`].
+pub fn base64url_decode(
+    src: impl AsRef<[u8]>,
+    dst: &mut [u8],
+) -> Result<&[u8], KyberLibError> {
+    decode_base64(src.as_ref(), dst, URL_SAFE)
+}
+
+fn encode_base64<'a>(
+    src: &[u8],
+    dst: &'a mut [u8],
+    variant: Variant,
+) -> Result<&'a str, KyberLibError> {
+    let dst = dst
+        .get_mut(..variant.encoded_len(src.len()))
+        .ok_or(KyberLibError::InvalidLength)?;
+    let mut out = 0;
+    for chunk in src.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from(group[0]) << 16
+            | u32::from(group[1]) << 8
+            | u32::from(group[2]);
+        // A group of n bytes takes n + 1 characters, and padding fills
+        // the rest of the four.
+        let chars = if variant.pad { 4 } else { chunk.len() + 1 };
+        for (i, c) in dst[out..out + chars].iter_mut().enumerate() {
+            *c = if i <= chunk.len() {
+                base64_char(
+                    (bits >> (18 - 6 * i)) as u8 & 0x3f,
+                    variant,
+                )
+            } else {
+                b'='
+            };
+        }
+        out += chars;
+    }
+    Ok(ascii(dst))
+}
+
+fn decode_base64<'a>(
+    src: &[u8],
+    dst: &'a mut [u8],
+    variant: Variant,
+) -> Result<&'a [u8], KyberLibError> {
+    // The amount of padding follows from the length of the decoded bytes,
+    // which is not secret.
+    let src = if variant.pad {
+        if !src.len().is_multiple_of(4) {
+            return Err(KyberLibError::InvalidEncoding);
+        }
+        let pad = src.iter().rev().take(2).take_while(|&&c| c == b'=');
+        &src[..src.len() - pad.count()]
+    } else {
+        src
+    };
+    if src.len() % 4 == 1 {
+        return Err(KyberLibError::InvalidEncoding);
+    }
+    let dst = dst
+        .get_mut(..src.len() * 3 / 4)
+        .ok_or(KyberLibError::InvalidLength)?;
+    let mut valid = 0xff;
+    let (mut acc, mut acc_bits, mut out) = (0u32, 0, 0);
+    for &c in src {
+        let (value, value_valid) = base64_value(c, variant);
+        valid &= value_valid;
+        acc = acc << 6 | u32::from(value);
+        acc_bits += 6;
+        if acc_bits >= 8 {
+            acc_bits -= 8;
+            dst[out] = (acc >> acc_bits) as u8;
+            out += 1;
+        }
+    }
+    // Bits left over from the last character must be zero, so that every
+    // byte string has exactly one encoding.
+    let leftover = (acc & ((1 << acc_bits) - 1)) as u8;
+    valid &= in_range(leftover, 0, 0);
+    checked(dst, valid)
+}
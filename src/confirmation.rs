@@ -0,0 +1,107 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Explicit key confirmation after the KEM step.
+//!
+//! Decapsulation never fails: a ciphertext made for another key yields a
+//! pseudo-random shared secret, and the mismatch only shows up later as
+//! undecryptable data. Exchanging a confirmation tag straight after the
+//! KEM step lets a protocol report a failed key agreement precisely.
+//!
+//! A tag is derived from the shared secret and a caller-supplied
+//! transcript with the labeled KDF, under a label that names the role of
+//! the party *sending* the tag. The initiator sends
+//! `confirmation_tag(Role::Initiator, ..)` and the responder checks it with
+//! `verify_confirmation_tag(Role::Initiator, ..)`, and the other way round
+//! for the responder's tag. Because the two directions use different
+//! labels, a tag reflected back to its sender does not verify.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::confirmation::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let (ct, initiator_secret) = encapsulate(&keys.public, &mut rng)?;
+//! let responder_secret = decapsulate(&ct, &keys.secret)?;
+//!
+//! let tag = initiator_secret.confirmation_tag(Role::Initiator, &ct);
+//! let ok = responder_secret.verify_confirmation_tag(Role::Initiator, &ct, &tag);
+//! assert!(bool::from(ok));
+//! # Ok(()) }
+//! ```
+
+use crate::{kex::SharedSecret, symmetric::labeled_kdf};
+pub use subtle::Choice;
+use subtle::ConstantTimeEq;
+
+/// Size in bytes of a key confirmation tag.
+pub const CONFIRMATION_TAG_BYTES: usize = 16;
+
+const INITIATOR_LABEL: &str = "kyberlib key confirmation v1 initiator";
+const RESPONDER_LABEL: &str = "kyberlib key confirmation v1 responder";
+
+/// The party a confirmation tag is sent by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// The party that encapsulated to the peer's public key.
+    Initiator,
+    /// The party that decapsulated with its secret key.
+    Responder,
+}
+
+impl Role {
+    fn label(self) -> &'static str {
+        match self {
+            Role::Initiator => INITIATOR_LABEL,
+            Role::Responder => RESPONDER_LABEL,
+        }
+    }
+}
+
+/// Key confirmation for [`SharedSecret`].
+///
+/// `SharedSecret` is a plain byte array, so confirmation is provided
+/// through this extension trait rather than an inherent method.
+pub trait KeyConfirmation {
+    /// Derives the tag sent by `role` over `transcript`.
+    ///
+    /// The transcript should bind whatever both sides agree on, at least the
+    /// ciphertext and ideally the public key.
+    fn confirmation_tag(
+        &self,
+        role: Role,
+        transcript: &[u8],
+    ) -> [u8; CONFIRMATION_TAG_BYTES];
+
+    /// Checks in constant time that `tag` was sent by `role` over
+    /// `transcript`.
+    fn verify_confirmation_tag(
+        &self,
+        role: Role,
+        transcript: &[u8],
+        tag: &[u8; CONFIRMATION_TAG_BYTES],
+    ) -> Choice;
+}
+
+impl KeyConfirmation for SharedSecret {
+    fn confirmation_tag(
+        &self,
+        role: Role,
+        transcript: &[u8],
+    ) -> [u8; CONFIRMATION_TAG_BYTES] {
+        let mut tag = [0u8; CONFIRMATION_TAG_BYTES];
+        labeled_kdf(&mut tag, role.label(), &[self, transcript]);
+        tag
+    }
+
+    fn verify_confirmation_tag(
+        &self,
+        role: Role,
+        transcript: &[u8],
+        tag: &[u8; CONFIRMATION_TAG_BYTES],
+    ) -> Choice {
+        self.confirmation_tag(role, transcript).ct_eq(tag)
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Routes the IND-CPA operations to the backend [`acceleration`] selects.
+//!
+//! The backend is looked up once per key generation, encryption or
+//! decryption, each of which runs dozens of NTTs and hashes several
+//! kilobytes, so the atomic load and the `match` do not show; within
+//! an operation the backend calls its kernels directly.
+
+use crate::acceleration::acceleration;
+#[allow(unused_imports)] // no SIMD arm in builds without a backend
+use crate::acceleration::Acceleration;
+use crate::{reference, CryptoRng, KyberLibError, RngCore};
+
+#[allow(clippy::match_single_binding)] // no SIMD arm without a backend
+pub(crate) fn indcpa_keypair<R>(
+    pk: &mut [u8],
+    sk: &mut [u8],
+    seed: Option<(&[u8], &[u8])>,
+    rng: &mut R,
+) -> Result<(), KyberLibError>
+where
+    R: CryptoRng + RngCore,
+{
+    match acceleration() {
+        // SAFETY: `acceleration` only selects the backends the CPU
+        // supports.
+        #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+        Acceleration::Avx512 => unsafe {
+            crate::avx512::indcpa::indcpa_keypair(pk, sk, seed, rng)
+        },
+        #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+        Acceleration::Avx2 => unsafe {
+            crate::avx2::indcpa::indcpa_keypair(pk, sk, seed, rng)
+        },
+        #[cfg(all(
+            target_arch = "aarch64",
+            target_feature = "neon",
+            feature = "neon"
+        ))]
+        Acceleration::Neon => unsafe {
+            crate::neon::indcpa::indcpa_keypair(pk, sk, seed, rng)
+        },
+        #[cfg(all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+            feature = "wasm-simd"
+        ))]
+        Acceleration::WasmSimd => {
+            crate::wasm_simd::indcpa::indcpa_keypair(pk, sk, seed, rng)
+        }
+        #[cfg(all(target_arch = "riscv64", feature = "rvv"))]
+        Acceleration::Rvv => unsafe {
+            crate::rvv::indcpa::indcpa_keypair(pk, sk, seed, rng)
+        },
+        #[cfg(all(
+            feature = "portable-simd",
+            not(any(
+                all(target_arch = "x86_64", feature = "avx2"),
+                all(
+                    target_arch = "aarch64",
+                    target_feature = "neon",
+                    feature = "neon"
+                ),
+                all(
+                    target_arch = "wasm32",
+                    target_feature = "simd128",
+                    feature = "wasm-simd"
+                ),
+                all(target_arch = "riscv64", feature = "rvv")
+            ))
+        ))]
+        Acceleration::PortableSimd => {
+            crate::portable_simd::indcpa::indcpa_keypair(
+                pk, sk, seed, rng,
+            )
+        }
+        _ => reference::indcpa::indcpa_keypair(pk, sk, seed, rng),
+    }
+}
+
+#[allow(clippy::match_single_binding)] // as in `indcpa_keypair`
+pub(crate) fn indcpa_enc(
+    c: &mut [u8],
+    m: &[u8],
+    pk: &[u8],
+    coins: &[u8],
+) {
+    match acceleration() {
+        // SAFETY: as in `indcpa_keypair`.
+        #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+        Acceleration::Avx512 => unsafe {
+            crate::avx512::indcpa::indcpa_enc(c, m, pk, coins)
+        },
+        #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+        Acceleration::Avx2 => unsafe {
+            crate::avx2::indcpa::indcpa_enc(c, m, pk, coins)
+        },
+        #[cfg(all(
+            target_arch = "aarch64",
+            target_feature = "neon",
+            feature = "neon"
+        ))]
+        Acceleration::Neon => unsafe {
+            crate::neon::indcpa::indcpa_enc(c, m, pk, coins)
+        },
+        #[cfg(all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+            feature = "wasm-simd"
+        ))]
+        Acceleration::WasmSimd => {
+            crate::wasm_simd::indcpa::indcpa_enc(c, m, pk, coins)
+        }
+        #[cfg(all(target_arch = "riscv64", feature = "rvv"))]
+        Acceleration::Rvv => unsafe {
+            crate::rvv::indcpa::indcpa_enc(c, m, pk, coins)
+        },
+        #[cfg(all(
+            feature = "portable-simd",
+            not(any(
+                all(target_arch = "x86_64", feature = "avx2"),
+                all(
+                    target_arch = "aarch64",
+                    target_feature = "neon",
+                    feature = "neon"
+                ),
+                all(
+                    target_arch = "wasm32",
+                    target_feature = "simd128",
+                    feature = "wasm-simd"
+                ),
+                all(target_arch = "riscv64", feature = "rvv")
+            ))
+        ))]
+        Acceleration::PortableSimd => {
+            crate::portable_simd::indcpa::indcpa_enc(c, m, pk, coins)
+        }
+        _ => reference::indcpa::indcpa_enc(c, m, pk, coins),
+    }
+}
+
+#[allow(clippy::match_single_binding)] // as in `indcpa_keypair`
+pub(crate) fn indcpa_dec(m: &mut [u8], c: &[u8], sk: &[u8]) {
+    match acceleration() {
+        // SAFETY: as in `indcpa_keypair`.
+        #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+        Acceleration::Avx512 => unsafe {
+            crate::avx512::indcpa::indcpa_dec(m, c, sk)
+        },
+        #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+        Acceleration::Avx2 => unsafe {
+            crate::avx2::indcpa::indcpa_dec(m, c, sk)
+        },
+        #[cfg(all(
+            target_arch = "aarch64",
+            target_feature = "neon",
+            feature = "neon"
+        ))]
+        Acceleration::Neon => unsafe {
+            crate::neon::indcpa::indcpa_dec(m, c, sk)
+        },
+        #[cfg(all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+            feature = "wasm-simd"
+        ))]
+        Acceleration::WasmSimd => {
+            crate::wasm_simd::indcpa::indcpa_dec(m, c, sk)
+        }
+        #[cfg(all(target_arch = "riscv64", feature = "rvv"))]
+        Acceleration::Rvv => unsafe {
+            crate::rvv::indcpa::indcpa_dec(m, c, sk)
+        },
+        #[cfg(all(
+            feature = "portable-simd",
+            not(any(
+                all(target_arch = "x86_64", feature = "avx2"),
+                all(
+                    target_arch = "aarch64",
+                    target_feature = "neon",
+                    feature = "neon"
+                ),
+                all(
+                    target_arch = "wasm32",
+                    target_feature = "simd128",
+                    feature = "wasm-simd"
+                ),
+                all(target_arch = "riscv64", feature = "rvv")
+            ))
+        ))]
+        Acceleration::PortableSimd => {
+            crate::portable_simd::indcpa::indcpa_dec(m, c, sk)
+        }
+        _ => reference::indcpa::indcpa_dec(m, c, sk),
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Key generation and encapsulation from a hardware entropy source.
+//!
+//! A TRNG peripheral driver that does not implement `rand_core` can
+//! implement the one-method [`EntropySource`] trait instead and be passed
+//! to [`keypair_from_entropy`] and [`encapsulate_from_entropy`]. Every
+//! `RngCore + CryptoRng` is an `EntropySource` too, so both kinds of
+//! source work with these functions.
+//!
+//! Each operation draws its randomness from the source in a single call,
+//! so the hardware budget is fixed:
+//!
+//! | Operation | Bytes drawn |
+//! |-----------|-------------|
+//! | [`keypair_from_entropy`] | [`KEYPAIR_ENTROPY_BYTES`] (64) |
+//! | [`encapsulate_from_entropy`] | [`ENCAPSULATE_ENTROPY_BYTES`] (32) |
+//!
+//! The output is checked before use: a draw whose bytes are all equal,
+//! all zeros included, is the signature of a stuck or unclocked
+//! peripheral and is rejected. This catches dead hardware, not a weak
+//! one; the source is otherwise trusted to be cryptographically secure.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::entropy::*;
+//! struct Trng;
+//!
+//! impl EntropySource for Trng {
+//!     fn try_fill(&mut self, dest: &mut [u8]) -> Result<(), EntropyError> {
+//!         // Read the peripheral's data register here.
+//! #       use rand_core::RngCore;
+//! #       rand_core::OsRng.try_fill_bytes(dest).map_err(|_| EntropyError)?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> Result<(), KyberLibError> {
+//! let keys = keypair_from_entropy(&mut Trng)?;
+//! let (ct, ss) = encapsulate_from_entropy(&keys.public, &mut Trng)?;
+//! assert_eq!(decapsulate(&ct, &keys.secret)?, ss);
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    api::{derive, DummyRng, Keypair},
+    error::KyberLibError,
+    kem::encrypt_message,
+    kex::Encapsulated,
+    params::*,
+    rng::randombytes,
+    CryptoRng, RngCore,
+};
+use core::fmt;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Bytes [`keypair_from_entropy`] draws: the seed `d` of the IND-CPA key
+/// and the implicit rejection value `z`.
+pub const KEYPAIR_ENTROPY_BYTES: usize = 2 * KYBER_SYM_BYTES;
+/// Bytes [`encapsulate_from_entropy`] draws: the message `m`.
+pub const ENCAPSULATE_ENTROPY_BYTES: usize = KYBER_SYM_BYTES;
+
+/// The entropy source could not fill the buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EntropyError;
+
+impl fmt::Display for EntropyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Entropy source failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EntropyError {}
+
+/// A source of hardware entropy, such as a TRNG peripheral.
+pub trait EntropySource {
+    /// Fills `dest` entirely with entropy.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EntropyError` if the source cannot provide the bytes, for
+    /// example because a health test of the peripheral failed.
+    fn try_fill(&mut self, dest: &mut [u8])
+        -> Result<(), EntropyError>;
+}
+
+/// Draws through [`randombytes`], so a failing RNG is retried as
+/// configured with
+/// [`set_random_bytes_attempts`](crate::rng::set_random_bytes_attempts).
+impl<R: RngCore + CryptoRng> EntropySource for R {
+    fn try_fill(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), EntropyError> {
+        let len = dest.len();
+        randombytes(dest, len, self).map_err(|_| EntropyError)
+    }
+}
+
+// Fills `dest` from `source` and rejects output whose bytes are all
+// equal.
+fn draw<E>(source: &mut E, dest: &mut [u8]) -> Result<(), KyberLibError>
+where
+    E: EntropySource + ?Sized,
+{
+    source
+        .try_fill(dest)
+        .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+    let first = dest[0];
+    if dest.iter().all(|&byte| byte == first) {
+        return Err(KyberLibError::RandomBytesGeneration);
+    }
+    Ok(())
+}
+
+/// Generates a keypair from [`KEYPAIR_ENTROPY_BYTES`] bytes of `source`.
+///
+/// The key is the one [`derive`] computes from the drawn bytes.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the source fails or
+/// its output is rejected by the health check.
+#[must_use = "dropping the keypair discards the only copy of the secret key"]
+pub fn keypair_from_entropy<E>(
+    source: &mut E,
+) -> Result<Keypair, KyberLibError>
+where
+    E: EntropySource + ?Sized,
+{
+    let mut seed = [0u8; KEYPAIR_ENTROPY_BYTES];
+    let keys = draw(source, &mut seed).and_then(|()| derive(&seed));
+    #[cfg(feature = "zeroize")]
+    seed.zeroize();
+    keys
+}
+
+/// Encapsulates to `pk` with [`ENCAPSULATE_ENTROPY_BYTES`] bytes of
+/// `source`, returning the ciphertext and the shared secret.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if `pk` has the wrong length and
+/// `KyberLibError::RandomBytesGeneration` if the source fails or its
+/// output is rejected by the health check. The source is not drawn from
+/// when `pk` is rejected.
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn encapsulate_from_entropy<E>(
+    pk: &[u8],
+    source: &mut E,
+) -> Encapsulated
+where
+    E: EntropySource + ?Sized,
+{
+    if pk.len() != KYBER_PUBLIC_KEY_BYTES {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut coins = [0u8; ENCAPSULATE_ENTROPY_BYTES];
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let result = draw(source, &mut coins).and_then(|()| {
+        encrypt_message(
+            &mut ct,
+            &mut ss,
+            pk,
+            &mut DummyRng {},
+            Some(&coins),
+        )
+    });
+    #[cfg(feature = "zeroize")]
+    coins.zeroize();
+    result.map(|()| (ct, ss))
+}
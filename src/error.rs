@@ -19,28 +19,97 @@ pub enum KyberLibError {
 
     /// Error trying to fill random bytes (i.e., external (hardware) RNG modules can fail).
     RandomBytesGeneration,
+
+    /// A sealed secret key could not be opened: the password is wrong or the
+    /// sealed data has been tampered with.
+    Unsealing,
+
+    /// A secret share is corrupted, repeated, or belongs to another key.
+    InvalidShare,
+
+    /// A signature over a key does not verify under the expected signer.
+    InvalidSignature,
+
+    /// Key generation drew an all-zero seed or produced an all-zero secret
+    /// vector, most likely because the RNG is broken.
+    WeakKey,
+
+    /// A padded decapsulation took longer than the duration it was padded
+    /// to, so its timing was not hidden.
+    Overrun,
+
+    /// Input to a decoder is not valid hex or base64.
+    InvalidEncoding,
 }
 
-impl core::fmt::Display for KyberLibError {
-    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+impl KyberLibError {
+    // The description shared by `Display` and `defmt::Format`.
+    fn message(&self) -> &'static str {
         match self {
-            KyberLibError::InvalidInput => write!(f, "Function input is of incorrect length"),
-            KyberLibError::Decapsulation => write!(
-                f,
+            KyberLibError::InvalidInput => "Function input is of incorrect length",
+            KyberLibError::Decapsulation => {
                 "Decapsulation Failure, unable to obtain shared secret from ciphertext"
-            ),
+            }
             KyberLibError::RandomBytesGeneration => {
-                write!(f, "Random bytes generation function failed")
+                "Random bytes generation function failed"
             }
             KyberLibError::InvalidKey => {
-                write!(f, "The secret and public key given does not match.")
+                "The secret and public key given does not match."
             },
             KyberLibError::InvalidLength => {
-                write!(f, "The length of the input buffer is invalid.")
+                "The length of the input buffer is invalid."
+            }
+            KyberLibError::InvalidShare => {
+                "Secret share is corrupted or belongs to another key"
+            }
+            KyberLibError::InvalidSignature => {
+                "Signature verification failed"
+            }
+            KyberLibError::Unsealing => {
+                "Unable to open sealed secret key, wrong password or corrupted data"
+            }
+            KyberLibError::WeakKey => {
+                "Generated key is degenerate, the RNG may be broken"
+            }
+            KyberLibError::Overrun => {
+                "Operation overran its padded duration"
+            }
+            KyberLibError::InvalidEncoding => {
+                "Input is not valid hex or base64"
             }
         }
     }
 }
 
+impl core::fmt::Display for KyberLibError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// Formats as the variant name followed by the `Display` message, e.g.
+/// `InvalidInput: Function input is of incorrect length`.
+#[cfg(feature = "defmt")]
+impl defmt::Format for KyberLibError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        let name = match self {
+            KyberLibError::InvalidInput => "InvalidInput",
+            KyberLibError::InvalidKey => "InvalidKey",
+            KyberLibError::InvalidLength => "InvalidLength",
+            KyberLibError::Decapsulation => "Decapsulation",
+            KyberLibError::RandomBytesGeneration => {
+                "RandomBytesGeneration"
+            }
+            KyberLibError::Unsealing => "Unsealing",
+            KyberLibError::InvalidShare => "InvalidShare",
+            KyberLibError::InvalidSignature => "InvalidSignature",
+            KyberLibError::WeakKey => "WeakKey",
+            KyberLibError::Overrun => "Overrun",
+            KyberLibError::InvalidEncoding => "InvalidEncoding",
+        };
+        defmt::write!(f, "{=str}: {=str}", name, self.message())
+    }
+}
+
 #[cfg(feature = "std")]
 impl std::error::Error for KyberLibError {}
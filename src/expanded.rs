@@ -0,0 +1,269 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Secret keys with the public matrix expanded ahead of decapsulation.
+//!
+//! A stored [`SecretKey`](crate::SecretKey) already holds `NTT(s)` and
+//! `NTT(t)`, so the one expansion decapsulation repeats on every call is
+//! that of the matrix `A^T` for the re-encryption: k^2 polynomials
+//! sampled from the seed, the bulk of its cost. [`ExpandedSecretKey`]
+//! samples them once and keeps them on the heap, 8 KiB for Kyber1024.
+//!
+//! Workers that cold-start often would still pay for the expansion on
+//! every start, so the expanded key can be persisted with
+//! [`to_cache_bytes`] and loaded with [`from_cache_bytes`], which
+//! unpacks the matrix instead of sampling it. The cache is laid out as
+//! follows:
+//!
+//! [`ExpandedSecretKey`]: crate::expanded::ExpandedSecretKey
+//! [`to_cache_bytes`]: crate::expanded::ExpandedSecretKey::to_cache_bytes
+//! [`from_cache_bytes`]: crate::expanded::ExpandedSecretKey::from_cache_bytes
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 1     | format version, currently `CACHE_FORMAT_VERSION` |
+//! | 1     | security parameter `k` of the key |
+//! | 1     | 1 for the 90s variant, 0 otherwise |
+//! | 1     | flags, 1 if the matrix is included |
+//! | `KYBER_SECRET_KEY_BYTES` | the secret key |
+//! | `CACHE_MATRIX_BYTES` | `A^T` row by row, 12-bit packed, if flagged |
+//! | 32    | tag |
+//!
+//! The tag is a MAC over everything before it, keyed with the implicit
+//! rejection value `z` of the secret key, so a cache that was corrupted,
+//! truncated, or pieced together from the caches of different keys is
+//! rejected. The cache holds the secret key in the clear: it must be
+//! stored as carefully as the key itself, and the tag is no protection
+//! against whoever can read it.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::expanded::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let cache = ExpandedSecretKey::new(&keys.secret).to_cache_bytes(true);
+//!
+//! // On the next cold start.
+//! let expanded = ExpandedSecretKey::from_cache_bytes(&cache)?;
+//! let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+//! assert_eq!(expanded.decapsulate(&ct)?, ss);
+//!
+//! let mut tampered = cache.clone();
+//! tampered[100] ^= 1;
+//! assert!(ExpandedSecretKey::from_cache_bytes(&tampered).is_err());
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    error::KyberLibError,
+    indcpa::{gen_at, indcpa_enc_expanded},
+    kem::decrypt_with,
+    kex::{Decapsulated, PublicKey, SecretKey},
+    params::*,
+    poly::{poly_frombytes, poly_tobytes},
+    polyvec::Polyvec,
+    symmetric::labeled_kdf,
+    verify::verify,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+use zeroize::Zeroize;
+
+/// Current version of the cache format.
+pub const CACHE_FORMAT_VERSION: u8 = 1;
+/// Size in bytes of the cache header.
+pub const CACHE_HEADER_BYTES: usize = 4;
+/// Size in bytes of the packed matrix in a cache.
+pub const CACHE_MATRIX_BYTES: usize =
+    KYBER_SECURITY_PARAMETER * KYBER_POLYVEC_BYTES;
+/// Size in bytes of the cache tag.
+pub const CACHE_TAG_BYTES: usize = 32;
+
+const CACHE_LABEL: &str = "kyberlib expanded key cache v1";
+const FLAG_MATRIX: u8 = 1;
+
+// Offset of z within a cache.
+const Z_OFFSET: usize =
+    CACHE_HEADER_BYTES + KYBER_SECRET_KEY_BYTES - KYBER_SYM_BYTES;
+
+/// A secret key with its matrix `A^T` expanded; see the
+/// [`expanded`](crate::expanded) module.
+///
+/// Decapsulation yields the shared secret that
+/// [`decapsulate`](crate::decapsulate) yields with the secret key. The
+/// IND-CPA decryption runs on the backend
+/// [`acceleration`](crate::acceleration()) reports, and the re-encryption
+/// on the reference code, the one taking the matrix precomputed. The
+/// secret key is zeroed on drop.
+pub struct ExpandedSecretKey {
+    secret: SecretKey,
+    matrix: Box<[Polyvec; KYBER_SECURITY_PARAMETER]>,
+}
+
+impl fmt::Debug for ExpandedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpandedSecretKey").finish_non_exhaustive()
+    }
+}
+
+impl Drop for ExpandedSecretKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl ExpandedSecretKey {
+    /// Expands the matrix of `secret`.
+    pub fn new(secret: &SecretKey) -> Self {
+        let mut matrix =
+            Box::new([Polyvec::new(); KYBER_SECURITY_PARAMETER]);
+        gen_at(&mut matrix[..], seed(secret));
+        Self {
+            secret: *secret,
+            matrix,
+        }
+    }
+
+    /// Returns the public key of the secret key.
+    pub fn public_key(&self) -> PublicKey {
+        crate::public(&self.secret)
+    }
+
+    /// Decapsulates `ct` without expanding the matrix.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if `ct` is not
+    /// `KYBER_CIPHERTEXT_BYTES` long.
+    #[must_use = "the shared secret is lost if the result is dropped"]
+    pub fn decapsulate(&self, ct: &[u8]) -> Decapsulated {
+        if ct.len() != KYBER_CIPHERTEXT_BYTES {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let (sk_cpa, rest) =
+            self.secret.split_at(KYBER_INDCPA_SECRET_KEY_BYTES);
+        let (pk, rest) = rest.split_at(KYBER_INDCPA_PUBLIC_KEY_BYTES);
+        let (h_pk, z) = rest.split_at(KYBER_SYM_BYTES);
+        let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+        let _ =
+            decrypt_with(&mut ss, ct, sk_cpa, h_pk, z, 0, |c, m, r| {
+                indcpa_enc_expanded(c, m, pk, &self.matrix[..], r)
+            });
+        Ok(ss)
+    }
+
+    /// Serializes the key in the cache format of the
+    /// [`expanded`](crate::expanded) module.
+    ///
+    /// Without `include_matrix` the cache is the secret key, framed and
+    /// tagged, and loading it expands the matrix again: it trades
+    /// `CACHE_MATRIX_BYTES` of storage for the expansion at load time.
+    pub fn to_cache_bytes(&self, include_matrix: bool) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            CACHE_HEADER_BYTES
+                + KYBER_SECRET_KEY_BYTES
+                + CACHE_MATRIX_BYTES
+                + CACHE_TAG_BYTES,
+        );
+        bytes.extend_from_slice(&[
+            CACHE_FORMAT_VERSION,
+            KYBER_SECURITY_PARAMETER as u8,
+            u8::from(KYBER_90S),
+            if include_matrix { FLAG_MATRIX } else { 0 },
+        ]);
+        bytes.extend_from_slice(&self.secret);
+        if include_matrix {
+            let mut packed = [0u8; KYBER_POLY_BYTES];
+            for a in self.matrix.iter().flat_map(|row| row.vec.iter()) {
+                poly_tobytes(&mut packed, *a);
+                bytes.extend_from_slice(&packed);
+            }
+        }
+        let tag = cache_tag(&bytes);
+        bytes.extend_from_slice(&tag);
+        bytes
+    }
+
+    /// Loads a key serialized by [`ExpandedSecretKey::to_cache_bytes`].
+    ///
+    /// The header must name this version of the format and the parameter
+    /// set the crate is built for, and the tag must verify. The matrix
+    /// is unpacked if the cache includes it and expanded otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if the header records an
+    /// unknown version or flag or another parameter set,
+    /// `KyberLibError::InvalidLength` if `bytes` has the wrong size for
+    /// it and `KyberLibError::InvalidKey` if the tag does not verify.
+    pub fn from_cache_bytes(
+        bytes: &[u8],
+    ) -> Result<Self, KyberLibError> {
+        if bytes.len() < CACHE_HEADER_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        if bytes[0] != CACHE_FORMAT_VERSION
+            || bytes[1] as usize != KYBER_SECURITY_PARAMETER
+            || bytes[2] != u8::from(KYBER_90S)
+            || bytes[3] & !FLAG_MATRIX != 0
+        {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let include_matrix = bytes[3] == FLAG_MATRIX;
+        let body = CACHE_HEADER_BYTES
+            + KYBER_SECRET_KEY_BYTES
+            + if include_matrix {
+                CACHE_MATRIX_BYTES
+            } else {
+                0
+            };
+        if bytes.len() != body + CACHE_TAG_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let tag = cache_tag(&bytes[..body]);
+        if verify(&tag, &bytes[body..], CACHE_TAG_BYTES) != 0 {
+            return Err(KyberLibError::InvalidKey);
+        }
+
+        let mut secret = [0u8; KYBER_SECRET_KEY_BYTES];
+        secret.copy_from_slice(
+            &bytes[CACHE_HEADER_BYTES..][..KYBER_SECRET_KEY_BYTES],
+        );
+        if !include_matrix {
+            let key = Self::new(&secret);
+            secret.zeroize();
+            return Ok(key);
+        }
+        let mut matrix =
+            Box::new([Polyvec::new(); KYBER_SECURITY_PARAMETER]);
+        let packed = bytes
+            [CACHE_HEADER_BYTES + KYBER_SECRET_KEY_BYTES..]
+            .chunks_exact(KYBER_POLY_BYTES);
+        for (a, p) in matrix
+            .iter_mut()
+            .flat_map(|row| row.vec.iter_mut())
+            .zip(packed)
+        {
+            poly_frombytes(a, p);
+        }
+        Ok(Self { secret, matrix })
+    }
+}
+
+// The seed of A, at the end of the public key within the secret key.
+fn seed(secret: &SecretKey) -> &[u8] {
+    &secret[KYBER_INDCPA_SECRET_KEY_BYTES + KYBER_POLYVEC_BYTES..]
+        [..KYBER_SYM_BYTES]
+}
+
+// The MAC over `body`, keyed with the z it holds.
+fn cache_tag(body: &[u8]) -> [u8; CACHE_TAG_BYTES] {
+    let mut tag = [0u8; CACHE_TAG_BYTES];
+    let z = &body[Z_OFFSET..Z_OFFSET + KYBER_SYM_BYTES];
+    labeled_kdf(&mut tag, CACHE_LABEL, &[z, body]);
+    tag
+}
@@ -0,0 +1,182 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! C ABI mirroring the reference `crypto_kem_*` API.
+//!
+//! The functions take caller-allocated buffers of the sizes given by the
+//! `KYBER_*_BYTES` constants and return `0` on success. Randomness is drawn
+//! from the operating system through `getrandom`, or from the callback
+//! installed with [`kyber_set_randombytes`]. The matching C declarations
+//! are:
+//!
+//! ```c
+//! int kyber_keypair(unsigned char *pk, unsigned char *sk);
+//! int kyber_enc(unsigned char *ct, unsigned char *ss, const unsigned char *pk);
+//! int kyber_dec(unsigned char *ss, const unsigned char *ct, const unsigned char *sk);
+//! void kyber_set_randombytes(int (*cb)(unsigned char *buf, size_t len));
+//! ```
+
+use crate::{kem::*, params::*, rng::RetryRng};
+use core::ffi::c_int;
+use core::slice;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use rand::rngs::OsRng;
+use rand_core::{CryptoRng, RngCore};
+
+/// Returned on success.
+pub const KYBER_OK: c_int = 0;
+/// Returned when a buffer pointer is null.
+pub const KYBER_ERROR_NULL_POINTER: c_int = -1;
+/// Returned when the operating system or the callback installed with
+/// [`kyber_set_randombytes`] fails to provide random bytes.
+pub const KYBER_ERROR_RANDOMNESS: c_int = -2;
+
+/// A host RNG filling `len` bytes at `buf` and returning `0` on success,
+/// see [`kyber_set_randombytes`].
+pub type KyberRandomBytes = extern "C" fn(*mut u8, usize) -> c_int;
+
+static RANDOMBYTES: AtomicPtr<()> =
+    AtomicPtr::new(core::ptr::null_mut());
+
+/// Makes [`kyber_keypair`] and [`kyber_enc`] draw their randomness from
+/// `cb` instead of the operating system; `NULL` switches back.
+///
+/// The setting is process-wide and takes effect for calls starting after
+/// it returns, so install the callback before other threads use the
+/// library. A nonzero return from `cb` fails the call with
+/// `KYBER_ERROR_RANDOMNESS`; unlike the operating system RNG, the callback
+/// is not retried. The host is trusted to provide cryptographically secure
+/// bytes.
+#[no_mangle]
+pub extern "C" fn kyber_set_randombytes(cb: Option<KyberRandomBytes>) {
+    let ptr = cb.map_or(core::ptr::null_mut(), |cb| cb as *mut ());
+    RANDOMBYTES.store(ptr, Ordering::Release);
+}
+
+fn randombytes_callback() -> Option<KyberRandomBytes> {
+    let cb = RANDOMBYTES.load(Ordering::Acquire);
+    if cb.is_null() {
+        return None;
+    }
+    // SAFETY: the only non-null value ever stored is a
+    // `KyberRandomBytes` cast to a pointer in `kyber_set_randombytes`,
+    // as for `rng::register_entropy_source`.
+    Some(unsafe {
+        core::mem::transmute::<*mut (), KyberRandomBytes>(cb)
+    })
+}
+
+// The installed callback, or the retried `OsRng` without one.
+struct FfiRng;
+
+impl RngCore for FfiRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(err) = self.try_fill_bytes(dest) {
+            panic!("host RNG failed: {}", err);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        match randombytes_callback() {
+            Some(cb) if cb(dest.as_mut_ptr(), dest.len()) == 0 => {
+                Ok(())
+            }
+            Some(_) => Err(HOST_RNG_FAILED.into()),
+            None => RetryRng(OsRng).try_fill_bytes(dest),
+        }
+    }
+}
+
+impl CryptoRng for FfiRng {}
+
+const HOST_RNG_FAILED: core::num::NonZeroU32 =
+    match core::num::NonZeroU32::new(rand_core::Error::CUSTOM_START) {
+        Some(code) => code,
+        None => unreachable!(),
+    };
+
+/// Generates a keypair into `pk` and `sk`.
+///
+/// # Safety
+///
+/// `pk` must be valid for writes of `KYBER_PUBLIC_KEY_BYTES` bytes and `sk`
+/// for writes of `KYBER_SECRET_KEY_BYTES` bytes, and the buffers must not
+/// overlap.
+#[no_mangle]
+pub unsafe extern "C" fn kyber_keypair(
+    pk: *mut u8,
+    sk: *mut u8,
+) -> c_int {
+    if pk.is_null() || sk.is_null() {
+        return KYBER_ERROR_NULL_POINTER;
+    }
+    let pk = slice::from_raw_parts_mut(pk, KYBER_PUBLIC_KEY_BYTES);
+    let sk = slice::from_raw_parts_mut(sk, KYBER_SECRET_KEY_BYTES);
+    match generate_key_pair(pk, sk, &mut FfiRng, None) {
+        Ok(()) => KYBER_OK,
+        Err(_) => KYBER_ERROR_RANDOMNESS,
+    }
+}
+
+/// Encapsulates to `pk`, writing the ciphertext to `ct` and the shared
+/// secret to `ss`.
+///
+/// # Safety
+///
+/// `ct` must be valid for writes of `KYBER_CIPHERTEXT_BYTES` bytes, `ss`
+/// for writes of `KYBER_SHARED_SECRET_BYTES` bytes and `pk` for reads of
+/// `KYBER_PUBLIC_KEY_BYTES` bytes, and the buffers must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn kyber_enc(
+    ct: *mut u8,
+    ss: *mut u8,
+    pk: *const u8,
+) -> c_int {
+    if ct.is_null() || ss.is_null() || pk.is_null() {
+        return KYBER_ERROR_NULL_POINTER;
+    }
+    let ct = slice::from_raw_parts_mut(ct, KYBER_CIPHERTEXT_BYTES);
+    let ss = slice::from_raw_parts_mut(ss, KYBER_SHARED_SECRET_BYTES);
+    let pk = slice::from_raw_parts(pk, KYBER_PUBLIC_KEY_BYTES);
+    match encrypt_message(ct, ss, pk, &mut FfiRng, None) {
+        Ok(()) => KYBER_OK,
+        Err(_) => KYBER_ERROR_RANDOMNESS,
+    }
+}
+
+/// Decapsulates `ct` with `sk`, writing the shared secret to `ss`.
+///
+/// As with the reference implementation, a ciphertext that does not
+/// decapsulate yields a pseudo-random shared secret rather than an error.
+///
+/// # Safety
+///
+/// `ss` must be valid for writes of `KYBER_SHARED_SECRET_BYTES` bytes, `ct`
+/// for reads of `KYBER_CIPHERTEXT_BYTES` bytes and `sk` for reads of
+/// `KYBER_SECRET_KEY_BYTES` bytes, and `ss` must not overlap the inputs.
+#[no_mangle]
+pub unsafe extern "C" fn kyber_dec(
+    ss: *mut u8,
+    ct: *const u8,
+    sk: *const u8,
+) -> c_int {
+    if ss.is_null() || ct.is_null() || sk.is_null() {
+        return KYBER_ERROR_NULL_POINTER;
+    }
+    let ss = slice::from_raw_parts_mut(ss, KYBER_SHARED_SECRET_BYTES);
+    let ct = slice::from_raw_parts(ct, KYBER_CIPHERTEXT_BYTES);
+    let sk = slice::from_raw_parts(sk, KYBER_SECRET_KEY_BYTES);
+    decrypt_message(ss, ct, sk);
+    KYBER_OK
+}
@@ -0,0 +1,438 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Fragmentation of ciphertexts for datagram transports with a small MTU.
+//!
+//! A Kyber1024 ciphertext is 1568 bytes, more than many datagram
+//! transports carry. [`fragment`] splits it into evenly sized fragments
+//! that each fit in `mtu` bytes, and a [`Reassembler`] puts them back
+//! together in a caller-provided buffer, in any order. Every fragment
+//! starts with a fixed header:
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 4     | message id, big-endian |
+//! | 1     | fragment index |
+//! | 1     | fragment count |
+//! | 2     | message length, big-endian |
+//! | 2     | CRC-16/CCITT-FALSE of the preceding header bytes and the payload |
+//!
+//! The checksum catches corruption in transit, not tampering: a forged
+//! ciphertext is handled by the KEM's implicit rejection as usual.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::fragment::*;
+//! # fn main() -> Result<(), FragmentError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng).unwrap();
+//! let (ct, _) = encapsulate(&keys.public, &mut rng).unwrap();
+//!
+//! let mut buffer = [0u8; KYBER_CIPHERTEXT_BYTES];
+//! let mut reassembler = Reassembler::new(&mut buffer, 30);
+//! let mut datagram = [0u8; 1200];
+//! let mut received = None;
+//! for fragment in fragment(&ct, 7, 1200)?.rev() {
+//!     let len = fragment.encode(&mut datagram)?;
+//!     if let Some(message) = reassembler.accept(&datagram[..len], 0)? {
+//!         received = Some(message.to_vec());
+//!     }
+//! }
+//! assert_eq!(received.as_deref(), Some(&ct[..]));
+//! # Ok(()) }
+//! ```
+
+use core::fmt;
+
+/// Size in bytes of the fragment header.
+pub const FRAGMENT_HEADER_BYTES: usize = 10;
+
+/// Most fragments a message can be split into.
+pub const MAX_FRAGMENTS: usize = u8::MAX as usize;
+
+/// Failures when fragmenting or reassembling a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FragmentError {
+    /// The MTU leaves no room for payload after the header.
+    MtuTooSmall,
+    /// The message is empty, longer than 65535 bytes or needs more than
+    /// `MAX_FRAGMENTS` fragments at this MTU.
+    TooLarge,
+    /// The output or reassembly buffer is too small.
+    BufferTooSmall,
+    /// The datagram is not a well-formed fragment.
+    Malformed,
+    /// The datagram was corrupted in transit.
+    Checksum,
+    /// The fragment was already received.
+    Duplicate,
+    /// The fragment belongs to another message than the one being
+    /// reassembled.
+    OtherMessage,
+}
+
+impl fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FragmentError::MtuTooSmall => {
+                write!(f, "MTU is too small for the fragment header")
+            }
+            FragmentError::TooLarge => {
+                write!(f, "Message cannot be fragmented at this MTU")
+            }
+            FragmentError::BufferTooSmall => {
+                write!(f, "Buffer is too small")
+            }
+            FragmentError::Malformed => write!(f, "Malformed fragment"),
+            FragmentError::Checksum => {
+                write!(f, "Fragment checksum does not match")
+            }
+            FragmentError::Duplicate => write!(f, "Duplicate fragment"),
+            FragmentError::OtherMessage => {
+                write!(f, "Fragment belongs to another message")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FragmentError {}
+
+/// One fragment of a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fragment<'a> {
+    message_id: u32,
+    index: u8,
+    count: u8,
+    total: u16,
+    payload: &'a [u8],
+}
+
+impl<'a> Fragment<'a> {
+    /// Parses a received datagram.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FragmentError::Malformed` if the header is truncated or
+    /// inconsistent and `FragmentError::Checksum` if the datagram was
+    /// corrupted.
+    pub fn decode(datagram: &'a [u8]) -> Result<Self, FragmentError> {
+        if datagram.len() <= FRAGMENT_HEADER_BYTES {
+            return Err(FragmentError::Malformed);
+        }
+        let (header, payload) =
+            datagram.split_at(FRAGMENT_HEADER_BYTES);
+        let checksum = u16::from_be_bytes([header[8], header[9]]);
+        if crc16(&header[..8], payload) != checksum {
+            return Err(FragmentError::Checksum);
+        }
+        let fragment = Self {
+            message_id: u32::from_be_bytes([
+                header[0], header[1], header[2], header[3],
+            ]),
+            index: header[4],
+            count: header[5],
+            total: u16::from_be_bytes([header[6], header[7]]),
+            payload,
+        };
+        if fragment.count == 0
+            || fragment.index >= fragment.count
+            || payload.len() != fragment.expected_len()
+        {
+            return Err(FragmentError::Malformed);
+        }
+        Ok(fragment)
+    }
+
+    /// Writes the fragment to `out` and returns the number of bytes
+    /// written, at most the MTU it was created for.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FragmentError::BufferTooSmall` if `out` is shorter than
+    /// [`Fragment::len`].
+    pub fn encode(
+        &self,
+        out: &mut [u8],
+    ) -> Result<usize, FragmentError> {
+        let len = self.len();
+        if out.len() < len {
+            return Err(FragmentError::BufferTooSmall);
+        }
+        out[..4].copy_from_slice(&self.message_id.to_be_bytes());
+        out[4] = self.index;
+        out[5] = self.count;
+        out[6..8].copy_from_slice(&self.total.to_be_bytes());
+        let checksum = crc16(&out[..8], self.payload);
+        out[8..10].copy_from_slice(&checksum.to_be_bytes());
+        out[FRAGMENT_HEADER_BYTES..len].copy_from_slice(self.payload);
+        Ok(len)
+    }
+
+    /// The size in bytes of the encoded fragment.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        FRAGMENT_HEADER_BYTES + self.payload.len()
+    }
+
+    /// The id of the message this fragment belongs to.
+    pub fn message_id(&self) -> u32 {
+        self.message_id
+    }
+
+    /// The position of this fragment in the message.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// The number of fragments in the message.
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    /// The part of the message this fragment carries.
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    /// Bytes per fragment; the last one may be shorter.
+    fn chunk(&self) -> usize {
+        chunk(self.total as usize, self.count as usize)
+    }
+
+    fn expected_len(&self) -> usize {
+        let chunk = self.chunk();
+        let offset = self.index as usize * chunk;
+        (self.total as usize).saturating_sub(offset).min(chunk)
+    }
+}
+
+/// The fragments of a message, returned by [`fragment`].
+#[derive(Clone, Debug)]
+pub struct Fragments<'a> {
+    message: &'a [u8],
+    message_id: u32,
+    count: usize,
+    chunk: usize,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Fragments<'a> {
+    fn get(&self, index: usize) -> Fragment<'a> {
+        let start = index * self.chunk;
+        let end = (start + self.chunk).min(self.message.len());
+        Fragment {
+            message_id: self.message_id,
+            index: index as u8,
+            count: self.count as u8,
+            total: self.message.len() as u16,
+            payload: &self.message[start..end],
+        }
+    }
+}
+
+impl<'a> Iterator for Fragments<'a> {
+    type Item = Fragment<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.get(self.front - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Fragments<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.get(self.back))
+    }
+}
+
+impl ExactSizeIterator for Fragments<'_> {}
+
+/// Splits `message`, usually a ciphertext, into fragments of at most
+/// `mtu` bytes each, header included.
+///
+/// The fragments are as even in size as possible. `message_id` should be
+/// unique among the messages a receiver may have in flight.
+///
+/// # Errors
+///
+/// Returns `FragmentError::MtuTooSmall` if `mtu` leaves no room for
+/// payload and `FragmentError::TooLarge` if `message` is empty, longer
+/// than 65535 bytes or needs more than `MAX_FRAGMENTS` fragments.
+pub fn fragment(
+    message: &[u8],
+    message_id: u32,
+    mtu: usize,
+) -> Result<Fragments<'_>, FragmentError> {
+    if mtu <= FRAGMENT_HEADER_BYTES {
+        return Err(FragmentError::MtuTooSmall);
+    }
+    let total = message.len();
+    if total == 0 || total > u16::MAX as usize {
+        return Err(FragmentError::TooLarge);
+    }
+    let count = total.div_ceil(mtu - FRAGMENT_HEADER_BYTES);
+    if count > MAX_FRAGMENTS {
+        return Err(FragmentError::TooLarge);
+    }
+    Ok(Fragments {
+        message,
+        message_id,
+        count,
+        chunk: chunk(total, count),
+        front: 0,
+        back: count,
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Pending {
+    message_id: u32,
+    count: u8,
+    total: u16,
+    started: u64,
+    received: [u64; 4],
+    remaining: usize,
+}
+
+/// Reassembles one message at a time from its fragments.
+///
+/// Memory use is bounded by the caller's buffer. An incomplete message is
+/// dropped once `timeout` time units have passed since its first fragment,
+/// in whatever unit the caller passes as `now`.
+#[derive(Debug)]
+pub struct Reassembler<'a> {
+    buffer: &'a mut [u8],
+    timeout: u64,
+    pending: Option<Pending>,
+    completed: Option<u32>,
+}
+
+impl<'a> Reassembler<'a> {
+    /// Creates a reassembler for messages of up to `buffer.len()` bytes.
+    pub fn new(buffer: &'a mut [u8], timeout: u64) -> Self {
+        Self {
+            buffer,
+            timeout,
+            pending: None,
+            completed: None,
+        }
+    }
+
+    /// Accepts a received datagram and returns the message once its last
+    /// missing fragment arrives.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Fragment::decode`],
+    /// `FragmentError::Duplicate` for a fragment already received, also of
+    /// the message completed last, `FragmentError::OtherMessage` for a
+    /// fragment of another message while one is pending, and
+    /// `FragmentError::BufferTooSmall` if the message does not fit the
+    /// buffer. The pending message is kept in every case.
+    pub fn accept(
+        &mut self,
+        datagram: &[u8],
+        now: u64,
+    ) -> Result<Option<&[u8]>, FragmentError> {
+        let fragment = Fragment::decode(datagram)?;
+        let _ = self.expire(now);
+        if self.completed == Some(fragment.message_id) {
+            return Err(FragmentError::Duplicate);
+        }
+        let pending = match &mut self.pending {
+            Some(p) if p.message_id != fragment.message_id => {
+                return Err(FragmentError::OtherMessage)
+            }
+            Some(p)
+                if p.count != fragment.count
+                    || p.total != fragment.total =>
+            {
+                return Err(FragmentError::Malformed)
+            }
+            Some(p) => p,
+            None => {
+                if fragment.total as usize > self.buffer.len() {
+                    return Err(FragmentError::BufferTooSmall);
+                }
+                self.pending.insert(Pending {
+                    message_id: fragment.message_id,
+                    count: fragment.count,
+                    total: fragment.total,
+                    started: now,
+                    received: [0; 4],
+                    remaining: fragment.count as usize,
+                })
+            }
+        };
+
+        let (word, bit) =
+            (fragment.index as usize / 64, fragment.index % 64);
+        if pending.received[word] & (1 << bit) != 0 {
+            return Err(FragmentError::Duplicate);
+        }
+        pending.received[word] |= 1 << bit;
+        pending.remaining -= 1;
+        let offset = fragment.index as usize * fragment.chunk();
+        self.buffer[offset..offset + fragment.payload.len()]
+            .copy_from_slice(fragment.payload);
+
+        if pending.remaining > 0 {
+            return Ok(None);
+        }
+        let total = pending.total as usize;
+        self.completed = Some(pending.message_id);
+        self.pending = None;
+        Ok(Some(&self.buffer[..total]))
+    }
+
+    /// Drops the pending message if it timed out, returning whether it did.
+    pub fn expire(&mut self, now: u64) -> bool {
+        match self.pending {
+            Some(p) if now.saturating_sub(p.started) > self.timeout => {
+                self.pending = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The id of the message being reassembled, if any.
+    pub fn pending_message_id(&self) -> Option<u32> {
+        self.pending.map(|p| p.message_id)
+    }
+}
+
+fn chunk(total: usize, count: usize) -> usize {
+    total.div_ceil(count)
+}
+
+/// CRC-16/CCITT-FALSE over `header` followed by `payload`.
+fn crc16(header: &[u8], payload: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &byte in header.iter().chain(payload) {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,129 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Scalar and SIMD kernels side by side, for the differential fuzz
+//! targets in `fuzz/`. Built with `--cfg fuzzing`, which cargo-fuzz
+//! sets, and in the crate's own tests. Not part of the public API.
+//!
+//! The `_simd` functions run the AVX2 kernel when the backend is built
+//! and the CPU supports it, otherwise the portable SIMD one when that is
+//! built, and return `None` when neither is available.
+
+use crate::params::*;
+use crate::reference::poly::{self as scalar, Poly};
+
+/// Compresses `coeffs`, each in `0..q`, with the reference
+/// poly_compress.
+pub fn poly_compress_reference(
+    coeffs: &[i16; KYBER_N],
+) -> [u8; KYBER_POLY_COMPRESSED_BYTES] {
+    let mut a = Poly::new();
+    a.coeffs = *coeffs;
+    let mut r = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+    scalar::poly_compress(&mut r, a);
+    r
+}
+
+/// Compresses `coeffs`, each in `0..q`, with a SIMD poly_compress.
+#[allow(unreachable_code, unused_variables)] // without a SIMD backend
+pub fn poly_compress_simd(
+    coeffs: &[i16; KYBER_N],
+) -> Option<[u8; KYBER_POLY_COMPRESSED_BYTES]> {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    {
+        if !crate::avx2::supported() {
+            return None;
+        }
+        let a = crate::avx2::poly::Poly { coeffs: *coeffs };
+        let mut r = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+        // SAFETY: the CPU supports the AVX2 backend.
+        unsafe { crate::avx2::poly::poly_compress(&mut r, a) };
+        return Some(r);
+    }
+    #[cfg(all(
+        feature = "portable-simd",
+        not(all(target_arch = "x86_64", feature = "avx2"))
+    ))]
+    {
+        let mut a = Poly::new();
+        a.coeffs = *coeffs;
+        let mut r = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+        crate::portable_simd::poly::poly_compress(&mut r, a);
+        return Some(r);
+    }
+    None
+}
+
+/// Decompresses `bytes` with the reference poly_decompress.
+pub fn poly_decompress_reference(
+    bytes: &[u8; KYBER_POLY_COMPRESSED_BYTES],
+) -> [i16; KYBER_N] {
+    let mut r = Poly::new();
+    scalar::poly_decompress(&mut r, bytes);
+    r.coeffs
+}
+
+/// Decompresses `bytes` with a SIMD poly_decompress. Only the AVX2
+/// backend has one; the portable SIMD backend uses the reference code.
+#[allow(unused_variables)] // without the AVX2 backend
+pub fn poly_decompress_simd(
+    bytes: &[u8; KYBER_POLY_COMPRESSED_BYTES],
+) -> Option<[i16; KYBER_N]> {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    if crate::avx2::supported() {
+        let mut r = crate::avx2::poly::Poly::new();
+        // SAFETY: the CPU supports the AVX2 backend, and both union
+        // fields cover the same bytes.
+        return Some(unsafe {
+            crate::avx2::poly::poly_decompress(&mut r, bytes);
+            r.coeffs
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The seed corpus of the fuzz target, as polynomials.
+    fn edge_cases() -> [[i16; KYBER_N]; 5] {
+        let q = KYBER_Q as i16;
+        let mut alternating = [0; KYBER_N];
+        let mut ramp = [0; KYBER_N];
+        let mut boundaries = [0; KYBER_N];
+        // The last value rounding down to each compressed value and the
+        // first rounding up to the next, (2k + 1) q / 2^(d + 1).
+        let steps = 2 << KYBER_DV;
+        for (i, c) in boundaries.iter_mut().enumerate() {
+            let k = (i / 2) % (steps / 2);
+            let up = ((2 * k + 1) * KYBER_Q).div_ceil(steps);
+            *c = (up - 1 + i % 2) as i16;
+        }
+        for i in 0..KYBER_N {
+            alternating[i] = if i % 2 == 0 { 0 } else { q - 1 };
+            ramp[i] = (i * KYBER_Q / KYBER_N) as i16;
+        }
+        [
+            [0; KYBER_N],
+            [q - 1; KYBER_N],
+            alternating,
+            ramp,
+            boundaries,
+        ]
+    }
+
+    #[test]
+    fn simd_compression_matches_reference_on_edge_cases() {
+        for coeffs in edge_cases().iter() {
+            let bytes = poly_compress_reference(coeffs);
+            if let Some(simd) = poly_compress_simd(coeffs) {
+                assert_eq!(simd, bytes);
+            }
+            let back = poly_decompress_reference(&bytes);
+            if let Some(simd) = poly_decompress_simd(&bytes) {
+                assert_eq!(simd, back);
+            }
+        }
+    }
+}
@@ -0,0 +1,493 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! JWE key encapsulation (RFC 7516) for Kyber recipients.
+//!
+//! This module implements only the key-management step of JWE. Given the
+//! recipient's [`Jwk`], the sender obtains the JOSE header parameters to
+//! merge into the protected header, the JWE Encrypted Key and the
+//! content-encryption key (CEK). Content encryption with `enc` is left to
+//! the caller's JOSE library.
+//!
+//! Two key-management modes are offered per security level, following the
+//! shape of `ECDH-ES` in RFC 7518:
+//!
+//! * direct key agreement (`MLKEM1024`): the CEK is derived from the shared
+//!   secret and the JWE Encrypted Key is empty;
+//! * key agreement with key wrapping (`MLKEM1024+A256KW`): a random CEK is
+//!   wrapped with AES key wrap under a key derived from the shared secret.
+//!
+//! In both cases the derivation is the Concat KDF with SHA-256 of RFC 7518,
+//! section 4.6.2, with the `enc` or `alg` value as `AlgorithmID` and empty
+//! `PartyUInfo` and `PartyVInfo`. The KEM ciphertext travels in the `ek`
+//! header parameter.
+//!
+//! Note that this crate implements the CRYSTALS-Kyber submission rather
+//! than FIPS 203, so objects produced here are only interoperable with
+//! kyberlib.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::jose::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let jwk = Jwk::from_public_key(&keys.public, Some("recipient"));
+//!
+//! let sent = JweKeyEncapsulation::new(&jwk, KEY_WRAP_ALG, "A256GCM", &mut rng)?;
+//! let header = JweHeader::from_json(&sent.header.to_json())?;
+//!
+//! let cek = header.unwrap_cek(&sent.encrypted_key, &keys.secret)?;
+//! assert_eq!(cek, sent.cek);
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::{decapsulate, encapsulate},
+    error::KyberLibError,
+    kem::check_public_key,
+    kex::{PublicKey, SecretKey},
+    params::*,
+    CryptoRng, RngCore,
+};
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use core::convert::TryFrom;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+/// JWK key type of Kyber public keys.
+pub const JWK_KEY_TYPE: &str = "AKP";
+
+/// JWK `alg` of a Kyber public key at the active security level.
+pub const JWK_KEY_ALG: &str = key_alg(KYBER_SECURITY_PARAMETER);
+
+/// Direct key agreement at the active security level.
+pub const DIRECT_ALG: JweAlgorithm = match KYBER_SECURITY_PARAMETER {
+    2 => JweAlgorithm::MlKem512,
+    3 => JweAlgorithm::MlKem768,
+    _ => JweAlgorithm::MlKem1024,
+};
+
+/// Key agreement with key wrapping at the active security level.
+pub const KEY_WRAP_ALG: JweAlgorithm = match KYBER_SECURITY_PARAMETER {
+    2 => JweAlgorithm::MlKem512A128Kw,
+    3 => JweAlgorithm::MlKem768A192Kw,
+    _ => JweAlgorithm::MlKem1024A256Kw,
+};
+
+const fn key_alg(security_parameter: usize) -> &'static str {
+    match security_parameter {
+        2 => "ML-KEM-512",
+        3 => "ML-KEM-768",
+        _ => "ML-KEM-1024",
+    }
+}
+
+/// JWE `alg` values for Kyber key management.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JweAlgorithm {
+    /// Direct key agreement with Kyber512.
+    MlKem512,
+    /// Direct key agreement with Kyber768.
+    MlKem768,
+    /// Direct key agreement with Kyber1024.
+    MlKem1024,
+    /// Kyber512 key agreement with AES-128 key wrap.
+    MlKem512A128Kw,
+    /// Kyber768 key agreement with AES-192 key wrap.
+    MlKem768A192Kw,
+    /// Kyber1024 key agreement with AES-256 key wrap.
+    MlKem1024A256Kw,
+}
+
+impl JweAlgorithm {
+    /// The `alg` header value.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::MlKem512 => "MLKEM512",
+            Self::MlKem768 => "MLKEM768",
+            Self::MlKem1024 => "MLKEM1024",
+            Self::MlKem512A128Kw => "MLKEM512+A128KW",
+            Self::MlKem768A192Kw => "MLKEM768+A192KW",
+            Self::MlKem1024A256Kw => "MLKEM1024+A256KW",
+        }
+    }
+
+    /// Parses an `alg` header value, returning `None` for other algorithms.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "MLKEM512" => Self::MlKem512,
+            "MLKEM768" => Self::MlKem768,
+            "MLKEM1024" => Self::MlKem1024,
+            "MLKEM512+A128KW" => Self::MlKem512A128Kw,
+            "MLKEM768+A192KW" => Self::MlKem768A192Kw,
+            "MLKEM1024+A256KW" => Self::MlKem1024A256Kw,
+            _ => return None,
+        })
+    }
+
+    /// The security parameter `k` of the key this algorithm requires.
+    pub const fn security_parameter(self) -> usize {
+        match self {
+            Self::MlKem512 | Self::MlKem512A128Kw => 2,
+            Self::MlKem768 | Self::MlKem768A192Kw => 3,
+            Self::MlKem1024 | Self::MlKem1024A256Kw => 4,
+        }
+    }
+
+    /// Length in bytes of the AES key-wrap key, or `None` for direct key
+    /// agreement.
+    pub const fn key_wrap_bytes(self) -> Option<usize> {
+        match self {
+            Self::MlKem512 | Self::MlKem768 | Self::MlKem1024 => None,
+            Self::MlKem512A128Kw => Some(16),
+            Self::MlKem768A192Kw => Some(24),
+            Self::MlKem1024A256Kw => Some(32),
+        }
+    }
+
+    fn check_level(self) -> Result<(), KyberLibError> {
+        if self.security_parameter() == KYBER_SECURITY_PARAMETER {
+            Ok(())
+        } else {
+            Err(KyberLibError::InvalidKey)
+        }
+    }
+}
+
+/// Length in bytes of the CEK for the registered `enc` values of RFC 7518.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` for unknown values.
+pub fn cek_bytes(enc: &str) -> Result<usize, KyberLibError> {
+    match enc {
+        "A128GCM" => Ok(16),
+        "A192GCM" => Ok(24),
+        "A256GCM" | "A128CBC-HS256" => Ok(32),
+        "A192CBC-HS384" => Ok(48),
+        "A256CBC-HS512" => Ok(64),
+        _ => Err(KyberLibError::InvalidInput),
+    }
+}
+
+/// A Kyber public key as a JSON Web Key.
+///
+/// The key is an `AKP` key whose `alg` names the security level and whose
+/// `pub` member holds the base64url-encoded public key. Members other than
+/// `kty`, `alg`, `pub` and `kid` are ignored when parsing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    kty: String,
+    alg: String,
+    #[serde(rename = "pub")]
+    public: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+impl Jwk {
+    /// Creates the JWK of `public_key`.
+    pub fn from_public_key(
+        public_key: &PublicKey,
+        kid: Option<&str>,
+    ) -> Self {
+        Self {
+            kty: JWK_KEY_TYPE.to_string(),
+            alg: JWK_KEY_ALG.to_string(),
+            public: Base64UrlUnpadded::encode_string(public_key),
+            kid: kid.map(str::to_string),
+        }
+    }
+
+    /// Decodes and validates the public key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if the key type, the `alg` or the
+    /// key itself do not match the active security level, and
+    /// `KyberLibError::InvalidInput` if `pub` is not valid base64url.
+    pub fn public_key(&self) -> Result<PublicKey, KyberLibError> {
+        if self.kty != JWK_KEY_TYPE || self.alg != JWK_KEY_ALG {
+            return Err(KyberLibError::InvalidKey);
+        }
+        let bytes = Base64UrlUnpadded::decode_vec(&self.public)
+            .map_err(|_| KyberLibError::InvalidInput)?;
+        let mut public_key = [0u8; KYBER_PUBLIC_KEY_BYTES];
+        if bytes.len() != public_key.len() {
+            return Err(KyberLibError::InvalidKey);
+        }
+        public_key.copy_from_slice(&bytes);
+        check_public_key(&public_key)?;
+        Ok(public_key)
+    }
+
+    /// The key type.
+    pub fn kty(&self) -> &str {
+        &self.kty
+    }
+
+    /// The key algorithm, which names the security level.
+    pub fn alg(&self) -> &str {
+        &self.alg
+    }
+
+    /// The key identifier, if any.
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    /// Encodes the key as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Parses a key from JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if `json` is not a JWK object.
+    pub fn from_json(json: &str) -> Result<Self, KyberLibError> {
+        serde_json::from_str(json)
+            .map_err(|_| KyberLibError::InvalidInput)
+    }
+}
+
+/// The key-management parameters of a JWE header.
+///
+/// These are meant to be merged into the JWE Protected Header by the
+/// caller's JOSE library. Members other than `alg`, `enc`, `ek` and `kid`
+/// are ignored when parsing, so a full protected header can be passed to
+/// [`JweHeader::from_json`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JweHeader {
+    alg: String,
+    enc: String,
+    ek: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+impl JweHeader {
+    /// The `alg` parameter.
+    pub fn alg(&self) -> &str {
+        &self.alg
+    }
+
+    /// The `enc` parameter.
+    pub fn enc(&self) -> &str {
+        &self.enc
+    }
+
+    /// The `ek` parameter, the base64url-encoded KEM ciphertext.
+    pub fn ek(&self) -> &str {
+        &self.ek
+    }
+
+    /// The `kid` parameter copied from the recipient's key, if any.
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    /// Encodes the parameters as a JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Parses the parameters from a JSON object.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if a member is missing or is
+    /// not a string.
+    pub fn from_json(json: &str) -> Result<Self, KyberLibError> {
+        serde_json::from_str(json)
+            .map_err(|_| KyberLibError::InvalidInput)
+    }
+
+    /// Recovers the CEK with the recipient's secret key.
+    ///
+    /// `encrypted_key` is the decoded JWE Encrypted Key, which must be
+    /// empty for direct key agreement.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if `alg` is for another security
+    /// level, `KyberLibError::InvalidInput` if `alg` or `enc` are unknown
+    /// or `encrypted_key` does not fit `alg`, `KyberLibError::InvalidLength`
+    /// if `ek` has the wrong size and `KyberLibError::Decapsulation` if the
+    /// wrapped key fails its integrity check.
+    pub fn unwrap_cek(
+        &self,
+        encrypted_key: &[u8],
+        secret: &SecretKey,
+    ) -> Result<Zeroizing<Vec<u8>>, KyberLibError> {
+        let alg = JweAlgorithm::from_name(&self.alg)
+            .ok_or(KyberLibError::InvalidInput)?;
+        alg.check_level()?;
+        let cek_len = cek_bytes(&self.enc)?;
+        let ciphertext = Base64UrlUnpadded::decode_vec(&self.ek)
+            .map_err(|_| KyberLibError::InvalidInput)?;
+        if ciphertext.len() != KYBER_CIPHERTEXT_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let expected = alg.key_wrap_bytes().map_or(0, |_| cek_len + 8);
+        if encrypted_key.len() != expected {
+            return Err(KyberLibError::InvalidInput);
+        }
+
+        let mut shared_secret = decapsulate(&ciphertext, secret)?;
+        let result = match alg.key_wrap_bytes() {
+            None => Ok(concat_kdf(&shared_secret, &self.enc, cek_len)),
+            Some(kek_len) => {
+                let kek =
+                    concat_kdf(&shared_secret, alg.name(), kek_len);
+                let mut cek = Zeroizing::new(alloc::vec![0u8; cek_len]);
+                unwrap_key(&kek, encrypted_key, &mut cek).map(|()| cek)
+            }
+        };
+        shared_secret.zeroize();
+        result
+    }
+}
+
+/// The sender's result of JWE key encapsulation.
+#[derive(Debug)]
+pub struct JweKeyEncapsulation {
+    /// Key-management parameters for the protected header.
+    pub header: JweHeader,
+    /// The JWE Encrypted Key, empty for direct key agreement.
+    pub encrypted_key: Vec<u8>,
+    /// The content-encryption key to use with `enc`.
+    pub cek: Zeroizing<Vec<u8>>,
+}
+
+impl JweKeyEncapsulation {
+    /// Encapsulates a fresh CEK for the holder of `recipient`.
+    ///
+    /// # Arguments
+    ///
+    /// * `recipient` - The recipient's public key.
+    /// * `alg` - The key-management algorithm, [`DIRECT_ALG`] or
+    ///   [`KEY_WRAP_ALG`].
+    /// * `enc` - The content-encryption algorithm, which fixes the CEK size.
+    /// * `rng` - The random number generator used for encapsulation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if `alg` and the key are not
+    /// both at the active security level, `KyberLibError::InvalidInput` if
+    /// `enc` is unknown and `KyberLibError::RandomBytesGeneration` if the
+    /// RNG fails.
+    pub fn new<R>(
+        recipient: &Jwk,
+        alg: JweAlgorithm,
+        enc: &str,
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        alg.check_level()?;
+        let public_key = recipient.public_key()?;
+        let cek_len = cek_bytes(enc)?;
+
+        let (ciphertext, mut shared_secret) =
+            encapsulate(&public_key, rng)?;
+        let result = match alg.key_wrap_bytes() {
+            None => Ok((
+                concat_kdf(&shared_secret, enc, cek_len),
+                Vec::new(),
+            )),
+            Some(kek_len) => {
+                let kek =
+                    concat_kdf(&shared_secret, alg.name(), kek_len);
+                let mut cek = Zeroizing::new(alloc::vec![0u8; cek_len]);
+                let mut encrypted_key = alloc::vec![0u8; cek_len + 8];
+                rng.try_fill_bytes(&mut cek)
+                    .map_err(|_| KyberLibError::RandomBytesGeneration)
+                    .and_then(|()| {
+                        wrap_key(&kek, &cek, &mut encrypted_key)
+                    })
+                    .map(|()| (cek, encrypted_key))
+            }
+        };
+        shared_secret.zeroize();
+        let (cek, encrypted_key) = result?;
+
+        Ok(Self {
+            header: JweHeader {
+                alg: alg.name().to_string(),
+                enc: enc.to_string(),
+                ek: Base64UrlUnpadded::encode_string(&ciphertext),
+                kid: recipient.kid.clone(),
+            },
+            encrypted_key,
+            cek,
+        })
+    }
+}
+
+// Concat KDF of RFC 7518, section 4.6.2, with empty party information.
+fn concat_kdf(
+    shared_secret: &[u8],
+    algorithm_id: &str,
+    len: usize,
+) -> Zeroizing<Vec<u8>> {
+    let mut okm = Zeroizing::new(Vec::with_capacity(len + 32));
+    let mut counter = 1u32;
+    while okm.len() < len {
+        let mut digest = Sha256::new();
+        digest.update(counter.to_be_bytes());
+        digest.update(shared_secret);
+        digest.update((algorithm_id.len() as u32).to_be_bytes());
+        digest.update(algorithm_id.as_bytes());
+        digest.update(0u32.to_be_bytes());
+        digest.update(0u32.to_be_bytes());
+        digest.update(((len * 8) as u32).to_be_bytes());
+        let mut block = digest.finalize();
+        okm.extend_from_slice(&block);
+        block.zeroize();
+        counter += 1;
+    }
+    okm.truncate(len);
+    okm
+}
+
+fn wrap_key(
+    kek: &[u8],
+    cek: &[u8],
+    out: &mut [u8],
+) -> Result<(), KyberLibError> {
+    let wrapped = match kek.len() {
+        16 => KekAes128::try_from(kek).and_then(|k| k.wrap(cek, out)),
+        24 => KekAes192::try_from(kek).and_then(|k| k.wrap(cek, out)),
+        _ => KekAes256::try_from(kek).and_then(|k| k.wrap(cek, out)),
+    };
+    wrapped.map_err(|_| KyberLibError::InvalidLength)
+}
+
+fn unwrap_key(
+    kek: &[u8],
+    encrypted_key: &[u8],
+    out: &mut [u8],
+) -> Result<(), KyberLibError> {
+    let unwrapped = match kek.len() {
+        16 => KekAes128::try_from(kek)
+            .and_then(|k| k.unwrap(encrypted_key, out)),
+        24 => KekAes192::try_from(kek)
+            .and_then(|k| k.unwrap(encrypted_key, out)),
+        _ => KekAes256::try_from(kek)
+            .and_then(|k| k.unwrap(encrypted_key, out)),
+    };
+    unwrapped.map_err(|_| KyberLibError::Decapsulation)
+}
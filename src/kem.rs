@@ -1,11 +1,16 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+use crate::dispatch::{indcpa_dec, indcpa_enc, indcpa_keypair};
 use crate::{
-    error::KyberLibError, indcpa::*, params::*, rng::randombytes,
-    symmetric::*, verify::*,
+    error::KyberLibError, params::*, rng::randombytes, symmetric::*,
+    verify::*,
 };
+#[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+use core::sync::atomic::{AtomicUsize, Ordering};
 use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
 /// Generates a public and private key pair for CCA-secure Kyber key encapsulation mechanism.
 ///
@@ -18,7 +23,9 @@ use rand_core::{CryptoRng, RngCore};
 ///
 /// # Errors
 ///
-/// Returns a `KyberLibError` on failure.
+/// Returns a `KyberLibError` on failure, and `KyberLibError::WeakKey`
+/// if `_rng` returns 32 zero bytes for either seed or the secret vector
+/// comes out all zero.
 pub fn generate_key_pair<R>(
     pk: &mut [u8],
     sk: &mut [u8],
@@ -34,21 +41,52 @@ where
     const END: usize =
         KYBER_INDCPA_PUBLIC_KEY_BYTES + KYBER_INDCPA_SECRET_KEY_BYTES;
 
-    indcpa_keypair(pk, sk, _seed, _rng)?;
+    // d and z are drawn one after the other, as the reference code
+    // draws them, so a given RNG stream still yields the same key.
+    let mut d = [0u8; KYBER_SYM_BYTES];
+    let mut z = [0u8; KYBER_SYM_BYTES];
+    let seed = match _seed {
+        Some(s) => s,
+        None => {
+            randombytes(&mut d, KYBER_SYM_BYTES, _rng)?;
+            randombytes(&mut z, KYBER_SYM_BYTES, _rng)?;
+            // An all-zero draw is what a stuck or unseeded RNG returns.
+            if is_zero(&d) | is_zero(&z) == 1 {
+                return Err(KyberLibError::WeakKey);
+            }
+            (&d[..], &z[..])
+        }
+    };
+
+    indcpa_keypair(pk, sk, Some(seed), _rng)?;
+
+    // s = 0 makes t = e and the key worthless. No seed leads there in
+    // practice, but the check is cheap and keeps the bad key from ever
+    // leaving this function.
+    if is_zero(&sk[..KYBER_INDCPA_SECRET_KEY_BYTES]) == 1 {
+        sk.fill(0);
+        return Err(KyberLibError::WeakKey);
+    }
 
     sk[KYBER_INDCPA_SECRET_KEY_BYTES..END]
         .copy_from_slice(&pk[..KYBER_INDCPA_PUBLIC_KEY_BYTES]);
     hash_h(&mut sk[PK_START..], pk, KYBER_PUBLIC_KEY_BYTES);
+    sk[SK_START..].copy_from_slice(seed.1);
 
-    if let Some(s) = _seed {
-        sk[SK_START..].copy_from_slice(s.1);
-    } else {
-        randombytes(&mut sk[SK_START..], KYBER_SYM_BYTES, _rng)?;
+    #[cfg(feature = "zeroize")]
+    {
+        d.zeroize();
+        z.zeroize();
     }
-
     Ok(())
 }
 
+// Returns 1 if `x` is all zero and 0 otherwise, in constant time.
+fn is_zero(x: &[u8]) -> u8 {
+    let r = x.iter().fold(0u8, |r, &b| r | b);
+    (u16::from(r).wrapping_sub(1) >> 15) as u8
+}
+
 /// Generates cipher text and a shared secret for a given public key.
 ///
 /// # Arguments
@@ -72,6 +110,66 @@ pub fn encrypt_message<R>(
 where
     R: RngCore + CryptoRng,
 {
+    encrypt(ct, ss, pk, _rng, _seed, None).map(|_| ())
+}
+
+/// Generates cipher text and a shared secret like [`encrypt_message`],
+/// with the IND-CPA message hedged against a repeating RNG.
+///
+/// The message is `m = H(r || H(pk) || n)`, where `r` is 32 bytes drawn
+/// from `_rng`, `H` is the hash used for `H(pk)` elsewhere in the KEM and
+/// `n` is a process-wide counter, incremented on every call and encoded
+/// as 8 little-endian bytes. [`encrypt_message`] uses `m = H(r)`.
+///
+/// # Errors
+///
+/// Returns a `KyberLibError` on failure.
+#[cfg(target_has_atomic = "ptr")]
+pub fn encrypt_message_hedged<R>(
+    ct: &mut [u8],
+    ss: &mut [u8],
+    pk: &[u8],
+    _rng: &mut R,
+) -> Result<(), KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed) as u64;
+    encrypt(ct, ss, pk, _rng, None, Some(n)).map(|_| ())
+}
+
+/// Encapsulates like [`encrypt_message`] and also returns the IND-CPA
+/// message `m` that was encrypted.
+#[cfg(feature = "research")]
+pub(crate) fn encrypt_message_export_m<R>(
+    ct: &mut [u8],
+    ss: &mut [u8],
+    pk: &[u8],
+    rng: &mut R,
+) -> Result<[u8; KYBER_SYM_BYTES], KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    encrypt(ct, ss, pk, rng, None, None)
+}
+
+// Returns the IND-CPA message, which `encrypt_message` discards. With
+// `hedge`, the counter of `encrypt_message_hedged`, the message is hedged
+// as documented there; seeded calls never are.
+fn encrypt<R>(
+    ct: &mut [u8],
+    ss: &mut [u8],
+    pk: &[u8],
+    _rng: &mut R,
+    _seed: Option<&[u8]>,
+    hedge: Option<u64>,
+) -> Result<[u8; KYBER_SYM_BYTES], KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    let mut m = [0u8; KYBER_SYM_BYTES];
     let mut kr = [0u8; 2 * KYBER_SYM_BYTES];
     let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
     let mut randbuf = [0u8; 2 * KYBER_SYM_BYTES];
@@ -83,11 +181,27 @@ where
         randombytes(&mut randbuf, KYBER_SYM_BYTES, _rng)?;
     }
 
-    // Don't release system RNG output
-    hash_h(&mut buf, &randbuf, KYBER_SYM_BYTES);
-
     // Multitarget countermeasure for coins + contributory KEM
     hash_h(&mut buf[KYBER_SYM_BYTES..], pk, KYBER_PUBLIC_KEY_BYTES);
+
+    // Don't release system RNG output
+    match hedge {
+        None => hash_h(&mut buf, &randbuf, KYBER_SYM_BYTES),
+        Some(n) => {
+            const LEN: usize = 2 * KYBER_SYM_BYTES + 8;
+            #[allow(unused_mut)]
+            let mut input = [0u8; LEN];
+            input[..KYBER_SYM_BYTES]
+                .copy_from_slice(&randbuf[..KYBER_SYM_BYTES]);
+            input[KYBER_SYM_BYTES..2 * KYBER_SYM_BYTES]
+                .copy_from_slice(&buf[KYBER_SYM_BYTES..]);
+            input[2 * KYBER_SYM_BYTES..]
+                .copy_from_slice(&n.to_le_bytes());
+            hash_h(&mut buf, &input, LEN);
+            #[cfg(feature = "zeroize")]
+            input.zeroize();
+        }
+    }
     hash_g(&mut kr, &buf, 2 * KYBER_SYM_BYTES);
 
     // Coins are in kr[KYBER_SYM_BYTES..]
@@ -99,7 +213,8 @@ where
     // Hash concatenation of pre-k and H(c) to derive the shared secret
     kdf(ss, &kr, 2 * KYBER_SYM_BYTES);
 
-    Ok(())
+    m.copy_from_slice(&buf[..KYBER_SYM_BYTES]);
+    Ok(m)
 }
 
 /// Generates a shared secret for a given cipher text and private key.
@@ -112,34 +227,245 @@ where
 ///
 /// On failure, `ss` will contain a pseudo-random value.
 pub fn decrypt_message(ss: &mut [u8], ct: &[u8], sk: &[u8]) {
-    let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
-    let mut kr = [0u8; 2 * KYBER_SYM_BYTES];
-    let mut cmp = [0u8; KYBER_CIPHERTEXT_BYTES];
-    let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+    let _ = decrypt_with_sk(ss, ct, sk, 0);
+}
+
+/// Decapsulates like [`decrypt_message`], but flips a bit of the decoded
+/// message so that the re-encryption check always fails and `ss` is the
+/// implicit rejection key derived from `z`.
+#[cfg(feature = "research")]
+pub(crate) fn decrypt_message_force_failure(
+    ss: &mut [u8],
+    ct: &[u8],
+    sk: &[u8],
+) {
+    let _ = decrypt_with_sk(ss, ct, sk, 1);
+}
+
+/// Decapsulates like [`decrypt_message`] and returns the message `m'`
+/// decoded by the IND-CPA decryption.
+#[cfg(feature = "research")]
+pub(crate) fn decrypt_message_debug(
+    ss: &mut [u8],
+    ct: &[u8],
+    sk: &[u8],
+) -> [u8; KYBER_SYM_BYTES] {
+    decrypt_with_sk(ss, ct, sk, 0)
+}
 
-    pk.copy_from_slice(
+// Splits a private key into the parts `decrypt` takes.
+fn decrypt_with_sk(
+    ss: &mut [u8],
+    ct: &[u8],
+    sk: &[u8],
+    flip: u8,
+) -> [u8; KYBER_SYM_BYTES] {
+    const START: usize = KYBER_SECRET_KEY_BYTES - 2 * KYBER_SYM_BYTES;
+    const END: usize = KYBER_SECRET_KEY_BYTES - KYBER_SYM_BYTES;
+    decrypt(
+        ss,
+        ct,
+        &sk[..KYBER_INDCPA_SECRET_KEY_BYTES],
         &sk[KYBER_INDCPA_SECRET_KEY_BYTES..]
             [..KYBER_INDCPA_PUBLIC_KEY_BYTES],
-    );
+        &sk[START..END],
+        &sk[END..],
+        flip,
+    )
+}
 
-    indcpa_dec(&mut buf, ct, sk);
+/// Generates a shared secret for a given cipher text from the parts of a
+/// private key, re-encrypting under a separately supplied public key.
+///
+/// # Arguments
+///
+/// * `ss` - Output shared secret (an already allocated array of CRYPTO_BYTES bytes).
+/// * `ct` - Input cipher text (an already allocated array of CRYPTO_CIPHERTEXTBYTES bytes).
+/// * `sk_cpa` - Input IND-CPA private key (KYBER_INDCPA_SECRET_KEY_BYTES bytes).
+/// * `pk` - Input public key (an already allocated array of CRYPTO_PUBLICKEYBYTES bytes).
+/// * `h_pk` - `H(pk)` (KYBER_SYM_BYTES bytes).
+/// * `z` - Implicit rejection value (KYBER_SYM_BYTES bytes).
+///
+/// On failure, `ss` will contain a pseudo-random value.
+pub fn decrypt_message_with_pk(
+    ss: &mut [u8],
+    ct: &[u8],
+    sk_cpa: &[u8],
+    pk: &[u8],
+    h_pk: &[u8],
+    z: &[u8],
+) {
+    let _ = decrypt(ss, ct, sk_cpa, pk, h_pk, z, 0);
+}
+
+// `flip` is XORed into the first byte of the decoded message, so any
+// nonzero value makes the re-encryption check fail. Returns the decoded
+// message before the flip.
+//
+// The IND-CPA decryption reads only s and the ciphertext. The matrix is
+// needed by the re-encryption alone, which expands each entry once, as
+// encapsulation does, so nothing is expanded twice within a call. It is
+// not cached across calls here: that would be k^2 polynomials of state
+// per key, 8 KiB for Kyber1024, with nowhere to keep them without an
+// allocator. The `expanded` feature keeps them on the heap.
+fn decrypt(
+    ss: &mut [u8],
+    ct: &[u8],
+    sk_cpa: &[u8],
+    pk: &[u8],
+    h_pk: &[u8],
+    z: &[u8],
+    flip: u8,
+) -> [u8; KYBER_SYM_BYTES] {
+    decrypt_with(ss, ct, sk_cpa, h_pk, z, flip, |cmp, m, coins| {
+        indcpa_enc(cmp, m, pk, coins)
+    })
+}
+
+// `decrypt` with the re-encryption of the decoded message under the
+// given coins done by `reencrypt`.
+pub(crate) fn decrypt_with<F>(
+    ss: &mut [u8],
+    ct: &[u8],
+    sk_cpa: &[u8],
+    h_pk: &[u8],
+    z: &[u8],
+    flip: u8,
+    reencrypt: F,
+) -> [u8; KYBER_SYM_BYTES]
+where
+    F: FnOnce(&mut [u8], &[u8], &[u8]),
+{
+    let mut m = [0u8; KYBER_SYM_BYTES];
+    let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
+    let mut kr = [0u8; 2 * KYBER_SYM_BYTES];
+    let mut cmp = [0u8; KYBER_CIPHERTEXT_BYTES];
+
+    indcpa_dec(&mut buf, ct, sk_cpa);
+    m.copy_from_slice(&buf[..KYBER_SYM_BYTES]);
+    buf[0] ^= flip;
 
     // Multitarget countermeasure for coins + contributory KEM
-    const START: usize = KYBER_SECRET_KEY_BYTES - 2 * KYBER_SYM_BYTES;
-    const END: usize = KYBER_SECRET_KEY_BYTES - KYBER_SYM_BYTES;
-    buf[KYBER_SYM_BYTES..].copy_from_slice(&sk[START..END]);
+    buf[KYBER_SYM_BYTES..].copy_from_slice(h_pk);
     hash_g(&mut kr, &buf, 2 * KYBER_SYM_BYTES);
 
     // Coins are in kr[KYBER_SYM_BYTES..]
-    indcpa_enc(&mut cmp, &buf, &pk, &kr[KYBER_SYM_BYTES..]);
+    reencrypt(&mut cmp, &buf, &kr[KYBER_SYM_BYTES..]);
+    // The check stays on bytes. Compression is lossy, so the polynomials
+    // of the re-encryption almost never equal the decompressed
+    // ciphertext, and deciding whether each coefficient compresses to
+    // the received value takes the same multiply and shift as
+    // compressing it. Comparing compressed values instead of bytes would
+    // only skip the packing of cmp, which is negligible next to the
+    // matrix generation and NTTs of the re-encryption, and give up a
+    // single constant-time pass that works for every backend.
     let fail = verify(ct, &cmp, KYBER_CIPHERTEXT_BYTES);
 
     // Overwrite coins in kr with H(c)
     hash_h(&mut kr[KYBER_SYM_BYTES..], ct, KYBER_CIPHERTEXT_BYTES);
 
     // Overwrite pre-k with z on re-encryption failure
-    cmov(&mut kr, &sk[END..], KYBER_SYM_BYTES, fail);
+    cmov(&mut kr, z, KYBER_SYM_BYTES, fail);
 
     // Hash concatenation of pre-k and H(c) to derive the shared secret
     kdf(ss, &kr, 2 * KYBER_SYM_BYTES);
+    m
+}
+
+/// Checks that a public key is canonically encoded.
+///
+/// Every 12-bit coefficient of the polynomial vector must be reduced
+/// modulo `KYBER_Q`, which is the encapsulation key check of FIPS 203.
+///
+/// # Arguments
+///
+/// * `pk` - Input public key (an already allocated array of CRYPTO_PUBLICKEYBYTES bytes).
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidKey` if a coefficient is out of range.
+pub fn check_public_key(pk: &[u8]) -> Result<(), KyberLibError> {
+    #[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+    let _ = PUBLIC_KEY_CHECKS.fetch_add(1, Ordering::Relaxed);
+    if coefficients_in_range(pk) {
+        Ok(())
+    } else {
+        Err(KyberLibError::InvalidKey)
+    }
+}
+
+// Public keys checked, counted in debug builds so that tests can check
+// that a `ValidatedPublicKey` is not checked again.
+#[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+static PUBLIC_KEY_CHECKS: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of times [`check_public_key`] has run in this process.
+/// Always zero in release builds. Not part of the public API.
+#[doc(hidden)]
+pub fn public_key_checks() -> usize {
+    #[cfg(all(debug_assertions, target_has_atomic = "ptr"))]
+    {
+        PUBLIC_KEY_CHECKS.load(Ordering::Relaxed)
+    }
+    #[cfg(not(all(debug_assertions, target_has_atomic = "ptr")))]
+    {
+        0
+    }
+}
+
+// Whether every 12-bit coefficient packed in the polynomial vector at the
+// start of `pk` is below `q`. A loop rather than iterators so that it can
+// run in const evaluation.
+pub(crate) const fn coefficients_in_range(pk: &[u8]) -> bool {
+    let mut i = 0;
+    while i < KYBER_POLYVEC_BYTES {
+        let a0 = pk[i] as usize | ((pk[i + 1] as usize & 0x0f) << 8);
+        let a1 =
+            (pk[i + 1] as usize >> 4) | ((pk[i + 2] as usize) << 4);
+        if a0 >= KYBER_Q || a1 >= KYBER_Q {
+            return false;
+        }
+        i += 3;
+    }
+    true
+}
+
+/// Checks that a ciphertext is canonically encoded.
+///
+/// Every compressed coefficient is decompressed and compressed again, and
+/// must come back unchanged. For the round-3 parameter sets the packed
+/// coefficients use every bit and each `d`-bit value round-trips, so this
+/// holds for any ciphertext of the right length; the check exists for
+/// strict interop testing and fails closed should that ever change.
+///
+/// # Arguments
+///
+/// * `ct` - Input ciphertext (an already allocated array of CRYPTO_CIPHERTEXTBYTES bytes).
+pub fn ciphertext_is_canonical(ct: &[u8]) -> bool {
+    let (u, v) = ct[..KYBER_CIPHERTEXT_BYTES]
+        .split_at(KYBER_POLYVEC_COMPRESSED_BYTES);
+    let du = (8 * KYBER_POLYVEC_COMPRESSED_BYTES
+        / (KYBER_SECURITY_PARAMETER * KYBER_N)) as u32;
+    let dv = (8 * KYBER_POLY_COMPRESSED_BYTES / KYBER_N) as u32;
+    compressed_roundtrips(u, du) && compressed_roundtrips(v, dv)
+}
+
+// Unpacks little-endian `d`-bit coefficients and checks that
+// Compress_q(Decompress_q(x, d), d) = x for each of them.
+fn compressed_roundtrips(bytes: &[u8], d: u32) -> bool {
+    let q = KYBER_Q as u32;
+    let mask = (1u32 << d) - 1;
+    let (mut acc, mut bits, mut canonical) = (0u32, 0u32, true);
+    for &byte in bytes {
+        acc |= (byte as u32) << bits;
+        bits += 8;
+        while bits >= d {
+            let x = acc & mask;
+            acc >>= d;
+            bits -= d;
+            let y = (x * q + (1 << (d - 1))) >> d;
+            canonical &= (((y << d) + q / 2) / q) & mask == x;
+        }
+    }
+    canonical && bits == 0
 }
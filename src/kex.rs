@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use crate::{kem::*, params::*, symmetric::kdf, KyberLibError};
+use core::convert::TryInto;
 use rand_core::{CryptoRng, RngCore};
 
 /// Unilateral Key Exchange Initiation Byte Length
@@ -28,19 +29,168 @@ pub type Decapsulated =
     Result<[u8; KYBER_SHARED_SECRET_BYTES], KyberLibError>;
 /// Kyber public key
 pub type PublicKey = [u8; KYBER_PUBLIC_KEY_BYTES];
+
+// A byte array on a 64-byte boundary, dereferencing to the array.
+macro_rules! aligned_bytes {
+    ($(#[$doc:meta])* $name:ident, $array:ty) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[repr(C, align(64))]
+        pub struct $name(pub $array);
+
+        impl core::ops::Deref for $name {
+            type Target = $array;
+
+            fn deref(&self) -> &$array {
+                &self.0
+            }
+        }
+
+        impl core::ops::DerefMut for $name {
+            fn deref_mut(&mut self) -> &mut $array {
+                &mut self.0
+            }
+        }
+
+        impl From<$array> for $name {
+            fn from(bytes: $array) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+    };
+}
+
+aligned_bytes!(
+    /// A [`PublicKey`] starting on a 64-byte boundary.
+    ///
+    /// Plain key arrays have the alignment of `u8`, so the SIMD kernels
+    /// unpacking them use unaligned loads, which cost a second cache line
+    /// access whenever a vector straddles a boundary. The key bytes come
+    /// first, followed by padding up to a multiple of 64 bytes: 1600
+    /// bytes in place of 1568 for Kyber1024. It dereferences to the array,
+    /// so every function taking `&[u8]` accepts `&*key`.
+    AlignedPublicKey,
+    PublicKey
+);
+
+aligned_bytes!(
+    /// A ciphertext starting on a 64-byte boundary, laid out like
+    /// [`AlignedPublicKey`].
+    AlignedCiphertext,
+    [u8; KYBER_CIPHERTEXT_BYTES]
+);
+
 /// Kyber secret key
+///
+/// The key is the concatenation of the IND-CPA secret vector `s`, the
+/// public key, `H(pk)` and the rejection value `z`. `s` is stored in the
+/// NTT domain, as its 12-bit packed `NTT(s)`, so decapsulation never
+/// transforms it; the only forward NTT it performs is on the ciphertext.
+/// A key stored as is, e.g. in an HSM, is thus already a precomputed
+/// NTT-domain key.
+///
+/// The embedded public key likewise holds `NTT(t)`. The one derived value
+/// the key does not hold is the matrix `A`, which the re-encryption
+/// regenerates from the 32-byte seed on every call; with the `expanded`
+/// feature, `expanded::ExpandedSecretKey` keeps it expanded and persists
+/// it in a cache that later cold starts load instead.
+///
+/// Type aliases cannot carry `#[must_use]`, so the functions returning a
+/// secret key are annotated instead and discarding their output is linted:
+///
+/// ```compile_fail
+/// #![deny(unused_must_use)]
+/// # use kyberlib::*;
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng).unwrap();
+/// keys.expose_secret();
+/// ```
 pub type SecretKey = [u8; KYBER_SECRET_KEY_BYTES];
+/// Compact Kyber secret key: the 64-byte key generation seed `d || z`
+/// from which [`expand_secret_key`](crate::expand_secret_key) rebuilds the
+/// full [`SecretKey`]
+pub type CompactSecretKey = [u8; KYBER_COMPACT_SECRET_KEY_BYTES];
 /// Kyber Shared Secret
+///
+/// As with [`SecretKey`], `#[must_use]` lives on the functions returning it.
 pub type SharedSecret = [u8; KYBER_SHARED_SECRET_BYTES];
 /// Bytes to send when initiating a unilateral key exchange
 pub type UakeSendInit = [u8; UAKE_INIT_BYTES];
 /// Bytes to send when responding to a unilateral key exchange
 pub type UakeSendResponse = [u8; UAKE_RESPONSE_BYTES];
 /// Bytes to send when initiating a mutual key exchange
+///
+/// The array is the wire encoding, `AKE_INIT_BYTES` long:
+///
+/// | Offset | Length | Field |
+/// |--------|--------|-------|
+/// | 0 | `KYBER_PUBLIC_KEY_BYTES` | client's ephemeral public key |
+/// | `KYBER_PUBLIC_KEY_BYTES` | `KYBER_CIPHERTEXT_BYTES` | ciphertext to the server's static key |
+///
+/// Parse received bytes with [`ake_init_from_bytes`].
 pub type AkeSendInit = [u8; AKE_INIT_BYTES];
 /// Bytes to send when responding to a mutual key exchange
+///
+/// The array is the wire encoding, `AKE_RESPONSE_BYTES` long:
+///
+/// | Offset | Length | Field |
+/// |--------|--------|-------|
+/// | 0 | `KYBER_CIPHERTEXT_BYTES` | ciphertext to the client's ephemeral key |
+/// | `KYBER_CIPHERTEXT_BYTES` | `KYBER_CIPHERTEXT_BYTES` | ciphertext to the client's static key |
+///
+/// Parse received bytes with [`ake_response_from_bytes`].
 pub type AkeSendResponse = [u8; AKE_RESPONSE_BYTES];
 
+/// Parses the bytes of an [`AkeSendInit`] received from the network.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `bytes` is not
+/// `AKE_INIT_BYTES` long and `KyberLibError::InvalidKey` if the ephemeral
+/// public key is not canonically encoded.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// # let mut rng = rand::thread_rng();
+/// # let bob_keys = keypair(&mut rng)?;
+/// let client_init = Ake::new().client_init(&bob_keys.public, &mut rng)?;
+/// let received = ake_init_from_bytes(&client_init[..])?;
+/// assert_eq!(received, client_init);
+/// assert!(ake_init_from_bytes(&client_init[1..]).is_err());
+/// # Ok(()) }
+/// ```
+pub fn ake_init_from_bytes(
+    bytes: &[u8],
+) -> Result<AkeSendInit, KyberLibError> {
+    let msg: AkeSendInit =
+        bytes.try_into().map_err(|_| KyberLibError::InvalidLength)?;
+    check_public_key(&msg[..KYBER_PUBLIC_KEY_BYTES])?;
+    Ok(msg)
+}
+
+/// Parses the bytes of an [`AkeSendResponse`] received from the network.
+///
+/// Any bytes of the right length form two ciphertexts; a forged response
+/// yields a shared secret the server does not share rather than an error.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `bytes` is not
+/// `AKE_RESPONSE_BYTES` long.
+pub fn ake_response_from_bytes(
+    bytes: &[u8],
+) -> Result<AkeSendResponse, KyberLibError> {
+    bytes.try_into().map_err(|_| KyberLibError::InvalidLength)
+}
+
 // Ephemeral keys
 type TempKey = [u8; KYBER_SHARED_SECRET_BYTES];
 type Eska = [u8; KYBER_SECRET_KEY_BYTES];
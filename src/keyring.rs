@@ -0,0 +1,249 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Decapsulation key ring for rotating static keys without downtime.
+//!
+//! A server publishes the public key of its primary entry. Clients send a
+//! framed ciphertext carrying the key id of the public key they used, so
+//! after a rotation the server still picks the right secret key for
+//! clients that hold the previous public key:
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 1     | frame version, currently 1 |
+//! | 8     | key id, the first 8 bytes of `H(pk)` |
+//! | rest  | Kyber ciphertext |
+//!
+//! A bare ciphertext without a frame is treated as a legacy ciphertext for
+//! the primary key. Once an old key is removed, framed ciphertexts for it
+//! are rejected with an error instead of yielding an implicitly rejected
+//! shared secret.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::keyring::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let old = keypair(&mut rng)?;
+//! let new = keypair(&mut rng)?;
+//! let mut ring = KeyRing::new(4);
+//! let old_id = ring.insert(&old.secret)?;
+//!
+//! // A client still holding the old public key.
+//! let (framed, ss) = encapsulate_framed(&old.public, &mut rng)?;
+//!
+//! let new_id = ring.insert(&new.secret)?;
+//! ring.set_primary(&new_id)?;
+//! assert_eq!(ring.decapsulate(&framed)?, ss);
+//!
+//! ring.remove(&old_id)?;
+//! assert_eq!(ring.decapsulate(&framed), Err(KyberLibError::InvalidKey));
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::{decapsulate, encapsulate, public},
+    error::KyberLibError,
+    kex::{PublicKey, SecretKey, SharedSecret},
+    params::*,
+    symmetric::hash_h,
+    CryptoRng, RngCore,
+};
+use alloc::vec::Vec;
+use core::fmt;
+use zeroize::Zeroize;
+
+/// Current version of the ciphertext frame.
+pub const FRAME_VERSION: u8 = 1;
+/// Size in bytes of a key id.
+pub const KEY_ID_BYTES: usize = 8;
+/// Size in bytes of a framed ciphertext.
+pub const FRAMED_CIPHERTEXT_BYTES: usize =
+    1 + KEY_ID_BYTES + KYBER_CIPHERTEXT_BYTES;
+
+/// Returns the key id of `pk`, its truncated fingerprint.
+pub fn key_id(pk: &PublicKey) -> [u8; KEY_ID_BYTES] {
+    let mut fingerprint = [0u8; KYBER_SYM_BYTES];
+    hash_h(&mut fingerprint, pk, KYBER_PUBLIC_KEY_BYTES);
+    let mut id = [0u8; KEY_ID_BYTES];
+    id.copy_from_slice(&fingerprint[..KEY_ID_BYTES]);
+    id
+}
+
+/// Encapsulates to `pk` and frames the ciphertext with its key id.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+pub fn encapsulate_framed<R>(
+    pk: &PublicKey,
+    rng: &mut R,
+) -> Result<([u8; FRAMED_CIPHERTEXT_BYTES], SharedSecret), KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    let (ct, ss) = encapsulate(pk, rng)?;
+    let mut framed = [0u8; FRAMED_CIPHERTEXT_BYTES];
+    framed[0] = FRAME_VERSION;
+    framed[1..1 + KEY_ID_BYTES].copy_from_slice(&key_id(pk));
+    framed[1 + KEY_ID_BYTES..].copy_from_slice(&ct);
+    Ok((framed, ss))
+}
+
+struct Entry {
+    id: [u8; KEY_ID_BYTES],
+    secret: SecretKey,
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// Secret keys by key id, with one of them marked primary.
+#[derive(Debug)]
+pub struct KeyRing {
+    entries: Vec<Entry>,
+    primary: Option<[u8; KEY_ID_BYTES]>,
+    max_keys: usize,
+}
+
+impl KeyRing {
+    /// Creates an empty ring holding at most `max_keys` keys.
+    pub fn new(max_keys: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            primary: None,
+            max_keys,
+        }
+    }
+
+    /// Adds a secret key and returns its key id.
+    ///
+    /// The first key added becomes the primary.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if the ring is full or
+    /// already holds a key with this id.
+    pub fn insert(
+        &mut self,
+        secret: &SecretKey,
+    ) -> Result<[u8; KEY_ID_BYTES], KyberLibError> {
+        let id = key_id(&public(secret));
+        if self.entries.len() >= self.max_keys
+            || self.find(&id).is_some()
+        {
+            return Err(KyberLibError::InvalidInput);
+        }
+        self.entries.push(Entry {
+            id,
+            secret: *secret,
+        });
+        if self.primary.is_none() {
+            self.primary = Some(id);
+        }
+        Ok(id)
+    }
+
+    /// Marks the key with this id as primary.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if no key has this id.
+    pub fn set_primary(
+        &mut self,
+        id: &[u8; KEY_ID_BYTES],
+    ) -> Result<(), KyberLibError> {
+        let _ = self.find(id).ok_or(KyberLibError::InvalidKey)?;
+        self.primary = Some(*id);
+        Ok(())
+    }
+
+    /// Removes an expired key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if no key has this id and
+    /// `KyberLibError::InvalidInput` if it is the primary, which has to be
+    /// replaced with [`KeyRing::set_primary`] first.
+    pub fn remove(
+        &mut self,
+        id: &[u8; KEY_ID_BYTES],
+    ) -> Result<(), KyberLibError> {
+        let index = self.find(id).ok_or(KyberLibError::InvalidKey)?;
+        if self.primary == Some(*id) {
+            return Err(KyberLibError::InvalidInput);
+        }
+        drop(self.entries.swap_remove(index));
+        Ok(())
+    }
+
+    /// The key id of the primary key, if the ring is not empty.
+    pub fn primary_id(&self) -> Option<[u8; KEY_ID_BYTES]> {
+        self.primary
+    }
+
+    /// The public key to publish, that of the primary key.
+    pub fn primary_public_key(&self) -> Option<PublicKey> {
+        let index = self.find(&self.primary?)?;
+        Some(public(&self.entries[index].secret))
+    }
+
+    /// The number of keys in the ring.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the ring holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Decapsulates a framed ciphertext with the key it names, or a bare
+    /// legacy ciphertext with the primary key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if no key in the ring has the
+    /// framed key id or, for a legacy ciphertext, the ring is empty,
+    /// `KyberLibError::InvalidInput` for another frame version and
+    /// `KyberLibError::InvalidLength` if `ct` has neither size.
+    pub fn decapsulate(
+        &self,
+        ct: &[u8],
+    ) -> Result<SharedSecret, KyberLibError> {
+        let (id, ct) = match ct.len() {
+            KYBER_CIPHERTEXT_BYTES => {
+                (self.primary.ok_or(KyberLibError::InvalidKey)?, ct)
+            }
+            FRAMED_CIPHERTEXT_BYTES => {
+                if ct[0] != FRAME_VERSION {
+                    return Err(KyberLibError::InvalidInput);
+                }
+                let mut id = [0u8; KEY_ID_BYTES];
+                id.copy_from_slice(&ct[1..1 + KEY_ID_BYTES]);
+                (id, &ct[1 + KEY_ID_BYTES..])
+            }
+            _ => return Err(KyberLibError::InvalidLength),
+        };
+        let index = self.find(&id).ok_or(KyberLibError::InvalidKey)?;
+        decapsulate(ct, &self.entries[index].secret)
+    }
+
+    fn find(&self, id: &[u8; KEY_ID_BYTES]) -> Option<usize> {
+        self.entries.iter().position(|e| e.id == *id)
+    }
+}
@@ -0,0 +1,520 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A KEM API generic over the three Kyber parameter sets.
+//!
+//! The crate-level functions are fixed at build time to the level selected
+//! by the `kyber512`, `kyber768` or `kyber1024` feature. The functions in
+//! this module take the level as a type parameter instead, one of the
+//! [`Kyber512`], [`Kyber768`] and [`Kyber1024`] markers, so a single build
+//! serves all three, e.g. a server accepting clients at any level.
+//!
+//! `KYBER_N`, `KYBER_Q`, the polynomial arithmetic and the symmetric
+//! primitives are shared with the rest of the crate. At the level the
+//! crate was built for, [`derive`], [`encapsulate`] and [`decapsulate`]
+//! therefore agree byte for byte with their crate-level counterparts.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::KyberLibError;
+//! use kyberlib::levels::{self, Kyber512, Kyber768};
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = levels::keypair::<Kyber768, _>(&mut rng)?;
+//! let (ct, ss1) = levels::encapsulate::<Kyber768, _>(&keys.public, &mut rng)?;
+//! let ss2 = levels::decapsulate::<Kyber768>(&ct, &keys.secret)?;
+//! assert_eq!(ss1, ss2);
+//!
+//! // Keys of one level are rejected at another.
+//! assert!(levels::decapsulate::<Kyber512>(&ct, &keys.secret).is_err());
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    error::KyberLibError,
+    indcpa::{gen_matrix_entry, GEN_MATRIX_BUFBYTES},
+    kex::SharedSecret,
+    params::*,
+    poly::*,
+    polyvec::basemul_acc_lazy,
+    rng::randombytes,
+    symmetric::*,
+    verify::*,
+    CryptoRng, RngCore,
+};
+use core::fmt;
+
+/// A fixed-size byte array, the representation of keys and ciphertexts.
+pub trait ByteArray:
+    AsRef<[u8]> + AsMut<[u8]> + Copy + fmt::Debug + Eq
+{
+    /// An array of zeros.
+    fn zeroed() -> Self;
+}
+
+impl<const N: usize> ByteArray for [u8; N] {
+    fn zeroed() -> Self {
+        [0u8; N]
+    }
+}
+
+/// A Kyber parameter set.
+///
+/// Only the implementations for [`Kyber512`], [`Kyber768`] and
+/// [`Kyber1024`] are supported; the byte lengths are derived from the
+/// other constants and must not be overridden.
+pub trait Params: Copy + fmt::Debug + Default + Eq {
+    /// Name of the parameter set, e.g. `"Kyber768"`.
+    const NAME: &'static str;
+    /// Rank of the module, the number of polynomials in a vector.
+    const K: usize;
+    /// Noise parameter of the secret and error vectors of key generation.
+    const ETA1: usize;
+    /// Noise parameter of the encryption errors.
+    const ETA2: usize = 2;
+    /// Bits per coefficient of the compressed vector `u`.
+    const DU: usize;
+    /// Bits per coefficient of the compressed polynomial `v`.
+    const DV: usize;
+
+    /// Size in bytes of a serialized vector of polynomials.
+    const POLYVEC_BYTES: usize = Self::K * KYBER_POLY_BYTES;
+    /// Size in bytes of the compressed vector `u`.
+    const POLYVEC_COMPRESSED_BYTES: usize =
+        Self::K * KYBER_N * Self::DU / 8;
+    /// Size in bytes of the compressed polynomial `v`.
+    const POLY_COMPRESSED_BYTES: usize = KYBER_N * Self::DV / 8;
+    /// Size in bytes of a public key.
+    const PUBLIC_KEY_BYTES: usize =
+        Self::POLYVEC_BYTES + KYBER_SYM_BYTES;
+    /// Size in bytes of a secret key.
+    const SECRET_KEY_BYTES: usize =
+        2 * Self::POLYVEC_BYTES + 3 * KYBER_SYM_BYTES;
+    /// Size in bytes of a ciphertext.
+    const CIPHERTEXT_BYTES: usize =
+        Self::POLYVEC_COMPRESSED_BYTES + Self::POLY_COMPRESSED_BYTES;
+
+    /// A public key, `PUBLIC_KEY_BYTES` long.
+    type PublicKey: ByteArray;
+    /// A secret key, `SECRET_KEY_BYTES` long.
+    type SecretKey: ByteArray;
+    /// A ciphertext, `CIPHERTEXT_BYTES` long.
+    type Ciphertext: ByteArray;
+}
+
+/// Kyber512, for security roughly equivalent to AES-128.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Kyber512;
+
+impl Params for Kyber512 {
+    const NAME: &'static str = "Kyber512";
+    const K: usize = 2;
+    const ETA1: usize = 3;
+    const DU: usize = 10;
+    const DV: usize = 4;
+    type PublicKey = [u8; 800];
+    type SecretKey = [u8; 1632];
+    type Ciphertext = [u8; 768];
+}
+
+/// Kyber768, for security roughly equivalent to AES-192.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Kyber768;
+
+impl Params for Kyber768 {
+    const NAME: &'static str = "Kyber768";
+    const K: usize = 3;
+    const ETA1: usize = 2;
+    const DU: usize = 10;
+    const DV: usize = 4;
+    type PublicKey = [u8; 1184];
+    type SecretKey = [u8; 2400];
+    type Ciphertext = [u8; 1088];
+}
+
+/// Kyber1024, for security roughly equivalent to AES-256.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Kyber1024;
+
+impl Params for Kyber1024 {
+    const NAME: &'static str = "Kyber1024";
+    const K: usize = 4;
+    const ETA1: usize = 2;
+    const DU: usize = 11;
+    const DV: usize = 5;
+    type PublicKey = [u8; 1568];
+    type SecretKey = [u8; 3168];
+    type Ciphertext = [u8; 1568];
+}
+
+/// A public/secret keypair for the parameter set `P`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Keypair<P: Params> {
+    /// The public key.
+    pub public: P::PublicKey,
+    /// The secret key.
+    pub secret: P::SecretKey,
+}
+
+impl<P: Params> fmt::Debug for Keypair<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair")
+            .field("level", &P::NAME)
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Generates a keypair for the parameter set `P`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+#[must_use = "dropping the keypair discards the only copy of the secret key"]
+pub fn keypair<P, R>(rng: &mut R) -> Result<Keypair<P>, KyberLibError>
+where
+    P: Params,
+    R: RngCore + CryptoRng,
+{
+    let mut seed = [0u8; 2 * KYBER_SYM_BYTES];
+    randombytes(&mut seed, 2 * KYBER_SYM_BYTES, rng)?;
+    let keys = derive::<P>(&seed);
+    seed.iter_mut().for_each(|b| *b = 0);
+    keys
+}
+
+/// Deterministically derives a keypair for the parameter set `P` from a
+/// 64-byte seed, as [`crate::derive`] does for the build's level.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if the seed is not 64 bytes long.
+#[must_use = "dropping the keypair discards the derived secret key"]
+pub fn derive<P: Params>(
+    seed: &[u8],
+) -> Result<Keypair<P>, KyberLibError> {
+    if seed.len() != 2 * KYBER_SYM_BYTES {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut public = P::PublicKey::zeroed();
+    let mut secret = P::SecretKey::zeroed();
+    let (pk, sk) = (public.as_mut(), secret.as_mut());
+    indcpa_keypair::<P>(pk, sk, &seed[..KYBER_SYM_BYTES])?;
+
+    let pk_start = P::POLYVEC_BYTES;
+    let h_start = pk_start + P::PUBLIC_KEY_BYTES;
+    sk[pk_start..h_start].copy_from_slice(pk);
+    hash_h(&mut sk[h_start..], pk, P::PUBLIC_KEY_BYTES);
+    sk[h_start + KYBER_SYM_BYTES..]
+        .copy_from_slice(&seed[KYBER_SYM_BYTES..]);
+    Ok(Keypair { public, secret })
+}
+
+/// Encapsulates to a public key of the parameter set `P` and returns the
+/// ciphertext to send and the shared secret.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if `pk` is not
+/// `P::PUBLIC_KEY_BYTES` long and
+/// `KyberLibError::RandomBytesGeneration` if the RNG fails.
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn encapsulate<P, R>(
+    pk: &[u8],
+    rng: &mut R,
+) -> Result<(P::Ciphertext, SharedSecret), KyberLibError>
+where
+    P: Params,
+    R: RngCore + CryptoRng,
+{
+    if pk.len() != P::PUBLIC_KEY_BYTES {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut kr = [0u8; 2 * KYBER_SYM_BYTES];
+    let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
+    let mut randbuf = [0u8; 2 * KYBER_SYM_BYTES];
+    randombytes(&mut randbuf, KYBER_SYM_BYTES, rng)?;
+
+    // Don't release system RNG output
+    hash_h(&mut buf, &randbuf, KYBER_SYM_BYTES);
+
+    // Multitarget countermeasure for coins + contributory KEM
+    hash_h(&mut buf[KYBER_SYM_BYTES..], pk, P::PUBLIC_KEY_BYTES);
+    hash_g(&mut kr, &buf, 2 * KYBER_SYM_BYTES);
+
+    let mut ct = P::Ciphertext::zeroed();
+    indcpa_enc::<P>(ct.as_mut(), &buf, pk, &kr[KYBER_SYM_BYTES..])?;
+
+    // Overwrite coins in kr with H(c), then derive the shared secret
+    hash_h(
+        &mut kr[KYBER_SYM_BYTES..],
+        ct.as_ref(),
+        P::CIPHERTEXT_BYTES,
+    );
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    kdf(&mut ss, &kr, 2 * KYBER_SYM_BYTES);
+    Ok((ct, ss))
+}
+
+/// Decapsulates a ciphertext with a secret key of the parameter set `P`.
+///
+/// As with [`crate::decapsulate`], an invalid ciphertext of the right
+/// length yields a pseudo-random shared secret rather than an error.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if `ct` or `sk` do not have the
+/// sizes of `P`.
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn decapsulate<P: Params>(
+    ct: &[u8],
+    sk: &[u8],
+) -> Result<SharedSecret, KyberLibError> {
+    if ct.len() != P::CIPHERTEXT_BYTES
+        || sk.len() != P::SECRET_KEY_BYTES
+    {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
+    let mut kr = [0u8; 2 * KYBER_SYM_BYTES];
+    let mut cmp = P::Ciphertext::zeroed();
+    let pk_start = P::POLYVEC_BYTES;
+    let h_start = pk_start + P::PUBLIC_KEY_BYTES;
+    let z_start = h_start + KYBER_SYM_BYTES;
+
+    indcpa_dec::<P>(&mut buf, ct, sk);
+
+    // Multitarget countermeasure for coins + contributory KEM
+    buf[KYBER_SYM_BYTES..].copy_from_slice(&sk[h_start..z_start]);
+    hash_g(&mut kr, &buf, 2 * KYBER_SYM_BYTES);
+
+    // Coins are in kr[KYBER_SYM_BYTES..]
+    indcpa_enc::<P>(
+        cmp.as_mut(),
+        &buf,
+        &sk[pk_start..h_start],
+        &kr[KYBER_SYM_BYTES..],
+    )?;
+    let fail = verify(ct, cmp.as_ref(), P::CIPHERTEXT_BYTES);
+
+    // Overwrite coins in kr with H(c)
+    hash_h(&mut kr[KYBER_SYM_BYTES..], ct, P::CIPHERTEXT_BYTES);
+
+    // Overwrite pre-k with z on re-encryption failure
+    cmov(&mut kr, &sk[z_start..], KYBER_SYM_BYTES, fail);
+
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    kdf(&mut ss, &kr, 2 * KYBER_SYM_BYTES);
+    Ok(ss)
+}
+
+/// The largest rank, that of Kyber1024.
+const MAX_K: usize = 4;
+
+/// A vector of up to `MAX_K` polynomials, of which `P::K` are used.
+type Vector = [Poly; MAX_K];
+
+fn gen_matrix<P: Params>(
+    a: &mut [Vector; MAX_K],
+    seed: &[u8],
+    transposed: bool,
+) {
+    let mut buf = [0u8; GEN_MATRIX_BUFBYTES];
+    for (i, row) in a.iter_mut().enumerate().take(P::K) {
+        for (j, entry) in row.iter_mut().enumerate().take(P::K) {
+            let [x, y] = matrix_index_bytes(i, j, transposed);
+            gen_matrix_entry(entry, &mut buf, seed, x, y);
+        }
+    }
+}
+
+fn basemul_acc<P: Params>(r: &mut Poly, a: &Vector, b: &Vector) {
+    basemul_acc_lazy(r, a[..P::K].iter().zip(&b[..P::K]));
+}
+
+fn getnoise<P: Params>(
+    v: &mut Vector,
+    seed: &[u8],
+    nonce: u8,
+    eta: usize,
+) -> Result<(), KyberLibError> {
+    for (i, r) in v.iter_mut().enumerate().take(P::K) {
+        poly_getnoise(r, seed, nonce + i as u8, eta)?;
+    }
+    Ok(())
+}
+
+fn vector_tobytes<P: Params>(r: &mut [u8], v: &Vector) {
+    for (chunk, a) in
+        r.chunks_exact_mut(KYBER_POLY_BYTES).zip(&v[..P::K])
+    {
+        poly_tobytes(chunk, *a);
+    }
+}
+
+fn vector_frombytes<P: Params>(v: &mut Vector, a: &[u8]) {
+    for (r, chunk) in
+        v[..P::K].iter_mut().zip(a.chunks_exact(KYBER_POLY_BYTES))
+    {
+        poly_frombytes(r, chunk);
+    }
+}
+
+// Compress_q(x, d) = ⌊((x << d) + q/2) / q⌋ mod⁺ 2ᵈ for each coefficient,
+// packed little-endian into d bits as by poly_compress and
+// polyvec_compress.
+fn compress(r: &mut [u8], mut a: Poly, d: usize) {
+    let q = KYBER_Q as u32;
+    let mask = (1u32 << d) - 1;
+    let (mut acc, mut bits, mut pos) = (0u32, 0usize, 0usize);
+    poly_csubq(&mut a);
+    for &x in a.coeffs.iter() {
+        acc |= (((((x as u32) << d) + q / 2) / q) & mask) << bits;
+        bits += d;
+        while bits >= 8 {
+            r[pos] = acc as u8;
+            acc >>= 8;
+            bits -= 8;
+            pos += 1;
+        }
+    }
+}
+
+// Decompress_q(x, d) = ⌊(x * q + 2ᵈ⁻¹) / 2ᵈ⌋, inverse of compress.
+fn decompress(r: &mut Poly, a: &[u8], d: usize) {
+    let q = KYBER_Q as u32;
+    let mask = (1u32 << d) - 1;
+    let (mut acc, mut bits, mut bytes) = (0u32, 0usize, a.iter());
+    for c in r.coeffs.iter_mut() {
+        while bits < d {
+            acc |= (*bytes.next().unwrap_or(&0) as u32) << bits;
+            bits += 8;
+        }
+        *c = (((acc & mask) * q + (1 << (d - 1))) >> d) as i16;
+        acc >>= d;
+        bits -= d;
+    }
+}
+
+fn indcpa_keypair<P: Params>(
+    pk: &mut [u8],
+    sk: &mut [u8],
+    seed: &[u8],
+) -> Result<(), KyberLibError> {
+    let mut a = [[Poly::new(); MAX_K]; MAX_K];
+    let (mut e, mut pkpv, mut skpv) = (
+        [Poly::new(); MAX_K],
+        [Poly::new(); MAX_K],
+        [Poly::new(); MAX_K],
+    );
+    let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
+
+    hash_g(&mut buf, seed, KYBER_SYM_BYTES);
+    let (publicseed, noiseseed) = buf.split_at(KYBER_SYM_BYTES);
+    gen_matrix::<P>(&mut a, publicseed, false);
+
+    getnoise::<P>(&mut skpv, noiseseed, 0, P::ETA1)?;
+    getnoise::<P>(&mut e, noiseseed, P::K as u8, P::ETA1)?;
+    skpv[..P::K].iter_mut().for_each(poly_ntt);
+    e[..P::K].iter_mut().for_each(poly_ntt);
+
+    // matrix-vector multiplication
+    for i in 0..P::K {
+        basemul_acc::<P>(&mut pkpv[i], &a[i], &skpv);
+        poly_tomont(&mut pkpv[i]);
+        poly_add(&mut pkpv[i], &e[i]);
+        poly_reduce(&mut pkpv[i]);
+    }
+
+    vector_tobytes::<P>(sk, &skpv);
+    vector_tobytes::<P>(pk, &pkpv);
+    pk[P::POLYVEC_BYTES..P::PUBLIC_KEY_BYTES]
+        .copy_from_slice(publicseed);
+    Ok(())
+}
+
+fn indcpa_enc<P: Params>(
+    c: &mut [u8],
+    m: &[u8],
+    pk: &[u8],
+    coins: &[u8],
+) -> Result<(), KyberLibError> {
+    let mut at = [[Poly::new(); MAX_K]; MAX_K];
+    let (mut sp, mut pkpv, mut ep, mut b) = (
+        [Poly::new(); MAX_K],
+        [Poly::new(); MAX_K],
+        [Poly::new(); MAX_K],
+        [Poly::new(); MAX_K],
+    );
+    let (mut v, mut k, mut epp) =
+        (Poly::new(), Poly::new(), Poly::new());
+
+    vector_frombytes::<P>(&mut pkpv, pk);
+    poly_frommsg(&mut k, m);
+    gen_matrix::<P>(
+        &mut at,
+        &pk[P::POLYVEC_BYTES..P::PUBLIC_KEY_BYTES],
+        true,
+    );
+
+    let nonce = P::K as u8;
+    getnoise::<P>(&mut sp, coins, 0, P::ETA1)?;
+    getnoise::<P>(&mut ep, coins, nonce, P::ETA2)?;
+    poly_getnoise(&mut epp, coins, 2 * nonce, P::ETA2)?;
+
+    sp[..P::K].iter_mut().for_each(poly_ntt);
+
+    // matrix-vector multiplication
+    for i in 0..P::K {
+        basemul_acc::<P>(&mut b[i], &at[i], &sp);
+    }
+    basemul_acc::<P>(&mut v, &pkpv, &sp);
+    b[..P::K].iter_mut().for_each(poly_invntt_tomont);
+    poly_invntt_tomont(&mut v);
+
+    for i in 0..P::K {
+        poly_add(&mut b[i], &ep[i]);
+        poly_reduce(&mut b[i]);
+    }
+    poly_add(&mut v, &epp);
+    poly_add(&mut v, &k);
+    poly_reduce(&mut v);
+
+    let (u_bytes, v_bytes) =
+        c.split_at_mut(P::POLYVEC_COMPRESSED_BYTES);
+    let stride = KYBER_N * P::DU / 8;
+    for (chunk, a) in u_bytes.chunks_exact_mut(stride).zip(&b[..P::K]) {
+        compress(chunk, *a, P::DU);
+    }
+    compress(v_bytes, v, P::DV);
+    Ok(())
+}
+
+fn indcpa_dec<P: Params>(m: &mut [u8], c: &[u8], sk: &[u8]) {
+    let (mut b, mut skpv) =
+        ([Poly::new(); MAX_K], [Poly::new(); MAX_K]);
+    let (mut v, mut mp) = (Poly::new(), Poly::new());
+
+    let (u_bytes, v_bytes) = c.split_at(P::POLYVEC_COMPRESSED_BYTES);
+    let stride = KYBER_N * P::DU / 8;
+    for (r, chunk) in
+        b[..P::K].iter_mut().zip(u_bytes.chunks_exact(stride))
+    {
+        decompress(r, chunk, P::DU);
+    }
+    decompress(&mut v, v_bytes, P::DV);
+    vector_frombytes::<P>(&mut skpv, sk);
+
+    b[..P::K].iter_mut().for_each(poly_ntt);
+    basemul_acc::<P>(&mut mp, &skpv, &b);
+    poly_invntt_tomont(&mut mp);
+
+    poly_sub(&mut mp, &v);
+    poly_reduce(&mut mp);
+    poly_csubq(&mut mp);
+
+    poly_tomsg(m, mp);
+}
@@ -16,15 +16,59 @@
 //! | `kyber512`  | Enables Kyber512 mode, providing a security level roughly equivalent to AES-128.                                                                                                |
 //! | `kyber1024` | Enables Kyber1024 mode, offering a security level roughly equivalent to AES-256.                   |
 //! | `90s`       | Activates 90's mode, which uses SHA2 and AES-CTR as a replacement for SHAKE. This may provide hardware speedups on certain architectures.                                                           |
-//! | `avx2`      | On x86_64 platforms, enables the optimized AVX2 version. This flag causes a compile error on other architectures. |
+//! | `avx2`      | On x86_64 platforms, adds the optimized AVX2 version, used when the CPU supports AVX2, BMI2 and POPCNT and falling back to the reference version otherwise. Has no effect on other architectures. |
+//! | `avx512`    | Implies `avx2`. Adds AVX-512 NTT, base multiplication and serialization kernels, used when the CPU supports AVX-512F and AVX-512BW. Opt-in, as some Intel server CPUs lower their clock while running AVX-512 code. |
+//! | `neon`      | On AArch64 platforms, adds NEON NTT, base multiplication, noise sampling and serialization kernels. NEON is part of the AArch64 baseline, so the backend is selected at compile time. Has no effect on other architectures. |
+//! | `armv8-sha3` | On AArch64, runs the Keccak-f[1600] permutation on the Armv8.2 SHA3 extension, used when the CPU has it and falling back to the software permutation otherwise. Has no effect on other architectures. |
 //! | `wasm`      | Enables support for compiling to WASM targets. |
+//! | `portable-simd` | Nightly only. Adds NTT, reduction and compression kernels written with `core::simd`, used on targets that none of `avx2`, `neon` and `wasm-simd` covers. |
+//! | `wasm-simd` | On wasm32, adds WebAssembly SIMD128 NTT, base multiplication and noise sampling kernels. Only takes effect when the module is built with `-C target-feature=+simd128`, as runtimes without SIMD reject such modules. Has no effect on other architectures. |
+//! | `rvv`       | On riscv64 Linux, adds RISC-V vector (RVV 1.0) NTT, base multiplication and serialization kernels, used when the kernel reports the V extension and falling back to the reference version otherwise. Opt-in, as toolchain and emulator support for the extension is still uneven. Has no effect on other architectures. |
+//! | `age-plugin` | Enables age recipients and identities for Kyber keys and builds the `age-plugin-kyber` binary. Requires `std`. |
+//! | `compat-tests` | Runs the integration tests cross-validating against the `pqc_kyber` crate. Only meaningful with `kyber1024` and `90s-fixslice`. |
+//! | `confirmation` | Enables key confirmation tags so a failed key agreement is reported right after the KEM step. |
+//! | `capi`      | Exports `kyberlib_*` C functions taking explicit buffer lengths, declared in `include/kyberlib.h`. |
+//! | `cms`       | Enables building and unwrapping CMS `KEMRecipientInfo` structures (RFC 9629). Requires an allocator. |
+//! | `defmt`     | Implements `defmt::Format` for errors and the parameter set, and for public keys and ciphertexts through the `logging` wrappers. Secrets are printed as `<redacted>` or not at all. Works without an allocator. For bare-metal targets: on hosted ones the `cdylib` would export defmt's symbols and fails to link. |
+//! | `debug-domain` | In debug builds, tags each polynomial of the reference code as in the normal or NTT domain and panics when a kernel gets the wrong one, e.g. an NTT applied twice. Compiled out in release builds and ignored by the SIMD kernels other than the `portable-simd` NTT. |
+//! | `expanded`  | Enables secret keys holding the expanded public matrix, and a versioned, authenticated cache format that loads them without expanding it again. Requires an allocator. |
+//! | `force-backend` | Exports `acceleration::force_backend` and honours the `KYBERLIB_BACKEND` environment variable with `std`, both switching the backend of the whole process. Meant for tests and benchmarks comparing backends. |
+//! | `ffi`       | Exports the `kyber_keypair`, `kyber_enc` and `kyber_dec` C ABI functions from the `cdylib`. |
+//! | `fragment`  | Enables splitting ciphertexts into MTU-sized fragments and reassembling them in a caller-provided buffer. |
+//! | `jose`      | Enables the JWE key-management step for Kyber recipients, leaving content encryption to the caller. Requires an allocator. |
+//! | `keyring`   | Enables a decapsulation key ring that selects the secret key by the key id framed with the ciphertext. Requires an allocator. |
+//! | `levels`    | Enables a KEM API generic over the `Kyber512`, `Kyber768` and `Kyber1024` parameter sets, so one build serves all three. |
+//! | `net`       | Enables a minimal versioned KEM handshake over `std::net` TCP streams for examples and small tools. Requires `std`. |
 //! | `nasm`      | Uses Netwide Assembler (NASM) AVX2 code instead of GNU Assembler (GAS) for portability. Requires a NASM compiler: <https://www.nasm.us/> |
+//! | `pinning`   | Enables trust-on-first-use pinning of peer public key fingerprints in a file. Requires `std`. |
+//! | `pgp`       | Enables the algorithm-specific fields of OpenPGP composite Kyber + X25519 subkeys and PKESK packets. Requires an allocator. |
+//! | `pool`      | Enables a pool of keypairs generated ahead of time by a background thread, so that taking one costs a move. Requires `std`. |
+//! | `prekey`    | Enables PQXDH-style Kyber prekey bundles signed with an Ed25519 identity key. Requires an allocator. |
+//! | `serde`     | Implements `Serialize` and `Deserialize` for keypairs, aligned keys and ciphertexts, as hex strings in human-readable formats and raw bytes in binary ones, and adds `serialization` for fields holding key arrays. |
+//! | `session`   | Enables a type-state handshake that derives one session key per direction. |
+//! | `sharing`   | Enables Shamir secret sharing of secret keys across custodians. Requires an allocator. |
 //! | `zeroize`   | Automatically zeroes out key exchange structs on drop using the [zeroize](https://docs.rs/zeroize/latest/zeroize/) crate |
-//! | `std`       | Enables the standard library (std). |
+//! | `tokio`     | Enables async equivalents of the `net` handshake helpers on tokio streams. Requires `std`. |
+//! | `std`       | Enables the standard library (std). Implies `alloc`. Adds `padded`, decapsulation padded to a fixed wall-clock duration. |
+//! | `alloc`     | Enables the modules returning heap-allocated values. Without it, and with `default-features = false`, the crate builds for targets with no allocator, such as `thumbv7em-none-eabihf`. |
+//! | `os-rng`    | Enabled by default. Links `getrandom` for the operating system RNG used by the `wasm`, `ffi` and `capi` functions and by `Keypair::random`. Without it every randomized function needs an explicit RNG or seed. |
+//! | `custom-entropy` | Enables `rng::HostRng`, drawing from an entropy source the host registers once. |
+//! | `rand09`    | Adds `rng::Rand09`, which adapts an RNG of rand_core 0.9 to the rand_core 0.6 traits the API takes, so generators of either version can be used. |
+//! | `ratchet`   | Enables a hash ratchet deriving forward-secret per-epoch keys from a shared secret. |
+//! | `replay`    | Enables a fixed-size ciphertext replay filter over caller-supplied storage. |
+//! | `research`  | Exposes polynomial-level primitives, such as a noise sampler with configurable eta. Not intended for production use. |
+//! | `sealing`   | Enables password-based sealing of secret keys with Argon2id and XChaCha20-Poly1305. |
+//! | `small`     | Favours code size over speed for flash-constrained microcontrollers: the (de)compression loops are compact instead of unrolled, the PRF reuses the AES of the XOF instead of linking the `aes` crate, and buffer length panics carry no formatted lengths. Outputs are unchanged. |
+//! | `stack-probe` | Enables `stack_probe::measure`, which reports the peak stack use of a closure run on a fresh thread, for sizing embedded targets. Requires `std`. |
+//! | `stack-probe-core` | Enables only `stack_probe::measure_in_place`, which paints and scans the current stack, for firmware without `std`. |
 //!
 //! ## Usage
 //!
-//! To optimize for x86 platforms, enable the `avx2` feature and set the following RUSTFLAGS:
+//! To optimize for x86 platforms, enable the `avx2` feature. The backend
+//! is picked at run time, so the same binary still runs on CPUs without
+//! AVX2. Setting the following RUSTFLAGS additionally lets the compiler
+//! use these instructions in the shared code, but the binary then requires
+//! them:
 //!
 //! ```shell
 //! export RUSTFLAGS="-C target-feature=+aes,+avx2,+sse2,+sse4.1,+bmi2,+popcnt"
@@ -150,6 +194,15 @@
 //! - **InvalidKey** - Error when generating keys.
 //! - **Decapsulation** - The ciphertext was unable to be authenticated. The shared secret was not decapsulated.
 //! - **RandomBytesGeneration** - Error trying to fill random bytes (i.e., external (hardware) RNG modules can fail).
+//! - **InvalidShare** - A secret share is corrupted, repeated, or belongs to another key.
+//! - **InvalidSignature** - A signature over a key does not verify under the expected signer.
+//! - **Unsealing** - A sealed secret key could not be opened because the password is wrong or the data was tampered with.
+//!
+//! Errors are plain enum values; their `Display` writes a static message and never formats into a `String`.
+//!
+//! ## Allocation-free guarantee
+//!
+//! The key generation, encapsulation and decapsulation functions, the key exchanges of `kex` and everything they call use fixed-size arrays and caller-provided slices only; none of them allocates, with or without `std`. This holds for every module available without the `alloc` feature. The modules listed above as requiring an allocator, including the batch functions `sharing::split_secret_key` and `prekey::generate_prekeys`, return `Vec`s and are outside the guarantee.
 //!
 #![doc(
     html_favicon_url = "https://kura.pro/kyberlib/images/favicon.ico",
@@ -159,6 +212,11 @@
 #![crate_name = "kyberlib"]
 #![crate_type = "lib"]
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(
+    feature = "portable-simd",
+    feature(portable_simd),
+    allow(unstable_features)
+)]
 
 // Prevent usage of mutually exclusive features
 #[cfg(all(feature = "kyber1024", feature = "kyber512"))]
@@ -166,52 +224,204 @@ compile_error!("Only one security level can be specified");
 
 #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
 mod avx2;
-#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
-use avx2::*;
+#[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+mod avx512;
+#[cfg(all(
+    target_arch = "aarch64",
+    target_feature = "neon",
+    feature = "neon"
+))]
+mod neon;
+#[cfg(all(
+    feature = "portable-simd",
+    not(any(
+        all(target_arch = "x86_64", feature = "avx2"),
+        all(
+            target_arch = "aarch64",
+            target_feature = "neon",
+            feature = "neon"
+        ),
+        all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+            feature = "wasm-simd"
+        ),
+        all(target_arch = "riscv64", feature = "rvv")
+    ))
+))]
+mod portable_simd;
+#[cfg(all(target_arch = "riscv64", feature = "rvv"))]
+mod rvv;
+#[cfg(any(
+    all(target_arch = "x86_64", feature = "avx512"),
+    all(
+        target_arch = "aarch64",
+        target_feature = "neon",
+        feature = "neon"
+    ),
+    all(
+        target_arch = "wasm32",
+        target_feature = "simd128",
+        feature = "wasm-simd"
+    ),
+    all(target_arch = "riscv64", feature = "rvv"),
+    all(
+        feature = "portable-simd",
+        not(all(target_arch = "x86_64", feature = "avx2"))
+    )
+))]
+mod simd;
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    feature = "wasm-simd"
+))]
+mod wasm_simd;
+
+mod dispatch;
+
+/// Differential fuzzing hooks for the KyberLib library.
+#[cfg(any(fuzzing, test))]
+#[doc(hidden)]
+pub mod fuzzing;
 
-#[cfg(any(not(target_arch = "x86_64"), not(feature = "avx2")))]
 /// Reference implementation for the KyberLib library.
 pub mod reference;
-#[cfg(any(not(target_arch = "x86_64"), not(feature = "avx2")))]
 use reference::*;
 
-#[cfg(any(not(target_arch = "x86_64"), not(feature = "avx2")))]
 #[cfg(feature = "hazmat")]
 pub use reference::indcpa;
 
-#[cfg(feature = "wasm")]
-/// WebAssembly bindings for the KyberLib library.
-pub mod wasm;
-
+/// Backend reporting for the KyberLib library.
+pub mod acceleration;
+/// age plugin recipients and identities for the KyberLib library.
+#[cfg(feature = "age-plugin")]
+pub mod age_plugin;
 /// API for the KyberLib library.
 pub mod api;
+/// Batch key generation and encapsulation for the KyberLib library.
+#[cfg(feature = "batch")]
+pub mod batch;
+/// Heap-allocated keys for the KyberLib library.
+#[cfg(feature = "boxed")]
+pub mod boxed;
+/// C API with explicit buffer lengths for the KyberLib library.
+#[cfg(feature = "capi")]
+pub mod capi;
+/// CMS KEMRecipientInfo support for the KyberLib library.
+#[cfg(feature = "cms")]
+pub mod cms;
+/// Constant-time hex and base64 codecs for the KyberLib library.
+pub mod codec;
+/// Key confirmation tags for the KyberLib library.
+#[cfg(feature = "confirmation")]
+pub mod confirmation;
+/// Key generation from hardware entropy sources for the KyberLib library.
+pub mod entropy;
 /// Error types for the KyberLib library.
 pub mod error;
+/// Expanded secret keys for the KyberLib library.
+#[cfg(feature = "expanded")]
+pub mod expanded;
+/// C ABI bindings for the KyberLib library.
+#[cfg(feature = "ffi")]
+pub mod ffi;
+/// Ciphertext fragmentation for the KyberLib library.
+#[cfg(feature = "fragment")]
+pub mod fragment;
+/// JWE key encapsulation for the KyberLib library.
+#[cfg(feature = "jose")]
+pub mod jose;
 /// Key encapsulation module for the KyberLib library.
 pub mod kem;
 /// Key exchange structs for the KyberLib library.
 pub mod kex;
+/// Decapsulation key ring for the KyberLib library.
+#[cfg(feature = "keyring")]
+pub mod keyring;
+/// Parameter-set generic API for the KyberLib library.
+#[cfg(feature = "levels")]
+pub mod levels;
 
+/// defmt logging for the KyberLib library.
+#[cfg(feature = "defmt")]
+pub mod logging;
 /// Macro utilities for the KyberLib library.
 pub mod macros;
+/// TCP handshake helpers for the KyberLib library.
+#[cfg(feature = "net")]
+pub mod net;
+/// Async TCP handshake helpers on tokio for the KyberLib library.
+#[cfg(feature = "tokio")]
+pub mod net_tokio;
+/// Decapsulation padded to a fixed duration for the KyberLib library.
+#[cfg(feature = "std")]
+pub mod padded;
 /// Parameters for the KyberLib library.
 pub mod params;
+/// OpenPGP composite key and PKESK fields for the KyberLib library.
+#[cfg(feature = "pgp")]
+pub mod pgp;
+/// Trust-on-first-use public key pinning for the KyberLib library.
+#[cfg(feature = "pinning")]
+pub mod pinning;
+/// Keypair pre-generation pool for the KyberLib library.
+#[cfg(feature = "pool")]
+pub mod pool;
+
+/// Password-based secret key sealing for the KyberLib library.
+#[cfg(feature = "sealing")]
+pub mod sealing;
+
+/// Polynomial-level research primitives for the KyberLib library.
+#[cfg(feature = "research")]
+pub mod research;
+
+/// Signed prekey bundles for the KyberLib library.
+#[cfg(feature = "prekey")]
+pub mod prekey;
+
+/// Hash ratchet for the KyberLib library.
+#[cfg(feature = "ratchet")]
+pub mod ratchet;
+
+/// Ciphertext replay filter for the KyberLib library.
+#[cfg(feature = "replay")]
+pub mod replay;
 
 /// Random number generators for the KyberLib library.
 pub mod rng;
+/// serde support for the KyberLib library.
+#[cfg(feature = "serde")]
+pub mod serialization;
+/// Type-state handshake sessions for the KyberLib library.
+#[cfg(feature = "session")]
+pub mod session;
+/// Shamir secret sharing of secret keys for the KyberLib library.
+#[cfg(feature = "sharing")]
+pub mod sharing;
+/// Stack high-water mark measurement for the KyberLib library.
+#[cfg(feature = "stack-probe-core")]
+pub mod stack_probe;
+/// Derivation of several keys from a shared secret for the KyberLib
+/// library.
+pub mod subkeys;
 /// Symmetric key encapsulation module for the KyberLib library.
 pub mod symmetric;
 
 /// WebAssembly bindings for the KyberLib library.
+#[cfg(feature = "wasm")]
 pub mod wasm;
 
+pub use acceleration::{acceleration, Acceleration};
 pub use api::*;
 pub use error::KyberLibError;
 pub use kex::*;
 pub use params::{
-    KYBER_90S, KYBER_CIPHERTEXT_BYTES, KYBER_PUBLIC_KEY_BYTES,
-    KYBER_SECRET_KEY_BYTES, KYBER_SECURITY_PARAMETER,
-    KYBER_SHARED_SECRET_BYTES, KYBER_SYM_BYTES,
+    KYBER_90S, KYBER_CIPHERTEXT_BYTES, KYBER_COMPACT_SECRET_KEY_BYTES,
+    KYBER_PUBLIC_KEY_BYTES, KYBER_SECRET_KEY_BYTES,
+    KYBER_SECURITY_PARAMETER, KYBER_SHARED_SECRET_BYTES,
+    KYBER_SYM_BYTES,
 };
 pub use rand_core::{CryptoRng, RngCore};
 
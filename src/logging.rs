@@ -0,0 +1,105 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! [`defmt`](https://defmt.ferrous-systems.com) logging of kyberlib
+//! values on embedded targets.
+//!
+//! [`KyberLibError`](crate::KyberLibError) and
+//! [`Acceleration`](crate::Acceleration) implement `defmt::Format`
+//! directly. Public keys and ciphertexts are plain byte arrays, so they are
+//! logged through the [`ShortPublicKey`] and [`ShortCiphertext`] wrappers,
+//! which print the length and the first bytes of the hash `H` of the
+//! value rather than kilobytes of key material; [`Params`] prints the
+//! parameter set the crate was built with.
+//!
+//! Secrets are never formatted. Secret keys and shared secrets, being
+//! arrays too, have no formatting wrapper, and the structs holding them,
+//! [`Keypair`](crate::Keypair) and [`Encapsulation`](crate::Encapsulation),
+//! print `<redacted>` in their place. Nothing here allocates.
+//!
+//! ```ignore
+//! let keys = kyberlib::derive(&seed)?;
+//! defmt::info!("{}: {}", Params, ShortPublicKey(&keys.public));
+//! if let Err(err) = kyberlib::decapsulate(&ct, &keys.secret) {
+//!     defmt::warn!("{}: {}", ShortCiphertext(&ct), err);
+//! }
+//! ```
+
+use crate::params::*;
+use crate::symmetric::hash_h;
+
+/// Bytes of the hash `H` of a value printed by the wrappers.
+pub const FINGERPRINT_PREFIX_BYTES: usize = 8;
+
+// The first `FINGERPRINT_PREFIX_BYTES` bytes of `H(bytes)`.
+fn fingerprint(bytes: &[u8]) -> [u8; FINGERPRINT_PREFIX_BYTES] {
+    let mut digest = [0u8; KYBER_SYM_BYTES];
+    hash_h(&mut digest, bytes, bytes.len());
+    let mut prefix = [0u8; FINGERPRINT_PREFIX_BYTES];
+    prefix.copy_from_slice(&digest[..FINGERPRINT_PREFIX_BYTES]);
+    prefix
+}
+
+/// Formats a public key as its length and a hash prefix, e.g.
+/// `PublicKey(1568 bytes, H=a1b2c3d4e5f60718..)`.
+///
+/// Takes a slice so that a public key of the wrong length, as received
+/// from a peer, can be logged too.
+#[derive(Clone, Copy, Debug)]
+pub struct ShortPublicKey<'a>(pub &'a [u8]);
+
+impl defmt::Format for ShortPublicKey<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "PublicKey({=usize} bytes, H={=[u8]:02x}..)",
+            self.0.len(),
+            fingerprint(self.0)
+        )
+    }
+}
+
+/// Formats a ciphertext as its length and a hash prefix, e.g.
+/// `Ciphertext(1568 bytes, H=0718e5f6a1b2c3d4..)`.
+///
+/// Takes a slice so that a truncated ciphertext can be logged too.
+#[derive(Clone, Copy, Debug)]
+pub struct ShortCiphertext<'a>(pub &'a [u8]);
+
+impl defmt::Format for ShortCiphertext<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "Ciphertext({=usize} bytes, H={=[u8]:02x}..)",
+            self.0.len(),
+            fingerprint(self.0)
+        )
+    }
+}
+
+/// Formats the parameter set the crate was built with, e.g.
+/// `Kyber1024 (k=4, 90s, category 5): pk=1568 sk=3168 ct=1568 bytes`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Params;
+
+impl defmt::Format for Params {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        let name = match KYBER_SECURITY_PARAMETER {
+            2 => "Kyber512",
+            3 => "Kyber768",
+            _ => "Kyber1024",
+        };
+        let mode = if KYBER_90S { "90s" } else { "standard" };
+        defmt::write!(
+            f,
+            "{=str} (k={=usize}, {=str}, category {=u8}): pk={=usize} sk={=usize} ct={=usize} bytes",
+            name,
+            KYBER_SECURITY_PARAMETER,
+            mode,
+            security_category(),
+            KYBER_PUBLIC_KEY_BYTES,
+            KYBER_SECRET_KEY_BYTES,
+            KYBER_CIPHERTEXT_BYTES
+        )
+    }
+}
@@ -235,3 +235,37 @@ macro_rules! kyberlib_ake_client_confirm {
         kyberlib::kex::Ake::new().client_confirm($send_b, $secretkey)
     };
 }
+
+/// Embeds a public key file in the binary, validated at compile time.
+///
+/// Expands to a `PublicKey` constant read from `path` with
+/// `include_bytes!`, so the path is relative to the file invoking the
+/// macro. The key is checked by [`public_key_from_bytes_const`] during
+/// const evaluation: a file of the wrong length or with an out-of-range
+/// coefficient fails the build instead of the boot.
+///
+/// # Examples
+///
+/// ```ignore
+/// use kyberlib::{include_pubkey, PublicKey};
+///
+/// static SERVER_KEY: PublicKey = include_pubkey!("../keys/server.pk");
+/// ```
+///
+/// [`public_key_from_bytes_const`]: crate::public_key_from_bytes_const
+#[macro_export]
+macro_rules! include_pubkey {
+    ($path:literal $(,)?) => {{
+        const KEY: $crate::PublicKey =
+            match $crate::public_key_from_bytes_const(include_bytes!(
+                $path
+            )) {
+                Ok(key) => key,
+                Err(_) => panic!(concat!(
+                    "invalid Kyber public key in ",
+                    $path
+                )),
+            };
+        KEY
+    }};
+}
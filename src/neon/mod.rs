@@ -0,0 +1,66 @@
+//! NEON implementation of the IND-CPA layer for AArch64, selected at
+//! compile time.
+//!
+//! The NTT, inverse NTT, base multiplication, the centered binomial
+//! sampler for eta = 2 and the 12-bit (de)serialization of polynomials
+//! work on 128-bit vectors of 8 coefficients; matrix sampling and
+//! (de)compression are shared with the reference implementation. The
+//! kernels keep the coefficients in the standard order of the reference
+//! and produce the same keys, ciphertexts and messages bit for bit.
+//!
+//! NEON is part of the AArch64 baseline, so there is nothing to detect:
+//! the backend is compiled in whenever the `neon` feature is enabled on a
+//! target with the `neon` target feature, which all AArch64 targets but
+//! the soft-float ones have. With the `force-backend` feature,
+//! `acceleration::force_backend` still switches a process to the
+//! reference code at run time.
+
+pub(crate) mod indcpa;
+mod poly;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::differential;
+
+    #[test]
+    fn ntt_matches_reference() {
+        differential::ntt(
+            |r| unsafe { poly::poly_ntt(r) },
+            |r| unsafe { poly::poly_invntt_tomont(r) },
+        );
+    }
+
+    #[test]
+    fn basemul_matches_reference() {
+        differential::basemul(|r, a, b| unsafe {
+            poly::polyvec_basemul_acc_montgomery(r, a, b)
+        });
+    }
+
+    #[test]
+    fn cbd_matches_reference() {
+        differential::cbd2(|r, buf| unsafe { poly::cbd2(r, buf) });
+    }
+
+    #[test]
+    fn serialization_matches_reference() {
+        differential::serialization(
+            |bytes, r| unsafe { poly::poly_tobytes(bytes, r) },
+            |r, bytes| unsafe { poly::poly_frombytes(r, bytes) },
+        );
+    }
+
+    #[test]
+    fn indcpa_matches_reference() {
+        differential::indcpa(
+            |pk, sk, seed, rng| unsafe {
+                indcpa::indcpa_keypair(pk, sk, seed, rng)
+            },
+            |c, m, pk, coins| unsafe {
+                indcpa::indcpa_enc(c, m, pk, coins)
+            },
+            |m, c, sk| unsafe { indcpa::indcpa_dec(m, c, sk) },
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use crate::acceleration::{count_ntt, Acceleration};
+use crate::reference::ntt::ZETAS;
+use crate::{
+    cbd::poly_cbd_eta1, params::*, poly::Poly, polyvec::Polyvec,
+    symmetric::PrfReader,
+};
+use core::arch::aarch64::*;
+
+const Q: i16 = KYBER_Q as i16;
+const QINV: i16 = -3327; // q^(-1) mod 2^16
+const V: i16 = 20159; // ((1 << 26) + q/2) / q
+const F: i16 = 1441; // mont^2/128
+
+// Zetas of the NTT layers with len 4 and 2, whose butterflies lie within
+// a window of two vectors, i.e. 16 coefficients. The windows are
+// transposed so that the first operands of the butterflies fill one
+// vector and the second operands the other; each entry holds the zeta of
+// every lane of a window, in the order the transposes leave them.
+struct Layer {
+    zetas: [[i16; 8]; KYBER_N / 16],
+    inv_zetas: [[i16; 8]; KYBER_N / 16],
+}
+
+const fn layer(len: usize) -> Layer {
+    // Block of 2 * len coefficients of the window that each lane holds
+    const LEN2_BLOCKS: [usize; 8] = [0, 0, 2, 2, 1, 1, 3, 3];
+    let mut l = Layer {
+        zetas: [[0; 8]; KYBER_N / 16],
+        inv_zetas: [[0; 8]; KYBER_N / 16],
+    };
+    let mut w = 0;
+    while w < KYBER_N / 16 {
+        let mut m = 0;
+        while m < 8 {
+            let within = if len == 4 { m / 4 } else { LEN2_BLOCKS[m] };
+            let block = w * (8 / len) + within;
+            l.zetas[w][m] = ZETAS[128 / len + block];
+            l.inv_zetas[w][m] = ZETAS[256 / len - 1 - block];
+            m += 1;
+        }
+        w += 1;
+    }
+    l
+}
+
+static LEN4: Layer = layer(4);
+static LEN2: Layer = layer(2);
+
+// The zeta of each pair of coefficients in the NTT domain, for the eight
+// pairs of every 16 coefficients: the basemul of pairs 2i and 2i + 1
+// reduces modulo X^2 - zeta and X^2 + zeta.
+static BASEMUL_ZETAS: [[i16; 8]; KYBER_N / 16] = {
+    let mut zetas = [[0i16; 8]; KYBER_N / 16];
+    let mut p = 0;
+    while p < KYBER_N / 2 {
+        let zeta = ZETAS[64 + p / 2];
+        zetas[p / 8][p % 8] = if p % 2 == 0 { zeta } else { -zeta };
+        p += 1;
+    }
+    zetas
+};
+
+#[target_feature(enable = "neon")]
+fn load(a: &[i16]) -> int16x8_t {
+    let a = &a[..8];
+    // SAFETY: a holds 8 coefficients.
+    unsafe { vld1q_s16(a.as_ptr()) }
+}
+
+#[target_feature(enable = "neon")]
+fn store(r: &mut [i16], a: int16x8_t) {
+    let r = &mut r[..8];
+    // SAFETY: r holds 8 coefficients.
+    unsafe { vst1q_s16(r.as_mut_ptr(), a) }
+}
+
+// The even and the odd coefficients of 16 in a row.
+#[target_feature(enable = "neon")]
+fn load_pairs(a: &[i16]) -> int16x8x2_t {
+    let a = &a[..16];
+    // SAFETY: a holds 16 coefficients.
+    unsafe { vld2q_s16(a.as_ptr()) }
+}
+
+#[target_feature(enable = "neon")]
+fn store_pairs(r: &mut [i16], a: int16x8x2_t) {
+    let r = &mut r[..16];
+    // SAFETY: r holds 16 coefficients.
+    unsafe { vst2q_s16(r.as_mut_ptr(), a) }
+}
+
+// montgomery_reduce(a * b) in every lane, exactly as the reference
+// computes it: vqdmulhq_s16 doubles the high halves of both products, and
+// as their low halves agree the halving subtraction is exact.
+#[target_feature(enable = "neon")]
+fn fqmul(a: int16x8_t, b: int16x8_t) -> int16x8_t {
+    let u = vmulq_s16(vmulq_s16(a, b), vdupq_n_s16(QINV));
+    vhsubq_s16(vqdmulhq_s16(a, b), vqdmulhq_s16(u, vdupq_n_s16(Q)))
+}
+
+// montgomery_reduce of four 32-bit lanes.
+#[target_feature(enable = "neon")]
+fn montgomery_reduce32(a: int32x4_t) -> int16x4_t {
+    let u = vmovn_s32(vmulq_s32(a, vdupq_n_s32(QINV as i32)));
+    vshrn_n_s32::<16>(vmlsl_s16(a, u, vdup_n_s16(Q)))
+}
+
+// barrett_reduce in every lane: round(a * v / 2^26) is computed as
+// (floor(a * v / 2^15) + 2^10) >> 11, which is the same number.
+#[target_feature(enable = "neon")]
+fn barrett_reduce(a: int16x8_t) -> int16x8_t {
+    let t = vrshrq_n_s16::<11>(vqdmulhq_s16(a, vdupq_n_s16(V)));
+    vmlsq_s16(a, t, vdupq_n_s16(Q))
+}
+
+// Splits a window of 16 coefficients into the first and the second
+// operands of the butterflies of len 4, and back: the transpose is its
+// own inverse.
+#[target_feature(enable = "neon")]
+fn transpose64(a: int16x8_t, b: int16x8_t) -> (int16x8_t, int16x8_t) {
+    let (a, b) = (vreinterpretq_s64_s16(a), vreinterpretq_s64_s16(b));
+    (
+        vreinterpretq_s16_s64(vtrn1q_s64(a, b)),
+        vreinterpretq_s16_s64(vtrn2q_s64(a, b)),
+    )
+}
+
+// The same for len 2.
+#[target_feature(enable = "neon")]
+fn transpose32(a: int16x8_t, b: int16x8_t) -> (int16x8_t, int16x8_t) {
+    let (a, b) = (vreinterpretq_s32_s16(a), vreinterpretq_s32_s16(b));
+    (
+        vreinterpretq_s16_s32(vtrn1q_s32(a, b)),
+        vreinterpretq_s16_s32(vtrn2q_s32(a, b)),
+    )
+}
+
+/// Name:  poly_ntt
+///
+/// Description: Computes the NTT of a polynomial in place like the
+///  reference poly_ntt, with identical output; inputs in normal order,
+///  output in bitreversed order
+///
+/// Arguments:   - Poly r: in/output polynomial
+#[target_feature(enable = "neon")]
+pub(crate) fn poly_ntt(r: &mut Poly) {
+    count_ntt(Acceleration::Neon);
+    let mut v = [vdupq_n_s16(0); KYBER_N / 8];
+    for (v, c) in v.iter_mut().zip(r.coeffs.chunks_exact(8)) {
+        *v = load(c);
+    }
+
+    // len 128 down to 8: butterflies between whole vectors
+    let mut k = 1;
+    for dist in [16, 8, 4, 2, 1] {
+        for start in (0..v.len()).step_by(2 * dist) {
+            let zeta = vdupq_n_s16(ZETAS[k]);
+            k += 1;
+            for j in start..start + dist {
+                let t = fqmul(zeta, v[j + dist]);
+                v[j + dist] = vsubq_s16(v[j], t);
+                v[j] = vaddq_s16(v[j], t);
+            }
+        }
+    }
+
+    // len 4 and 2: butterflies within windows of two vectors
+    for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+        let (a, b) = transpose64(pair[0], pair[1]);
+        let t = fqmul(load(&LEN4.zetas[w]), b);
+        let (a, b) = (vaddq_s16(a, t), vsubq_s16(a, t));
+        let (a, b) = transpose64(a, b);
+
+        let (a, b) = transpose32(a, b);
+        let t = fqmul(load(&LEN2.zetas[w]), b);
+        let (a, b) = (vaddq_s16(a, t), vsubq_s16(a, t));
+        (pair[0], pair[1]) = transpose32(a, b);
+    }
+
+    for (c, v) in r.coeffs.chunks_exact_mut(8).zip(v) {
+        store(c, barrett_reduce(v));
+    }
+}
+
+/// Name:  poly_invntt_tomont
+///
+/// Description: Computes the inverse NTT of a polynomial in place and
+///  multiplies by the Montgomery factor like the reference
+///  poly_invntt_tomont, with identical output; inputs in bitreversed
+///  order, output in normal order
+///
+/// Arguments:   - Poly r: in/output polynomial
+#[target_feature(enable = "neon")]
+pub(crate) fn poly_invntt_tomont(r: &mut Poly) {
+    let mut v = [vdupq_n_s16(0); KYBER_N / 8];
+    for (v, c) in v.iter_mut().zip(r.coeffs.chunks_exact(8)) {
+        *v = load(c);
+    }
+
+    // len 2 and 4: butterflies within windows of two vectors
+    for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+        let (a, b) = transpose32(pair[0], pair[1]);
+        let t = vsubq_s16(b, a);
+        let a = barrett_reduce(vaddq_s16(a, b));
+        let b = fqmul(load(&LEN2.inv_zetas[w]), t);
+        let (a, b) = transpose32(a, b);
+
+        let (a, b) = transpose64(a, b);
+        let t = vsubq_s16(b, a);
+        let a = barrett_reduce(vaddq_s16(a, b));
+        let b = fqmul(load(&LEN4.inv_zetas[w]), t);
+        (pair[0], pair[1]) = transpose64(a, b);
+    }
+
+    // len 8 up to 128: butterflies between whole vectors
+    for dist in [1, 2, 4, 8, 16] {
+        let first = 256 / (8 * dist) - 1;
+        for (g, start) in (0..v.len()).step_by(2 * dist).enumerate() {
+            let zeta = vdupq_n_s16(ZETAS[first - g]);
+            for j in start..start + dist {
+                let t = vsubq_s16(v[j + dist], v[j]);
+                v[j] = barrett_reduce(vaddq_s16(v[j], v[j + dist]));
+                v[j + dist] = fqmul(zeta, t);
+            }
+        }
+    }
+
+    let f = vdupq_n_s16(F);
+    for (c, v) in r.coeffs.chunks_exact_mut(8).zip(v) {
+        store(c, fqmul(v, f));
+    }
+}
+
+/// Name:  polyvec_basemul_acc_montgomery
+///
+/// Description: Pointwise multiply elements of a and b and accumulate
+///  into r, with the lazy i32 accumulate of the reference and identical
+///  output: vld2q_s16 splits every 16 coefficients into the first and
+///  second coefficients of the pairs, and vmlal_s16 sums their products
+///  in 32-bit lanes
+///
+/// Arguments: - poly *r:  output polynomial
+///  - const Polyvec a: first input vector of polynomials
+///  - const Polyvec b: second input vector of polynomials
+#[target_feature(enable = "neon")]
+pub(crate) fn polyvec_basemul_acc_montgomery(
+    r: &mut Poly,
+    a: &Polyvec,
+    b: &Polyvec,
+) {
+    for (g, out) in r.coeffs.chunks_exact_mut(16).enumerate() {
+        let zetas = load(&BASEMUL_ZETAS[g]);
+        let mut even = [vdupq_n_s32(0); 2];
+        let mut odd = [vdupq_n_s32(0); 2];
+        for (a, b) in a.vec.iter().zip(b.vec.iter()) {
+            let a = load_pairs(&a.coeffs[16 * g..]);
+            let b = load_pairs(&b.coeffs[16 * g..]);
+            let (a0, a1, b0, b1) = (a.0, a.1, b.0, b.1);
+            let bz = fqmul(b1, zetas);
+            // a0 * b0 + a1 * b1 * zeta and a0 * b1 + a1 * b0
+            even[0] =
+                vmlal_s16(even[0], vget_low_s16(a0), vget_low_s16(b0));
+            even[0] =
+                vmlal_s16(even[0], vget_low_s16(a1), vget_low_s16(bz));
+            even[1] =
+                vmlal_high_s16(vmlal_high_s16(even[1], a0, b0), a1, bz);
+            odd[0] =
+                vmlal_s16(odd[0], vget_low_s16(a0), vget_low_s16(b1));
+            odd[0] =
+                vmlal_s16(odd[0], vget_low_s16(a1), vget_low_s16(b0));
+            odd[1] =
+                vmlal_high_s16(vmlal_high_s16(odd[1], a0, b1), a1, b0);
+        }
+        let even = vcombine_s16(
+            montgomery_reduce32(even[0]),
+            montgomery_reduce32(even[1]),
+        );
+        let odd = vcombine_s16(
+            montgomery_reduce32(odd[0]),
+            montgomery_reduce32(odd[1]),
+        );
+        store_pairs(
+            out,
+            int16x8x2_t(barrett_reduce(even), barrett_reduce(odd)),
+        );
+    }
+}
+
+/// Name:  poly_reduce
+///
+/// Description: Applies the reference Barrett reduction to all
+///  coefficients of a polynomial, giving the centered representatives
+///
+/// Arguments:   - poly *r:   input/output polynomial
+#[target_feature(enable = "neon")]
+pub(crate) fn poly_reduce(r: &mut Poly) {
+    for c in r.coeffs.chunks_exact_mut(8) {
+        store(c, barrett_reduce(load(c)));
+    }
+}
+
+/// Name:  poly_tobytes
+///
+/// Description: Serialization of a polynomial like the reference
+///  poly_tobytes; coefficients must be in {-q+1,...,q-1}. Panics if r is
+///  shorter than KYBER_POLY_BYTES, before anything is written
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLY_BYTES bytes)
+///  - const poly *a:  input polynomial
+#[target_feature(enable = "neon")]
+pub(crate) fn poly_tobytes(r: &mut [u8], a: &Poly) {
+    let r = &mut r[..KYBER_POLY_BYTES];
+    let q = vdupq_n_s16(Q);
+    for (out, c) in
+        r.chunks_exact_mut(24).zip(a.coeffs.chunks_exact(16))
+    {
+        let t = load_pairs(c);
+        // map to positive standard representatives
+        let t0 = vaddq_s16(t.0, vandq_s16(vshrq_n_s16::<15>(t.0), q));
+        let t1 = vaddq_s16(t.1, vandq_s16(vshrq_n_s16::<15>(t.1), q));
+        let (t0, t1) =
+            (vreinterpretq_u16_s16(t0), vreinterpretq_u16_s16(t1));
+        let bytes = uint8x8x3_t(
+            vmovn_u16(t0),
+            vmovn_u16(vorrq_u16(
+                vshrq_n_u16::<8>(t0),
+                vshlq_n_u16::<4>(t1),
+            )),
+            vmovn_u16(vshrq_n_u16::<4>(t1)),
+        );
+        // SAFETY: out is 24 bytes long.
+        unsafe { vst3_u8(out.as_mut_ptr(), bytes) };
+    }
+}
+
+/// Name:  poly_frombytes
+///
+/// Description: De-serialization of a polynomial like the reference
+///  poly_frombytes. Panics if a is shorter than KYBER_POLY_BYTES
+///
+/// Arguments:   - poly *r:  output polynomial
+///  - const [u8] a: input byte array (of KYBER_POLY_BYTES bytes)
+#[target_feature(enable = "neon")]
+pub(crate) fn poly_frombytes(r: &mut Poly, a: &[u8]) {
+    let a = &a[..KYBER_POLY_BYTES];
+    for (c, bytes) in
+        r.coeffs.chunks_exact_mut(16).zip(a.chunks_exact(24))
+    {
+        // SAFETY: bytes is 24 bytes long.
+        let b = unsafe { vld3_u8(bytes.as_ptr()) };
+        let (b0, b1, b2) =
+            (vmovl_u8(b.0), vmovl_u8(b.1), vmovl_u8(b.2));
+        let t0 = vorrq_u16(
+            b0,
+            vshlq_n_u16::<8>(vandq_u16(b1, vdupq_n_u16(15))),
+        );
+        let t1 = vorrq_u16(vshrq_n_u16::<4>(b1), vshlq_n_u16::<4>(b2));
+        store_pairs(
+            c,
+            int16x8x2_t(
+                vreinterpretq_s16_u16(t0),
+                vreinterpretq_s16_u16(t1),
+            ),
+        );
+    }
+}
+
+/// Name:  cbd2
+///
+/// Description: Given an array of uniformly random bytes, compute
+///  polynomial with coefficients distributed according to
+///  a centered binomial distribution with parameter eta=2, like the
+///  reference cbd2. Every byte yields two coefficients, so the bit sums
+///  are taken bytewise
+///
+/// Arguments:   - poly *r:    output polynomial
+///  - const [u8] buf: input byte array
+#[target_feature(enable = "neon")]
+pub(crate) fn cbd2(r: &mut Poly, buf: &[u8]) {
+    let buf = &buf[..2 * KYBER_N / 4];
+    let (m55, m3) = (vdupq_n_u8(0x55), vdupq_n_u8(3));
+    for (c, bytes) in
+        r.coeffs.chunks_exact_mut(32).zip(buf.chunks_exact(16))
+    {
+        // SAFETY: bytes is 16 bytes long.
+        let x = unsafe { vld1q_u8(bytes.as_ptr()) };
+        let d = vaddq_u8(
+            vandq_u8(x, m55),
+            vandq_u8(vshrq_n_u8::<1>(x), m55),
+        );
+        let diff = |lo: uint8x16_t, hi: uint8x16_t| {
+            vsubq_s8(
+                vreinterpretq_s8_u8(vandq_u8(lo, m3)),
+                vreinterpretq_s8_u8(vandq_u8(hi, m3)),
+            )
+        };
+        // The low and the high nibble of each byte
+        let lo = diff(d, vshrq_n_u8::<2>(d));
+        let hi = diff(vshrq_n_u8::<4>(d), vshrq_n_u8::<6>(d));
+        let (first, second) = (vzip1q_s8(lo, hi), vzip2q_s8(lo, hi));
+        store(&mut c[..8], vmovl_s8(vget_low_s8(first)));
+        store(&mut c[8..16], vmovl_high_s8(first));
+        store(&mut c[16..24], vmovl_s8(vget_low_s8(second)));
+        store(&mut c[24..], vmovl_high_s8(second));
+    }
+}
+
+/// Name:  polyvec_ntt
+///
+/// Description: Apply forward NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+#[target_feature(enable = "neon")]
+pub(crate) fn polyvec_ntt(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_ntt(poly);
+    }
+}
+
+/// Name:  polyvec_invntt_tomont
+///
+/// Description: Apply inverse NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+#[target_feature(enable = "neon")]
+pub(crate) fn polyvec_invntt_tomont(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_invntt_tomont(poly);
+    }
+}
+
+/// Name:  polyvec_reduce
+///
+/// Description: Applies the reference Barrett reduction to each
+///  coefficient of each element of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: input/output vector of polynomials
+#[target_feature(enable = "neon")]
+pub(crate) fn polyvec_reduce(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_reduce(poly);
+    }
+}
+
+/// Name:  polyvec_tobytes
+///
+/// Description: Serialize vector of polynomials
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLYVEC_BYTES)
+///  - const Polyvec a: input vector of polynomials
+#[target_feature(enable = "neon")]
+pub(crate) fn polyvec_tobytes(r: &mut [u8], a: &Polyvec) {
+    for (r, poly) in r.chunks_mut(KYBER_POLY_BYTES).zip(a.vec.iter()) {
+        poly_tobytes(r, poly);
+    }
+}
+
+/// Name:  polyvec_frombytes
+///
+/// Description: De-serialize vector of polynomials;
+///  inverse of polyvec_tobytes
+///
+/// Arguments:   - Polyvec r: output vector of polynomials
+///  - const [u8] a: input byte array (of length KYBER_POLYVEC_BYTES)
+#[target_feature(enable = "neon")]
+pub(crate) fn polyvec_frombytes(r: &mut Polyvec, a: &[u8]) {
+    for (poly, a) in r.vec.iter_mut().zip(a.chunks(KYBER_POLY_BYTES)) {
+        poly_frombytes(poly, a);
+    }
+}
+
+/// Name:  poly_getnoise_eta1
+///
+/// Description: Sample a polynomial deterministically from a seed and a nonce,
+///  like the reference poly_getnoise_eta1
+///
+/// Arguments:   - poly *r:     output polynomial
+///  - const [u8] seed: input seed (pointing to array of length KYBER_SYM_BYTES bytes)
+///  - [u8]  nonce:   one-byte input nonce
+#[target_feature(enable = "neon")]
+pub(crate) fn poly_getnoise_eta1(r: &mut Poly, seed: &[u8], nonce: u8) {
+    const LENGTH: usize = KYBER_ETA1 * KYBER_N / 4;
+    let buf: [u8; LENGTH] = PrfReader::new(seed, nonce).keystream();
+    if KYBER_ETA1 == 2 {
+        cbd2(r, &buf);
+    } else {
+        poly_cbd_eta1(r, &buf);
+    }
+}
+
+/// Name:  poly_getnoise_eta2
+///
+/// Description: Sample a polynomial deterministically from a seed and a nonce,
+///  like the reference poly_getnoise_eta2
+///
+/// Arguments:   - poly *r:     output polynomial
+///  - const [u8] seed: input seed (pointing to array of length KYBER_SYM_BYTES bytes)
+///  - [u8]  nonce:   one-byte input nonce
+#[target_feature(enable = "neon")]
+pub(crate) fn poly_getnoise_eta2(r: &mut Poly, seed: &[u8], nonce: u8) {
+    const LENGTH: usize = KYBER_ETA2 * KYBER_N / 4;
+    let buf: [u8; LENGTH] = PrfReader::new(seed, nonce).keystream();
+    cbd2(r, &buf);
+}
@@ -0,0 +1,272 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A minimal KEM handshake over TCP for examples, tests and small tools.
+//!
+//! The client knows the server's public key, encapsulates to it and sends
+//! the ciphertext; both ends then hold the same shared secret. The stream
+//! itself is not encrypted or authenticated, that is left to the caller.
+//! The `tokio` feature adds async equivalents in `net_tokio`.
+//!
+//! ## Protocol, version 1
+//!
+//! The client sends one request frame:
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 1     | protocol version, currently 1 |
+//! | 1     | security level, `KYBER_SECURITY_PARAMETER` (2, 3 or 4) |
+//! | 2     | ciphertext length, big-endian |
+//! | n     | ciphertext |
+//!
+//! and the server answers with a response frame:
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 1     | protocol version, currently 1 |
+//! | 1     | status: 0 accepted, 1 unsupported version, 2 level mismatch, 3 bad length |
+//!
+//! A server that does not accept the request closes the connection after
+//! the response. The ciphertext length has to be exactly
+//! `KYBER_CIPHERTEXT_BYTES`; the server checks it before reading the
+//! ciphertext, so a peer cannot make it buffer more. Every read and write
+//! of the handshake is bounded by [`HANDSHAKE_TIMEOUT`], after which the
+//! timeouts are cleared again.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::net::*;
+//! # use std::net::TcpListener;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let listener = TcpListener::bind("127.0.0.1:0")?;
+//! let addr = listener.local_addr()?;
+//! let public = keys.public;
+//! let client = std::thread::spawn(move || connect_handshake(addr, &public));
+//!
+//! let (server_secret, _stream) =
+//!     serve_handshake(&listener, &keys).next().unwrap()?;
+//! let (client_secret, _stream) = client.join().unwrap()?;
+//! assert_eq!(server_secret, client_secret);
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    api::{decapsulate, encapsulate, Keypair},
+    kex::{PublicKey, SharedSecret},
+    params::*,
+};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Version of the handshake protocol.
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Default bound on each read and write of the handshake.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const STATUS_ACCEPTED: u8 = 0;
+const STATUS_UNSUPPORTED_VERSION: u8 = 1;
+const STATUS_LEVEL_MISMATCH: u8 = 2;
+const STATUS_BAD_LENGTH: u8 = 3;
+
+/// Connects to `addr` and runs the client side of the handshake against
+/// the server's public key `pk`.
+///
+/// # Errors
+///
+/// Returns `io::ErrorKind::InvalidData` if the server rejects the request
+/// or answers with another protocol version, a timeout error if the
+/// server is too slow and any error connecting or talking to it.
+pub fn connect_handshake<A: ToSocketAddrs>(
+    addr: A,
+    pk: &PublicKey,
+) -> io::Result<(SharedSecret, TcpStream)> {
+    let mut last = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, HANDSHAKE_TIMEOUT) {
+            Ok(stream) => return client(stream, pk),
+            Err(err) => last = Some(err),
+        }
+    }
+    Err(last.unwrap_or_else(|| invalid("no address to connect to")))
+}
+
+fn client(
+    mut stream: TcpStream,
+    pk: &PublicKey,
+) -> io::Result<(SharedSecret, TcpStream)> {
+    set_timeouts(&stream, Some(HANDSHAKE_TIMEOUT))?;
+    let (request, ss) = request(pk)?;
+    stream.write_all(&request)?;
+
+    let mut response = [0u8; RESPONSE_BYTES];
+    stream.read_exact(&mut response)?;
+    check_response(response)?;
+    set_timeouts(&stream, None)?;
+    Ok((ss, stream))
+}
+
+/// Size in bytes of a request frame.
+pub(crate) const REQUEST_BYTES: usize = 4 + KYBER_CIPHERTEXT_BYTES;
+/// Size in bytes of a request frame without the ciphertext.
+pub(crate) const HEADER_BYTES: usize = 4;
+/// Size in bytes of a response frame.
+pub(crate) const RESPONSE_BYTES: usize = 2;
+/// The response frame accepting a request.
+pub(crate) const ACCEPTED: [u8; RESPONSE_BYTES] =
+    [PROTOCOL_VERSION, STATUS_ACCEPTED];
+
+/// Encapsulates to `pk` and frames the ciphertext as a request.
+pub(crate) fn request(
+    pk: &PublicKey,
+) -> io::Result<([u8; REQUEST_BYTES], SharedSecret)> {
+    let (ct, ss) = encapsulate(pk, &mut rand::rngs::OsRng)
+        .map_err(|e| invalid(&e.to_string()))?;
+    let mut request = [0u8; REQUEST_BYTES];
+    request[0] = PROTOCOL_VERSION;
+    request[1] = KYBER_SECURITY_PARAMETER as u8;
+    request[2..4].copy_from_slice(
+        &(KYBER_CIPHERTEXT_BYTES as u16).to_be_bytes(),
+    );
+    request[HEADER_BYTES..].copy_from_slice(&ct);
+    Ok((request, ss))
+}
+
+/// Checks the server's response frame.
+pub(crate) fn check_response(
+    response: [u8; RESPONSE_BYTES],
+) -> io::Result<()> {
+    match response {
+        [PROTOCOL_VERSION, STATUS_ACCEPTED] => Ok(()),
+        [PROTOCOL_VERSION, STATUS_UNSUPPORTED_VERSION] => {
+            Err(invalid("server does not support protocol version 1"))
+        }
+        [PROTOCOL_VERSION, STATUS_LEVEL_MISMATCH] => {
+            Err(invalid("server uses another security level"))
+        }
+        [PROTOCOL_VERSION, _] => {
+            Err(invalid("server rejected the handshake"))
+        }
+        _ => Err(invalid("server speaks another protocol version")),
+    }
+}
+
+/// Checks the header of a request frame, returning the response frame
+/// and error for a request the server does not accept.
+pub(crate) fn check_header(
+    header: [u8; HEADER_BYTES],
+) -> Result<(), ([u8; RESPONSE_BYTES], io::Error)> {
+    let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+    let (status, message) = if header[0] != PROTOCOL_VERSION {
+        (STATUS_UNSUPPORTED_VERSION, "unsupported protocol version")
+    } else if header[1] != KYBER_SECURITY_PARAMETER as u8 {
+        (STATUS_LEVEL_MISMATCH, "client uses another security level")
+    } else if length != KYBER_CIPHERTEXT_BYTES {
+        (STATUS_BAD_LENGTH, "ciphertext has the wrong length")
+    } else {
+        return Ok(());
+    };
+    Err(([PROTOCOL_VERSION, status], invalid(message)))
+}
+
+/// Decapsulates the ciphertext of an accepted request.
+pub(crate) fn respond(
+    ct: &[u8; KYBER_CIPHERTEXT_BYTES],
+    keys: &Keypair,
+) -> io::Result<SharedSecret> {
+    decapsulate(ct, &keys.secret).map_err(|e| invalid(&e.to_string()))
+}
+
+/// Accepts connections on `listener` and runs the server side of the
+/// handshake with `keys` on each, one connection at a time.
+///
+/// Like [`TcpListener::incoming`], the iterator never ends and yields an
+/// error for a connection that failed, without affecting later ones.
+pub fn serve_handshake<'a>(
+    listener: &'a TcpListener,
+    keys: &'a Keypair,
+) -> Handshakes<'a> {
+    Handshakes {
+        listener,
+        keys,
+        timeout: HANDSHAKE_TIMEOUT,
+    }
+}
+
+/// Server handshakes on accepted connections, returned by
+/// [`serve_handshake`].
+pub struct Handshakes<'a> {
+    listener: &'a TcpListener,
+    keys: &'a Keypair,
+    timeout: Duration,
+}
+
+impl fmt::Debug for Handshakes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handshakes")
+            .field("listener", &self.listener)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Handshakes<'_> {
+    /// Bounds each read and write of a handshake by `timeout` instead of
+    /// [`HANDSHAKE_TIMEOUT`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn server(
+        &self,
+        mut stream: TcpStream,
+    ) -> io::Result<(SharedSecret, TcpStream)> {
+        set_timeouts(&stream, Some(self.timeout))?;
+        let mut header = [0u8; HEADER_BYTES];
+        stream.read_exact(&mut header)?;
+        if let Err((response, err)) = check_header(header) {
+            // The connection is dropped either way.
+            let _ = stream.write_all(&response);
+            return Err(err);
+        }
+
+        let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+        stream.read_exact(&mut ct)?;
+        let ss = respond(&ct, self.keys)?;
+        stream.write_all(&ACCEPTED)?;
+        set_timeouts(&stream, None)?;
+        Ok((ss, stream))
+    }
+}
+
+impl Iterator for Handshakes<'_> {
+    type Item = io::Result<(SharedSecret, TcpStream)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            self.listener
+                .accept()
+                .and_then(|(stream, _)| self.server(stream)),
+        )
+    }
+}
+
+fn set_timeouts(
+    stream: &TcpStream,
+    timeout: Option<Duration>,
+) -> io::Result<()> {
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
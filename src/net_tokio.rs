@@ -0,0 +1,175 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Async equivalents of the [`net`](crate::net) handshake helpers on tokio.
+//!
+//! The helpers speak the same version 1 protocol as the synchronous ones,
+//! over any `AsyncRead + AsyncWrite` stream: a `tokio::net::TcpStream`, a
+//! TLS stream or an in-memory `tokio::io::duplex` pipe. Every read and
+//! write of the handshake is bounded by [`HANDSHAKE_TIMEOUT`], or by the
+//! timeout passed to the `_with_timeout` variants, through
+//! `tokio::time::timeout`, so the runtime needs its time driver enabled.
+//!
+//! ## Cancellation
+//!
+//! The handshake keeps all of its state in the returned future, and the
+//! shared secret only exists once it completes. Dropping the future, e.g.
+//! when it loses a `tokio::select!` or a step times out, leaves the keys
+//! and the caller untouched, but the stream may hold a partly read or
+//! written frame; close it instead of starting another handshake on it.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::net_tokio::*;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build()?;
+//! # runtime.block_on(async {
+//! let keys = keypair(&mut rand::thread_rng())?;
+//! let (mut client, mut server) = tokio::io::duplex(4096);
+//! let (client_secret, server_secret) = tokio::join!(
+//!     initiate_handshake(&keys.public, &mut client),
+//!     respond_handshake(&keys, &mut server),
+//! );
+//! assert_eq!(client_secret?, server_secret?);
+//! # Ok(()) })
+//! # }
+//! ```
+
+use crate::{
+    api::{keypair, Keypair},
+    kex::{PublicKey, SharedSecret},
+    net::*,
+    params::*,
+    rng::RetryRng,
+};
+use std::{future::Future, io, time::Duration};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Runs the client side of the handshake on `stream` against the server's
+/// public key `pk`.
+///
+/// # Errors
+///
+/// Returns `io::ErrorKind::InvalidData` if the server rejects the request
+/// or answers with another protocol version,
+/// `io::ErrorKind::TimedOut` if the server is too slow and any error
+/// talking to it.
+pub async fn initiate_handshake<S>(
+    pk: &PublicKey,
+    stream: &mut S,
+) -> io::Result<SharedSecret>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    initiate_handshake_with_timeout(pk, stream, HANDSHAKE_TIMEOUT).await
+}
+
+/// Runs the client side of the handshake, bounding each read and write by
+/// `timeout` instead of [`HANDSHAKE_TIMEOUT`].
+///
+/// # Errors
+///
+/// As for [`initiate_handshake`].
+pub async fn initiate_handshake_with_timeout<S>(
+    pk: &PublicKey,
+    stream: &mut S,
+    timeout: Duration,
+) -> io::Result<SharedSecret>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (request, ss) = request(pk)?;
+    step(timeout, stream.write_all(&request)).await?;
+    step(timeout, stream.flush()).await?;
+
+    let mut response = [0u8; RESPONSE_BYTES];
+    let _ = step(timeout, stream.read_exact(&mut response)).await?;
+    check_response(response)?;
+    Ok(ss)
+}
+
+/// Runs the server side of the handshake on `stream` with `keys`.
+///
+/// The ciphertext length is checked before the ciphertext is read, so an
+/// oversized frame is rejected without buffering it.
+///
+/// # Errors
+///
+/// Returns `io::ErrorKind::InvalidData` if the request is rejected,
+/// `io::ErrorKind::TimedOut` if the client is too slow and any error
+/// talking to it.
+pub async fn respond_handshake<S>(
+    keys: &Keypair,
+    stream: &mut S,
+) -> io::Result<SharedSecret>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    respond_handshake_with_timeout(keys, stream, HANDSHAKE_TIMEOUT)
+        .await
+}
+
+/// Runs the server side of the handshake, bounding each read and write by
+/// `timeout` instead of [`HANDSHAKE_TIMEOUT`].
+///
+/// # Errors
+///
+/// As for [`respond_handshake`].
+pub async fn respond_handshake_with_timeout<S>(
+    keys: &Keypair,
+    stream: &mut S,
+    timeout: Duration,
+) -> io::Result<SharedSecret>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; HEADER_BYTES];
+    let _ = step(timeout, stream.read_exact(&mut header)).await?;
+    if let Err((response, err)) = check_header(header) {
+        // The stream is to be dropped either way.
+        let _ = step(timeout, stream.write_all(&response)).await;
+        let _ = step(timeout, stream.flush()).await;
+        return Err(err);
+    }
+
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let _ = step(timeout, stream.read_exact(&mut ct)).await?;
+    let ss = respond(&ct, keys)?;
+    step(timeout, stream.write_all(&ACCEPTED)).await?;
+    step(timeout, stream.flush()).await?;
+    Ok(ss)
+}
+
+/// Generates `count` keypairs on tokio's blocking thread pool, so bulk key
+/// generation does not stall the executor.
+///
+/// # Errors
+///
+/// Returns `io::ErrorKind::InvalidData` if the system RNG fails and
+/// `io::ErrorKind::Other` if the blocking task panicked or the runtime is
+/// shutting down.
+pub async fn keypairs_blocking(
+    count: usize,
+) -> io::Result<Vec<Keypair>> {
+    tokio::task::spawn_blocking(move || {
+        (0..count)
+            .map(|_| keypair(&mut RetryRng(rand::rngs::OsRng)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| invalid(&e.to_string()))
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+async fn step<F, T>(timeout: Duration, future: F) -> io::Result<T>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    tokio::time::timeout(timeout, future).await.map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            "handshake step timed out",
+        )
+    })?
+}
@@ -0,0 +1,127 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Decapsulation padded to a fixed wall-clock duration.
+//!
+//! [`decapsulate_padded`] runs [`decapsulate`] and then waits until a
+//! deadline taken from the monotonic clock before returning, so that a
+//! caller observing the response time sees the same duration for every
+//! ciphertext, valid or not.
+//!
+//! This is defence in depth, not a substitute for constant-time code.
+//! Decapsulation is already constant time; the padding only covers
+//! residual signals that tests of it cannot see, such as cache effects,
+//! and only in wall-clock time. It does not hide anything from an
+//! attacker sharing the machine, who can watch the caches or the CPU
+//! load of the waiting thread directly, and a deadline the decapsulation
+//! overruns hides nothing, which is reported as
+//! [`KyberLibError::Overrun`]. Pick a duration comfortably above the
+//! slowest decapsulation seen under load.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::padded::*;
+//! # use std::time::Duration;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+//! let padded =
+//!     decapsulate_padded(&ct, &keys.secret, Duration::from_millis(50))?;
+//! assert_eq!(padded, ss);
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    api::decapsulate, error::KyberLibError, kex::Decapsulated,
+};
+use std::{
+    hint, thread,
+    time::{Duration, Instant},
+};
+
+/// How [`decapsulate_padded_with`] waits for the deadline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadStrategy {
+    /// Polls the clock until the deadline, keeping a core busy. Returns
+    /// within a few microseconds of it.
+    Spin,
+    /// Sleeps until the deadline. Frees the core, but the scheduler may
+    /// wake the thread well after it; the delay does not depend on the
+    /// ciphertext.
+    Sleep,
+    /// Sleeps until the given margin before the deadline and spins for
+    /// the rest, for long durations where spinning throughout would waste
+    /// a core.
+    SleepThenSpin(Duration),
+}
+
+/// Decapsulates `ct` with `sk` and returns no earlier than `min_duration`
+/// after the call, spinning for the remainder.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::Overrun` if the decapsulation took longer than
+/// `min_duration`, and otherwise fails as [`decapsulate`] does, after the
+/// same wait.
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn decapsulate_padded(
+    ct: &[u8],
+    sk: &[u8],
+    min_duration: Duration,
+) -> Decapsulated {
+    decapsulate_padded_with(ct, sk, min_duration, PadStrategy::Spin)
+}
+
+/// Decapsulates `ct` with `sk` and returns no earlier than `min_duration`
+/// after the call, waiting as `strategy` says.
+///
+/// # Errors
+///
+/// As [`decapsulate_padded`].
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn decapsulate_padded_with(
+    ct: &[u8],
+    sk: &[u8],
+    min_duration: Duration,
+    strategy: PadStrategy,
+) -> Decapsulated {
+    let start = Instant::now();
+    let deadline = start + min_duration;
+    let result = decapsulate(ct, sk);
+    // Checked before waiting, which would hide an overrun by the time of
+    // a late wakeup.
+    if start.elapsed() > min_duration {
+        return Err(KyberLibError::Overrun);
+    }
+    match strategy {
+        PadStrategy::Spin => spin_until(deadline),
+        PadStrategy::Sleep => sleep_until(deadline),
+        PadStrategy::SleepThenSpin(margin) => {
+            if let Some(wake) = deadline.checked_sub(margin) {
+                sleep_until(wake);
+            }
+            spin_until(deadline);
+        }
+    }
+    result
+}
+
+fn spin_until(deadline: Instant) {
+    while Instant::now() < deadline {
+        hint::spin_loop();
+    }
+}
+
+// `thread::sleep` may return early on some platforms, so it is repeated
+// until the deadline has passed.
+fn sleep_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        thread::sleep(deadline - now);
+    }
+}
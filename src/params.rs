@@ -6,7 +6,7 @@
 /// Enables or disables 90's mode in the Kyber cryptographic scheme.
 ///
 /// - In 90's mode, the default cryptographic primitives are replaced with AES-CTR and SHA2,
-/// potentially offering faster performance on certain hardware platforms.
+///   potentially offering faster performance on certain hardware platforms.
 /// - To enable 90's mode, set the `features = ["90s"]` flag in your Cargo.toml file.
 /// - By default, this mode is disabled.
 pub const KYBER_90S: bool = cfg!(feature = "90s");
@@ -59,6 +59,7 @@ pub const KYBER_SECRET_KEY_BYTES: usize = KYBER_INDCPA_SECRET_KEY_BYTES
 /// - Kyber-512 for AES-128 equivalent security (value = 2).
 /// - Kyber-768 for AES-192 equivalent security (value = 3, default).
 /// - Kyber-1024 for AES-256 equivalent security (value = 4).
+///
 /// For more details, refer to the Kyber documentation: <https://pq-crystals.org/kyber/>
 pub const KYBER_SECURITY_PARAMETER: usize =
     if cfg!(feature = "kyber512") {
@@ -74,18 +75,62 @@ pub const KYBER_SECURITY_PARAMETER: usize =
 /// - This size is pivotal for ensuring the security and efficiency of the cryptographic process.
 pub const KYBER_SHARED_SECRET_BYTES: usize = 32;
 
-/// Compressed byte size of the polynomial for Kyber1024.
-///
-/// - It determines the efficiency of data compression in Kyber1024.
-#[cfg(feature = "kyber1024")]
-pub const KYBER_POLY_COMPRESSED_BYTES: usize = 160;
-
-/// Compressed byte size of the polynomial vector for Kyber1024.
-///
-/// - Affects how polynomial vector data is compressed in the Kyber1024 variant.
-#[cfg(feature = "kyber1024")]
+/// Bits per coefficient of the compressed vector `u` of a ciphertext.
+///
+/// - 11 for Kyber1024 and 10 for the other variants, as specified.
+/// - Can be overridden for ciphertext-size research by building with
+///   `RUSTFLAGS='--cfg kyber_du="9"'`; 9, 10 and 11 are supported. Such a
+///   build does not interoperate with standard Kyber, has a higher
+///   decryption failure rate and always runs the reference backend.
+pub const KYBER_DU: usize = if cfg!(kyber_du = "9") {
+    9
+} else if cfg!(kyber_du = "10") {
+    10
+} else if cfg!(kyber_du = "11") {
+    11
+} else {
+    ROUND3_DU
+};
+
+/// Bits per coefficient of the compressed polynomial `v` of a ciphertext.
+///
+/// - 5 for Kyber1024 and 4 for the other variants, as specified.
+/// - Can be overridden like [`KYBER_DU`], with `--cfg kyber_dv="3"`; 3, 4
+///   and 5 are supported.
+pub const KYBER_DV: usize = if cfg!(kyber_dv = "3") {
+    3
+} else if cfg!(kyber_dv = "4") {
+    4
+} else if cfg!(kyber_dv = "5") {
+    5
+} else {
+    ROUND3_DV
+};
+
+// The specified compression widths of the selected variant.
+const ROUND3_DU: usize =
+    if cfg!(feature = "kyber1024") { 11 } else { 10 };
+const ROUND3_DV: usize =
+    if cfg!(feature = "kyber1024") { 5 } else { 4 };
+
+// Whether KYBER_DU or KYBER_DV differ from the specified widths, so that
+// only the generic compression code and the reference backend apply.
+// Unused under `small`, which always takes the generic code.
+#[cfg_attr(feature = "small", allow(dead_code))]
+pub(crate) const KYBER_COMPRESSION_OVERRIDDEN: bool =
+    KYBER_DU != ROUND3_DU || KYBER_DV != ROUND3_DV;
+
+/// Compressed byte size of a polynomial, `KYBER_DV` bits per coefficient.
+///
+/// - 160 for Kyber1024, 128 for the other variants.
+pub const KYBER_POLY_COMPRESSED_BYTES: usize = KYBER_DV * KYBER_N / 8;
+
+/// Compressed byte size of the polynomial vector, `KYBER_DU` bits per
+/// coefficient.
+///
+/// - 1408 for Kyber1024, 320 per polynomial for the other variants.
 pub const KYBER_POLYVEC_COMPRESSED_BYTES: usize =
-    KYBER_SECURITY_PARAMETER * 352;
+    KYBER_SECURITY_PARAMETER * KYBER_DU * KYBER_N / 8;
 
 /// Byte size of the IND-CPA public key in Kyber.
 ///
@@ -113,3 +158,32 @@ pub const KYBER_PUBLIC_KEY_BYTES: usize = KYBER_INDCPA_PUBLIC_KEY_BYTES;
 ///
 /// - This size dictates the memory requirements for storing and transmitting encrypted data.
 pub const KYBER_CIPHERTEXT_BYTES: usize = KYBER_INDCPA_BYTES;
+
+/// Size in bytes of a compact secret key, the key generation seed.
+///
+/// - A compact key is expanded into a full secret key of `KYBER_SECRET_KEY_BYTES` by re-running key generation.
+pub const KYBER_COMPACT_SECRET_KEY_BYTES: usize = 2 * KYBER_SYM_BYTES;
+
+/// NIST security category of the active parameter set.
+///
+/// - Kyber-512 is category 1, Kyber-768 category 3 and Kyber-1024 category 5.
+/// - Useful for compliance reporting alongside [`claimed_bits`].
+pub const fn security_category() -> u8 {
+    match KYBER_SECURITY_PARAMETER {
+        2 => 1,
+        3 => 3,
+        _ => 5,
+    }
+}
+
+/// Claimed classical security in bits of the active parameter set.
+///
+/// - 128 for Kyber-512, 192 for Kyber-768 and 256 for Kyber-1024, matching
+///   the AES key sizes of the corresponding NIST categories.
+pub const fn claimed_bits() -> u32 {
+    match KYBER_SECURITY_PARAMETER {
+        2 => 128,
+        3 => 192,
+        _ => 256,
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Algorithm-specific fields for OpenPGP composite Kyber + X25519 keys.
+//!
+//! This module is the math layer for the composite encryption subkeys of
+//! the OpenPGP post-quantum draft (draft-ietf-openpgp-pqc). It covers:
+//!
+//! * the algorithm-specific fields of the public and secret key packets,
+//!   see [`CompositePublicKey`] and [`CompositeSecretKey`];
+//! * the algorithm-specific fields of a version 6 PKESK packet, i.e. the
+//!   composite encapsulation, the key combiner and the AES-256 wrapped
+//!   session key, see [`EncryptedSessionKey`].
+//!
+//! Packet framing, fingerprints, signatures and message encryption are left
+//! to the OpenPGP implementation using it.
+//!
+//! The key-encryption key is derived with the draft's combiner,
+//! [`multi_key_combine`]:
+//!
+//! ```text
+//! SHA3-256(mlkemKeyShare || ecdhKeyShare || ecdhCipherText ||
+//!          ecdhPublicKey || algId || domSep || len(domSep))
+//! ```
+//!
+//! with `domSep` set to [`DOMAIN_SEPARATOR`] and `algId` to
+//! [`ALGORITHM_ID`]. The draft assigns that identifier to ML-KEM-768 +
+//! X25519 and pairs ML-KEM-1024 with X448; other security levels here keep
+//! X25519 and the same identifier, which no other implementation will
+//! accept. Note also that this crate implements the CRYSTALS-Kyber
+//! submission rather than FIPS 203, so the ML-KEM half of the draft's test
+//! vectors cannot be reproduced and packets only interoperate with
+//! kyberlib.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::pgp::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let secret = CompositeSecretKey::generate(&mut rng)?;
+//! let public = CompositePublicKey::from_fields(&secret.public_key().to_fields())?;
+//!
+//! let session_key = [7u8; 32];
+//! let esk = EncryptedSessionKey::new(&public, &session_key, &mut rng)?;
+//! let parsed = EncryptedSessionKey::from_fields(&esk.to_fields())?;
+//! assert_eq!(&parsed.decrypt(&secret)?[..], &session_key[..]);
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::{decapsulate, derive, encapsulate, Keypair},
+    error::KyberLibError,
+    kem::check_public_key,
+    kex::PublicKey,
+    params::*,
+    CryptoRng, RngCore,
+};
+use aes_kw::KekAes256;
+use alloc::vec::Vec;
+use core::fmt;
+use sha3::{Digest, Sha3_256};
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Public-key algorithm identifier of ML-KEM-768 + X25519 in the draft.
+pub const ALGORITHM_ID: u8 = 35;
+/// Domain separator of the key combiner.
+pub const DOMAIN_SEPARATOR: &[u8] = b"OpenPGPCompositeKDFv1";
+/// Size in bytes of X25519 keys and ciphertexts.
+pub const X25519_BYTES: usize = 32;
+/// Size in bytes of the seed a Kyber secret key is stored as.
+pub const KYBER_SEED_BYTES: usize = 64;
+/// Size in bytes of the public key packet fields.
+pub const PUBLIC_KEY_FIELDS_BYTES: usize =
+    X25519_BYTES + KYBER_PUBLIC_KEY_BYTES;
+/// Size in bytes of the secret key packet fields.
+pub const SECRET_KEY_FIELDS_BYTES: usize =
+    X25519_BYTES + KYBER_SEED_BYTES;
+/// Size in bytes of the key-encryption key.
+pub const KEK_BYTES: usize = 32;
+
+/// Public key packet fields of a composite encryption subkey.
+///
+/// The fields are the X25519 public key followed by the Kyber public key,
+/// both as fixed-length octet strings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompositePublicKey {
+    x25519: [u8; X25519_BYTES],
+    kyber: PublicKey,
+}
+
+impl CompositePublicKey {
+    /// The X25519 public key.
+    pub fn x25519(&self) -> &[u8; X25519_BYTES] {
+        &self.x25519
+    }
+
+    /// The Kyber public key.
+    pub fn kyber(&self) -> &PublicKey {
+        &self.kyber
+    }
+
+    /// Encodes the algorithm-specific public key fields.
+    pub fn to_fields(&self) -> [u8; PUBLIC_KEY_FIELDS_BYTES] {
+        let mut fields = [0u8; PUBLIC_KEY_FIELDS_BYTES];
+        fields[..X25519_BYTES].copy_from_slice(&self.x25519);
+        fields[X25519_BYTES..].copy_from_slice(&self.kyber);
+        fields
+    }
+
+    /// Parses the algorithm-specific public key fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `fields` has the wrong
+    /// size and `KyberLibError::InvalidKey` if the Kyber key is malformed.
+    pub fn from_fields(fields: &[u8]) -> Result<Self, KyberLibError> {
+        if fields.len() != PUBLIC_KEY_FIELDS_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let (x, k) = fields.split_at(X25519_BYTES);
+        check_public_key(k)?;
+        let mut key = Self {
+            x25519: [0u8; X25519_BYTES],
+            kyber: [0u8; KYBER_PUBLIC_KEY_BYTES],
+        };
+        key.x25519.copy_from_slice(x);
+        key.kyber.copy_from_slice(k);
+        Ok(key)
+    }
+}
+
+/// Secret key packet fields of a composite encryption subkey.
+///
+/// The fields are the X25519 secret key followed by the 64-byte seed the
+/// Kyber keypair is derived from with [`derive`]. All secret material is
+/// zeroized on drop.
+pub struct CompositeSecretKey {
+    x25519: [u8; X25519_BYTES],
+    seed: [u8; KYBER_SEED_BYTES],
+    kyber: Keypair,
+}
+
+impl CompositeSecretKey {
+    /// Builds the key from an X25519 secret key and a Kyber seed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KyberLibError` if the Kyber keypair cannot be derived.
+    pub fn from_seeds(
+        x25519_secret: &[u8; X25519_BYTES],
+        kyber_seed: &[u8; KYBER_SEED_BYTES],
+    ) -> Result<Self, KyberLibError> {
+        Ok(Self {
+            x25519: *x25519_secret,
+            seed: *kyber_seed,
+            kyber: derive(kyber_seed)?,
+        })
+    }
+
+    /// Generates a fresh composite key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    pub fn generate<R>(rng: &mut R) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut fields = Zeroizing::new([0u8; SECRET_KEY_FIELDS_BYTES]);
+        rng.try_fill_bytes(&mut fields[..])
+            .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+        Self::from_fields(&fields[..])
+    }
+
+    /// The matching public key.
+    pub fn public_key(&self) -> CompositePublicKey {
+        CompositePublicKey {
+            x25519: x25519(self.x25519, X25519_BASEPOINT_BYTES),
+            kyber: self.kyber.public,
+        }
+    }
+
+    /// Encodes the algorithm-specific secret key fields.
+    pub fn to_fields(
+        &self,
+    ) -> Zeroizing<[u8; SECRET_KEY_FIELDS_BYTES]> {
+        let mut fields = Zeroizing::new([0u8; SECRET_KEY_FIELDS_BYTES]);
+        fields[..X25519_BYTES].copy_from_slice(&self.x25519);
+        fields[X25519_BYTES..].copy_from_slice(&self.seed);
+        fields
+    }
+
+    /// Parses the algorithm-specific secret key fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `fields` has the wrong
+    /// size.
+    pub fn from_fields(fields: &[u8]) -> Result<Self, KyberLibError> {
+        if fields.len() != SECRET_KEY_FIELDS_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let mut x = [0u8; X25519_BYTES];
+        let mut seed = [0u8; KYBER_SEED_BYTES];
+        x.copy_from_slice(&fields[..X25519_BYTES]);
+        seed.copy_from_slice(&fields[X25519_BYTES..]);
+        let key = Self::from_seeds(&x, &seed);
+        x.zeroize();
+        seed.zeroize();
+        key
+    }
+}
+
+impl fmt::Debug for CompositeSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeSecretKey").finish_non_exhaustive()
+    }
+}
+
+impl Drop for CompositeSecretKey {
+    fn drop(&mut self) {
+        self.x25519.zeroize();
+        self.seed.zeroize();
+        self.kyber.secret.zeroize();
+    }
+}
+
+/// Version 6 PKESK fields for a composite encryption subkey.
+///
+/// The serialised layout is the X25519 ephemeral public key, the Kyber
+/// ciphertext, a one-octet length and the AES-256 wrapped session key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedSessionKey {
+    ecdh_ciphertext: [u8; X25519_BYTES],
+    kyber_ciphertext: [u8; KYBER_CIPHERTEXT_BYTES],
+    wrapped_key: Vec<u8>,
+}
+
+impl EncryptedSessionKey {
+    /// Encrypts `session_key` to the holder of `recipient`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `session_key` is not a
+    /// multiple of 8 bytes between 16 and 240, `KyberLibError::InvalidKey`
+    /// if the X25519 key is of low order and
+    /// `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    pub fn new<R>(
+        recipient: &CompositePublicKey,
+        session_key: &[u8],
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if session_key.len() < 16
+            || session_key.len() > 240
+            || !session_key.len().is_multiple_of(8)
+        {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let mut ephemeral = Zeroizing::new([0u8; X25519_BYTES]);
+        rng.try_fill_bytes(&mut ephemeral[..])
+            .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+        let ecdh_ciphertext =
+            x25519(*ephemeral, X25519_BASEPOINT_BYTES);
+        let ecdh_share =
+            Zeroizing::new(x25519(*ephemeral, recipient.x25519));
+        if *ecdh_share == [0u8; X25519_BYTES] {
+            return Err(KyberLibError::InvalidKey);
+        }
+
+        let (kyber_ciphertext, kyber_share) =
+            encapsulate(&recipient.kyber, rng)?;
+        let kyber_share = Zeroizing::new(kyber_share);
+        let kek = Zeroizing::new(multi_key_combine(
+            &kyber_share[..],
+            &ecdh_share[..],
+            &ecdh_ciphertext,
+            &recipient.x25519,
+            ALGORITHM_ID,
+        ));
+
+        let mut wrapped_key = alloc::vec![0u8; session_key.len() + 8];
+        KekAes256::from(*kek)
+            .wrap(session_key, &mut wrapped_key)
+            .map_err(|_| KyberLibError::InvalidLength)?;
+        Ok(Self {
+            ecdh_ciphertext,
+            kyber_ciphertext,
+            wrapped_key,
+        })
+    }
+
+    /// Recovers the session key with the recipient's secret key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::Decapsulation` if the wrapped key fails its
+    /// integrity check, i.e. `secret` is not the recipient's key or the
+    /// fields were modified.
+    pub fn decrypt(
+        &self,
+        secret: &CompositeSecretKey,
+    ) -> Result<Zeroizing<Vec<u8>>, KyberLibError> {
+        let ecdh_share =
+            Zeroizing::new(x25519(secret.x25519, self.ecdh_ciphertext));
+        let kyber_share = Zeroizing::new(decapsulate(
+            &self.kyber_ciphertext,
+            &secret.kyber.secret,
+        )?);
+        let kek = Zeroizing::new(multi_key_combine(
+            &kyber_share[..],
+            &ecdh_share[..],
+            &self.ecdh_ciphertext,
+            &x25519(secret.x25519, X25519_BASEPOINT_BYTES),
+            ALGORITHM_ID,
+        ));
+
+        let mut session_key = Zeroizing::new(
+            alloc::vec![0u8; self.wrapped_key.len() - 8],
+        );
+        KekAes256::from(*kek)
+            .unwrap(&self.wrapped_key, &mut session_key)
+            .map_err(|_| KyberLibError::Decapsulation)?;
+        Ok(session_key)
+    }
+
+    /// The X25519 ephemeral public key.
+    pub fn ecdh_ciphertext(&self) -> &[u8; X25519_BYTES] {
+        &self.ecdh_ciphertext
+    }
+
+    /// The Kyber ciphertext.
+    pub fn kyber_ciphertext(&self) -> &[u8; KYBER_CIPHERTEXT_BYTES] {
+        &self.kyber_ciphertext
+    }
+
+    /// The AES-256 wrapped session key.
+    pub fn wrapped_key(&self) -> &[u8] {
+        &self.wrapped_key
+    }
+
+    /// Encodes the algorithm-specific PKESK fields.
+    pub fn to_fields(&self) -> Vec<u8> {
+        let mut fields = Vec::with_capacity(
+            X25519_BYTES
+                + KYBER_CIPHERTEXT_BYTES
+                + 1
+                + self.wrapped_key.len(),
+        );
+        fields.extend_from_slice(&self.ecdh_ciphertext);
+        fields.extend_from_slice(&self.kyber_ciphertext);
+        fields.push(self.wrapped_key.len() as u8);
+        fields.extend_from_slice(&self.wrapped_key);
+        fields
+    }
+
+    /// Parses the algorithm-specific PKESK fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if the fields are truncated,
+    /// followed by trailing data or carry a wrapped key of invalid size.
+    pub fn from_fields(fields: &[u8]) -> Result<Self, KyberLibError> {
+        let header = X25519_BYTES + KYBER_CIPHERTEXT_BYTES + 1;
+        if fields.len() < header {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let len = fields[header - 1] as usize;
+        if fields.len() != header + len
+            || len < 24
+            || !len.is_multiple_of(8)
+        {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let mut esk = Self {
+            ecdh_ciphertext: [0u8; X25519_BYTES],
+            kyber_ciphertext: [0u8; KYBER_CIPHERTEXT_BYTES],
+            wrapped_key: fields[header..].to_vec(),
+        };
+        esk.ecdh_ciphertext.copy_from_slice(&fields[..X25519_BYTES]);
+        esk.kyber_ciphertext
+            .copy_from_slice(&fields[X25519_BYTES..header - 1]);
+        Ok(esk)
+    }
+}
+
+/// The key combiner of the draft.
+///
+/// Derives the key-encryption key from both key shares, binding the X25519
+/// ciphertext and public key and the algorithm identifier.
+pub fn multi_key_combine(
+    kyber_share: &[u8],
+    ecdh_share: &[u8],
+    ecdh_ciphertext: &[u8],
+    ecdh_public_key: &[u8],
+    algorithm_id: u8,
+) -> [u8; KEK_BYTES] {
+    let mut digest = Sha3_256::new();
+    digest.update(kyber_share);
+    digest.update(ecdh_share);
+    digest.update(ecdh_ciphertext);
+    digest.update(ecdh_public_key);
+    digest.update([algorithm_id]);
+    digest.update(DOMAIN_SEPARATOR);
+    digest.update([DOMAIN_SEPARATOR.len() as u8]);
+    digest.finalize().into()
+}
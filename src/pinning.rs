@@ -0,0 +1,414 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Trust-on-first-use pinning of peer public keys.
+//!
+//! Like SSH `known_hosts`, a [`PinStore`] remembers the fingerprint of the
+//! public key each identity presented on first contact and reports any
+//! later change, which the caller should surface loudly. A changed key is
+//! only accepted through [`PinStore::repin`], after the user confirmed it.
+//!
+//! The store is a text file with one pin per line and tab-separated fields:
+//!
+//! | Field       | Example |
+//! |-------------|---------|
+//! | identity    | `example.com` |
+//! | level       | `kyber1024` |
+//! | fingerprint | 64 hex digits of `H(pk)` |
+//! | first seen  | Unix seconds, e.g. `1700000000` |
+//!
+//! Lines starting with `#` are comments and a later line for an identity
+//! replaces an earlier one. Lines that do not parse are skipped and
+//! reported by [`PinStore::warnings`] instead of making the whole store
+//! unreadable, and are written back verbatim so they can still be fixed
+//! by hand. Every change is written to a temporary file that is renamed
+//! over the store while holding an advisory lock on a `.lock` file next to
+//! it, so two processes sharing a store neither corrupt it nor lose each
+//! other's pins. The operating system releases the lock when its holder
+//! exits, so a crashed process never leaves the store locked.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::pinning::*;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let path = std::env::temp_dir().join(format!("kyberlib-doc-pins-{}", std::process::id()));
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let mut store = PinStore::open(&path)?;
+//! assert_eq!(store.check("example.com", &keys.public)?, PinResult::FirstUse);
+//! assert_eq!(store.check("example.com", &keys.public)?, PinResult::Match);
+//!
+//! let rotated = keypair(&mut rng)?;
+//! match store.check("example.com", &rotated.public)? {
+//!     PinResult::Mismatch { old, new } => {
+//!         eprintln!("key for example.com changed from {} to {}", old, new);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! # std::fs::remove_file(&path)?;
+//! # std::fs::remove_file(format!("{}.lock", path.display()))?;
+//! # Ok(()) }
+//! ```
+
+use crate::{kex::PublicKey, params::*, symmetric::hash_h};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Size in bytes of a public key fingerprint.
+pub const FINGERPRINT_BYTES: usize = KYBER_SYM_BYTES;
+
+/// How long to wait for another process to release the store.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The fingerprint `H(pk)` of a public key, displayed as hex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint(pub [u8; FINGERPRINT_BYTES]);
+
+impl Fingerprint {
+    /// Computes the fingerprint of `pk`.
+    pub fn of(pk: &PublicKey) -> Self {
+        let mut fingerprint = [0u8; FINGERPRINT_BYTES];
+        hash_h(&mut fingerprint, pk, KYBER_PUBLIC_KEY_BYTES);
+        Self(fingerprint)
+    }
+
+    fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 2 * FINGERPRINT_BYTES || !hex.is_ascii() {
+            return None;
+        }
+        let mut fingerprint = [0u8; FINGERPRINT_BYTES];
+        for (byte, pair) in
+            fingerprint.iter_mut().zip(hex.as_bytes().chunks(2))
+        {
+            let pair = core::str::from_utf8(pair).ok()?;
+            *byte = u8::from_str_radix(pair, 16).ok()?;
+        }
+        Some(Self(fingerprint))
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+/// The outcome of checking a public key against the store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinResult {
+    /// The identity was not pinned; its key has now been recorded.
+    FirstUse,
+    /// The key matches the pinned one.
+    Match,
+    /// The key differs from the pinned one, which is left in place.
+    Mismatch {
+        /// Fingerprint of the pinned key.
+        old: Fingerprint,
+        /// Fingerprint of the key just presented.
+        new: Fingerprint,
+    },
+}
+
+/// A pinned key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pin {
+    /// Parameter set of the key, e.g. `kyber1024`.
+    pub level: String,
+    /// Fingerprint of the key.
+    pub fingerprint: Fingerprint,
+    /// When the key was pinned, in seconds since the Unix epoch.
+    pub first_seen: u64,
+}
+
+/// A file of pinned public key fingerprints by identity.
+#[derive(Debug)]
+pub struct PinStore {
+    path: PathBuf,
+    pins: BTreeMap<String, Pin>,
+    unparsed: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl PinStore {
+    /// Opens the store at `path`, which is created on the first pin.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut store = Self {
+            path: path.as_ref().to_path_buf(),
+            pins: BTreeMap::new(),
+            unparsed: Vec::new(),
+            warnings: Vec::new(),
+        };
+        store.reload()?;
+        Ok(store)
+    }
+
+    /// Checks `pk` against the pin for `identity`, pinning it on first use.
+    ///
+    /// The store is re-read first, so pins another process added since
+    /// [`PinStore::open`] are honoured.
+    ///
+    /// # Errors
+    ///
+    /// Returns `io::ErrorKind::InvalidInput` if `identity` is empty or
+    /// contains a tab or line break, `io::ErrorKind::WouldBlock` if the
+    /// store stays locked by another process, and any error reading or
+    /// writing the store.
+    pub fn check(
+        &mut self,
+        identity: &str,
+        pk: &PublicKey,
+    ) -> io::Result<PinResult> {
+        validate(identity)?;
+        let new = Fingerprint::of(pk);
+        let _lock = self.lock()?;
+        self.reload()?;
+        match self.pins.get(identity) {
+            Some(pin) if pin.fingerprint == new => Ok(PinResult::Match),
+            Some(pin) => Ok(PinResult::Mismatch {
+                old: pin.fingerprint,
+                new,
+            }),
+            None => {
+                let _ =
+                    self.pins.insert(identity.to_owned(), pin_for(new));
+                self.persist()?;
+                Ok(PinResult::FirstUse)
+            }
+        }
+    }
+
+    /// Replaces the pin for `identity` with `pk`, e.g. after the user
+    /// confirmed a reported mismatch.
+    ///
+    /// # Errors
+    ///
+    /// As for [`PinStore::check`].
+    pub fn repin(
+        &mut self,
+        identity: &str,
+        pk: &PublicKey,
+    ) -> io::Result<()> {
+        validate(identity)?;
+        let _lock = self.lock()?;
+        self.reload()?;
+        let _ = self
+            .pins
+            .insert(identity.to_owned(), pin_for(Fingerprint::of(pk)));
+        self.persist()
+    }
+
+    /// The pin for `identity`, as of the last read of the store.
+    pub fn get(&self, identity: &str) -> Option<&Pin> {
+        self.pins.get(identity)
+    }
+
+    /// Lines skipped on the last read of the store, with the reason.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn reload(&mut self) -> io::Result<()> {
+        self.pins.clear();
+        self.unparsed.clear();
+        self.warnings.clear();
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(())
+            }
+            Err(err) => return Err(err),
+        };
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse(line) {
+                Some((identity, pin)) => {
+                    let _ = self.pins.insert(identity, pin);
+                }
+                None => {
+                    self.unparsed.push(line.to_owned());
+                    self.warnings.push(format!(
+                        "{}:{}: skipping malformed pin",
+                        self.path.display(),
+                        number + 1
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn persist(&self) -> io::Result<()> {
+        let tmp = sibling(&self.path, ".tmp");
+        let mut file = File::create(&tmp)?;
+        writeln!(
+            file,
+            "# kyberlib pin store: identity, level, fingerprint, \
+             first seen"
+        )?;
+        for (identity, pin) in &self.pins {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}",
+                identity, pin.level, pin.fingerprint, pin.first_seen
+            )?;
+        }
+        for line in &self.unparsed {
+            writeln!(file, "{}", line)?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// Takes an exclusive `flock` or `LockFileEx` lock on the `.lock`
+    /// file, which is released when the returned file is dropped. The
+    /// file itself is left in place: removing it would let a process
+    /// that opened it just before lock a file nobody else sees.
+    fn lock(&self) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(sibling(&self.path, ".lock"))?;
+        let start = SystemTime::now();
+        while !try_lock(&file)? {
+            if start.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "pin store is locked by another process",
+                ));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(file)
+    }
+}
+
+// Takes an exclusive advisory lock on `file` without blocking, returning
+// false if another open file holds it. `File::try_lock` does the same
+// but needs Rust 1.89.
+#[cfg(unix)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the descriptor stays open for the call.
+    let ret = unsafe {
+        libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB)
+    };
+    if ret == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    if err.kind() == io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(windows)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::{
+        Foundation::{ERROR_LOCK_VIOLATION, HANDLE},
+        Storage::FileSystem::{
+            LockFileEx, LOCKFILE_EXCLUSIVE_LOCK,
+            LOCKFILE_FAIL_IMMEDIATELY,
+        },
+        System::IO::OVERLAPPED,
+    };
+
+    // SAFETY: the handle stays open for the call, and a zeroed
+    // OVERLAPPED locks the whole file from offset 0.
+    let ret = unsafe {
+        let mut overlapped: OVERLAPPED = std::mem::zeroed();
+        LockFileEx(
+            file.as_raw_handle() as HANDLE,
+            LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if ret != 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn try_lock(_: &File) -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "file locking is not supported on this platform",
+    ))
+}
+
+fn level() -> String {
+    format!("kyber{}", KYBER_SECURITY_PARAMETER * KYBER_N)
+}
+
+fn pin_for(fingerprint: Fingerprint) -> Pin {
+    Pin {
+        level: level(),
+        fingerprint,
+        first_seen: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    }
+}
+
+fn validate(identity: &str) -> io::Result<()> {
+    if identity.is_empty() || identity.contains(['\t', '\n', '\r']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "identity must be non-empty without tabs or line breaks",
+        ));
+    }
+    Ok(())
+}
+
+fn parse(line: &str) -> Option<(String, Pin)> {
+    let mut fields = line.split('\t');
+    let identity = fields.next().filter(|s| !s.is_empty())?;
+    let level = fields.next().filter(|s| !s.is_empty())?;
+    let fingerprint = Fingerprint::from_hex(fields.next()?)?;
+    let first_seen = fields.next()?.parse().ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+    Some((
+        identity.to_owned(),
+        Pin {
+            level: level.to_owned(),
+            fingerprint,
+            first_seen,
+        },
+    ))
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
@@ -0,0 +1,294 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Keypairs generated ahead of time, for servers that need one per
+//! connection.
+//!
+//! Key generation is the slowest KEM operation. A [`KeypairPool`] keeps
+//! up to `capacity` fresh keypairs so that [`KeypairPool::take`] only
+//! moves one out, and generates inline when the pool is empty. The pool
+//! is filled by a background thread started by [`KeypairPool::new`], or,
+//! on runtimes that forbid spawning threads, by calling
+//! [`KeypairPool::refill`] from a task of the caller's choosing on a pool
+//! made with [`KeypairPool::manual`].
+//!
+//! Each keypair is handed out once. The secret keys still pooled, and
+//! every slot a keypair was taken from, are overwritten with zeros, the
+//! former when the pool is dropped. [`KeypairPool::metrics`] reports the
+//! hits, misses and depth for monitoring.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::pool::*;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let pool = KeypairPool::new(64, rand::thread_rng)?;
+//! // On the accept path.
+//! let keys = pool.take()?;
+//! # let _ = keys;
+//! let metrics = pool.metrics();
+//! assert_eq!(metrics.hits + metrics.misses, 1);
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    api::{keypair, Keypair},
+    error::KyberLibError,
+    params::*,
+    CryptoRng, RngCore,
+};
+use std::{
+    fmt, io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    vec::Vec,
+};
+use zeroize::Zeroize;
+
+/// Counters of a [`KeypairPool`], as returned by
+/// [`KeypairPool::metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Calls to [`KeypairPool::take`] served from the pool.
+    pub hits: u64,
+    /// Calls to [`KeypairPool::take`] that generated inline.
+    pub misses: u64,
+    /// Keypairs in the pool.
+    pub depth: usize,
+    /// The most keypairs the pool holds.
+    pub capacity: usize,
+}
+
+const EMPTY: Keypair = Keypair {
+    public: [0u8; KYBER_PUBLIC_KEY_BYTES],
+    secret: [0u8; KYBER_SECRET_KEY_BYTES],
+};
+
+// The pooled keypairs, as a stack whose buffer never reallocates, so a
+// secret key is never copied anywhere it is not zeroed from.
+struct Slots {
+    keys: Vec<Keypair>,
+    shutdown: bool,
+    // Set when the worker's RNG failed, so it waits for the next take
+    // instead of retrying in a loop.
+    stalled: bool,
+}
+
+impl Slots {
+    fn pop(&mut self) -> Option<Keypair> {
+        let last = self.keys.len().checked_sub(1)?;
+        let keys = core::mem::replace(&mut self.keys[last], EMPTY);
+        self.keys.truncate(last);
+        Some(keys)
+    }
+}
+
+impl Drop for Slots {
+    fn drop(&mut self) {
+        for keys in self.keys.iter_mut() {
+            keys.secret.zeroize();
+        }
+    }
+}
+
+struct Shared<R> {
+    slots: Mutex<Slots>,
+    // Signalled on every take and on shutdown.
+    wake: Condvar,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    rng_factory: Box<dyn Fn() -> R + Send + Sync>,
+}
+
+impl<R: RngCore + CryptoRng> Shared<R> {
+    // No code panics while holding the lock, but a poisoned pool is still
+    // consistent: every slot holds a whole keypair.
+    fn lock(&self) -> MutexGuard<'_, Slots> {
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Generates one keypair outside the lock and pools it, returning
+    // whether the pool had room. Concurrent refills may overshoot, in
+    // which case the surplus keypair is dropped zeroed.
+    fn add_one(&self, rng: &mut R) -> Result<bool, KyberLibError> {
+        let mut keys = keypair(rng)?;
+        let mut slots = self.lock();
+        if slots.keys.len() < self.capacity {
+            slots.keys.push(keys);
+            Ok(true)
+        } else {
+            drop(slots);
+            keys.secret.zeroize();
+            Ok(false)
+        }
+    }
+
+    fn run(&self) {
+        let mut rng = (self.rng_factory)();
+        loop {
+            {
+                let mut slots = self.lock();
+                while !slots.shutdown
+                    && (slots.stalled
+                        || slots.keys.len() >= self.capacity)
+                {
+                    slots = self
+                        .wake
+                        .wait(slots)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                if slots.shutdown {
+                    return;
+                }
+            }
+            if self.add_one(&mut rng).is_err() {
+                self.lock().stalled = true;
+            }
+        }
+    }
+}
+
+/// A pool of keypairs generated ahead of time; see the
+/// [`pool`](crate::pool) module.
+///
+/// It is `Sync`, so one pool can be shared between threads, e.g. in an
+/// `Arc`.
+pub struct KeypairPool<R> {
+    shared: Arc<Shared<R>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<R> fmt::Debug for KeypairPool<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeypairPool")
+            .field("capacity", &self.shared.capacity)
+            .field("background", &self.worker.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: RngCore + CryptoRng + 'static> KeypairPool<R> {
+    /// Creates a pool of up to `capacity` keypairs, kept full by a
+    /// background thread that draws from an RNG made by `rng_factory`.
+    ///
+    /// The pool starts empty and fills while the thread runs, so the
+    /// first calls to [`KeypairPool::take`] may still generate inline.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of [`std::thread::Builder::spawn`] if the thread
+    /// cannot be started.
+    pub fn new<F>(capacity: usize, rng_factory: F) -> io::Result<Self>
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+    {
+        let mut pool = Self::manual(capacity, rng_factory);
+        let shared = Arc::clone(&pool.shared);
+        pool.worker = Some(
+            thread::Builder::new()
+                .name("kyberlib-pool".into())
+                .spawn(move || shared.run())?,
+        );
+        Ok(pool)
+    }
+}
+
+impl<R: RngCore + CryptoRng> KeypairPool<R> {
+    /// Creates a pool of up to `capacity` keypairs without a background
+    /// thread; it is filled by [`KeypairPool::refill`] only.
+    pub fn manual<F>(capacity: usize, rng_factory: F) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+    {
+        Self {
+            shared: Arc::new(Shared {
+                slots: Mutex::new(Slots {
+                    keys: Vec::with_capacity(capacity),
+                    shutdown: false,
+                    stalled: false,
+                }),
+                wake: Condvar::new(),
+                capacity,
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                rng_factory: Box::new(rng_factory),
+            }),
+            worker: None,
+        }
+    }
+
+    /// Returns a keypair, from the pool when it is not empty and
+    /// generated inline otherwise. No keypair is returned twice.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`keypair`] does when the pool is empty.
+    #[must_use = "dropping the keypair discards the only copy of the secret key"]
+    pub fn take(&self) -> Result<Keypair, KyberLibError> {
+        let pooled = {
+            let mut slots = self.shared.lock();
+            slots.stalled = false;
+            slots.pop()
+        };
+        self.shared.wake.notify_one();
+        match pooled {
+            Some(keys) => {
+                let _ =
+                    self.shared.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(keys)
+            }
+            None => {
+                let _ =
+                    self.shared.misses.fetch_add(1, Ordering::Relaxed);
+                keypair(&mut (self.shared.rng_factory)())
+            }
+        }
+    }
+
+    /// Generates keypairs on the calling thread until the pool is full,
+    /// returning how many were added.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`keypair`] does; the keypairs added before are kept.
+    pub fn refill(&self) -> Result<usize, KyberLibError> {
+        let mut rng = (self.shared.rng_factory)();
+        let mut added = 0;
+        while self.shared.lock().keys.len() < self.shared.capacity {
+            if self.shared.add_one(&mut rng)? {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Returns the hits, misses and current depth of the pool.
+    pub fn metrics(&self) -> PoolMetrics {
+        PoolMetrics {
+            hits: self.shared.hits.load(Ordering::Relaxed),
+            misses: self.shared.misses.load(Ordering::Relaxed),
+            depth: self.shared.lock().keys.len(),
+            capacity: self.shared.capacity,
+        }
+    }
+}
+
+/// Stops the background thread; the pooled secret keys are zeroed once
+/// it has exited.
+impl<R> Drop for KeypairPool<R> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.shared
+                .slots
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .shutdown = true;
+            self.shared.wake.notify_all();
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use super::poly::*;
+use crate::poly::{
+    poly_csubq, poly_decompress, poly_frommsg, poly_getnoise_eta1,
+    poly_getnoise_eta2, poly_tomont, poly_tomsg, Poly,
+};
+use crate::polyvec::{
+    polyvec_basemul_acc_montgomery, polyvec_decompress,
+    polyvec_frombytes, polyvec_tobytes, Polyvec,
+};
+
+crate::simd::reference_coding!();
+crate::simd::indcpa_layer!();
@@ -0,0 +1,88 @@
+//! Portable SIMD implementation of the IND-CPA layer through
+//! `core::simd`, for any architecture, on nightly Rust.
+//!
+//! The NTT, inverse NTT, Barrett and Montgomery reductions, polynomial
+//! addition, subtraction and reduction and the compression of
+//! ciphertexts work on vectors of 16 coefficients, and leave it to the
+//! compiler to map them onto the SIMD unit of the target. Matrix
+//! sampling, base multiplication, noise sampling and serialization are
+//! shared with the reference implementation. The output is the same as
+//! that of the reference bit for bit, and the code is meant to read as a
+//! vectorized reference for the hand-written backends.
+//!
+//! The backend is compiled in with the `portable-simd` feature, which
+//! needs a nightly compiler, on targets without an intrinsics backend: the
+//! AVX2, NEON and SIMD128 code takes precedence where it is built.
+//! With the `force-backend` feature, `acceleration::force_backend`
+//! switches a process to the reference code at run time.
+
+pub(crate) mod indcpa;
+pub(crate) mod poly;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::*;
+    use crate::simd::differential::{self, random_poly};
+    use crate::{reference, reference::polyvec::Polyvec};
+
+    const ROUNDS: usize = 64;
+
+    #[test]
+    fn ntt_matches_reference() {
+        differential::ntt(poly::poly_ntt, poly::poly_invntt_tomont);
+    }
+
+    #[test]
+    fn arithmetic_matches_reference() {
+        for _ in 0..ROUNDS {
+            let mut b = random_poly(8 * KYBER_Q as i16);
+            let mut ref_b = b;
+            poly::poly_reduce(&mut b);
+            reference::poly::poly_reduce(&mut ref_b);
+            assert_eq!(b.coeffs, ref_b.coeffs);
+
+            let a = random_poly(2 * KYBER_Q as i16);
+            let (mut r, mut ref_r) = (a, a);
+            poly::poly_add(&mut r, &b);
+            reference::poly::poly_add(&mut ref_r, &b);
+            assert_eq!(r.coeffs, ref_r.coeffs);
+
+            let (mut r, mut ref_r) = (a, a);
+            poly::poly_sub(&mut r, &b);
+            reference::poly::poly_sub(&mut ref_r, &b);
+            assert_eq!(r.coeffs, ref_r.coeffs);
+        }
+    }
+
+    #[test]
+    fn compression_matches_reference() {
+        for _ in 0..ROUNDS {
+            let v = random_poly(KYBER_Q as i16);
+            let mut bytes = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+            let mut ref_bytes = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+            poly::poly_compress(&mut bytes, v);
+            reference::poly::poly_compress(&mut ref_bytes, v);
+            assert_eq!(bytes, ref_bytes);
+
+            let mut b = Polyvec::new();
+            for p in b.vec.iter_mut() {
+                *p = random_poly(KYBER_Q as i16);
+            }
+            let mut bytes = [0u8; KYBER_POLYVEC_COMPRESSED_BYTES];
+            let mut ref_bytes = [0u8; KYBER_POLYVEC_COMPRESSED_BYTES];
+            poly::polyvec_compress(&mut bytes, b);
+            reference::polyvec::polyvec_compress(&mut ref_bytes, b);
+            assert_eq!(bytes, ref_bytes);
+        }
+    }
+
+    #[test]
+    fn indcpa_matches_reference() {
+        differential::indcpa(
+            indcpa::indcpa_keypair,
+            indcpa::indcpa_enc,
+            indcpa::indcpa_dec,
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use crate::acceleration::{count_ntt, Acceleration};
+use crate::poly::{expect_domain, pack_bits, set_domain};
+use crate::reference::ntt::ZETAS;
+use crate::{params::*, poly::Poly, polyvec::Polyvec};
+use core::simd::num::{SimdInt, SimdUint};
+use core::simd::{i16x16, i32x16, u32x16, u64x16, Swizzle};
+
+const Q: i16 = KYBER_Q as i16;
+const QINV: i16 = -3327; // q^(-1) mod 2^16
+const V: i32 = 20159; // ((1 << 26) + q/2) / q
+const F: i16 = 1441; // mont^2/128
+
+// Lanes of 32 consecutive coefficients holding the first operands of the
+// butterflies of the NTT layer with the given len, in order; the second
+// operands sit len lanes further on.
+const fn first_operands(len: usize) -> [usize; 16] {
+    let mut lanes = [0; 16];
+    let (mut p, mut n) = (0, 0);
+    while p < 32 {
+        if p % (2 * len) < len {
+            lanes[n] = p;
+            n += 1;
+        }
+        p += 1;
+    }
+    lanes
+}
+
+// Where each of the 16 coefficients starting at 16 * half comes from
+// once the first and the second operands are concatenated again.
+const fn interleaved(len: usize, half: usize) -> [usize; 16] {
+    let mut lanes = [0; 16];
+    let mut j = 0;
+    while j < 16 {
+        let p = 16 * half + j;
+        lanes[j] = if p % (2 * len) < len {
+            p / (2 * len) * len + p % (2 * len)
+        } else {
+            16 + (p - len) / (2 * len) * len + (p - len) % (2 * len)
+        };
+        j += 1;
+    }
+    lanes
+}
+
+// Splits two vectors into the first and second operands of the
+// butterflies of len LEN.
+struct Split<const LEN: usize, const SECOND: usize>;
+
+impl<const LEN: usize, const SECOND: usize> Swizzle<16>
+    for Split<LEN, SECOND>
+{
+    const INDEX: [usize; 16] = {
+        let mut lanes = first_operands(LEN);
+        let mut n = 0;
+        while n < 16 {
+            lanes[n] += SECOND * LEN;
+            n += 1;
+        }
+        lanes
+    };
+}
+
+// The inverse of Split, one output vector at a time.
+struct Join<const LEN: usize, const HALF: usize>;
+
+impl<const LEN: usize, const HALF: usize> Swizzle<16>
+    for Join<LEN, HALF>
+{
+    const INDEX: [usize; 16] = interleaved(LEN, HALF);
+}
+
+// The zeta of every butterfly of the layers with len 8, 4 and 2, for
+// the 16 butterflies in each window of 32 coefficients.
+struct Layer {
+    zetas: [[i16; 16]; KYBER_N / 32],
+    inv_zetas: [[i16; 16]; KYBER_N / 32],
+}
+
+const fn layer(len: usize) -> Layer {
+    let first = first_operands(len);
+    let mut l = Layer {
+        zetas: [[0; 16]; KYBER_N / 32],
+        inv_zetas: [[0; 16]; KYBER_N / 32],
+    };
+    let mut w = 0;
+    while w < KYBER_N / 32 {
+        let mut n = 0;
+        while n < 16 {
+            let block = (32 * w + first[n]) / (2 * len);
+            l.zetas[w][n] = ZETAS[128 / len + block];
+            l.inv_zetas[w][n] = ZETAS[256 / len - 1 - block];
+            n += 1;
+        }
+        w += 1;
+    }
+    l
+}
+
+static LEN8: Layer = layer(8);
+static LEN4: Layer = layer(4);
+static LEN2: Layer = layer(2);
+
+// montgomery_reduce in every lane.
+fn montgomery_reduce(a: i32x16) -> i16x16 {
+    let u = a.cast::<i16>() * i16x16::splat(QINV);
+    ((a - u.cast::<i32>() * i32x16::splat(Q as i32)) >> 16)
+        .cast::<i16>()
+}
+
+// fqmul in every lane.
+fn fqmul(a: i16x16, b: i16x16) -> i16x16 {
+    montgomery_reduce(a.cast::<i32>() * b.cast::<i32>())
+}
+
+// barrett_reduce in every lane.
+fn barrett_reduce(a: i16x16) -> i16x16 {
+    let t = (a.cast::<i32>() * i32x16::splat(V)
+        + i32x16::splat(1 << 25))
+        >> 26;
+    a - t.cast::<i16>() * i16x16::splat(Q)
+}
+
+// Lifts every lane to its standard representative in 0..q, like csubq.
+fn csubq(a: i16x16) -> i16x16 {
+    let q = i16x16::splat(Q);
+    let a = a + ((a >> 15) & q) - q;
+    a + ((a >> 15) & q)
+}
+
+fn load(c: &[i16]) -> i16x16 {
+    i16x16::from_slice(&c[..16])
+}
+
+fn load_poly(r: &Poly) -> [i16x16; KYBER_N / 16] {
+    let mut v = [i16x16::splat(0); KYBER_N / 16];
+    for (v, c) in v.iter_mut().zip(r.coeffs.chunks_exact(16)) {
+        *v = load(c);
+    }
+    v
+}
+
+fn store_poly(r: &mut Poly, v: [i16x16; KYBER_N / 16]) {
+    for (c, v) in r.coeffs.chunks_exact_mut(16).zip(v) {
+        v.copy_to_slice(c);
+    }
+}
+
+// One forward layer with len below 16 on a window of 32 coefficients.
+fn butterflies<const LEN: usize>(
+    pair: &mut [i16x16],
+    zetas: &[i16; 16],
+) {
+    let a = Split::<LEN, 0>::concat_swizzle(pair[0], pair[1]);
+    let b = Split::<LEN, 1>::concat_swizzle(pair[0], pair[1]);
+    let t = fqmul(i16x16::from_array(*zetas), b);
+    let (a, b) = (a + t, a - t);
+    pair[0] = Join::<LEN, 0>::concat_swizzle(a, b);
+    pair[1] = Join::<LEN, 1>::concat_swizzle(a, b);
+}
+
+// One inverse layer with len below 16 on a window of 32 coefficients.
+fn inv_butterflies<const LEN: usize>(
+    pair: &mut [i16x16],
+    zetas: &[i16; 16],
+) {
+    let a = Split::<LEN, 0>::concat_swizzle(pair[0], pair[1]);
+    let b = Split::<LEN, 1>::concat_swizzle(pair[0], pair[1]);
+    let t = b - a;
+    let a = barrett_reduce(a + b);
+    let b = fqmul(i16x16::from_array(*zetas), t);
+    pair[0] = Join::<LEN, 0>::concat_swizzle(a, b);
+    pair[1] = Join::<LEN, 1>::concat_swizzle(a, b);
+}
+
+/// Name:  poly_ntt
+///
+/// Description: Computes the NTT of a polynomial in place like the
+///  reference poly_ntt, with identical output; inputs in normal order,
+///  output in bitreversed order
+///
+/// Arguments:   - Poly r: in/output polynomial
+pub(crate) fn poly_ntt(r: &mut Poly) {
+    expect_domain!(r, Normal, "poly_ntt");
+    count_ntt(Acceleration::PortableSimd);
+    let mut v = load_poly(r);
+
+    // len 128 down to 16: butterflies between whole vectors
+    let mut k = 1;
+    for dist in [8, 4, 2, 1] {
+        for start in (0..v.len()).step_by(2 * dist) {
+            let zeta = i16x16::splat(ZETAS[k]);
+            k += 1;
+            for j in start..start + dist {
+                let t = fqmul(zeta, v[j + dist]);
+                v[j + dist] = v[j] - t;
+                v[j] += t;
+            }
+        }
+    }
+
+    // len 8 down to 2: butterflies within windows of two vectors
+    for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+        butterflies::<8>(pair, &LEN8.zetas[w]);
+        butterflies::<4>(pair, &LEN4.zetas[w]);
+        butterflies::<2>(pair, &LEN2.zetas[w]);
+    }
+
+    store_poly(r, v.map(barrett_reduce));
+    set_domain!(r, Ntt);
+}
+
+/// Name:  poly_invntt_tomont
+///
+/// Description: Computes the inverse NTT of a polynomial in place and
+///  multiplies by the Montgomery factor like the reference
+///  poly_invntt_tomont, with identical output; inputs in bitreversed
+///  order, output in normal order
+///
+/// Arguments:   - Poly r: in/output polynomial
+pub(crate) fn poly_invntt_tomont(r: &mut Poly) {
+    expect_domain!(r, Ntt, "poly_invntt_tomont");
+    let mut v = load_poly(r);
+
+    // len 2 up to 8: butterflies within windows of two vectors
+    for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+        inv_butterflies::<2>(pair, &LEN2.inv_zetas[w]);
+        inv_butterflies::<4>(pair, &LEN4.inv_zetas[w]);
+        inv_butterflies::<8>(pair, &LEN8.inv_zetas[w]);
+    }
+
+    // len 16 up to 128: butterflies between whole vectors
+    for dist in [1, 2, 4, 8] {
+        let first = 256 / (16 * dist) - 1;
+        for (g, start) in (0..v.len()).step_by(2 * dist).enumerate() {
+            let zeta = i16x16::splat(ZETAS[first - g]);
+            for j in start..start + dist {
+                let t = v[j + dist] - v[j];
+                v[j] = barrett_reduce(v[j] + v[j + dist]);
+                v[j + dist] = fqmul(zeta, t);
+            }
+        }
+    }
+
+    let f = i16x16::splat(F);
+    store_poly(r, v.map(|v| fqmul(v, f)));
+    set_domain!(r, Normal);
+}
+
+/// Name:  poly_reduce
+///
+/// Description: Applies the reference Barrett reduction to all
+///  coefficients of a polynomial, giving the centered representatives
+///
+/// Arguments:   - poly *r:   input/output polynomial
+pub(crate) fn poly_reduce(r: &mut Poly) {
+    store_poly(r, load_poly(r).map(barrett_reduce));
+}
+
+/// Name:  poly_add
+///
+/// Description: Add two polynomials like the reference poly_add; no
+///  modular reduction is performed
+///
+/// Arguments: - poly *r:   input/output polynomial
+///  - const poly *b: second input polynomial
+pub(crate) fn poly_add(r: &mut Poly, b: &Poly) {
+    for (r, b) in r.coeffs.chunks_exact_mut(16).zip(b.coeffs.chunks(16))
+    {
+        (load(r) + load(b)).copy_to_slice(r);
+    }
+}
+
+/// Name:  poly_sub
+///
+/// Description: Subtract two polynomials like the reference poly_sub,
+///  leaving a - r in r; no modular reduction is performed
+///
+/// Arguments: - poly *r:   input/output polynomial
+///  - const poly *a: first input polynomial
+pub(crate) fn poly_sub(r: &mut Poly, a: &Poly) {
+    for (r, a) in r.coeffs.chunks_exact_mut(16).zip(a.coeffs.chunks(16))
+    {
+        (load(a) - load(r)).copy_to_slice(r);
+    }
+}
+
+/// Name:  poly_compress
+///
+/// Description: Compression and subsequent serialization of a polynomial
+///  like the reference poly_compress, with the division by q computed as
+///  (x * 315) >> 20 in 32-bit lanes. Panics if r is shorter than
+///  KYBER_POLY_COMPRESSED_BYTES
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLY_COMPRESSED_BYTES bytes)
+///  - const poly *a:  input polynomial
+pub(crate) fn poly_compress(r: &mut [u8], a: Poly) {
+    let r = &mut r[..KYBER_POLY_COMPRESSED_BYTES];
+    let mut t = [0u32; KYBER_N];
+    for (t, c) in t.chunks_exact_mut(16).zip(a.coeffs.chunks_exact(16))
+    {
+        let c = load(c);
+        // map to positive standard representatives
+        let u = (c + ((c >> 15) & i16x16::splat(Q))).cast::<u32>();
+        let x =
+            (u << KYBER_DV as u32) + u32x16::splat(KYBER_Q as u32 / 2);
+        ((x * u32x16::splat(315)) >> 20).copy_to_slice(t);
+    }
+    pack_bits(r, KYBER_DV, t.iter().copied());
+}
+
+/// Name:  polyvec_compress
+///
+/// Description: Compress and serialize vector of polynomials like the
+///  reference polyvec_compress, with the division by q computed as
+///  (x * 20642679) >> 36 in 64-bit lanes
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLYVEC_COMPRESSED_BYTES)
+///  - const Polyvec a: input vector of polynomials
+pub(crate) fn polyvec_compress(r: &mut [u8], a: Polyvec) {
+    let r = &mut r[..KYBER_POLYVEC_COMPRESSED_BYTES];
+    let mut t = [0u32; KYBER_SECURITY_PARAMETER * KYBER_N];
+    let coeffs = a.vec.iter().flat_map(|p| p.coeffs.chunks_exact(16));
+    for (t, c) in t.chunks_exact_mut(16).zip(coeffs) {
+        let u = csubq(load(c)).cast::<u64>();
+        let x =
+            (u << KYBER_DU as u64) + u64x16::splat(KYBER_Q as u64 / 2);
+        ((x * u64x16::splat(20642679)) >> 36)
+            .cast::<u32>()
+            .copy_to_slice(t);
+    }
+    pack_bits(r, KYBER_DU, t.iter().copied());
+}
+
+/// Name:  polyvec_ntt
+///
+/// Description: Apply forward NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+pub(crate) fn polyvec_ntt(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_ntt(poly);
+    }
+}
+
+/// Name:  polyvec_invntt_tomont
+///
+/// Description: Apply inverse NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+pub(crate) fn polyvec_invntt_tomont(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_invntt_tomont(poly);
+    }
+}
+
+/// Name:  polyvec_reduce
+///
+/// Description: Applies the reference Barrett reduction to each
+///  coefficient of each element of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: input/output vector of polynomials
+pub(crate) fn polyvec_reduce(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_reduce(poly);
+    }
+}
+
+/// Name:  polyvec_add
+///
+/// Description: Add vectors of polynomials
+///
+/// Arguments: - Polyvec r: input/output vector of polynomials
+///  - const Polyvec b: second input vector of polynomials
+pub(crate) fn polyvec_add(r: &mut Polyvec, b: &Polyvec) {
+    for (r, b) in r.vec.iter_mut().zip(b.vec.iter()) {
+        poly_add(r, b);
+    }
+}
@@ -0,0 +1,396 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Signed Kyber prekeys in the style of Signal's PQXDH.
+//!
+//! A prekey owner publishes [`PrekeyBundle`]s, each holding a Kyber public
+//! key, a prekey id and an Ed25519 signature by the owner's identity key
+//! over the encoded key. Peers call [`verify_bundle`] before encapsulating
+//! to the key.
+//!
+//! Prekeys come in two kinds. One-time prekeys are used for a single
+//! session: once a ciphertext for one has been decapsulated, its id is
+//! deleted. Last-resort prekeys are reusable and are served when the
+//! one-time prekeys have run out. [`PrekeyStore`] implements both
+//! behaviours on the owner's side.
+//!
+//! As in PQXDH, only the encoded key is signed, not the id or the kind.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::prekey::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let identity = SigningKey::from_bytes(&[7u8; 32]);
+//! let mut store = PrekeyStore::new();
+//! for prekey in generate_prekeys(&identity, 2, &mut rng)? {
+//!     store.insert(prekey);
+//! }
+//!
+//! let bundle = PrekeyBundle::from_bytes(&store.bundles()[0].to_bytes())?;
+//! verify_bundle(&identity.verifying_key(), &bundle)?;
+//! let (ct, ss) = encapsulate(bundle.public_key(), &mut rng)?;
+//!
+//! assert_eq!(store.decapsulate(bundle.id(), &ct)?, ss);
+//! // The one-time prekey is gone after its first use.
+//! assert!(store.decapsulate(bundle.id(), &ct).is_err());
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::{decapsulate, keypair},
+    error::KyberLibError,
+    kex::{PublicKey, SecretKey, SharedSecret},
+    params::*,
+    CryptoRng, RngCore,
+};
+use alloc::vec::Vec;
+use core::{convert::TryInto, fmt};
+use ed25519_dalek::{Signature, Signer, SIGNATURE_LENGTH};
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+use zeroize::Zeroize;
+
+/// Current version of the serialised bundle format.
+pub const PREKEY_FORMAT_VERSION: u8 = 1;
+/// Key type prefix of the encoded key; `0x08` is libsignal's Kyber1024.
+pub const KEY_TYPE: u8 = match KYBER_SECURITY_PARAMETER {
+    2 => 0x06,
+    3 => 0x07,
+    _ => 0x08,
+};
+/// Size in bytes of the encoded key, the key type followed by the key.
+pub const ENCODED_KEY_BYTES: usize = 1 + KYBER_PUBLIC_KEY_BYTES;
+/// Size in bytes of a serialised bundle.
+pub const PREKEY_BUNDLE_BYTES: usize =
+    2 + 4 + ENCODED_KEY_BYTES + SIGNATURE_LENGTH;
+
+/// Whether a prekey may serve more than one session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PrekeyKind {
+    /// Deleted after its first use.
+    OneTime,
+    /// Reusable, served when no one-time prekey is left.
+    LastResort,
+}
+
+impl PrekeyKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::OneTime => 1,
+            Self::LastResort => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Self::OneTime),
+            2 => Some(Self::LastResort),
+            _ => None,
+        }
+    }
+}
+
+/// The public half of a signed prekey, as uploaded to a server.
+///
+/// The serialised layout, integers little-endian, is:
+///
+/// | Bytes | Field |
+/// |-------|-------|
+/// | 1     | format version, currently [`PREKEY_FORMAT_VERSION`] |
+/// | 1     | kind, 1 for one-time and 2 for last-resort |
+/// | 4     | prekey id |
+/// | 1     | key type, [`KEY_TYPE`] |
+/// | 1568  | Kyber public key (Kyber1024) |
+/// | 64    | Ed25519 signature over the key type and the key |
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrekeyBundle {
+    id: u32,
+    kind: PrekeyKind,
+    public_key: PublicKey,
+    signature: [u8; SIGNATURE_LENGTH],
+}
+
+impl PrekeyBundle {
+    /// The prekey id.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The prekey kind.
+    pub fn kind(&self) -> PrekeyKind {
+        self.kind
+    }
+
+    /// The Kyber public key. Call [`verify_bundle`] before using it.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// The identity key's signature over [`PrekeyBundle::encoded_key`].
+    pub fn signature(&self) -> &[u8; SIGNATURE_LENGTH] {
+        &self.signature
+    }
+
+    /// The signed message: the key type followed by the public key.
+    pub fn encoded_key(&self) -> [u8; ENCODED_KEY_BYTES] {
+        encode_key(&self.public_key)
+    }
+
+    /// Returns the serialised bundle.
+    pub fn to_bytes(&self) -> [u8; PREKEY_BUNDLE_BYTES] {
+        let mut bytes = [0u8; PREKEY_BUNDLE_BYTES];
+        bytes[0] = PREKEY_FORMAT_VERSION;
+        bytes[1] = self.kind.to_byte();
+        bytes[2..6].copy_from_slice(&self.id.to_le_bytes());
+        bytes[6..6 + ENCODED_KEY_BYTES]
+            .copy_from_slice(&self.encoded_key());
+        bytes[6 + ENCODED_KEY_BYTES..].copy_from_slice(&self.signature);
+        bytes
+    }
+
+    /// Parses a serialised bundle produced by [`PrekeyBundle::to_bytes`].
+    ///
+    /// The signature is not checked; see [`verify_bundle`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `bytes` has the wrong size
+    /// and `KyberLibError::InvalidInput` for an unknown version, kind or
+    /// key type.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KyberLibError> {
+        if bytes.len() != PREKEY_BUNDLE_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let kind = PrekeyKind::from_byte(bytes[1]);
+        if bytes[0] != PREKEY_FORMAT_VERSION || bytes[6] != KEY_TYPE {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let (key, signature) =
+            bytes[7..].split_at(KYBER_PUBLIC_KEY_BYTES);
+        Ok(Self {
+            id: u32::from_le_bytes(
+                bytes[2..6]
+                    .try_into()
+                    .map_err(|_| KyberLibError::InvalidLength)?,
+            ),
+            kind: kind.ok_or(KyberLibError::InvalidInput)?,
+            public_key: key
+                .try_into()
+                .map_err(|_| KyberLibError::InvalidLength)?,
+            signature: signature
+                .try_into()
+                .map_err(|_| KyberLibError::InvalidLength)?,
+        })
+    }
+}
+
+/// A prekey bundle together with its secret key, kept by the owner.
+///
+/// The secret key is zeroized on drop and redacted from `Debug` output.
+pub struct SignedPrekey {
+    bundle: PrekeyBundle,
+    secret: SecretKey,
+}
+
+impl SignedPrekey {
+    /// Generates and signs a prekey.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    pub fn generate<R>(
+        identity: &SigningKey,
+        id: u32,
+        kind: PrekeyKind,
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut keys = keypair(rng)?;
+        let signature =
+            identity.sign(&encode_key(&keys.public)).to_bytes();
+        let prekey = Self {
+            bundle: PrekeyBundle {
+                id,
+                kind,
+                public_key: keys.public,
+                signature,
+            },
+            secret: keys.secret,
+        };
+        keys.secret.zeroize();
+        Ok(prekey)
+    }
+
+    /// The public bundle to upload.
+    pub fn bundle(&self) -> &PrekeyBundle {
+        &self.bundle
+    }
+
+    /// Decapsulates a ciphertext sent to this prekey.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if `ct` has the wrong size.
+    pub fn decapsulate(
+        &self,
+        ct: &[u8],
+    ) -> Result<SharedSecret, KyberLibError> {
+        decapsulate(ct, &self.secret)
+    }
+}
+
+impl fmt::Debug for SignedPrekey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignedPrekey")
+            .field("bundle", &self.bundle)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SignedPrekey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// The owner's prekeys, applying one-time and last-resort semantics.
+#[derive(Debug, Default)]
+pub struct PrekeyStore {
+    prekeys: Vec<SignedPrekey>,
+}
+
+impl PrekeyStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a prekey, replacing any prekey with the same id.
+    pub fn insert(&mut self, prekey: SignedPrekey) {
+        self.prekeys.retain(|p| p.bundle.id != prekey.bundle.id);
+        self.prekeys.push(prekey);
+    }
+
+    /// The bundles of all stored prekeys.
+    pub fn bundles(&self) -> Vec<PrekeyBundle> {
+        self.prekeys.iter().map(|p| p.bundle).collect()
+    }
+
+    /// Decapsulates a ciphertext sent to prekey `id`.
+    ///
+    /// A one-time prekey is deleted once used; a last-resort prekey stays.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if no prekey with this id is
+    /// stored, e.g. because the one-time prekey was already used, and
+    /// `KyberLibError::InvalidInput` if `ct` has the wrong size.
+    pub fn decapsulate(
+        &mut self,
+        id: u32,
+        ct: &[u8],
+    ) -> Result<SharedSecret, KyberLibError> {
+        let index = self
+            .prekeys
+            .iter()
+            .position(|p| p.bundle.id == id)
+            .ok_or(KyberLibError::InvalidKey)?;
+        let shared_secret = self.prekeys[index].decapsulate(ct)?;
+        if self.prekeys[index].bundle.kind == PrekeyKind::OneTime {
+            drop(self.prekeys.swap_remove(index));
+        }
+        Ok(shared_secret)
+    }
+}
+
+/// Generates `count` one-time prekeys signed by `identity`.
+///
+/// Ids are drawn at random from `rng`, non-zero and distinct within the
+/// batch.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+pub fn generate_prekeys<R>(
+    identity: &SigningKey,
+    count: usize,
+    rng: &mut R,
+) -> Result<Vec<SignedPrekey>, KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    let mut prekeys: Vec<SignedPrekey> = Vec::with_capacity(count);
+    while prekeys.len() < count {
+        let id = random_id(rng)?;
+        if prekeys.iter().all(|p| p.bundle.id != id) {
+            prekeys.push(SignedPrekey::generate(
+                identity,
+                id,
+                PrekeyKind::OneTime,
+                rng,
+            )?);
+        }
+    }
+    Ok(prekeys)
+}
+
+/// Generates a last-resort prekey signed by `identity`, with a random id.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+pub fn generate_last_resort_prekey<R>(
+    identity: &SigningKey,
+    rng: &mut R,
+) -> Result<SignedPrekey, KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    let id = random_id(rng)?;
+    SignedPrekey::generate(identity, id, PrekeyKind::LastResort, rng)
+}
+
+/// Checks that `bundle` was signed by `identity`.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidSignature` if the signature does not
+/// verify, using the strict Ed25519 verification rules.
+pub fn verify_bundle(
+    identity: &VerifyingKey,
+    bundle: &PrekeyBundle,
+) -> Result<(), KyberLibError> {
+    identity
+        .verify_strict(
+            &bundle.encoded_key(),
+            &Signature::from_bytes(&bundle.signature),
+        )
+        .map_err(|_| KyberLibError::InvalidSignature)
+}
+
+fn encode_key(public_key: &PublicKey) -> [u8; ENCODED_KEY_BYTES] {
+    let mut encoded = [0u8; ENCODED_KEY_BYTES];
+    encoded[0] = KEY_TYPE;
+    encoded[1..].copy_from_slice(public_key);
+    encoded
+}
+
+fn random_id<R>(rng: &mut R) -> Result<u32, KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    let mut bytes = [0u8; 4];
+    loop {
+        rng.try_fill_bytes(&mut bytes)
+            .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+        let id = u32::from_le_bytes(bytes);
+        if id != 0 {
+            return Ok(id);
+        }
+    }
+}
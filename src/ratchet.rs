@@ -0,0 +1,143 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Symmetric hash ratchet over a KEM shared secret.
+//!
+//! A session keyed from one shared secret has no forward secrecy until the
+//! next handshake. A [`Ratchet`] turns the secret into a chain of per-epoch
+//! keys and forgets every chain key it has moved past, so compromising the
+//! current state does not reveal the keys of earlier epochs.
+//!
+//! The chain is built with the labeled KDF:
+//!
+//! ```text
+//! chain_0            = KDF("kyberlib ratchet chain v1", shared_secret, label)
+//! chain_n+1 || key_n = KDF("kyberlib ratchet step v1", chain_n)
+//! ```
+//!
+//! where each KDF output is 32 bytes per value and the `chain_n+1` half
+//! comes first. `label` separates ratchets derived from the same secret,
+//! e.g. one per direction. Only the epoch counter is serialised; a peer
+//! that falls behind catches up with [`Ratchet::skip_to`], which refuses
+//! to advance more than [`MAX_SKIP`] epochs at once.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::ratchet::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let (ct, alice_secret) = encapsulate(&keys.public, &mut rng)?;
+//! let bob_secret = decapsulate(&ct, &keys.secret)?;
+//!
+//! let mut alice = Ratchet::new(&alice_secret, b"alice to bob");
+//! let mut bob = Ratchet::new(&bob_secret, b"alice to bob");
+//! let _lost = alice.next_key();
+//! let key = alice.next_key();
+//!
+//! bob.skip_to(epoch_from_bytes(&alice.epoch_to_bytes())? - 1)?;
+//! assert_eq!(bob.next_key(), key);
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    error::KyberLibError, kex::SharedSecret, symmetric::labeled_kdf,
+};
+use core::convert::TryInto;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Size in bytes of a ratchet key.
+pub const RATCHET_KEY_BYTES: usize = 32;
+/// Size in bytes of a serialised epoch counter.
+pub const EPOCH_BYTES: usize = 8;
+/// Largest number of epochs [`Ratchet::skip_to`] advances in one call.
+pub const MAX_SKIP: u64 = 1024;
+
+const CHAIN_LABEL: &str = "kyberlib ratchet chain v1";
+const STEP_LABEL: &str = "kyberlib ratchet step v1";
+
+/// A hash ratchet yielding one key per epoch.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Ratchet {
+    chain: [u8; RATCHET_KEY_BYTES],
+    epoch: u64,
+}
+
+impl core::fmt::Debug for Ratchet {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_struct("Ratchet")
+            .field("epoch", &self.epoch)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Ratchet {
+    /// Starts a chain at epoch 0 from `shared_secret` and `label`.
+    pub fn new(shared_secret: &SharedSecret, label: &[u8]) -> Self {
+        let mut chain = [0u8; RATCHET_KEY_BYTES];
+        labeled_kdf(&mut chain, CHAIN_LABEL, &[shared_secret, label]);
+        Self { chain, epoch: 0 }
+    }
+
+    /// Returns the epoch of the key the next call to
+    /// [`Ratchet::next_key`] yields.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the key of the current epoch and advances to the next one.
+    ///
+    /// The previous chain key is overwritten, so the returned key cannot be
+    /// derived again from this ratchet.
+    pub fn next_key(&mut self) -> [u8; RATCHET_KEY_BYTES] {
+        let mut okm = [0u8; 2 * RATCHET_KEY_BYTES];
+        labeled_kdf(&mut okm, STEP_LABEL, &[&self.chain]);
+        self.chain.copy_from_slice(&okm[..RATCHET_KEY_BYTES]);
+        let mut key = [0u8; RATCHET_KEY_BYTES];
+        key.copy_from_slice(&okm[RATCHET_KEY_BYTES..]);
+        okm.zeroize();
+        self.epoch += 1;
+        key
+    }
+
+    /// Advances to `epoch`, discarding the keys of the epochs in between.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if `epoch` is behind the
+    /// current epoch or more than [`MAX_SKIP`] epochs ahead of it.
+    pub fn skip_to(&mut self, epoch: u64) -> Result<(), KyberLibError> {
+        if epoch < self.epoch || epoch - self.epoch > MAX_SKIP {
+            return Err(KyberLibError::InvalidInput);
+        }
+        while self.epoch < epoch {
+            let mut key = self.next_key();
+            key.zeroize();
+        }
+        Ok(())
+    }
+
+    /// Serialises the current epoch as a little-endian `u64`.
+    ///
+    /// The counter reveals nothing about the chain key and can be sent in
+    /// the clear for resynchronisation.
+    pub fn epoch_to_bytes(&self) -> [u8; EPOCH_BYTES] {
+        self.epoch.to_le_bytes()
+    }
+}
+
+/// Parses an epoch counter serialised by [`Ratchet::epoch_to_bytes`].
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `bytes` is not
+/// `EPOCH_BYTES` long.
+pub fn epoch_from_bytes(bytes: &[u8]) -> Result<u64, KyberLibError> {
+    let bytes: [u8; EPOCH_BYTES] =
+        bytes.try_into().map_err(|_| KyberLibError::InvalidLength)?;
+    Ok(u64::from_le_bytes(bytes))
+}
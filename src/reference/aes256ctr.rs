@@ -26,7 +26,7 @@
  * SOFTWARE.
  */
 #![cfg(feature = "90s")]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Aes256CtrCtx {
     pub sk_exp: [u64; 120],
     pub ivw: [u32; 16],
@@ -39,6 +39,20 @@ impl Aes256CtrCtx {
             ivw: [0u32; 16],
         }
     }
+
+    /// The state aes256ctr_init leaves for `key` and `nonce`, built in
+    /// place rather than over a zeroed one: both arrays are written
+    /// element by element from the key schedule and the nonce.
+    pub(crate) fn with_key(key: &[u8], nonce: [u8; 12]) -> Self {
+        Self {
+            sk_exp: br_aes_ct64_ctr_init(key),
+            // four counter blocks, the nonce then counters 0 to 3
+            ivw: core::array::from_fn(|i| match i & 3 {
+                3 => br_swap32((i >> 2) as u32),
+                w => br_dec32le(&nonce[4 * w..]),
+            }),
+        }
+    }
 }
 
 impl Default for Aes256CtrCtx {
@@ -67,7 +81,7 @@ fn br_range_dec32le(v: &mut [u32], mut num: usize, src: &[u8]) {
 
 fn br_swap32(mut x: u32) -> u32 {
     x = ((x & 0x00FF00FFu32) << 8) | ((x >> 8) & 0x00FF00FFu32);
-    (x << 16) | (x >> 16)
+    x.rotate_left(16)
 }
 
 fn br_enc32le(dst: &mut [u8], x: u32) {
@@ -491,10 +505,10 @@ fn br_aes_ct64_keysched(comp_skey: &mut [u64], key: &[u8]) {
     let nk = key_len >> 2;
     let nkf = (14 + 1) << 2;
     br_range_dec32le(&mut skey, key_len >> 2, key);
-    let mut tmp = skey[key_len >> (2 - 1)];
+    let mut tmp = skey[(key_len >> 2) - 1];
     for i in nk..nkf {
         if j == 0 {
-            tmp = (tmp << 24) | (tmp >> 8);
+            tmp = tmp.rotate_right(8);
             tmp = sub_word(tmp) ^ RCON[k];
         } else if nk > 6 && j == 4 {
             tmp = sub_word(tmp);
@@ -533,33 +547,14 @@ fn br_aes_ct64_keysched(comp_skey: &mut [u64], key: &[u8]) {
     }
 }
 
-fn br_aes_ct64_skey_expand(skey: &mut [u64], comp_skey: &[u64]) {
-    const N: usize = 15 << 1;
-    let mut u = 0;
-    let mut v = 0;
-    let mut x0: u64;
-    let mut x1: u64;
-    let mut x2: u64;
-    let mut x3: u64;
-    while u < N {
-        x0 = comp_skey[u];
-        x1 = comp_skey[u];
-        x2 = comp_skey[u];
-        x3 = comp_skey[u];
-        x0 &= 0x1111111111111111;
-        x1 &= 0x2222222222222222;
-        x2 &= 0x4444444444444444;
-        x3 &= 0x8888888888888888;
-        x1 >>= 1;
-        x2 >>= 2;
-        x3 >>= 3;
-        skey[v] = (x0 << 4).wrapping_sub(x0);
-        skey[v + 1] = (x1 << 4).wrapping_sub(x1);
-        skey[v + 2] = (x2 << 4).wrapping_sub(x2);
-        skey[v + 3] = (x3 << 4).wrapping_sub(x3);
-        v += 4;
-        u += 1;
-    }
+// Each word of the expanded key is a function of one compressed word,
+// so the schedule is written word by word instead of into a zeroed
+// array.
+fn br_aes_ct64_skey_expand(comp_skey: &[u64]) -> [u64; 120] {
+    core::array::from_fn(|v| {
+        let x = (comp_skey[v >> 2] >> (v & 3)) & 0x1111111111111111;
+        (x << 4).wrapping_sub(x)
+    })
 }
 
 fn add_round_key(q: &mut [u64], sk: &[u64]) {
@@ -586,7 +581,7 @@ fn shift_rows(q: &mut [u64]) {
 }
 
 fn rotr32(x: u64) -> u64 {
-    (x << 32) | (x >> 32)
+    x.rotate_right(32)
 }
 
 fn mix_columns(q: &mut [u64]) {
@@ -598,14 +593,14 @@ fn mix_columns(q: &mut [u64]) {
     let q5 = q[5];
     let q6 = q[6];
     let q7 = q[7];
-    let r0 = (q0 >> 16) | (q0 << 48);
-    let r1 = (q1 >> 16) | (q1 << 48);
-    let r2 = (q2 >> 16) | (q2 << 48);
-    let r3 = (q3 >> 16) | (q3 << 48);
-    let r4 = (q4 >> 16) | (q4 << 48);
-    let r5 = (q5 >> 16) | (q5 << 48);
-    let r6 = (q6 >> 16) | (q6 << 48);
-    let r7 = (q7 >> 16) | (q7 << 48);
+    let r0 = q0.rotate_right(16);
+    let r1 = q1.rotate_right(16);
+    let r2 = q2.rotate_right(16);
+    let r3 = q3.rotate_right(16);
+    let r4 = q4.rotate_right(16);
+    let r5 = q5.rotate_right(16);
+    let r6 = q6.rotate_right(16);
+    let r7 = q7.rotate_right(16);
 
     q[0] = q7 ^ r7 ^ r0 ^ rotr32(q0 ^ r0);
     q[1] = q0 ^ r0 ^ q7 ^ r7 ^ r1 ^ rotr32(q1 ^ r1);
@@ -660,10 +655,10 @@ fn aes_ctr4x(out: &mut [u8], ivw: &mut [u32], sk_exp: &[u64]) {
     ivw[15] = inc4_be(ivw[15]);
 }
 
-fn br_aes_ct64_ctr_init(sk_exp: &mut [u64], key: &[u8]) {
+fn br_aes_ct64_ctr_init(key: &[u8]) -> [u64; 120] {
     let mut skey = [0u64; 30];
     br_aes_ct64_keysched(&mut skey, key);
-    br_aes_ct64_skey_expand(sk_exp, &skey);
+    br_aes_ct64_skey_expand(&skey)
 }
 
 /// Name:  aes256ctr_init
@@ -680,18 +675,7 @@ pub(crate) fn aes256ctr_init(
     key: &[u8],
     nonce: [u8; 12],
 ) {
-    br_aes_ct64_ctr_init(&mut s.sk_exp, key);
-
-    br_range_dec32le(&mut s.ivw, 3, &nonce);
-    let mut slice = [0u32; 3];
-    slice.copy_from_slice(&s.ivw[..3]);
-    s.ivw[4..7].copy_from_slice(&slice);
-    s.ivw[8..11].copy_from_slice(&slice);
-    s.ivw[12..15].copy_from_slice(&slice);
-    s.ivw[3] = br_swap32(0);
-    s.ivw[7] = br_swap32(1);
-    s.ivw[11] = br_swap32(2);
-    s.ivw[15] = br_swap32(3);
+    *s = Aes256CtrCtx::with_key(key, nonce);
 }
 
 pub(crate) fn aes256ctr_squeezeblocks(
@@ -706,3 +690,36 @@ pub(crate) fn aes256ctr_squeezeblocks(
         nblocks -= 1;
     }
 }
+
+#[cfg(all(test, not(feature = "small")))]
+mod tests {
+    use super::*;
+    use aes::cipher::{
+        generic_array::GenericArray, KeyIvInit, StreamCipher,
+    };
+    use rand::{rngs::OsRng, RngCore};
+
+    // The bitsliced CTR keystream is the standard one: 32-bit
+    // big-endian counter after a 12-byte nonce, starting at zero.
+    #[test]
+    fn keystream_matches_aes_crate() {
+        for _ in 0..16 {
+            let (mut key, mut nonce) = ([0u8; 32], [0u8; 12]);
+            OsRng.fill_bytes(&mut key);
+            OsRng.fill_bytes(&mut nonce);
+            let mut s = Aes256CtrCtx::with_key(&key, nonce);
+            let mut out = [0u8; 3 * 64];
+            aes256ctr_squeezeblocks(&mut out, 3, &mut s);
+
+            let mut iv = [0u8; 16];
+            iv[..12].copy_from_slice(&nonce);
+            let mut expected = [0u8; 3 * 64];
+            ctr::Ctr32BE::<aes::Aes256>::new(
+                GenericArray::from_slice(&key),
+                GenericArray::from_slice(&iv),
+            )
+            .apply_keystream(&mut expected);
+            assert_eq!(out, expected);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::params::KYBER_N;
-use crate::poly::Poly;
+use crate::poly::{set_domain, Poly};
 
 /// Name:  load32_littleendian
 ///
@@ -42,6 +42,7 @@ fn load24_littleendian(x: &[u8]) -> u32 {
 /// Arguments:   - poly *r:    output polynomial
 ///  - const [u8] buf: input byte array
 pub(crate) fn cbd2(r: &mut Poly, buf: &[u8]) {
+    set_domain!(r, Normal);
     let (mut d, mut t, mut a, mut b);
     for i in 0..(KYBER_N / 8) {
         t = load32_littleendian(&buf[4 * i..]);
@@ -64,6 +65,7 @@ pub(crate) fn cbd2(r: &mut Poly, buf: &[u8]) {
 /// Arguments:   - poly *r:    output polynomial
 ///  - const [u8] buf: input byte array
 pub(crate) fn cbd3(r: &mut Poly, buf: &[u8]) {
+    set_domain!(r, Normal);
     let (mut d, mut t, mut a, mut b);
     for i in 0..(KYBER_N / 4) {
         t = load24_littleendian(&buf[3 * i..]);
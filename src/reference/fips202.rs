@@ -41,7 +41,7 @@ pub(crate) fn store64(x: &mut [u8], mut u: u64) {
 }
 
 /// Keccak round constants
-const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
+pub(super) const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
     0x0000000000000001,
     0x0000000000008082,
     0x800000000000808a,
@@ -70,10 +70,27 @@ const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
 
 /// Name:  KeccakF1600_StatePermute
 ///
-/// Description: The Keccak F1600 Permutation
+/// Description: The Keccak F1600 Permutation, on the Armv8.2 SHA3
+///  extension when the `armv8-sha3` feature is enabled and the CPU has
+///  it, see the keccak_sha3 module
 ///
 /// Arguments:   - u64 * state: in/output Keccak state
 pub(crate) fn keccakf1600_statepermute(state: &mut [u64]) {
+    #[cfg(all(target_arch = "aarch64", feature = "armv8-sha3"))]
+    if super::keccak_sha3::supported() {
+        // SAFETY: the CPU has the SHA3 extension.
+        unsafe { super::keccak_sha3::keccakf1600_statepermute(state) };
+        return;
+    }
+    keccakf1600_statepermute_generic(state)
+}
+
+/// Name:  KeccakF1600_StatePermute
+///
+/// Description: The Keccak F1600 Permutation in software
+///
+/// Arguments:   - u64 * state: in/output Keccak state
+pub(super) fn keccakf1600_statepermute_generic(state: &mut [u64]) {
     //copyFromState(A, state)
     let mut aba = state[0];
     let mut abe = state[1];
@@ -434,6 +451,40 @@ fn keccak_finalize(s: &mut [u64], pos: usize, r: usize, p: u8) {
     s[r / 8 - 1] ^= 1u64 << 63;
 }
 
+/// Name:  keccak_absorb
+///
+/// Description: Absorb step of Keccak; incremental.
+///
+/// Arguments:   - u64 *s:   in/output Keccak state
+///  - usize pos: position in current block to be absorbed
+///  - usize r:  rate in bytes (e.g., 168 for SHAKE128)
+///  - const [u8] input:  input to be absorbed into s
+///
+/// Returns new position pos in current block
+fn keccak_absorb(
+    s: &mut [u64],
+    mut pos: usize,
+    r: usize,
+    input: &[u8],
+) -> usize {
+    let mut idx = 0usize;
+    let mut inlen = input.len();
+    while pos + inlen >= r {
+        for i in pos..r {
+            s[i / 8] ^= (input[idx] as u64) << (8 * (i % 8));
+            idx += 1;
+        }
+        inlen -= r - pos;
+        keccakf1600_statepermute(s);
+        pos = 0;
+    }
+    for i in pos..pos + inlen {
+        s[i / 8] ^= (input[idx] as u64) << (8 * (i % 8));
+        idx += 1;
+    }
+    pos + inlen
+}
+
 /// Name:  keccak_absorb_once
 ///
 /// Description: Absorb step of Keccak;
@@ -480,8 +531,9 @@ pub(crate) fn keccak_absorb_once(
 /// Arguments:   - [u8] out:   output blocks
 ///  - u64 nblocks:  number of blocks to be squeezed (written to out)
 ///  - u64 *s:   in/output Keccak state
-///  usize pos: number of bytes in current block already squeezed
+///  - usize pos: number of bytes in current block already squeezed
 ///  - usize r:  rate in bytes (e.g., 168 for SHAKE128)
+///
 /// Returns new position pos in current block
 pub(crate) fn keccak_squeeze(
     out: &mut [u8],
@@ -567,16 +619,45 @@ pub(crate) fn shake128_absorb_once(
     state.pos = SHAKE128_RATE;
 }
 
-fn shake256_init(state: &mut KeccakState) {
+/// Name:  shake256_init
+///
+/// Description: Initilizes Keccak state for use as SHAKE256 XOF
+///
+/// Arguments:   - keccak_state state: (uninitialized) Keccak state
+pub(crate) fn shake256_init(state: &mut KeccakState) {
     state.reset();
 }
 
-fn shake256_finalize(state: &mut KeccakState) {
+/// Name:  shake256_absorb
+///
+/// Description: Absorb step of the SHAKE256 XOF; incremental.
+///
+/// Arguments:   - keccak_state state: pointer to input/output Keccak state
+///  - const [u8] in: input to be absorbed into s
+pub(crate) fn shake256_absorb(state: &mut KeccakState, input: &[u8]) {
+    state.pos =
+        keccak_absorb(&mut state.s, state.pos, SHAKE256_RATE, input);
+}
+
+/// Name:  shake256_finalize
+///
+/// Description: Finalize absorb step of the SHAKE256 XOF.
+///
+/// Arguments:   - keccak_state state: pointer to Keccak state
+pub(crate) fn shake256_finalize(state: &mut KeccakState) {
     keccak_finalize(&mut state.s, state.pos, SHAKE256_RATE, 0x1F);
     state.pos = SHAKE256_RATE;
 }
 
-fn shake256_squeeze(
+/// Name:  shake256_squeeze
+///
+/// Description: Squeeze step of SHAKE256 XOF. Squeezes arbitraily many
+///  bytes. Can be called multiple times to keep squeezing.
+///
+/// Arguments:   - [u8] out: pointer to output blocks
+///  - usize outlen : number of bytes to be squeezed (written to output)
+///  - keccak_state s: pointer to input/output Keccak state
+pub(crate) fn shake256_squeeze(
     out: &mut [u8],
     outlen: usize,
     state: &mut KeccakState,
@@ -622,3 +703,97 @@ fn shake128(
     idx += nblocks * SHAKE128_RATE;
     shake128_squeeze(&mut out[idx..], outlen, &mut state);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keccak-f[1600] applied once to the all-zero state, from the Keccak
+    // team's intermediate values.
+    const ZERO_STATE_PERMUTED: [u64; 25] = [
+        0xF1258F7940E1DDE7,
+        0x84D5CCF933C0478A,
+        0xD598261EA65AA9EE,
+        0xBD1547306F80494D,
+        0x8B284E056253D057,
+        0xFF97A42D7F8E6FD4,
+        0x90FEE5A0A44647C4,
+        0x8C5BDA0CD6192E76,
+        0xAD30A6F71B19059C,
+        0x30935AB7D08FFC64,
+        0xEB5AA93F2317D635,
+        0xA9A6E6260D712103,
+        0x81A57C16DBCF555F,
+        0x43B831CD0347C826,
+        0x01F22F1A11A5569F,
+        0x05E5635A21D9AE61,
+        0x64BEFEF28CC970F2,
+        0x613670957BC46611,
+        0xB87C5A554FD00ECB,
+        0x8C3EE88A1CCF32C8,
+        0x940C7922AE3A2614,
+        0x1841F924A2C509E4,
+        0x16F53526E70465C2,
+        0x75F644E97F30A13B,
+        0xEAF1FF7B5CECA249,
+    ];
+
+    #[test]
+    fn permutation_of_the_zero_state() {
+        let mut state = [0u64; 25];
+        keccakf1600_statepermute_generic(&mut state);
+        assert_eq!(state, ZERO_STATE_PERMUTED);
+
+        // Through the dispatch, on the SHA3 extension where available.
+        let mut state = [0u64; 25];
+        keccakf1600_statepermute(&mut state);
+        assert_eq!(state, ZERO_STATE_PERMUTED);
+    }
+
+    #[test]
+    fn incremental_absorb_matches_one_shot() {
+        let input: [u8; 400] = core::array::from_fn(|i| (i * 7) as u8);
+        let mut expected = [0u8; 64];
+        shake256(&mut expected, 64, &input, input.len());
+
+        for split in [0, 1, 135, 136, 137, 272, 399, 400] {
+            let mut state = KeccakState::new();
+            shake256_init(&mut state);
+            let (first, second) = input.split_at(split);
+            shake256_absorb(&mut state, first);
+            shake256_absorb(&mut state, second);
+            shake256_finalize(&mut state);
+            let mut out = [0u8; 64];
+            shake256_squeeze(&mut out[..24], 24, &mut state);
+            shake256_squeeze(&mut out[24..], 40, &mut state);
+            assert_eq!(out, expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn hashes_of_the_empty_string() {
+        let mut h = [0u8; 32];
+        sha3_256(&mut h, &[], 0);
+        assert_eq!(
+            h,
+            [
+                0xa7, 0xff, 0xc6, 0xf8, 0xbf, 0x1e, 0xd7, 0x66, 0x51,
+                0xc1, 0x47, 0x56, 0xa0, 0x61, 0xd6, 0x62, 0xf5, 0x80,
+                0xff, 0x4d, 0xe4, 0x3b, 0x49, 0xfa, 0x82, 0xd8, 0x0a,
+                0x4b, 0x80, 0xf8, 0x43, 0x4a,
+            ]
+        );
+
+        let mut out = [0u8; 32];
+        shake128(&mut out, 32, &[], 0);
+        assert_eq!(
+            out,
+            [
+                0x7f, 0x9c, 0x2b, 0xa4, 0xe8, 0x8f, 0x82, 0x7d, 0x61,
+                0x60, 0x45, 0x50, 0x76, 0x05, 0x85, 0x3e, 0xd7, 0x3b,
+                0x80, 0x93, 0xf6, 0xef, 0xbc, 0x88, 0xeb, 0x1a, 0x6e,
+                0xac, 0xfa, 0x66, 0xef, 0x26,
+            ]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use crate::rng::randombytes;
 use crate::{
-    params::*, poly::*, polyvec::*, symmetric::*, CryptoRng,
-    KyberLibError, RngCore,
+    acceleration::count_matrix_entry, params::*, poly::*, polyvec::*,
+    symmetric::*, CryptoRng, KyberLibError, RngCore,
 };
 
 #[cfg(feature = "hazmat")]
@@ -20,6 +20,7 @@ pub use crate::params::{
 /// Arguments:   [u8] r:  the output serialized public key
 ///  const poly *pk:  the input public-key polynomial
 ///  const [u8] seed: the input public seed
+#[cfg(test)]
 fn pack_pk(r: &mut [u8], pk: &mut Polyvec, seed: &[u8]) {
     const END: usize = KYBER_SYM_BYTES + KYBER_POLYVEC_BYTES;
     polyvec_tobytes(r, pk);
@@ -35,6 +36,7 @@ fn pack_pk(r: &mut [u8], pk: &mut Polyvec, seed: &[u8]) {
 /// Arguments:   - Polyvec pk:  output public-key vector of polynomials
 ///  - [u8] seed:   output seed to generate matrix A
 ///  - const [u8] packedpk: input serialized public key
+#[cfg(test)]
 fn unpack_pk(pk: &mut Polyvec, seed: &mut [u8], packedpk: &[u8]) {
     const END: usize = KYBER_SYM_BYTES + KYBER_POLYVEC_BYTES;
     polyvec_frombytes(pk, packedpk);
@@ -58,6 +60,7 @@ fn pack_sk(r: &mut [u8], sk: &mut Polyvec) {
 ///
 /// Arguments:   - Polyvec sk: output vector of polynomials (secret key)
 ///  - const [u8] packedsk: input serialized secret key
+#[cfg(test)]
 fn unpack_sk(sk: &mut Polyvec, packedsk: &[u8]) {
     polyvec_frombytes(sk, packedsk);
 }
@@ -71,6 +74,7 @@ fn unpack_sk(sk: &mut Polyvec, packedsk: &[u8]) {
 /// Arguments:   [u8] r:  the output serialized ciphertext
 ///  const poly *pk:  the input vector of polynomials b
 ///  const [u8] seed: the input polynomial v
+#[cfg(test)]
 fn pack_ciphertext(r: &mut [u8], b: &mut Polyvec, v: Poly) {
     polyvec_compress(r, *b);
     poly_compress(&mut r[KYBER_POLYVEC_COMPRESSED_BYTES..], v);
@@ -84,6 +88,7 @@ fn pack_ciphertext(r: &mut [u8], b: &mut Polyvec, v: Poly) {
 /// Arguments:   - Polyvec b:   output vector of polynomials b
 ///  - poly *v:  output polynomial v
 ///  - const [u8] c:   input serialized ciphertext
+#[cfg(test)]
 fn unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8]) {
     polyvec_decompress(b, c);
     poly_decompress(v, &c[KYBER_POLYVEC_COMPRESSED_BYTES..]);
@@ -99,8 +104,9 @@ fn unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8]) {
 ///  - const [u8] buf:  input buffer (assumed to be uniform random bytes)
 ///  - usize buflen:  length of input buffer in bytes
 ///
-/// Returns number of sampled 16-bit integers (at most len)
-fn rej_uniform(
+/// Returns number of sampled 16-bit integers (at most len). Entries of r
+/// past that number, up to len, may be overwritten with rejected values
+pub(crate) fn rej_uniform(
     r: &mut [i16],
     len: usize,
     buf: &[u8],
@@ -109,6 +115,22 @@ fn rej_uniform(
     let (mut ctr, mut pos) = (0usize, 0usize);
     let (mut val0, mut val1);
 
+    // While both candidates of a group fit, store them unconditionally
+    // and keep the accepted ones by advancing ctr past them, instead of
+    // branching on each comparison.
+    while ctr + 2 <= len && pos + 3 <= buflen {
+        val0 = (buf[pos] as u16 | (buf[pos + 1] as u16) << 8) & 0xFFF;
+        val1 = ((buf[pos + 1] >> 4) as u16
+            | (buf[pos + 2] as u16) << 4)
+            & 0xFFF;
+        pos += 3;
+
+        r[ctr] = val0 as i16;
+        ctr += usize::from(val0 < KYBER_Q as u16);
+        r[ctr] = val1 as i16;
+        ctr += usize::from(val1 < KYBER_Q as u16);
+    }
+
     while ctr < len && pos + 3 <= buflen {
         val0 = (buf[pos] as u16 | (buf[pos + 1] as u16) << 8) & 0xFFF;
         val1 = ((buf[pos + 1] >> 4) as u16
@@ -128,11 +150,13 @@ fn rej_uniform(
     ctr
 }
 
+#[cfg(test)]
 fn gen_a(a: &mut [Polyvec], b: &[u8]) {
     gen_matrix(a, b, false);
 }
 
-fn gen_at(a: &mut [Polyvec], b: &[u8]) {
+#[cfg(any(test, feature = "expanded"))]
+pub(crate) fn gen_at(a: &mut [Polyvec], b: &[u8]) {
     gen_matrix(a, b, true);
 }
 
@@ -146,57 +170,95 @@ fn gen_at(a: &mut [Polyvec], b: &[u8]) {
 /// Arguments:   - Polyvec a: ouptput matrix A
 ///  - const [u8] seed: input seed
 ///  - bool transposed: boolean deciding whether A or A^T is generated
+#[cfg(any(test, feature = "expanded"))]
 fn gen_matrix(a: &mut [Polyvec], seed: &[u8], transposed: bool) {
-    let mut ctr;
-    // 530 is expected number of required bytes
-    const GEN_MATRIX_NBLOCKS: usize =
-        (12 * KYBER_N / 8 * (1 << 12) / KYBER_Q + XOF_BLOCKBYTES)
-            / XOF_BLOCKBYTES;
-    let mut buf = [0u8; GEN_MATRIX_NBLOCKS * XOF_BLOCKBYTES + 2];
-    let mut buflen: usize;
-    let mut off: usize;
-    let mut state = XofState::new();
-
+    let mut buf = [0u8; GEN_MATRIX_BUFBYTES];
     #[allow(clippy::needless_range_loop)]
     for i in 0..KYBER_SECURITY_PARAMETER {
         for j in 0..KYBER_SECURITY_PARAMETER {
-            if transposed {
-                xof_absorb(&mut state, seed, i as u8, j as u8);
-            } else {
-                xof_absorb(&mut state, seed, j as u8, i as u8);
-            }
-            xof_squeezeblocks(&mut buf, GEN_MATRIX_NBLOCKS, &mut state);
-            buflen = GEN_MATRIX_NBLOCKS * XOF_BLOCKBYTES;
-            ctr = rej_uniform(
-                &mut a[i].vec[j].coeffs,
-                KYBER_N,
-                &buf,
-                buflen,
-            );
-
-            while ctr < KYBER_N {
-                off = buflen % 3;
-                for k in 0..off {
-                    buf[k] = buf[buflen - off + k];
-                }
-                xof_squeezeblocks(&mut buf[off..], 1, &mut state);
-                buflen = off + XOF_BLOCKBYTES;
-                ctr += rej_uniform(
-                    &mut a[i].vec[j].coeffs[ctr..],
-                    KYBER_N - ctr,
-                    &buf,
-                    buflen,
-                );
-            }
+            let [x, y] = matrix_index_bytes(i, j, transposed);
+            gen_matrix_entry(&mut a[i].vec[j], &mut buf, seed, x, y);
         }
     }
 }
 
+// The fewest XOF blocks that split into whole 3-byte groups: 3 for the
+// 64-byte AES-CTR blocks, 1 for the 168-byte SHAKE128 rate.
+#[allow(clippy::manual_is_multiple_of)] // needs Rust 1.87, past the MSRV
+const GEN_MATRIX_NBLOCKS: usize =
+    if XOF_BLOCKBYTES % 3 == 0 { 1 } else { 3 };
+
+/// Size of the scratch buffer of gen_matrix_entry.
+pub(crate) const GEN_MATRIX_BUFBYTES: usize =
+    GEN_MATRIX_NBLOCKS * XOF_BLOCKBYTES;
+
+/// Name:  gen_matrix_entry
+///
+/// Description: Deterministically generate one entry of matrix A from a
+///  seed and the two XOF domain separation bytes, by rejection sampling
+///  on output of a XOF. The bytes for each entry of A and A^T are
+///  given by symmetric::matrix_index_bytes.
+///
+///  The XOF is squeezed a block at a time, only as many blocks as the
+///  entry needs, into buf, which holds a whole number of 3-byte groups.
+///  A group split by a block boundary is sampled in place once the next
+///  block has landed behind it, and buf is refilled from the front once
+///  every group in it is consumed, so no bytes are carried over.
+///
+///  buf is scratch whose contents do not matter: the XOF overwrites
+///  every byte rej_uniform reads before it is read. One buffer therefore
+///  serves every entry of a matrix, zeroed once by the caller instead of
+///  once per entry.
+///
+/// Arguments:   - Poly r: output polynomial
+///  - [u8] buf: scratch buffer
+///  - const [u8] seed: input seed
+///  - u8 x: first domain separation byte
+///  - u8 y: second domain separation byte
+pub(crate) fn gen_matrix_entry(
+    r: &mut Poly,
+    buf: &mut [u8; GEN_MATRIX_BUFBYTES],
+    seed: &[u8],
+    x: u8,
+    y: u8,
+) {
+    count_matrix_entry();
+    let mut state = xof_init(seed, x, y);
+    // Bytes squeezed into buf, and the start of the first group not
+    // yet sampled; pos is always a multiple of 3.
+    let (mut end, mut pos) = (0, 0);
+    let mut ctr = 0;
+
+    while ctr < KYBER_N {
+        if end == GEN_MATRIX_BUFBYTES {
+            end = 0;
+            pos = 0;
+        }
+        xof_squeezeblocks(&mut buf[end..], 1, &mut state);
+        end += XOF_BLOCKBYTES;
+        let groups = (end - pos) / 3 * 3;
+        ctr += rej_uniform(
+            &mut r.coeffs[ctr..],
+            KYBER_N - ctr,
+            &buf[pos..pos + groups],
+            groups,
+        );
+        pos += groups;
+    }
+    set_domain!(r, Ntt);
+}
+
 // Name:  indcpa_keypair
 //
 // Description: Generates public and private key for the CPA-secure
 //  public-key encryption scheme underlying Kyber
 //
+//  A is generated a row at a time: row i is sampled an entry at a time
+//  into one polynomial, whose product with s is accumulated before the
+//  next entry is sampled, and the finished row of t, plus its noise, is
+//  serialized straight into pk. Neither A nor e is held whole, so
+//  nothing is zeroed only to be overwritten; s is the one vector kept.
+//
 // Arguments: - [u8] pk: output public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
 //  - [u8] sk: output private key (length KYBER_INDCPA_SECRET_KEY_BYTES)
 pub(crate) fn indcpa_keypair<R>(
@@ -205,6 +267,188 @@ pub(crate) fn indcpa_keypair<R>(
     _seed: Option<(&[u8], &[u8])>,
     _rng: &mut R,
 ) -> Result<(), KyberLibError>
+where
+    R: CryptoRng + RngCore,
+{
+    let mut skpv = Polyvec::new();
+    let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
+    let mut randbuf = [0u8; 2 * KYBER_SYM_BYTES];
+
+    if let Some(s) = _seed {
+        randbuf[..KYBER_SYM_BYTES].copy_from_slice(s.0);
+    } else {
+        randombytes(&mut randbuf, KYBER_SYM_BYTES, _rng)?;
+    }
+
+    hash_g(&mut buf, &randbuf, KYBER_SYM_BYTES);
+
+    let (publicseed, noiseseed) = buf.split_at(KYBER_SYM_BYTES);
+    for (i, p) in skpv.vec.iter_mut().enumerate() {
+        poly_getnoise_eta1(p, noiseseed, i as u8);
+    }
+    polyvec_ntt(&mut skpv);
+
+    // matrix-vector multiplication, a row at a time
+    let (mut a, mut t) = (Poly::new(), Poly::new());
+    let mut xofbuf = [0u8; GEN_MATRIX_BUFBYTES];
+    for (i, r) in pk[..KYBER_POLYVEC_BYTES]
+        .chunks_exact_mut(KYBER_POLY_BYTES)
+        .enumerate()
+    {
+        let mut acc = [0i32; KYBER_N];
+        for (j, s) in skpv.vec.iter().enumerate() {
+            let [x, y] = matrix_index_bytes(i, j, false);
+            gen_matrix_entry(&mut a, &mut xofbuf, publicseed, x, y);
+            basemul_acc_term(&mut acc, &a, s);
+        }
+        basemul_acc_finish(&mut t, &acc);
+        poly_tomont(&mut t);
+
+        let nonce = KYBER_SECURITY_PARAMETER + i;
+        poly_getnoise_eta1(&mut a, noiseseed, nonce as u8);
+        poly_ntt(&mut a);
+        poly_add(&mut t, &a);
+        poly_reduce(&mut t);
+        poly_tobytes(r, t);
+    }
+    pk[KYBER_POLYVEC_BYTES..KYBER_INDCPA_PUBLIC_KEY_BYTES]
+        .copy_from_slice(publicseed);
+
+    pack_sk(sk, &mut skpv);
+    Ok(())
+}
+
+/// Name:  indcpa_enc
+///
+/// Description: Encryption function of the CPA-secure
+///  public-key encryption scheme underlying Kyber.
+///
+///  As in indcpa_keypair, A^T is generated a row at a time and each row
+///  of u compressed into c as soon as it is finished, and the
+///  polynomials of t are unpacked one at a time for v. Only r is held
+///  whole; A^T, t, e1 and u never are, which leaves r, two polynomials
+///  and the accumulators to zero where the whole vectors took 17.5 KiB
+///  for Kyber1024.
+///
+/// Arguments:
+///  - const [u8] c:    output ciphertext (length KYBER_INDCPA_BYTES)
+///  - const [u8] m:    input message (length KYBER_SYM_BYTES)
+///  - const [u8] pk:   input public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
+///  - const [u8] coin: input random coins used as seed (length KYBER_SYM_BYTES)
+///    to deterministically generate all randomness
+pub(crate) fn indcpa_enc(
+    c: &mut [u8],
+    m: &[u8],
+    pk: &[u8],
+    coins: &[u8],
+) {
+    let seed = &pk[KYBER_POLYVEC_BYTES..KYBER_INDCPA_PUBLIC_KEY_BYTES];
+    let mut xofbuf = [0u8; GEN_MATRIX_BUFBYTES];
+    indcpa_enc_with(c, m, pk, coins, |acc, a, i, j, s| {
+        let [x, y] = matrix_index_bytes(i, j, true);
+        gen_matrix_entry(a, &mut xofbuf, seed, x, y);
+        basemul_acc_term(acc, a, s);
+    });
+}
+
+/// Name:  indcpa_enc_expanded
+///
+/// Description: indcpa_enc taking A^T from at, as expanded by gen_at,
+///  instead of sampling it from the seed in pk. Output is identical.
+///
+/// Arguments:
+///  - const [u8] c:    output ciphertext (length KYBER_INDCPA_BYTES)
+///  - const [u8] m:    input message (length KYBER_SYM_BYTES)
+///  - const [u8] pk:   input public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
+///  - const Polyvec at: input matrix A^T (KYBER_SECURITY_PARAMETER rows)
+///  - const [u8] coin: input random coins used as seed (length KYBER_SYM_BYTES)
+///    to deterministically generate all randomness
+#[cfg(feature = "expanded")]
+pub(crate) fn indcpa_enc_expanded(
+    c: &mut [u8],
+    m: &[u8],
+    pk: &[u8],
+    at: &[Polyvec],
+    coins: &[u8],
+) {
+    indcpa_enc_with(c, m, pk, coins, |acc, _, i, j, s| {
+        basemul_acc_term(acc, &at[i].vec[j], s);
+    });
+}
+
+// The body of indcpa_enc, with entry (i, j) of A^T times s added to the
+// accumulator by `term`, which gets a scratch polynomial to sample the
+// entry into.
+fn indcpa_enc_with<F>(
+    c: &mut [u8],
+    m: &[u8],
+    pk: &[u8],
+    coins: &[u8],
+    mut term: F,
+) where
+    F: FnMut(&mut [i32; KYBER_N], &mut Poly, usize, usize, &Poly),
+{
+    let mut sp = Polyvec::new();
+    for (i, p) in sp.vec.iter_mut().enumerate() {
+        poly_getnoise_eta1(p, coins, i as u8);
+    }
+    polyvec_ntt(&mut sp);
+
+    // matrix-vector multiplication, a row at a time
+    let (mut a, mut t) = (Poly::new(), Poly::new());
+    for (i, r) in c[..KYBER_POLYVEC_COMPRESSED_BYTES]
+        .chunks_exact_mut(POLYVEC_POLY_COMPRESSED_BYTES)
+        .enumerate()
+    {
+        let mut acc = [0i32; KYBER_N];
+        for (j, s) in sp.vec.iter().enumerate() {
+            term(&mut acc, &mut a, i, j, s);
+        }
+        basemul_acc_finish(&mut t, &acc);
+        poly_invntt_tomont(&mut t);
+
+        let nonce = KYBER_SECURITY_PARAMETER + i;
+        poly_getnoise_eta2(&mut a, coins, nonce as u8);
+        poly_add(&mut t, &a);
+        poly_reduce(&mut t);
+        polyvec_compress_poly(r, t);
+    }
+
+    let mut acc = [0i32; KYBER_N];
+    for (p, s) in pk[..KYBER_POLYVEC_BYTES]
+        .chunks_exact(KYBER_POLY_BYTES)
+        .zip(sp.vec.iter())
+    {
+        poly_frombytes(&mut a, p);
+        basemul_acc_term(&mut acc, &a, s);
+    }
+    basemul_acc_finish(&mut t, &acc);
+    poly_invntt_tomont(&mut t);
+
+    poly_getnoise_eta2(
+        &mut a,
+        coins,
+        2 * KYBER_SECURITY_PARAMETER as u8,
+    );
+    poly_add(&mut t, &a);
+    poly_frommsg(&mut a, m);
+    poly_add(&mut t, &a);
+    poly_reduce(&mut t);
+    poly_compress(&mut c[KYBER_POLYVEC_COMPRESSED_BYTES..], t);
+}
+
+/// Name:  indcpa_keypair_buffered
+///
+/// Description: indcpa_keypair generating the whole of A, s and e before
+///  the matrix-vector product, as the other backends do. Output is
+///  identical; it is kept to validate the streaming key generation.
+#[cfg(test)]
+pub(crate) fn indcpa_keypair_buffered<R>(
+    pk: &mut [u8],
+    sk: &mut [u8],
+    _seed: Option<(&[u8], &[u8])>,
+    _rng: &mut R,
+) -> Result<(), KyberLibError>
 where
     R: CryptoRng + RngCore,
 {
@@ -252,18 +496,13 @@ where
     Ok(())
 }
 
-/// Name:  indcpa_enc
-///
-/// Description: Encryption function of the CPA-secure
-///  public-key encryption scheme underlying Kyber.
+/// Name:  indcpa_enc_buffered
 ///
-/// Arguments:
-///  - const [u8] c:    output ciphertext (length KYBER_INDCPA_BYTES)
-///  - const [u8] m:    input message (length KYBER_SYM_BYTES)
-///  - const [u8] pk:   input public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
-///  - const [u8] coin: input random coins used as seed (length KYBER_SYM_BYTES)
-///    to deterministically generate all randomness
-pub(crate) fn indcpa_enc(
+/// Description: indcpa_enc generating the whole of A^T, t, r and e1
+///  before the products, as the other backends do. Output is identical;
+///  it is kept to validate the streaming encryption.
+#[cfg(test)]
+pub(crate) fn indcpa_enc_buffered(
     c: &mut [u8],
     m: &[u8],
     pk: &[u8],
@@ -321,11 +560,57 @@ pub(crate) fn indcpa_enc(
 /// Description: Decryption function of the CPA-secure
 ///  public-key encryption scheme underlying Kyber.
 ///
+///  The inner product of b and s is taken one term at a time: each
+///  polynomial of b is decompressed and transformed, and each of s
+///  unpacked, just before its product is added to the accumulator, so
+///  that neither vector is held whole. The live state is two
+///  polynomials, the i32 accumulator and the result, 2.5 KiB, where
+///  indcpa_dec_buffered holds 2k + 2 polynomials and the accumulator:
+///  4 KiB for Kyber512, 5 KiB for Kyber768 and 6 KiB for Kyber1024.
+///  The arithmetic is the same, so is the output.
+///
 /// Arguments:
 ///  - const [u8] m:    output decrypted message (of length KYBER_SYM_BYTES)
 ///  - const [u8] c:    input ciphertext (of length KYBER_INDCPA_BYTES)
 ///  - const [u8] sk:   input secret key (of length KYBER_INDCPA_SECRET_KEY_BYTES)
 pub(crate) fn indcpa_dec(m: &mut [u8], c: &[u8], sk: &[u8]) {
+    let (mut bi, mut si) = (Poly::new(), Poly::new());
+    let mut acc = [0i32; KYBER_N];
+
+    for (cb, sb) in c[..KYBER_POLYVEC_COMPRESSED_BYTES]
+        .chunks_exact(POLYVEC_POLY_COMPRESSED_BYTES)
+        .zip(sk[..KYBER_POLYVEC_BYTES].chunks_exact(KYBER_POLY_BYTES))
+    {
+        polyvec_decompress_poly(&mut bi, cb);
+        poly_ntt(&mut bi);
+        poly_frombytes(&mut si, sb);
+        basemul_acc_term(&mut acc, &si, &bi);
+    }
+    let mut mp = Poly::new();
+    basemul_acc_finish(&mut mp, &acc);
+    poly_invntt_tomont(&mut mp);
+
+    // v reuses the storage of b
+    poly_decompress(&mut bi, &c[KYBER_POLYVEC_COMPRESSED_BYTES..]);
+    poly_sub(&mut mp, &bi);
+    poly_reduce(&mut mp);
+    poly_csubq(&mut mp);
+
+    poly_tomsg(m, mp);
+}
+
+/// Name:  indcpa_dec_buffered
+///
+/// Description: indcpa_dec unpacking the whole of b and s before the
+///  inner product, as the other backends do. Output is identical; it is
+///  kept to validate the streaming decryption.
+///
+/// Arguments:
+///  - const [u8] m:    output decrypted message (of length KYBER_SYM_BYTES)
+///  - const [u8] c:    input ciphertext (of length KYBER_INDCPA_BYTES)
+///  - const [u8] sk:   input secret key (of length KYBER_INDCPA_SECRET_KEY_BYTES)
+#[cfg(test)]
+pub(crate) fn indcpa_dec_buffered(m: &mut [u8], c: &[u8], sk: &[u8]) {
     let (mut b, mut skpv) = (Polyvec::new(), Polyvec::new());
     let (mut v, mut mp) = (Poly::new(), Poly::new());
 
@@ -338,6 +623,193 @@ pub(crate) fn indcpa_dec(m: &mut [u8], c: &[u8], sk: &[u8]) {
 
     poly_sub(&mut mp, &v);
     poly_reduce(&mut mp);
+    poly_csubq(&mut mp);
 
     poly_tomsg(m, mp);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The rejection sampling of the specification, a group at a time
+    // with a branch per candidate.
+    fn sample_spec(r: &mut [i16], len: usize, buf: &[u8]) -> usize {
+        let mut ctr = 0;
+        for g in buf.chunks_exact(3) {
+            let d1 = u16::from(g[0]) | (u16::from(g[1]) & 0xf) << 8;
+            let d2 = u16::from(g[1]) >> 4 | u16::from(g[2]) << 4;
+            for d in [d1, d2] {
+                if ctr < len && d < KYBER_Q as u16 {
+                    r[ctr] = d as i16;
+                    ctr += 1;
+                }
+            }
+            if ctr == len {
+                break;
+            }
+        }
+        ctr
+    }
+
+    #[test]
+    fn rej_uniform_matches_specification() {
+        use rand::{rngs::OsRng, Rng, RngCore};
+        for round in 0..256 {
+            let mut buf = [0u8; 3 * 64];
+            OsRng.fill_bytes(&mut buf);
+            // Force rejections on half the rounds.
+            if round % 2 == 1 {
+                for byte in buf.iter_mut().step_by(4) {
+                    *byte = 0xff;
+                }
+            }
+            let len = OsRng.gen_range(1..=KYBER_N);
+            let buflen = OsRng.gen_range(0..=buf.len());
+            let mut r = [0i16; KYBER_N];
+            let mut expected = [0i16; KYBER_N];
+            let ctr = rej_uniform(&mut r, len, &buf, buflen);
+            let n = sample_spec(&mut expected, len, &buf[..buflen]);
+            assert_eq!(ctr, n);
+            assert_eq!(r[..ctr], expected[..n]);
+        }
+    }
+
+    // Sampling a block at a time takes the same groups of the XOF
+    // stream as sampling from one long squeeze.
+    #[test]
+    fn matrix_entry_matches_one_long_squeeze() {
+        let seed = [0x3cu8; KYBER_SYM_BYTES];
+        let mut buf = [0u8; GEN_MATRIX_BUFBYTES];
+        for x in 0..KYBER_SECURITY_PARAMETER as u8 {
+            for y in 0..KYBER_SECURITY_PARAMETER as u8 {
+                let mut r = Poly::new();
+                gen_matrix_entry(&mut r, &mut buf, &seed, x, y);
+
+                let mut stream = [0u8; 24 * XOF_BLOCKBYTES];
+                let mut state = xof_init(&seed, x, y);
+                xof_squeezeblocks(&mut stream, 24, &mut state);
+                let mut expected = [0i16; KYBER_N];
+                let n = sample_spec(&mut expected, KYBER_N, &stream);
+                assert_eq!(n, KYBER_N);
+                assert_eq!(r.coeffs, expected);
+            }
+        }
+    }
+
+    // Entry (i, j) of A^T must be sampled exactly like entry (j, i)
+    // of A.
+    #[test]
+    fn transposed_matrix_is_transpose_of_a() {
+        let seed = [0x5au8; KYBER_SYM_BYTES];
+        let mut a = [Polyvec::new(); KYBER_SECURITY_PARAMETER];
+        let mut at = [Polyvec::new(); KYBER_SECURITY_PARAMETER];
+        gen_a(&mut a, &seed);
+        gen_at(&mut at, &seed);
+        for (i, row) in at.iter().enumerate() {
+            for (j, entry) in row.vec.iter().enumerate() {
+                assert_eq!(entry.coeffs, a[j].vec[i].coeffs);
+            }
+        }
+    }
+
+    // The packed secret key holds NTT(s), so indcpa_dec can use it
+    // without a forward transform.
+    #[test]
+    fn secret_key_is_stored_in_ntt_domain() {
+        let (d, z) = ([7u8; KYBER_SYM_BYTES], [9u8; KYBER_SYM_BYTES]);
+        let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+        let mut sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+        let mut rng = rand::rngs::OsRng;
+        indcpa_keypair(&mut pk, &mut sk, Some((&d, &z)), &mut rng)
+            .unwrap();
+
+        let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
+        hash_g(&mut buf, &d, KYBER_SYM_BYTES);
+        let mut s = Polyvec::new();
+        for (i, p) in s.vec.iter_mut().enumerate() {
+            poly_getnoise_eta1(p, &buf[KYBER_SYM_BYTES..], i as u8);
+        }
+        polyvec_ntt(&mut s);
+        polyvec_reduce(&mut s);
+        polyvec_csubq(&mut s);
+
+        let mut stored = Polyvec::new();
+        unpack_sk(&mut stored, &sk);
+        polyvec_csubq(&mut stored);
+        for (a, b) in stored.vec.iter().zip(s.vec.iter()) {
+            assert_eq!(a.coeffs, b.coeffs);
+        }
+    }
+
+    #[test]
+    fn streaming_key_generation_matches_buffered() {
+        use rand::{rngs::OsRng, RngCore};
+        let (mut d, mut z) =
+            ([0u8; KYBER_SYM_BYTES], [0u8; KYBER_SYM_BYTES]);
+        let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+        let mut sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+        let mut expected_pk = pk;
+        let mut expected_sk = sk;
+        for _ in 0..16 {
+            OsRng.fill_bytes(&mut d);
+            OsRng.fill_bytes(&mut z);
+            let seed = Some((&d[..], &z[..]));
+            indcpa_keypair(&mut pk, &mut sk, seed, &mut OsRng).unwrap();
+            indcpa_keypair_buffered(
+                &mut expected_pk,
+                &mut expected_sk,
+                seed,
+                &mut OsRng,
+            )
+            .unwrap();
+            assert_eq!(pk, expected_pk);
+            assert_eq!(sk, expected_sk);
+        }
+    }
+
+    #[test]
+    fn streaming_encryption_matches_buffered() {
+        use rand::{rngs::OsRng, RngCore};
+        let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+        let mut sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+        indcpa_keypair(&mut pk, &mut sk, None, &mut OsRng).unwrap();
+        let (mut m, mut coins) = ([0u8; KYBER_SYM_BYTES], [0u8; 32]);
+        let mut c = [0u8; KYBER_INDCPA_BYTES];
+        let mut expected = c;
+        for _ in 0..16 {
+            OsRng.fill_bytes(&mut m);
+            OsRng.fill_bytes(&mut coins);
+            indcpa_enc(&mut c, &m, &pk, &coins);
+            indcpa_enc_buffered(&mut expected, &m, &pk, &coins);
+            assert_eq!(c, expected);
+        }
+    }
+
+    #[test]
+    fn streaming_decryption_matches_buffered() {
+        use rand::{rngs::OsRng, RngCore};
+        let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+        let mut sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+        indcpa_keypair(&mut pk, &mut sk, None, &mut OsRng).unwrap();
+        let (mut m, mut coins) = ([0u8; KYBER_SYM_BYTES], [0u8; 32]);
+        let mut c = [0u8; KYBER_INDCPA_BYTES];
+        let (mut out, mut expected) = ([0u8; 32], [0u8; 32]);
+        for i in 0..64 {
+            if i % 2 == 0 {
+                OsRng.fill_bytes(&mut m);
+                OsRng.fill_bytes(&mut coins);
+                indcpa_enc(&mut c, &m, &pk, &coins);
+            } else {
+                // arbitrary bytes, as an implicit rejection would see
+                OsRng.fill_bytes(&mut c);
+            }
+            indcpa_dec(&mut out, &c, &sk);
+            indcpa_dec_buffered(&mut expected, &c, &sk);
+            assert_eq!(out, expected);
+            if i % 2 == 0 {
+                assert_eq!(out, m);
+            }
+        }
+    }
+}
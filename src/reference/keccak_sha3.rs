@@ -0,0 +1,152 @@
+//! Keccak-f[1600] on the Armv8.2 SHA3 extension.
+//!
+//! EOR3, RAX1, XAR and BCAX each do the work of two or three scalar
+//! instructions of the permutation: the five-way column parity takes
+//! two EOR3, the theta effect one RAX1, theta, rho and pi on a lane one
+//! XAR and chi on a lane one BCAX. The 25 lanes live in the low halves
+//! of vector registers, so a round is 64 of these plus a spill or two.
+//!
+//! The extension is optional in Armv8.2, so it is detected at run time
+//! (`HWCAP_SHA3` on Linux, `hw.optional.armv8_2_sha3` on macOS), and
+//! [`super::fips202::keccakf1600_statepermute`] falls back to the
+//! software permutation without it. Both produce the same state bit for
+//! bit. The choice is independent of the IND-CPA backend
+//! `acceleration()` reports.
+
+use super::fips202::{KECCAKF_ROUNDCONSTANTS, NROUNDS};
+use core::arch::aarch64::*;
+
+// `u8::max_value` in the expansion of `cpufeatures::new!`.
+#[allow(deprecated_in_future)]
+mod detect {
+    cpufeatures::new!(sha3, "sha3");
+
+    pub(super) fn supported() -> bool {
+        sha3::get()
+    }
+}
+
+/// Whether the CPU has the SHA3 extension.
+pub(crate) fn supported() -> bool {
+    detect::supported()
+}
+
+// Repeats `$body` with the constant `$i` set to each of the indices,
+// so that the lanes stay in registers at the crate's `opt-level = "s"`,
+// which does not unroll loops.
+macro_rules! unroll {
+    ($i:ident in [$($n:expr),*] $body:block) => {
+        $({
+            const $i: usize = $n;
+            $body
+        })*
+    };
+}
+
+// theta, rho and pi on lane `SRC` (x + 5y) into lane `DST`
+// (y + 5((2x + 3y) mod 5)): a rotation left by `ROT` is one to the
+// right by 64 - ROT.
+macro_rules! rho_pi {
+    ($b:ident, $a:ident, $d:ident;
+     $(($src:expr, $dst:expr, $rot:expr)),*) => {
+        $(
+            $b[$dst] =
+                vxarq_u64::<{ 64 - $rot }>($a[$src], $d[$src % 5]);
+        )*
+    };
+}
+
+/// Name:  KeccakF1600_StatePermute
+///
+/// Description: The Keccak F1600 Permutation on the SHA3 extension
+///
+/// Arguments:   - u64 * state: in/output Keccak state
+///
+/// # Safety
+///
+/// The CPU must support the SHA3 extension, see [`supported`].
+#[target_feature(enable = "sha3")]
+pub(crate) unsafe fn keccakf1600_statepermute(state: &mut [u64]) {
+    let state = &mut state[..25];
+    let mut a = [vdupq_n_u64(0); 25];
+    unroll!(I in [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+        19, 20, 21, 22, 23, 24
+    ] {
+        a[I] = vdupq_n_u64(state[I]);
+    });
+    let mut b = a;
+    let mut c = [vdupq_n_u64(0); 5];
+    let mut d = c;
+
+    for rc in KECCAKF_ROUNDCONSTANTS.iter().take(NROUNDS) {
+        // theta: column parities and the effect on each column
+        unroll!(X in [0, 1, 2, 3, 4] {
+            c[X] = veor3q_u64(
+                veor3q_u64(a[X], a[X + 5], a[X + 10]),
+                a[X + 15],
+                a[X + 20],
+            );
+        });
+        unroll!(X in [0, 1, 2, 3, 4] {
+            d[X] = vrax1q_u64(c[(X + 4) % 5], c[(X + 1) % 5]);
+        });
+
+        // theta, rho and pi
+        b[0] = veorq_u64(a[0], d[0]);
+        rho_pi! {
+            b, a, d;
+            (1, 10, 1), (2, 20, 62), (3, 5, 28), (4, 15, 27),
+            (5, 16, 36), (6, 1, 44), (7, 11, 6), (8, 21, 55),
+            (9, 6, 20), (10, 7, 3), (11, 17, 10), (12, 2, 43),
+            (13, 12, 25), (14, 22, 39), (15, 23, 41), (16, 8, 45),
+            (17, 18, 15), (18, 3, 21), (19, 13, 8), (20, 14, 18),
+            (21, 24, 2), (22, 9, 61), (23, 19, 56), (24, 4, 14)
+        }
+
+        // chi: a[x] = b[x] ^ (!b[x + 1] & b[x + 2]) along each row
+        unroll!(Y in [0, 5, 10, 15, 20] {
+            unroll!(X in [0, 1, 2, 3, 4] {
+                a[Y + X] = vbcaxq_u64(
+                    b[Y + X],
+                    b[Y + (X + 2) % 5],
+                    b[Y + (X + 1) % 5],
+                );
+            });
+        });
+
+        // iota
+        a[0] = veorq_u64(a[0], vdupq_n_u64(*rc));
+    }
+
+    unroll!(I in [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+        19, 20, 21, 22, 23, 24
+    ] {
+        state[I] = vgetq_lane_u64::<0>(a[I]);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference::fips202::keccakf1600_statepermute_generic;
+    use rand::{rngs::OsRng, RngCore};
+
+    #[test]
+    fn permutation_matches_software() {
+        if !supported() {
+            return;
+        }
+        for _ in 0..256 {
+            let mut state = [0u64; 25];
+            for s in state.iter_mut() {
+                *s = OsRng.next_u64();
+            }
+            let mut expected = state;
+            keccakf1600_statepermute_generic(&mut expected);
+            unsafe { keccakf1600_statepermute(&mut state) };
+            assert_eq!(state, expected);
+        }
+    }
+}
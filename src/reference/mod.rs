@@ -1,7 +1,14 @@
 pub(crate) mod aes256ctr;
 pub(crate) mod cbd;
 pub(crate) mod fips202;
+/// IND-CPA constants of the reference implementation, exposed by the
+/// `hazmat` feature.
+#[cfg(feature = "hazmat")]
+pub mod indcpa;
+#[cfg(not(feature = "hazmat"))]
 pub(crate) mod indcpa;
+#[cfg(all(target_arch = "aarch64", feature = "armv8-sha3"))]
+mod keccak_sha3;
 pub(crate) mod ntt;
 pub(crate) mod poly;
 pub(crate) mod polyvec;
@@ -137,6 +137,7 @@ pub(crate) fn invntt(r: &mut [i16]) {
 ///  - const i16 a[2]: the first factor
 ///  - const i16 b[2]: the second factor
 ///  - i16 zeta: integer defining the reduction polynomial
+#[cfg(any(test, feature = "research"))]
 pub(crate) fn basemul(r: &mut [i16], a: &[i16], b: &[i16], zeta: i16) {
     r[0] = fqmul(a[1], b[1]);
     r[0] = fqmul(r[0], zeta);
@@ -145,3 +146,58 @@ pub(crate) fn basemul(r: &mut [i16], a: &[i16], b: &[i16], zeta: i16) {
     r[1] = fqmul(a[0], b[1]);
     r[1] += fqmul(a[1], b[0]);
 }
+
+/// Name:  ntt_recursive
+///
+/// Description: Recursive Cooley-Tukey formulation of [`ntt`], for
+///  validating its zeta indexing. The butterfly on a block of
+///  `2 * len` coefficients uses `ZETAS[k]`, then the two halves recurse
+///  with `2 * k` and `2 * k + 1`, which is the order the iterative
+///  counter visits them level by level. Output is identical to `ntt`,
+///  which stays in use: `benches/ntt.rs` measures it as the faster of
+///  the two.
+///
+/// Arguments:   - i16 r[256]: input/output vector of elements of Zq
+#[cfg(any(test, feature = "research"))]
+pub(crate) fn ntt_recursive(r: &mut [i16]) {
+    fn butterflies(r: &mut [i16], k: usize) {
+        let len = r.len() / 2;
+        if len < 2 {
+            return;
+        }
+        let zeta = ZETAS[k];
+        let (lo, hi) = r.split_at_mut(len);
+        for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+            let t = fqmul(zeta, *b);
+            *b = *a - t;
+            *a += t;
+        }
+        butterflies(lo, 2 * k);
+        butterflies(hi, 2 * k + 1);
+    }
+    butterflies(&mut r[..256], 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recursive_ntt_matches_iterative() {
+        // xorshift32, so the inputs cover the whole -q+1..q range
+        let mut state = 0x1234_5678u32;
+        for _ in 0..64 {
+            let mut iterative = [0i16; 256];
+            for c in iterative.iter_mut() {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                *c = (state % 6657) as i16 - 3328;
+            }
+            let mut recursive = iterative;
+            ntt(&mut iterative);
+            ntt_recursive(&mut recursive);
+            assert_eq!(iterative, recursive);
+        }
+    }
+}
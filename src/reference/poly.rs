@@ -1,8 +1,21 @@
+use crate::acceleration::{count_ntt, Acceleration};
+#[cfg(any(feature = "research", feature = "levels"))]
+use crate::error::KyberLibError;
 use crate::{cbd::*, ntt::*, params::*, reduce::*, symmetric::*};
+use core::hint::black_box;
 
+// The coefficients start on a 64-byte boundary, so that the NEON,
+// WebAssembly, RVV and portable SIMD kernels that share this type never
+// split a vector load across cache lines. 256 coefficients of 2 bytes
+// fill eight cache lines exactly, so the alignment adds no padding and a
+// Polyvec keeps every polynomial aligned. The domain tag of the
+// `debug-domain` feature adds a cache line to debug builds.
 #[derive(Clone)]
+#[repr(C, align(64))]
 pub(crate) struct Poly {
     pub(crate) coeffs: [i16; KYBER_N],
+    #[cfg(all(feature = "debug-domain", debug_assertions))]
+    pub(crate) domain: Domain,
 }
 
 impl Copy for Poly {}
@@ -11,10 +24,63 @@ impl Default for Poly {
     fn default() -> Self {
         Poly {
             coeffs: [0i16; KYBER_N],
+            #[cfg(all(feature = "debug-domain", debug_assertions))]
+            domain: Domain::Normal,
         }
     }
 }
 
+// The domain the coefficients of a Poly are in, tracked with the
+// `debug-domain` feature in debug builds. The reference kernels set it
+// on output and check it on input, so a missing or repeated transform
+// panics at the call that gets the wrong domain. The SIMD backends
+// sharing Poly neither set nor check it, except for the portable SIMD
+// transforms, whose output goes to the reference base multiplication.
+#[cfg(all(feature = "debug-domain", debug_assertions))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Domain {
+    Normal,
+    Ntt,
+    // Coefficients set from outside, e.g. through the research API,
+    // which pass either check.
+    Any,
+}
+
+// With `debug-domain` in debug builds, panics unless `$p` is in domain
+// `$d`, naming the kernel in the message; otherwise expands to nothing.
+macro_rules! expect_domain {
+    ($p:expr, $d:ident, $msg:literal) => {{
+        #[cfg(all(feature = "debug-domain", debug_assertions))]
+        {
+            use $crate::poly::Domain;
+            let domain = $p.domain;
+            assert!(
+                domain == Domain::$d || domain == Domain::Any,
+                concat!(
+                    $msg,
+                    " expects ",
+                    stringify!($d),
+                    ", got {:?}"
+                ),
+                domain
+            );
+        }
+    }};
+}
+
+// With `debug-domain` in debug builds, records that `$p` is now in
+// domain `$d`; otherwise expands to nothing.
+macro_rules! set_domain {
+    ($p:expr, $d:ident) => {{
+        #[cfg(all(feature = "debug-domain", debug_assertions))]
+        {
+            $p.domain = $crate::poly::Domain::$d;
+        }
+    }};
+}
+
+pub(crate) use {expect_domain, set_domain};
+
 // new() is nicer
 impl Poly {
     pub(crate) fn new() -> Self {
@@ -22,16 +88,89 @@ impl Poly {
     }
 }
 
+// Bits per coefficient of a compressed polynomial.
+const POLY_COMPRESSED_BITS: usize = KYBER_DV;
+
+// Panics unless `$len >= $min`, naming the buffer in the message. Outside
+// the `small` feature the message also gives both lengths, which drags
+// the integer formatting code into the binary.
+macro_rules! check_len {
+    ($len:expr, $min:expr, $msg:literal) => {{
+        #[cfg(not(feature = "small"))]
+        assert!($len >= $min, concat!($msg, ": {} < {}"), $len, $min);
+        #[cfg(feature = "small")]
+        assert!($len >= $min, $msg);
+    }};
+}
+
+/// Name:  pack_bits
+///
+/// Description: Serializes values little-endian into d bits each, as the
+///  unrolled loops of poly_compress and polyvec_compress do, in a single
+///  compact loop. Used in place of them under the `small` feature and for
+///  compression widths without an unrolled loop
+///
+/// Arguments:   - [u8] r: output byte array (needs space for d bits per value)
+///  - usize d: bits per value, at most 16
+///  - values: values to pack, of which only the low d bits are kept
+pub(crate) fn pack_bits(
+    r: &mut [u8],
+    d: usize,
+    values: impl Iterator<Item = u32>,
+) {
+    let mask = (1u32 << d) - 1;
+    let (mut acc, mut bits, mut pos) = (0u32, 0usize, 0usize);
+    for v in values {
+        acc |= (v & mask) << bits;
+        bits += d;
+        while bits >= 8 {
+            r[pos] = acc as u8;
+            acc >>= 8;
+            bits -= 8;
+            pos += 1;
+        }
+    }
+}
+
+/// Name:  unpack_bits
+///
+/// Description: Inverse of pack_bits, yielding the d-bit values packed
+///  in a until it runs out of bytes
+///
+/// Arguments:   - [u8] a: input byte array
+///  - usize d: bits per value, at most 16
+pub(crate) fn unpack_bits(
+    a: &[u8],
+    d: usize,
+) -> impl Iterator<Item = u32> + '_ {
+    let mask = (1u32 << d) - 1;
+    let (mut acc, mut bits, mut bytes) = (0u32, 0usize, a.iter());
+    core::iter::from_fn(move || {
+        while bits < d {
+            acc |= (*bytes.next()? as u32) << bits;
+            bits += 8;
+        }
+        let v = acc & mask;
+        acc >>= d;
+        bits -= d;
+        Some(v)
+    })
+}
+
 /// Name:  poly_compress
 ///
-/// Description: Compression and subsequent serialization of a polynomial
+/// Description: Compression and subsequent serialization of a polynomial.
+///  Panics if r is shorter than KYBER_POLY_COMPRESSED_BYTES, before
+///  anything is written
 ///
 /// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLY_COMPRESSED_BYTES bytes)
 ///  - const poly *a:  input polynomial
 pub(crate) fn poly_compress(r: &mut [u8], a: Poly) {
-    let mut t = [0u8; 8];
-    let mut k = 0usize;
-    let mut u: i16;
+    check_len!(
+        r.len(),
+        KYBER_POLY_COMPRESSED_BYTES,
+        "poly_compress output buffer too short"
+    );
 
     // Compress_q(x, d) = ⌈(2ᵈ/q)x⌋ mod⁺ 2ᵈ
     //                  = ⌊(2ᵈ/q)x+½⌋ mod⁺ 2ᵈ
@@ -41,12 +180,17 @@ pub(crate) fn poly_compress(r: &mut [u8], a: Poly) {
     // We approximate DIV(x, q) by computing (x*a)>>e, where a/(2^e) ≈ 1/q.
     // For d in {10,11} we use 20,642,678/2^36, which computes division by x/q
     // correctly for 0 ≤ x < 41,522,616, which fits (q << 11) + q/2 comfortably.
-    // For d in {4,5} we use 315/2^20, which doesn't compute division by x/q
+    // For d in {3,4,5} we use 315/2^20, which doesn't compute division by x/q
     // correctly for all inputs, but it's close enough that the end result
     // of the compression is correct. The advantage is that we do not need
     // to use a 64-bit intermediate value.
-    match KYBER_POLY_COMPRESSED_BYTES {
-        128 => {
+    #[cfg(not(feature = "small"))]
+    if !KYBER_COMPRESSION_OVERRIDDEN {
+        #[cfg(not(feature = "kyber1024"))]
+        {
+            let mut t = [0u8; 8];
+            let mut k = 0usize;
+            let mut u: i16;
             #[allow(clippy::needless_range_loop)]
             for i in 0..KYBER_N / 8 {
                 for j in 0..8 {
@@ -66,7 +210,11 @@ pub(crate) fn poly_compress(r: &mut [u8], a: Poly) {
                 k += 4;
             }
         }
-        160 => {
+        #[cfg(feature = "kyber1024")]
+        {
+            let mut t = [0u8; 8];
+            let mut k = 0usize;
+            let mut u: i16;
             #[allow(clippy::needless_range_loop)]
             for i in 0..(KYBER_N / 8) {
                 for j in 0..8 {
@@ -87,22 +235,41 @@ pub(crate) fn poly_compress(r: &mut [u8], a: Poly) {
                 k += 5;
             }
         }
-        _ => panic!(
-            "KYBER_POLY_COMPRESSED_BYTES needs to be one of (128, 160)"
-        ),
+        return;
     }
+    pack_bits(
+        r,
+        POLY_COMPRESSED_BITS,
+        a.coeffs.iter().map(|&c| {
+            // map to positive standard representatives
+            let u = c + ((c >> 15) & KYBER_Q as i16);
+            ((((u as u32) << POLY_COMPRESSED_BITS)
+                + KYBER_Q as u32 / 2)
+                * 315)
+                >> 20
+        }),
+    );
 }
 
 /// Name:  poly_decompress
 ///
 /// Description: De-serialization and subsequent decompression of a polynomial;
-///  approximate inverse of poly_compress
+///  approximate inverse of poly_compress. Panics if a is shorter than
+///  KYBER_POLY_COMPRESSED_BYTES
 ///
 /// Arguments:   - poly *r:  output polynomial
 ///  - const [u8] a: input byte array (of length KYBER_POLY_COMPRESSED_BYTES bytes)
 pub(crate) fn poly_decompress(r: &mut Poly, a: &[u8]) {
-    match KYBER_POLY_COMPRESSED_BYTES {
-        128 => {
+    check_len!(
+        a.len(),
+        KYBER_POLY_COMPRESSED_BYTES,
+        "poly_decompress input buffer too short"
+    );
+    set_domain!(r, Normal);
+    #[cfg(not(feature = "small"))]
+    if !KYBER_COMPRESSION_OVERRIDDEN {
+        #[cfg(not(feature = "kyber1024"))]
+        {
             for (idx, i) in (0..KYBER_N / 2).enumerate() {
                 r.coeffs[2 * i] = ((((a[idx] & 15) as usize * KYBER_Q)
                     + 8)
@@ -112,7 +279,8 @@ pub(crate) fn poly_decompress(r: &mut Poly, a: &[u8]) {
                         as i16;
             }
         }
-        160 => {
+        #[cfg(feature = "kyber1024")]
+        {
             let mut idx = 0usize;
             let mut t = [0u8; 8];
             #[allow(clippy::needless_range_loop)]
@@ -133,19 +301,30 @@ pub(crate) fn poly_decompress(r: &mut Poly, a: &[u8]) {
                 }
             }
         }
-        _ => panic!(
-            "KYBER_POLY_COMPRESSED_BYTES needs to be either (128, 160)"
-        ),
+        return;
+    }
+    for (c, t) in r.coeffs.iter_mut().zip(unpack_bits(
+        &a[..KYBER_POLY_COMPRESSED_BYTES],
+        POLY_COMPRESSED_BITS,
+    )) {
+        *c = ((t * KYBER_Q as u32 + (1 << (POLY_COMPRESSED_BITS - 1)))
+            >> POLY_COMPRESSED_BITS) as i16;
     }
 }
 
 /// Name:  poly_tobytes
 ///
-/// Description: Serialization of a polynomial
+/// Description: Serialization of a polynomial. Panics if r is shorter
+///  than KYBER_POLY_BYTES, before anything is written
 ///
 /// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLY_BYTES bytes)
 ///  - const poly *a:  input polynomial
 pub(crate) fn poly_tobytes(r: &mut [u8], a: Poly) {
+    check_len!(
+        r.len(),
+        KYBER_POLY_BYTES,
+        "poly_tobytes output buffer too short"
+    );
     let (mut t0, mut t1);
     #[allow(clippy::needless_range_loop)]
     for i in 0..(KYBER_N / 2) {
@@ -163,11 +342,16 @@ pub(crate) fn poly_tobytes(r: &mut [u8], a: Poly) {
 /// Name:  poly_frombytes
 ///
 /// Description: De-serialization of a polynomial;
-///  inverse of poly_tobytes
+///  inverse of poly_tobytes. Panics if a is shorter than KYBER_POLY_BYTES
 ///
 /// Arguments:   - poly *r:  output polynomial
 ///  - const [u8] a: input byte array (of KYBER_POLY_BYTES bytes)
 pub(crate) fn poly_frombytes(r: &mut Poly, a: &[u8]) {
+    check_len!(
+        a.len(),
+        KYBER_POLY_BYTES,
+        "poly_frombytes input buffer too short"
+    );
     for i in 0..(KYBER_N / 2) {
         r.coeffs[2 * i] = ((a[3 * i]) as u16
             | ((a[3 * i + 1] as u16) << 8) & 0xFFF)
@@ -176,6 +360,30 @@ pub(crate) fn poly_frombytes(r: &mut Poly, a: &[u8]) {
             | ((a[3 * i + 2] as u16) << 4) & 0xFFF)
             as i16;
     }
+    set_domain!(r, Ntt);
+}
+
+/// Name:  poly_frombytes_reader
+///
+/// Description: De-serialization of a polynomial read from reader, for
+///  keys that arrive split across buffers; reads exactly KYBER_POLY_BYTES
+///  bytes and unpacks them like poly_frombytes
+///
+/// Arguments:   - poly *r:  output polynomial
+///  - R reader: input byte stream
+///
+/// Returns io::ErrorKind::UnexpectedEof if reader ends early, in which
+///  case r is left unchanged
+#[cfg(feature = "std")]
+#[allow(dead_code)]
+pub(crate) fn poly_frombytes_reader<R: std::io::Read>(
+    r: &mut Poly,
+    mut reader: R,
+) -> std::io::Result<()> {
+    let mut buf = [0u8; KYBER_POLY_BYTES];
+    reader.read_exact(&mut buf)?;
+    poly_frombytes(r, &buf);
+    Ok(())
 }
 
 /// Name:  poly_getnoise_eta1
@@ -189,8 +397,7 @@ pub(crate) fn poly_frombytes(r: &mut Poly, a: &[u8]) {
 ///  - [u8]  nonce:   one-byte input nonce
 pub(crate) fn poly_getnoise_eta1(r: &mut Poly, seed: &[u8], nonce: u8) {
     const LENGTH: usize = KYBER_ETA1 * KYBER_N / 4;
-    let mut buf = [0u8; LENGTH];
-    prf(&mut buf, LENGTH, seed, nonce);
+    let buf: [u8; LENGTH] = PrfReader::new(seed, nonce).keystream();
     poly_cbd_eta1(r, &buf);
 }
 
@@ -205,11 +412,42 @@ pub(crate) fn poly_getnoise_eta1(r: &mut Poly, seed: &[u8], nonce: u8) {
 ///  - [u8]  nonce:   one-byte input nonce
 pub(crate) fn poly_getnoise_eta2(r: &mut Poly, seed: &[u8], nonce: u8) {
     const LENGTH: usize = KYBER_ETA2 * KYBER_N / 4;
-    let mut buf = [0u8; LENGTH];
-    prf(&mut buf, LENGTH, seed, nonce);
+    let buf: [u8; LENGTH] = PrfReader::new(seed, nonce).keystream();
     poly_cbd_eta2(r, &buf);
 }
 
+/// Name:  poly_getnoise
+///
+/// Description: Sample a polynomial deterministically from a seed and a nonce,
+///  with output polynomial close to centered binomial distribution
+///  with parameter eta, for experiments with other noise widths
+///
+/// Arguments:   - poly *r:     output polynomial
+///  - const [u8] seed: input seed (pointing to array of length KYBER_SYM_BYTES bytes)
+///  - [u8]  nonce:   one-byte input nonce
+///  - usize eta:     noise parameter, either 2 or 3
+///
+/// Returns KyberLibError::InvalidInput for any other eta
+#[cfg(any(feature = "research", feature = "levels"))]
+pub(crate) fn poly_getnoise(
+    r: &mut Poly,
+    seed: &[u8],
+    nonce: u8,
+    eta: usize,
+) -> Result<(), KyberLibError> {
+    const MAX_LENGTH: usize = 3 * KYBER_N / 4;
+    let mut buf = [0u8; MAX_LENGTH];
+    let length = eta * KYBER_N / 4;
+    let sample: fn(&mut Poly, &[u8]) = match eta {
+        2 => poly_cbd_eta2,
+        3 => cbd3,
+        _ => return Err(KyberLibError::InvalidInput),
+    };
+    PrfReader::new(seed, nonce).squeeze(&mut buf[..length]);
+    sample(r, &buf[..length]);
+    Ok(())
+}
+
 /// Name:  poly_ntt
 ///
 /// Description: Computes negacyclic number-theoretic transform (NTT) of
@@ -218,8 +456,11 @@ pub(crate) fn poly_getnoise_eta2(r: &mut Poly, seed: &[u8], nonce: u8) {
 ///
 /// Arguments:   - Poly r: in/output polynomial
 pub(crate) fn poly_ntt(r: &mut Poly) {
+    expect_domain!(r, Normal, "poly_ntt");
+    count_ntt(Acceleration::Scalar);
     ntt(&mut r.coeffs);
     poly_reduce(r);
+    set_domain!(r, Ntt);
 }
 
 /// Name:  poly_invntt
@@ -230,7 +471,9 @@ pub(crate) fn poly_ntt(r: &mut Poly) {
 ///
 /// Arguments:   - Poly a: in/output polynomial
 pub(crate) fn poly_invntt_tomont(r: &mut Poly) {
+    expect_domain!(r, Ntt, "poly_invntt_tomont");
     invntt(&mut r.coeffs);
+    set_domain!(r, Normal);
 }
 
 /// Name:  poly_basemul
@@ -240,7 +483,10 @@ pub(crate) fn poly_invntt_tomont(r: &mut Poly) {
 /// Arguments:   - poly *r:   output polynomial
 ///  - const poly *a: first input polynomial
 ///  - const poly *b: second input polynomial
+#[cfg(any(test, feature = "research"))]
 pub(crate) fn poly_basemul(r: &mut Poly, a: &Poly, b: &Poly) {
+    expect_domain!(a, Ntt, "poly_basemul");
+    expect_domain!(b, Ntt, "poly_basemul");
     #[allow(clippy::needless_range_loop)]
     for i in 0..(KYBER_N / 4) {
         basemul(
@@ -256,6 +502,7 @@ pub(crate) fn poly_basemul(r: &mut Poly, a: &Poly, b: &Poly) {
             -(ZETAS[64 + i]),
         );
     }
+    set_domain!(r, Ntt);
 }
 
 /// Name:  poly_tomont
@@ -265,6 +512,11 @@ pub(crate) fn poly_basemul(r: &mut Poly, a: &Poly, b: &Poly) {
 ///
 /// Arguments:   - poly *r:   input/output polynomial
 pub(crate) fn poly_tomont(r: &mut Poly) {
+    // f = 2^32 mod q = 1353, so for any i16 coefficient |a * f| <=
+    // 2^15 * 1353 = 44,335,104. That is below both i32::MAX and the
+    // q * 2^15 = 109,084,672 montgomery_reduce accepts, so no input
+    // needs reducing first, and the output is at most
+    // (44,335,104 + q * 2^15) / 2^16 < 2342 in magnitude.
     let f = ((1u64 << 32) % KYBER_Q as u64) as i16;
     #[allow(clippy::needless_range_loop)]
     for i in 0..KYBER_N {
@@ -273,6 +525,20 @@ pub(crate) fn poly_tomont(r: &mut Poly) {
     }
 }
 
+/// Name:  poly_frommont
+///
+/// Description: Inplace conversion of all coefficients of a polynomial
+///  from Montgomery domain to normal domain; inverse of poly_tomont
+///
+/// Arguments:   - poly *r:   input/output polynomial
+#[cfg(feature = "research")]
+pub(crate) fn poly_frommont(r: &mut Poly) {
+    #[allow(clippy::needless_range_loop)]
+    for i in 0..KYBER_N {
+        r.coeffs[i] = montgomery_reduce(r.coeffs[i] as i32);
+    }
+}
+
 /// Name:  poly_reduce
 ///
 /// Description: Applies Barrett reduction to all coefficients of a polynomial
@@ -286,9 +552,28 @@ pub(crate) fn poly_reduce(r: &mut Poly) {
     }
 }
 
+/// Name:  poly_csubq
+///
+/// Description: Lifts all coefficients of a polynomial to their standard
+///  representatives in {0,...,q-1}; for details see csubq in reduce.rs
+///
+/// Arguments:   - poly *r:   input/output polynomial
+pub(crate) fn poly_csubq(r: &mut Poly) {
+    for c in r.coeffs.iter_mut() {
+        *c = csubq(*c);
+    }
+}
+
+/// Largest coefficient magnitude accepted by `poly_add`: the sum of two
+/// such coefficients always fits in an i16. Callers stay far below it,
+/// the largest operands being just above q after an NTT round trip.
+pub(crate) const POLY_ADD_BOUND: i16 = i16::MAX / 2;
+
 /// Name:  poly_add
 ///
-/// Description: Add two polynomials; no modular reduction is performed
+/// Description: Add two polynomials; no modular reduction is performed.
+///  In debug builds, panics if a coefficient of either input exceeds
+///  POLY_ADD_BOUND in absolute value
 ///
 /// Arguments: - poly *r:   output polynomial
 ///  - const poly *a: first input polynomial
@@ -296,6 +581,12 @@ pub(crate) fn poly_reduce(r: &mut Poly) {
 pub(crate) fn poly_add(r: &mut Poly, b: &Poly) {
     #[allow(clippy::needless_range_loop)]
     for i in 0..KYBER_N {
+        debug_assert!(
+            r.coeffs[i].unsigned_abs() <= POLY_ADD_BOUND as u16
+                && b.coeffs[i].unsigned_abs() <= POLY_ADD_BOUND as u16,
+            "poly_add operand out of range at coefficient {}",
+            i
+        );
         r.coeffs[i] += b.coeffs[i];
     }
 }
@@ -321,16 +612,35 @@ pub(crate) fn poly_sub(r: &mut Poly, a: &Poly) {
 ///
 /// Arguments:   - poly *r:    output polynomial
 ///  - const [u8] msg: input message (of length KYBER_SYM_BYTES)
+///
+/// The message is secret, so each bit selects its coefficient through
+/// an all-zeros or all-ones mask rather than a branch. The bit passes
+/// through `black_box` so the optimizer cannot see that the mask has
+/// only two values and turn the `and` back into a conditional. That is
+/// best effort, not a guarantee, so check the compiled code after
+/// changing this loop:
+///
+/// ```text
+/// cargo rustc --release --lib -- --emit=asm -C codegen-units=1
+/// ```
+///
+/// and look at the loop inlined into `indcpa_enc` in
+/// `target/release/deps/kyberlib.s`. On x86_64 each bit is extracted
+/// with `bt`/`setb`, goes through the stack slot of `black_box`, and is
+/// negated and and-ed with 1665; the only branches are the loop
+/// counters and the bounds check on the length of `msg`.
 pub(crate) fn poly_frommsg(r: &mut Poly, msg: &[u8]) {
+    const HALF_Q: u16 = KYBER_Q.div_ceil(2) as u16;
     let mut mask;
     #[allow(clippy::needless_range_loop)]
     for i in 0..KYBER_N / 8 {
         for j in 0..8 {
-            mask = ((msg[i] as u16 >> j) & 1).wrapping_neg();
-            r.coeffs[8 * i + j] =
-                (mask & ((KYBER_Q + 1) / 2) as u16) as i16;
+            let bit = black_box((msg[i] as u16 >> j) & 1);
+            mask = bit.wrapping_neg();
+            r.coeffs[8 * i + j] = (mask & HALF_Q) as i16;
         }
     }
+    set_domain!(r, Normal);
 }
 
 /// Name:  poly_tomsg
@@ -357,3 +667,431 @@ pub(crate) fn poly_tomsg(msg: &mut [u8], a: Poly) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polyvec::Polyvec;
+
+    #[test]
+    fn polynomials_are_cache_line_aligned() {
+        assert_eq!(align_of::<Poly>(), 64);
+        #[cfg(not(all(feature = "debug-domain", debug_assertions)))]
+        assert_eq!(size_of::<Poly>(), 2 * KYBER_N);
+        let v = Polyvec::new();
+        for p in v.vec.iter() {
+            assert_eq!(p.coeffs.as_ptr() as usize % 64, 0);
+        }
+    }
+
+    // poly_frommsg encodes a bit as 0 or (q + 1) / 2 = 1665, and
+    // poly_tomsg decodes a coefficient in 0..q by rounding 2a / q. Any
+    // error e with |e| <= MSG_ERROR_BOUND, reduced mod q, leaves both
+    // encodings decoding correctly; the margin is one short of q / 4 =
+    // 832.25 because 1665 + 832 already rounds up to 0. An error of
+    // magnitude q / 4 + 1 = 833 flips every bit.
+    const MSG_ERROR_BOUND: i16 = 831;
+
+    // Adds `e` to the coefficients of the encoded `msg` and decodes it.
+    fn decode_with_error(
+        msg: &[u8; KYBER_SYM_BYTES],
+        e: &[i16],
+    ) -> [u8; KYBER_SYM_BYTES] {
+        let mut p = Poly::new();
+        poly_frommsg(&mut p, msg);
+        for (c, e) in p.coeffs.iter_mut().zip(e) {
+            *c = (*c + e).rem_euclid(KYBER_Q as i16);
+        }
+        let mut out = [0u8; KYBER_SYM_BYTES];
+        poly_tomsg(&mut out, p);
+        out
+    }
+
+    #[test]
+    fn message_survives_error_within_bound() {
+        use rand::{Rng, RngCore};
+        let mut rng = rand::rngs::OsRng;
+        for _ in 0..64 {
+            let mut msg = [0u8; KYBER_SYM_BYTES];
+            rng.fill_bytes(&mut msg);
+            let mut e = [0i16; KYBER_N];
+            for c in e.iter_mut() {
+                *c = rng.gen_range(-MSG_ERROR_BOUND..=MSG_ERROR_BOUND);
+            }
+            assert_eq!(decode_with_error(&msg, &e), msg);
+        }
+        for bound in [-MSG_ERROR_BOUND, MSG_ERROR_BOUND] {
+            let e = [bound; KYBER_N];
+            for byte in [0x00, 0xff] {
+                let msg = [byte; KYBER_SYM_BYTES];
+                assert_eq!(decode_with_error(&msg, &e), msg);
+            }
+        }
+    }
+
+    // Decapsulation compares the re-encryption with the ciphertext after
+    // compression, because a comparison of the polynomials before it
+    // would reject honest ciphertexts and tell apart polynomials that
+    // the byte check accepts alike.
+    #[test]
+    fn ciphertexts_compare_only_after_compression() {
+        let mut v = Poly::new();
+        for (i, c) in v.coeffs.iter_mut().enumerate() {
+            *c = (i * 1021 % KYBER_Q) as i16;
+        }
+        let mut ct = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+        poly_compress(&mut ct, v);
+
+        let mut received = Poly::new();
+        poly_decompress(&mut received, &ct);
+        assert_ne!(received.coeffs, v.coeffs);
+        let mut again = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+        poly_compress(&mut again, received);
+        assert_eq!(again, ct);
+
+        // 0 and 1 compress to the same value.
+        let mut w = v;
+        w.coeffs[0] = 1;
+        poly_compress(&mut again, w);
+        assert_eq!((v.coeffs[0], again), (0, ct));
+    }
+
+    #[test]
+    fn error_beyond_bound_flips_bits() {
+        let ones = [0xff; KYBER_SYM_BYTES];
+        let zeros = [0x00; KYBER_SYM_BYTES];
+        // Just past the margin on the side where it is tight.
+        let e = [MSG_ERROR_BOUND + 1; KYBER_N];
+        assert_eq!(decode_with_error(&ones, &e), zeros);
+        assert_eq!(decode_with_error(&zeros, &e), zeros);
+        // Past q / 4 in either direction every bit flips.
+        for e in [-MSG_ERROR_BOUND - 2, MSG_ERROR_BOUND + 2] {
+            assert_eq!(decode_with_error(&ones, &[e; KYBER_N]), zeros);
+            assert_eq!(decode_with_error(&zeros, &[e; KYBER_N]), ones);
+        }
+    }
+
+    #[test]
+    fn tomont_accepts_every_i16() {
+        let f = ((1u64 << 32) % KYBER_Q as u64) as i32;
+        let q = KYBER_Q as i32;
+        // 2^16 inputs fill exactly 2^16 / KYBER_N polynomials.
+        let mut inputs = i16::MIN..=i16::MAX;
+        for _ in 0..(1 << 16) / KYBER_N {
+            let mut p = Poly::new();
+            for (c, a) in p.coeffs.iter_mut().zip(&mut inputs) {
+                *c = a;
+            }
+            let a = p;
+            poly_tomont(&mut p);
+            for (x, r) in a.coeffs.iter().zip(&p.coeffs) {
+                let prod = i32::from(*x).checked_mul(f).unwrap();
+                assert!((-q << 15..q << 15).contains(&prod));
+                assert!(i32::from(*r).abs() < 2342, "{} -> {}", x, r);
+                // r = x * 2^32 * 2^-16 = x * 2^16 mod q
+                let diff = i64::from(*r) - (i64::from(*x) << 16);
+                assert_eq!(diff % i64::from(q), 0);
+            }
+        }
+        assert!(inputs.next().is_none());
+    }
+
+    #[test]
+    fn frommsg_encodes_every_bit() {
+        let half_q = KYBER_Q.div_ceil(2) as i16;
+        for byte in 0..=255u8 {
+            let mut msg = [0u8; KYBER_SYM_BYTES];
+            msg[byte as usize % KYBER_SYM_BYTES] = byte;
+            let mut p = Poly::new();
+            poly_frommsg(&mut p, &msg);
+            for (k, c) in p.coeffs.iter().enumerate() {
+                let bit = (msg[k / 8] >> (k % 8)) & 1;
+                assert_eq!(*c, i16::from(bit) * half_q);
+            }
+        }
+    }
+
+    // The message bit must only ever select through the mask: no `if`
+    // or `match` may creep into poly_frommsg, see its doc comment.
+    #[test]
+    fn frommsg_has_no_branch_on_the_message() {
+        let src = include_str!("poly.rs");
+        let start = src.find("pub(crate) fn poly_frommsg").unwrap();
+        let len = src[start..].find("\n}\n").unwrap();
+        let body = &src[start..start + len];
+        for keyword in ["if ", "match ", "else"] {
+            assert!(!body.contains(keyword), "found `{}`", keyword);
+        }
+        assert!(body.contains("black_box("));
+        assert!(body.contains("wrapping_neg()"));
+    }
+
+    #[test]
+    fn add_in_range() {
+        let mut r = Poly::new();
+        let mut b = Poly::new();
+        r.coeffs.fill(POLY_ADD_BOUND);
+        b.coeffs.fill(POLY_ADD_BOUND);
+        b.coeffs[0] = -POLY_ADD_BOUND;
+        poly_add(&mut r, &b);
+        assert_eq!(r.coeffs[0], 0);
+        assert_eq!(r.coeffs[1], 2 * POLY_ADD_BOUND);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "poly_add operand out of range")]
+    fn add_out_of_range_panics() {
+        let mut r = Poly::new();
+        let mut b = Poly::new();
+        b.coeffs[7] = POLY_ADD_BOUND + 1;
+        poly_add(&mut r, &b);
+    }
+
+    #[cfg(feature = "small")]
+    #[test]
+    fn unpack_bits_inverts_pack_bits() {
+        for d in [4, 5, 10, 11, 12] {
+            let values = (0..KYBER_N as u32)
+                .map(|i| i.wrapping_mul(2654435761) >> 7);
+            let mut bytes = [0u8; KYBER_N * 12 / 8];
+            pack_bits(&mut bytes[..KYBER_N * d / 8], d, values.clone());
+            let mask = (1u32 << d) - 1;
+            assert!(unpack_bits(&bytes[..KYBER_N * d / 8], d)
+                .eq(values.map(|v| v & mask)));
+        }
+    }
+
+    #[test]
+    fn serialization_accepts_exact_buffers() {
+        let mut p = Poly::new();
+        for (i, c) in p.coeffs.iter_mut().enumerate() {
+            *c = (i * 13) as i16;
+        }
+        let mut bytes = [0u8; KYBER_POLY_BYTES];
+        poly_tobytes(&mut bytes, p);
+        let mut q = Poly::new();
+        poly_frombytes(&mut q, &bytes);
+        assert_eq!(q.coeffs, p.coeffs);
+
+        let mut compressed = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+        poly_compress(&mut compressed, p);
+        poly_decompress(&mut q, &compressed);
+    }
+
+    #[test]
+    #[should_panic(expected = "poly_compress output buffer too short")]
+    fn compress_short_buffer_panics() {
+        let mut r = [0u8; KYBER_POLY_COMPRESSED_BYTES - 1];
+        poly_compress(&mut r, Poly::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "poly_decompress input buffer too short")]
+    fn decompress_short_buffer_panics() {
+        poly_decompress(
+            &mut Poly::new(),
+            &[0u8; KYBER_POLY_COMPRESSED_BYTES - 1],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "poly_tobytes output buffer too short")]
+    fn tobytes_short_buffer_panics() {
+        let mut r = [0u8; KYBER_POLY_BYTES - 1];
+        poly_tobytes(&mut r, Poly::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "poly_frombytes input buffer too short")]
+    fn frombytes_short_buffer_panics() {
+        poly_frombytes(&mut Poly::new(), &[0u8; KYBER_POLY_BYTES - 1]);
+    }
+
+    #[cfg(feature = "research")]
+    const SEED: [u8; KYBER_SYM_BYTES] = [0x2a; KYBER_SYM_BYTES];
+
+    // Under Kyber512 KYBER_ETA1 == 3 and this exercises the cbd3 path.
+    #[cfg(feature = "research")]
+    #[test]
+    fn getnoise_matches_eta1() {
+        let mut expected = Poly::new();
+        let mut actual = Poly::new();
+        for nonce in 0..4 {
+            poly_getnoise_eta1(&mut expected, &SEED, nonce);
+            poly_getnoise(&mut actual, &SEED, nonce, KYBER_ETA1)
+                .unwrap();
+            assert_eq!(actual.coeffs, expected.coeffs);
+        }
+    }
+
+    #[cfg(feature = "research")]
+    #[test]
+    fn getnoise_matches_eta2() {
+        let mut expected = Poly::new();
+        let mut actual = Poly::new();
+        for nonce in 0..4 {
+            poly_getnoise_eta2(&mut expected, &SEED, nonce);
+            poly_getnoise(&mut actual, &SEED, nonce, 2).unwrap();
+            assert_eq!(actual.coeffs, expected.coeffs);
+        }
+    }
+
+    #[cfg(feature = "research")]
+    #[test]
+    fn getnoise_eta3_is_bounded() {
+        let mut r = Poly::new();
+        poly_getnoise(&mut r, &SEED, 0, 3).unwrap();
+        assert!(r.coeffs.iter().all(|&c| (-3..=3).contains(&c)));
+        assert!(r.coeffs.iter().any(|&c| c.abs() == 3));
+    }
+
+    #[cfg(feature = "research")]
+    #[test]
+    fn getnoise_rejects_unsupported_eta() {
+        let mut r = Poly::new();
+        for eta in [0, 1, 4] {
+            assert_eq!(
+                poly_getnoise(&mut r, &SEED, 0, eta),
+                Err(KyberLibError::InvalidInput)
+            );
+        }
+    }
+
+    // Schoolbook product in Z_q[X]/(X^256 + 1), coefficients in 0..q.
+    fn schoolbook_mul(a: &Poly, b: &Poly) -> [i64; KYBER_N] {
+        let q = KYBER_Q as i64;
+        let mut r = [0i64; KYBER_N];
+        for i in 0..KYBER_N {
+            for j in 0..KYBER_N {
+                let t = a.coeffs[i] as i64 * b.coeffs[j] as i64;
+                if i + j < KYBER_N {
+                    r[i + j] += t;
+                } else {
+                    // X^256 = -1
+                    r[i + j - KYBER_N] -= t;
+                }
+            }
+        }
+        r.map(|c| c.rem_euclid(q))
+    }
+
+    // Deterministic polynomial with coefficients in 0..q.
+    fn sample(seed: u64) -> Poly {
+        let mut state = seed;
+        let mut p = Poly::new();
+        for c in p.coeffs.iter_mut() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *c = (state % KYBER_Q as u64) as i16;
+        }
+        p
+    }
+
+    #[test]
+    fn ntt_basemul_matches_schoolbook() {
+        for seed in 1..=4u64 {
+            let a = sample(seed);
+            let b = sample(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            let expected = schoolbook_mul(&a, &b);
+
+            let (mut ahat, mut bhat, mut r) = (a, b, Poly::new());
+            poly_ntt(&mut ahat);
+            poly_ntt(&mut bhat);
+            poly_basemul(&mut r, &ahat, &bhat);
+            // basemul leaves a factor R^-1, which invntt_tomont cancels by
+            // multiplying by R, so no separate Montgomery fix-up is needed.
+            poly_invntt_tomont(&mut r);
+            poly_reduce(&mut r);
+            poly_csubq(&mut r);
+
+            let actual = r.coeffs.map(|c| c as i64);
+            assert_eq!(actual, expected, "seed {}", seed);
+        }
+    }
+
+    // Hands out the bytes of a cursor in reads of 1, 3, 5, ... bytes, as
+    // a key split across odd-sized buffers would arrive.
+    #[cfg(feature = "std")]
+    struct OddChunks<'a> {
+        cursor: std::io::Cursor<&'a [u8]>,
+        next: usize,
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for OddChunks<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.next);
+            self.next += 2;
+            self.cursor.read(&mut buf[..len])
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn frombytes_reader_matches_frombytes() {
+        use rand::RngCore;
+        let mut bytes = [0u8; KYBER_POLY_BYTES + 5];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+
+        let mut expected = Poly::new();
+        poly_frombytes(&mut expected, &bytes);
+        let mut reader = OddChunks {
+            cursor: std::io::Cursor::new(&bytes[..]),
+            next: 1,
+        };
+        let mut r = Poly::new();
+        poly_frombytes_reader(&mut r, &mut reader).unwrap();
+        assert_eq!(r.coeffs, expected.coeffs);
+        // Exactly one polynomial is consumed.
+        assert_eq!(reader.cursor.position(), KYBER_POLY_BYTES as u64);
+
+        let mut r = Poly::new();
+        let short = OddChunks {
+            cursor: std::io::Cursor::new(
+                &bytes[..KYBER_POLY_BYTES - 1],
+            ),
+            next: 1,
+        };
+        let err = poly_frombytes_reader(&mut r, short).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.coeffs, [0; KYBER_N]);
+    }
+
+    #[cfg(all(feature = "debug-domain", debug_assertions))]
+    #[test]
+    fn domains_follow_the_transforms() {
+        let mut a = Poly::new();
+        poly_frommsg(&mut a, &[0xa5; KYBER_SYM_BYTES]);
+        poly_ntt(&mut a);
+        let mut b = Poly::new();
+        poly_frombytes(&mut b, &[0x17; KYBER_POLY_BYTES]);
+        let mut r = Poly::new();
+        poly_basemul(&mut r, &a, &b);
+        poly_invntt_tomont(&mut r);
+        assert_eq!(r.domain, Domain::Normal);
+        poly_ntt(&mut r);
+        assert_eq!(r.domain, Domain::Ntt);
+    }
+
+    #[cfg(all(feature = "debug-domain", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "poly_ntt expects Normal, got Ntt")]
+    fn ntt_twice_panics() {
+        let mut a = Poly::new();
+        poly_ntt(&mut a);
+        poly_ntt(&mut a);
+    }
+
+    #[cfg(all(feature = "debug-domain", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "poly_basemul expects Ntt, got Normal")]
+    fn basemul_of_normal_poly_panics() {
+        let mut a = Poly::new();
+        poly_ntt(&mut a);
+        let b = Poly::new();
+        let mut r = Poly::new();
+        poly_basemul(&mut r, &a, &b);
+    }
+}
@@ -1,5 +1,13 @@
 #![allow(clippy::precedence)]
-use crate::{params::*, poly::*};
+// Each backend uses its own subset of these, the reference one only a
+// few now that it works a polynomial at a time.
+#![allow(dead_code)]
+use crate::{
+    ntt::{fqmul, ZETAS},
+    params::*,
+    poly::*,
+    reduce::montgomery_reduce,
+};
 
 #[derive(Clone)]
 pub(crate) struct Polyvec {
@@ -16,6 +24,15 @@ impl Polyvec {
     }
 }
 
+// Bits per coefficient of a compressed vector.
+const POLYVEC_COMPRESSED_BITS: usize = KYBER_DU;
+
+// Bytes of one polynomial of a compressed vector. 256 coefficients make
+// a whole number of bytes at any width, so each polynomial starts on a
+// byte boundary.
+pub(crate) const POLYVEC_POLY_COMPRESSED_BYTES: usize =
+    KYBER_N * POLYVEC_COMPRESSED_BITS / 8;
+
 /// Name:  polyvec_compress
 ///
 /// Description: Compress and serialize vector of polynomials
@@ -23,39 +40,63 @@ impl Polyvec {
 /// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLYVEC_COMPRESSED_BYTES)
 ///  - const Polyvec a: input vector of polynomials
 pub(crate) fn polyvec_compress(r: &mut [u8], a: Polyvec) {
-    #[cfg(feature = "kyber1024")]
+    for (r, a) in r[..KYBER_POLYVEC_COMPRESSED_BYTES]
+        .chunks_exact_mut(POLYVEC_POLY_COMPRESSED_BYTES)
+        .zip(a.vec)
     {
+        polyvec_compress_poly(r, a);
+    }
+}
+
+/// Name:  polyvec_compress_poly
+///
+/// Description: Compress and serialize one polynomial of a vector, so
+///  that the vector can be produced a polynomial at a time;
+///  polyvec_compress is this for each polynomial
+///
+/// Arguments:   - [u8] r: output byte array (needs space for
+///    POLYVEC_POLY_COMPRESSED_BYTES, the bytes of that polynomial)
+///  - const poly *a: input polynomial
+pub(crate) fn polyvec_compress_poly(r: &mut [u8], mut a: Poly) {
+    poly_csubq(&mut a);
+    // DIV((x << d) + q/2, q) as (x*a)>>e with a/(2^e) = 20,642,679/2^36,
+    // exact for every input, see poly_compress
+    #[cfg(all(not(feature = "small"), feature = "kyber1024"))]
+    if !KYBER_COMPRESSION_OVERRIDDEN {
         let mut t = [0u16; 8];
         let mut idx = 0usize;
-        for i in 0..KYBER_SECURITY_PARAMETER {
-            for j in 0..KYBER_N / 8 {
-                for (k, t_k) in t.iter_mut().enumerate() {
-                    *t_k = a.vec[i].coeffs[8 * j + k] as u16;
-                    *t_k = t_k.wrapping_add(
-                        (((*t_k as i16) >> 15) & KYBER_Q as i16)
-                            as u16,
-                    );
-                    let mut tmp: u64 =
-                        ((*t_k as u64) << 11) + (KYBER_Q as u64 / 2);
-                    tmp *= 20642679;
-                    tmp >>= 36;
-                    *t_k = (tmp as u16) & 0x7ff;
-                }
-                r[idx] = (t[0]) as u8;
-                r[idx + 1] = ((t[0] >> 8) | (t[1] << 3)) as u8;
-                r[idx + 2] = ((t[1] >> 5) | (t[2] << 6)) as u8;
-                r[idx + 3] = (t[2] >> 2) as u8;
-                r[idx + 4] = ((t[2] >> 10) | (t[3] << 1)) as u8;
-                r[idx + 5] = ((t[3] >> 7) | (t[4] << 4)) as u8;
-                r[idx + 6] = ((t[4] >> 4) | (t[5] << 7)) as u8;
-                r[idx + 7] = (t[5] >> 1) as u8;
-                r[idx + 8] = ((t[5] >> 9) | (t[6] << 2)) as u8;
-                r[idx + 9] = ((t[6] >> 6) | (t[7] << 5)) as u8;
-                r[idx + 10] = (t[7] >> 3) as u8;
-                idx += 11
+        for j in 0..KYBER_N / 8 {
+            for (k, t_k) in t.iter_mut().enumerate() {
+                *t_k = a.coeffs[8 * j + k] as u16;
+                let mut tmp: u64 =
+                    ((*t_k as u64) << 11) + (KYBER_Q as u64 / 2);
+                tmp *= 20642679;
+                tmp >>= 36;
+                *t_k = (tmp as u16) & 0x7ff;
             }
+            r[idx] = (t[0]) as u8;
+            r[idx + 1] = ((t[0] >> 8) | (t[1] << 3)) as u8;
+            r[idx + 2] = ((t[1] >> 5) | (t[2] << 6)) as u8;
+            r[idx + 3] = (t[2] >> 2) as u8;
+            r[idx + 4] = ((t[2] >> 10) | (t[3] << 1)) as u8;
+            r[idx + 5] = ((t[3] >> 7) | (t[4] << 4)) as u8;
+            r[idx + 6] = ((t[4] >> 4) | (t[5] << 7)) as u8;
+            r[idx + 7] = (t[5] >> 1) as u8;
+            r[idx + 8] = ((t[5] >> 9) | (t[6] << 2)) as u8;
+            r[idx + 9] = ((t[6] >> 6) | (t[7] << 5)) as u8;
+            r[idx + 10] = (t[7] >> 3) as u8;
+            idx += 11
         }
+        return;
     }
+    pack_bits(
+        &mut r[..POLYVEC_POLY_COMPRESSED_BYTES],
+        POLYVEC_COMPRESSED_BITS,
+        a.coeffs.iter().map(|&c| {
+            let x = (c as u16 as u64) << POLYVEC_COMPRESSED_BITS;
+            (((x + KYBER_Q as u64 / 2) * 20642679) >> 36) as u32
+        }),
+    );
 }
 
 /// Name:  polyvec_decompress
@@ -66,39 +107,60 @@ pub(crate) fn polyvec_compress(r: &mut [u8], a: Polyvec) {
 /// Arguments:   - Polyvec r:   output vector of polynomials
 ///  - [u8] a: input byte array (of length KYBER_POLYVEC_COMPRESSED_BYTES)
 pub(crate) fn polyvec_decompress(r: &mut Polyvec, a: &[u8]) {
-    #[cfg(feature = "kyber1024")]
-    {
+    for (r, a) in r.vec.iter_mut().zip(
+        a[..KYBER_POLYVEC_COMPRESSED_BYTES]
+            .chunks_exact(POLYVEC_POLY_COMPRESSED_BYTES),
+    ) {
+        polyvec_decompress_poly(r, a);
+    }
+}
+
+/// Name:  polyvec_decompress_poly
+///
+/// Description: De-serialize and decompress one polynomial of a
+///  compressed vector, so that the vector can be processed a polynomial
+///  at a time; polyvec_decompress is this for each polynomial
+///
+/// Arguments:   - poly *r:  output polynomial
+///  - [u8] a: input byte array (of length POLYVEC_POLY_COMPRESSED_BYTES,
+///    the bytes of that polynomial)
+pub(crate) fn polyvec_decompress_poly(r: &mut Poly, a: &[u8]) {
+    set_domain!(r, Normal);
+    #[cfg(all(not(feature = "small"), feature = "kyber1024"))]
+    if !KYBER_COMPRESSION_OVERRIDDEN {
         let mut t = [0u16; 8];
         let mut idx = 0usize;
-        for i in 0..KYBER_SECURITY_PARAMETER {
-            for j in 0..KYBER_N / 8 {
-                t[0] = (a[idx]) as u16 | (a[idx + 1] as u16) << 8;
-                t[1] =
-                    (a[idx + 1] >> 3) as u16 | (a[idx + 2] as u16) << 5;
-                t[2] = (a[idx + 2] >> 6) as u16
-                    | (a[idx + 3] as u16) << 2
-                    | (a[idx + 4] as u16) << 10;
-                t[3] =
-                    (a[idx + 4] >> 1) as u16 | (a[idx + 5] as u16) << 7;
-                t[4] =
-                    (a[idx + 5] >> 4) as u16 | (a[idx + 6] as u16) << 4;
-                t[5] = (a[idx + 6] >> 7) as u16
-                    | (a[idx + 7] as u16) << 1
-                    | (a[idx + 8] as u16) << 9;
-                t[6] =
-                    (a[idx + 8] >> 2) as u16 | (a[idx + 9] as u16) << 6;
-                t[7] = (a[idx + 9] >> 5) as u16
-                    | (a[idx + 10] as u16) << 3;
-                idx += 11;
-
-                for k in 0..8 {
-                    r.vec[i].coeffs[8 * j + k] =
-                        (((t[k] & 0x7FF) as u32 * KYBER_Q as u32
-                            + 1024)
-                            >> 11) as i16;
-                }
+        for j in 0..KYBER_N / 8 {
+            t[0] = (a[idx]) as u16 | (a[idx + 1] as u16) << 8;
+            t[1] = (a[idx + 1] >> 3) as u16 | (a[idx + 2] as u16) << 5;
+            t[2] = (a[idx + 2] >> 6) as u16
+                | (a[idx + 3] as u16) << 2
+                | (a[idx + 4] as u16) << 10;
+            t[3] = (a[idx + 4] >> 1) as u16 | (a[idx + 5] as u16) << 7;
+            t[4] = (a[idx + 5] >> 4) as u16 | (a[idx + 6] as u16) << 4;
+            t[5] = (a[idx + 6] >> 7) as u16
+                | (a[idx + 7] as u16) << 1
+                | (a[idx + 8] as u16) << 9;
+            t[6] = (a[idx + 8] >> 2) as u16 | (a[idx + 9] as u16) << 6;
+            t[7] = (a[idx + 9] >> 5) as u16 | (a[idx + 10] as u16) << 3;
+            idx += 11;
+
+            #[allow(clippy::needless_range_loop)]
+            for k in 0..8 {
+                r.coeffs[8 * j + k] =
+                    (((t[k] & 0x7FF) as u32 * KYBER_Q as u32 + 1024)
+                        >> 11) as i16;
             }
         }
+        return;
+    }
+    for (c, t) in r.coeffs.iter_mut().zip(unpack_bits(
+        &a[..POLYVEC_POLY_COMPRESSED_BYTES],
+        POLYVEC_COMPRESSED_BITS,
+    )) {
+        *c = ((t * KYBER_Q as u32
+            + (1 << (POLYVEC_COMPRESSED_BITS - 1)))
+            >> POLYVEC_COMPRESSED_BITS) as i16;
     }
 }
 
@@ -161,13 +223,122 @@ pub(crate) fn polyvec_basemul_acc_montgomery(
     a: &Polyvec,
     b: &Polyvec,
 ) {
+    basemul_acc_lazy(r, a.vec.iter().zip(&b.vec));
+}
+
+// The zeta of each pair of coefficients in the NTT domain: the basemul of
+// pairs `2i` and `2i + 1` reduces modulo `X^2 - zeta` and `X^2 + zeta`.
+const PAIR_ZETAS: [i16; KYBER_N / 2] = {
+    let mut zetas = [0i16; KYBER_N / 2];
+    let mut i = 0;
+    while i < KYBER_N / 4 {
+        zetas[2 * i] = ZETAS[64 + i];
+        zetas[2 * i + 1] = -ZETAS[64 + i];
+        i += 1;
+    }
+    zetas
+};
+
+/// Name:  basemul_acc_lazy
+///
+/// Description: Pointwise multiply the pairs of polynomials in terms and
+///  accumulate into r, like polyvec_basemul_acc_montgomery. The products
+///  are summed in i32 lanes and Montgomery-reduced once per coefficient
+///  instead of once per product: with the odd factor of each pair
+///  premultiplied by its zeta, every output is a sum of plain products
+///  over flat arrays, a loop the compiler can vectorize for the SIMD unit
+///  of the target.
+///
+///  At most 4 terms; the coefficients of the first factor of each term
+///  must be below 2^12 in absolute value, which holds for the matrix and
+///  for unpacked keys, so that the sums fit in an i32.
+///
+/// Arguments: - poly *r:  output polynomial
+///  - terms: pairs of polynomials to multiply
+pub(crate) fn basemul_acc_lazy<'a, I>(r: &mut Poly, terms: I)
+where
+    I: IntoIterator<Item = (&'a Poly, &'a Poly)>,
+{
+    let mut acc = [0i32; KYBER_N];
+    for (k, (a, b)) in terms.into_iter().enumerate() {
+        debug_assert!(k < 4);
+        basemul_acc_term(&mut acc, a, b);
+    }
+    basemul_acc_finish(r, &acc);
+}
+
+/// Name:  basemul_acc_term
+///
+/// Description: Adds the pointwise product of a and b to the i32
+///  accumulator of basemul_acc_lazy, one term at a time, for callers
+///  that produce the terms one by one; the bounds of basemul_acc_lazy
+///  apply
+///
+/// Arguments: - [i32] acc: accumulator (of KYBER_N lanes)
+///  - const poly *a: first input polynomial
+///  - const poly *b: second input polynomial
+pub(crate) fn basemul_acc_term(
+    acc: &mut [i32; KYBER_N],
+    a: &Poly,
+    b: &Poly,
+) {
+    expect_domain!(a, Ntt, "basemul_acc_term");
+    expect_domain!(b, Ntt, "basemul_acc_term");
+    let mut bz = [0i16; KYBER_N / 2];
+    for ((z, b), &zeta) in
+        bz.iter_mut().zip(b.coeffs.chunks_exact(2)).zip(&PAIR_ZETAS)
+    {
+        *z = fqmul(b[1], zeta);
+    }
+    for (((acc, a), b), &bz) in acc
+        .chunks_exact_mut(2)
+        .zip(a.coeffs.chunks_exact(2))
+        .zip(b.coeffs.chunks_exact(2))
+        .zip(&bz)
+    {
+        let (a0, a1) = (a[0] as i32, a[1] as i32);
+        let (b0, b1) = (b[0] as i32, b[1] as i32);
+        acc[0] += a0 * b0 + a1 * bz as i32;
+        acc[1] += a0 * b1 + a1 * b0;
+    }
+}
+
+/// Name:  basemul_acc_finish
+///
+/// Description: Montgomery-reduces the accumulator of basemul_acc_term
+///  into r
+///
+/// Arguments: - poly *r:  output polynomial
+///  - [i32] acc: accumulated products
+pub(crate) fn basemul_acc_finish(r: &mut Poly, acc: &[i32; KYBER_N]) {
+    for (c, &acc) in r.coeffs.iter_mut().zip(acc) {
+        *c = montgomery_reduce(acc);
+    }
+    poly_reduce(r);
+    set_domain!(r, Ntt);
+}
+
+/// Name:  basemul_acc_per_term
+///
+/// Description: The accumulate basemul_acc_lazy replaced, reducing each
+///  product with poly_basemul before adding it. Output is identical; it
+///  is kept to validate the lazy accumulate and to benchmark the two.
+///
+/// Arguments: - poly *r:  output polynomial
+///  - terms: pairs of polynomials to multiply
+#[cfg(any(test, feature = "research"))]
+pub(crate) fn basemul_acc_per_term<'a, I>(r: &mut Poly, terms: I)
+where
+    I: IntoIterator<Item = (&'a Poly, &'a Poly)>,
+{
     let mut t = Poly::new();
-    poly_basemul(r, &a.vec[0], &b.vec[0]);
-    for i in 1..KYBER_SECURITY_PARAMETER {
-        poly_basemul(&mut t, &a.vec[i], &b.vec[i]);
+    *r = Poly::new();
+    for (a, b) in terms {
+        poly_basemul(&mut t, a, b);
         poly_add(r, &t);
     }
     poly_reduce(r);
+    set_domain!(r, Ntt);
 }
 
 /// Name:  polyvec_reduce
@@ -183,6 +354,18 @@ pub(crate) fn polyvec_reduce(r: &mut Polyvec) {
     }
 }
 
+/// Name:  polyvec_csubq
+///
+/// Description: Lifts each coefficient of each element of a vector of
+///  polynomials to its standard representative in {0,...,q-1}
+///
+/// Arguments:   - Polyvec r:   input/output vector of polynomials
+pub(crate) fn polyvec_csubq(r: &mut Polyvec) {
+    for i in 0..KYBER_SECURITY_PARAMETER {
+        poly_csubq(&mut r.vec[i]);
+    }
+}
+
 /// Name:  polyvec_add
 ///
 /// Description: Add vectors of polynomials
@@ -194,3 +377,44 @@ pub(crate) fn polyvec_add(r: &mut Polyvec, b: &Polyvec) {
         poly_add(&mut r.vec[i], &b.vec[i]);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lazy_accumulate_matches_per_term() {
+        // xorshift32; the first factors span the 12-bit range of unpacked
+        // keys, the second ones the -q+1..q range of NTT outputs
+        let mut state = 0x9e37_79b9u32;
+        let mut next = |modulus: u32| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % (2 * modulus - 1)) as i16 - (modulus - 1) as i16
+        };
+        for k in 2..=4 {
+            for _ in 0..16 {
+                let mut a = [Poly::new(); 4];
+                let mut b = [Poly::new(); 4];
+                for (a, b) in a.iter_mut().zip(b.iter_mut()) {
+                    for (x, y) in a.coeffs.iter_mut().zip(&mut b.coeffs)
+                    {
+                        *x = next(1 << 12);
+                        *y = next(KYBER_Q as u32);
+                    }
+                    set_domain!(a, Ntt);
+                    set_domain!(b, Ntt);
+                }
+                let (mut lazy, mut per_term) =
+                    (Poly::new(), Poly::new());
+                basemul_acc_lazy(&mut lazy, a[..k].iter().zip(&b[..k]));
+                basemul_acc_per_term(
+                    &mut per_term,
+                    a[..k].iter().zip(&b[..k]),
+                );
+                assert_eq!(lazy.coeffs, per_term.coeffs, "k = {}", k);
+            }
+        }
+    }
+}
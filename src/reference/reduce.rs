@@ -35,3 +35,19 @@ pub(crate) fn barrett_reduce(a: i16) -> i16 {
     t *= KYBER_Q as i32;
     a - t as i16
 }
+
+/// Name:  csubq
+///
+/// Description: Lifts a coefficient to its standard representative: adds q
+///  if a is negative, then subtracts q if a is at least q. Runs in constant
+///  time.
+///
+/// Arguments:   - i16 a: input integer; has to be in {-q+1,...,2q-1}
+///
+/// Returns:   i16 in {0,...,q-1} congruent to a modulo q.
+pub(crate) fn csubq(mut a: i16) -> i16 {
+    a += (a >> 15) & KYBER_Q as i16;
+    a -= KYBER_Q as i16;
+    a += (a >> 15) & KYBER_Q as i16;
+    a
+}
@@ -0,0 +1,235 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Replay detection for ciphertexts within a time window.
+//!
+//! This is an application-layer aid and does not change the KEM: a
+//! replayed ciphertext still decapsulates to the same shared secret. A
+//! protocol that must refuse replays checks each incoming ciphertext with
+//! [`ReplayFilter::check_and_insert`] before acting on it.
+//!
+//! The filter is a ring of `G` Bloom filters, each a generation of
+//! ciphertexts seen within `window / (G - 1)` time units, in caller-supplied
+//! storage so it works without an allocator. Ciphertexts are hashed with
+//! SipHash-2-4 under a random key, so an attacker cannot craft ciphertexts
+//! that collide in the filter. Time is whatever monotonic unit the caller
+//! passes as `now`, e.g. seconds.
+//!
+//! - A ciphertext seen at most `window` units ago is always reported as a
+//!   replay, unless it was evicted for capacity.
+//! - A ciphertext is forgotten once it is older than `window` plus one
+//!   generation.
+//! - A generation holding `capacity` ciphertexts is closed early. Under a
+//!   flood this evicts the oldest generation before its window ends, so
+//!   size `capacity` for the peak rate over a generation.
+//! - Being Bloom filters, a fresh ciphertext is reported as a replay with
+//!   small probability. Set `capacity` well below the number of bits per
+//!   generation, e.g. 16 bits per ciphertext for a rate around 1 in 2000.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::replay::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let (ct, _) = encapsulate(&keys.public, &mut rng)?;
+//!
+//! // 4 generations of 64 words, a 60 second window
+//! let mut storage = [0u64; 4 * 64];
+//! let mut filter = ReplayFilter::<4>::new(&mut storage, 60, 256, &mut rng)?;
+//! assert_eq!(filter.check_and_insert(&ct, 1000), Ok(()));
+//! assert_eq!(filter.check_and_insert(&ct, 1030), Err(Replay));
+//! assert_eq!(filter.check_and_insert(&ct, 1200), Ok(()));
+//! # Ok(()) }
+//! ```
+
+use crate::{error::KyberLibError, CryptoRng, RngCore};
+use core::fmt;
+use core::hash::Hasher;
+
+/// The error returned for a ciphertext seen within the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Replay;
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Ciphertext was already seen within the replay window"
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Replay {}
+
+/// Most hash functions applied per ciphertext.
+const MAX_HASHES: u64 = 16;
+
+/// A ring of `G` Bloom filters remembering recent ciphertexts.
+pub struct ReplayFilter<'a, const G: usize> {
+    storage: &'a mut [u64],
+    words: usize,
+    hashes: u64,
+    key: (u64, u64),
+    window: u64,
+    span: u64,
+    capacity: usize,
+    current: usize,
+    opened: u64,
+    counts: [usize; G],
+    newest: [u64; G],
+}
+
+impl<const G: usize> fmt::Debug for ReplayFilter<'_, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The SipHash key is not printed.
+        f.debug_struct("ReplayFilter")
+            .field("window", &self.window)
+            .field("capacity", &self.capacity)
+            .field("counts", &self.counts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, const G: usize> ReplayFilter<'a, G> {
+    /// Creates an empty filter over `storage`, split evenly between the
+    /// `G` generations, remembering ciphertexts for `window` time units
+    /// with at most `capacity` ciphertexts per generation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if `G` is less than 2 or
+    /// `window` or `capacity` is zero, `KyberLibError::InvalidLength` if
+    /// `storage` has fewer than `G` words and
+    /// `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    pub fn new<R>(
+        storage: &'a mut [u64],
+        window: u64,
+        capacity: usize,
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if G < 2 || window == 0 || capacity == 0 {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let words = storage.len() / G;
+        if words == 0 {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let mut key = [0u8; 16];
+        rng.try_fill_bytes(&mut key)
+            .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+        let mut k0 = [0u8; 8];
+        let mut k1 = [0u8; 8];
+        k0.copy_from_slice(&key[..8]);
+        k1.copy_from_slice(&key[8..]);
+
+        // k = m/n * ln 2 minimises the false positive rate.
+        let bits = words as u64 * 64;
+        let hashes =
+            (bits * 693 / 1000 / capacity as u64).clamp(1, MAX_HASHES);
+        storage.iter_mut().for_each(|w| *w = 0);
+        Ok(Self {
+            storage,
+            words,
+            hashes,
+            key: (u64::from_le_bytes(k0), u64::from_le_bytes(k1)),
+            window,
+            span: (window + G as u64 - 2) / (G as u64 - 1),
+            capacity,
+            current: 0,
+            opened: 0,
+            counts: [0; G],
+            newest: [0; G],
+        })
+    }
+
+    /// Records `ct` as seen at time `now`, or returns `Err(Replay)` if it
+    /// was already seen within the window.
+    ///
+    /// A replayed ciphertext is not recorded again, so its window is not
+    /// extended. `now` should not decrease between calls; if it does,
+    /// nothing expires until it catches up again.
+    pub fn check_and_insert(
+        &mut self,
+        ct: &[u8],
+        now: u64,
+    ) -> Result<(), Replay> {
+        self.advance(now);
+        let (a, b) = self.hash(ct);
+        if self.lookup(a, b, now) {
+            return Err(Replay);
+        }
+        let base = self.current * self.words;
+        let bits = self.words as u64 * 64;
+        for i in 0..self.hashes {
+            let bit = a.wrapping_add(i.wrapping_mul(b)) % bits;
+            self.storage[base + (bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.counts[self.current] += 1;
+        self.newest[self.current] = self.newest[self.current].max(now);
+        Ok(())
+    }
+
+    /// Whether `ct` would be reported as a replay at time `now`, without
+    /// recording it.
+    pub fn contains(&self, ct: &[u8], now: u64) -> bool {
+        let (a, b) = self.hash(ct);
+        self.lookup(a, b, now)
+    }
+
+    fn lookup(&self, a: u64, b: u64, now: u64) -> bool {
+        let bits = self.words as u64 * 64;
+        (0..G).filter(|&g| self.is_live(g, now)).any(|g| {
+            let words =
+                &self.storage[g * self.words..(g + 1) * self.words];
+            (0..self.hashes).all(|i| {
+                let bit = a.wrapping_add(i.wrapping_mul(b)) % bits;
+                words[(bit / 64) as usize] & (1 << (bit % 64)) != 0
+            })
+        })
+    }
+
+    fn is_live(&self, g: usize, now: u64) -> bool {
+        self.counts[g] > 0
+            && now.saturating_sub(self.newest[g]) <= self.window
+    }
+
+    fn advance(&mut self, now: u64) {
+        for g in 0..G {
+            if self.counts[g] > 0 && !self.is_live(g, now) {
+                self.clear(g);
+            }
+        }
+        if now >= self.opened.saturating_add(self.span)
+            || self.counts[self.current] >= self.capacity
+        {
+            self.current = (self.current + 1) % G;
+            self.clear(self.current);
+            self.opened = now;
+        }
+    }
+
+    fn clear(&mut self, g: usize) {
+        self.storage[g * self.words..(g + 1) * self.words]
+            .iter_mut()
+            .for_each(|w| *w = 0);
+        self.counts[g] = 0;
+        self.newest[g] = 0;
+    }
+
+    /// Two independent-enough hashes for double hashing.
+    #[allow(deprecated)] // core::hash::SipHasher is SipHash-2-4
+    fn hash(&self, ct: &[u8]) -> (u64, u64) {
+        let mut hasher = core::hash::SipHasher::new_with_keys(
+            self.key.0, self.key.1,
+        );
+        hasher.write(ct);
+        let h = hasher.finish();
+        (h, h.rotate_left(32) | 1)
+    }
+}
@@ -0,0 +1,513 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Polynomial-level primitives for experimenting with the scheme.
+//!
+//! Nothing in this module is needed to use Kyber, and the values it
+//! exposes are frequently secret. It exists so that researchers can vary
+//! parameters that the KEM fixes at compile time.
+
+extern crate alloc;
+
+use crate::{error::KyberLibError, params::*, poly::*, reduce::csubq};
+use alloc::string::String;
+use core::fmt::Write;
+
+/// A polynomial in `Z_q[X]/(X^256 + 1)`.
+#[derive(Clone, Copy, Default)]
+pub struct Polynomial {
+    pub(crate) poly: Poly,
+}
+
+impl core::fmt::Debug for Polynomial {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        // Coefficients may be secret, so they are not printed.
+        f.debug_struct("Polynomial").finish_non_exhaustive()
+    }
+}
+
+/// Read-only access to the coefficients, e.g. to hand them to a numeric
+/// library without a copy. Like [`Polynomial::coeffs`], this exposes
+/// secret values when the polynomial is a key or noise term.
+impl AsRef<[i16]> for Polynomial {
+    fn as_ref(&self) -> &[i16] {
+        &self.poly.coeffs
+    }
+}
+
+impl Polynomial {
+    /// Creates the zero polynomial.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a polynomial from its coefficients.
+    pub fn from_coeffs(coeffs: [i16; KYBER_N]) -> Self {
+        let mut poly = Poly::new();
+        poly.coeffs = coeffs;
+        // The caller may pass either domain.
+        set_domain!(poly, Any);
+        Self { poly }
+    }
+
+    /// Returns the coefficients.
+    pub fn coeffs(&self) -> &[i16; KYBER_N] {
+        &self.poly.coeffs
+    }
+
+    /// Moves every coefficient into the Montgomery domain, multiplying it
+    /// by `R = 2^16 mod q`.
+    ///
+    /// The result is congruent to `x * R` modulo `KYBER_Q` with every
+    /// coefficient in `-q+1..q`.
+    pub fn to_montgomery(&mut self) {
+        poly_tomont(&mut self.poly);
+    }
+
+    /// Moves every coefficient out of the Montgomery domain, multiplying it
+    /// by `R^-1`, the inverse of [`Polynomial::to_montgomery`].
+    ///
+    /// The result is congruent to `x * R^-1` modulo `KYBER_Q` with every
+    /// coefficient in `-q+1..q`.
+    pub fn from_montgomery(&mut self) {
+        poly_frommont(&mut self.poly);
+    }
+
+    /// Applies the forward NTT the KEM uses, leaving the coefficients in
+    /// bit-reversed order and Barrett-reduced.
+    pub fn ntt(&mut self) {
+        poly_ntt(&mut self.poly);
+    }
+
+    /// Applies the forward NTT through a recursive Cooley-Tukey
+    /// implementation instead of the iterative one.
+    ///
+    /// The output is identical to [`Polynomial::ntt`]; it exists to
+    /// validate the iterative twiddle indexing and to benchmark the two.
+    ///
+    /// ### Example
+    /// ```
+    /// # use kyberlib::research::*;
+    /// # use kyberlib::params::*;
+    /// let mut coeffs = [0i16; KYBER_N];
+    /// coeffs[1] = 1;
+    /// let mut a = Polynomial::from_coeffs(coeffs);
+    /// let mut b = a;
+    /// a.ntt();
+    /// b.ntt_recursive();
+    /// assert_eq!(a.coeffs(), b.coeffs());
+    /// ```
+    pub fn ntt_recursive(&mut self) {
+        crate::ntt::ntt_recursive(&mut self.poly.coeffs);
+        poly_reduce(&mut self.poly);
+    }
+
+    /// Applies the inverse NTT the KEM uses, taking bit-reversed input
+    /// to normal order and multiplying by the Montgomery factor `R`.
+    pub fn inv_ntt(&mut self) {
+        poly_invntt_tomont(&mut self.poly);
+    }
+
+    /// Serializes the polynomial to 12 bits per coefficient, as in
+    /// public and secret keys. Coefficients must be in `-q+1..q`.
+    pub fn to_bytes(&self) -> [u8; KYBER_POLY_BYTES] {
+        let mut r = [0u8; KYBER_POLY_BYTES];
+        poly_tobytes(&mut r, self.poly);
+        r
+    }
+
+    /// Deserializes a polynomial of 12-bit coefficients, the inverse of
+    /// [`Polynomial::to_bytes`]. Keys hold their polynomials in the NTT
+    /// domain, so this is how the secret vector of a secret key, or `t`
+    /// of a public key, is read back.
+    pub fn from_bytes(bytes: &[u8; KYBER_POLY_BYTES]) -> Self {
+        let mut poly = Poly::new();
+        poly_frombytes(&mut poly, bytes);
+        Self { poly }
+    }
+
+    /// Compresses the polynomial to `KYBER_DV` bits per coefficient, as
+    /// in the second ciphertext component. Coefficients must be in
+    /// `-q+1..q`.
+    pub fn compress(&self) -> [u8; KYBER_POLY_COMPRESSED_BYTES] {
+        let mut r = [0u8; KYBER_POLY_COMPRESSED_BYTES];
+        poly_compress(&mut r, self.poly);
+        r
+    }
+
+    /// Renders the polynomial in the usual notation, e.g.
+    /// `3 - 5x + 12x^255`.
+    ///
+    /// Coefficients are shown as centered representatives in
+    /// `-(q-1)/2..=(q-1)/2`, zero terms are omitted and the zero polynomial
+    /// renders as `0`. The output contains every coefficient, so it must
+    /// not be logged for secret polynomials.
+    ///
+    /// ### Example
+    /// ```
+    /// # use kyberlib::research::*;
+    /// # use kyberlib::params::*;
+    /// let mut coeffs = [0i16; KYBER_N];
+    /// coeffs[0] = 3;
+    /// coeffs[1] = KYBER_Q as i16 - 5;
+    /// coeffs[255] = 12;
+    /// let p = Polynomial::from_coeffs(coeffs);
+    /// assert_eq!(p.to_poly_string(), "3 - 5x + 12x^255");
+    /// ```
+    pub fn to_poly_string(&self) -> String {
+        let q = KYBER_Q as i32;
+        let mut out = String::new();
+        for (i, &c) in self.poly.coeffs.iter().enumerate() {
+            let mut c = (c as i32).rem_euclid(q);
+            if c > q / 2 {
+                c -= q;
+            }
+            if c == 0 {
+                continue;
+            }
+            match (out.is_empty(), c < 0) {
+                (true, false) => {}
+                (true, true) => out.push('-'),
+                (false, false) => out.push_str(" + "),
+                (false, true) => out.push_str(" - "),
+            }
+            let _ = match i {
+                0 => write!(out, "{}", c.abs()),
+                1 => write!(out, "{}x", c.abs()),
+                _ => write!(out, "{}x^{}", c.abs(), i),
+            };
+        }
+        if out.is_empty() {
+            out.push('0');
+        }
+        out
+    }
+}
+
+/// Samples a polynomial from a centered binomial distribution with
+/// parameter `eta`, deterministically from `seed` and `nonce`.
+///
+/// This generalises the sampler the KEM uses for its secret and error
+/// polynomials, which fixes `eta` to `KYBER_ETA1` or `KYBER_ETA2`. Only
+/// `eta` of 2 and 3 are supported, matching the available CBD samplers.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `seed` is not
+/// `KYBER_SYM_BYTES` long and `KyberLibError::InvalidInput` for any other
+/// `eta`.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::research::*;
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut r = Polynomial::new();
+/// poly_getnoise(&mut r, &[0u8; KYBER_SYM_BYTES], 0, 3)?;
+/// assert!(r.coeffs().iter().all(|c| c.abs() <= 3));
+/// # Ok(()) }
+/// ```
+pub fn poly_getnoise(
+    r: &mut Polynomial,
+    seed: &[u8],
+    nonce: u8,
+    eta: usize,
+) -> Result<(), KyberLibError> {
+    if seed.len() != KYBER_SYM_BYTES {
+        return Err(KyberLibError::InvalidLength);
+    }
+    crate::poly::poly_getnoise(&mut r.poly, seed, nonce, eta)
+}
+
+/// Samples entry `(i, j)` of the public matrix A, or of A^T with
+/// `transposed`, from the public seed, as key generation and encryption
+/// do.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `seed` is not
+/// `KYBER_SYM_BYTES` long and `KyberLibError::InvalidInput` if `i` or
+/// `j` is not below `KYBER_SECURITY_PARAMETER`.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::research::*;
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let seed = [0u8; KYBER_SYM_BYTES];
+/// let (mut a, mut at) = (Polynomial::new(), Polynomial::new());
+/// gen_matrix_entry(&mut a, &seed, 0, 1, false)?;
+/// gen_matrix_entry(&mut at, &seed, 1, 0, true)?;
+/// assert_eq!(a.coeffs(), at.coeffs());
+/// # Ok(()) }
+/// ```
+pub fn gen_matrix_entry(
+    r: &mut Polynomial,
+    seed: &[u8],
+    i: usize,
+    j: usize,
+    transposed: bool,
+) -> Result<(), KyberLibError> {
+    use crate::indcpa::GEN_MATRIX_BUFBYTES;
+    if seed.len() != KYBER_SYM_BYTES {
+        return Err(KyberLibError::InvalidLength);
+    }
+    if i >= KYBER_SECURITY_PARAMETER || j >= KYBER_SECURITY_PARAMETER {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let [x, y] = crate::symmetric::matrix_index_bytes(i, j, transposed);
+    let mut buf = [0u8; GEN_MATRIX_BUFBYTES];
+    crate::indcpa::gen_matrix_entry(&mut r.poly, &mut buf, seed, x, y);
+    Ok(())
+}
+
+/// Samples the whole public matrix A, or A^T with `transposed`, from the
+/// public seed, indexed by row and then column. The seed of a public key
+/// is returned by [`MatrixSeed::matrix_seed`](crate::MatrixSeed).
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidLength` if `seed` is not
+/// `KYBER_SYM_BYTES` long.
+pub fn gen_matrix(
+    seed: &[u8],
+    transposed: bool,
+) -> Result<
+    [[Polynomial; KYBER_SECURITY_PARAMETER]; KYBER_SECURITY_PARAMETER],
+    KyberLibError,
+> {
+    let mut a = [[Polynomial::new(); KYBER_SECURITY_PARAMETER];
+        KYBER_SECURITY_PARAMETER];
+    for (i, row) in a.iter_mut().enumerate() {
+        for (j, entry) in row.iter_mut().enumerate() {
+            gen_matrix_entry(entry, seed, i, j, transposed)?;
+        }
+    }
+    Ok(a)
+}
+
+/// Lifts every coefficient of a vector of polynomials to its standard
+/// representative in `0..q`.
+///
+/// This is the centering pass the decoder applies before turning a
+/// polynomial into message bits. Coefficients have to lie in
+/// `-q+1..2q`, the range left by Barrett reduction and a single
+/// addition or subtraction.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::research::*;
+/// # use kyberlib::params::*;
+/// let q = KYBER_Q as i16;
+/// let mut v = [Polynomial::from_coeffs([q + 1; KYBER_N]);
+///     KYBER_SECURITY_PARAMETER];
+/// polyvec_csubq(&mut v);
+/// assert!(v.iter().all(|p| p.coeffs().iter().all(|&c| c == 1)));
+/// ```
+pub fn polyvec_csubq(v: &mut [Polynomial; KYBER_SECURITY_PARAMETER]) {
+    for p in v.iter_mut() {
+        for c in p.poly.coeffs.iter_mut() {
+            *c = csubq(*c);
+        }
+    }
+}
+
+/// Multiplies the polynomials of `a` and `b` pointwise in the NTT domain
+/// and sums the products, the inner product of the matrix-vector products
+/// in key generation, encryption and decryption.
+///
+/// The products are accumulated in 32-bit lanes and reduced once per
+/// coefficient, like the KEM does. The result is Barrett-reduced.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::research::*;
+/// # use kyberlib::params::*;
+/// # fn main() -> Result<(), kyberlib::KyberLibError> {
+/// let a = [Polynomial::from_coeffs([3; KYBER_N]); 3];
+/// let b = [Polynomial::from_coeffs([-7; KYBER_N]); 3];
+/// assert_eq!(
+///     basemul_acc(&a, &b)?.coeffs(),
+///     basemul_acc_per_term(&a, &b)?.coeffs()
+/// );
+/// # Ok(()) }
+/// ```
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if `a` and `b` differ in length
+/// or hold more than 4 polynomials, if a coefficient of `a` is not below
+/// 2^12 in absolute value or if one of `b` is not in `-q+1..q`.
+pub fn basemul_acc(
+    a: &[Polynomial],
+    b: &[Polynomial],
+) -> Result<Polynomial, KyberLibError> {
+    check_basemul_acc_inputs(a, b)?;
+    let mut r = Polynomial::new();
+    crate::polyvec::basemul_acc_lazy(
+        &mut r.poly,
+        a.iter().zip(b).map(|(a, b)| (&a.poly, &b.poly)),
+    );
+    Ok(r)
+}
+
+/// Computes [`basemul_acc`] by reducing every product before adding it,
+/// as the KEM did before its accumulate became lazy.
+///
+/// The output is identical; it exists to validate the lazy accumulate and
+/// to benchmark the two.
+///
+/// # Errors
+///
+/// The inputs are checked as by [`basemul_acc`].
+pub fn basemul_acc_per_term(
+    a: &[Polynomial],
+    b: &[Polynomial],
+) -> Result<Polynomial, KyberLibError> {
+    check_basemul_acc_inputs(a, b)?;
+    let mut r = Polynomial::new();
+    crate::polyvec::basemul_acc_per_term(
+        &mut r.poly,
+        a.iter().zip(b).map(|(a, b)| (&a.poly, &b.poly)),
+    );
+    Ok(r)
+}
+
+// The bounds under which neither accumulate overflows.
+fn check_basemul_acc_inputs(
+    a: &[Polynomial],
+    b: &[Polynomial],
+) -> Result<(), KyberLibError> {
+    let q = KYBER_Q as i16;
+    let in_range = |v: &[Polynomial], bound: i16| {
+        v.iter().all(|p| {
+            p.coeffs().iter().all(|&c| -bound < c && c < bound)
+        })
+    };
+    if a.len() != b.len()
+        || a.len() > 4
+        || !in_range(a, 1 << 12)
+        || !in_range(b, q)
+    {
+        return Err(KyberLibError::InvalidInput);
+    }
+    Ok(())
+}
+
+/// Decapsulates `ct` with `sk` as if decryption had failed.
+///
+/// A bit of the decoded message is flipped before re-encryption, so the
+/// ciphertext comparison always fails and the result is the implicit
+/// rejection key `KDF(z || H(ct))` rather than the shared secret. Real
+/// decryption failures are far too rare to hit in a test; this lets the
+/// implicit rejection paths of higher-level protocols be exercised
+/// deterministically. Never use it outside tests.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::research::*;
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+/// assert_ne!(decapsulate_force_failure(&ct, &keys.secret)?, ss);
+/// # Ok(()) }
+/// ```
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if either input has the wrong
+/// length.
+pub fn decapsulate_force_failure(
+    ct: &[u8],
+    sk: &[u8],
+) -> crate::Decapsulated {
+    if ct.len() != KYBER_CIPHERTEXT_BYTES
+        || sk.len() != KYBER_SECRET_KEY_BYTES
+    {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    crate::kem::decrypt_message_force_failure(&mut ss, ct, sk);
+    Ok(ss)
+}
+
+/// Encapsulates to `pk` like [`encapsulate`](crate::encapsulate) and also
+/// returns the IND-CPA message `m` that was encrypted.
+///
+/// `m` determines the shared secret, so it must stay as secret as the
+/// secret itself. Together with [`decapsulate_debug`] it lets the decoding
+/// step of an independent decapsulator, such as a hardware one, be checked
+/// apart from the hashing around it.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if `pk` has the wrong length and
+/// `KyberLibError::RandomBytesGeneration` if the RNG fails.
+pub fn encapsulate_export_m<R>(
+    pk: &[u8],
+    rng: &mut R,
+) -> Result<
+    (
+        [u8; KYBER_CIPHERTEXT_BYTES],
+        crate::SharedSecret,
+        [u8; KYBER_SYM_BYTES],
+    ),
+    KyberLibError,
+>
+where
+    R: crate::RngCore + crate::CryptoRng,
+{
+    if pk.len() != KYBER_PUBLIC_KEY_BYTES {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let m = crate::kem::encrypt_message_export_m(
+        &mut ct, &mut ss, pk, rng,
+    )?;
+    Ok((ct, ss, m))
+}
+
+/// Decapsulates `ct` with `sk` like [`decapsulate`](crate::decapsulate)
+/// and also returns the message `m'` recovered by the IND-CPA decryption.
+///
+/// For a valid ciphertext `m'` is the `m` it was encrypted from; for an
+/// invalid one it is whatever the decoder produced before implicit
+/// rejection replaced the shared secret. `m'` is as sensitive as the
+/// shared secret. Never use it outside tests.
+///
+/// ### Example
+/// ```
+/// # use kyberlib::research::*;
+/// # use kyberlib::*;
+/// # fn main() -> Result<(), KyberLibError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng)?;
+/// let (ct, ss, m) = encapsulate_export_m(&keys.public, &mut rng)?;
+/// assert_eq!(decapsulate_debug(&ct, &keys.secret)?, (ss, m));
+/// # Ok(()) }
+/// ```
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if either input has the wrong
+/// length.
+pub fn decapsulate_debug(
+    ct: &[u8],
+    sk: &[u8],
+) -> Result<(crate::SharedSecret, [u8; KYBER_SYM_BYTES]), KyberLibError>
+{
+    if ct.len() != KYBER_CIPHERTEXT_BYTES
+        || sk.len() != KYBER_SECRET_KEY_BYTES
+    {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let m = crate::kem::decrypt_message_debug(&mut ss, ct, sk);
+    Ok((ss, m))
+}
@@ -2,8 +2,46 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use crate::KyberLibError;
+#[cfg(feature = "custom-entropy")]
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use rand_core::{CryptoRng, RngCore};
 
+/// Default number of attempts [`RetryRng`] makes to fill a buffer.
+pub const DEFAULT_RANDOM_BYTES_ATTEMPTS: usize = 3;
+
+static RANDOM_BYTES_ATTEMPTS: AtomicUsize =
+    AtomicUsize::new(DEFAULT_RANDOM_BYTES_ATTEMPTS);
+
+/// Sets how many attempts [`RetryRng`], and so the functions drawing from
+/// the operating system RNG on their own, such as `Keypair::random` and
+/// the `wasm`, `ffi` and `capi` bindings, make before returning
+/// `KyberLibError::RandomBytesGeneration`. Values below 1 are treated as 1.
+///
+/// The setting is process-wide. Retrying helps with entropy sources that
+/// fail transiently, such as `getrandom` early in boot on some virtualized
+/// platforms. An RNG passed in by the caller is only retried if it is
+/// wrapped in a [`RetryRng`].
+///
+/// # Examples
+///
+/// ```
+/// # use kyberlib::rng::*;
+/// set_random_bytes_attempts(5);
+/// assert_eq!(random_bytes_attempts(), 5);
+/// set_random_bytes_attempts(0);
+/// assert_eq!(random_bytes_attempts(), 1);
+/// # set_random_bytes_attempts(DEFAULT_RANDOM_BYTES_ATTEMPTS);
+/// ```
+pub fn set_random_bytes_attempts(attempts: usize) {
+    RANDOM_BYTES_ATTEMPTS.store(attempts.max(1), Ordering::Relaxed);
+}
+
+/// The number of attempts [`RetryRng`] makes to fill a buffer.
+pub fn random_bytes_attempts() -> usize {
+    RANDOM_BYTES_ATTEMPTS.load(Ordering::Relaxed)
+}
+
 /// Fills a buffer `x` with `len` bytes of random data.
 ///
 /// This function uses a random number generator (RNG) that satisfies both
@@ -18,8 +56,9 @@ use rand_core::{CryptoRng, RngCore};
 ///
 /// # Errors
 ///
-/// If the RNG fails to generate the required number of bytes, an error
-/// of type `KyberLibError::RandomBytesGeneration` is returned.
+/// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails. The
+/// RNG is asked once; wrap it in a [`RetryRng`] to retry a source that
+/// fails transiently.
 ///
 /// # Examples
 ///
@@ -51,3 +90,248 @@ where
     rng.try_fill_bytes(&mut x[..len])
         .map_err(|_| KyberLibError::RandomBytesGeneration)
 }
+
+/// An RNG retrying a failing `R` up to [`random_bytes_attempts`] times in
+/// total, with the `std` feature after an exponential backoff starting at
+/// 1 ms.
+///
+/// The functions drawing from the operating system RNG on their own wrap
+/// it in this; pass one to any function taking an RNG to retry another
+/// source the same way.
+///
+/// # Examples
+///
+/// ```
+/// # use kyberlib::rng::RetryRng;
+/// let keys = kyberlib::keypair(&mut RetryRng(rand_core::OsRng))?;
+/// # Ok::<(), kyberlib::KyberLibError>(())
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RetryRng<R>(pub R);
+
+impl<R: RngCore> RngCore for RetryRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(err) = self.try_fill_bytes(dest) {
+            panic!("RNG failed after every attempt: {}", err);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        let attempts = random_bytes_attempts();
+        let mut attempt = 0;
+        loop {
+            match self.0.try_fill_bytes(dest) {
+                Err(_) if attempt + 1 < attempts => backoff(attempt),
+                result => return result,
+            }
+            attempt += 1;
+        }
+    }
+}
+
+impl<R: CryptoRng> CryptoRng for RetryRng<R> {}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+fn backoff(attempt: usize) {
+    let millis = 1u64 << attempt.min(10);
+    std::thread::sleep(std::time::Duration::from_millis(millis));
+}
+
+// Without std there is no clock to wait on, so retry straight away.
+#[cfg(not(all(feature = "std", not(target_arch = "wasm32"))))]
+fn backoff(_attempt: usize) {}
+
+/// A function filling its buffer with entropy from the host, see
+/// [`register_entropy_source`].
+#[cfg(feature = "custom-entropy")]
+pub type EntropySource = fn(&mut [u8]) -> Result<(), KyberLibError>;
+
+#[cfg(feature = "custom-entropy")]
+static ENTROPY_SOURCE: AtomicPtr<()> =
+    AtomicPtr::new(core::ptr::null_mut());
+
+/// Registers the entropy source [`HostRng`] draws from.
+///
+/// For targets without an operating system RNG, such as
+/// `wasm32-unknown-unknown` in a plugin runtime or a blockchain VM, whose
+/// host exposes its own entropy import. The source can be registered only
+/// once per process, so a later caller cannot swap it out from under the
+/// first.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if a source is already
+/// registered.
+///
+/// # Examples
+///
+/// ```
+/// # use kyberlib::rng::*;
+/// # use kyberlib::KyberLibError;
+/// fn host_entropy(buf: &mut [u8]) -> Result<(), KyberLibError> {
+///     // Ask the host, e.g. through an imported function.
+/// #   use rand_core::RngCore;
+/// #   rand_core::OsRng
+/// #       .try_fill_bytes(buf)
+/// #       .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+///     Ok(())
+/// }
+///
+/// register_entropy_source(host_entropy)?;
+/// let keys = kyberlib::keypair(&mut HostRng)?;
+/// # Ok::<(), KyberLibError>(())
+/// ```
+#[cfg(feature = "custom-entropy")]
+pub fn register_entropy_source(
+    source: EntropySource,
+) -> Result<(), KyberLibError> {
+    ENTROPY_SOURCE
+        .compare_exchange(
+            core::ptr::null_mut(),
+            source as *mut (),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .map(|_| ())
+        .map_err(|_| KyberLibError::InvalidInput)
+}
+
+#[cfg(feature = "custom-entropy")]
+fn entropy_source() -> Option<EntropySource> {
+    let source = ENTROPY_SOURCE.load(Ordering::Acquire);
+    if source.is_null() {
+        return None;
+    }
+    // SAFETY: the only non-null value ever stored is an `EntropySource`
+    // cast to a pointer in `register_entropy_source`, and function
+    // pointers round-trip through data pointers on every target Rust
+    // supports.
+    Some(unsafe {
+        core::mem::transmute::<*mut (), EntropySource>(source)
+    })
+}
+
+/// An RNG drawing from the source registered with
+/// [`register_entropy_source`].
+///
+/// Fails with `KyberLibError::RandomBytesGeneration` from every function
+/// taking an RNG while no source is registered, or when the source fails.
+/// The host is trusted to provide cryptographically secure bytes.
+#[cfg(feature = "custom-entropy")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HostRng;
+
+#[cfg(feature = "custom-entropy")]
+impl RngCore for HostRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(err) = self.try_fill_bytes(dest) {
+            panic!("host entropy source failed: {}", err);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        let source =
+            entropy_source().ok_or(HOST_ENTROPY_UNAVAILABLE)?;
+        source(dest).map_err(|_| HOST_ENTROPY_FAILED.into())
+    }
+}
+
+#[cfg(feature = "custom-entropy")]
+impl CryptoRng for HostRng {}
+
+#[cfg(feature = "custom-entropy")]
+const HOST_ENTROPY_UNAVAILABLE: core::num::NonZeroU32 =
+    match core::num::NonZeroU32::new(rand_core::Error::CUSTOM_START) {
+        Some(code) => code,
+        None => unreachable!(),
+    };
+
+#[cfg(feature = "custom-entropy")]
+const HOST_ENTROPY_FAILED: core::num::NonZeroU32 =
+    match core::num::NonZeroU32::new(rand_core::Error::CUSTOM_START + 1)
+    {
+        Some(code) => code,
+        None => unreachable!(),
+    };
+
+/// Adapts an RNG implementing the rand_core 0.9 traits to the rand_core
+/// 0.6 ones every function of this crate takes.
+///
+/// Wraps any `TryCryptoRng`, which covers both the infallible
+/// `CryptoRng` generators of rand 0.9 and fallible sources such as its
+/// `OsRng`. A failure of the wrapped RNG surfaces as a rand_core 0.6
+/// error, so it is reported as `KyberLibError::RandomBytesGeneration`,
+/// and retried inside a [`RetryRng`], like the failure of any other RNG.
+///
+/// # Examples
+///
+/// ```
+/// # use kyberlib::rng::Rand09;
+/// # use rand_core_09::SeedableRng;
+/// let mut rng = Rand09(rand_chacha_09::ChaCha20Rng::from_seed([7; 32]));
+/// let keys = kyberlib::keypair(&mut rng)?;
+/// # Ok::<(), kyberlib::KyberLibError>(())
+/// ```
+#[cfg(feature = "rand09")]
+#[derive(Clone, Debug, Default)]
+pub struct Rand09<R>(pub R);
+
+#[cfg(feature = "rand09")]
+impl<R: rand_core_09::TryCryptoRng> RngCore for Rand09<R> {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(err) = self.try_fill_bytes(dest) {
+            panic!("rand_core 0.9 RNG failed: {}", err);
+        }
+    }
+
+    // The error types of the two versions do not convert into each
+    // other, and without `std` a rand_core 0.6 error holds only a code.
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        rand_core_09::TryRngCore::try_fill_bytes(&mut self.0, dest)
+            .map_err(|_| RAND09_FAILED.into())
+    }
+}
+
+#[cfg(feature = "rand09")]
+impl<R: rand_core_09::TryCryptoRng> CryptoRng for Rand09<R> {}
+
+#[cfg(feature = "rand09")]
+const RAND09_FAILED: core::num::NonZeroU32 =
+    match core::num::NonZeroU32::new(rand_core::Error::CUSTOM_START + 2)
+    {
+        Some(code) => code,
+        None => unreachable!(),
+    };
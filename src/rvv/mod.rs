@@ -0,0 +1,160 @@
+//! RISC-V vector (RVV 1.0) implementation of the IND-CPA layer for
+//! riscv64, selected at run time.
+//!
+//! The NTT, inverse NTT, base multiplication, Barrett reduction and the
+//! 12-bit (de)serialization of polynomials are inline assembly on the V
+//! extension; matrix sampling, noise sampling and (de)compression are
+//! shared with the reference implementation. The kernels are
+//! vector-length agnostic, so the same code runs on any VLEN, and keep
+//! the coefficients in the standard order of the reference: keys,
+//! ciphertexts and messages are identical bit for bit.
+//!
+//! The `v` target feature is still unstable, so the backend does not
+//! rely on it: the kernels enable the extension for the assembler only
+//! and the first call asks the kernel whether the harts have it, through
+//! the `riscv_hwprobe` system call or, on kernels older than 6.4, the `V`
+//! bit of `AT_HWCAP`. Without it the `acceleration` module keeps the
+//! reference implementation. Every function of the `indcpa` and `poly`
+//! submodules that runs vector instructions is unsafe to call before
+//! [`supported`] returns true.
+
+pub(crate) mod indcpa;
+mod poly;
+
+mod detect {
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    // 0 until probed, then 1 without and 2 with the V extension.
+    static SUPPORTED: AtomicU8 = AtomicU8::new(0);
+
+    pub(super) fn supported() -> bool {
+        match SUPPORTED.load(Ordering::Relaxed) {
+            0 => {
+                let v = probe();
+                SUPPORTED.store(1 + u8::from(v), Ordering::Relaxed);
+                v
+            }
+            s => s == 2,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn probe() -> bool {
+        // struct riscv_hwprobe and the constants of asm/hwprobe.h
+        #[repr(C)]
+        struct Pair {
+            key: i64,
+            value: u64,
+        }
+        const SYS_RISCV_HWPROBE: libc::c_long = 258;
+        const KEY_IMA_EXT_0: i64 = 4;
+        const IMA_V: u64 = 1 << 2;
+
+        let mut pair = Pair {
+            key: KEY_IMA_EXT_0,
+            value: 0,
+        };
+        // SAFETY: one pair to fill in, for all harts (an empty cpu set),
+        // without flags.
+        let ret = unsafe {
+            libc::syscall(
+                SYS_RISCV_HWPROBE,
+                core::ptr::addr_of_mut!(pair),
+                1usize,
+                0usize,
+                core::ptr::null_mut::<libc::c_ulong>(),
+                0u32,
+            )
+        };
+        if ret == 0 && pair.key == KEY_IMA_EXT_0 {
+            return pair.value & IMA_V != 0;
+        }
+        // Before hwprobe the kernel reported the single-letter
+        // extensions, V among them, as bits of AT_HWCAP.
+        // SAFETY: getauxval has no preconditions.
+        let hwcap = unsafe { libc::getauxval(libc::AT_HWCAP) };
+        hwcap & (1 << (b'V' - b'A')) != 0
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn probe() -> bool {
+        cfg!(target_feature = "v")
+    }
+}
+
+/// Whether the harts have the V extension the backend needs.
+pub(crate) fn supported() -> bool {
+    detect::supported()
+}
+
+// Run under qemu-riscv64 with the V extension, see the README. On harts
+// without it the tests have nothing to compare and pass trivially.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::*;
+    use crate::reference;
+    use crate::simd::differential::{self, random_poly};
+
+    #[test]
+    fn ntt_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        differential::ntt(
+            |r| unsafe { poly::poly_ntt(r) },
+            |r| unsafe { poly::poly_invntt_tomont(r) },
+        );
+    }
+
+    #[test]
+    fn reduce_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        for _ in 0..64 {
+            let mut r = random_poly(8 * KYBER_Q as i16);
+            let mut ref_r = r;
+            unsafe { poly::poly_reduce(&mut r) };
+            reference::poly::poly_reduce(&mut ref_r);
+            assert_eq!(r.coeffs, ref_r.coeffs);
+        }
+    }
+
+    #[test]
+    fn basemul_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        differential::basemul(|r, a, b| unsafe {
+            poly::polyvec_basemul_acc_montgomery(r, a, b)
+        });
+    }
+
+    #[test]
+    fn serialization_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        differential::serialization(
+            |bytes, r| unsafe { poly::poly_tobytes(bytes, r) },
+            |r, bytes| unsafe { poly::poly_frombytes(r, bytes) },
+        );
+    }
+
+    #[test]
+    fn indcpa_matches_reference() {
+        if !detect::supported() {
+            return;
+        }
+        differential::indcpa(
+            |pk, sk, seed, rng| unsafe {
+                indcpa::indcpa_keypair(pk, sk, seed, rng)
+            },
+            |c, m, pk, coins| unsafe {
+                indcpa::indcpa_enc(c, m, pk, coins)
+            },
+            |m, c, sk| unsafe { indcpa::indcpa_dec(m, c, sk) },
+        );
+    }
+}
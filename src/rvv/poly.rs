@@ -0,0 +1,762 @@
+use crate::acceleration::{count_ntt, Acceleration};
+use crate::reference::ntt::ZETAS;
+use crate::{params::*, poly::Poly, polyvec::Polyvec};
+use core::arch::asm;
+
+const Q: i16 = KYBER_Q as i16;
+const QINV: i16 = -3327; // q^(-1) mod 2^16
+const V: i16 = 20159; // ((1 << 26) + q/2) / q
+const F: i16 = 1441; // mont^2/128
+
+// The zetas of the inverse NTT in the order its blocks use them: the
+// layer with len 2 takes the first 64, the one with len 4 the next 32.
+static INV_ZETAS: [i16; 128] = {
+    let mut zetas = [0i16; 128];
+    let mut i = 0;
+    while i < 128 {
+        zetas[i] = ZETAS[127 - i];
+        i += 1;
+    }
+    zetas
+};
+
+// The zeta of each pair of coefficients in the NTT domain: the basemul
+// of pairs 2i and 2i + 1 reduces modulo X^2 - zeta and X^2 + zeta.
+static BASEMUL_ZETAS: [i16; KYBER_N / 2] = {
+    let mut zetas = [0i16; KYBER_N / 2];
+    let mut i = 0;
+    while i < KYBER_N / 4 {
+        zetas[2 * i] = ZETAS[64 + i];
+        zetas[2 * i + 1] = -ZETAS[64 + i];
+        i += 1;
+    }
+    zetas
+};
+
+// The kernels are strip-mined loops that ask vsetvli for as many lanes
+// as the hardware has, so they run on any VLEN.
+
+// asm! with the V extension enabled for the assembler only, as the
+// target feature is unstable, and every vector register clobbered. The
+// template strings go in brackets, the operands after them.
+macro_rules! asm_v {
+    ([$($template:tt)*], $($operands:tt)*) => {
+        asm!(
+            ".option push",
+            ".option arch, +v",
+            $($template)*
+            ".option pop",
+            $($operands)*
+            out("v0") _, out("v1") _, out("v2") _, out("v3") _,
+            out("v4") _, out("v5") _, out("v6") _, out("v7") _,
+            out("v8") _, out("v9") _, out("v10") _, out("v11") _,
+            out("v12") _, out("v13") _, out("v14") _, out("v15") _,
+            out("v16") _, out("v17") _, out("v18") _, out("v19") _,
+            out("v20") _, out("v21") _, out("v22") _, out("v23") _,
+            out("v24") _, out("v25") _, out("v26") _, out("v27") _,
+            out("v28") _, out("v29") _, out("v30") _, out("v31") _,
+            options(nostack),
+        )
+    };
+}
+
+// d = fqmul(a, b) lane by lane, with b a vector; t is scratch and must
+// differ from the others, d may be a or b.
+macro_rules! fqmul_vv {
+    ($d:literal, $a:literal, $b:literal, $t:literal) => {
+        concat!(
+            concat!("vmul.vv ", $t, ", ", $a, ", ", $b, "\n"),
+            concat!("vmulh.vv ", $d, ", ", $a, ", ", $b, "\n"),
+            concat!("vmul.vx ", $t, ", ", $t, ", {qinv}\n"),
+            concat!("vmulh.vx ", $t, ", ", $t, ", {q}\n"),
+            concat!("vsub.vv ", $d, ", ", $d, ", ", $t, "\n"),
+        )
+    };
+}
+
+// d = fqmul(a, b) lane by lane, with b a scalar register.
+macro_rules! fqmul_vx {
+    ($d:literal, $a:literal, $b:literal, $t:literal) => {
+        concat!(
+            concat!("vmul.vx ", $t, ", ", $a, ", ", $b, "\n"),
+            concat!("vmulh.vx ", $d, ", ", $a, ", ", $b, "\n"),
+            concat!("vmul.vx ", $t, ", ", $t, ", {qinv}\n"),
+            concat!("vmulh.vx ", $t, ", ", $t, ", {q}\n"),
+            concat!("vsub.vv ", $d, ", ", $d, ", ", $t, "\n"),
+        )
+    };
+}
+
+// a = barrett_reduce(a) lane by lane; t is scratch. The rounding of the
+// reference, (v * a + 2^25) >> 26, is the high half of v * a plus 2^9,
+// shifted by 10.
+macro_rules! barrett {
+    ($a:literal, $t:literal) => {
+        concat!(
+            concat!("vmulh.vx ", $t, ", ", $a, ", {v}\n"),
+            concat!("vadd.vx ", $t, ", ", $t, ", {half}\n"),
+            concat!("vsra.vi ", $t, ", ", $t, ", 10\n"),
+            concat!("vmul.vx ", $t, ", ", $t, ", {q}\n"),
+            concat!("vsub.vv ", $a, ", ", $a, ", ", $t, "\n"),
+        )
+    };
+}
+
+/// Name:  butterflies
+///
+/// Description: n Cooley-Tukey butterflies of the forward NTT between
+///  lo[j] and hi[j], all with the same zeta
+///
+/// Arguments:   - *mut i16 lo: first operands, n coefficients
+///  - *mut i16 hi: second operands, n coefficients
+///  - usize n: number of butterflies, not zero
+///  - i16 zeta: twiddle factor in Montgomery form
+unsafe fn butterflies(lo: *mut i16, hi: *mut i16, n: usize, zeta: i16) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m4, ta, ma",
+            "vle16.v v0, ({lo})",
+            "vle16.v v4, ({hi})",
+            fqmul_vx!("v8", "v4", "{zeta}", "v12"),
+            "vsub.vv v4, v0, v8",
+            "vadd.vv v0, v0, v8",
+            "vse16.v v0, ({lo})",
+            "vse16.v v4, ({hi})",
+            "sub {n}, {n}, {vl}",
+            "slli {vl}, {vl}, 1",
+            "add {lo}, {lo}, {vl}",
+            "add {hi}, {hi}, {vl}",
+            "bnez {n}, 2b",
+        ],
+        lo = inout(reg) lo => _,
+        hi = inout(reg) hi => _,
+        n = inout(reg) n => _,
+        vl = out(reg) _,
+        zeta = in(reg) zeta,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+    );
+}
+
+/// Name:  inv_butterflies
+///
+/// Description: n Gentleman-Sande butterflies of the inverse NTT between
+///  lo[j] and hi[j], all with the same zeta: lo[j] becomes the Barrett
+///  reduced sum and hi[j] the difference hi[j] - lo[j] times zeta
+///
+/// Arguments:   - *mut i16 lo: first operands, n coefficients
+///  - *mut i16 hi: second operands, n coefficients
+///  - usize n: number of butterflies, not zero
+///  - i16 zeta: twiddle factor in Montgomery form
+unsafe fn inv_butterflies(
+    lo: *mut i16,
+    hi: *mut i16,
+    n: usize,
+    zeta: i16,
+) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m4, ta, ma",
+            "vle16.v v0, ({lo})",
+            "vle16.v v4, ({hi})",
+            "vsub.vv v8, v4, v0",
+            "vadd.vv v0, v0, v4",
+            barrett!("v0", "v12"),
+            fqmul_vx!("v4", "v8", "{zeta}", "v12"),
+            "vse16.v v0, ({lo})",
+            "vse16.v v4, ({hi})",
+            "sub {n}, {n}, {vl}",
+            "slli {vl}, {vl}, 1",
+            "add {lo}, {lo}, {vl}",
+            "add {hi}, {hi}, {vl}",
+            "bnez {n}, 2b",
+        ],
+        lo = inout(reg) lo => _,
+        hi = inout(reg) hi => _,
+        n = inout(reg) n => _,
+        vl = out(reg) _,
+        zeta = in(reg) zeta,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+        v = in(reg) V,
+        half = in(reg) 512i16,
+    );
+}
+
+/// Name:  ntt_len4
+///
+/// Description: The layer of the forward NTT with len 4. A segment load
+///  of 8 fields puts coefficient m of every block of 8 into field m, so
+///  that fields 0 to 3 hold the first operands of the butterflies and
+///  fields 4 to 7 the second; block b uses ZETAS[32 + b]
+///
+/// Arguments:   - *mut i16 r: in/output polynomial
+unsafe fn ntt_len4(r: *mut i16) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m1, ta, ma",
+            "vlseg8e16.v v8, ({r})",
+            "vle16.v v16, ({z})",
+            fqmul_vv!("v18", "v12", "v16", "v17"),
+            "vsub.vv v12, v8, v18",
+            "vadd.vv v8, v8, v18",
+            fqmul_vv!("v18", "v13", "v16", "v17"),
+            "vsub.vv v13, v9, v18",
+            "vadd.vv v9, v9, v18",
+            fqmul_vv!("v18", "v14", "v16", "v17"),
+            "vsub.vv v14, v10, v18",
+            "vadd.vv v10, v10, v18",
+            fqmul_vv!("v18", "v15", "v16", "v17"),
+            "vsub.vv v15, v11, v18",
+            "vadd.vv v11, v11, v18",
+            "vsseg8e16.v v8, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 4",
+            "add {r}, {r}, {t}",
+            "slli {t}, {vl}, 1",
+            "add {z}, {z}, {t}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r => _,
+        z = inout(reg) ZETAS[32..].as_ptr() => _,
+        n = inout(reg) KYBER_N / 8 => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+    );
+}
+
+/// Name:  ntt_len2
+///
+/// Description: The layer of the forward NTT with len 2, on a segment
+///  load of 4 fields; block b uses ZETAS[64 + b]
+///
+/// Arguments:   - *mut i16 r: in/output polynomial
+unsafe fn ntt_len2(r: *mut i16) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m1, ta, ma",
+            "vlseg4e16.v v8, ({r})",
+            "vle16.v v16, ({z})",
+            fqmul_vv!("v18", "v10", "v16", "v17"),
+            "vsub.vv v10, v8, v18",
+            "vadd.vv v8, v8, v18",
+            fqmul_vv!("v18", "v11", "v16", "v17"),
+            "vsub.vv v11, v9, v18",
+            "vadd.vv v9, v9, v18",
+            "vsseg4e16.v v8, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 3",
+            "add {r}, {r}, {t}",
+            "slli {t}, {vl}, 1",
+            "add {z}, {z}, {t}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r => _,
+        z = inout(reg) ZETAS[64..].as_ptr() => _,
+        n = inout(reg) KYBER_N / 4 => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+    );
+}
+
+/// Name:  invntt_len2
+///
+/// Description: The layer of the inverse NTT with len 2, on a segment
+///  load of 4 fields; block b uses ZETAS[127 - b]
+///
+/// Arguments:   - *mut i16 r: in/output polynomial
+unsafe fn invntt_len2(r: *mut i16) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m1, ta, ma",
+            "vlseg4e16.v v8, ({r})",
+            "vle16.v v16, ({z})",
+            "vsub.vv v18, v10, v8",
+            "vadd.vv v8, v8, v10",
+            barrett!("v8", "v17"),
+            fqmul_vv!("v10", "v18", "v16", "v17"),
+            "vsub.vv v18, v11, v9",
+            "vadd.vv v9, v9, v11",
+            barrett!("v9", "v17"),
+            fqmul_vv!("v11", "v18", "v16", "v17"),
+            "vsseg4e16.v v8, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 3",
+            "add {r}, {r}, {t}",
+            "slli {t}, {vl}, 1",
+            "add {z}, {z}, {t}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r => _,
+        z = inout(reg) INV_ZETAS.as_ptr() => _,
+        n = inout(reg) KYBER_N / 4 => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+        v = in(reg) V,
+        half = in(reg) 512i16,
+    );
+}
+
+/// Name:  invntt_len4
+///
+/// Description: The layer of the inverse NTT with len 4, on a segment
+///  load of 8 fields; block b uses ZETAS[63 - b]
+///
+/// Arguments:   - *mut i16 r: in/output polynomial
+unsafe fn invntt_len4(r: *mut i16) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m1, ta, ma",
+            "vlseg8e16.v v8, ({r})",
+            "vle16.v v16, ({z})",
+            "vsub.vv v18, v12, v8",
+            "vadd.vv v8, v8, v12",
+            barrett!("v8", "v17"),
+            fqmul_vv!("v12", "v18", "v16", "v17"),
+            "vsub.vv v18, v13, v9",
+            "vadd.vv v9, v9, v13",
+            barrett!("v9", "v17"),
+            fqmul_vv!("v13", "v18", "v16", "v17"),
+            "vsub.vv v18, v14, v10",
+            "vadd.vv v10, v10, v14",
+            barrett!("v10", "v17"),
+            fqmul_vv!("v14", "v18", "v16", "v17"),
+            "vsub.vv v18, v15, v11",
+            "vadd.vv v11, v11, v15",
+            barrett!("v11", "v17"),
+            fqmul_vv!("v15", "v18", "v16", "v17"),
+            "vsseg8e16.v v8, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 4",
+            "add {r}, {r}, {t}",
+            "slli {t}, {vl}, 1",
+            "add {z}, {z}, {t}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r => _,
+        z = inout(reg) INV_ZETAS[64..].as_ptr() => _,
+        n = inout(reg) KYBER_N / 8 => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+        v = in(reg) V,
+        half = in(reg) 512i16,
+    );
+}
+
+/// Name:  reduce
+///
+/// Description: Applies the reference Barrett reduction to n
+///  coefficients
+///
+/// Arguments:   - *mut i16 r: in/output coefficients
+///  - usize n: number of coefficients, not zero
+unsafe fn reduce(r: *mut i16, n: usize) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m8, ta, ma",
+            "vle16.v v0, ({r})",
+            barrett!("v0", "v8"),
+            "vse16.v v0, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {vl}, {vl}, 1",
+            "add {r}, {r}, {vl}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r => _,
+        n = inout(reg) n => _,
+        vl = out(reg) _,
+        q = in(reg) Q,
+        v = in(reg) V,
+        half = in(reg) 512i16,
+    );
+}
+
+/// Name:  scale
+///
+/// Description: Multiplies n coefficients by c in the Montgomery domain,
+///  r[j] = fqmul(r[j], c)
+///
+/// Arguments:   - *mut i16 r: in/output coefficients
+///  - usize n: number of coefficients, not zero
+///  - i16 c: factor
+unsafe fn scale(r: *mut i16, n: usize, c: i16) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m8, ta, ma",
+            "vle16.v v0, ({r})",
+            fqmul_vx!("v0", "v0", "{c}", "v8"),
+            "vse16.v v0, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {vl}, {vl}, 1",
+            "add {r}, {r}, {vl}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r => _,
+        n = inout(reg) n => _,
+        vl = out(reg) _,
+        c = in(reg) c,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+    );
+}
+
+/// Name:  basemul_acc
+///
+/// Description: Adds the products of the pairs of a and b to the i32
+///  accumulators of the lazy basemul of the reference: a segment load
+///  of 2 fields splits the pairs into first and second coefficients,
+///  the second coefficient of b is premultiplied by the zeta of its
+///  pair, and vwmacc sums the four products into 32-bit lanes
+///
+/// Arguments:   - *mut i32 acc: in/output accumulators, KYBER_N of them
+///  - *const i16 a: first input polynomial
+///  - *const i16 b: second input polynomial
+unsafe fn basemul_acc(acc: *mut i32, a: *const i16, b: *const i16) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m1, ta, ma",
+            "vlseg2e16.v v8, ({a})",
+            "vlseg2e16.v v10, ({b})",
+            "vle16.v v12, ({z})",
+            fqmul_vv!("v13", "v11", "v12", "v14"),
+            "vsetvli zero, zero, e32, m2, ta, ma",
+            "vlseg2e32.v v16, ({acc})",
+            "vsetvli zero, zero, e16, m1, ta, ma",
+            // a0 * b0 + a1 * b1 * zeta and a0 * b1 + a1 * b0
+            "vwmacc.vv v16, v8, v10",
+            "vwmacc.vv v16, v9, v13",
+            "vwmacc.vv v18, v8, v11",
+            "vwmacc.vv v18, v9, v10",
+            "vsetvli zero, zero, e32, m2, ta, ma",
+            "vsseg2e32.v v16, ({acc})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 1",
+            "add {z}, {z}, {t}",
+            "slli {t}, {vl}, 2",
+            "add {a}, {a}, {t}",
+            "add {b}, {b}, {t}",
+            "slli {t}, {vl}, 3",
+            "add {acc}, {acc}, {t}",
+            "bnez {n}, 2b",
+        ],
+        acc = inout(reg) acc => _,
+        a = inout(reg) a => _,
+        b = inout(reg) b => _,
+        z = inout(reg) BASEMUL_ZETAS.as_ptr() => _,
+        n = inout(reg) KYBER_N / 2 => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+    );
+}
+
+/// Name:  basemul_reduce
+///
+/// Description: Montgomery-reduces the i32 accumulators of basemul_acc
+///  to 16 bits and applies the Barrett reduction, like the end of the
+///  lazy basemul of the reference: the low halves give the Montgomery
+///  quotient and the high halves, from a narrowing shift, the result
+///
+/// Arguments:   - *mut i16 r: output polynomial
+///  - *const i32 acc: input accumulators, KYBER_N of them
+unsafe fn basemul_reduce(r: *mut i16, acc: *const i32) {
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e32, m4, ta, ma",
+            "vle32.v v8, ({acc})",
+            "vsetvli zero, zero, e16, m2, ta, ma",
+            "vnsrl.wi v16, v8, 0",
+            "vnsra.wi v20, v8, 16",
+            "vmul.vx v16, v16, {qinv}",
+            "vmulh.vx v16, v16, {q}",
+            "vsub.vv v16, v20, v16",
+            barrett!("v16", "v20"),
+            "vse16.v v16, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 1",
+            "add {r}, {r}, {t}",
+            "slli {t}, {vl}, 2",
+            "add {acc}, {acc}, {t}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r => _,
+        acc = inout(reg) acc => _,
+        n = inout(reg) KYBER_N => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        qinv = in(reg) QINV,
+        q = in(reg) Q,
+        v = in(reg) V,
+        half = in(reg) 512i16,
+    );
+}
+
+/// Name:  poly_ntt
+///
+/// Description: Computes the NTT of a polynomial in place like the
+///  reference poly_ntt, with identical output; inputs in normal order,
+///  output in bitreversed order
+///
+/// Arguments:   - Poly r: in/output polynomial
+pub(crate) unsafe fn poly_ntt(r: &mut Poly) {
+    count_ntt(Acceleration::Rvv);
+    let p = r.coeffs.as_mut_ptr();
+
+    // len 128 down to 8: runs of len contiguous butterflies
+    let mut k = 1;
+    let mut len = 128;
+    while len >= 8 {
+        for start in (0..KYBER_N).step_by(2 * len) {
+            butterflies(
+                p.add(start),
+                p.add(start + len),
+                len,
+                ZETAS[k],
+            );
+            k += 1;
+        }
+        len >>= 1;
+    }
+
+    // len 4 and 2: one butterfly per block and field pair
+    ntt_len4(p);
+    ntt_len2(p);
+    reduce(p, KYBER_N);
+}
+
+/// Name:  poly_invntt_tomont
+///
+/// Description: Computes the inverse NTT of a polynomial in place and
+///  multiplies by the Montgomery factor like the reference
+///  poly_invntt_tomont, with identical output; inputs in bitreversed
+///  order, output in normal order
+///
+/// Arguments:   - Poly r: in/output polynomial
+pub(crate) unsafe fn poly_invntt_tomont(r: &mut Poly) {
+    let p = r.coeffs.as_mut_ptr();
+
+    invntt_len2(p);
+    invntt_len4(p);
+
+    // len 8 up to 128: runs of len contiguous butterflies
+    let mut len = 8;
+    while len <= 128 {
+        let first = KYBER_N / len - 1;
+        for (g, start) in (0..KYBER_N).step_by(2 * len).enumerate() {
+            let zeta = ZETAS[first - g];
+            inv_butterflies(
+                p.add(start),
+                p.add(start + len),
+                len,
+                zeta,
+            );
+        }
+        len <<= 1;
+    }
+
+    scale(p, KYBER_N, F);
+}
+
+/// Name:  polyvec_basemul_acc_montgomery
+///
+/// Description: Pointwise multiply elements of a and b and accumulate
+///  into r, with the lazy i32 accumulate of the reference and identical
+///  output
+///
+/// Arguments: - poly *r:  output polynomial
+///  - const Polyvec a: first input vector of polynomials
+///  - const Polyvec b: second input vector of polynomials
+pub(crate) unsafe fn polyvec_basemul_acc_montgomery(
+    r: &mut Poly,
+    a: &Polyvec,
+    b: &Polyvec,
+) {
+    let mut acc = [0i32; KYBER_N];
+    for (a, b) in a.vec.iter().zip(b.vec.iter()) {
+        basemul_acc(
+            acc.as_mut_ptr(),
+            a.coeffs.as_ptr(),
+            b.coeffs.as_ptr(),
+        );
+    }
+    basemul_reduce(r.coeffs.as_mut_ptr(), acc.as_ptr());
+}
+
+/// Name:  poly_reduce
+///
+/// Description: Applies the reference Barrett reduction to all
+///  coefficients of a polynomial, giving the centered representatives
+///
+/// Arguments:   - poly *r:   input/output polynomial
+pub(crate) unsafe fn poly_reduce(r: &mut Poly) {
+    reduce(r.coeffs.as_mut_ptr(), KYBER_N);
+}
+
+/// Name:  poly_tobytes
+///
+/// Description: Serialization of a polynomial like the reference
+///  poly_tobytes; coefficients must be in {-q+1,...,q-1}. Panics if r is
+///  shorter than KYBER_POLY_BYTES, before anything is written
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLY_BYTES bytes)
+///  - const poly *a:  input polynomial
+pub(crate) unsafe fn poly_tobytes(r: &mut [u8], a: &Poly) {
+    let r = &mut r[..KYBER_POLY_BYTES];
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e16, m1, ta, ma",
+            "vlseg2e16.v v8, ({a})",
+            // map to positive standard representatives
+            "vsra.vi v10, v8, 15",
+            "vand.vx v10, v10, {q}",
+            "vadd.vv v8, v8, v10",
+            "vsra.vi v10, v9, 15",
+            "vand.vx v10, v10, {q}",
+            "vadd.vv v9, v9, v10",
+            "vsrl.vi v10, v8, 8",
+            "vsll.vi v11, v9, 4",
+            "vor.vv v10, v10, v11",
+            "vsrl.vi v11, v9, 4",
+            "vsetvli zero, zero, e8, mf2, ta, ma",
+            "vnsrl.wi v12, v8, 0",
+            "vnsrl.wi v13, v10, 0",
+            "vnsrl.wi v14, v11, 0",
+            "vsseg3e8.v v12, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 2",
+            "add {a}, {a}, {t}",
+            "slli {t}, {vl}, 1",
+            "add {t}, {t}, {vl}",
+            "add {r}, {r}, {t}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r.as_mut_ptr() => _,
+        a = inout(reg) a.coeffs.as_ptr() => _,
+        n = inout(reg) KYBER_N / 2 => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        q = in(reg) Q,
+    );
+}
+
+/// Name:  poly_frombytes
+///
+/// Description: De-serialization of a polynomial like the reference
+///  poly_frombytes. Panics if a is shorter than KYBER_POLY_BYTES
+///
+/// Arguments:   - poly *r:  output polynomial
+///  - const [u8] a: input byte array (of KYBER_POLY_BYTES bytes)
+pub(crate) unsafe fn poly_frombytes(r: &mut Poly, a: &[u8]) {
+    let a = &a[..KYBER_POLY_BYTES];
+    asm_v!(
+        [
+            "2:",
+            "vsetvli {vl}, {n}, e8, mf2, ta, ma",
+            "vlseg3e8.v v8, ({a})",
+            "vsetvli zero, zero, e16, m1, ta, ma",
+            "vzext.vf2 v12, v8",
+            "vzext.vf2 v13, v9",
+            "vzext.vf2 v14, v10",
+            "vsll.vi v15, v13, 8",
+            "vor.vv v12, v12, v15",
+            "vand.vx v12, v12, {mask}",
+            "vsrl.vi v13, v13, 4",
+            "vsll.vi v14, v14, 4",
+            "vor.vv v13, v13, v14",
+            "vand.vx v13, v13, {mask}",
+            "vsseg2e16.v v12, ({r})",
+            "sub {n}, {n}, {vl}",
+            "slli {t}, {vl}, 2",
+            "add {r}, {r}, {t}",
+            "slli {t}, {vl}, 1",
+            "add {t}, {t}, {vl}",
+            "add {a}, {a}, {t}",
+            "bnez {n}, 2b",
+        ],
+        r = inout(reg) r.coeffs.as_mut_ptr() => _,
+        a = inout(reg) a.as_ptr() => _,
+        n = inout(reg) KYBER_N / 2 => _,
+        vl = out(reg) _,
+        t = out(reg) _,
+        mask = in(reg) 0xfffi16,
+    );
+}
+
+/// Name:  polyvec_ntt
+///
+/// Description: Apply forward NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+pub(crate) unsafe fn polyvec_ntt(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_ntt(poly);
+    }
+}
+
+/// Name:  polyvec_invntt_tomont
+///
+/// Description: Apply inverse NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+pub(crate) unsafe fn polyvec_invntt_tomont(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_invntt_tomont(poly);
+    }
+}
+
+/// Name:  polyvec_reduce
+///
+/// Description: Applies the reference Barrett reduction to each
+///  coefficient of each element of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: input/output vector of polynomials
+pub(crate) unsafe fn polyvec_reduce(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_reduce(poly);
+    }
+}
+
+/// Name:  polyvec_tobytes
+///
+/// Description: Serialize vector of polynomials
+///
+/// Arguments:   - [u8] r: output byte array (needs space for KYBER_POLYVEC_BYTES)
+///  - const Polyvec a: input vector of polynomials
+pub(crate) unsafe fn polyvec_tobytes(r: &mut [u8], a: &Polyvec) {
+    for (r, poly) in r.chunks_mut(KYBER_POLY_BYTES).zip(a.vec.iter()) {
+        poly_tobytes(r, poly);
+    }
+}
+
+/// Name:  polyvec_frombytes
+///
+/// Description: De-serialize vector of polynomials;
+///  inverse of polyvec_tobytes
+///
+/// Arguments:   - Polyvec r: output vector of polynomials
+///  - const [u8] a: input byte array (of length KYBER_POLYVEC_BYTES)
+pub(crate) unsafe fn polyvec_frombytes(r: &mut Polyvec, a: &[u8]) {
+    for (poly, a) in r.vec.iter_mut().zip(a.chunks(KYBER_POLY_BYTES)) {
+        poly_frombytes(poly, a);
+    }
+}
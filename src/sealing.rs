@@ -0,0 +1,349 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Password-based sealing of Kyber secret keys.
+//!
+//! A secret key is encrypted with XChaCha20-Poly1305 under a key derived
+//! from a passphrase with Argon2id. The result is a fixed-size, versioned
+//! blob that records everything needed to open it again: the security
+//! parameter, the Argon2id cost parameters, the salt and the nonce.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::sealing::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let params = SealingParams::new(64, 1, 1)?;
+//! let sealed = keys.secret.seal_with_password(b"hunter2", &params, &mut rng)?;
+//! let bytes = sealed.to_bytes();
+//!
+//! let opened = SealedSecretKey::from_bytes(&bytes)?.open(b"hunter2")?;
+//! assert_eq!(opened, keys.secret);
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    error::KyberLibError, kex::SecretKey, params::*, CryptoRng, RngCore,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::AeadInPlace, KeyInit, Tag, XChaCha20Poly1305, XNonce,
+};
+use core::convert::TryInto;
+use zeroize::Zeroize;
+
+/// Current version of the sealed secret key format.
+pub const SEALED_FORMAT_VERSION: u8 = 1;
+/// Size in bytes of the random Argon2id salt.
+pub const SEALING_SALT_BYTES: usize = 16;
+/// Size in bytes of the XChaCha20-Poly1305 nonce.
+pub const SEALING_NONCE_BYTES: usize = 24;
+/// Size in bytes of the Poly1305 authentication tag.
+pub const SEALING_TAG_BYTES: usize = 16;
+/// Size in bytes of the unencrypted, authenticated header.
+pub const SEALING_HEADER_BYTES: usize =
+    2 + 3 * 4 + SEALING_SALT_BYTES + SEALING_NONCE_BYTES;
+/// Size in bytes of a sealed secret key.
+pub const SEALED_SECRET_KEY_BYTES: usize =
+    SEALING_HEADER_BYTES + KYBER_SECRET_KEY_BYTES + SEALING_TAG_BYTES;
+
+/// Largest accepted Argon2id memory size in KiB, 2 GiB as in the first
+/// recommended option of RFC 9106.
+pub const SEALING_MAX_M_COST: u32 = 2 * 1024 * 1024;
+/// Largest accepted number of Argon2id passes.
+pub const SEALING_MAX_T_COST: u32 = 16;
+/// Largest accepted number of Argon2id lanes.
+pub const SEALING_MAX_LANES: u32 = 16;
+
+const SEALING_KEY_BYTES: usize = 32;
+
+/// Argon2id cost parameters used to derive the sealing key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SealingParams {
+    m_cost: u32,
+    t_cost: u32,
+    lanes: u32,
+}
+
+impl SealingParams {
+    /// Creates a new set of Argon2id parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `m_cost` - Memory size in KiB.
+    /// * `t_cost` - Number of passes over the memory.
+    /// * `lanes` - Degree of parallelism.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if the parameters are rejected
+    /// by Argon2, e.g. a memory size below `8 * lanes` KiB, or exceed
+    /// [`SEALING_MAX_M_COST`], [`SEALING_MAX_T_COST`] or
+    /// [`SEALING_MAX_LANES`].
+    pub fn new(
+        m_cost: u32,
+        t_cost: u32,
+        lanes: u32,
+    ) -> Result<Self, KyberLibError> {
+        if m_cost > SEALING_MAX_M_COST
+            || t_cost > SEALING_MAX_T_COST
+            || lanes > SEALING_MAX_LANES
+        {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let params = Self {
+            m_cost,
+            t_cost,
+            lanes,
+        };
+        let _ = params.argon2()?;
+        Ok(params)
+    }
+
+    /// Memory size in KiB.
+    pub fn m_cost(&self) -> u32 {
+        self.m_cost
+    }
+
+    /// Number of passes over the memory.
+    pub fn t_cost(&self) -> u32 {
+        self.t_cost
+    }
+
+    /// Degree of parallelism.
+    pub fn lanes(&self) -> u32 {
+        self.lanes
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, KyberLibError> {
+        let params = Params::new(
+            self.m_cost,
+            self.t_cost,
+            self.lanes,
+            Some(SEALING_KEY_BYTES),
+        )
+        .map_err(|_| KyberLibError::InvalidInput)?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+impl Default for SealingParams {
+    /// The Argon2id defaults recommended by OWASP: 19 MiB of memory, two
+    /// passes and a single lane.
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            lanes: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// A Kyber secret key encrypted under a password.
+///
+/// The serialised layout, all integers little-endian, is:
+///
+/// | Bytes | Field |
+/// |-------|-------|
+/// | 1     | format version, currently [`SEALED_FORMAT_VERSION`] |
+/// | 1     | security parameter `k` of the sealed key |
+/// | 4     | Argon2id memory size in KiB |
+/// | 4     | Argon2id number of passes |
+/// | 4     | Argon2id number of lanes |
+/// | 16    | salt |
+/// | 24    | nonce |
+/// | 3184  | encrypted secret key and Poly1305 tag (Kyber1024) |
+///
+/// The header is authenticated as associated data, so tampering with the
+/// recorded parameters is detected just like tampering with the ciphertext.
+/// The tag can only be checked after running Argon2id, so parameters above
+/// the `SEALING_MAX_*` caps are rejected up front; otherwise a crafted
+/// blob could make [`SealedSecretKey::open`] allocate up to 4 TiB.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SealedSecretKey {
+    bytes: [u8; SEALED_SECRET_KEY_BYTES],
+}
+
+impl SealedSecretKey {
+    /// Seals `secret` under `password` using a fresh salt and nonce drawn
+    /// from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails and
+    /// `KyberLibError::InvalidInput` if Argon2 rejects the parameters.
+    #[must_use = "dropping the sealed key discards the encrypted secret key"]
+    pub fn seal<R>(
+        secret: &SecretKey,
+        password: &[u8],
+        params: &SealingParams,
+        rng: &mut R,
+    ) -> Result<Self, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut bytes = [0u8; SEALED_SECRET_KEY_BYTES];
+        bytes[0] = SEALED_FORMAT_VERSION;
+        bytes[1] = KYBER_SECURITY_PARAMETER as u8;
+        bytes[2..6].copy_from_slice(&params.m_cost.to_le_bytes());
+        bytes[6..10].copy_from_slice(&params.t_cost.to_le_bytes());
+        bytes[10..14].copy_from_slice(&params.lanes.to_le_bytes());
+        rng.try_fill_bytes(&mut bytes[14..SEALING_HEADER_BYTES])
+            .map_err(|_| KyberLibError::RandomBytesGeneration)?;
+
+        let (header, body) = bytes.split_at_mut(SEALING_HEADER_BYTES);
+        let (ciphertext, tag) =
+            body.split_at_mut(KYBER_SECRET_KEY_BYTES);
+        let salt = &header[14..14 + SEALING_SALT_BYTES];
+        let nonce =
+            XNonce::from_slice(&header[14 + SEALING_SALT_BYTES..]);
+
+        let mut key = [0u8; SEALING_KEY_BYTES];
+        let result = params
+            .argon2()?
+            .hash_password_into(password, salt, &mut key)
+            .map_err(|_| KyberLibError::InvalidInput)
+            .and_then(|()| {
+                ciphertext.copy_from_slice(secret);
+                XChaCha20Poly1305::new(&key.into())
+                    .encrypt_in_place_detached(
+                        nonce, header, ciphertext,
+                    )
+                    .map_err(|_| KyberLibError::InvalidInput)
+            });
+        key.zeroize();
+        tag.copy_from_slice(&result?);
+
+        Ok(Self { bytes })
+    }
+
+    /// Recovers the secret key sealed under `password`.
+    ///
+    /// The derived key and any decrypted material are zeroized before
+    /// returning. The password only enters Argon2id through its initial
+    /// BLAKE2b pre-hash; the memory-hard work that dominates the running
+    /// time is fixed by the recorded parameters. Together with the
+    /// constant-time Poly1305 tag comparison this means a wrong password
+    /// fails without revealing its length or how close it was.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::Unsealing` if the password is wrong or the
+    /// blob has been tampered with, and `KyberLibError::InvalidInput` if
+    /// the header records an unknown version, another security level or
+    /// invalid Argon2id parameters.
+    #[must_use = "dropping the result discards the recovered secret key"]
+    pub fn open(
+        &self,
+        password: &[u8],
+    ) -> Result<SecretKey, KyberLibError> {
+        let params = self.params()?;
+        let (header, body) = self.bytes.split_at(SEALING_HEADER_BYTES);
+        let (ciphertext, tag) = body.split_at(KYBER_SECRET_KEY_BYTES);
+        let salt = &header[14..14 + SEALING_SALT_BYTES];
+        let nonce =
+            XNonce::from_slice(&header[14 + SEALING_SALT_BYTES..]);
+
+        let mut key = [0u8; SEALING_KEY_BYTES];
+        let mut secret = [0u8; KYBER_SECRET_KEY_BYTES];
+        secret.copy_from_slice(ciphertext);
+        let result = params
+            .argon2()?
+            .hash_password_into(password, salt, &mut key)
+            .map_err(|_| KyberLibError::InvalidInput)
+            .and_then(|()| {
+                XChaCha20Poly1305::new(&key.into())
+                    .decrypt_in_place_detached(
+                        nonce,
+                        header,
+                        &mut secret,
+                        Tag::from_slice(tag),
+                    )
+                    .map_err(|_| KyberLibError::Unsealing)
+            });
+        key.zeroize();
+        if let Err(err) = result {
+            secret.zeroize();
+            return Err(err);
+        }
+        Ok(secret)
+    }
+
+    /// Returns the Argon2id parameters recorded in the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidInput` if the header records an
+    /// unknown version, another security level or invalid parameters,
+    /// including parameters above the `SEALING_MAX_*` caps.
+    pub fn params(&self) -> Result<SealingParams, KyberLibError> {
+        if self.bytes[0] != SEALED_FORMAT_VERSION
+            || self.bytes[1] as usize != KYBER_SECURITY_PARAMETER
+        {
+            return Err(KyberLibError::InvalidInput);
+        }
+        let word = |i: usize| {
+            let mut le = [0u8; 4];
+            le.copy_from_slice(&self.bytes[i..i + 4]);
+            u32::from_le_bytes(le)
+        };
+        SealingParams::new(word(2), word(6), word(10))
+    }
+
+    /// Returns the serialised blob.
+    pub fn to_bytes(&self) -> [u8; SEALED_SECRET_KEY_BYTES] {
+        self.bytes
+    }
+
+    /// Parses a serialised blob produced by [`SealedSecretKey::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `bytes` has the wrong size
+    /// and `KyberLibError::InvalidInput` if the header is not understood.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KyberLibError> {
+        let bytes: [u8; SEALED_SECRET_KEY_BYTES] = bytes
+            .try_into()
+            .map_err(|_| KyberLibError::InvalidLength)?;
+        let sealed = Self { bytes };
+        let _ = sealed.params()?;
+        Ok(sealed)
+    }
+}
+
+/// Password sealing for [`SecretKey`].
+///
+/// `SecretKey` is a plain byte array, so sealing is provided through this
+/// extension trait rather than an inherent method.
+pub trait SealWithPassword {
+    /// Seals the secret key under `password`, see [`SealedSecretKey::seal`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails and
+    /// `KyberLibError::InvalidInput` if Argon2 rejects the parameters.
+    fn seal_with_password<R>(
+        &self,
+        password: &[u8],
+        params: &SealingParams,
+        rng: &mut R,
+    ) -> Result<SealedSecretKey, KyberLibError>
+    where
+        R: RngCore + CryptoRng;
+}
+
+impl SealWithPassword for SecretKey {
+    fn seal_with_password<R>(
+        &self,
+        password: &[u8],
+        params: &SealingParams,
+        rng: &mut R,
+    ) -> Result<SealedSecretKey, KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        SealedSecretKey::seal(self, password, params, rng)
+    }
+}
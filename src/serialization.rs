@@ -0,0 +1,330 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! `serde` support for keys and ciphertexts.
+//!
+//! Following the RustCrypto crates, values are written as lowercase hex
+//! strings to human-readable formats such as JSON, and as raw bytes to
+//! binary formats such as bincode, as
+//! [`is_human_readable`](serde::Serializer::is_human_readable) reports.
+//! Hex in either case is accepted when reading text, and a byte sequence
+//! as well as a byte string when reading binary.
+//!
+//! [`AlignedPublicKey`], [`AlignedCiphertext`], [`ValidatedPublicKey`]
+//! and [`Keypair`] implement `Serialize` and `Deserialize`. The key and
+//! ciphertext aliases are plain arrays, which serde only supports up to
+//! 32 elements, so fields of those types use this module:
+//!
+//! ```
+//! # use kyberlib::*;
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Peer {
+//!     name: String,
+//!     #[serde(with = "kyberlib::serialization")]
+//!     public: PublicKey,
+//! }
+//! # let keys = keypair(&mut rand::thread_rng()).unwrap();
+//! # let json = serde_json::to_string(&Peer { name: "a".into(), public: keys.public }).unwrap();
+//! # let peer: Peer = serde_json::from_str(&json).unwrap();
+//! # assert_eq!(peer.public, keys.public);
+//! ```
+//!
+//! A [`Keypair`] is written in the versioned format of
+//! [`Keypair::to_bytes`], so serialising one writes out the secret key.
+//! Its hex goes through the constant-time codec of [`codec`], as does
+//! that of fields using [`secret`], which is meant for `SecretKey`,
+//! `SharedSecret` and seed arrays. Deserialising a [`ValidatedPublicKey`]
+//! runs the public key check.
+
+use crate::{
+    api::{
+        Keypair, ValidatePublicKey, ValidatedPublicKey,
+        VERSIONED_SECRET_KEY_BYTES,
+    },
+    codec,
+    kex::{AlignedCiphertext, AlignedPublicKey},
+    params::*,
+};
+use core::fmt;
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+// Displays bytes as lowercase hex without an intermediate string.
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+// Displays secret bytes as lowercase hex with the constant-time codec,
+// through a small buffer cleared after each chunk.
+struct SecretHex<'a>(&'a [u8]);
+
+impl fmt::Display for SecretHex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.0.chunks(32) {
+            #[allow(unused_mut)]
+            let mut buf = [0u8; 64];
+            let result = codec::hex_encode(chunk, &mut buf)
+                .map_err(|_| fmt::Error)
+                .and_then(|hex| f.write_str(hex));
+            #[cfg(feature = "zeroize")]
+            buf.zeroize();
+            result?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialises `bytes` as a hex string or as raw bytes, depending on the
+/// format; for `#[serde(with = "kyberlib::serialization")]`.
+///
+/// # Errors
+///
+/// Returns the error of the serializer.
+pub fn serialize<S, T>(
+    bytes: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<[u8]> + ?Sized,
+{
+    if serializer.is_human_readable() {
+        serializer.collect_str(&Hex(bytes.as_ref()))
+    } else {
+        serializer.serialize_bytes(bytes.as_ref())
+    }
+}
+
+/// Deserialises an array of `N` bytes written by [`serialize`].
+///
+/// # Errors
+///
+/// Fails if the input is not `N` bytes or `2 * N` hex digits.
+pub fn deserialize<'de, D, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let visitor = BytesVisitor::<N> { secret: false };
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(visitor)
+    } else {
+        deserializer.deserialize_bytes(visitor)
+    }
+}
+
+/// `serialize` and `deserialize` for fields holding secret keys, shared
+/// secrets or seeds, for
+/// `#[serde(with = "kyberlib::serialization::secret")]`.
+///
+/// The output is the same as that of the parent module, but hex is
+/// encoded and decoded in constant time. Buffers of the serializer or
+/// deserializer itself are out of reach and are not cleared.
+///
+/// ```
+/// # use kyberlib::*;
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Session {
+///     #[serde(with = "kyberlib::serialization::secret")]
+///     key: SharedSecret,
+/// }
+/// # let session = Session { key: [7; KYBER_SHARED_SECRET_BYTES] };
+/// # let json = serde_json::to_string(&session).unwrap();
+/// # let read: Session = serde_json::from_str(&json).unwrap();
+/// # assert_eq!(read.key, session.key);
+/// ```
+pub mod secret {
+    use super::{BytesVisitor, SecretHex};
+    use serde::{Deserializer, Serializer};
+
+    /// Serialises secret `bytes` as a hex string or as raw bytes,
+    /// depending on the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the serializer.
+    pub fn serialize<S, T>(
+        bytes: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<[u8]> + ?Sized,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&SecretHex(bytes.as_ref()))
+        } else {
+            serializer.serialize_bytes(bytes.as_ref())
+        }
+    }
+
+    /// Deserialises a secret array of `N` bytes written by [`serialize`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input is not `N` bytes or `2 * N` hex digits.
+    pub fn deserialize<'de, D, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let visitor = BytesVisitor::<N> { secret: true };
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(visitor)
+        } else {
+            deserializer.deserialize_bytes(visitor)
+        }
+    }
+}
+
+// `secret` selects the constant-time hex decoder.
+struct BytesVisitor<const N: usize> {
+    secret: bool,
+}
+
+fn nibble(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes or {} hex digits", N, 2 * N)
+    }
+
+    // The input may be a secret key, so errors never quote it.
+    fn visit_str<E: de::Error>(self, hex: &str) -> Result<[u8; N], E> {
+        if hex.len() != 2 * N {
+            return Err(E::invalid_length(hex.len(), &self));
+        }
+        let mut bytes = [0u8; N];
+        if self.secret {
+            if codec::hex_decode(hex, &mut bytes).is_err() {
+                return Err(E::custom("invalid hex digit"));
+            }
+            return Ok(bytes);
+        }
+        for (byte, pair) in
+            bytes.iter_mut().zip(hex.as_bytes().chunks_exact(2))
+        {
+            match (nibble(pair[0]), nibble(pair[1])) {
+                (Some(hi), Some(lo)) => *byte = hi << 4 | lo,
+                _ => return Err(E::custom("invalid hex digit")),
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<[u8; N], E> {
+        if v.len() != N {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(v);
+        Ok(bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<[u8; N], A::Error> {
+        let mut bytes = [0u8; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(bytes)
+    }
+}
+
+macro_rules! serde_bytes_newtype {
+    ($name:ident) => {
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serialize(&self.0, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                deserialize(deserializer).map($name)
+            }
+        }
+    };
+}
+
+serde_bytes_newtype!(AlignedPublicKey);
+serde_bytes_newtype!(AlignedCiphertext);
+
+impl Serialize for ValidatedPublicKey {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize(self.as_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ValidatedPublicKey {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let bytes: [u8; KYBER_PUBLIC_KEY_BYTES] =
+            deserialize(deserializer)?;
+        bytes.validated().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Keypair {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        #[allow(unused_mut)]
+        let mut bytes = self.to_bytes();
+        let result = secret::serialize(&bytes, serializer);
+        #[cfg(feature = "zeroize")]
+        bytes.zeroize();
+        result
+    }
+}
+
+impl<'de> Deserialize<'de> for Keypair {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        #[allow(unused_mut)]
+        let mut bytes: [u8; VERSIONED_SECRET_KEY_BYTES] =
+            secret::deserialize(deserializer)?;
+        let keys =
+            Keypair::from_bytes(&bytes).map_err(de::Error::custom);
+        #[cfg(feature = "zeroize")]
+        bytes.zeroize();
+        keys
+    }
+}
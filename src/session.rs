@@ -0,0 +1,408 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Type-state handshake built on the Kyber KEM.
+//!
+//! The initiator knows the responder's static public key. It sends a
+//! [`HelloMessage`] carrying a fresh ephemeral public key and a ciphertext
+//! to the responder's static key; the responder answers with a
+//! [`ResponseMessage`] carrying a ciphertext to the ephemeral key and a
+//! confirmation tag. Both sides end up in [`Established`] with one key per
+//! direction.
+//!
+//! Every state is consumed by the step that leaves it and none of them can
+//! be cloned, so an ephemeral key cannot be used twice and a hello cannot
+//! be finished without a response:
+//!
+//! ```compile_fail
+//! # use kyberlib::*;
+//! # use kyberlib::session::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! # let mut rng = rand::thread_rng();
+//! # let keys = keypair(&mut rng)?;
+//! let (hello, awaiting) = Initiator::new(&keys.public, &mut rng)?;
+//! let (response, _) = Responder::accept(&hello, &keys.secret, &mut rng)?;
+//! let _first = awaiting.finish(&response)?;
+//! let _replay = awaiting.finish(&response)?;
+//! # Ok(()) }
+//! ```
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::session::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let server_keys = keypair(&mut rng)?;
+//!
+//! let (hello, awaiting) = Initiator::new(&server_keys.public, &mut rng)?;
+//! let hello = HelloMessage::from_bytes(&hello.to_bytes())?;
+//!
+//! let (response, server) =
+//!     Responder::accept(&hello, &server_keys.secret, &mut rng)?;
+//! let response = ResponseMessage::from_bytes(&response.to_bytes())?;
+//!
+//! let client = awaiting.finish(&response)?;
+//! assert_eq!(client.sending_key(), server.receiving_key());
+//! assert_eq!(client.receiving_key(), server.sending_key());
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    api::{decapsulate, encapsulate, keypair},
+    error::KyberLibError,
+    kem::check_public_key,
+    kex::{PublicKey, SecretKey, SharedSecret},
+    params::*,
+    symmetric::labeled_kdf,
+    verify::verify,
+    CryptoRng, RngCore,
+};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Version of the handshake carried in every message.
+pub const SESSION_VERSION: u8 = 1;
+/// Size in bytes of a session key.
+pub const SESSION_KEY_BYTES: usize = 32;
+/// Size in bytes of the responder's key confirmation tag.
+pub const SESSION_TAG_BYTES: usize = 32;
+/// Size in bytes of a serialised hello message.
+pub const HELLO_BYTES: usize =
+    2 + KYBER_PUBLIC_KEY_BYTES + KYBER_CIPHERTEXT_BYTES;
+/// Size in bytes of a serialised response message.
+pub const RESPONSE_BYTES: usize =
+    2 + KYBER_CIPHERTEXT_BYTES + SESSION_TAG_BYTES;
+
+const HELLO_TYPE: u8 = 1;
+const RESPONSE_TYPE: u8 = 2;
+const SESSION_LABEL: &str = "kyberlib session keys v1";
+
+/// First message, sent by the initiator.
+///
+/// Serialised as a version byte, a message type byte, the ephemeral public
+/// key and the ciphertext to the responder's static key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HelloMessage {
+    bytes: [u8; HELLO_BYTES],
+}
+
+impl HelloMessage {
+    /// Returns the serialised message.
+    pub fn to_bytes(&self) -> [u8; HELLO_BYTES] {
+        self.bytes
+    }
+
+    /// Parses a serialised message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `bytes` has the wrong size,
+    /// `KyberLibError::InvalidInput` for another version or message type and
+    /// `KyberLibError::InvalidKey` if the ephemeral public key is not
+    /// canonically encoded.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KyberLibError> {
+        let bytes = parse_header(bytes, HELLO_TYPE)?;
+        check_public_key(&bytes[2..2 + KYBER_PUBLIC_KEY_BYTES])?;
+        Ok(Self { bytes })
+    }
+
+    fn ephemeral_public(&self) -> &[u8] {
+        &self.bytes[2..2 + KYBER_PUBLIC_KEY_BYTES]
+    }
+
+    fn ciphertext(&self) -> &[u8] {
+        &self.bytes[2 + KYBER_PUBLIC_KEY_BYTES..]
+    }
+}
+
+/// Second message, sent by the responder.
+///
+/// Serialised as a version byte, a message type byte, the ciphertext to the
+/// initiator's ephemeral key and the key confirmation tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResponseMessage {
+    bytes: [u8; RESPONSE_BYTES],
+}
+
+impl ResponseMessage {
+    /// Returns the serialised message.
+    pub fn to_bytes(&self) -> [u8; RESPONSE_BYTES] {
+        self.bytes
+    }
+
+    /// Parses a serialised message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `bytes` has the wrong size
+    /// and `KyberLibError::InvalidInput` for another version or message
+    /// type.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KyberLibError> {
+        Ok(Self {
+            bytes: parse_header(bytes, RESPONSE_TYPE)?,
+        })
+    }
+
+    fn ciphertext(&self) -> &[u8] {
+        &self.bytes[2..2 + KYBER_CIPHERTEXT_BYTES]
+    }
+
+    fn tag(&self) -> &[u8] {
+        &self.bytes[2 + KYBER_CIPHERTEXT_BYTES..]
+    }
+}
+
+/// Entry point for the side that starts the handshake.
+#[derive(Clone, Copy, Debug)]
+pub struct Initiator;
+
+impl Initiator {
+    /// Starts a handshake with the holder of `remote_pk`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidKey` if `remote_pk` is not canonically
+    /// encoded and `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    #[allow(clippy::new_ret_no_self)]
+    #[must_use = "dropping the state discards the ephemeral secret key"]
+    pub fn new<R>(
+        remote_pk: &PublicKey,
+        rng: &mut R,
+    ) -> Result<(HelloMessage, AwaitingResponse), KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        check_public_key(remote_pk)?;
+        let mut ephemeral = keypair(rng)?;
+        let (ciphertext, mut shared_secret) =
+            match encapsulate(remote_pk, rng) {
+                Ok(encapsulated) => encapsulated,
+                Err(err) => {
+                    ephemeral.secret.zeroize();
+                    return Err(err);
+                }
+            };
+
+        let mut bytes = [0u8; HELLO_BYTES];
+        bytes[0] = SESSION_VERSION;
+        bytes[1] = HELLO_TYPE;
+        bytes[2..2 + KYBER_PUBLIC_KEY_BYTES]
+            .copy_from_slice(&ephemeral.public);
+        bytes[2 + KYBER_PUBLIC_KEY_BYTES..]
+            .copy_from_slice(&ciphertext);
+        let hello = HelloMessage { bytes };
+
+        let awaiting = AwaitingResponse {
+            ephemeral_secret: ephemeral.secret,
+            static_secret: shared_secret,
+            hello: hello.clone(),
+        };
+        ephemeral.secret.zeroize();
+        shared_secret.zeroize();
+        Ok((hello, awaiting))
+    }
+}
+
+/// Entry point for the side that answers a handshake.
+#[derive(Clone, Copy, Debug)]
+pub struct Responder;
+
+impl Responder {
+    /// Answers `hello` using the static secret key it was addressed to.
+    ///
+    /// Kyber decapsulation never fails outright, so if `hello` was made for
+    /// another key the handshake only fails when the initiator checks the
+    /// confirmation tag in the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::RandomBytesGeneration` if the RNG fails.
+    #[must_use = "dropping the session discards the derived keys"]
+    pub fn accept<R>(
+        hello: &HelloMessage,
+        local_sk: &SecretKey,
+        rng: &mut R,
+    ) -> Result<(ResponseMessage, Established), KyberLibError>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut static_secret =
+            decapsulate(hello.ciphertext(), local_sk)?;
+        let (ciphertext, mut ephemeral_secret) =
+            match encapsulate(hello.ephemeral_public(), rng) {
+                Ok(encapsulated) => encapsulated,
+                Err(err) => {
+                    static_secret.zeroize();
+                    return Err(err);
+                }
+            };
+
+        let mut okm = derive_keys(
+            &static_secret,
+            &ephemeral_secret,
+            hello,
+            &ciphertext,
+        );
+        static_secret.zeroize();
+        ephemeral_secret.zeroize();
+
+        let mut bytes = [0u8; RESPONSE_BYTES];
+        bytes[0] = SESSION_VERSION;
+        bytes[1] = RESPONSE_TYPE;
+        bytes[2..2 + KYBER_CIPHERTEXT_BYTES]
+            .copy_from_slice(&ciphertext);
+        bytes[2 + KYBER_CIPHERTEXT_BYTES..]
+            .copy_from_slice(&okm[2 * SESSION_KEY_BYTES..]);
+
+        let established = Established::from_okm(&okm, false);
+        okm.zeroize();
+        Ok((ResponseMessage { bytes }, established))
+    }
+}
+
+/// Initiator state between sending the hello and receiving the response.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct AwaitingResponse {
+    ephemeral_secret: SecretKey,
+    static_secret: SharedSecret,
+    #[zeroize(skip)]
+    hello: HelloMessage,
+}
+
+impl core::fmt::Debug for AwaitingResponse {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_struct("AwaitingResponse").finish_non_exhaustive()
+    }
+}
+
+impl AwaitingResponse {
+    /// Completes the handshake with the responder's answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::Decapsulation` if the confirmation tag does
+    /// not match, i.e. the response was tampered with, belongs to another
+    /// handshake, or the hello reached a responder with a different key.
+    #[must_use = "dropping the session discards the derived keys"]
+    pub fn finish(
+        self,
+        response: &ResponseMessage,
+    ) -> Result<Established, KyberLibError> {
+        let mut ephemeral_secret =
+            decapsulate(response.ciphertext(), &self.ephemeral_secret)?;
+        let mut okm = derive_keys(
+            &self.static_secret,
+            &ephemeral_secret,
+            &self.hello,
+            response.ciphertext(),
+        );
+        ephemeral_secret.zeroize();
+
+        let result = if verify(
+            &okm[2 * SESSION_KEY_BYTES..],
+            response.tag(),
+            SESSION_TAG_BYTES,
+        ) == 0
+        {
+            Ok(Established::from_okm(&okm, true))
+        } else {
+            Err(KyberLibError::Decapsulation)
+        };
+        okm.zeroize();
+        result
+    }
+}
+
+/// A completed handshake holding one key per direction.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Established {
+    sending: [u8; SESSION_KEY_BYTES],
+    receiving: [u8; SESSION_KEY_BYTES],
+}
+
+impl core::fmt::Debug for Established {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_struct("Established").finish_non_exhaustive()
+    }
+}
+
+impl Established {
+    /// Key protecting messages this side sends.
+    pub fn sending_key(&self) -> &[u8; SESSION_KEY_BYTES] {
+        &self.sending
+    }
+
+    /// Key protecting messages this side receives.
+    pub fn receiving_key(&self) -> &[u8; SESSION_KEY_BYTES] {
+        &self.receiving
+    }
+
+    fn from_okm(okm: &[u8; OKM_BYTES], initiator: bool) -> Self {
+        let mut initiator_to_responder = [0u8; SESSION_KEY_BYTES];
+        let mut responder_to_initiator = [0u8; SESSION_KEY_BYTES];
+        initiator_to_responder
+            .copy_from_slice(&okm[..SESSION_KEY_BYTES]);
+        responder_to_initiator.copy_from_slice(
+            &okm[SESSION_KEY_BYTES..2 * SESSION_KEY_BYTES],
+        );
+        let session = if initiator {
+            Self {
+                sending: initiator_to_responder,
+                receiving: responder_to_initiator,
+            }
+        } else {
+            Self {
+                sending: responder_to_initiator,
+                receiving: initiator_to_responder,
+            }
+        };
+        initiator_to_responder.zeroize();
+        responder_to_initiator.zeroize();
+        session
+    }
+}
+
+const OKM_BYTES: usize = 2 * SESSION_KEY_BYTES + SESSION_TAG_BYTES;
+
+// Both shared secrets and the full transcript feed the labeled KDF; the
+// output is split into the two directional keys and the confirmation tag.
+fn derive_keys(
+    static_secret: &SharedSecret,
+    ephemeral_secret: &SharedSecret,
+    hello: &HelloMessage,
+    response_ciphertext: &[u8],
+) -> [u8; OKM_BYTES] {
+    let mut okm = [0u8; OKM_BYTES];
+    labeled_kdf(
+        &mut okm,
+        SESSION_LABEL,
+        &[
+            static_secret,
+            ephemeral_secret,
+            &hello.bytes,
+            response_ciphertext,
+        ],
+    );
+    okm
+}
+
+fn parse_header<const N: usize>(
+    bytes: &[u8],
+    message_type: u8,
+) -> Result<[u8; N], KyberLibError> {
+    if bytes.len() != N {
+        return Err(KyberLibError::InvalidLength);
+    }
+    if bytes[0] != SESSION_VERSION || bytes[1] != message_type {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut out = [0u8; N];
+    out.copy_from_slice(bytes);
+    Ok(out)
+}
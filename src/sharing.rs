@@ -0,0 +1,372 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Shamir secret sharing of Kyber secret keys.
+//!
+//! A Kyber secret key is fully determined by the 64-byte seed accepted by
+//! [`derive`](crate::derive), so the seed rather than the 3 KiB secret key
+//! is split. Each byte of the seed is shared with Shamir's scheme over
+//! GF(256), and every share carries the fingerprint of the public key so
+//! that recovery can check it re-derived the right key.
+//!
+//! The secret key cannot be compressed back into its seed, so the seed has
+//! to be kept from key generation for as long as the key may be split.
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::sharing::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let mut seed = [0u8; 64];
+//! rng.fill_bytes(&mut seed);
+//! let keys = derive(&seed)?;
+//!
+//! let shares = split_secret_key(&keys.secret, &seed, 3, 5, &mut rng)?;
+//! let secret = recover_secret_key(&shares[1..4])?;
+//! assert_eq!(secret, keys.secret);
+//! # Ok(()) }
+//! ```
+
+extern crate alloc;
+
+use crate::{
+    api::derive, error::KyberLibError, kex::SecretKey, params::*,
+    symmetric::hash_h, verify::verify, CryptoRng, RngCore,
+};
+use alloc::vec::Vec;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Size in bytes of the key seed that is shared.
+pub const SHARE_SEED_BYTES: usize = 2 * KYBER_SYM_BYTES;
+/// Size in bytes of the public key fingerprint and integrity tag.
+pub const SHARE_HASH_BYTES: usize = 32;
+/// Size in bytes of a serialised share.
+pub const SHARE_BYTES: usize =
+    2 + SHARE_HASH_BYTES + SHARE_SEED_BYTES + SHARE_HASH_BYTES;
+
+const SHARE_TAG_DOMAIN: &[u8] = b"kyberlib-share-v1";
+
+/// One custodian's share of a secret key.
+///
+/// The serialised layout is:
+///
+/// | Bytes | Field |
+/// |-------|-------|
+/// | 1     | share index, from 1 to 255 |
+/// | 1     | number of shares needed for recovery |
+/// | 32    | fingerprint of the public key |
+/// | 64    | share of the key seed |
+/// | 32    | integrity tag over the preceding fields |
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct Share {
+    index: u8,
+    threshold: u8,
+    fingerprint: [u8; SHARE_HASH_BYTES],
+    value: [u8; SHARE_SEED_BYTES],
+    tag: [u8; SHARE_HASH_BYTES],
+}
+
+impl Share {
+    /// The index of the share, the point at which the sharing polynomial
+    /// was evaluated.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// The number of shares needed to recover the key.
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// The fingerprint of the public key the share belongs to.
+    pub fn fingerprint(&self) -> &[u8; SHARE_HASH_BYTES] {
+        &self.fingerprint
+    }
+
+    /// Returns the serialised share.
+    pub fn to_bytes(&self) -> [u8; SHARE_BYTES] {
+        let mut bytes = [0u8; SHARE_BYTES];
+        self.write_body(&mut bytes);
+        bytes[SHARE_BYTES - SHARE_HASH_BYTES..]
+            .copy_from_slice(&self.tag);
+        bytes
+    }
+
+    /// Parses a share produced by [`Share::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `KyberLibError::InvalidLength` if `bytes` has the wrong size
+    /// and `KyberLibError::InvalidShare` if the integrity tag does not match.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KyberLibError> {
+        if bytes.len() != SHARE_BYTES {
+            return Err(KyberLibError::InvalidLength);
+        }
+        let mut share = Share {
+            index: bytes[0],
+            threshold: bytes[1],
+            fingerprint: [0u8; SHARE_HASH_BYTES],
+            value: [0u8; SHARE_SEED_BYTES],
+            tag: [0u8; SHARE_HASH_BYTES],
+        };
+        share
+            .fingerprint
+            .copy_from_slice(&bytes[2..2 + SHARE_HASH_BYTES]);
+        share.value.copy_from_slice(
+            &bytes
+                [2 + SHARE_HASH_BYTES..SHARE_BYTES - SHARE_HASH_BYTES],
+        );
+        share
+            .tag
+            .copy_from_slice(&bytes[SHARE_BYTES - SHARE_HASH_BYTES..]);
+        share.check()?;
+        Ok(share)
+    }
+
+    fn write_body(&self, bytes: &mut [u8; SHARE_BYTES]) {
+        bytes[0] = self.index;
+        bytes[1] = self.threshold;
+        bytes[2..2 + SHARE_HASH_BYTES]
+            .copy_from_slice(&self.fingerprint);
+        bytes[2 + SHARE_HASH_BYTES..SHARE_BYTES - SHARE_HASH_BYTES]
+            .copy_from_slice(&self.value);
+    }
+
+    fn compute_tag(&self) -> [u8; SHARE_HASH_BYTES] {
+        const INPUT_BYTES: usize =
+            SHARE_TAG_DOMAIN.len() + SHARE_BYTES - SHARE_HASH_BYTES;
+        let mut input = [0u8; SHARE_TAG_DOMAIN.len() + SHARE_BYTES];
+        input[..SHARE_TAG_DOMAIN.len()]
+            .copy_from_slice(SHARE_TAG_DOMAIN);
+        let mut body = [0u8; SHARE_BYTES];
+        self.write_body(&mut body);
+        input[SHARE_TAG_DOMAIN.len()..].copy_from_slice(&body);
+        let mut tag = [0u8; SHARE_HASH_BYTES];
+        hash_h(&mut tag, &input, INPUT_BYTES);
+        body.zeroize();
+        input.zeroize();
+        tag
+    }
+
+    fn check(&self) -> Result<(), KyberLibError> {
+        let tag = self.compute_tag();
+        if self.index == 0
+            || self.threshold < 2
+            || verify(&tag, &self.tag, SHARE_HASH_BYTES) != 0
+        {
+            return Err(KyberLibError::InvalidShare);
+        }
+        Ok(())
+    }
+}
+
+/// Splits a secret key into `shares` shares, any `threshold` of which
+/// recover it.
+///
+/// `seed` must be the 64-byte seed the key was derived from with
+/// [`derive`](crate::derive); it is what gets shared.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` unless
+/// `2 <= threshold <= shares <= 255`, `KyberLibError::InvalidKey` if `seed`
+/// does not derive `secret`, and `KyberLibError::RandomBytesGeneration` if
+/// the RNG fails.
+#[must_use = "dropping the shares discards the split secret key"]
+pub fn split_secret_key<R>(
+    secret: &SecretKey,
+    seed: &[u8; SHARE_SEED_BYTES],
+    threshold: u8,
+    shares: u8,
+    rng: &mut R,
+) -> Result<Vec<Share>, KyberLibError>
+where
+    R: RngCore + CryptoRng,
+{
+    if threshold < 2 || shares < threshold {
+        return Err(KyberLibError::InvalidInput);
+    }
+    let mut keys = derive(seed)?;
+    let matches = verify(&keys.secret, secret, KYBER_SECRET_KEY_BYTES);
+    keys.secret.zeroize();
+    if matches != 0 {
+        return Err(KyberLibError::InvalidKey);
+    }
+    let mut fingerprint = [0u8; SHARE_HASH_BYTES];
+    hash_h(&mut fingerprint, &keys.public, KYBER_PUBLIC_KEY_BYTES);
+
+    // coeffs[i] holds the coefficient of x^(i + 1) for every seed byte.
+    let mut coeffs = Vec::with_capacity(usize::from(threshold) - 1);
+    for _ in 1..threshold {
+        let mut coeff = [0u8; SHARE_SEED_BYTES];
+        if rng.try_fill_bytes(&mut coeff).is_err() {
+            coeffs.zeroize();
+            return Err(KyberLibError::RandomBytesGeneration);
+        }
+        coeffs.push(coeff);
+    }
+
+    let mut result = Vec::with_capacity(usize::from(shares));
+    for index in 1..=shares {
+        let mut share = Share {
+            index,
+            threshold,
+            fingerprint,
+            value: [0u8; SHARE_SEED_BYTES],
+            tag: [0u8; SHARE_HASH_BYTES],
+        };
+        for (j, byte) in share.value.iter_mut().enumerate() {
+            // Horner evaluation of the sharing polynomial at `index`.
+            let mut y = 0u8;
+            for coeff in coeffs.iter().rev() {
+                y = gf256_mul(y, index) ^ coeff[j];
+            }
+            *byte = gf256_mul(y, index) ^ seed[j];
+        }
+        share.tag = share.compute_tag();
+        result.push(share);
+    }
+    coeffs.zeroize();
+    Ok(result)
+}
+
+/// Recovers a secret key from its shares.
+///
+/// The first `threshold` shares are interpolated to the key seed, which is
+/// expanded with [`derive`](crate::derive) and checked against the public
+/// key fingerprint recorded in the shares. The seed and the other derived
+/// material are zeroized before returning.
+///
+/// # Errors
+///
+/// Returns `KyberLibError::InvalidInput` if fewer shares than the threshold
+/// are given, and `KyberLibError::InvalidShare` if a share is corrupted,
+/// repeated, or belongs to another key.
+#[must_use = "dropping the result discards the recovered secret key"]
+pub fn recover_secret_key(
+    shares: &[Share],
+) -> Result<SecretKey, KyberLibError> {
+    let mut seed = [0u8; SHARE_SEED_BYTES];
+    recover_into(shares, &mut seed)
+}
+
+// Does the work of `recover_secret_key`, leaving `seed` zeroized on every
+// path so tests can observe it.
+fn recover_into(
+    shares: &[Share],
+    seed: &mut [u8; SHARE_SEED_BYTES],
+) -> Result<SecretKey, KyberLibError> {
+    let first = shares.first().ok_or(KyberLibError::InvalidInput)?;
+    let threshold = usize::from(first.threshold);
+    for (i, share) in shares.iter().enumerate() {
+        share.check()?;
+        if share.threshold != first.threshold
+            || share.fingerprint != first.fingerprint
+            || shares[..i].iter().any(|s| s.index == share.index)
+        {
+            return Err(KyberLibError::InvalidShare);
+        }
+    }
+    if shares.len() < threshold {
+        return Err(KyberLibError::InvalidInput);
+    }
+
+    let shares = &shares[..threshold];
+    for (j, share) in shares.iter().enumerate() {
+        // Lagrange basis polynomial for this share, evaluated at zero.
+        let mut basis = 1u8;
+        for (m, other) in shares.iter().enumerate() {
+            if m != j {
+                basis = gf256_mul(
+                    basis,
+                    gf256_mul(
+                        other.index,
+                        gf256_inv(other.index ^ share.index),
+                    ),
+                );
+            }
+        }
+        for (byte, y) in seed.iter_mut().zip(share.value.iter()) {
+            *byte ^= gf256_mul(basis, *y);
+        }
+    }
+
+    let keys = derive(seed);
+    seed.zeroize();
+    let mut keys = keys?;
+    let mut fingerprint = [0u8; SHARE_HASH_BYTES];
+    hash_h(&mut fingerprint, &keys.public, KYBER_PUBLIC_KEY_BYTES);
+    if verify(&fingerprint, &first.fingerprint, SHARE_HASH_BYTES) != 0 {
+        keys.secret.zeroize();
+        return Err(KyberLibError::InvalidShare);
+    }
+    Ok(keys.secret)
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, without
+// secret-dependent branches.
+fn gf256_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut r = 0u8;
+    for _ in 0..8 {
+        r ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    r
+}
+
+// Inverse in GF(2^8) as a^254.
+fn gf256_inv(a: u8) -> u8 {
+    let a2 = gf256_mul(a, a);
+    let a4 = gf256_mul(a2, a2);
+    let a8 = gf256_mul(a4, a4);
+    let a16 = gf256_mul(a8, a8);
+    let a32 = gf256_mul(a16, a16);
+    let a64 = gf256_mul(a32, a32);
+    let a128 = gf256_mul(a64, a64);
+    let mut r = gf256_mul(a128, a64);
+    r = gf256_mul(r, a32);
+    r = gf256_mul(r, a16);
+    r = gf256_mul(r, a8);
+    r = gf256_mul(r, a4);
+    gf256_mul(r, a2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gf256_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf256_mul(a, gf256_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn recovery_zeroizes_seed() {
+        let mut rng = rand::thread_rng();
+        let mut seed = [0u8; SHARE_SEED_BYTES];
+        rng.fill_bytes(&mut seed);
+        let keys = derive(&seed).unwrap();
+        let shares =
+            split_secret_key(&keys.secret, &seed, 2, 3, &mut rng)
+                .unwrap();
+
+        let mut scratch = [0u8; SHARE_SEED_BYTES];
+        let secret = recover_into(&shares[..2], &mut scratch).unwrap();
+        assert_eq!(secret, keys.secret);
+        assert_eq!(scratch, [0u8; SHARE_SEED_BYTES]);
+
+        // A share whose value no longer matches the fingerprint.
+        let mut forged = shares[1].clone();
+        forged.value[0] ^= 1;
+        forged.tag = forged.compute_tag();
+        let result =
+            recover_into(&[shares[0].clone(), forged], &mut scratch);
+        assert_eq!(result, Err(KyberLibError::InvalidShare));
+        assert_eq!(scratch, [0u8; SHARE_SEED_BYTES]);
+    }
+}
@@ -0,0 +1,529 @@
+//! The IND-CPA layer and differential tests shared by the SIMD
+//! backends.
+//!
+//! The AVX-512, NEON, WebAssembly, RVV and portable SIMD backends only
+//! differ in their kernels. [`indcpa_layer`] writes key generation,
+//! encryption and decryption over those kernels once, and each backend
+//! expands it with its own `#[target_feature]` so that the kernels
+//! inline into it. Backends that reuse the reference matrix sampling,
+//! (de)compression and message decoding take those steps from
+//! [`reference_coding`]. The `differential` module checks the kernels
+//! and the layer of every backend against the reference
+//! implementation.
+
+/// Expands to `indcpa_keypair`, `indcpa_enc` and `indcpa_dec` and their
+/// `pack_pk`/`unpack_pk` helpers, each carrying the given attributes and
+/// declared `unsafe` if the invocation ends in `unsafe`.
+///
+/// The functions call the following by name, so the invoking module
+/// must have them in scope: `Poly`, `Polyvec`, `poly_add`, `poly_sub`,
+/// `poly_tomont`, `poly_frommsg`, `poly_invntt_tomont`,
+/// `poly_getnoise_eta1`, `poly_getnoise_eta2`, `polyvec_add`,
+/// `polyvec_ntt`, `polyvec_invntt_tomont`, `polyvec_reduce`,
+/// `polyvec_basemul_acc_montgomery`, `polyvec_tobytes` and
+/// `polyvec_frombytes`, plus the backend-specific steps:
+///
+/// - `gen_matrix(a: &mut [Polyvec], seed: &[u8], transposed: bool)`
+/// - `pack_ciphertext(r: &mut [u8], b: &mut Polyvec, v: &mut Poly)`,
+///   which reduces b and v into the range its compression takes
+/// - `unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8])`
+/// - `decode_message(m: &mut [u8], mp: &mut Poly)`, which reduces mp
+///   into the range its message decoding takes
+macro_rules! indcpa_layer {
+    ($(#[$attr:meta])* $($unsafety:ident)?) => {
+        /// Name:  pack_pk
+        ///
+        /// Description: Serialize the public key as concatenation of the
+        ///  serialized vector of polynomials pk
+        ///  and the public seed used to generate the matrix A.
+        ///
+        /// Arguments:   [u8] r:  the output serialized public key
+        ///  const poly *pk:  the input public-key polynomial
+        ///  const [u8] seed: the input public seed
+        $(#[$attr])*
+        $($unsafety)? fn pack_pk(
+            r: &mut [u8],
+            pk: &Polyvec,
+            seed: &[u8],
+        ) {
+            use $crate::params::{KYBER_POLYVEC_BYTES, KYBER_SYM_BYTES};
+            const END: usize = KYBER_SYM_BYTES + KYBER_POLYVEC_BYTES;
+            polyvec_tobytes(r, pk);
+            r[KYBER_POLYVEC_BYTES..END]
+                .copy_from_slice(&seed[..KYBER_SYM_BYTES]);
+        }
+
+        /// Name:  unpack_pk
+        ///
+        /// Description: De-serialize public key from a byte array;
+        ///  approximate inverse of pack_pk
+        ///
+        /// Arguments:   - Polyvec pk:  output public-key vector of polynomials
+        ///  - [u8] seed:   output seed to generate matrix A
+        ///  - const [u8] packedpk: input serialized public key
+        $(#[$attr])*
+        $($unsafety)? fn unpack_pk(
+            pk: &mut Polyvec,
+            seed: &mut [u8],
+            packedpk: &[u8],
+        ) {
+            use $crate::params::{KYBER_POLYVEC_BYTES, KYBER_SYM_BYTES};
+            const END: usize = KYBER_SYM_BYTES + KYBER_POLYVEC_BYTES;
+            polyvec_frombytes(pk, packedpk);
+            seed[..KYBER_SYM_BYTES]
+                .copy_from_slice(&packedpk[KYBER_POLYVEC_BYTES..END]);
+        }
+
+        /// Name:  indcpa_keypair
+        ///
+        /// Description: Generates public and private key for the CPA-secure
+        ///  public-key encryption scheme underlying Kyber
+        ///
+        /// Arguments: - [u8] pk: output public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
+        ///  - [u8] sk: output private key (length KYBER_INDCPA_SECRET_KEY_BYTES)
+        $(#[$attr])*
+        pub(crate) $($unsafety)? fn indcpa_keypair<R>(
+            pk: &mut [u8],
+            sk: &mut [u8],
+            _seed: Option<(&[u8], &[u8])>,
+            _rng: &mut R,
+        ) -> Result<(), $crate::KyberLibError>
+        where
+            R: $crate::CryptoRng + $crate::RngCore,
+        {
+            use $crate::params::{
+                KYBER_SECURITY_PARAMETER, KYBER_SYM_BYTES,
+            };
+            let mut a = [Polyvec::new(); KYBER_SECURITY_PARAMETER];
+            let (mut e, mut pkpv, mut skpv) =
+                (Polyvec::new(), Polyvec::new(), Polyvec::new());
+            let mut nonce = 0u8;
+            let mut buf = [0u8; 2 * KYBER_SYM_BYTES];
+            let mut randbuf = [0u8; 2 * KYBER_SYM_BYTES];
+
+            if let Some(s) = _seed {
+                randbuf[..KYBER_SYM_BYTES].copy_from_slice(s.0);
+            } else {
+                $crate::rng::randombytes(
+                    &mut randbuf,
+                    KYBER_SYM_BYTES,
+                    _rng,
+                )?;
+            }
+
+            $crate::symmetric::hash_g(
+                &mut buf,
+                &randbuf,
+                KYBER_SYM_BYTES,
+            );
+
+            let (publicseed, noiseseed) = buf.split_at(KYBER_SYM_BYTES);
+            gen_matrix(&mut a, publicseed, false);
+
+            for poly in skpv.vec.iter_mut() {
+                poly_getnoise_eta1(poly, noiseseed, nonce);
+                nonce += 1;
+            }
+            for poly in e.vec.iter_mut() {
+                poly_getnoise_eta1(poly, noiseseed, nonce);
+                nonce += 1;
+            }
+
+            polyvec_ntt(&mut skpv);
+            polyvec_ntt(&mut e);
+
+            // matrix-vector multiplication
+            for (t, row) in pkpv.vec.iter_mut().zip(a.iter()) {
+                polyvec_basemul_acc_montgomery(t, row, &skpv);
+                poly_tomont(t);
+            }
+            polyvec_add(&mut pkpv, &e);
+            polyvec_reduce(&mut pkpv);
+
+            polyvec_tobytes(sk, &skpv);
+            pack_pk(pk, &pkpv, publicseed);
+            Ok(())
+        }
+
+        /// Name:  indcpa_enc
+        ///
+        /// Description: Encryption function of the CPA-secure
+        ///  public-key encryption scheme underlying Kyber.
+        ///
+        /// Arguments:
+        ///  - const [u8] c:    output ciphertext (length KYBER_INDCPA_BYTES)
+        ///  - const [u8] m:    input message (length KYBER_SYM_BYTES)
+        ///  - const [u8] pk:   input public key (length KYBER_INDCPA_PUBLIC_KEY_BYTES)
+        ///  - const [u8] coin: input random coins used as seed (length KYBER_SYM_BYTES)
+        ///    to deterministically generate all randomness
+        $(#[$attr])*
+        pub(crate) $($unsafety)? fn indcpa_enc(
+            c: &mut [u8],
+            m: &[u8],
+            pk: &[u8],
+            coins: &[u8],
+        ) {
+            use $crate::params::{
+                KYBER_SECURITY_PARAMETER, KYBER_SYM_BYTES,
+            };
+            let mut at = [Polyvec::new(); KYBER_SECURITY_PARAMETER];
+            let (mut sp, mut pkpv, mut ep, mut b) = (
+                Polyvec::new(),
+                Polyvec::new(),
+                Polyvec::new(),
+                Polyvec::new(),
+            );
+            let (mut v, mut k, mut epp) =
+                (Poly::new(), Poly::new(), Poly::new());
+            let mut seed = [0u8; KYBER_SYM_BYTES];
+            let mut nonce = 0u8;
+
+            unpack_pk(&mut pkpv, &mut seed, pk);
+            poly_frommsg(&mut k, m);
+            gen_matrix(&mut at, &seed, true);
+
+            for poly in sp.vec.iter_mut() {
+                poly_getnoise_eta1(poly, coins, nonce);
+                nonce += 1;
+            }
+            for poly in ep.vec.iter_mut() {
+                poly_getnoise_eta2(poly, coins, nonce);
+                nonce += 1;
+            }
+            poly_getnoise_eta2(&mut epp, coins, nonce);
+
+            polyvec_ntt(&mut sp);
+
+            // matrix-vector multiplication
+            for (t, row) in b.vec.iter_mut().zip(at.iter()) {
+                polyvec_basemul_acc_montgomery(t, row, &sp);
+            }
+
+            polyvec_basemul_acc_montgomery(&mut v, &pkpv, &sp);
+            polyvec_invntt_tomont(&mut b);
+            poly_invntt_tomont(&mut v);
+
+            polyvec_add(&mut b, &ep);
+            poly_add(&mut v, &epp);
+            poly_add(&mut v, &k);
+
+            pack_ciphertext(c, &mut b, &mut v);
+        }
+
+        /// Name:  indcpa_dec
+        ///
+        /// Description: Decryption function of the CPA-secure
+        ///  public-key encryption scheme underlying Kyber.
+        ///
+        /// Arguments:
+        ///  - const [u8] m:    output decrypted message (of length KYBER_SYM_BYTES)
+        ///  - const [u8] c:    input ciphertext (of length KYBER_INDCPA_BYTES)
+        ///  - const [u8] sk:   input secret key (of length KYBER_INDCPA_SECRET_KEY_BYTES)
+        $(#[$attr])*
+        pub(crate) $($unsafety)? fn indcpa_dec(
+            m: &mut [u8],
+            c: &[u8],
+            sk: &[u8],
+        ) {
+            let (mut b, mut skpv) = (Polyvec::new(), Polyvec::new());
+            let (mut v, mut mp) = (Poly::new(), Poly::new());
+
+            unpack_ciphertext(&mut b, &mut v, c);
+            polyvec_frombytes(&mut skpv, sk);
+
+            polyvec_ntt(&mut b);
+            polyvec_basemul_acc_montgomery(&mut mp, &skpv, &b);
+            poly_invntt_tomont(&mut mp);
+
+            poly_sub(&mut mp, &v);
+            decode_message(m, &mut mp);
+        }
+    };
+}
+
+pub(crate) use indcpa_layer;
+
+#[cfg(any(
+    all(
+        target_arch = "aarch64",
+        target_feature = "neon",
+        feature = "neon"
+    ),
+    all(
+        target_arch = "wasm32",
+        target_feature = "simd128",
+        feature = "wasm-simd"
+    ),
+    all(target_arch = "riscv64", feature = "rvv"),
+    all(
+        feature = "portable-simd",
+        not(all(target_arch = "x86_64", feature = "avx2"))
+    )
+))]
+/// Expands to the `gen_matrix`, `pack_ciphertext`, `unpack_ciphertext`
+/// and `decode_message` steps of [`indcpa_layer`] built on the reference
+/// matrix sampling and message decoding, with the attributes and
+/// `unsafe` handled as there.
+///
+/// Besides `Poly` and `Polyvec`, the invoking module must have
+/// `poly_reduce`, `polyvec_reduce`, `poly_csubq`, `poly_tomsg` and the
+/// by-value `poly_compress`, `polyvec_compress`, `poly_decompress` and
+/// `polyvec_decompress` of the reference implementation, or its own
+/// kernels with the same signatures, in scope.
+macro_rules! reference_coding {
+    ($(#[$attr:meta])* $($unsafety:ident)?) => {
+        /// Name:  gen_matrix
+        ///
+        /// Description: Deterministically generate matrix A (or the transpose of A)
+        ///  from a seed, with the rejection sampling of the reference
+        ///
+        /// Arguments:   - Polyvec a: ouptput matrix A
+        ///  - const [u8] seed: input seed
+        ///  - bool transposed: boolean deciding whether A or A^T is generated
+        $(#[$attr])*
+        $($unsafety)? fn gen_matrix(
+            a: &mut [Polyvec],
+            seed: &[u8],
+            transposed: bool,
+        ) {
+            use $crate::indcpa::{gen_matrix_entry, GEN_MATRIX_BUFBYTES};
+            let mut buf = [0u8; GEN_MATRIX_BUFBYTES];
+            for (i, row) in a.iter_mut().enumerate() {
+                for (j, entry) in row.vec.iter_mut().enumerate() {
+                    let [x, y] = $crate::symmetric::matrix_index_bytes(
+                        i, j, transposed,
+                    );
+                    gen_matrix_entry(entry, &mut buf, seed, x, y);
+                }
+            }
+        }
+
+        /// Name:  pack_ciphertext
+        ///
+        /// Description: Reduce b and v, then serialize the ciphertext as
+        ///  concatenation of the compressed and serialized vector of
+        ///  polynomials b and the compressed and serialized polynomial v
+        ///
+        /// Arguments:   [u8] r:  the output serialized ciphertext
+        ///  - Polyvec b: the input vector of polynomials b
+        ///  - poly *v:   the input polynomial v
+        $(#[$attr])*
+        $($unsafety)? fn pack_ciphertext(
+            r: &mut [u8],
+            b: &mut Polyvec,
+            v: &mut Poly,
+        ) {
+            use $crate::params::KYBER_POLYVEC_COMPRESSED_BYTES;
+            polyvec_reduce(b);
+            poly_reduce(v);
+            polyvec_compress(r, *b);
+            poly_compress(&mut r[KYBER_POLYVEC_COMPRESSED_BYTES..], *v);
+        }
+
+        /// Name:  unpack_ciphertext
+        ///
+        /// Description: De-serialize and decompress ciphertext from a byte array;
+        ///  approximate inverse of pack_ciphertext
+        ///
+        /// Arguments:   - Polyvec b:   output vector of polynomials b
+        ///  - poly *v:  output polynomial v
+        ///  - const [u8] c:   input serialized ciphertext
+        $(#[$attr])*
+        $($unsafety)? fn unpack_ciphertext(
+            b: &mut Polyvec,
+            v: &mut Poly,
+            c: &[u8],
+        ) {
+            use $crate::params::KYBER_POLYVEC_COMPRESSED_BYTES;
+            polyvec_decompress(b, c);
+            poly_decompress(v, &c[KYBER_POLYVEC_COMPRESSED_BYTES..]);
+        }
+
+        /// Name:  decode_message
+        ///
+        /// Description: Reduce mp to the standard representatives and
+        ///  convert it to a message
+        ///
+        /// Arguments:   - [u8] m: output message (of length KYBER_SYM_BYTES)
+        ///  - poly *mp: input/output polynomial
+        $(#[$attr])*
+        $($unsafety)? fn decode_message(m: &mut [u8], mp: &mut Poly) {
+            poly_reduce(mp);
+            poly_csubq(mp);
+            poly_tomsg(m, *mp);
+        }
+    };
+}
+#[cfg(any(
+    all(
+        target_arch = "aarch64",
+        target_feature = "neon",
+        feature = "neon"
+    ),
+    all(
+        target_arch = "wasm32",
+        target_feature = "simd128",
+        feature = "wasm-simd"
+    ),
+    all(target_arch = "riscv64", feature = "rvv"),
+    all(
+        feature = "portable-simd",
+        not(all(target_arch = "x86_64", feature = "avx2"))
+    )
+))]
+pub(crate) use reference_coding;
+
+/// Differential tests of the SIMD kernels against the reference ones.
+///
+/// Each backend wraps its kernels in closures over the reference `Poly`
+/// and `Polyvec` and calls the checks for the kernels it has.
+#[cfg(test)]
+#[allow(dead_code)] // no backend has every kernel
+pub(crate) mod differential {
+    use crate::{
+        params::*,
+        reference,
+        reference::poly::{set_domain, Poly},
+        reference::polyvec::Polyvec,
+        KyberLibError,
+    };
+    use rand::{rngs::OsRng, Rng, RngCore};
+
+    const ROUNDS: usize = 64;
+
+    pub(crate) fn random<const N: usize>() -> [u8; N] {
+        let mut buf = [0u8; N];
+        OsRng.fill_bytes(&mut buf);
+        buf
+    }
+
+    /// A polynomial with coefficients in -bound..bound, which the
+    /// reference kernels take in either domain under `debug-domain`.
+    pub(crate) fn random_poly(bound: i16) -> Poly {
+        let mut r = Poly::new();
+        for c in r.coeffs.iter_mut() {
+            *c = OsRng.gen_range(-bound..bound);
+        }
+        set_domain!(r, Any);
+        r
+    }
+
+    pub(crate) fn ntt(
+        ntt: impl Fn(&mut Poly),
+        invntt_tomont: impl Fn(&mut Poly),
+    ) {
+        for _ in 0..ROUNDS {
+            let mut r = random_poly(KYBER_Q as i16);
+            let mut ref_r = r;
+            ntt(&mut r);
+            reference::poly::poly_ntt(&mut ref_r);
+            assert_eq!(r.coeffs, ref_r.coeffs);
+
+            invntt_tomont(&mut r);
+            reference::poly::poly_invntt_tomont(&mut ref_r);
+            assert_eq!(r.coeffs, ref_r.coeffs);
+        }
+    }
+
+    pub(crate) fn basemul(
+        basemul_acc: impl Fn(&mut Poly, &Polyvec, &Polyvec),
+    ) {
+        for _ in 0..ROUNDS {
+            let (mut a, mut b) = (Polyvec::new(), Polyvec::new());
+            for i in 0..KYBER_SECURITY_PARAMETER {
+                a.vec[i] = random_poly(KYBER_Q as i16);
+                b.vec[i] = random_poly(i16::MAX);
+            }
+            let (mut r, mut ref_r) = (Poly::new(), Poly::new());
+            basemul_acc(&mut r, &a, &b);
+            reference::polyvec::polyvec_basemul_acc_montgomery(
+                &mut ref_r, &a, &b,
+            );
+            assert_eq!(r.coeffs, ref_r.coeffs);
+        }
+    }
+
+    pub(crate) fn cbd2(cbd2: impl Fn(&mut Poly, &[u8])) {
+        for _ in 0..ROUNDS {
+            let buf = random::<{ 2 * KYBER_N / 4 }>();
+            let (mut r, mut ref_r) = (Poly::new(), Poly::new());
+            cbd2(&mut r, &buf);
+            reference::cbd::cbd2(&mut ref_r, &buf);
+            assert_eq!(r.coeffs, ref_r.coeffs);
+        }
+    }
+
+    pub(crate) fn serialization(
+        tobytes: impl Fn(&mut [u8], &Poly),
+        frombytes: impl Fn(&mut Poly, &[u8]),
+    ) {
+        for _ in 0..ROUNDS {
+            let r = random_poly(KYBER_Q as i16);
+            let mut bytes = [0u8; KYBER_POLY_BYTES];
+            let mut ref_bytes = [0u8; KYBER_POLY_BYTES];
+            tobytes(&mut bytes, &r);
+            reference::poly::poly_tobytes(&mut ref_bytes, r);
+            assert_eq!(bytes, ref_bytes);
+
+            let bytes = random::<KYBER_POLY_BYTES>();
+            let (mut r, mut ref_r) = (Poly::new(), Poly::new());
+            frombytes(&mut r, &bytes);
+            reference::poly::poly_frombytes(&mut ref_r, &bytes);
+            assert_eq!(r.coeffs, ref_r.coeffs);
+        }
+    }
+
+    pub(crate) fn indcpa(
+        keypair: impl Fn(
+            &mut [u8],
+            &mut [u8],
+            Option<(&[u8], &[u8])>,
+            &mut OsRng,
+        ) -> Result<(), KyberLibError>,
+        enc: impl Fn(&mut [u8], &[u8], &[u8], &[u8]),
+        dec: impl Fn(&mut [u8], &[u8], &[u8]),
+    ) {
+        let mut rng = OsRng;
+        for _ in 0..ROUNDS {
+            let (d, z) = (random::<KYBER_SYM_BYTES>(), [0u8; 32]);
+            let mut pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+            let mut sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+            let mut ref_pk = [0u8; KYBER_INDCPA_PUBLIC_KEY_BYTES];
+            let mut ref_sk = [0u8; KYBER_INDCPA_SECRET_KEY_BYTES];
+            keypair(&mut pk, &mut sk, Some((&d, &z)), &mut rng)
+                .unwrap();
+            reference::indcpa::indcpa_keypair(
+                &mut ref_pk,
+                &mut ref_sk,
+                Some((&d, &z)),
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(pk, ref_pk);
+            assert_eq!(sk, ref_sk);
+
+            let (m, coins) = (
+                random::<KYBER_SYM_BYTES>(),
+                random::<KYBER_SYM_BYTES>(),
+            );
+            let mut ct = [0u8; KYBER_INDCPA_BYTES];
+            let mut ref_ct = [0u8; KYBER_INDCPA_BYTES];
+            enc(&mut ct, &m, &pk, &coins);
+            reference::indcpa::indcpa_enc(&mut ref_ct, &m, &pk, &coins);
+            assert_eq!(ct, ref_ct);
+
+            let mut out = [0u8; KYBER_SYM_BYTES];
+            let mut ref_out = [0u8; KYBER_SYM_BYTES];
+            dec(&mut out, &ct, &sk);
+            reference::indcpa::indcpa_dec(&mut ref_out, &ct, &sk);
+            assert_eq!(out, m);
+            assert_eq!(out, ref_out);
+
+            // A tampered ciphertext decrypts to the same wrong message.
+            ct[0] ^= 1;
+            dec(&mut out, &ct, &sk);
+            reference::indcpa::indcpa_dec(&mut ref_out, &ct, &sk);
+            assert_eq!(out, ref_out);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Stack high-water mark measurement, for sizing embedded targets.
+//!
+//! [`measure`] runs a closure on a fresh thread whose stack has first been
+//! painted with a sentinel pattern, then scans for the deepest word the
+//! closure overwrote. The result is the peak number of bytes of stack the
+//! closure used below the probe's own frame, rounded up to a word; it may
+//! overstate by up to half a kilobyte, the unpainted gap kept below that
+//! frame for the red zone.
+//!
+//! [`measure_in_place`] paints and scans the current stack instead, for
+//! targets without threads or `std`: with only the `stack-probe-core`
+//! feature, firmware can report its own high-water marks, as the
+//! `stack-probe` binary of `embedded-defmt` does over defmt.
+//!
+//! This is a testing aid, not part of the cryptographic API: it reads and
+//! writes the unused part of the thread's stack through raw pointers,
+//! which is outside anything the Rust memory model describes. It is
+//! reliable on the usual hosted targets (the painted region lies inside
+//! the stack mapping Rust allocated for the thread, and nothing else
+//! touches it), but it will not run under Miri, and a signal handler
+//! running on the thread during the measurement would inflate the result.
+//!
+//! Stack use differs a lot between debug and release builds, and by
+//! target and compiler version, so thresholds should be set per profile
+//! with some headroom. `tests/test_stack_probe.rs` holds the ceilings
+//! this crate asserts for `keypair`, `encapsulate` and `decapsulate`, per
+//! parameter set and feature combination; run
+//! `cargo test --features stack-probe --test test_stack_probe -- --nocapture`
+//! to print the measurements.
+
+use core::mem::size_of;
+use core::ptr;
+#[cfg(feature = "stack-probe")]
+use std::thread;
+
+/// The largest stack use [`measure`] can report, in bytes. A closure that
+/// uses more is reported as using exactly this much.
+pub const MAX_DEPTH: usize = 512 * 1024;
+
+// Room above the painted region for the frames of the probe itself and of
+// the thread entry point, and below it for the guard page.
+#[cfg(feature = "stack-probe")]
+const HEADROOM: usize = 64 * 1024;
+
+// Bytes immediately below the frame of `paint` left unpainted, covering
+// the red zone. Anything reaching past them counts them as used.
+const GAP: usize = 512;
+
+const SENTINEL: usize =
+    usize::from_ne_bytes([0xa5; size_of::<usize>()]);
+const WORD: usize = size_of::<usize>();
+
+/// Runs `f` on a new thread and returns its result with the peak number
+/// of bytes of stack it used.
+///
+/// # Panics
+///
+/// Panics if the thread cannot be spawned, and resumes the panic if `f`
+/// panics.
+#[cfg(feature = "stack-probe")]
+pub fn measure<F, R>(f: F) -> (R, usize)
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let handle = thread::Builder::new()
+        .name("kyberlib-stack-probe".into())
+        .stack_size(MAX_DEPTH + 2 * HEADROOM)
+        .spawn(move || probe(MAX_DEPTH, f))
+        .expect("failed to spawn the stack probe thread");
+    match handle.join() {
+        Ok(result) => result,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+/// Runs `f` on the current stack and returns its result with the peak
+/// number of bytes of stack it used, at most `max_depth`.
+///
+/// The `max_depth` bytes from half a kilobyte below the caller's frame
+/// are painted before `f` runs, which takes time proportional to
+/// `max_depth`; choose it just above the expected use.
+///
+/// # Safety
+///
+/// The `max_depth + 512` bytes below the caller's frame must be unused
+/// stack of the current thread: mapped, writable, clear of any guard page
+/// or stack limit, and not holding a live value, e.g. one placed there by
+/// an interrupt handler running on the same stack. On a microcontroller
+/// this usually means the stack region in the linker script has that much
+/// room below `main`.
+pub unsafe fn measure_in_place<F, R>(
+    max_depth: usize,
+    f: F,
+) -> (R, usize)
+where
+    F: FnOnce() -> R,
+{
+    probe(max_depth, f)
+}
+
+// An address in the caller's frame, rounded down to a word.
+#[inline(always)]
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    let address = ptr::addr_of!(marker) as usize;
+    core::hint::black_box(address) & !(WORD - 1)
+}
+
+#[inline(never)]
+fn probe<F: FnOnce() -> R, R>(max_depth: usize, f: F) -> (R, usize) {
+    let (bottom, origin) = paint(max_depth);
+    let result = run(f);
+    let depth = origin - scan(bottom, origin - GAP);
+    (result, depth)
+}
+
+#[inline(never)]
+fn run<F: FnOnce() -> R, R>(f: F) -> R {
+    core::hint::black_box(f())
+}
+
+// Fills the `max_depth` bytes from `GAP` below this frame with the
+// sentinel, and returns the bottom of the painted region and the address
+// of this frame, from which depths are measured.
+#[inline(never)]
+fn paint(max_depth: usize) -> (usize, usize) {
+    let origin = stack_pointer();
+    let top = origin - GAP;
+    let bottom = top - (max_depth & !(WORD - 1));
+    let mut address = bottom;
+    while address < top {
+        // SAFETY: `[bottom, top)` lies in the unused part of this thread's
+        // stack, below the current frame and above the guard page, since
+        // `measure` spawned the thread with `MAX_DEPTH` plus headroom and
+        // callers of `measure_in_place` guarantee the room; no live value
+        // occupies it. The writes are volatile so that they are not
+        // elided as dead stores.
+        unsafe { ptr::write_volatile(address as *mut usize, SENTINEL) };
+        address += WORD;
+    }
+    (bottom, origin)
+}
+
+// The lowest address in `[bottom, top)` no longer holding the sentinel,
+// or `top` if the region is untouched.
+#[inline(never)]
+fn scan(bottom: usize, top: usize) -> usize {
+    let mut address = bottom;
+    while address < top {
+        // SAFETY: as in `paint`; this frame is shallow enough to stay
+        // above the deepest frame `run` reached, so it does not disturb
+        // the words still to be read.
+        let word =
+            unsafe { ptr::read_volatile(address as *const usize) };
+        if word != SENTINEL {
+            return address;
+        }
+        address += WORD;
+    }
+    top
+}
@@ -0,0 +1,100 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Several independent keys from one shared secret.
+//!
+//! A protocol usually needs more than one key per exchange, for example
+//! an encryption key and a MAC key in each direction. [`split`] derives
+//! all of them from a single SHAKE256 stream over the shared secret and a
+//! caller-supplied context, so `N` keys cost one absorb and a squeeze of
+//! `32 N` bytes instead of `N` separate hashes.
+//!
+//! Key `i` is the `i`-th 32-byte block of the stream: its position is its
+//! domain separation. Splitting into fewer keys therefore yields a prefix
+//! of the same keys, and [`derive_key`] is `split::<1>`. Different
+//! contexts give unrelated streams, so the context should name the
+//! protocol and what the keys are for, e.g. `b"myproto v1 traffic keys"`.
+//!
+//! [`split`]: SubkeyDerivation::split
+//! [`derive_key`]: SubkeyDerivation::derive_key
+//!
+//! ### Example
+//! ```
+//! # use kyberlib::*;
+//! # use kyberlib::subkeys::*;
+//! # fn main() -> Result<(), KyberLibError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng)?;
+//! let (ct, initiator_secret) = encapsulate(&keys.public, &mut rng)?;
+//! let responder_secret = decapsulate(&ct, &keys.secret)?;
+//!
+//! let [enc, mac] = initiator_secret.split::<2>(b"example v1");
+//! assert_eq!(responder_secret.split::<2>(b"example v1"), [enc, mac]);
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    fips202::{
+        shake256_absorb, shake256_finalize, shake256_init,
+        shake256_squeeze,
+    },
+    kex::SharedSecret,
+    symmetric::KeccakState,
+};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size in bytes of each derived key.
+pub const SUBKEY_BYTES: usize = 32;
+
+const SUBKEY_LABEL: &[u8] = b"kyberlib subkeys v1";
+
+/// Key derivation for [`SharedSecret`].
+///
+/// `SharedSecret` is a plain byte array, so derivation is provided
+/// through this extension trait rather than an inherent method.
+pub trait SubkeyDerivation {
+    /// Derives a single key for `context`, the first key of
+    /// [`split`](SubkeyDerivation::split).
+    fn derive_key(&self, context: &[u8]) -> [u8; SUBKEY_BYTES];
+
+    /// Derives `N` independent keys for `context` from one SHAKE256
+    /// stream.
+    fn split<const N: usize>(
+        &self,
+        context: &[u8],
+    ) -> [[u8; SUBKEY_BYTES]; N];
+}
+
+impl SubkeyDerivation for SharedSecret {
+    fn derive_key(&self, context: &[u8]) -> [u8; SUBKEY_BYTES] {
+        let [key] = self.split::<1>(context);
+        key
+    }
+
+    fn split<const N: usize>(
+        &self,
+        context: &[u8],
+    ) -> [[u8; SUBKEY_BYTES]; N] {
+        // The label and the secret have fixed lengths and the context
+        // is length-prefixed, so the encoding is injective.
+        let mut state = KeccakState::new();
+        shake256_init(&mut state);
+        shake256_absorb(&mut state, SUBKEY_LABEL);
+        shake256_absorb(&mut state, self);
+        shake256_absorb(
+            &mut state,
+            &(context.len() as u64).to_le_bytes(),
+        );
+        shake256_absorb(&mut state, context);
+        shake256_finalize(&mut state);
+
+        let mut keys = [[0u8; SUBKEY_BYTES]; N];
+        for key in keys.iter_mut() {
+            shake256_squeeze(key, SUBKEY_BYTES, &mut state);
+        }
+        #[cfg(feature = "zeroize")]
+        state.s.zeroize();
+        keys
+    }
+}
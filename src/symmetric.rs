@@ -6,11 +6,11 @@
 #[cfg(feature = "90s")]
 use crate::aes256ctr::*;
 
-#[cfg(feature = "90s-fixslice")]
+#[cfg(all(feature = "90s-fixslice", not(feature = "small")))]
 use aes::cipher::{
     generic_array::GenericArray, KeyIvInit, StreamCipher,
 };
-#[cfg(feature = "90s-fixslice")]
+#[cfg(all(feature = "90s-fixslice", not(feature = "small")))]
 type Aes256Ctr = ctr::Ctr32BE<aes::Aes256>;
 
 /// Block size for AES256CTR in bytes.
@@ -54,7 +54,7 @@ impl KeccakState {
 #[cfg(feature = "90s")]
 pub fn hash_h(out: &mut [u8], input: &[u8], inlen: usize) {
     let mut hasher = blake3::Hasher::new();
-    hasher.update(&input[..inlen]);
+    let _ = hasher.update(&input[..inlen]);
     let digest = hasher.finalize();
 
     let digest_bytes = digest.as_bytes();
@@ -65,7 +65,7 @@ pub fn hash_h(out: &mut [u8], input: &[u8], inlen: usize) {
 #[cfg(feature = "90s")]
 pub fn hash_g(out: &mut [u8], input: &[u8], inlen: usize) {
     let mut hasher = blake3::Hasher::new();
-    hasher.update(&input[..inlen]);
+    let _ = hasher.update(&input[..inlen]);
     let digest = hasher.finalize();
 
     let digest_bytes = digest.as_bytes();
@@ -81,6 +81,16 @@ pub fn xof_absorb(state: &mut XofState, input: &[u8], x: u8, y: u8) {
     aes256ctr_init(state, input, nonce);
 }
 
+/// Starts a XOF stream like [`xof_absorb`] on a fresh state, building
+/// the state in place instead of zeroing it first
+#[cfg(feature = "90s")]
+pub fn xof_init(input: &[u8], x: u8, y: u8) -> XofState {
+    let mut nonce = [0u8; 12];
+    nonce[0] = x;
+    nonce[1] = y;
+    XofState::with_key(input, nonce)
+}
+
 /// Squeezes XOF data into output in 90s mode
 #[cfg(feature = "90s")]
 pub fn xof_squeezeblocks(
@@ -91,18 +101,152 @@ pub fn xof_squeezeblocks(
     aes256ctr_squeezeblocks(out, outblocks, state);
 }
 
+/// Returns the XOF domain separation bytes `[x, y]` for entry `(i, j)`
+/// of the public matrix.
+///
+/// Entry `(i, j)` of A is sampled from `XOF(rho, j, i)`, as in the
+/// Kyber specification, and entry `(i, j)` of A^T from
+/// `XOF(rho, i, j)`, so both are fully determined by the seed and
+/// A^T is exactly the transpose of A. Every backend's `gen_matrix`
+/// derives its bytes here. Both indices are below the module rank k.
+///
+/// Transposing only swaps the indices, never the layout: A and A^T are
+/// both generated row by row, and row `i` is what the matrix-vector
+/// product multiplies into output `i`, so neither product reads the
+/// matrix with a stride. The scalar backend goes further and consumes
+/// each entry as soon as it is sampled, without a matrix buffer.
+pub fn matrix_index_bytes(
+    i: usize,
+    j: usize,
+    transposed: bool,
+) -> [u8; 2] {
+    if transposed {
+        [i as u8, j as u8]
+    } else {
+        [j as u8, i as u8]
+    }
+}
+
 /// Pseudo-random function (PRF) in 90s mode
+///
+/// Fills `out` with the first `out.len()` bytes of the PRF stream, see
+/// [`PrfReader`].
 #[cfg(feature = "90s")]
 pub fn prf(out: &mut [u8], _outbytes: usize, key: &[u8], nonce: u8) {
     #[cfg(feature = "90s-fixslice")]
-    {
-        // RustCrypto fixslice
+    PrfReader::new(key, nonce).squeeze(out)
+}
+
+/// Streaming pseudo-random function (PRF) in 90s mode
+///
+/// Produces the same AES-256-CTR keystream as [`prf`], but lets callers
+/// pull it incrementally, so samplers can read exactly the bytes they need
+/// without sizing a buffer up front. Successive calls to
+/// [`PrfReader::squeeze`] continue where the previous one stopped.
+#[cfg(feature = "90s-fixslice")]
+#[derive(Clone)]
+pub struct PrfReader {
+    #[cfg(not(feature = "small"))]
+    cipher: Aes256Ctr,
+    // Under `small` the keystream comes a block at a time from the AES
+    // already linked for the XOF, so that the `aes` crate drops out of
+    // the binary
+    #[cfg(feature = "small")]
+    state: XofState,
+    #[cfg(feature = "small")]
+    block: [u8; XOF_BLOCKBYTES],
+    #[cfg(feature = "small")]
+    pos: usize,
+}
+
+#[cfg(feature = "90s-fixslice")]
+impl PrfReader {
+    /// Starts the PRF stream for a `KYBER_SYM_BYTES` key and a nonce.
+    #[cfg(not(feature = "small"))]
+    pub fn new(key: &[u8], nonce: u8) -> Self {
         let mut expnonce = [0u8; 16];
         expnonce[0] = nonce;
         let key = GenericArray::from_slice(key);
         let iv = GenericArray::from_slice(&expnonce);
-        let mut cipher = Aes256Ctr::new(key, iv);
-        cipher.apply_keystream(out)
+        PrfReader {
+            cipher: Aes256Ctr::new(key, iv),
+        }
+    }
+
+    /// Starts the PRF stream for a `KYBER_SYM_BYTES` key and a nonce.
+    #[cfg(feature = "small")]
+    pub fn new(key: &[u8], nonce: u8) -> Self {
+        let mut expnonce = [0u8; 12];
+        expnonce[0] = nonce;
+        PrfReader {
+            state: XofState::with_key(key, expnonce),
+            block: [0u8; XOF_BLOCKBYTES],
+            pos: XOF_BLOCKBYTES,
+        }
+    }
+
+    /// Overwrites `out` with the next `out.len()` bytes of the stream.
+    #[cfg(not(feature = "small"))]
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        out.fill(0);
+        self.cipher.apply_keystream(out)
+    }
+
+    /// Overwrites `out` with the next `out.len()` bytes of the stream.
+    #[cfg(feature = "small")]
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        for byte in out.iter_mut() {
+            *byte = self.next_byte();
+        }
+    }
+
+    #[cfg(feature = "small")]
+    fn next_byte(&mut self) -> u8 {
+        if self.pos == XOF_BLOCKBYTES {
+            aes256ctr_squeezeblocks(
+                &mut self.block,
+                1,
+                &mut self.state,
+            );
+            self.pos = 0;
+        }
+        self.pos += 1;
+        self.block[self.pos - 1]
+    }
+
+    /// Returns the next `N` bytes of the stream.
+    #[cfg(not(feature = "small"))]
+    pub fn keystream<const N: usize>(&mut self) -> [u8; N] {
+        // The keystream is XORed in, so this zeroing is its input; a
+        // zeroed array passed to squeeze would be cleared twice.
+        let mut out = [0u8; N];
+        self.cipher.apply_keystream(&mut out);
+        out
+    }
+
+    /// Returns the next `N` bytes of the stream.
+    #[cfg(feature = "small")]
+    pub fn keystream<const N: usize>(&mut self) -> [u8; N] {
+        // Each byte is written as it is produced, never zeroed first.
+        core::array::from_fn(|_| self.next_byte())
+    }
+}
+
+#[cfg(feature = "90s-fixslice")]
+impl core::fmt::Debug for PrfReader {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_struct("PrfReader").finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(feature = "90s-fixslice", feature = "std"))]
+impl std::io::Read for PrfReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.squeeze(buf);
+        Ok(buf.len())
     }
 }
 
@@ -110,9 +254,26 @@ pub fn prf(out: &mut [u8], _outbytes: usize, key: &[u8], nonce: u8) {
 #[cfg(feature = "90s")]
 pub fn kdf(out: &mut [u8], input: &[u8], inlen: usize) {
     let mut hasher = blake3::Hasher::new();
-    hasher.update(&input[..inlen]);
+    let _ = hasher.update(&input[..inlen]);
     let digest = hasher.finalize();
 
     let digest_bytes = digest.as_bytes();
     out[..digest_bytes.len()].copy_from_slice(digest_bytes)
 }
+
+/// Labeled key derivation function in 90s mode
+///
+/// Derives `out.len()` bytes from `inputs` with BLAKE3 in key derivation
+/// mode, using `label` as the context string. Each input is prefixed with
+/// its length as a little-endian `u64`, so different splits of the same
+/// bytes derive different keys. Labels should be hardcoded, unique to their
+/// purpose and versioned, e.g. `"kyberlib session keys v1"`.
+#[cfg(feature = "90s")]
+pub fn labeled_kdf(out: &mut [u8], label: &str, inputs: &[&[u8]]) {
+    let mut hasher = blake3::Hasher::new_derive_key(label);
+    for input in inputs {
+        let _ = hasher.update(&(input.len() as u64).to_le_bytes());
+        let _ = hasher.update(input);
+    }
+    hasher.finalize_xof().fill(out);
+}
@@ -5,8 +5,8 @@
 extern crate alloc;
 
 use super::*;
-use crate::params::*;
-use alloc::boxed::Box;
+use crate::{params::*, rng::RetryRng};
+use alloc::{boxed::Box, format};
 use rand::rngs::OsRng;
 use wasm_bindgen::prelude::*;
 
@@ -16,8 +16,9 @@ use wasm_bindgen::prelude::*;
 ///
 /// Returns a `JsError` if an error occurs during key pair generation.
 #[wasm_bindgen]
+#[must_use = "dropping the keys discards the only copy of the secret key"]
 pub fn keypair() -> Result<Keys, JsError> {
-    let mut rng = OsRng {};
+    let mut rng = RetryRng(OsRng);
     match api::keypair(&mut rng) {
         Ok(keys) => Ok(Keys {
             pubkey: Box::new(keys.public),
@@ -38,20 +39,22 @@ pub fn keypair() -> Result<Keys, JsError> {
 ///
 /// # Errors
 ///
-/// Returns a `JsValue` that is `null()` if the public key size is incorrect or if an error occurs during encapsulation.
+/// Returns a `JsError` naming the expected size if the public key size is
+/// incorrect, or if random bytes cannot be generated.
 #[wasm_bindgen]
-pub fn encapsulate(pk: Box<[u8]>) -> Result<Kex, JsValue> {
-    if pk.len() != KYBER_PUBLIC_KEY_BYTES {
-        return Err(JsValue::null());
-    }
+#[must_use = "the shared secret is lost if the result is dropped"]
+pub fn encapsulate(pk: Box<[u8]>) -> Result<Kex, JsError> {
+    check_length("public key", KYBER_PUBLIC_KEY_BYTES, &pk)?;
 
-    let mut rng = OsRng {};
+    let mut rng = RetryRng(OsRng);
     match api::encapsulate(&pk, &mut rng) {
         Ok(kex) => Ok(Kex {
             ciphertext: Box::new(kex.0),
             sharedSecret: Box::new(kex.1),
         }),
-        Err(_) => Err(JsValue::null()),
+        Err(_) => {
+            Err(JsError::new("Error trying to fill random bytes"))
+        }
     }
 }
 
@@ -64,24 +67,43 @@ pub fn encapsulate(pk: Box<[u8]>) -> Result<Kex, JsValue> {
 ///
 /// # Errors
 ///
-/// Returns a `JsValue` that is `null()` if the input sizes are incorrect or if an error occurs during decapsulation.
+/// Returns a `JsError` naming the expected size if the ciphertext or
+/// secret key size is incorrect.
 #[wasm_bindgen]
+#[must_use = "the shared secret is lost if the result is dropped"]
 pub fn decapsulate(
     ct: Box<[u8]>,
     sk: Box<[u8]>,
-) -> Result<Box<[u8]>, JsValue> {
-    if ct.len() != KYBER_CIPHERTEXT_BYTES
-        || sk.len() != KYBER_SECRET_KEY_BYTES
-    {
-        return Err(JsValue::null());
-    }
+) -> Result<Box<[u8]>, JsError> {
+    check_length("ciphertext", KYBER_CIPHERTEXT_BYTES, &ct)?;
+    check_length("secret key", KYBER_SECRET_KEY_BYTES, &sk)?;
 
     match api::decapsulate(&ct, &sk) {
         Ok(ss) => Ok(Box::new(ss)),
-        Err(_) => Err(JsValue::null()),
+        Err(_) => Err(JsError::new(
+            "The ciphertext could not be decapsulated",
+        )),
     }
 }
 
+// Builds the exception thrown into JavaScript for a buffer of the wrong
+// size, e.g. `public key must be 1568 bytes, got 32`.
+fn check_length(
+    name: &str,
+    expected: usize,
+    bytes: &[u8],
+) -> Result<(), JsError> {
+    if bytes.len() == expected {
+        return Ok(());
+    }
+    Err(JsError::new(&format!(
+        "{} must be {} bytes, got {}",
+        name,
+        expected,
+        bytes.len()
+    )))
+}
+
 /// Represents Kyber key pair.
 #[wasm_bindgen]
 #[derive(Debug)]
@@ -124,9 +146,60 @@ impl Keys {
     ///
     /// Returns the secret key as a boxed slice of bytes.
     #[wasm_bindgen(getter)]
+    #[must_use]
     pub fn secret(&self) -> Box<[u8]> {
         self.secret.clone()
     }
+
+    /// Generate a new key pair, as `Keys.generate()` in JavaScript.
+    ///
+    /// Randomness comes from `crypto.getRandomValues` through `getrandom`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `JsError` if an error occurs during key pair generation.
+    pub fn generate() -> Result<Keys, JsError> {
+        keypair()
+    }
+
+    /// Get the public key as a `Uint8Array`.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn publicKey(&self) -> Box<[u8]> {
+        self.pubkey.clone()
+    }
+
+    /// Get the secret key as a `Uint8Array`.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn secretKey(&self) -> Box<[u8]> {
+        self.secret.clone()
+    }
+
+    /// Encapsulate a shared secret to `pk`, as `Keys.encapsulate(pk)` in
+    /// JavaScript.
+    ///
+    /// The result has `ciphertext` and `sharedSecret` properties.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `JsError` if the public key size is incorrect or if an
+    /// error occurs during encapsulation.
+    pub fn encapsulate(pk: Box<[u8]>) -> Result<Kex, JsError> {
+        encapsulate(pk)
+    }
+
+    /// Decapsulate `ct` with this key pair's secret key.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `JsError` if the ciphertext size is incorrect.
+    pub fn decapsulate(
+        &self,
+        ct: Box<[u8]>,
+    ) -> Result<Box<[u8]>, JsError> {
+        decapsulate(ct, self.secret.clone())
+    }
 }
 
 #[wasm_bindgen]
@@ -137,12 +210,13 @@ impl Kex {
     ///
     /// * `public_key` - The public key as a boxed slice of bytes.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if the public key size is incorrect.
+    /// Returns a `JsError` if the public key size is incorrect or if an
+    /// error occurs during encapsulation.
     #[wasm_bindgen(constructor)]
-    pub fn new(public_key: Box<[u8]>) -> Self {
-        encapsulate(public_key).expect("Invalid Public Key Size")
+    pub fn new(public_key: Box<[u8]>) -> Result<Kex, JsError> {
+        encapsulate(public_key)
     }
 
     /// Get the ciphertext.
@@ -157,6 +231,7 @@ impl Kex {
     ///
     /// Returns the shared secret as a boxed slice of bytes.
     #[wasm_bindgen(getter)]
+    #[must_use]
     pub fn sharedSecret(&self) -> Box<[u8]> {
         self.sharedSecret.clone()
     }
@@ -0,0 +1,12 @@
+use super::poly::*;
+use crate::poly::{
+    poly_add, poly_compress, poly_csubq, poly_decompress, poly_frommsg,
+    poly_sub, poly_tomont, poly_tomsg, Poly,
+};
+use crate::polyvec::{
+    polyvec_add, polyvec_compress, polyvec_decompress,
+    polyvec_frombytes, polyvec_tobytes, Polyvec,
+};
+
+crate::simd::reference_coding!(#[target_feature(enable = "simd128")]);
+crate::simd::indcpa_layer!(#[target_feature(enable = "simd128")]);
@@ -0,0 +1,59 @@
+//! WebAssembly SIMD128 implementation of the IND-CPA layer, selected at
+//! compile time.
+//!
+//! The NTT, inverse NTT, base multiplication and the centered binomial
+//! sampler for eta = 2 work on 128-bit vectors of 8 coefficients; matrix
+//! sampling, serialization and (de)compression are shared with the
+//! reference implementation. The kernels keep the coefficients in the
+//! standard order of the reference and produce the same keys,
+//! ciphertexts and messages bit for bit.
+//!
+//! A wasm module cannot probe its runtime for SIMD support: one that
+//! uses SIMD128 instructions fails validation where they are missing. The
+//! backend is therefore compiled in only when the `wasm-simd` feature is
+//! enabled and the module is built with `-C target-feature=+simd128`;
+//! without the target feature the build keeps the reference code and
+//! runs on any runtime. With the `force-backend` feature,
+//! `acceleration::force_backend` still switches a process to the
+//! reference code at run time.
+
+pub(crate) mod indcpa;
+mod poly;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::differential;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn ntt_matches_reference() {
+        differential::ntt(
+            |r| poly::poly_ntt(r),
+            |r| poly::poly_invntt_tomont(r),
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn basemul_matches_reference() {
+        differential::basemul(|r, a, b| {
+            poly::polyvec_basemul_acc_montgomery(r, a, b)
+        });
+    }
+
+    #[wasm_bindgen_test]
+    fn cbd_matches_reference() {
+        differential::cbd2(|r, buf| poly::cbd2(r, buf));
+    }
+
+    #[wasm_bindgen_test]
+    fn indcpa_matches_reference() {
+        differential::indcpa(
+            |pk, sk, seed, rng| {
+                indcpa::indcpa_keypair(pk, sk, seed, rng)
+            },
+            |c, m, pk, coins| indcpa::indcpa_enc(c, m, pk, coins),
+            |m, c, sk| indcpa::indcpa_dec(m, c, sk),
+        );
+    }
+}
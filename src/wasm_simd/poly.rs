@@ -0,0 +1,470 @@
+use crate::acceleration::{count_ntt, Acceleration};
+use crate::reference::ntt::ZETAS;
+use crate::{
+    cbd::poly_cbd_eta1, params::*, poly::Poly, polyvec::Polyvec,
+    symmetric::PrfReader,
+};
+use core::arch::wasm32::*;
+
+const Q: i16 = KYBER_Q as i16;
+const QINV: i16 = -3327; // q^(-1) mod 2^16
+const V: i16 = 20159; // ((1 << 26) + q/2) / q
+const F: i16 = 1441; // mont^2/128
+
+// Zetas of the NTT layers with len 4 and 2, whose butterflies lie within
+// a window of two vectors, i.e. 16 coefficients. The windows are
+// transposed so that the first operands of the butterflies fill one
+// vector and the second operands the other; each entry holds the zeta of
+// every lane of a window, in the order the transposes leave them.
+struct Layer {
+    zetas: [[i16; 8]; KYBER_N / 16],
+    inv_zetas: [[i16; 8]; KYBER_N / 16],
+}
+
+const fn layer(len: usize) -> Layer {
+    // Block of 2 * len coefficients of the window that each lane holds
+    const LEN2_BLOCKS: [usize; 8] = [0, 0, 2, 2, 1, 1, 3, 3];
+    let mut l = Layer {
+        zetas: [[0; 8]; KYBER_N / 16],
+        inv_zetas: [[0; 8]; KYBER_N / 16],
+    };
+    let mut w = 0;
+    while w < KYBER_N / 16 {
+        let mut m = 0;
+        while m < 8 {
+            let within = if len == 4 { m / 4 } else { LEN2_BLOCKS[m] };
+            let block = w * (8 / len) + within;
+            l.zetas[w][m] = ZETAS[128 / len + block];
+            l.inv_zetas[w][m] = ZETAS[256 / len - 1 - block];
+            m += 1;
+        }
+        w += 1;
+    }
+    l
+}
+
+static LEN4: Layer = layer(4);
+static LEN2: Layer = layer(2);
+
+// The zeta of each pair of coefficients in the NTT domain, for the eight
+// pairs of every 16 coefficients: the basemul of pairs 2i and 2i + 1
+// reduces modulo X^2 - zeta and X^2 + zeta.
+static BASEMUL_ZETAS: [[i16; 8]; KYBER_N / 16] = {
+    let mut zetas = [[0i16; 8]; KYBER_N / 16];
+    let mut p = 0;
+    while p < KYBER_N / 2 {
+        let zeta = ZETAS[64 + p / 2];
+        zetas[p / 8][p % 8] = if p % 2 == 0 { zeta } else { -zeta };
+        p += 1;
+    }
+    zetas
+};
+
+#[target_feature(enable = "simd128")]
+fn load(a: &[i16]) -> v128 {
+    let a = &a[..8];
+    // SAFETY: a holds 8 coefficients; v128_load has no alignment
+    // requirement.
+    unsafe { v128_load(a.as_ptr().cast()) }
+}
+
+#[target_feature(enable = "simd128")]
+fn store(r: &mut [i16], a: v128) {
+    let r = &mut r[..8];
+    // SAFETY: r holds 8 coefficients.
+    unsafe { v128_store(r.as_mut_ptr().cast(), a) }
+}
+
+// The high halves of the 32-bit lanes of lo and hi, in order.
+#[target_feature(enable = "simd128")]
+fn high_halves(lo: v128, hi: v128) -> v128 {
+    i16x8_shuffle::<1, 3, 5, 7, 9, 11, 13, 15>(lo, hi)
+}
+
+// The high 16 bits of the products a * b, which wasm has no instruction
+// for: the products are widened to 32 bits and their high halves picked.
+#[target_feature(enable = "simd128")]
+fn mulhi(a: v128, b: v128) -> v128 {
+    high_halves(
+        i32x4_extmul_low_i16x8(a, b),
+        i32x4_extmul_high_i16x8(a, b),
+    )
+}
+
+// montgomery_reduce(a * b) in every lane, exactly as the reference
+// computes it: the low halves of a * b and u * q agree, so the high half
+// of their difference is the difference of the high halves.
+#[target_feature(enable = "simd128")]
+fn fqmul(a: v128, b: v128) -> v128 {
+    let u = i16x8_mul(i16x8_mul(a, b), i16x8_splat(QINV));
+    i16x8_sub(mulhi(a, b), mulhi(u, i16x8_splat(Q)))
+}
+
+// montgomery_reduce of the 32-bit lanes of lo and hi, in order.
+#[target_feature(enable = "simd128")]
+fn montgomery_reduce32(lo: v128, hi: v128) -> v128 {
+    let reduce = |a: v128| {
+        // u sign-extended from the low 16 bits of a * QINV
+        let u = i32x4_shr(
+            i32x4_shl(i32x4_mul(a, i32x4_splat(QINV as i32)), 16),
+            16,
+        );
+        i32x4_sub(a, i32x4_mul(u, i32x4_splat(Q as i32)))
+    };
+    high_halves(reduce(lo), reduce(hi))
+}
+
+// barrett_reduce in every lane: round(a * v / 2^26) is computed as
+// (floor(a * v / 2^16) + 2^9) >> 10, which is the same number.
+#[target_feature(enable = "simd128")]
+fn barrett_reduce(a: v128) -> v128 {
+    let t = i16x8_shr(
+        i16x8_add(mulhi(a, i16x8_splat(V)), i16x8_splat(1 << 9)),
+        10,
+    );
+    i16x8_sub(a, i16x8_mul(t, i16x8_splat(Q)))
+}
+
+// Splits a window of 16 coefficients into the first and the second
+// operands of the butterflies of len 4, and back: the transpose is its
+// own inverse.
+#[target_feature(enable = "simd128")]
+fn transpose64(a: v128, b: v128) -> (v128, v128) {
+    (i64x2_shuffle::<0, 2>(a, b), i64x2_shuffle::<1, 3>(a, b))
+}
+
+// The same for len 2.
+#[target_feature(enable = "simd128")]
+fn transpose32(a: v128, b: v128) -> (v128, v128) {
+    (
+        i32x4_shuffle::<0, 4, 2, 6>(a, b),
+        i32x4_shuffle::<1, 5, 3, 7>(a, b),
+    )
+}
+
+/// Name:  poly_ntt
+///
+/// Description: Computes the NTT of a polynomial in place like the
+///  reference poly_ntt, with identical output; inputs in normal order,
+///  output in bitreversed order
+///
+/// Arguments:   - Poly r: in/output polynomial
+#[target_feature(enable = "simd128")]
+pub(crate) fn poly_ntt(r: &mut Poly) {
+    count_ntt(Acceleration::WasmSimd);
+    let mut v = [i16x8_splat(0); KYBER_N / 8];
+    for (v, c) in v.iter_mut().zip(r.coeffs.chunks_exact(8)) {
+        *v = load(c);
+    }
+
+    // len 128 down to 8: butterflies between whole vectors
+    let mut k = 1;
+    for dist in [16, 8, 4, 2, 1] {
+        for start in (0..v.len()).step_by(2 * dist) {
+            let zeta = i16x8_splat(ZETAS[k]);
+            k += 1;
+            for j in start..start + dist {
+                let t = fqmul(zeta, v[j + dist]);
+                v[j + dist] = i16x8_sub(v[j], t);
+                v[j] = i16x8_add(v[j], t);
+            }
+        }
+    }
+
+    // len 4 and 2: butterflies within windows of two vectors
+    for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+        let (a, b) = transpose64(pair[0], pair[1]);
+        let t = fqmul(load(&LEN4.zetas[w]), b);
+        let (a, b) = (i16x8_add(a, t), i16x8_sub(a, t));
+        let (a, b) = transpose64(a, b);
+
+        let (a, b) = transpose32(a, b);
+        let t = fqmul(load(&LEN2.zetas[w]), b);
+        let (a, b) = (i16x8_add(a, t), i16x8_sub(a, t));
+        (pair[0], pair[1]) = transpose32(a, b);
+    }
+
+    for (c, v) in r.coeffs.chunks_exact_mut(8).zip(v) {
+        store(c, barrett_reduce(v));
+    }
+}
+
+/// Name:  poly_invntt_tomont
+///
+/// Description: Computes the inverse NTT of a polynomial in place and
+///  multiplies by the Montgomery factor like the reference
+///  poly_invntt_tomont, with identical output; inputs in bitreversed
+///  order, output in normal order
+///
+/// Arguments:   - Poly r: in/output polynomial
+#[target_feature(enable = "simd128")]
+pub(crate) fn poly_invntt_tomont(r: &mut Poly) {
+    let mut v = [i16x8_splat(0); KYBER_N / 8];
+    for (v, c) in v.iter_mut().zip(r.coeffs.chunks_exact(8)) {
+        *v = load(c);
+    }
+
+    // len 2 and 4: butterflies within windows of two vectors
+    for (w, pair) in v.chunks_exact_mut(2).enumerate() {
+        let (a, b) = transpose32(pair[0], pair[1]);
+        let t = i16x8_sub(b, a);
+        let a = barrett_reduce(i16x8_add(a, b));
+        let b = fqmul(load(&LEN2.inv_zetas[w]), t);
+        let (a, b) = transpose32(a, b);
+
+        let (a, b) = transpose64(a, b);
+        let t = i16x8_sub(b, a);
+        let a = barrett_reduce(i16x8_add(a, b));
+        let b = fqmul(load(&LEN4.inv_zetas[w]), t);
+        (pair[0], pair[1]) = transpose64(a, b);
+    }
+
+    // len 8 up to 128: butterflies between whole vectors
+    for dist in [1, 2, 4, 8, 16] {
+        let first = 256 / (8 * dist) - 1;
+        for (g, start) in (0..v.len()).step_by(2 * dist).enumerate() {
+            let zeta = i16x8_splat(ZETAS[first - g]);
+            for j in start..start + dist {
+                let t = i16x8_sub(v[j + dist], v[j]);
+                v[j] = barrett_reduce(i16x8_add(v[j], v[j + dist]));
+                v[j + dist] = fqmul(zeta, t);
+            }
+        }
+    }
+
+    let f = i16x8_splat(F);
+    for (c, v) in r.coeffs.chunks_exact_mut(8).zip(v) {
+        store(c, fqmul(v, f));
+    }
+}
+
+/// Name:  polyvec_basemul_acc_montgomery
+///
+/// Description: Pointwise multiply elements of a and b and accumulate
+///  into r, with the lazy i32 accumulate of the reference and identical
+///  output: every 16 coefficients are shuffled into the first and second
+///  coefficients of the pairs, and their products are widened and summed
+///  in 32-bit lanes
+///
+/// Arguments: - poly *r:  output polynomial
+///  - const Polyvec a: first input vector of polynomials
+///  - const Polyvec b: second input vector of polynomials
+#[target_feature(enable = "simd128")]
+pub(crate) fn polyvec_basemul_acc_montgomery(
+    r: &mut Poly,
+    a: &Polyvec,
+    b: &Polyvec,
+) {
+    // The even and the odd coefficients of 16 in a row.
+    let pairs = |c: &[i16]| {
+        let (lo, hi) = (load(&c[..8]), load(&c[8..16]));
+        (
+            i16x8_shuffle::<0, 2, 4, 6, 8, 10, 12, 14>(lo, hi),
+            i16x8_shuffle::<1, 3, 5, 7, 9, 11, 13, 15>(lo, hi),
+        )
+    };
+    for (g, out) in r.coeffs.chunks_exact_mut(16).enumerate() {
+        let zetas = load(&BASEMUL_ZETAS[g]);
+        let mut even = [i32x4_splat(0); 2];
+        let mut odd = [i32x4_splat(0); 2];
+        for (a, b) in a.vec.iter().zip(b.vec.iter()) {
+            let (a0, a1) = pairs(&a.coeffs[16 * g..]);
+            let (b0, b1) = pairs(&b.coeffs[16 * g..]);
+            let bz = fqmul(b1, zetas);
+            // a0 * b0 + a1 * b1 * zeta and a0 * b1 + a1 * b0
+            even[0] = i32x4_add(
+                even[0],
+                i32x4_add(
+                    i32x4_extmul_low_i16x8(a0, b0),
+                    i32x4_extmul_low_i16x8(a1, bz),
+                ),
+            );
+            even[1] = i32x4_add(
+                even[1],
+                i32x4_add(
+                    i32x4_extmul_high_i16x8(a0, b0),
+                    i32x4_extmul_high_i16x8(a1, bz),
+                ),
+            );
+            odd[0] = i32x4_add(
+                odd[0],
+                i32x4_add(
+                    i32x4_extmul_low_i16x8(a0, b1),
+                    i32x4_extmul_low_i16x8(a1, b0),
+                ),
+            );
+            odd[1] = i32x4_add(
+                odd[1],
+                i32x4_add(
+                    i32x4_extmul_high_i16x8(a0, b1),
+                    i32x4_extmul_high_i16x8(a1, b0),
+                ),
+            );
+        }
+        let even =
+            barrett_reduce(montgomery_reduce32(even[0], even[1]));
+        let odd = barrett_reduce(montgomery_reduce32(odd[0], odd[1]));
+        store(
+            &mut out[..8],
+            i16x8_shuffle::<0, 8, 1, 9, 2, 10, 3, 11>(even, odd),
+        );
+        store(
+            &mut out[8..],
+            i16x8_shuffle::<4, 12, 5, 13, 6, 14, 7, 15>(even, odd),
+        );
+    }
+}
+
+/// Name:  poly_reduce
+///
+/// Description: Applies the reference Barrett reduction to all
+///  coefficients of a polynomial, giving the centered representatives
+///
+/// Arguments:   - poly *r:   input/output polynomial
+#[target_feature(enable = "simd128")]
+pub(crate) fn poly_reduce(r: &mut Poly) {
+    for c in r.coeffs.chunks_exact_mut(8) {
+        store(c, barrett_reduce(load(c)));
+    }
+}
+
+/// Name:  cbd2
+///
+/// Description: Given an array of uniformly random bytes, compute
+///  polynomial with coefficients distributed according to
+///  a centered binomial distribution with parameter eta=2, like the
+///  reference cbd2. Every byte yields two coefficients, so the bit sums
+///  are taken bytewise
+///
+/// Arguments:   - poly *r:    output polynomial
+///  - const [u8] buf: input byte array
+#[target_feature(enable = "simd128")]
+pub(crate) fn cbd2(r: &mut Poly, buf: &[u8]) {
+    let buf = &buf[..2 * KYBER_N / 4];
+    let (m55, m3) = (u8x16_splat(0x55), u8x16_splat(3));
+    for (c, bytes) in
+        r.coeffs.chunks_exact_mut(32).zip(buf.chunks_exact(16))
+    {
+        // SAFETY: bytes is 16 bytes long.
+        let x = unsafe { v128_load(bytes.as_ptr().cast()) };
+        let d =
+            u8x16_add(v128_and(x, m55), v128_and(u8x16_shr(x, 1), m55));
+        let diff = |lo: v128, hi: v128| {
+            i8x16_sub(v128_and(lo, m3), v128_and(hi, m3))
+        };
+        // The low and the high nibble of each byte
+        let lo = diff(d, u8x16_shr(d, 2));
+        let hi = diff(u8x16_shr(d, 4), u8x16_shr(d, 6));
+        let first = i8x16_shuffle::<
+            0,
+            16,
+            1,
+            17,
+            2,
+            18,
+            3,
+            19,
+            4,
+            20,
+            5,
+            21,
+            6,
+            22,
+            7,
+            23,
+        >(lo, hi);
+        let second = i8x16_shuffle::<
+            8,
+            24,
+            9,
+            25,
+            10,
+            26,
+            11,
+            27,
+            12,
+            28,
+            13,
+            29,
+            14,
+            30,
+            15,
+            31,
+        >(lo, hi);
+        store(&mut c[..8], i16x8_extend_low_i8x16(first));
+        store(&mut c[8..16], i16x8_extend_high_i8x16(first));
+        store(&mut c[16..24], i16x8_extend_low_i8x16(second));
+        store(&mut c[24..], i16x8_extend_high_i8x16(second));
+    }
+}
+
+/// Name:  polyvec_ntt
+///
+/// Description: Apply forward NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+#[target_feature(enable = "simd128")]
+pub(crate) fn polyvec_ntt(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_ntt(poly);
+    }
+}
+
+/// Name:  polyvec_invntt_tomont
+///
+/// Description: Apply inverse NTT to all elements of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: in/output vector of polynomials
+#[target_feature(enable = "simd128")]
+pub(crate) fn polyvec_invntt_tomont(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_invntt_tomont(poly);
+    }
+}
+
+/// Name:  polyvec_reduce
+///
+/// Description: Applies the reference Barrett reduction to each
+///  coefficient of each element of a vector of polynomials
+///
+/// Arguments:   - Polyvec r: input/output vector of polynomials
+#[target_feature(enable = "simd128")]
+pub(crate) fn polyvec_reduce(r: &mut Polyvec) {
+    for poly in r.vec.iter_mut() {
+        poly_reduce(poly);
+    }
+}
+
+/// Name:  poly_getnoise_eta1
+///
+/// Description: Sample a polynomial deterministically from a seed and a nonce,
+///  like the reference poly_getnoise_eta1
+///
+/// Arguments:   - poly *r:     output polynomial
+///  - const [u8] seed: input seed (pointing to array of length KYBER_SYM_BYTES bytes)
+///  - [u8]  nonce:   one-byte input nonce
+#[target_feature(enable = "simd128")]
+pub(crate) fn poly_getnoise_eta1(r: &mut Poly, seed: &[u8], nonce: u8) {
+    const LENGTH: usize = KYBER_ETA1 * KYBER_N / 4;
+    let buf: [u8; LENGTH] = PrfReader::new(seed, nonce).keystream();
+    if KYBER_ETA1 == 2 {
+        cbd2(r, &buf);
+    } else {
+        poly_cbd_eta1(r, &buf);
+    }
+}
+
+/// Name:  poly_getnoise_eta2
+///
+/// Description: Sample a polynomial deterministically from a seed and a nonce,
+///  like the reference poly_getnoise_eta2
+///
+/// Arguments:   - poly *r:     output polynomial
+///  - const [u8] seed: input seed (pointing to array of length KYBER_SYM_BYTES bytes)
+///  - [u8]  nonce:   one-byte input nonce
+#[target_feature(enable = "simd128")]
+pub(crate) fn poly_getnoise_eta2(r: &mut Poly, seed: &[u8], nonce: u8) {
+    const LENGTH: usize = KYBER_ETA2 * KYBER_N / 4;
+    let buf: [u8; LENGTH] = PrfReader::new(seed, nonce).keystream();
+    cbd2(r, &buf);
+}
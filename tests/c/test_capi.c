@@ -0,0 +1,53 @@
+/* End-to-end test of the kyberlib C API, built and run by tests/test_capi.rs. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "kyberlib.h"
+
+#define CHECK(expr)                                                      \
+    do {                                                                 \
+        if (!(expr)) {                                                   \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,      \
+                    __LINE__, #expr);                                    \
+            return 1;                                                    \
+        }                                                                \
+    } while (0)
+
+int main(void) {
+    uint8_t pk[4096], sk[4096], ct[4096], ss1[64], ss2[64];
+    size_t pk_len = kyberlib_public_key_bytes();
+    size_t sk_len = kyberlib_secret_key_bytes();
+    size_t ct_len = kyberlib_ciphertext_bytes();
+    size_t ss_len = kyberlib_shared_secret_bytes();
+
+    CHECK(pk_len <= sizeof pk && sk_len <= sizeof sk);
+    CHECK(ct_len <= sizeof ct && ss_len <= sizeof ss1);
+
+    CHECK(kyberlib_keypair(pk, pk_len, sk, sk_len) == KYBERLIB_OK);
+    CHECK(kyberlib_encapsulate(ct, ct_len, ss1, ss_len, pk, pk_len) ==
+          KYBERLIB_OK);
+    CHECK(kyberlib_decapsulate(ss2, ss_len, ct, ct_len, sk, sk_len) ==
+          KYBERLIB_OK);
+    CHECK(memcmp(ss1, ss2, ss_len) == 0);
+
+    /* A tampered ciphertext decapsulates to another secret. */
+    ct[0] ^= 1;
+    CHECK(kyberlib_decapsulate(ss2, ss_len, ct, ct_len, sk, sk_len) ==
+          KYBERLIB_OK);
+    CHECK(memcmp(ss1, ss2, ss_len) != 0);
+
+    CHECK(kyberlib_keypair(NULL, pk_len, sk, sk_len) ==
+          KYBERLIB_ERROR_NULL_POINTER);
+    CHECK(kyberlib_keypair(pk, pk_len - 1, sk, sk_len) ==
+          KYBERLIB_ERROR_INVALID_LENGTH);
+    CHECK(kyberlib_encapsulate(ct, ct_len, ss1, ss_len, NULL, pk_len) ==
+          KYBERLIB_ERROR_NULL_POINTER);
+    CHECK(kyberlib_decapsulate(ss2, ss_len, ct, ct_len + 1, sk, sk_len) ==
+          KYBERLIB_ERROR_INVALID_LENGTH);
+    CHECK(strcmp(kyberlib_error_message(KYBERLIB_ERROR_INVALID_LENGTH),
+                 "invalid buffer length") == 0);
+
+    puts("kyberlib C API: ok");
+    return 0;
+}
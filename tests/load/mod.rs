@@ -5,16 +5,16 @@ use std::path::PathBuf;
 
 // Known Answer Tests
 #[derive(Debug)]
-pub struct Kat {
+pub(crate) struct Kat {
     // Deterministic RNG buffers
-    pub keygen_buffer1: String,
-    pub keygen_buffer2: String,
-    pub encap_buffer: String,
+    pub(crate) keygen_buffer1: String,
+    pub(crate) keygen_buffer2: String,
+    pub(crate) encap_buffer: String,
     // Keys, Ciphertext and Shared Secret
-    pub pk: String,
-    pub sk: String,
-    pub ct: String,
-    pub ss: String,
+    pub(crate) pk: String,
+    pub(crate) sk: String,
+    pub(crate) ct: String,
+    pub(crate) ss: String,
 }
 
 // Converts string octuples from tvec files into Kat structs
@@ -88,7 +88,7 @@ fn parse_kats() -> Vec<String> {
 }
 
 // Packs chunks of lines into Kat structs
-pub fn build_kats() -> Vec<Kat> {
+pub(crate) fn build_kats() -> Vec<Kat> {
     let lines = parse_kats();
     let kats = lines.chunks_exact(8);
     // Map String slices into Vec<KAT>
@@ -0,0 +1,216 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the backend selection and reporting.
+
+#[cfg(test)]
+mod tests {
+    use kyberlib::acceleration::available;
+    #[cfg(feature = "force-backend")]
+    use kyberlib::acceleration::{force_backend, reset_backend};
+    use kyberlib::{acceleration, Acceleration};
+    use std::sync::{Mutex, MutexGuard};
+
+    const ALL: [Acceleration; 8] = [
+        Acceleration::Scalar,
+        Acceleration::Sse2,
+        Acceleration::Avx2,
+        Acceleration::Neon,
+        Acceleration::WasmSimd,
+        Acceleration::Avx512,
+        Acceleration::PortableSimd,
+        Acceleration::Rvv,
+    ];
+
+    // The backend is process-wide, so tests that force one or count the
+    // NTTs run by the live one take turns.
+    fn lock() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[test]
+    fn acceleration_is_plausible_for_the_target() {
+        let _guard = lock();
+        let live = acceleration();
+        if cfg!(feature = "portable-simd")
+            && live == Acceleration::PortableSimd
+        {
+            // Compiled in on any target no intrinsics backend covers
+        } else if cfg!(target_arch = "x86_64") {
+            assert!(matches!(
+                live,
+                Acceleration::Scalar
+                    | Acceleration::Sse2
+                    | Acceleration::Avx2
+                    | Acceleration::Avx512
+            ));
+        } else if cfg!(any(
+            target_arch = "aarch64",
+            target_arch = "arm"
+        )) {
+            assert!(matches!(
+                live,
+                Acceleration::Scalar | Acceleration::Neon
+            ));
+        } else if cfg!(target_arch = "wasm32") {
+            assert!(matches!(
+                live,
+                Acceleration::Scalar | Acceleration::WasmSimd
+            ));
+        } else if cfg!(target_arch = "riscv64") {
+            assert!(matches!(
+                live,
+                Acceleration::Scalar | Acceleration::Rvv
+            ));
+        } else {
+            assert_eq!(live, Acceleration::Scalar);
+        }
+        assert_eq!(acceleration(), live);
+    }
+
+    #[test]
+    fn names_are_distinct() {
+        for (i, a) in ALL.iter().enumerate() {
+            assert_eq!(a.to_string(), a.name());
+            assert!(ALL[i + 1..].iter().all(|b| b.name() != a.name()));
+        }
+    }
+
+    // Other tests in this binary may run NTTs concurrently, which only
+    // raises the count of the live backend.
+    #[cfg(debug_assertions)]
+    #[test]
+    fn ntts_run_on_the_reported_backend() {
+        use kyberlib::acceleration::ntt_calls;
+        use kyberlib::{decapsulate, encapsulate, keypair};
+        use rand_core::OsRng;
+
+        let _guard = lock();
+        let live = acceleration();
+        let before = ALL.map(ntt_calls);
+
+        let keys = keypair(&mut OsRng).unwrap();
+        let (ct, ss) = encapsulate(&keys.public, &mut OsRng).unwrap();
+        assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+
+        for (backend, before) in ALL.iter().zip(before) {
+            let ran = ntt_calls(*backend) - before;
+            if *backend == live {
+                assert!(ran > 0, "{} ran no NTT", backend);
+            } else {
+                assert_eq!(ran, 0, "{} ran {} NTTs", backend, ran);
+            }
+        }
+    }
+
+    // Decapsulation needs A only to re-encrypt, so each operation
+    // expands it exactly once.
+    #[cfg(all(debug_assertions, feature = "force-backend"))]
+    #[test]
+    fn each_operation_expands_the_matrix_once() {
+        use kyberlib::acceleration::matrix_entries;
+        use kyberlib::params::KYBER_SECURITY_PARAMETER as K;
+        use kyberlib::{decapsulate, encapsulate, keypair};
+        use rand_core::OsRng;
+
+        let _guard = lock();
+        force_backend(Acceleration::Scalar).unwrap();
+        let before = matrix_entries();
+        let keys = keypair(&mut OsRng).unwrap();
+        assert_eq!(matrix_entries() - before, K * K);
+        let before = matrix_entries();
+        let (ct, ss) = encapsulate(&keys.public, &mut OsRng).unwrap();
+        assert_eq!(matrix_entries() - before, K * K);
+        let before = matrix_entries();
+        assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+        assert_eq!(matrix_entries() - before, K * K);
+        reset_backend();
+    }
+
+    #[test]
+    fn available_is_fastest_first_and_ends_with_scalar() {
+        let _guard = lock();
+        let backends: Vec<_> = available().collect();
+        assert_eq!(backends.last(), Some(&Acceleration::Scalar));
+        assert!(!backends.contains(&Acceleration::Sse2));
+        // Unless KYBERLIB_BACKEND picks another in a `force-backend`
+        // build.
+        if std::env::var_os("KYBERLIB_BACKEND").is_none() {
+            assert_eq!(acceleration(), backends[0]);
+        }
+    }
+
+    // The forced reference path and every SIMD one produce the same
+    // keys, ciphertext and shared secret from the same seeds.
+    #[cfg(feature = "force-backend")]
+    #[test]
+    fn forced_backends_agree() {
+        use kyberlib::acceleration::ntt_calls;
+        use kyberlib::kem::{
+            decrypt_message, encrypt_message, generate_key_pair,
+        };
+        use kyberlib::{
+            KYBER_CIPHERTEXT_BYTES, KYBER_PUBLIC_KEY_BYTES,
+            KYBER_SECRET_KEY_BYTES, KYBER_SHARED_SECRET_BYTES,
+        };
+        use rand_core::OsRng;
+
+        let _guard = lock();
+        let detected = acceleration();
+        let mut outputs = Vec::new();
+        for backend in available() {
+            force_backend(backend).unwrap();
+            assert_eq!(acceleration(), backend);
+            let before = ntt_calls(backend);
+            let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+            let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
+            let seeds = (&[1u8; 32][..], &[2u8; 32][..]);
+            generate_key_pair(
+                &mut pk,
+                &mut sk,
+                &mut OsRng,
+                Some(seeds),
+            )
+            .unwrap();
+            let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+            let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+            encrypt_message(
+                &mut ct,
+                &mut ss,
+                &pk,
+                &mut OsRng,
+                Some(&[3; 32]),
+            )
+            .unwrap();
+            let mut decrypted = [0u8; KYBER_SHARED_SECRET_BYTES];
+            decrypt_message(&mut decrypted, &ct, &sk);
+            assert_eq!(decrypted, ss, "{}", backend);
+            if cfg!(debug_assertions) {
+                assert!(ntt_calls(backend) > before, "{}", backend);
+            }
+            outputs.push((pk, sk, ct, ss));
+        }
+        reset_backend();
+        assert_eq!(acceleration(), detected);
+        assert!(outputs.windows(2).all(|w| w[0] == w[1]));
+    }
+
+    #[cfg(feature = "force-backend")]
+    #[test]
+    fn unavailable_backends_cannot_be_forced() {
+        use kyberlib::KyberLibError;
+
+        let _guard = lock();
+        let live = acceleration();
+        for backend in ALL {
+            if available().all(|b| b != backend) {
+                assert_eq!(
+                    force_backend(backend),
+                    Err(KyberLibError::InvalidInput)
+                );
+                assert_eq!(acceleration(), live);
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the `KYBERLIB_BACKEND` override. The variable is read on
+//! first use only, so this binary holds a single test per build that
+//! sets it before anything else runs.
+#![cfg(feature = "std")]
+
+use kyberlib::acceleration::available;
+#[cfg(feature = "force-backend")]
+use kyberlib::acceleration::{force_backend, reset_backend};
+use kyberlib::{acceleration, decapsulate, encapsulate, keypair};
+#[cfg(feature = "force-backend")]
+use kyberlib::{Acceleration, KyberLibError};
+use rand_core::OsRng;
+
+#[cfg(feature = "force-backend")]
+#[test]
+fn environment_selects_the_backend() {
+    std::env::set_var("KYBERLIB_BACKEND", "scalar");
+    assert_eq!(acceleration(), Acceleration::Scalar);
+    let keys = keypair(&mut OsRng).unwrap();
+    let (ct, ss) = encapsulate(&keys.public, &mut OsRng).unwrap();
+    assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+
+    // Read once: changing it later has no effect.
+    std::env::set_var("KYBERLIB_BACKEND", "avx2");
+    assert_eq!(acceleration(), Acceleration::Scalar);
+
+    // An explicit choice still takes precedence.
+    let fastest = available().next().unwrap();
+    force_backend(fastest).unwrap();
+    assert_eq!(acceleration(), fastest);
+    reset_backend();
+    assert_eq!(acceleration(), Acceleration::Scalar);
+    assert_eq!(
+        force_backend(Acceleration::Sse2),
+        Err(KyberLibError::InvalidInput)
+    );
+}
+
+// Without the feature no dependency can switch the backend through the
+// environment either.
+#[cfg(not(feature = "force-backend"))]
+#[test]
+fn environment_is_ignored() {
+    std::env::set_var("KYBERLIB_BACKEND", "scalar");
+    assert_eq!(acceleration(), available().next().unwrap());
+    let keys = keypair(&mut OsRng).unwrap();
+    let (ct, ss) = encapsulate(&keys.public, &mut OsRng).unwrap();
+    assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+}
@@ -0,0 +1,285 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the age plugin.
+
+#![cfg(feature = "age-plugin")]
+
+use kyberlib::age_plugin::*;
+use kyberlib::*;
+use std::io::Cursor;
+
+fn script(commands: &[Stanza]) -> Cursor<Vec<u8>> {
+    let mut input = Vec::new();
+    for command in commands {
+        command.write(&mut input).unwrap();
+    }
+    Cursor::new(input)
+}
+
+fn replies(output: &[u8]) -> Vec<Stanza> {
+    let mut output = Cursor::new(output);
+    let mut stanzas = Vec::new();
+    while let Some(stanza) = Stanza::read(&mut output).unwrap() {
+        stanzas.push(stanza);
+    }
+    stanzas
+}
+
+fn ok() -> Stanza {
+    Stanza::new("ok", &[], &[])
+}
+
+fn done() -> Stanza {
+    Stanza::new("done", &[], &[])
+}
+
+// Runs recipient-v1 and returns the stanzas it produced, per file.
+fn encrypt(
+    recipients: &[String],
+    file_keys: &[FileKey],
+) -> Vec<Vec<Stanza>> {
+    let mut commands: Vec<Stanza> = recipients
+        .iter()
+        .map(|r| Stanza::new("add-recipient", &[r], &[]))
+        .collect();
+    commands.extend(
+        file_keys
+            .iter()
+            .map(|key| Stanza::new("wrap-file-key", &[], key)),
+    );
+    commands.push(done());
+    commands
+        .extend((0..recipients.len() * file_keys.len()).map(|_| ok()));
+
+    let mut output = Vec::new();
+    run_recipient_v1(
+        &mut script(&commands),
+        &mut output,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    let replies = replies(&output);
+    assert_eq!(replies.last(), Some(&done()));
+
+    let mut files = vec![Vec::new(); file_keys.len()];
+    for reply in &replies[..replies.len() - 1] {
+        assert_eq!(reply.tag, "recipient-stanza");
+        let file: usize = reply.args[0].parse().unwrap();
+        files[file].push(Stanza {
+            tag: reply.args[1].clone(),
+            args: reply.args[2..].to_vec(),
+            body: reply.body.clone(),
+        });
+    }
+    files
+}
+
+// Runs identity-v1 over the given files and returns the plugin's commands.
+fn decrypt(
+    identities: &[String],
+    files: &[Vec<Stanza>],
+) -> Vec<Stanza> {
+    let mut commands: Vec<Stanza> = identities
+        .iter()
+        .map(|i| Stanza::new("add-identity", &[i], &[]))
+        .collect();
+    for (file, stanzas) in files.iter().enumerate() {
+        for stanza in stanzas {
+            let file = file.to_string();
+            let mut args = vec![file.as_str(), stanza.tag.as_str()];
+            args.extend(stanza.args.iter().map(String::as_str));
+            commands.push(Stanza::new(
+                "recipient-stanza",
+                &args,
+                &stanza.body,
+            ));
+        }
+    }
+    commands.push(done());
+    commands.extend((0..files.len() * 2).map(|_| ok()));
+
+    let mut output = Vec::new();
+    run_identity_v1(&mut script(&commands), &mut output).unwrap();
+    let replies = replies(&output);
+    assert_eq!(replies.last(), Some(&done()));
+    replies[..replies.len() - 1].to_vec()
+}
+
+#[test]
+fn encodings_round_trip() {
+    let identity = Identity::generate(&mut rand::thread_rng()).unwrap();
+    let recipient = identity.recipient().to_string();
+    assert!(recipient.starts_with("age1kyber1"));
+    assert_eq!(
+        recipient.parse::<Recipient>().unwrap(),
+        identity.recipient()
+    );
+
+    let encoded = identity.to_encoded();
+    assert!(encoded.starts_with("AGE-PLUGIN-KYBER-1"));
+    let parsed: Identity = encoded.parse().unwrap();
+    assert_eq!(parsed.recipient(), identity.recipient());
+    assert!(!format!("{:?}", parsed).contains(&encoded[20..40]));
+
+    // Each encoding only parses as its own kind.
+    assert_eq!(
+        encoded.parse::<Recipient>().unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    assert_eq!(
+        recipient.parse::<Identity>().unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+}
+
+#[test]
+fn stanza_encoding_wraps_at_64_columns() {
+    for len in [0, 47, 48, 49, 2000] {
+        let stanza = Stanza::new("t", &["a", "b"], &vec![0xa5; len]);
+        let mut encoded = Vec::new();
+        stanza.write(&mut encoded).unwrap();
+        let text = String::from_utf8(encoded.clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "-> t a b");
+        assert!(lines[1..].iter().all(|l| l.len() <= 64));
+        assert!(lines.last().unwrap().len() < 64);
+        assert_eq!(
+            Stanza::read(&mut Cursor::new(encoded)).unwrap(),
+            Some(stanza)
+        );
+    }
+    for malformed in
+        [&b"-> t\n"[..], b"t\n\n", b"-> t  a\n\n", b"-> t\nAA=\n"]
+    {
+        assert!(Stanza::read(&mut Cursor::new(malformed)).is_err());
+    }
+}
+
+#[test]
+fn multi_recipient_round_trip() {
+    let mut rng = rand::thread_rng();
+    let alice = Identity::generate(&mut rng).unwrap();
+    let bob = Identity::generate(&mut rng).unwrap();
+    let recipients =
+        [alice.recipient().to_string(), bob.recipient().to_string()];
+    let file_keys = [[1u8; FILE_KEY_BYTES], [2u8; FILE_KEY_BYTES]];
+
+    let files = encrypt(&recipients, &file_keys);
+    assert!(files.iter().all(|stanzas| stanzas.len() == 2));
+    assert!(files
+        .iter()
+        .flatten()
+        .all(|s| s.tag == STANZA_TYPE
+            && s.body.len() == STANZA_BODY_BYTES));
+
+    // Bob's stanzas come second, so Alice's must be skipped as foreign.
+    let replies = decrypt(&[bob.to_encoded().to_string()], &files);
+    assert_eq!(
+        replies,
+        vec![
+            Stanza::new("file-key", &["0"], &file_keys[0]),
+            Stanza::new("file-key", &["1"], &file_keys[1]),
+        ]
+    );
+}
+
+#[test]
+fn unknown_stanzas_pass_through() {
+    let mut rng = rand::thread_rng();
+    let identity = Identity::generate(&mut rng).unwrap();
+    let file_key = [9u8; FILE_KEY_BYTES];
+    let files = vec![vec![
+        Stanza::new("X25519", &["c29tZSBzaGFyZQ"], &[0u8; 32]),
+        identity
+            .recipient()
+            .wrap_file_key(&file_key, &mut rng)
+            .unwrap(),
+    ]];
+    assert_eq!(
+        decrypt(&[identity.to_encoded().to_string()], &files),
+        vec![Stanza::new("file-key", &["0"], &file_key)]
+    );
+
+    // A file holding no stanza for us yields nothing.
+    let other = Identity::generate(&mut rng).unwrap();
+    assert!(
+        decrypt(&[other.to_encoded().to_string()], &files).is_empty()
+    );
+}
+
+#[test]
+fn malformed_stanzas_are_rejected() {
+    let mut rng = rand::thread_rng();
+    let identity = Identity::generate(&mut rng).unwrap();
+    let good = identity
+        .recipient()
+        .wrap_file_key(&[3u8; 16], &mut rng)
+        .unwrap();
+    let mut short = good.clone();
+    let _ = short.body.pop();
+    let mut with_args = good.clone();
+    with_args.args.push("extra".to_string());
+    for bad in [&short, &with_args] {
+        assert_eq!(
+            identity.unwrap_stanza(bad),
+            Some(Err(KyberLibError::InvalidInput))
+        );
+    }
+
+    // The malformed stanza is reported and the next one still unwraps.
+    let replies = decrypt(
+        &[identity.to_encoded().to_string()],
+        &[vec![short, good]],
+    );
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[0].tag, "error");
+    assert_eq!(replies[0].args, ["stanza", "0", "0"]);
+    assert_eq!(replies[1], Stanza::new("file-key", &["0"], &[3u8; 16]));
+}
+
+#[test]
+fn invalid_recipients_are_reported() {
+    let commands = [
+        Stanza::new("add-recipient", &["age1kyber1qqqq"], &[]),
+        Stanza::new("wrap-file-key", &[], &[0u8; FILE_KEY_BYTES]),
+        done(),
+        ok(),
+    ];
+    let mut output = Vec::new();
+    run_recipient_v1(
+        &mut script(&commands),
+        &mut output,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    let replies = replies(&output);
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[0].tag, "error");
+    assert_eq!(replies[0].args, ["recipient", "0"]);
+    assert_eq!(replies[1], done());
+}
+
+#[test]
+fn labels_are_sent_on_request() {
+    let identity = Identity::generate(&mut rand::thread_rng()).unwrap();
+    let commands = [
+        Stanza::new("add-identity", &[&identity.to_encoded()[..]], &[]),
+        Stanza::new("wrap-file-key", &[], &[0u8; FILE_KEY_BYTES]),
+        Stanza::new("extension-labels", &[], &[]),
+        done(),
+        ok(),
+        ok(),
+    ];
+    let mut output = Vec::new();
+    run_recipient_v1(
+        &mut script(&commands),
+        &mut output,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    let replies = replies(&output);
+    assert_eq!(replies[0], Stanza::new("labels", &[LABEL], &[]));
+    assert_eq!(replies[1].tag, "recipient-stanza");
+    assert_eq!(replies[2], done());
+}
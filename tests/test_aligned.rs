@@ -0,0 +1,63 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the 64-byte aligned key and ciphertext buffers.
+
+use core::mem::{align_of, size_of};
+use kyberlib::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(64) * 64
+}
+
+#[test]
+fn layout_is_aligned_and_padded() {
+    assert_eq!(align_of::<AlignedPublicKey>(), 64);
+    assert_eq!(align_of::<AlignedCiphertext>(), 64);
+    assert_eq!(
+        size_of::<AlignedPublicKey>(),
+        padded(KYBER_PUBLIC_KEY_BYTES)
+    );
+    assert_eq!(
+        size_of::<AlignedCiphertext>(),
+        padded(KYBER_CIPHERTEXT_BYTES)
+    );
+    assert_eq!(align_of::<ValidatedPublicKey>(), 64);
+}
+
+#[test]
+fn bytes_start_on_a_cache_line() {
+    let keys = derive(&[1u8; 64]).unwrap();
+    let aligned = AlignedPublicKey::from(keys.public);
+    assert_eq!(aligned.as_ptr() as usize % 64, 0);
+    let boxed = Box::new(aligned);
+    assert_eq!(boxed.as_ptr() as usize % 64, 0);
+    let many = vec![aligned; 3];
+    for key in &many {
+        assert_eq!(key.as_ptr() as usize % 64, 0);
+    }
+
+    let peer = keys.public.validated().unwrap();
+    assert_eq!(peer.as_bytes().as_ptr() as usize % 64, 0);
+    assert_eq!(peer.as_bytes(), &keys.public);
+}
+
+#[test]
+fn aligned_buffers_give_the_same_results() {
+    let keys = derive(&[2u8; 64]).unwrap();
+    let aligned = AlignedPublicKey::from(keys.public);
+    let (ct, ss) =
+        encapsulate(&keys.public, &mut StdRng::from_seed([4u8; 32]))
+            .unwrap();
+    let (aligned_ct, aligned_ss) =
+        encapsulate(&*aligned, &mut StdRng::from_seed([4u8; 32]))
+            .unwrap();
+    assert_eq!((ct, ss), (aligned_ct, aligned_ss));
+
+    let aligned_ct = AlignedCiphertext::from(aligned_ct);
+    assert_eq!(
+        decapsulate(&*aligned_ct, keys.expose_secret()).unwrap(),
+        ss
+    );
+}
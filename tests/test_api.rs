@@ -1,6 +1,8 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the high-level KEM API.
+
 // Import necessary modules
 use kyberlib::*;
 use rand::rngs::OsRng;
@@ -103,6 +105,20 @@ mod tests {
         assert_eq!(keypair.secret.len(), KYBER_SECRET_KEY_BYTES);
     }
 
+    // Test for Keypair::random
+    #[test]
+    fn test_keypair_random() {
+        let first = Keypair::random().unwrap();
+        let second = Keypair::random().unwrap();
+        assert_ne!(first.public, second.public);
+        assert_ne!(first.secret, second.secret);
+        for keys in [first, second] {
+            let (ct, ss) =
+                encapsulate(&keys.public, &mut OsRng).unwrap();
+            assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+        }
+    }
+
     // Test for Keypair::import method
     #[test]
     fn test_keypair_import() {
@@ -187,11 +203,11 @@ mod tests {
         // Initialize a random number generator
         let mut rng = OsRng;
         // Define invalid public key and secret key
-        let mut invalid_public_key = [0u8; KYBER_PUBLIC_KEY_BYTES];
-        let mut invalid_secret_key = [0u8; KYBER_SECRET_KEY_BYTES];
+        let mut _invalid_public_key = [0u8; KYBER_PUBLIC_KEY_BYTES];
+        let mut _invalid_secret_key = [0u8; KYBER_SECRET_KEY_BYTES];
         // Modify the public key and secret key to make them invalid
-        invalid_public_key[0] = 0xFF;
-        invalid_secret_key[0] = 0xFF;
+        _invalid_public_key[0] = 0xFF;
+        _invalid_secret_key[0] = 0xFF;
         // Assert error handling for Keypair::generate with invalid public key and secret key
         assert!(Keypair::generate(&mut rng).is_ok());
     }
@@ -276,4 +292,314 @@ mod tests {
             decapsulate(&ciphertext, &keypair.secret).unwrap();
         assert_eq!(shared_secret, decapsulated_secret);
     }
+
+    // Test that the slice variants match the typed-key variants
+    #[test]
+    fn test_slice_variants_match_typed_keys() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        let keypair = derive(&[7u8; 64]).unwrap();
+        let pk: &[u8] = &keypair.public;
+        let sk: &[u8] = &keypair.secret;
+        let (ct, ss) = encapsulate(
+            &keypair.public,
+            &mut StdRng::from_seed([1u8; 32]),
+        )
+        .unwrap();
+        let (ct_slice, ss_slice) =
+            encapsulate_slice(pk, &mut StdRng::from_seed([1u8; 32]))
+                .unwrap();
+        assert_eq!(ct, ct_slice);
+        assert_eq!(ss, ss_slice);
+        assert_eq!(
+            decapsulate_slice(sk, &ct_slice).unwrap(),
+            decapsulate(&ct, &keypair.secret).unwrap()
+        );
+    }
+
+    // Test that the slice variants reject buffers of the wrong length
+    #[test]
+    fn test_slice_variants_reject_wrong_lengths() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let (ct, _) = encapsulate(&keypair.public, &mut rng).unwrap();
+        for len in
+            [0, KYBER_PUBLIC_KEY_BYTES - 1, KYBER_PUBLIC_KEY_BYTES + 1]
+        {
+            assert_eq!(
+                encapsulate_slice(&vec![0u8; len], &mut rng)
+                    .unwrap_err(),
+                KyberLibError::InvalidLength
+            );
+        }
+        assert_eq!(
+            decapsulate_slice(&keypair.secret[1..], &ct).unwrap_err(),
+            KyberLibError::InvalidLength
+        );
+        assert_eq!(
+            decapsulate_slice(&keypair.secret, &ct[1..]).unwrap_err(),
+            KyberLibError::InvalidLength
+        );
+    }
+
+    // Test that strict decapsulation matches decapsulate and flags
+    // canonical ciphertexts
+    #[test]
+    fn test_decapsulate_strict_matches_decapsulate() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let (ct, ss) = encapsulate(&keypair.public, &mut rng).unwrap();
+        assert_eq!(
+            decapsulate_strict(&ct, &keypair.secret).unwrap(),
+            (ss, true)
+        );
+        assert_eq!(
+            decapsulate_strict(&ct[1..], &keypair.secret).unwrap_err(),
+            KyberLibError::InvalidInput
+        );
+    }
+
+    // Test that a tampered coefficient is still canonical: every 11-bit
+    // and 5-bit value survives decompress + recompress, so no ciphertext
+    // of these parameters is non-canonical, and rejection still applies
+    #[test]
+    fn test_decapsulate_strict_tampered_coefficients() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let (ct, ss) = encapsulate(&keypair.public, &mut rng).unwrap();
+        let last = KYBER_CIPHERTEXT_BYTES - 1;
+        for x in 1..(1u16 << 11) {
+            let mut tampered = ct;
+            // First 11-bit coefficient of u and last 5-bit coefficient of v
+            tampered[0] ^= x as u8;
+            tampered[1] ^= (x >> 8) as u8;
+            tampered[last] ^= ((x & 31) as u8) << 3;
+            let (ss_strict, canonical) =
+                decapsulate_strict(&tampered, &keypair.secret).unwrap();
+            assert!(canonical);
+            assert_eq!(
+                ss_strict,
+                decapsulate(&tampered, &keypair.secret).unwrap()
+            );
+            assert_ne!(ss_strict, ss);
+        }
+    }
+
+    // Test that a compact key and its expansion decapsulate a ciphertext,
+    // including a tampered one, to the same shared secret
+    #[test]
+    fn test_decapsulate_compact_matches_full_key() {
+        let mut rng = OsRng;
+        let (public, compact) = keypair_compact(&mut rng).unwrap();
+        let secret = expand_secret_key(&compact).unwrap();
+        assert_eq!(public, kyberlib::public(&secret));
+
+        let (ct, ss) = encapsulate(&public, &mut rng).unwrap();
+        assert_eq!(decapsulate_compact(&ct, &compact).unwrap(), ss);
+        assert_eq!(decapsulate(&ct, &secret).unwrap(), ss);
+
+        let mut tampered = ct;
+        tampered[0] ^= 1;
+        assert_eq!(
+            decapsulate_compact(&tampered, &compact).unwrap(),
+            decapsulate(&tampered, &secret).unwrap()
+        );
+        assert_eq!(
+            decapsulate_compact(&ct, &compact[1..]).unwrap_err(),
+            KyberLibError::InvalidInput
+        );
+    }
+
+    // Test that the Encapsulation struct destructures like the tuple API
+    #[test]
+    fn test_encapsulate_struct_matches_tuple() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let keypair = keypair(&mut OsRng).unwrap();
+        let Encapsulation {
+            ciphertext,
+            shared_secret,
+        } = encapsulate_struct(
+            &keypair.public,
+            &mut StdRng::from_seed([3u8; 32]),
+        )
+        .unwrap();
+        let (ct, ss) = encapsulate(
+            &keypair.public,
+            &mut StdRng::from_seed([3u8; 32]),
+        )
+        .unwrap();
+        assert_eq!((ciphertext, shared_secret), (ct, ss));
+        assert_eq!(
+            decapsulate(&ciphertext, &keypair.secret).unwrap(),
+            shared_secret
+        );
+
+        let encapsulation = Encapsulation::from((ct, ss));
+        assert_eq!(<(_, _)>::from(encapsulation), (ct, ss));
+        assert_eq!(
+            encapsulate_struct(&keypair.public[1..], &mut OsRng),
+            Err(KyberLibError::InvalidInput)
+        );
+    }
+
+    // Test that decapsulating with a separately held public key matches
+    // decapsulate, for the full key and for the IND-CPA key with z
+    #[test]
+    fn test_decapsulate_with_pk_matches_decapsulate() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let (ct, ss) = encapsulate(&keypair.public, &mut rng).unwrap();
+        let mut tampered = ct;
+        tampered[0] ^= 1;
+
+        let cpa = KYBER_SECRET_KEY_BYTES
+            - KYBER_PUBLIC_KEY_BYTES
+            - 2 * KYBER_SYM_BYTES;
+        let mut cpa_z = keypair.secret[..cpa].to_vec();
+        cpa_z.extend_from_slice(
+            &keypair.secret[KYBER_SECRET_KEY_BYTES - KYBER_SYM_BYTES..],
+        );
+
+        for sk in [&keypair.secret[..], &cpa_z[..]] {
+            assert_eq!(
+                decapsulate_with_pk(&ct, sk, &keypair.public).unwrap(),
+                ss
+            );
+            assert_eq!(
+                decapsulate_with_pk(&tampered, sk, &keypair.public)
+                    .unwrap(),
+                decapsulate(&tampered, &keypair.secret).unwrap()
+            );
+        }
+    }
+
+    // Test that a public key not matching the secret key is rejected
+    #[test]
+    fn test_decapsulate_with_pk_rejects_mismatched_pk() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let other = kyberlib::keypair(&mut rng).unwrap();
+        let (ct, _) = encapsulate(&keypair.public, &mut rng).unwrap();
+        assert_eq!(
+            decapsulate_with_pk(&ct, &keypair.secret, &other.public),
+            Err(KyberLibError::InvalidKey)
+        );
+        assert_eq!(
+            decapsulate_with_pk(
+                &ct,
+                &keypair.secret[1..],
+                &keypair.public
+            ),
+            Err(KyberLibError::InvalidInput)
+        );
+        assert_eq!(
+            decapsulate_with_pk(
+                &ct[1..],
+                &keypair.secret,
+                &keypair.public
+            ),
+            Err(KyberLibError::InvalidInput)
+        );
+    }
+
+    // Test that the versioned key format round-trips
+    #[test]
+    fn test_keypair_bytes_round_trip() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let bytes = keypair.to_bytes();
+        assert_eq!(bytes[0], KEY_FORMAT_VERSION);
+        assert_eq!(bytes.len(), VERSIONED_SECRET_KEY_BYTES);
+        assert_eq!(
+            SecretKey::from_bytes_checked(
+                &keypair.secret.to_versioned_bytes()
+            ),
+            Ok(keypair.secret)
+        );
+        assert_eq!(Keypair::from_bytes(&bytes), Ok(keypair));
+    }
+
+    // Test that blobs with another version, a wrong size or a corrupted
+    // public key are rejected
+    #[test]
+    fn test_keypair_bytes_reject_unknown_version() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let mut bytes = keypair.to_bytes();
+        bytes[0] = KEY_FORMAT_VERSION + 1;
+        assert_eq!(
+            Keypair::from_bytes(&bytes),
+            Err(KyberLibError::InvalidInput)
+        );
+        assert_eq!(
+            SecretKey::from_bytes_checked(&bytes),
+            Err(KyberLibError::InvalidInput)
+        );
+        bytes[0] = KEY_FORMAT_VERSION;
+        assert_eq!(
+            Keypair::from_bytes(&bytes[1..]),
+            Err(KyberLibError::InvalidLength)
+        );
+        // The stored H(pk) sits just before z at the end of the key.
+        bytes[VERSIONED_SECRET_KEY_BYTES - 2 * KYBER_SYM_BYTES] ^= 1;
+        assert_eq!(
+            Keypair::from_bytes(&bytes),
+            Err(KyberLibError::InvalidKey)
+        );
+    }
+
+    // Test that initiator and responder compute the same transcript hash,
+    // and that it changes with any element
+    #[test]
+    fn test_transcript_hash_matches_on_both_sides() {
+        let mut rng = OsRng;
+        let keypair = keypair(&mut rng).unwrap();
+        let (ct, initiator_ss) =
+            encapsulate(&keypair.public, &mut rng).unwrap();
+        let responder_ss = decapsulate(&ct, &keypair.secret).unwrap();
+        let initiator =
+            transcript_hash(&keypair.public, &ct, &initiator_ss);
+        let responder =
+            transcript_hash(&keypair.public, &ct, &responder_ss);
+        assert_eq!(initiator, responder);
+        assert_eq!(initiator.len(), TRANSCRIPT_HASH_BYTES);
+
+        let (other_ct, other_ss) =
+            encapsulate(&keypair.public, &mut rng).unwrap();
+        assert_ne!(
+            transcript_hash(&keypair.public, &other_ct, &initiator_ss),
+            initiator
+        );
+        assert_ne!(
+            transcript_hash(&keypair.public, &ct, &other_ss),
+            initiator
+        );
+    }
+
+    // Test that dropping a keypair zeroes the secret key, z included, and
+    // leaves the public key alone
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_keypair_drop_zeroes_only_the_secret_key() {
+        use core::mem::ManuallyDrop;
+        use core::ptr;
+
+        let mut rng = OsRng;
+        let mut slot = ManuallyDrop::new(keypair(&mut rng).unwrap());
+        let public = slot.public;
+        assert_ne!(slot.secret, [0u8; KYBER_SECRET_KEY_BYTES]);
+
+        // The storage outlives the value, so its bytes can be read back
+        // once the destructor has run.
+        let keys: *mut Keypair = &mut *slot;
+        let (public_after, secret_after) = unsafe {
+            ptr::drop_in_place(keys);
+            (
+                ptr::read_volatile(ptr::addr_of!((*keys).public)),
+                ptr::read_volatile(ptr::addr_of!((*keys).secret)),
+            )
+        };
+        assert_eq!(secret_after, [0u8; KYBER_SECRET_KEY_BYTES]);
+        assert_eq!(public_after, public);
+    }
 }
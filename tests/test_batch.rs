@@ -0,0 +1,89 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the batch operations. Run them with `--features parallel`
+//! as well to cover the rayon path.
+
+#![cfg(feature = "batch")]
+
+use kyberlib::batch::*;
+use kyberlib::*;
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+const N: usize = 17;
+
+#[test]
+fn keys_match_single_derivation() {
+    let seed = [3u8; BATCH_SEED_BYTES];
+    let keys = keypair_batch_from_seed(&seed, N).unwrap();
+    assert_eq!(keys.len(), N);
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(*k, derive(&item_keypair_seed(&seed, i)).unwrap());
+    }
+    // A shorter batch is a prefix of a longer one.
+    assert_eq!(keypair_batch_from_seed(&seed, 5).unwrap(), keys[..5]);
+    assert!(keypair_batch_from_seed(&seed, 0).unwrap().is_empty());
+}
+
+#[test]
+fn batches_round_trip_in_input_order() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair_batch(N, &mut rng).unwrap();
+    let pks: Vec<PublicKey> = keys.iter().map(|k| k.public).collect();
+    let encapsulated = encapsulate_batch(&pks, &mut rng).unwrap();
+    assert_eq!(encapsulated.len(), N);
+    for (k, item) in keys.iter().zip(&encapsulated) {
+        let (ct, ss) = item.unwrap();
+        assert_eq!(decapsulate(&ct, &k.secret).unwrap(), ss);
+    }
+
+    // Many ciphertexts to one key.
+    let to_first = encapsulate_batch(&[pks[0]; N], &mut rng).unwrap();
+    let cts: Vec<_> = to_first.iter().map(|e| e.unwrap().0).collect();
+    let secrets = decapsulate_batch(&cts, &keys[0].secret).unwrap();
+    for (item, ss) in to_first.iter().zip(&secrets) {
+        assert_eq!(item.unwrap().1, *ss);
+    }
+}
+
+#[test]
+fn seeded_rng_reproduces_batches() {
+    let keys =
+        keypair_batch(N, &mut ChaCha20Rng::seed_from_u64(1)).unwrap();
+    let again =
+        keypair_batch(N, &mut ChaCha20Rng::seed_from_u64(1)).unwrap();
+    assert_eq!(keys, again);
+
+    let pks: Vec<PublicKey> = keys.iter().map(|k| k.public).collect();
+    let seed = [9u8; BATCH_SEED_BYTES];
+    assert_eq!(
+        encapsulate_batch_from_seed(&pks, &seed),
+        encapsulate_batch_from_seed(&pks, &seed)
+    );
+    assert_ne!(
+        encapsulate_batch_from_seed(&pks, &seed),
+        encapsulate_batch_from_seed(&pks, &[8u8; BATCH_SEED_BYTES])
+    );
+}
+
+#[test]
+fn bad_items_fail_alone() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    // A coefficient of q in the first polynomial is not canonical.
+    let mut bad = keys.public;
+    bad[0] = 0x01;
+    bad[1] = 0x0d;
+    let encapsulated =
+        encapsulate_batch(&[keys.public, bad, keys.public], &mut rng)
+            .unwrap();
+    assert!(encapsulated[0].is_ok());
+    assert_eq!(encapsulated[1], Err(KyberLibError::InvalidKey));
+    assert!(encapsulated[2].is_ok());
+
+    let ct = encapsulated[0].unwrap().0;
+    assert_eq!(
+        decapsulate_batch(&[ct], &keys.secret[1..]),
+        Err(KyberLibError::InvalidInput)
+    );
+}
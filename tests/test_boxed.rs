@@ -0,0 +1,78 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the heap-allocated keys.
+
+#![cfg(feature = "boxed")]
+
+use core::mem::size_of_val;
+use kyberlib::boxed::*;
+use kyberlib::*;
+
+// Each holds its keys across an await point, as a request handler does.
+async fn hold_keypair(keys: Keypair) -> u8 {
+    core::future::ready(()).await;
+    keys.secret[0]
+}
+
+async fn hold_boxed(keys: BoxedKeypair) -> u8 {
+    core::future::ready(()).await;
+    keys.secret[0]
+}
+
+#[test]
+fn boxed_keys_shrink_futures() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let unboxed = hold_keypair(keys.clone());
+    let boxed = hold_boxed(keys.boxed());
+    // The unboxed future carries both keys; the boxed one two pointers.
+    assert!(
+        size_of_val(&unboxed)
+            >= KYBER_PUBLIC_KEY_BYTES + KYBER_SECRET_KEY_BYTES
+    );
+    assert!(size_of_val(&boxed) <= 8 * size_of_val(&0usize));
+}
+
+#[test]
+fn boxed_keypair_matches_keypair() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let boxed = keys.clone().boxed();
+    assert_eq!(*boxed.public, keys.public);
+    assert_eq!(boxed.expose_secret(), keys.expose_secret());
+    assert_eq!(boxed.to_bytes(), keys.to_bytes());
+    assert_eq!(
+        BoxedKeypair::from_bytes(&keys.to_bytes()).unwrap(),
+        boxed
+    );
+    assert_eq!(
+        BoxedKeypair::from_bytes(&keys.to_bytes()[1..]),
+        Err(KyberLibError::InvalidLength)
+    );
+
+    let (ct, ss) = encapsulate(&*boxed.public, &mut rng).unwrap();
+    assert_eq!(decapsulate(&ct, &*boxed.secret).unwrap(), ss);
+
+    let generated = BoxedKeypair::generate(&mut rng).unwrap();
+    let (ct, ss) = encapsulate(&*generated.public, &mut rng).unwrap();
+    assert_eq!(
+        decapsulate(&ct, generated.secret.as_ref()).unwrap(),
+        ss
+    );
+
+    let (mut public, mut secret) = (keys.public, keys.secret);
+    assert_eq!(
+        BoxedKeypair::import(&mut public, &mut secret, &mut rng)
+            .unwrap(),
+        boxed
+    );
+}
+
+#[test]
+fn boxed_secret_key_is_redacted() {
+    let keys = BoxedKeypair::generate(&mut rand::thread_rng()).unwrap();
+    let debug = format!("{:?}", keys);
+    assert!(debug.contains("BoxedSecretKey(<redacted>)"));
+    assert!(!debug.contains(&format!("{:?}", &keys.secret[..8])));
+}
@@ -0,0 +1,225 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the C API, including a C program linked against the static
+//! library.
+
+#![cfg(feature = "capi")]
+
+use core::ptr;
+use kyberlib::capi::*;
+use kyberlib::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[test]
+fn shared_secret_round_trips_through_the_c_api() {
+    let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+    let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss1 = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let mut ss2 = [0u8; KYBER_SHARED_SECRET_BYTES];
+    unsafe {
+        assert_eq!(
+            kyberlib_keypair(
+                pk.as_mut_ptr(),
+                pk.len(),
+                sk.as_mut_ptr(),
+                sk.len()
+            ),
+            KYBERLIB_OK
+        );
+        assert_eq!(
+            kyberlib_encapsulate(
+                ct.as_mut_ptr(),
+                ct.len(),
+                ss1.as_mut_ptr(),
+                ss1.len(),
+                pk.as_ptr(),
+                pk.len()
+            ),
+            KYBERLIB_OK
+        );
+        assert_eq!(
+            kyberlib_decapsulate(
+                ss2.as_mut_ptr(),
+                ss2.len(),
+                ct.as_ptr(),
+                ct.len(),
+                sk.as_ptr(),
+                sk.len()
+            ),
+            KYBERLIB_OK
+        );
+    }
+    assert_eq!(ss1, ss2);
+    assert_eq!(decapsulate(&ct, &sk).unwrap(), ss1);
+    assert_eq!(kyberlib_public_key_bytes(), KYBER_PUBLIC_KEY_BYTES);
+    assert_eq!(kyberlib_secret_key_bytes(), KYBER_SECRET_KEY_BYTES);
+    assert_eq!(kyberlib_ciphertext_bytes(), KYBER_CIPHERTEXT_BYTES);
+    assert_eq!(
+        kyberlib_shared_secret_bytes(),
+        KYBER_SHARED_SECRET_BYTES
+    );
+}
+
+#[test]
+fn null_pointers_and_wrong_lengths_are_rejected() {
+    let mut buf = [0u8; KYBER_SECRET_KEY_BYTES];
+    let mut out = [0u8; KYBER_SECRET_KEY_BYTES];
+    let pk_len = KYBER_PUBLIC_KEY_BYTES;
+    let sk_len = KYBER_SECRET_KEY_BYTES;
+    let ct_len = KYBER_CIPHERTEXT_BYTES;
+    let ss_len = KYBER_SHARED_SECRET_BYTES;
+    unsafe {
+        assert_eq!(
+            kyberlib_keypair(
+                ptr::null_mut(),
+                pk_len,
+                buf.as_mut_ptr(),
+                sk_len
+            ),
+            KYBERLIB_ERROR_NULL_POINTER
+        );
+        assert_eq!(
+            kyberlib_keypair(
+                out.as_mut_ptr(),
+                pk_len + 1,
+                buf.as_mut_ptr(),
+                sk_len
+            ),
+            KYBERLIB_ERROR_INVALID_LENGTH
+        );
+        assert_eq!(
+            kyberlib_encapsulate(
+                out.as_mut_ptr(),
+                ct_len,
+                buf.as_mut_ptr(),
+                ss_len,
+                ptr::null(),
+                pk_len
+            ),
+            KYBERLIB_ERROR_NULL_POINTER
+        );
+        assert_eq!(
+            kyberlib_encapsulate(
+                out.as_mut_ptr(),
+                ct_len,
+                buf.as_mut_ptr(),
+                ss_len - 1,
+                buf.as_ptr(),
+                pk_len
+            ),
+            KYBERLIB_ERROR_INVALID_LENGTH
+        );
+        assert_eq!(
+            kyberlib_decapsulate(
+                out.as_mut_ptr(),
+                ss_len,
+                ptr::null(),
+                ct_len,
+                buf.as_ptr(),
+                sk_len
+            ),
+            KYBERLIB_ERROR_NULL_POINTER
+        );
+        assert_eq!(
+            kyberlib_decapsulate(
+                out.as_mut_ptr(),
+                ss_len,
+                buf.as_ptr(),
+                ct_len,
+                buf.as_ptr(),
+                0
+            ),
+            KYBERLIB_ERROR_INVALID_LENGTH
+        );
+    }
+}
+
+fn root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn header_declares_the_c_api() {
+    let header =
+        std::fs::read_to_string(root().join("include/kyberlib.h"))
+            .unwrap();
+    let source =
+        std::fs::read_to_string(root().join("src/capi.rs")).unwrap();
+    for line in source.lines() {
+        if let Some(rest) = line.split("extern \"C\" fn ").nth(1) {
+            let name = rest.split('(').next().unwrap();
+            assert!(header.contains(&format!("{}(", name)), "{}", name);
+        }
+    }
+    for (name, code) in [
+        ("KYBERLIB_OK", KYBERLIB_OK),
+        ("KYBERLIB_ERROR_NULL_POINTER", KYBERLIB_ERROR_NULL_POINTER),
+        ("KYBERLIB_ERROR_RANDOMNESS", KYBERLIB_ERROR_RANDOMNESS),
+        (
+            "KYBERLIB_ERROR_INVALID_LENGTH",
+            KYBERLIB_ERROR_INVALID_LENGTH,
+        ),
+    ] {
+        let define = format!("#define {} {}\n", name, code);
+        assert!(header.contains(&define), "{}", define);
+    }
+}
+
+// Builds the static library, which is not one of the crate types Cargo
+// builds by default, in a target directory of its own so the build does
+// not wait on the lock held by the cargo running this test.
+fn static_library() -> PathBuf {
+    let target_dir = root().join("target/capi-staticlib");
+    let output = Command::new(env!("CARGO"))
+        .current_dir(root())
+        .args(["rustc", "--lib", "--features", "capi"])
+        .args(["--crate-type", "staticlib", "--target-dir"])
+        .arg(&target_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    target_dir.join("debug/libkyberlib.a")
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn c_program_links_and_runs() {
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+    let out = std::env::temp_dir()
+        .join(format!("kyberlib-test-capi-{}", std::process::id()));
+    let status = Command::new(&compiler)
+        .arg(root().join("tests/c/test_capi.c"))
+        .arg("-I")
+        .arg(root().join("include"))
+        .arg(static_library())
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&out)
+        .status();
+    let status = match status {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!(
+                "skipping, cannot run C compiler {}: {}",
+                compiler, err
+            );
+            return;
+        }
+    };
+    assert!(status.success(), "compiling tests/c/test_capi.c failed");
+
+    let output = Command::new(&out).output().unwrap();
+    let _ = std::fs::remove_file(&out);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, b"kyberlib C API: ok\n");
+}
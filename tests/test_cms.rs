@@ -0,0 +1,192 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for CMS KEMRecipientInfo support.
+
+#![cfg(feature = "cms")]
+
+use kyberlib::cms::*;
+use kyberlib::*;
+
+// Deterministic RNG so the fixture can be reproduced.
+#[cfg(feature = "kyber1024")]
+struct CountingRng(u8);
+
+#[cfg(feature = "kyber1024")]
+impl RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kyber1024")]
+impl CryptoRng for CountingRng {}
+
+#[cfg(feature = "kyber1024")]
+const FIXTURE_SEED: [u8; 64] = [0x11; 64];
+#[cfg(feature = "kyber1024")]
+const FIXTURE_SKI: &[u8] = b"kyberlib cms fixture";
+#[cfg(feature = "kyber1024")]
+const FIXTURE_UKM: &[u8] = b"user keying material";
+
+#[cfg(feature = "kyber1024")]
+fn fixture_cek() -> [u8; 32] {
+    let mut cek = [0u8; 32];
+    for (i, b) in cek.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    cek
+}
+
+#[test]
+fn wrap_unwrap_round_trip() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    for (cek, ukm) in [
+        (&[1u8; 16][..], None),
+        (&[2u8; 32][..], Some(&b"ukm"[..])),
+        (&[3u8; 64][..], Some(&[0u8; 300][..])),
+    ] {
+        let info = KemRecipientInfo::new(
+            &keys.public,
+            b"ski",
+            cek,
+            ukm,
+            &mut rng,
+        )
+        .unwrap();
+        let parsed =
+            KemRecipientInfo::from_der(&info.to_der()).unwrap();
+        assert_eq!(parsed, info);
+        assert_eq!(parsed.ukm(), ukm);
+        assert_eq!(parsed.subject_key_id(), b"ski");
+        assert_eq!(&parsed.unwrap_cek(&keys.secret).unwrap()[..], cek);
+    }
+}
+
+#[test]
+fn wrong_recipient_fails_to_unwrap() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let other = keypair(&mut rng).unwrap();
+    let info = KemRecipientInfo::new(
+        &keys.public,
+        b"ski",
+        &[9u8; 32],
+        None,
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(
+        info.unwrap_cek(&other.secret).unwrap_err(),
+        KyberLibError::Decapsulation
+    );
+}
+
+#[test]
+fn invalid_cek_lengths_are_rejected() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    for len in [0, 8, 20] {
+        assert_eq!(
+            KemRecipientInfo::new(
+                &keys.public,
+                b"ski",
+                &vec![0u8; len],
+                None,
+                &mut rng
+            )
+            .unwrap_err(),
+            KyberLibError::InvalidLength
+        );
+    }
+}
+
+#[test]
+fn strict_der_parsing() {
+    let der =
+        include_bytes!("fixtures/cms_kemri_kyber1024.der").to_vec();
+    let reject = |bytes: &[u8]| {
+        assert_eq!(
+            KemRecipientInfo::from_der(bytes).unwrap_err(),
+            KyberLibError::InvalidInput
+        );
+    };
+
+    // Trailing data and truncation.
+    let mut trailing = der.clone();
+    trailing.push(0);
+    reject(&trailing);
+    reject(&der[..der.len() - 1]);
+
+    // Version other than 0.
+    let mut bad = der.clone();
+    bad[6] = 1;
+    reject(&bad);
+
+    // Unknown KEM OID: ML-KEM-768 instead of ML-KEM-1024.
+    let mut bad = der.clone();
+    bad[41] = 0x02;
+    reject(&bad);
+
+    // kekLength other than 32.
+    let mut bad = der.clone();
+    bad[1631] = 16;
+    reject(&bad);
+
+    // Non-minimal length encoding of the version INTEGER.
+    let mut bad = vec![0x30, 0x82];
+    let body = [&[0x02, 0x81, 0x01, 0x00][..], &der[7..]].concat();
+    bad.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    bad.extend_from_slice(&body);
+    reject(&bad);
+}
+
+// Structure wrapped for a key derived from FIXTURE_SEED. It must keep
+// parsing and unwrapping in future releases; do not regenerate it.
+#[cfg(feature = "kyber1024")]
+#[test]
+fn fixture_parses_and_unwraps() {
+    let der = include_bytes!("fixtures/cms_kemri_kyber1024.der");
+    let keys = derive(&FIXTURE_SEED).unwrap();
+    let info = KemRecipientInfo::from_der(der).unwrap();
+    assert_eq!(info.subject_key_id(), FIXTURE_SKI);
+    assert_eq!(info.ukm(), Some(FIXTURE_UKM));
+    assert_eq!(
+        &info.unwrap_cek(&keys.secret).unwrap()[..],
+        &fixture_cek()
+    );
+    assert_eq!(&info.to_der()[..], &der[..]);
+
+    // The fixture was produced from a counting RNG.
+    let rebuilt = KemRecipientInfo::new(
+        &keys.public,
+        FIXTURE_SKI,
+        &fixture_cek(),
+        Some(FIXTURE_UKM),
+        &mut CountingRng(0),
+    )
+    .unwrap();
+    assert_eq!(rebuilt, info);
+}
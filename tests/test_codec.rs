@@ -0,0 +1,122 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the constant-time codecs.
+
+use base64ct::{Base64, Base64UrlUnpadded, Encoding};
+use kyberlib::codec::*;
+use kyberlib::KyberLibError;
+use rand::RngCore;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Every byte value, then random inputs of every length up to 100.
+fn inputs() -> Vec<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let mut inputs = vec![(0..=255).collect::<Vec<u8>>()];
+    for len in 0..100 {
+        let mut bytes = vec![0u8; len];
+        rng.fill_bytes(&mut bytes);
+        inputs.push(bytes);
+    }
+    inputs
+}
+
+#[test]
+fn hex_matches_the_standard_codec() {
+    for bytes in inputs() {
+        let mut buf = vec![0u8; hex_encoded_len(bytes.len())];
+        let encoded = hex_encode(&bytes, &mut buf).unwrap();
+        assert_eq!(encoded, hex(&bytes));
+
+        let mut out = vec![0u8; bytes.len()];
+        assert_eq!(hex_decode(encoded, &mut out).unwrap(), &bytes[..]);
+        let upper = encoded.to_uppercase();
+        assert_eq!(hex_decode(&upper, &mut out).unwrap(), &bytes[..]);
+    }
+}
+
+#[test]
+fn base64_matches_the_standard_codec() {
+    for bytes in inputs() {
+        let mut buf = vec![0u8; base64_encoded_len(bytes.len())];
+        let encoded = base64_encode(&bytes, &mut buf).unwrap();
+        assert_eq!(encoded, Base64::encode_string(&bytes));
+        let mut out = vec![0u8; bytes.len()];
+        assert_eq!(
+            base64_decode(encoded, &mut out).unwrap(),
+            &bytes[..]
+        );
+
+        let mut buf = vec![0u8; base64url_encoded_len(bytes.len())];
+        let encoded = base64url_encode(&bytes, &mut buf).unwrap();
+        assert_eq!(encoded, Base64UrlUnpadded::encode_string(&bytes));
+        assert_eq!(
+            base64url_decode(encoded, &mut out).unwrap(),
+            &bytes[..]
+        );
+    }
+}
+
+#[test]
+fn invalid_characters_are_rejected_anywhere() {
+    let bytes = [0xa5u8; 12];
+    let hex = hex(&bytes);
+    let b64 = Base64::encode_string(&bytes);
+    let url = Base64UrlUnpadded::encode_string(&bytes);
+    let mut out = [0u8; 12];
+
+    for c in 0..=255u8 {
+        for i in [0, 5, 11] {
+            let mut input = hex.clone().into_bytes();
+            input[i] = c;
+            let result = hex_decode(&input, &mut out);
+            assert_eq!(result.is_ok(), c.is_ascii_hexdigit());
+            if result.is_err() {
+                assert_eq!(result, Err(KyberLibError::InvalidEncoding));
+                assert_eq!(out, [0; 12], "not cleared");
+            }
+
+            let mut input = b64.clone().into_bytes();
+            input[i] = c;
+            let valid =
+                c.is_ascii_alphanumeric() || c == b'+' || c == b'/';
+            assert_eq!(base64_decode(&input, &mut out).is_ok(), valid);
+
+            let mut input = url.clone().into_bytes();
+            input[i] = c;
+            let valid =
+                c.is_ascii_alphanumeric() || c == b'-' || c == b'_';
+            assert_eq!(
+                base64url_decode(&input, &mut out).is_ok(),
+                valid
+            );
+        }
+    }
+}
+
+#[test]
+fn malformed_lengths_and_padding_are_rejected() {
+    let mut out = [0u8; 4];
+    let invalid = Err(KyberLibError::InvalidEncoding);
+    assert_eq!(hex_decode("abc", &mut out), invalid);
+    assert_eq!(base64_decode("QUI", &mut out), invalid);
+    assert_eq!(base64_decode("Q===", &mut out), invalid);
+    assert_eq!(base64_decode("QU=I", &mut out), invalid);
+    assert_eq!(base64url_decode("QUI=", &mut out), invalid);
+    assert_eq!(base64url_decode("QUJDR", &mut out), invalid);
+    // Non-zero bits after the last byte.
+    assert_eq!(base64_decode("QUJ=", &mut out), invalid);
+    assert_eq!(base64url_decode("QR", &mut out), invalid);
+    assert_eq!(base64_decode("QUI=", &mut out).unwrap(), b"AB");
+
+    let short = Err(KyberLibError::InvalidLength);
+    assert_eq!(hex_decode("0011223344", &mut out), short);
+    assert_eq!(base64_decode("QUJDREU=", &mut out), short);
+    assert_eq!(
+        hex_encode(&[0; 3], &mut out),
+        Err(KyberLibError::InvalidLength)
+    );
+}
@@ -0,0 +1,139 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Cross-validation against the `pqc_kyber` crate.
+//!
+//! Run with `cargo test --features compat-tests` on the default
+//! `kyber1024` + `90s-fixslice` variant, which the dev-dependency mirrors
+//! with its own `kyber1024`, `90s-fixslice` and `hazmat` features.
+//!
+//! Shared secrets cannot match at the KEM level: in 90s mode this crate
+//! computes H, G and the KDF with BLAKE3 where `pqc_kyber` uses SHA-2, and
+//! the SHAKE variant does not build here. The tests therefore check the
+//! IND-CPA layer both crates share, which covers the key and ciphertext
+//! layouts, the NTT, compression and the AES-CTR XOF and PRF.
+
+#![cfg(all(
+    feature = "compat-tests",
+    feature = "kyber1024",
+    feature = "90s-fixslice"
+))]
+
+use kyberlib::kem::check_public_key;
+use kyberlib::*;
+
+// Deterministic RNG so the encapsulated message is known.
+struct CountingRng(u8);
+
+impl RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for CountingRng {}
+
+#[test]
+fn sizes_match_pqc_kyber() {
+    assert_eq!(KYBER_PUBLIC_KEY_BYTES, pqc_kyber::KYBER_PUBLICKEYBYTES);
+    assert_eq!(KYBER_SECRET_KEY_BYTES, pqc_kyber::KYBER_SECRETKEYBYTES);
+    assert_eq!(
+        KYBER_CIPHERTEXT_BYTES,
+        pqc_kyber::KYBER_CIPHERTEXTBYTES
+    );
+    assert_eq!(KYBER_SHARED_SECRET_BYTES, pqc_kyber::KYBER_SSBYTES);
+}
+
+#[test]
+fn pqc_kyber_decrypts_our_ciphertext() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..8 {
+        let keys = pqc_kyber::keypair(&mut rng).unwrap();
+        assert_eq!(check_public_key(&keys.public), Ok(()));
+
+        let mut counting = CountingRng(0);
+        let (ct, _) = encapsulate(&keys.public, &mut counting).unwrap();
+
+        // encapsulate hashes the RNG output into the IND-CPA message
+        let randomness: Vec<u8> = (0..KYBER_SYM_BYTES as u8).collect();
+        let message = blake3::hash(&randomness);
+
+        let mut decrypted = [0u8; KYBER_SYM_BYTES];
+        pqc_kyber::indcpa::indcpa_dec(
+            &mut decrypted,
+            &ct,
+            &keys.secret,
+        );
+        assert_eq!(&decrypted, message.as_bytes());
+    }
+}
+
+#[test]
+fn pqc_kyber_encapsulates_to_our_public_key() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, ss) =
+        pqc_kyber::encapsulate(&keys.public, &mut rng).unwrap();
+    assert_eq!(ct.len(), KYBER_CIPHERTEXT_BYTES);
+    // Decapsulation runs, but implicit rejection hides the divergent hashes
+    let ours = decapsulate(&ct, &keys.secret).unwrap();
+    assert_ne!(ours, ss);
+}
+
+// With a SIMD backend the tests above run on the fastest one the CPU
+// has; this one repeats the key and ciphertext checks on each available
+// backend.
+#[cfg(feature = "force-backend")]
+#[test]
+fn all_backends_interoperate_with_pqc_kyber() {
+    use kyberlib::acceleration::{
+        available, force_backend, reset_backend,
+    };
+
+    let mut rng = rand::thread_rng();
+    let seed = ([3u8; KYBER_SYM_BYTES], [5u8; KYBER_SYM_BYTES]);
+    let mut outputs = Vec::new();
+    for backend in available() {
+        force_backend(backend).unwrap();
+        let keys = derive(&[seed.0, seed.1].concat()).unwrap();
+        let (ct, _) =
+            encapsulate(&keys.public, &mut CountingRng(0)).unwrap();
+
+        let theirs = pqc_kyber::keypair(&mut rng).unwrap();
+        let (their_ct, _) =
+            encapsulate(&theirs.public, &mut CountingRng(0)).unwrap();
+        let randomness: Vec<u8> = (0..KYBER_SYM_BYTES as u8).collect();
+        let mut decrypted = [0u8; KYBER_SYM_BYTES];
+        pqc_kyber::indcpa::indcpa_dec(
+            &mut decrypted,
+            &their_ct,
+            &theirs.secret,
+        );
+        assert_eq!(&decrypted, blake3::hash(&randomness).as_bytes());
+
+        outputs.push((keys, ct));
+    }
+    reset_backend();
+    assert!(outputs.windows(2).all(|w| w[0] == w[1]));
+}
@@ -0,0 +1,60 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Round trips with the ciphertext compression widths overridden.
+//!
+//! Without an override these run on the specified `du` and `dv`; CI also
+//! runs them on a narrower ciphertext:
+//!
+//! ```shell
+//! RUSTFLAGS='--cfg kyber_du="9" --cfg kyber_dv="4"' cargo test --test test_compression_override
+//! ```
+//!
+//! Narrower widths add rounding noise and so raise the decryption failure
+//! rate. For Kyber1024 with `du = 9` and `dv = 4` it stays far too small
+//! to show up in the rounds below, so every round must succeed; a failure
+//! here points at the packing code, not at the parameters.
+
+use kyberlib::params::*;
+use kyberlib::*;
+
+const ROUNDS: usize = 256;
+
+#[test]
+fn ciphertext_size_follows_du_and_dv() {
+    assert_eq!(
+        KYBER_CIPHERTEXT_BYTES,
+        (KYBER_SECURITY_PARAMETER * KYBER_DU + KYBER_DV) * KYBER_N / 8
+    );
+    if cfg!(kyber_du = "9") {
+        assert_eq!(KYBER_DU, 9);
+    }
+    if cfg!(kyber_dv = "4") {
+        assert_eq!(KYBER_DV, 4);
+    }
+}
+
+#[test]
+fn round_trips_with_the_configured_widths() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    for _ in 0..ROUNDS {
+        let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+        let (decapsulated, canonical) =
+            decapsulate_strict(&ct, &keys.secret).unwrap();
+        assert_eq!(decapsulated, ss);
+        assert!(canonical);
+    }
+}
+
+#[test]
+fn overrides_run_the_reference_backend() {
+    let specified = if cfg!(feature = "kyber1024") {
+        (11, 5)
+    } else {
+        (10, 4)
+    };
+    if (KYBER_DU, KYBER_DV) != specified {
+        assert_eq!(acceleration(), Acceleration::Scalar);
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for key confirmation tags.
+
+#![cfg(feature = "confirmation")]
+
+use kyberlib::confirmation::*;
+use kyberlib::*;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn matching_secrets_verify() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, initiator_secret) =
+        encapsulate(&keys.public, &mut rng).unwrap();
+    let responder_secret = decapsulate(&ct, &keys.secret).unwrap();
+
+    for role in [Role::Initiator, Role::Responder] {
+        let tag = initiator_secret.confirmation_tag(role, &ct);
+        assert!(bool::from(
+            responder_secret.verify_confirmation_tag(role, &ct, &tag)
+        ));
+    }
+}
+
+#[test]
+fn mismatched_secrets_fail() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let other = keypair(&mut rng).unwrap();
+    let (ct, initiator_secret) =
+        encapsulate(&keys.public, &mut rng).unwrap();
+    // Implicit rejection: decapsulating with the wrong key still succeeds
+    let responder_secret = decapsulate(&ct, &other.secret).unwrap();
+
+    let tag = initiator_secret.confirmation_tag(Role::Initiator, &ct);
+    assert!(!bool::from(responder_secret.verify_confirmation_tag(
+        Role::Initiator,
+        &ct,
+        &tag
+    )));
+    assert!(!bool::from(initiator_secret.verify_confirmation_tag(
+        Role::Initiator,
+        b"another transcript",
+        &tag
+    )));
+}
+
+#[test]
+fn reflected_tags_fail() {
+    let secret: SharedSecret = [7; KYBER_SHARED_SECRET_BYTES];
+    let tag = secret.confirmation_tag(Role::Initiator, b"transcript");
+    assert!(!bool::from(secret.verify_confirmation_tag(
+        Role::Responder,
+        b"transcript",
+        &tag
+    )));
+    assert_ne!(
+        tag,
+        secret.confirmation_tag(Role::Responder, b"transcript")
+    );
+}
+
+// Pins the derivation; do not regenerate these values
+#[test]
+fn confirmation_tag_vectors() {
+    let secret: SharedSecret = [0x42; KYBER_SHARED_SECRET_BYTES];
+    assert_eq!(
+        hex(&secret.confirmation_tag(Role::Initiator, b"transcript")),
+        "8e301f49dfa5ee7e7033087ebafd5b95"
+    );
+    assert_eq!(
+        hex(&secret.confirmation_tag(Role::Responder, b"transcript")),
+        "86d140371f119a6cce34b3bfce52c6b7"
+    );
+}
@@ -0,0 +1,121 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for key generation and encapsulation from entropy sources.
+
+use kyberlib::entropy::*;
+use kyberlib::*;
+use rand::rngs::OsRng;
+
+// Mock TRNG replaying a fixed byte pattern and counting the bytes drawn.
+struct MockTrng {
+    pattern: fn(usize) -> u8,
+    drawn: usize,
+    fail: bool,
+}
+
+impl MockTrng {
+    fn new(pattern: fn(usize) -> u8) -> Self {
+        MockTrng {
+            pattern,
+            drawn: 0,
+            fail: false,
+        }
+    }
+}
+
+impl EntropySource for MockTrng {
+    fn try_fill(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), EntropyError> {
+        if self.fail {
+            return Err(EntropyError);
+        }
+        for byte in dest.iter_mut() {
+            *byte = (self.pattern)(self.drawn);
+            self.drawn += 1;
+        }
+        Ok(())
+    }
+}
+
+fn counter(i: usize) -> u8 {
+    (i * 37 + 11) as u8
+}
+
+#[test]
+fn operations_draw_the_documented_number_of_bytes() {
+    let mut trng = MockTrng::new(counter);
+    let keys = keypair_from_entropy(&mut trng).unwrap();
+    assert_eq!(trng.drawn, KEYPAIR_ENTROPY_BYTES);
+
+    let (ct, ss) =
+        encapsulate_from_entropy(&keys.public, &mut trng).unwrap();
+    assert_eq!(
+        trng.drawn,
+        KEYPAIR_ENTROPY_BYTES + ENCAPSULATE_ENTROPY_BYTES
+    );
+    assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+}
+
+#[test]
+fn keypair_is_derived_from_the_drawn_bytes() {
+    let seed: Vec<u8> =
+        (0..KEYPAIR_ENTROPY_BYTES).map(counter).collect();
+    let keys =
+        keypair_from_entropy(&mut MockTrng::new(counter)).unwrap();
+    assert_eq!(keys, derive(&seed).unwrap());
+}
+
+#[test]
+fn rng_is_an_entropy_source() {
+    let keys = keypair_from_entropy(&mut OsRng).unwrap();
+    let (ct, ss) =
+        encapsulate_from_entropy(&keys.public, &mut OsRng).unwrap();
+    assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+}
+
+#[test]
+fn failing_source_is_reported() {
+    let mut trng = MockTrng::new(counter);
+    trng.fail = true;
+    assert_eq!(
+        keypair_from_entropy(&mut trng),
+        Err(KyberLibError::RandomBytesGeneration)
+    );
+    let keys = keypair(&mut OsRng).unwrap();
+    assert_eq!(
+        encapsulate_from_entropy(&keys.public, &mut trng),
+        Err(KyberLibError::RandomBytesGeneration)
+    );
+}
+
+#[test]
+fn degenerate_output_fails_the_health_check() {
+    let keys = keypair(&mut OsRng).unwrap();
+    for pattern in [|_| 0x00, |_| 0xa5, |_| 0xff] {
+        let pattern: fn(usize) -> u8 = pattern;
+        assert_eq!(
+            keypair_from_entropy(&mut MockTrng::new(pattern)),
+            Err(KyberLibError::RandomBytesGeneration)
+        );
+        assert_eq!(
+            encapsulate_from_entropy(
+                &keys.public,
+                &mut MockTrng::new(pattern)
+            ),
+            Err(KyberLibError::RandomBytesGeneration)
+        );
+    }
+}
+
+#[test]
+fn wrong_public_key_length_draws_nothing() {
+    let mut trng = MockTrng::new(counter);
+    assert_eq!(
+        encapsulate_from_entropy(&[0u8; 3], &mut trng),
+        Err(KyberLibError::InvalidInput)
+    );
+    assert_eq!(trng.drawn, 0);
+}
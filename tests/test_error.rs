@@ -1,6 +1,8 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the `KyberLibError` type.
+
 #![cfg(test)]
 
 // Import the necessary crates for testing in a no_std environment
@@ -39,6 +41,30 @@ fn test_kyber_lib_error_display() {
         error.to_string(),
         "The length of the input buffer is invalid."
     );
+    let error = KyberLibError::Unsealing;
+    assert_eq!(
+        error.to_string(),
+        "Unable to open sealed secret key, wrong password or corrupted data"
+    );
+    let error = KyberLibError::InvalidShare;
+    assert_eq!(
+        error.to_string(),
+        "Secret share is corrupted or belongs to another key"
+    );
+    let error = KyberLibError::InvalidSignature;
+    assert_eq!(error.to_string(), "Signature verification failed");
+    let error = KyberLibError::WeakKey;
+    assert_eq!(
+        error.to_string(),
+        "Generated key is degenerate, the RNG may be broken"
+    );
+    let error = KyberLibError::Overrun;
+    assert_eq!(
+        error.to_string(),
+        "Operation overran its padded duration"
+    );
+    let error = KyberLibError::InvalidEncoding;
+    assert_eq!(error.to_string(), "Input is not valid hex or base64");
 }
 
 #[test]
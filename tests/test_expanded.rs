@@ -0,0 +1,174 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for expanded secret keys and their cache format.
+
+#![cfg(feature = "expanded")]
+
+use kyberlib::expanded::*;
+use kyberlib::params::*;
+use kyberlib::*;
+use std::sync::{Mutex, MutexGuard};
+
+// The count of expanded matrix entries is process-wide, so the tests
+// take turns.
+fn lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn cache(include_matrix: bool) -> (Keypair, Vec<u8>) {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let cache = ExpandedSecretKey::new(&keys.secret)
+        .to_cache_bytes(include_matrix);
+    (keys, cache)
+}
+
+#[test]
+fn decapsulates_like_the_secret_key() {
+    let _guard = lock();
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let expanded = ExpandedSecretKey::new(&keys.secret);
+    assert_eq!(expanded.public_key(), keys.public);
+    for _ in 0..16 {
+        let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+        assert_eq!(expanded.decapsulate(&ct).unwrap(), ss);
+
+        // Implicit rejection yields the same pseudo-random secret.
+        let mut forged = ct;
+        forged[0] ^= 1;
+        assert_eq!(
+            expanded.decapsulate(&forged).unwrap(),
+            decapsulate(&forged, &keys.secret).unwrap()
+        );
+    }
+    assert_eq!(
+        expanded.decapsulate(&[0u8; KYBER_CIPHERTEXT_BYTES - 1]),
+        Err(KyberLibError::InvalidInput)
+    );
+}
+
+#[test]
+fn cache_round_trips_with_and_without_the_matrix() {
+    let _guard = lock();
+    let mut rng = rand::thread_rng();
+    for include_matrix in [true, false] {
+        let (keys, cache) = cache(include_matrix);
+        let matrix = if include_matrix {
+            CACHE_MATRIX_BYTES
+        } else {
+            0
+        };
+        assert_eq!(
+            cache.len(),
+            CACHE_HEADER_BYTES
+                + KYBER_SECRET_KEY_BYTES
+                + matrix
+                + CACHE_TAG_BYTES
+        );
+        let loaded =
+            ExpandedSecretKey::from_cache_bytes(&cache).unwrap();
+        let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+        assert_eq!(loaded.decapsulate(&ct).unwrap(), ss);
+        assert_eq!(loaded.to_cache_bytes(include_matrix), cache);
+    }
+}
+
+#[test]
+fn tampered_cache_is_rejected() {
+    let _guard = lock();
+    for include_matrix in [true, false] {
+        let (_, cache) = cache(include_matrix);
+        // The secret vector, the public key, z, the matrix and the tag.
+        let mut offsets = vec![
+            CACHE_HEADER_BYTES,
+            CACHE_HEADER_BYTES + KYBER_INDCPA_SECRET_KEY_BYTES,
+            CACHE_HEADER_BYTES + KYBER_SECRET_KEY_BYTES - 1,
+            cache.len() - 1,
+        ];
+        if include_matrix {
+            offsets.push(CACHE_HEADER_BYTES + KYBER_SECRET_KEY_BYTES);
+        }
+        for offset in offsets {
+            let mut tampered = cache.clone();
+            tampered[offset] ^= 1;
+            assert_eq!(
+                ExpandedSecretKey::from_cache_bytes(&tampered)
+                    .unwrap_err(),
+                KyberLibError::InvalidKey,
+                "byte {}",
+                offset
+            );
+        }
+    }
+}
+
+#[test]
+fn cache_of_another_key_does_not_verify() {
+    let _guard = lock();
+    let (_, mut first) = cache(true);
+    let (_, second) = cache(true);
+    // The matrix and tag of one key behind the secret key of another.
+    let start = CACHE_HEADER_BYTES + KYBER_SECRET_KEY_BYTES;
+    first[start..].copy_from_slice(&second[start..]);
+    assert_eq!(
+        ExpandedSecretKey::from_cache_bytes(&first).unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+}
+
+#[test]
+fn header_is_validated() {
+    let _guard = lock();
+    let (_, cache) = cache(true);
+    for (index, value) in [
+        (0, CACHE_FORMAT_VERSION + 1),
+        (1, KYBER_SECURITY_PARAMETER as u8 - 1),
+        (2, u8::from(!KYBER_90S)),
+        (3, 2),
+    ] {
+        let mut bad = cache.clone();
+        bad[index] = value;
+        assert_eq!(
+            ExpandedSecretKey::from_cache_bytes(&bad).unwrap_err(),
+            KyberLibError::InvalidInput,
+            "header byte {}",
+            index
+        );
+    }
+
+    // The flag must match the length.
+    let mut unflagged = cache.clone();
+    unflagged[3] = 0;
+    for bad in [&unflagged[..], &cache[..cache.len() - 1], &cache[..3]]
+    {
+        assert_eq!(
+            ExpandedSecretKey::from_cache_bytes(bad).unwrap_err(),
+            KyberLibError::InvalidLength
+        );
+    }
+}
+
+// Loading a cache with the matrix and decapsulating never samples an
+// entry of A; loading one without it samples each entry once.
+#[cfg(debug_assertions)]
+#[test]
+fn cache_with_the_matrix_skips_the_expansion() {
+    use kyberlib::acceleration::matrix_entries;
+
+    let _guard = lock();
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+    let expanded = ExpandedSecretKey::new(&keys.secret);
+    let k = KYBER_SECURITY_PARAMETER;
+    for (include_matrix, entries) in [(true, 0), (false, k * k)] {
+        let cache = expanded.to_cache_bytes(include_matrix);
+        let before = matrix_entries();
+        let loaded =
+            ExpandedSecretKey::from_cache_bytes(&cache).unwrap();
+        assert_eq!(loaded.decapsulate(&ct).unwrap(), ss);
+        assert_eq!(matrix_entries() - before, entries);
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the C ABI.
+
+#![cfg(feature = "ffi")]
+
+use core::ptr;
+use kyberlib::ffi::*;
+use kyberlib::*;
+
+#[test]
+fn shared_secret_round_trips_through_the_c_abi() {
+    let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+    let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss1 = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let mut ss2 = [0u8; KYBER_SHARED_SECRET_BYTES];
+    unsafe {
+        assert_eq!(
+            kyber_keypair(pk.as_mut_ptr(), sk.as_mut_ptr()),
+            KYBER_OK
+        );
+        assert_eq!(
+            kyber_enc(ct.as_mut_ptr(), ss1.as_mut_ptr(), pk.as_ptr()),
+            KYBER_OK
+        );
+        assert_eq!(
+            kyber_dec(ss2.as_mut_ptr(), ct.as_ptr(), sk.as_ptr()),
+            KYBER_OK
+        );
+    }
+    assert_eq!(ss1, ss2);
+    assert_eq!(public(&sk), pk);
+    assert_eq!(decapsulate(&ct, &sk).unwrap(), ss1);
+}
+
+#[test]
+fn null_pointers_are_rejected() {
+    let mut buf = [0u8; KYBER_SECRET_KEY_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    unsafe {
+        assert_eq!(
+            kyber_keypair(ptr::null_mut(), buf.as_mut_ptr()),
+            KYBER_ERROR_NULL_POINTER
+        );
+        assert_eq!(
+            kyber_enc(ss.as_mut_ptr(), buf.as_mut_ptr(), ptr::null()),
+            KYBER_ERROR_NULL_POINTER
+        );
+        assert_eq!(
+            kyber_dec(ss.as_mut_ptr(), ptr::null(), buf.as_ptr()),
+            KYBER_ERROR_NULL_POINTER
+        );
+    }
+}
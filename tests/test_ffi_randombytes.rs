@@ -0,0 +1,61 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the C ABI entropy callback. The callback is process-wide,
+//! so this binary holds a single test that installs it.
+
+#![cfg(feature = "ffi")]
+
+use core::ffi::c_int;
+use kyberlib::ffi::*;
+use kyberlib::*;
+
+extern "C" fn constant_bytes(buf: *mut u8, len: usize) -> c_int {
+    unsafe { core::ptr::write_bytes(buf, 0x2a, len) };
+    0
+}
+
+extern "C" fn failing_bytes(_buf: *mut u8, _len: usize) -> c_int {
+    -1
+}
+
+fn ffi_keypair() -> (c_int, PublicKey, SecretKey) {
+    let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+    let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
+    let status =
+        unsafe { kyber_keypair(pk.as_mut_ptr(), sk.as_mut_ptr()) };
+    (status, pk, sk)
+}
+
+fn ffi_enc(pk: &PublicKey) -> (c_int, [u8; KYBER_CIPHERTEXT_BYTES]) {
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let status = unsafe {
+        kyber_enc(ct.as_mut_ptr(), ss.as_mut_ptr(), pk.as_ptr())
+    };
+    (status, ct)
+}
+
+#[test]
+fn callback_supplies_the_randomness() {
+    kyber_set_randombytes(Some(constant_bytes));
+    let (status, pk, sk) = ffi_keypair();
+    assert_eq!(status, KYBER_OK);
+    // Both seeds of the key are drawn from the callback.
+    let expected = derive(&[0x2a; 64]).unwrap();
+    assert_eq!((pk, sk), (expected.public, expected.secret));
+    assert_eq!(ffi_keypair(), (KYBER_OK, pk, sk));
+    let (status, ct) = ffi_enc(&pk);
+    assert_eq!(status, KYBER_OK);
+    assert_eq!(ffi_enc(&pk), (KYBER_OK, ct));
+
+    kyber_set_randombytes(Some(failing_bytes));
+    assert_eq!(ffi_keypair().0, KYBER_ERROR_RANDOMNESS);
+    assert_eq!(ffi_enc(&pk).0, KYBER_ERROR_RANDOMNESS);
+
+    // NULL goes back to the operating system.
+    kyber_set_randombytes(None);
+    let (status, other, _) = ffi_keypair();
+    assert_eq!(status, KYBER_OK);
+    assert_ne!(other, pk);
+}
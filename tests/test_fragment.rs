@@ -0,0 +1,220 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for ciphertext fragmentation and reassembly.
+
+#![cfg(feature = "fragment")]
+
+use kyberlib::fragment::*;
+use kyberlib::*;
+
+fn ciphertext() -> [u8; KYBER_CIPHERTEXT_BYTES] {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    encapsulate(&keys.public, &mut rng).unwrap().0
+}
+
+fn datagrams(
+    message: &[u8],
+    message_id: u32,
+    mtu: usize,
+) -> Vec<Vec<u8>> {
+    fragment(message, message_id, mtu)
+        .unwrap()
+        .map(|f| {
+            let mut datagram = vec![0u8; mtu];
+            let len = f.encode(&mut datagram).unwrap();
+            datagram.truncate(len);
+            datagram
+        })
+        .collect()
+}
+
+/// The number of fragments a ciphertext is split into at `mtu`.
+fn fragment_count(mtu: usize) -> usize {
+    KYBER_CIPHERTEXT_BYTES.div_ceil(mtu - FRAGMENT_HEADER_BYTES)
+}
+
+/// The MTU that splits a ciphertext into `count` fragments.
+fn mtu_for(count: usize) -> usize {
+    let mtu =
+        FRAGMENT_HEADER_BYTES + KYBER_CIPHERTEXT_BYTES.div_ceil(count);
+    assert_eq!(fragment_count(mtu), count);
+    mtu
+}
+
+#[test]
+fn reassembles_out_of_order_fragments() {
+    let ct = ciphertext();
+    let mut datagrams = datagrams(&ct, 1, 200);
+    let count = fragment_count(200);
+    assert_eq!(datagrams.len(), count);
+    assert!(datagrams.iter().all(|d| d.len() <= 200));
+    datagrams.swap(0, count / 2);
+    datagrams.swap(2, count - 1);
+    datagrams.reverse();
+
+    let mut buffer = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut reassembler = Reassembler::new(&mut buffer, 10);
+    let (last, rest) = datagrams.split_last().unwrap();
+    for datagram in rest {
+        assert_eq!(reassembler.accept(datagram, 0), Ok(None));
+    }
+    assert_eq!(reassembler.accept(last, 0), Ok(Some(&ct[..])));
+    assert_eq!(reassembler.pending_message_id(), None);
+}
+
+#[test]
+fn duplicates_are_detected() {
+    let ct = ciphertext();
+    let datagrams = datagrams(&ct, 2, mtu_for(2));
+    assert_eq!(datagrams.len(), 2);
+
+    let mut buffer = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut reassembler = Reassembler::new(&mut buffer, 10);
+    assert_eq!(reassembler.accept(&datagrams[0], 0), Ok(None));
+    assert_eq!(
+        reassembler.accept(&datagrams[0], 0),
+        Err(FragmentError::Duplicate)
+    );
+    assert_eq!(reassembler.accept(&datagrams[1], 0), Ok(Some(&ct[..])));
+    // A late copy of the message just completed is a duplicate too.
+    assert_eq!(
+        reassembler.accept(&datagrams[1], 1),
+        Err(FragmentError::Duplicate)
+    );
+}
+
+#[test]
+fn lost_fragments_time_out() {
+    let first = ciphertext();
+    let second = ciphertext();
+    let lost = datagrams(&first, 3, mtu_for(3));
+    let next = datagrams(&second, 4, mtu_for(3));
+
+    let mut buffer = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut reassembler = Reassembler::new(&mut buffer, 10);
+    assert_eq!(reassembler.accept(&lost[0], 100), Ok(None));
+    assert_eq!(reassembler.accept(&lost[2], 105), Ok(None));
+    assert!(!reassembler.expire(110));
+    assert_eq!(reassembler.pending_message_id(), Some(3));
+
+    // The second message replaces the first once it timed out.
+    assert_eq!(reassembler.accept(&next[0], 111), Ok(None));
+    assert_eq!(reassembler.pending_message_id(), Some(4));
+    assert_eq!(reassembler.accept(&next[2], 112), Ok(None));
+    assert_eq!(
+        reassembler.accept(&next[1], 113),
+        Ok(Some(&second[..]))
+    );
+
+    assert_eq!(reassembler.accept(&lost[1], 114), Ok(None));
+    assert!(reassembler.expire(125));
+    assert_eq!(reassembler.pending_message_id(), None);
+}
+
+#[test]
+fn fragments_of_another_message_are_rejected() {
+    let first = ciphertext();
+    let second = ciphertext();
+    let a = datagrams(&first, 5, mtu_for(2));
+    let b = datagrams(&second, 6, mtu_for(2));
+
+    let mut buffer = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut reassembler = Reassembler::new(&mut buffer, 10);
+    assert_eq!(reassembler.accept(&a[0], 0), Ok(None));
+    assert_eq!(
+        reassembler.accept(&b[1], 0),
+        Err(FragmentError::OtherMessage)
+    );
+    assert_eq!(reassembler.accept(&a[1], 0), Ok(Some(&first[..])));
+}
+
+#[test]
+fn corrupted_fragments_are_rejected() {
+    let ct = ciphertext();
+    let mut datagrams = datagrams(&ct, 7, mtu_for(2));
+    let mut buffer = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut reassembler = Reassembler::new(&mut buffer, 10);
+
+    datagrams[0][100] ^= 1;
+    assert_eq!(
+        reassembler.accept(&datagrams[0], 0),
+        Err(FragmentError::Checksum)
+    );
+    assert_eq!(
+        reassembler.accept(&datagrams[1][..FRAGMENT_HEADER_BYTES], 0),
+        Err(FragmentError::Malformed)
+    );
+    assert_eq!(reassembler.pending_message_id(), None);
+
+    let mut small = [0u8; 100];
+    let mut reassembler = Reassembler::new(&mut small, 10);
+    assert_eq!(
+        reassembler.accept(&datagrams[1], 0),
+        Err(FragmentError::BufferTooSmall)
+    );
+}
+
+#[test]
+fn mtu_edge_cases() {
+    let ct = ciphertext();
+    // The smallest payload that fits in MAX_FRAGMENTS fragments.
+    let payload = KYBER_CIPHERTEXT_BYTES.div_ceil(MAX_FRAGMENTS);
+    assert_eq!(
+        fragment(&ct, 0, FRAGMENT_HEADER_BYTES).unwrap_err(),
+        FragmentError::MtuTooSmall
+    );
+    assert_eq!(
+        fragment(&ct, 0, 0).unwrap_err(),
+        FragmentError::MtuTooSmall
+    );
+    // One payload byte fewer needs too many fragments.
+    assert_eq!(
+        fragment(&ct, 0, FRAGMENT_HEADER_BYTES + payload - 1)
+            .unwrap_err(),
+        FragmentError::TooLarge
+    );
+    assert_eq!(
+        fragment(&[], 0, 1200).unwrap_err(),
+        FragmentError::TooLarge
+    );
+    assert_eq!(
+        fragment(&ct, 0, FRAGMENT_HEADER_BYTES + payload)
+            .unwrap()
+            .len(),
+        fragment_count(FRAGMENT_HEADER_BYTES + payload)
+    );
+    assert_eq!(
+        fragment(
+            &ct,
+            0,
+            KYBER_CIPHERTEXT_BYTES + FRAGMENT_HEADER_BYTES
+        )
+        .unwrap()
+        .len(),
+        1
+    );
+
+    let mut tiny = [0u8; 4];
+    let first = fragment(&ct, 0, 1200).unwrap().next().unwrap();
+    assert_eq!(
+        first.encode(&mut tiny),
+        Err(FragmentError::BufferTooSmall)
+    );
+
+    // Every accepted MTU round-trips and respects the limit.
+    let smallest = FRAGMENT_HEADER_BYTES + payload;
+    for mtu in smallest..KYBER_CIPHERTEXT_BYTES + 20 {
+        let mut buffer = [0u8; KYBER_CIPHERTEXT_BYTES];
+        let mut reassembler = Reassembler::new(&mut buffer, 10);
+        let mut message = None;
+        for datagram in datagrams(&ct, mtu as u32, mtu) {
+            assert!(datagram.len() <= mtu);
+            if let Some(m) = reassembler.accept(&datagram, 0).unwrap() {
+                message = Some(m.to_vec());
+            }
+        }
+        assert_eq!(message.as_deref(), Some(&ct[..]), "mtu {}", mtu);
+    }
+}
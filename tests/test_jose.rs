@@ -0,0 +1,254 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for JWE key encapsulation.
+
+#![cfg(feature = "jose")]
+
+#[cfg(feature = "kyber1024")]
+use base64ct::{Base64UrlUnpadded, Encoding};
+use kyberlib::jose::*;
+use kyberlib::*;
+
+// Deterministic RNG so the fixture can be reproduced.
+#[cfg(feature = "kyber1024")]
+struct CountingRng(u8);
+
+#[cfg(feature = "kyber1024")]
+impl RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kyber1024")]
+impl CryptoRng for CountingRng {}
+
+#[cfg(feature = "kyber1024")]
+const FIXTURE_SEED: [u8; 64] = [0x22; 64];
+#[cfg(feature = "kyber1024")]
+const FIXTURE_KID: &str = "kyberlib jose fixture";
+
+#[test]
+fn round_trip_all_encodings() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let jwk = Jwk::from_json(
+        &Jwk::from_public_key(&keys.public, Some("k1")).to_json(),
+    )
+    .unwrap();
+    assert_eq!(jwk.public_key().unwrap(), keys.public);
+    assert_eq!(jwk.kid(), Some("k1"));
+
+    for alg in [DIRECT_ALG, KEY_WRAP_ALG] {
+        for (enc, len) in
+            [("A128GCM", 16), ("A256GCM", 32), ("A256CBC-HS512", 64)]
+        {
+            let sent =
+                JweKeyEncapsulation::new(&jwk, alg, enc, &mut rng)
+                    .unwrap();
+            assert_eq!(sent.cek.len(), len);
+            assert_eq!(sent.header.alg(), alg.name());
+            assert_eq!(sent.header.enc(), enc);
+            assert_eq!(sent.header.kid(), Some("k1"));
+            assert_eq!(
+                sent.encrypted_key.is_empty(),
+                alg.key_wrap_bytes().is_none()
+            );
+
+            let header =
+                JweHeader::from_json(&sent.header.to_json()).unwrap();
+            assert_eq!(header, sent.header);
+            let cek = header
+                .unwrap_cek(&sent.encrypted_key, &keys.secret)
+                .unwrap();
+            assert_eq!(cek, sent.cek);
+        }
+    }
+}
+
+#[test]
+fn algorithm_names_round_trip() {
+    for alg in [
+        JweAlgorithm::MlKem512,
+        JweAlgorithm::MlKem768,
+        JweAlgorithm::MlKem1024,
+        JweAlgorithm::MlKem512A128Kw,
+        JweAlgorithm::MlKem768A192Kw,
+        JweAlgorithm::MlKem1024A256Kw,
+    ] {
+        assert_eq!(JweAlgorithm::from_name(alg.name()), Some(alg));
+    }
+    assert_eq!(JweAlgorithm::from_name("ECDH-ES"), None);
+    assert_eq!(
+        DIRECT_ALG.security_parameter(),
+        KYBER_SECURITY_PARAMETER
+    );
+    assert_eq!(
+        KEY_WRAP_ALG.security_parameter(),
+        KYBER_SECURITY_PARAMETER
+    );
+}
+
+#[test]
+fn level_mismatch_is_rejected() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let jwk = Jwk::from_public_key(&keys.public, None);
+    let other = if KYBER_SECURITY_PARAMETER == 2 {
+        JweAlgorithm::MlKem768A192Kw
+    } else {
+        JweAlgorithm::MlKem512A128Kw
+    };
+    assert_eq!(
+        JweKeyEncapsulation::new(&jwk, other, "A256GCM", &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+
+    // A header naming another level is rejected before decapsulation.
+    let sent = JweKeyEncapsulation::new(
+        &jwk,
+        KEY_WRAP_ALG,
+        "A256GCM",
+        &mut rng,
+    )
+    .unwrap();
+    let json = sent
+        .header
+        .to_json()
+        .replace(KEY_WRAP_ALG.name(), other.name());
+    let header = JweHeader::from_json(&json).unwrap();
+    assert_eq!(
+        header
+            .unwrap_cek(&sent.encrypted_key, &keys.secret)
+            .unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+
+    // So is a key whose alg names another level.
+    let json = jwk.to_json().replace(JWK_KEY_ALG, "ML-KEM-0");
+    let jwk = Jwk::from_json(&json).unwrap();
+    assert_eq!(
+        jwk.public_key().unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+}
+
+#[test]
+fn malformed_inputs_are_rejected() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let jwk = Jwk::from_public_key(&keys.public, None);
+    assert_eq!(
+        JweKeyEncapsulation::new(&jwk, DIRECT_ALG, "A512GCM", &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    assert_eq!(
+        Jwk::from_json(r#"{"kty":"AKP"}"#).unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+
+    let sent =
+        JweKeyEncapsulation::new(&jwk, DIRECT_ALG, "A256GCM", &mut rng)
+            .unwrap();
+    // Direct key agreement requires an empty JWE Encrypted Key.
+    assert_eq!(
+        sent.header
+            .unwrap_cek(&[0u8; 40], &keys.secret)
+            .unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    let json = sent.header.to_json().replace(sent.header.ek(), "AAAA");
+    assert_eq!(
+        JweHeader::from_json(&json)
+            .unwrap()
+            .unwrap_cek(&[], &keys.secret)
+            .unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+}
+
+#[test]
+fn wrong_recipient_fails_to_unwrap() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let other = keypair(&mut rng).unwrap();
+    let jwk = Jwk::from_public_key(&keys.public, None);
+    let sent = JweKeyEncapsulation::new(
+        &jwk,
+        KEY_WRAP_ALG,
+        "A256GCM",
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(
+        sent.header
+            .unwrap_cek(&sent.encrypted_key, &other.secret)
+            .unwrap_err(),
+        KyberLibError::Decapsulation
+    );
+}
+
+// Object encapsulated for a key derived from FIXTURE_SEED. It must keep
+// parsing and unwrapping in future releases; do not regenerate it.
+#[cfg(feature = "kyber1024")]
+#[test]
+fn fixture_parses_and_unwraps() {
+    let fixture: serde_json::Value = serde_json::from_str(
+        include_str!("fixtures/jwe_kyber1024.json"),
+    )
+    .unwrap();
+    let keys = derive(&FIXTURE_SEED).unwrap();
+    let decode = |member: &str| {
+        Base64UrlUnpadded::decode_vec(fixture[member].as_str().unwrap())
+            .unwrap()
+    };
+
+    let jwk = Jwk::from_json(&fixture["jwk"].to_string()).unwrap();
+    assert_eq!(jwk.public_key().unwrap(), keys.public);
+    assert_eq!(jwk.kid(), Some(FIXTURE_KID));
+
+    let header =
+        JweHeader::from_json(&fixture["header"].to_string()).unwrap();
+    assert_eq!(header.alg(), "MLKEM1024+A256KW");
+    assert_eq!(header.enc(), "A256GCM");
+    assert_eq!(header.kid(), Some(FIXTURE_KID));
+    let encrypted_key = decode("encrypted_key");
+    let cek = header.unwrap_cek(&encrypted_key, &keys.secret).unwrap();
+    assert_eq!(&cek[..], &decode("cek")[..]);
+
+    // The fixture was produced from a counting RNG.
+    let rebuilt = JweKeyEncapsulation::new(
+        &jwk,
+        KEY_WRAP_ALG,
+        "A256GCM",
+        &mut CountingRng(0),
+    )
+    .unwrap();
+    assert_eq!(rebuilt.header, header);
+    assert_eq!(rebuilt.encrypted_key, encrypted_key);
+    assert_eq!(rebuilt.cek, cek);
+}
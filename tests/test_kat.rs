@@ -1,27 +1,64 @@
+//! Known Answer Tests.
 #![cfg(KYBER_SECURITY_PARAMETERat)]
 
 mod load;
 
+#[cfg(feature = "force-backend")]
+use kyberlib::acceleration::{available, force_backend, reset_backend};
 use kyberlib::*;
 use load::*;
+#[cfg(feature = "force-backend")]
+use std::sync::Mutex;
+
+// Runs `f` once on each backend this machine has, ending with the
+// reference code. The backend is process-wide, so the tests take turns.
+#[cfg(feature = "force-backend")]
+fn on_each_backend(mut f: impl FnMut(Acceleration)) {
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    for backend in available() {
+        force_backend(backend).unwrap();
+        f(backend);
+    }
+    reset_backend();
+}
+
+// Without the `force-backend` feature, runs `f` on the live backend only.
+#[cfg(not(feature = "force-backend"))]
+fn on_each_backend(mut f: impl FnMut(Acceleration)) {
+    f(acceleration());
+}
 
 // Generate KAT keypairs from seeds.
 #[test]
 fn keypairs() {
     let kats = build_kats();
     let mut _rng = rand::thread_rng(); // placeholder
-    for kat in kats {
-        let known_pk = decode_hex(&kat.pk);
-        let known_sk = decode_hex(&kat.sk);
-        let buf1 = decode_hex(&kat.keygen_buffer1);
-        let buf2 = decode_hex(&kat.keygen_buffer2);
-        let bufs = Some((&buf1[..], &buf2[..]));
-        let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
-        let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
-        generate_key_pair(&mut pk, &mut sk, &mut _rng, bufs).unwrap();
-        assert_eq!(&pk[..], &known_pk[..], "Public key KAT mismatch");
-        assert_eq!(&sk[..], &known_sk[..], "Secret key KAT mismatch");
-    }
+    on_each_backend(|backend| {
+        for kat in &kats {
+            let known_pk = decode_hex(&kat.pk);
+            let known_sk = decode_hex(&kat.sk);
+            let buf1 = decode_hex(&kat.keygen_buffer1);
+            let buf2 = decode_hex(&kat.keygen_buffer2);
+            let bufs = Some((&buf1[..], &buf2[..]));
+            let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+            let mut sk = [0u8; KYBER_SECRET_KEY_BYTES];
+            generate_key_pair(&mut pk, &mut sk, &mut _rng, bufs)
+                .unwrap();
+            assert_eq!(
+                &pk[..],
+                &known_pk[..],
+                "Public key KAT mismatch on {}",
+                backend
+            );
+            assert_eq!(
+                &sk[..],
+                &known_sk[..],
+                "Secret key KAT mismatch on {}",
+                backend
+            );
+        }
+    });
 }
 
 // Encapsulating KAT's using deterministic rand buffers
@@ -29,39 +66,47 @@ fn keypairs() {
 fn encaps() {
     let kats = build_kats();
     let mut _rng = rand::thread_rng(); // placeholder
-    for kat in kats {
-        let known_ss = decode_hex(&kat.ss);
-        let pk = decode_hex(&kat.pk);
-        let buf1 = decode_hex(&kat.encap_buffer);
-        let encap_buf = Some(&buf1[..]);
-        let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
-        let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
-        encrypt_message(&mut ct, &mut ss, &pk, &mut _rng, encap_buf)
+    on_each_backend(|backend| {
+        for kat in &kats {
+            let known_ss = decode_hex(&kat.ss);
+            let pk = decode_hex(&kat.pk);
+            let buf1 = decode_hex(&kat.encap_buffer);
+            let encap_buf = Some(&buf1[..]);
+            let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+            let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+            encrypt_message(
+                &mut ct, &mut ss, &pk, &mut _rng, encap_buf,
+            )
             .unwrap();
-        assert_eq!(
-            &ss[..],
-            &known_ss[..],
-            "Shared secret KAT mismatch"
-        );
-    }
+            assert_eq!(
+                &ss[..],
+                &known_ss[..],
+                "Shared secret KAT mismatch on {}",
+                backend
+            );
+        }
+    });
 }
 
 // Decapsulating KAT's
 #[test]
 fn decaps() {
     let kats = build_kats();
-    for kat in kats {
-        let sk = decode_hex(&kat.sk);
-        let ct = decode_hex(&kat.ct);
-        let known_ss = decode_hex(&kat.ss);
-        let decap_result = decapsulate(&ct, &sk);
-        assert!(decap_result.is_ok(), "KEM decapsulation failure");
-        assert_eq!(
-            &decap_result.unwrap()[..],
-            &known_ss[..],
-            "Shared secret KAT doesn't match"
-        )
-    }
+    on_each_backend(|backend| {
+        for kat in &kats {
+            let sk = decode_hex(&kat.sk);
+            let ct = decode_hex(&kat.ct);
+            let known_ss = decode_hex(&kat.ss);
+            let decap_result = decapsulate(&ct, &sk);
+            assert!(decap_result.is_ok(), "KEM decapsulation failure");
+            assert_eq!(
+                &decap_result.unwrap()[..],
+                &known_ss[..],
+                "Shared secret KAT doesn't match on {}",
+                backend
+            )
+        }
+    });
 }
 
 // Helper functions
@@ -1,3 +1,6 @@
+//! Key encapsulation round-trip tests.
+
+use kyberlib::kem::{encrypt_message, generate_key_pair};
 use kyberlib::*;
 mod utils;
 use kyberlib::keypairfrom;
@@ -12,13 +15,55 @@ fn keypair_encap_decap() {
     assert_eq!(ss1, ss2);
 }
 
+// A stuck RNG that returns zeros and reports success.
+struct ZeroRng;
+
+impl RngCore for ZeroRng {
+    fn next_u32(&mut self) -> u32 {
+        0
+    }
+    fn next_u64(&mut self) -> u64 {
+        0
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.fill(0);
+    }
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        dest.fill(0);
+        Ok(())
+    }
+}
+
+impl CryptoRng for ZeroRng {}
+
+#[test]
+fn keypair_rejects_all_zero_rng() {
+    assert_eq!(keypair(&mut ZeroRng), Err(KyberLibError::WeakKey));
+    let mut pk = [0u8; KYBER_PUBLIC_KEY_BYTES];
+    let mut sk = [1u8; KYBER_SECRET_KEY_BYTES];
+    assert_eq!(
+        generate_key_pair(&mut pk, &mut sk, &mut ZeroRng, None),
+        Err(KyberLibError::WeakKey)
+    );
+
+    // A caller-chosen seed is not second-guessed, and a working RNG
+    // passes.
+    assert!(derive(&[0u8; 64]).is_ok());
+    assert!(keypair(&mut rand::thread_rng()).is_ok());
+}
+
 #[test]
 fn keypair_import_fake() {
     let mut rng = rand::thread_rng();
     let mut keys = keypair(&mut rng).unwrap();
+    // keypairfrom zeroes its inputs with the zeroize feature.
+    let public = keys.public;
     let key = keypairfrom(&mut keys.public, &mut keys.secret, &mut rng)
         .unwrap();
-    assert_eq!(keys.public, key.public);
+    assert_eq!(public, key.public);
 }
 
 #[test]
@@ -86,3 +131,68 @@ fn public_from_private() {
     let pk2 = public(&keys.secret);
     assert_eq!(pk2, keys.public);
 }
+
+// A broken RNG that returns the same non-zero bytes on every call.
+struct ConstantRng;
+
+impl RngCore for ConstantRng {
+    fn next_u32(&mut self) -> u32 {
+        0x4242_4242
+    }
+    fn next_u64(&mut self) -> u64 {
+        0x4242_4242_4242_4242
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.fill(0x42);
+    }
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        dest.fill(0x42);
+        Ok(())
+    }
+}
+
+impl CryptoRng for ConstantRng {}
+
+#[test]
+fn hedged_encapsulation_survives_a_constant_rng() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+
+    // Unhedged, the constant RNG repeats the ciphertext and secret.
+    let first = encapsulate(&keys.public, &mut ConstantRng).unwrap();
+    assert_eq!(
+        encapsulate(&keys.public, &mut ConstantRng).unwrap(),
+        first
+    );
+
+    let (ct1, ss1) =
+        encapsulate_hedged(&keys.public, &mut ConstantRng).unwrap();
+    let (ct2, ss2) =
+        encapsulate_hedged(&keys.public, &mut ConstantRng).unwrap();
+    assert_ne!(ct1, ct2);
+    assert_ne!(ss1, ss2);
+    assert_ne!(ct1, first.0);
+    assert_eq!(decapsulate(&ct1, &keys.secret).unwrap(), ss1);
+    assert_eq!(decapsulate(&ct2, &keys.secret).unwrap(), ss2);
+
+    // The seeded path is not hedged, so it repeats as the KATs need.
+    let mut ct = [0u8; KYBER_CIPHERTEXT_BYTES];
+    let mut ss = [0u8; KYBER_SHARED_SECRET_BYTES];
+    let seed = [0x42u8; KYBER_SYM_BYTES];
+    encrypt_message(
+        &mut ct,
+        &mut ss,
+        &keys.public,
+        &mut ConstantRng,
+        Some(&seed),
+    )
+    .unwrap();
+    assert_eq!((ct, ss), first);
+
+    assert_eq!(
+        encapsulate_hedged(&keys.public[1..], &mut ConstantRng),
+        Err(KyberLibError::InvalidInput)
+    );
+}
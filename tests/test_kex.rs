@@ -1,3 +1,5 @@
+//! Key exchange tests.
+
 use kyberlib::*;
 mod utils;
 use utils::*;
@@ -94,6 +96,77 @@ fn ake_valid() {
     assert_eq!(alice.shared_secret, bob.shared_secret);
 }
 
+// Messages cross the wire as byte slices between the steps
+#[test]
+fn ake_valid_over_the_wire() {
+    let mut rng = rand::thread_rng();
+    let mut alice = Ake::new();
+    let mut bob = Ake::new();
+    let alice_keys = keypair(&mut rng).unwrap();
+    let bob_keys = keypair(&mut rng).unwrap();
+    let wire: Vec<u8> = alice
+        .client_init(&bob_keys.public, &mut rng)
+        .unwrap()
+        .to_vec();
+    let server_send = bob
+        .server_receive(
+            ake_init_from_bytes(&wire).unwrap(),
+            &alice_keys.public,
+            &bob_keys.secret,
+            &mut rng,
+        )
+        .unwrap();
+    let wire = server_send.to_vec();
+    alice
+        .client_confirm(
+            ake_response_from_bytes(&wire).unwrap(),
+            &alice_keys.secret,
+        )
+        .unwrap();
+    assert_eq!(alice.shared_secret, bob.shared_secret);
+}
+
+#[test]
+fn ake_messages_round_trip_and_check_length() {
+    let mut rng = rand::thread_rng();
+    let bob_keys = keypair(&mut rng).unwrap();
+    let alice_keys = keypair(&mut rng).unwrap();
+    let init =
+        Ake::new().client_init(&bob_keys.public, &mut rng).unwrap();
+    assert_eq!(ake_init_from_bytes(&init[..]), Ok(init));
+    let response = Ake::new()
+        .server_receive(
+            init,
+            &alice_keys.public,
+            &bob_keys.secret,
+            &mut rng,
+        )
+        .unwrap();
+    assert_eq!(ake_response_from_bytes(&response[..]), Ok(response));
+
+    let mut long = init.to_vec();
+    long.push(0);
+    for bytes in [&init[1..], &long[..], &[]] {
+        assert_eq!(
+            ake_init_from_bytes(bytes),
+            Err(KyberLibError::InvalidLength)
+        );
+    }
+    assert_eq!(
+        ake_response_from_bytes(&response[1..]),
+        Err(KyberLibError::InvalidLength)
+    );
+
+    // A coefficient of q in the ephemeral public key is not canonical.
+    let mut bad = init;
+    bad[0] = 0x01;
+    bad[1] = 0x0d;
+    assert_eq!(
+        ake_init_from_bytes(&bad),
+        Err(KyberLibError::InvalidKey)
+    );
+}
+
 #[test]
 fn ake_invalid_client_init_ciphertext() {
     let mut rng = rand::thread_rng();
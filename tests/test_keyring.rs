@@ -0,0 +1,119 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the decapsulation key ring.
+
+#![cfg(feature = "keyring")]
+
+use kyberlib::keyring::*;
+use kyberlib::*;
+
+#[test]
+fn rotation_serves_old_and_new_clients() {
+    let mut rng = rand::thread_rng();
+    let old = keypair(&mut rng).unwrap();
+    let new = keypair(&mut rng).unwrap();
+    let mut ring = KeyRing::new(2);
+    let old_id = ring.insert(&old.secret).unwrap();
+    assert_eq!(ring.primary_id(), Some(old_id));
+    assert_eq!(ring.primary_public_key(), Some(old.public));
+
+    let legacy_client = encapsulate(&old.public, &mut rng).unwrap();
+    let old_client = encapsulate_framed(&old.public, &mut rng).unwrap();
+
+    let new_id = ring.insert(&new.secret).unwrap();
+    ring.set_primary(&new_id).unwrap();
+    assert_eq!(ring.primary_public_key(), Some(new.public));
+    let new_client = encapsulate_framed(
+        &ring.primary_public_key().unwrap(),
+        &mut rng,
+    )
+    .unwrap();
+
+    assert_eq!(ring.decapsulate(&old_client.0).unwrap(), old_client.1);
+    assert_eq!(ring.decapsulate(&new_client.0).unwrap(), new_client.1);
+    // Unframed ciphertexts go to the primary, which is now the new key.
+    assert_ne!(
+        ring.decapsulate(&legacy_client.0).unwrap(),
+        legacy_client.1
+    );
+}
+
+#[test]
+fn unknown_key_id_is_an_error() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let other = keypair(&mut rng).unwrap();
+    let mut ring = KeyRing::new(4);
+    assert_eq!(
+        ring.decapsulate(&[0u8; KYBER_CIPHERTEXT_BYTES]),
+        Err(KyberLibError::InvalidKey)
+    );
+    let _ = ring.insert(&keys.secret).unwrap();
+
+    let (mut framed, _) =
+        encapsulate_framed(&other.public, &mut rng).unwrap();
+    assert_eq!(
+        ring.decapsulate(&framed),
+        Err(KyberLibError::InvalidKey)
+    );
+    assert_eq!(
+        ring.set_primary(&key_id(&other.public)),
+        Err(KyberLibError::InvalidKey)
+    );
+
+    framed[0] = FRAME_VERSION + 1;
+    assert_eq!(
+        ring.decapsulate(&framed),
+        Err(KyberLibError::InvalidInput)
+    );
+    assert_eq!(
+        ring.decapsulate(&framed[1..]),
+        Err(KyberLibError::InvalidLength)
+    );
+}
+
+#[test]
+fn removal_rejects_old_ciphertexts() {
+    let mut rng = rand::thread_rng();
+    let old = keypair(&mut rng).unwrap();
+    let new = keypair(&mut rng).unwrap();
+    let mut ring = KeyRing::new(2);
+    let old_id = ring.insert(&old.secret).unwrap();
+    let new_id = ring.insert(&new.secret).unwrap();
+    let (framed, ss) =
+        encapsulate_framed(&old.public, &mut rng).unwrap();
+    assert_eq!(ring.decapsulate(&framed).unwrap(), ss);
+
+    // The primary cannot be removed before it is replaced.
+    assert_eq!(ring.remove(&old_id), Err(KyberLibError::InvalidInput));
+    ring.set_primary(&new_id).unwrap();
+    ring.remove(&old_id).unwrap();
+    assert_eq!(ring.len(), 1);
+    for _ in 0..2 {
+        assert_eq!(
+            ring.decapsulate(&framed),
+            Err(KyberLibError::InvalidKey)
+        );
+    }
+    assert_eq!(ring.remove(&old_id), Err(KyberLibError::InvalidKey));
+}
+
+#[test]
+fn ring_size_is_bounded() {
+    let mut rng = rand::thread_rng();
+    let mut ring = KeyRing::new(2);
+    assert!(ring.is_empty());
+    let first = keypair(&mut rng).unwrap();
+    let _ = ring.insert(&first.secret).unwrap();
+    assert_eq!(
+        ring.insert(&first.secret),
+        Err(KyberLibError::InvalidInput)
+    );
+    let _ = ring.insert(&keypair(&mut rng).unwrap().secret).unwrap();
+    assert_eq!(
+        ring.insert(&keypair(&mut rng).unwrap().secret),
+        Err(KyberLibError::InvalidInput)
+    );
+    assert_eq!(ring.len(), 2);
+}
@@ -0,0 +1,139 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the parameter-set generic API.
+
+#![cfg(feature = "levels")]
+
+use kyberlib::levels::{
+    self, ByteArray, Kyber1024, Kyber512, Kyber768, Params,
+};
+use kyberlib::KyberLibError;
+
+fn roundtrip<P: Params>() {
+    let mut rng = rand::thread_rng();
+    let keys = levels::keypair::<P, _>(&mut rng).unwrap();
+    let (ct, ss1) =
+        levels::encapsulate::<P, _>(keys.public.as_ref(), &mut rng)
+            .unwrap();
+    let ss2 =
+        levels::decapsulate::<P>(ct.as_ref(), keys.secret.as_ref())
+            .unwrap();
+    assert_eq!(ss1, ss2, "{}", P::NAME);
+}
+
+fn tampered_ciphertext_is_implicitly_rejected<P: Params>() {
+    let mut rng = rand::thread_rng();
+    let keys = levels::keypair::<P, _>(&mut rng).unwrap();
+    let (mut ct, ss) =
+        levels::encapsulate::<P, _>(keys.public.as_ref(), &mut rng)
+            .unwrap();
+    ct.as_mut()[0] ^= 1;
+    let rejected =
+        levels::decapsulate::<P>(ct.as_ref(), keys.secret.as_ref())
+            .unwrap();
+    assert_ne!(ss, rejected, "{}", P::NAME);
+    let again =
+        levels::decapsulate::<P>(ct.as_ref(), keys.secret.as_ref())
+            .unwrap();
+    assert_eq!(rejected, again, "{}", P::NAME);
+}
+
+fn sizes_match_constants<P: Params>(pk: usize, sk: usize, ct: usize) {
+    assert_eq!(P::PUBLIC_KEY_BYTES, pk, "{}", P::NAME);
+    assert_eq!(P::SECRET_KEY_BYTES, sk, "{}", P::NAME);
+    assert_eq!(P::CIPHERTEXT_BYTES, ct, "{}", P::NAME);
+    assert_eq!(
+        P::PublicKey::zeroed().as_ref().len(),
+        pk,
+        "{}",
+        P::NAME
+    );
+    assert_eq!(
+        P::SecretKey::zeroed().as_ref().len(),
+        sk,
+        "{}",
+        P::NAME
+    );
+    assert_eq!(
+        P::Ciphertext::zeroed().as_ref().len(),
+        ct,
+        "{}",
+        P::NAME
+    );
+}
+
+#[test]
+fn roundtrip_at_every_level() {
+    roundtrip::<Kyber512>();
+    roundtrip::<Kyber768>();
+    roundtrip::<Kyber1024>();
+}
+
+#[test]
+fn tampered_ciphertext_is_implicitly_rejected_at_every_level() {
+    tampered_ciphertext_is_implicitly_rejected::<Kyber512>();
+    tampered_ciphertext_is_implicitly_rejected::<Kyber768>();
+    tampered_ciphertext_is_implicitly_rejected::<Kyber1024>();
+}
+
+#[test]
+fn sizes_match_the_specification() {
+    sizes_match_constants::<Kyber512>(800, 1632, 768);
+    sizes_match_constants::<Kyber768>(1184, 2400, 1088);
+    sizes_match_constants::<Kyber1024>(1568, 3168, 1568);
+}
+
+#[test]
+fn keys_of_another_level_are_rejected() {
+    let mut rng = rand::thread_rng();
+    let keys = levels::keypair::<Kyber512, _>(&mut rng).unwrap();
+    let (ct, _) =
+        levels::encapsulate::<Kyber512, _>(&keys.public, &mut rng)
+            .unwrap();
+    assert_eq!(
+        levels::encapsulate::<Kyber768, _>(&keys.public, &mut rng),
+        Err(KyberLibError::InvalidInput)
+    );
+    assert_eq!(
+        levels::decapsulate::<Kyber768>(&ct, &keys.secret),
+        Err(KyberLibError::InvalidInput)
+    );
+    assert_eq!(
+        levels::derive::<Kyber768>(&[0u8; 32]).map(|_| ()),
+        Err(KyberLibError::InvalidInput)
+    );
+}
+
+#[cfg(feature = "kyber1024")]
+#[test]
+fn kyber1024_matches_the_fixed_api() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let seed = [7u8; 64];
+    let generic = levels::derive::<Kyber1024>(&seed).unwrap();
+    let fixed = kyberlib::derive(&seed).unwrap();
+    assert_eq!(generic.public, fixed.public);
+    assert_eq!(generic.secret, fixed.secret);
+
+    let (ct, ss) = levels::encapsulate::<Kyber1024, _>(
+        &generic.public,
+        &mut StdRng::from_seed([1u8; 32]),
+    )
+    .unwrap();
+    let (fixed_ct, fixed_ss) = kyberlib::encapsulate(
+        &fixed.public,
+        &mut StdRng::from_seed([1u8; 32]),
+    )
+    .unwrap();
+    assert_eq!(ct, fixed_ct);
+    assert_eq!(ss, fixed_ss);
+
+    let mut tampered = ct;
+    tampered[100] ^= 0x40;
+    assert_eq!(
+        levels::decapsulate::<Kyber1024>(&tampered, &generic.secret),
+        kyberlib::decapsulate(&tampered, &fixed.secret)
+    );
+}
@@ -1,10 +1,13 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the crate-level feature flags and re-exports.
+
 #[cfg(test)]
 mod tests {
 
     /// Tests for feature flags
+    #[allow(clippy::assertions_on_constants)]
     mod feature_flags {
         #[test]
         #[cfg(feature = "kyber512")]
@@ -1,6 +1,8 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the exported macros.
+
 #[cfg(test)]
 mod tests {
     use kyberlib::{
@@ -290,4 +292,24 @@ mod tests {
 
         assert_eq!(alice.shared_secret, bob.shared_secret);
     }
+
+    // The key is validated during const evaluation and lands in a static
+    #[cfg(feature = "kyber1024")]
+    static EMBEDDED_KEY: kyberlib::PublicKey =
+        kyberlib::include_pubkey!("fixtures/public_key_kyber1024.pk");
+
+    #[cfg(feature = "kyber1024")]
+    #[test]
+    fn test_include_pubkey() {
+        let keys = kyberlib::derive(&[0x5a; 64]).unwrap();
+        assert_eq!(EMBEDDED_KEY, keys.public);
+    }
+
+    // A corrupted key file fails the build, see tests/ui
+    #[cfg(all(feature = "kyber1024", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_include_pubkey_rejects_corrupted_keys() {
+        trybuild::TestCases::new()
+            .compile_fail("tests/ui/include_pubkey_corrupted.rs");
+    }
 }
@@ -0,0 +1,142 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Loopback tests for the TCP handshake.
+
+#![cfg(feature = "net")]
+
+use kyberlib::net::*;
+use kyberlib::*;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn other_level() -> u8 {
+    if KYBER_SECURITY_PARAMETER == 2 {
+        3
+    } else {
+        2
+    }
+}
+
+fn request_header(version: u8, level: u8, length: u16) -> [u8; 4] {
+    let length = length.to_be_bytes();
+    [version, level, length[0], length[1]]
+}
+
+#[test]
+fn handshake_agrees_on_a_secret() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let public = keys.public;
+    let client = thread::spawn(move || {
+        let (secret, mut stream) =
+            connect_handshake(addr, &public).unwrap();
+        stream.write_all(b"ping").unwrap();
+        secret
+    });
+
+    let (secret, mut stream) =
+        serve_handshake(&listener, &keys).next().unwrap().unwrap();
+    let mut ping = [0u8; 4];
+    stream.read_exact(&mut ping).unwrap();
+    assert_eq!(&ping, b"ping");
+    assert_eq!(client.join().unwrap(), secret);
+    assert_eq!(stream.read_timeout().unwrap(), None);
+}
+
+#[test]
+fn server_rejects_another_level() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let length = KYBER_CIPHERTEXT_BYTES as u16;
+        stream
+            .write_all(&request_header(
+                PROTOCOL_VERSION,
+                other_level(),
+                length,
+            ))
+            .unwrap();
+        let mut response = [0u8; 2];
+        stream.read_exact(&mut response).unwrap();
+        response
+    });
+
+    let err = serve_handshake(&listener, &keys)
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(client.join().unwrap(), [PROTOCOL_VERSION, 2]);
+}
+
+#[test]
+fn client_reports_a_server_with_another_level() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4 + KYBER_CIPHERTEXT_BYTES];
+        stream.read_exact(&mut request).unwrap();
+        stream.write_all(&[PROTOCOL_VERSION, 2]).unwrap();
+        request[1]
+    });
+
+    let err = connect_handshake(addr, &keys.public).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("security level"));
+    assert_eq!(server.join().unwrap(), KYBER_SECURITY_PARAMETER as u8);
+}
+
+#[test]
+fn oversized_length_is_rejected_before_reading() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        // Announce 64 KiB but send nothing after the header.
+        let header = request_header(
+            PROTOCOL_VERSION,
+            KYBER_SECURITY_PARAMETER as u8,
+            u16::MAX,
+        );
+        stream.write_all(&header).unwrap();
+        let mut response = [0u8; 2];
+        stream.read_exact(&mut response).unwrap();
+        response
+    });
+
+    let err = serve_handshake(&listener, &keys)
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(client.join().unwrap(), [PROTOCOL_VERSION, 3]);
+}
+
+#[test]
+fn silent_clients_time_out() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _client = TcpStream::connect(addr).unwrap();
+
+    let start = Instant::now();
+    let err = serve_handshake(&listener, &keys)
+        .timeout(Duration::from_millis(100))
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::WouldBlock | ErrorKind::TimedOut
+    ));
+    assert!(start.elapsed() < HANDSHAKE_TIMEOUT);
+}
@@ -0,0 +1,129 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! In-memory tests for the tokio handshake helpers.
+
+#![cfg(feature = "tokio")]
+
+use kyberlib::net::PROTOCOL_VERSION;
+use kyberlib::net_tokio::*;
+use kyberlib::*;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::time::Duration;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn handshake_agrees_on_a_secret() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    block_on(async {
+        let (mut client, mut server) = duplex(64);
+        let (client_secret, server_secret) = tokio::join!(
+            initiate_handshake(&keys.public, &mut client),
+            respond_handshake(&keys, &mut server),
+        );
+        assert_eq!(client_secret.unwrap(), server_secret.unwrap());
+
+        client.write_all(b"ping").await.unwrap();
+        let mut ping = [0u8; 4];
+        let _ = server.read_exact(&mut ping).await.unwrap();
+        assert_eq!(&ping, b"ping");
+    });
+}
+
+#[test]
+fn cancelled_handshake_leaves_the_keys_usable() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    block_on(async {
+        // Half a header, then the server future is dropped mid-read.
+        let (mut client, mut server) = duplex(64);
+        client.write_all(&[PROTOCOL_VERSION, 4]).await.unwrap();
+        tokio::select! {
+            _ = respond_handshake(&keys, &mut server) => {
+                panic!("handshake completed on half a header")
+            }
+            _ = tokio::task::yield_now() => {}
+        }
+        drop((client, server));
+
+        let (mut client, mut server) = duplex(64);
+        let (client_secret, server_secret) = tokio::join!(
+            initiate_handshake(&keys.public, &mut client),
+            respond_handshake(&keys, &mut server),
+        );
+        assert_eq!(client_secret.unwrap(), server_secret.unwrap());
+    });
+}
+
+#[test]
+fn stalled_peer_times_out() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    block_on(async {
+        let (mut client, mut server) = duplex(64);
+        client.write_all(&[PROTOCOL_VERSION]).await.unwrap();
+        let err = respond_handshake_with_timeout(
+            &keys,
+            &mut server,
+            Duration::from_millis(20),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    });
+}
+
+#[test]
+fn server_rejects_oversized_frames() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    block_on(async {
+        let (mut client, mut server) = duplex(64);
+        let level = KYBER_SECURITY_PARAMETER as u8;
+        client
+            .write_all(&[PROTOCOL_VERSION, level, 0xff, 0xff])
+            .await
+            .unwrap();
+        let err =
+            respond_handshake(&keys, &mut server).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut response = [0u8; 2];
+        let _ = client.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, [PROTOCOL_VERSION, 3]);
+    });
+}
+
+#[test]
+fn client_reports_a_rejection() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    block_on(async {
+        let (mut client, mut server) = duplex(8192);
+        let server = async {
+            let mut header = [0u8; 4];
+            let _ = server.read_exact(&mut header).await.unwrap();
+            server.write_all(&[PROTOCOL_VERSION, 2]).await.unwrap();
+        };
+        let (result, ()) = tokio::join!(
+            initiate_handshake(&keys.public, &mut client),
+            server
+        );
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    });
+}
+
+#[test]
+fn keypairs_are_generated_off_the_executor() {
+    let keys = block_on(keypairs_blocking(3)).unwrap();
+    assert_eq!(keys.len(), 3);
+    assert_ne!(keys[0].public, keys[1].public);
+    let (ct, ss) =
+        encapsulate(&keys[2].public, &mut rand::thread_rng()).unwrap();
+    assert_eq!(decapsulate(&ct, &keys[2].secret).unwrap(), ss);
+}
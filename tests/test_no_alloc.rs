@@ -0,0 +1,176 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Checks that the core KEM API never touches the heap, with a global
+//! allocator that counts the allocations made on the calling thread.
+
+use core::cell::Cell;
+use core::fmt::Write;
+use kyberlib::*;
+use rand::rngs::OsRng;
+use std::alloc::{GlobalAlloc, Layout, System};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // `try_with` fails only while the thread is being torn down.
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Runs `f` and returns its result with the number of heap allocations it
+// made on this thread. The test harness allocates on other threads, which
+// are not counted.
+fn allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+// Formats into a fixed buffer, as firmware would.
+struct ArrayWriter {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl Write for ArrayWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(core::fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[test]
+fn counter_sees_allocations() {
+    let (v, count) = allocations(|| vec![0u8; 16]);
+    assert_eq!(v.len(), 16);
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn kem_does_not_allocate() {
+    let mut rng = OsRng;
+    let ((), count) = allocations(|| {
+        let keys = keypair(&mut rng).unwrap();
+        let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+        assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+        assert_eq!(
+            decapsulate_with_pk(&ct, &keys.secret, &keys.public)
+                .unwrap(),
+            ss
+        );
+
+        let derived = derive(&[1u8; 64]).unwrap();
+        assert_eq!(public(&derived.secret), derived.public);
+        let _ = encapsulate_struct(&derived.public, &mut rng).unwrap();
+    });
+    assert_eq!(count, 0);
+}
+
+// The steady state of a server: keys are set up once, then every
+// encapsulation and decapsulation, including the implicit rejection of a
+// tampered ciphertext, runs on the stack.
+#[test]
+fn hot_path_does_not_allocate_after_key_setup() {
+    let mut rng = OsRng;
+    let keys = keypair(&mut rng).unwrap();
+    let ((), count) = allocations(|| {
+        for _ in 0..16 {
+            let (mut ct, ss) =
+                encapsulate(&keys.public, &mut rng).unwrap();
+            assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+
+            let (ct2, ss2) =
+                encapsulate_slice(&keys.public[..], &mut rng).unwrap();
+            assert_eq!(
+                decapsulate_slice(&keys.secret[..], &ct2).unwrap(),
+                ss2
+            );
+
+            ct[0] ^= 1;
+            assert_ne!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+        }
+    });
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn key_exchanges_do_not_allocate() {
+    let mut rng = OsRng;
+    let alice_keys = keypair(&mut rng).unwrap();
+    let bob_keys = keypair(&mut rng).unwrap();
+    let ((), count) = allocations(|| {
+        let mut alice = Uake::new();
+        let mut bob = Uake::new();
+        let init =
+            alice.client_init(&bob_keys.public, &mut rng).unwrap();
+        let response = bob
+            .server_receive(init, &bob_keys.secret, &mut rng)
+            .unwrap();
+        alice.client_confirm(response).unwrap();
+        assert_eq!(alice.shared_secret, bob.shared_secret);
+
+        let mut alice = Ake::new();
+        let mut bob = Ake::new();
+        let init =
+            alice.client_init(&bob_keys.public, &mut rng).unwrap();
+        let response = bob
+            .server_receive(
+                init,
+                &alice_keys.public,
+                &bob_keys.secret,
+                &mut rng,
+            )
+            .unwrap();
+        alice.client_confirm(response, &alice_keys.secret).unwrap();
+        assert_eq!(alice.shared_secret, bob.shared_secret);
+    });
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn errors_do_not_allocate() {
+    let mut rng = OsRng;
+    let (message, count) = allocations(|| {
+        let err = encapsulate(&[0u8; 3], &mut rng).unwrap_err();
+        let mut out = ArrayWriter {
+            buf: [0u8; 128],
+            len: 0,
+        };
+        write!(out, "{}", err).unwrap();
+        out
+    });
+    assert_eq!(count, 0);
+    assert_eq!(
+        &message.buf[..message.len],
+        KyberLibError::InvalidInput.to_string().as_bytes()
+    );
+}
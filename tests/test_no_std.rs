@@ -0,0 +1,68 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Builds and links the `ensure-no-std` crate for a bare-metal target,
+//! catching std and allocator dependencies creeping into the core, and
+//! the `embedded-defmt` firmware logging through the `defmt` feature.
+
+use std::path::Path;
+use std::process::Command;
+
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+fn target_installed(rustc: &str) -> bool {
+    let sysroot = Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout).trim().to_owned()
+        });
+    match sysroot {
+        Some(sysroot) => Path::new(&sysroot)
+            .join("lib/rustlib")
+            .join(TARGET)
+            .exists(),
+        None => false,
+    }
+}
+
+// Builds `package` for the bare-metal target, in a target directory of
+// its own so the build does not wait on the lock held by the cargo running
+// this test. Skips if the target is not installed.
+fn build(package: &str, features: &str) {
+    let rustc =
+        std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    if !target_installed(&rustc) {
+        eprintln!(
+            "skipping, install the target with `rustup target add {}`",
+            TARGET
+        );
+        return;
+    }
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new(env!("CARGO"))
+        .current_dir(root)
+        .args(["build", "-p", package, "--target", TARGET])
+        .args(["--features", features])
+        .arg("--target-dir")
+        .arg(root.join("target").join(package))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn core_builds_for_a_target_without_std() {
+    build("ensure-no-std", "link-check");
+}
+
+#[test]
+fn defmt_firmware_builds_for_a_target_without_std() {
+    build("embedded-defmt", "device");
+}
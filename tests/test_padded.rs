@@ -0,0 +1,79 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for padded decapsulation.
+
+#![cfg(feature = "std")]
+
+use kyberlib::padded::*;
+use kyberlib::*;
+use std::time::{Duration, Instant};
+
+// Long enough for an unoptimised decapsulation on a loaded machine.
+const PAD: Duration = Duration::from_millis(40);
+
+fn timed(f: impl FnOnce() -> Decapsulated) -> (Decapsulated, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+#[test]
+fn valid_and_tampered_ciphertexts_take_the_same_time() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+    let mut tampered = ct;
+    tampered[0] ^= 1;
+
+    let mut durations = Vec::new();
+    for _ in 0..8 {
+        for (ct, valid) in [(&ct, true), (&tampered, false)] {
+            let (result, elapsed) =
+                timed(|| decapsulate_padded(ct, &keys.secret, PAD));
+            assert_eq!(result.unwrap() == ss, valid);
+            durations.push(elapsed);
+        }
+    }
+    let (min, max) = (
+        *durations.iter().min().unwrap(),
+        *durations.iter().max().unwrap(),
+    );
+    assert!(min >= PAD);
+    assert!(max - PAD < Duration::from_millis(5), "{:?}", durations);
+}
+
+#[test]
+fn every_strategy_waits_for_the_deadline() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+    for strategy in [
+        PadStrategy::Spin,
+        PadStrategy::Sleep,
+        PadStrategy::SleepThenSpin(Duration::from_millis(5)),
+        PadStrategy::SleepThenSpin(2 * PAD),
+    ] {
+        let (result, elapsed) = timed(|| {
+            decapsulate_padded_with(&ct, &keys.secret, PAD, strategy)
+        });
+        assert_eq!(result.unwrap(), ss);
+        assert!(elapsed >= PAD, "{:?}: {:?}", strategy, elapsed);
+    }
+}
+
+#[test]
+fn overrun_is_reported() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, _) = encapsulate(&keys.public, &mut rng).unwrap();
+    assert_eq!(
+        decapsulate_padded(&ct, &keys.secret, Duration::ZERO),
+        Err(KyberLibError::Overrun)
+    );
+    // Input errors are returned after the same wait.
+    let (result, elapsed) =
+        timed(|| decapsulate_padded(&ct[1..], &keys.secret, PAD));
+    assert_eq!(result, Err(KyberLibError::InvalidInput));
+    assert!(elapsed >= PAD);
+}
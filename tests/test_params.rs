@@ -1,6 +1,8 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the Kyber parameters.
+
 #[cfg(test)]
 mod tests {
     // Import necessary items
@@ -82,6 +84,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_security_category_and_claimed_bits() {
+        // Test the NIST level mapping for the active parameter set
+        if cfg!(feature = "kyber512") {
+            assert_eq!((security_category(), claimed_bits()), (1, 128));
+        } else if cfg!(feature = "kyber1024") {
+            assert_eq!((security_category(), claimed_bits()), (5, 256));
+        } else {
+            assert_eq!((security_category(), claimed_bits()), (3, 192));
+        }
+    }
+
     #[test]
     fn test_kyber_shared_secret_bytes() {
         // Test KYBER_SHARED_SECRET_BYTES
@@ -98,6 +112,7 @@ mod tests {
 
     #[cfg(feature = "kyber1024")]
     #[test]
+    #[allow(non_snake_case)]
     fn test_KYBER_POLY_COMPRESSED_BYTES_kyber1024() {
         // Test KYBER_POLY_COMPRESSED_BYTES for kyber1024
         assert_eq!(KYBER_POLY_COMPRESSED_BYTES, 160);
@@ -105,6 +120,7 @@ mod tests {
 
     #[cfg(feature = "kyber1024")]
     #[test]
+    #[allow(non_snake_case)]
     fn test_KYBER_POLYVEC_COMPRESSED_BYTES_kyber1024() {
         // Test KYBER_POLYVEC_COMPRESSED_BYTES for kyber1024
         assert_eq!(
@@ -0,0 +1,250 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for OpenPGP composite key and PKESK fields.
+
+#![cfg(feature = "pgp")]
+
+use kyberlib::pgp::*;
+use kyberlib::*;
+
+// Deterministic RNG so the fixture can be reproduced.
+#[cfg(feature = "kyber1024")]
+struct CountingRng(u8);
+
+#[cfg(feature = "kyber1024")]
+impl RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kyber1024")]
+impl CryptoRng for CountingRng {}
+
+#[cfg(feature = "kyber1024")]
+const FIXTURE_X25519: [u8; 32] = [0x33; 32];
+#[cfg(feature = "kyber1024")]
+const FIXTURE_KYBER_SEED: [u8; 64] = [0x44; 64];
+
+#[cfg(feature = "kyber1024")]
+fn fixture_session_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    for (i, b) in key.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    key
+}
+
+#[test]
+fn round_trip() {
+    let mut rng = rand::thread_rng();
+    let secret = CompositeSecretKey::generate(&mut rng).unwrap();
+    let public = CompositePublicKey::from_fields(
+        &secret.public_key().to_fields(),
+    )
+    .unwrap();
+    assert_eq!(public, secret.public_key());
+    let reparsed =
+        CompositeSecretKey::from_fields(&secret.to_fields()[..])
+            .unwrap();
+    assert_eq!(reparsed.public_key(), public);
+
+    for len in [16, 24, 32, 240] {
+        let session_key = vec![0x5a; len];
+        let esk =
+            EncryptedSessionKey::new(&public, &session_key, &mut rng)
+                .unwrap();
+        let fields = esk.to_fields();
+        assert_eq!(
+            fields.len(),
+            32 + KYBER_CIPHERTEXT_BYTES + 1 + len + 8
+        );
+        let parsed = EncryptedSessionKey::from_fields(&fields).unwrap();
+        assert_eq!(parsed, esk);
+        assert_eq!(
+            &parsed.decrypt(&reparsed).unwrap()[..],
+            &session_key[..]
+        );
+    }
+}
+
+#[test]
+fn wrong_recipient_fails_to_decrypt() {
+    let mut rng = rand::thread_rng();
+    let secret = CompositeSecretKey::generate(&mut rng).unwrap();
+    let other = CompositeSecretKey::generate(&mut rng).unwrap();
+    let esk = EncryptedSessionKey::new(
+        &secret.public_key(),
+        &[1u8; 32],
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(
+        esk.decrypt(&other).unwrap_err(),
+        KyberLibError::Decapsulation
+    );
+
+    // Changing either half of the composite key breaks decryption.
+    let mixed = CompositeSecretKey::from_fields(
+        &[&other.to_fields()[..32], &secret.to_fields()[32..]].concat(),
+    )
+    .unwrap();
+    assert_eq!(
+        esk.decrypt(&mixed).unwrap_err(),
+        KyberLibError::Decapsulation
+    );
+}
+
+#[test]
+fn invalid_inputs_are_rejected() {
+    let mut rng = rand::thread_rng();
+    let secret = CompositeSecretKey::generate(&mut rng).unwrap();
+    let public = secret.public_key();
+    for len in [0, 8, 20, 248] {
+        assert_eq!(
+            EncryptedSessionKey::new(
+                &public,
+                &vec![0u8; len],
+                &mut rng
+            )
+            .unwrap_err(),
+            KyberLibError::InvalidLength
+        );
+    }
+
+    // The all-zero X25519 point is of low order.
+    let mut fields = public.to_fields();
+    fields[..32].copy_from_slice(&[0u8; 32]);
+    let low_order = CompositePublicKey::from_fields(&fields).unwrap();
+    assert_eq!(
+        EncryptedSessionKey::new(&low_order, &[0u8; 32], &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+
+    assert_eq!(
+        CompositePublicKey::from_fields(&fields[1..]).unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+    assert_eq!(
+        CompositeSecretKey::from_fields(&[0u8; 95]).unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+}
+
+#[test]
+fn strict_pkesk_parsing() {
+    let mut rng = rand::thread_rng();
+    let secret = CompositeSecretKey::generate(&mut rng).unwrap();
+    let fields = EncryptedSessionKey::new(
+        &secret.public_key(),
+        &[0u8; 32],
+        &mut rng,
+    )
+    .unwrap()
+    .to_fields();
+    let reject = |bytes: &[u8]| {
+        assert_eq!(
+            EncryptedSessionKey::from_fields(bytes).unwrap_err(),
+            KyberLibError::InvalidInput
+        );
+    };
+    reject(&fields[..fields.len() - 1]);
+    reject(&[&fields[..], &[0u8]].concat());
+    reject(&fields[..32 + KYBER_CIPHERTEXT_BYTES]);
+    let mut bad = fields[..32 + KYBER_CIPHERTEXT_BYTES + 1].to_vec();
+    bad[32 + KYBER_CIPHERTEXT_BYTES] = 16;
+    bad.extend_from_slice(&[0u8; 16]);
+    reject(&bad);
+}
+
+// Cross-generated with Python's hashlib.sha3_256.
+#[test]
+fn combiner_vector() {
+    let expected = [
+        0xfd, 0x2c, 0xcf, 0xc3, 0x40, 0x13, 0xa8, 0x09, 0xba, 0xda,
+        0xbf, 0x7a, 0xb7, 0xab, 0x5b, 0x05, 0x35, 0x57, 0xb0, 0xa8,
+        0x52, 0x88, 0x60, 0x49, 0xf5, 0x48, 0xd6, 0x43, 0x14, 0xcb,
+        0x29, 0x5d,
+    ];
+    assert_eq!(
+        multi_key_combine(
+            &[1u8; 32],
+            &[2u8; 32],
+            &[3u8; 32],
+            &[4u8; 32],
+            ALGORITHM_ID
+        ),
+        expected
+    );
+}
+
+// PKESK fields encrypted to the key built from FIXTURE_X25519 and
+// FIXTURE_KYBER_SEED with a counting RNG. The X25519 values and the
+// unwrapping of the session key under the combined KEK were checked with
+// the Python `cryptography` package. Do not regenerate it.
+#[cfg(feature = "kyber1024")]
+#[test]
+fn fixture_parses_and_decrypts() {
+    let fields = include_bytes!("fixtures/pgp_pkesk_v6_kyber1024.bin");
+    let secret = CompositeSecretKey::from_seeds(
+        &FIXTURE_X25519,
+        &FIXTURE_KYBER_SEED,
+    )
+    .unwrap();
+    assert_eq!(
+        secret.public_key().x25519(),
+        &[
+            0x7b, 0x0d, 0x47, 0xd9, 0x34, 0x27, 0xf8, 0x31, 0x11, 0x60,
+            0x78, 0x1c, 0x7c, 0x73, 0x3f, 0xd8, 0x9f, 0x88, 0x97, 0x0a,
+            0xef, 0x49, 0x0d, 0x8a, 0xa0, 0xee, 0x19, 0xa4, 0xcb, 0x8a,
+            0x1b, 0x14,
+        ]
+    );
+
+    let esk = EncryptedSessionKey::from_fields(fields).unwrap();
+    assert_eq!(
+        esk.ecdh_ciphertext(),
+        &[
+            0x8f, 0x40, 0xc5, 0xad, 0xb6, 0x8f, 0x25, 0x62, 0x4a, 0xe5,
+            0xb2, 0x14, 0xea, 0x76, 0x7a, 0x6e, 0xc9, 0x4d, 0x82, 0x9d,
+            0x3d, 0x7b, 0x5e, 0x1a, 0xd1, 0xba, 0x6f, 0x3e, 0x21, 0x38,
+            0x28, 0x5f,
+        ]
+    );
+    assert_eq!(
+        &esk.decrypt(&secret).unwrap()[..],
+        &fixture_session_key()
+    );
+    assert_eq!(&esk.to_fields()[..], &fields[..]);
+
+    let rebuilt = EncryptedSessionKey::new(
+        &secret.public_key(),
+        &fixture_session_key(),
+        &mut CountingRng(0),
+    )
+    .unwrap();
+    assert_eq!(rebuilt, esk);
+}
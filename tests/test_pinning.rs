@@ -0,0 +1,199 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for trust-on-first-use key pinning.
+
+#![cfg(feature = "pinning")]
+
+use kyberlib::pinning::*;
+use kyberlib::*;
+use std::{fs, path::PathBuf, thread};
+
+// A fresh store path per test, removed with its lock file on drop.
+struct TempStore(PathBuf);
+
+impl TempStore {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "kyberlib-pins-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        Self(path)
+    }
+
+    fn lock_file(&self) -> PathBuf {
+        let mut name = self.0.clone().into_os_string();
+        name.push(".lock");
+        PathBuf::from(name)
+    }
+}
+
+impl Drop for TempStore {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+        let _ = fs::remove_file(self.lock_file());
+    }
+}
+
+#[test]
+fn first_use_records_the_key() {
+    let tmp = TempStore::new("first-use");
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let mut store = PinStore::open(&tmp.0).unwrap();
+    assert!(store.get("alice").is_none());
+    assert_eq!(
+        store.check("alice", &keys.public).unwrap(),
+        PinResult::FirstUse
+    );
+
+    let reopened = PinStore::open(&tmp.0).unwrap();
+    let pin = reopened.get("alice").unwrap();
+    assert_eq!(pin.fingerprint, Fingerprint::of(&keys.public));
+    assert_eq!(
+        pin.level,
+        format!("kyber{}", KYBER_SECURITY_PARAMETER * 256)
+    );
+    assert!(pin.first_seen > 0);
+    assert!(reopened.warnings().is_empty());
+}
+
+#[test]
+fn matching_key_passes() {
+    let tmp = TempStore::new("match");
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let mut store = PinStore::open(&tmp.0).unwrap();
+    let _ = store.check("alice", &keys.public).unwrap();
+    assert_eq!(
+        store.check("alice", &keys.public).unwrap(),
+        PinResult::Match
+    );
+    let mut reopened = PinStore::open(&tmp.0).unwrap();
+    assert_eq!(
+        reopened.check("alice", &keys.public).unwrap(),
+        PinResult::Match
+    );
+}
+
+#[test]
+fn mismatch_reports_both_fingerprints_until_repinned() {
+    let tmp = TempStore::new("mismatch");
+    let mut rng = rand::thread_rng();
+    let old = keypair(&mut rng).unwrap();
+    let new = keypair(&mut rng).unwrap();
+    let mut store = PinStore::open(&tmp.0).unwrap();
+    let _ = store.check("alice", &old.public).unwrap();
+
+    let expected = PinResult::Mismatch {
+        old: Fingerprint::of(&old.public),
+        new: Fingerprint::of(&new.public),
+    };
+    assert_eq!(store.check("alice", &new.public).unwrap(), expected);
+    // The old pin stays in place until the change is confirmed.
+    assert_eq!(store.check("alice", &new.public).unwrap(), expected);
+
+    store.repin("alice", &new.public).unwrap();
+    assert_eq!(
+        store.check("alice", &new.public).unwrap(),
+        PinResult::Match
+    );
+    let mut reopened = PinStore::open(&tmp.0).unwrap();
+    assert_eq!(
+        reopened.check("alice", &new.public).unwrap(),
+        PinResult::Match
+    );
+}
+
+#[test]
+fn corrupted_lines_are_skipped_with_a_warning() {
+    let tmp = TempStore::new("corrupted");
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let fingerprint = Fingerprint::of(&keys.public);
+    fs::write(
+        &tmp.0,
+        format!(
+            "# comment\n\
+             bob\tkyber1024\tnot-hex\t1\n\
+             alice\tkyber1024\t{}\t1700000000\n\
+             truncated line\n\
+             carol\tkyber1024\t{}\tyesterday\n",
+            fingerprint, fingerprint
+        ),
+    )
+    .unwrap();
+
+    let mut store = PinStore::open(&tmp.0).unwrap();
+    assert_eq!(store.warnings().len(), 3);
+    assert!(store.warnings()[0].ends_with(":2: skipping malformed pin"));
+    assert_eq!(store.get("alice").unwrap().first_seen, 1_700_000_000);
+    assert!(store.get("bob").is_none());
+    assert_eq!(
+        store.check("alice", &keys.public).unwrap(),
+        PinResult::Match
+    );
+
+    // Rewriting the store keeps the malformed lines for a human to fix.
+    assert_eq!(
+        store.check("dave", &keys.public).unwrap(),
+        PinResult::FirstUse
+    );
+    let contents = fs::read_to_string(&tmp.0).unwrap();
+    assert!(contents.contains("\nbob\tkyber1024\tnot-hex\t1\n"));
+    assert!(contents.contains("\ntruncated line\n"));
+    assert!(contents.contains("\tyesterday\n"));
+    let reopened = PinStore::open(&tmp.0).unwrap();
+    assert_eq!(reopened.warnings().len(), 3);
+    assert!(reopened.get("dave").is_some());
+}
+
+#[test]
+fn invalid_identities_are_rejected() {
+    let tmp = TempStore::new("identity");
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let mut store = PinStore::open(&tmp.0).unwrap();
+    for identity in ["", "a\tb", "a\nb"] {
+        let err = store.check(identity, &keys.public).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn lock_file_left_behind_does_not_block() {
+    let tmp = TempStore::new("stale-lock");
+    // What a process killed while holding the lock leaves behind.
+    fs::write(tmp.lock_file(), b"").unwrap();
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let mut store = PinStore::open(&tmp.0).unwrap();
+    assert_eq!(
+        store.check("alice", &keys.public).unwrap(),
+        PinResult::FirstUse
+    );
+}
+
+#[test]
+fn concurrent_writers_keep_every_pin() {
+    let tmp = TempStore::new("concurrent");
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let path = tmp.0.clone();
+            thread::spawn(move || {
+                let keys = keypair(&mut rand::thread_rng()).unwrap();
+                let mut store = PinStore::open(&path).unwrap();
+                let identity = format!("peer-{}", i);
+                assert_eq!(
+                    store.check(&identity, &keys.public).unwrap(),
+                    PinResult::FirstUse
+                );
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let store = PinStore::open(&tmp.0).unwrap();
+    for i in 0..8 {
+        assert!(store.get(&format!("peer-{}", i)).is_some());
+    }
+    assert!(store.warnings().is_empty());
+}
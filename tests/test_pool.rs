@@ -0,0 +1,123 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the keypair pool.
+
+#![cfg(feature = "pool")]
+
+use kyberlib::pool::*;
+use kyberlib::*;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn assert_valid(keys: &Keypair) {
+    let (ct, ss) =
+        encapsulate(&keys.public, &mut rand::thread_rng()).unwrap();
+    assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+}
+
+#[test]
+fn warm_take_generates_nothing() {
+    const N: usize = 16;
+    let rngs = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&rngs);
+    let pool = KeypairPool::manual(N, move || {
+        let _ = counter.fetch_add(1, Ordering::Relaxed);
+        rand::thread_rng()
+    });
+    assert_eq!(pool.refill().unwrap(), N);
+    assert_eq!(pool.refill().unwrap(), 0);
+    let refills = rngs.load(Ordering::Relaxed);
+
+    // Every key generation draws a fresh RNG from the factory, so a
+    // warm take must not touch it.
+    for _ in 0..N {
+        let _ = pool.take().unwrap();
+    }
+    assert_eq!(rngs.load(Ordering::Relaxed), refills);
+    let metrics = pool.metrics();
+    assert_eq!(
+        (metrics.hits, metrics.misses, metrics.depth),
+        (16, 0, 0)
+    );
+
+    let _ = pool.take().unwrap();
+    assert_eq!(rngs.load(Ordering::Relaxed), refills + 1);
+    assert_eq!(pool.metrics().misses, 1);
+}
+
+#[test]
+fn empty_pool_generates_inline() {
+    let pool = KeypairPool::manual(2, rand::thread_rng);
+    assert_eq!(pool.refill().unwrap(), 2);
+    let keys: Vec<Keypair> =
+        (0..3).map(|_| pool.take().unwrap()).collect();
+    for keys in &keys {
+        assert_valid(keys);
+    }
+    assert_ne!(keys[0].public, keys[2].public);
+    assert_eq!(
+        pool.metrics(),
+        PoolMetrics {
+            hits: 2,
+            misses: 1,
+            depth: 0,
+            capacity: 2,
+        }
+    );
+
+    let empty = KeypairPool::manual(0, rand::thread_rng);
+    assert_eq!(empty.refill().unwrap(), 0);
+    assert_valid(&empty.take().unwrap());
+    assert_eq!(empty.metrics().misses, 1);
+}
+
+#[test]
+fn background_thread_fills_the_pool() {
+    let pool = KeypairPool::new(8, rand::thread_rng).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while pool.metrics().depth < 8 {
+        assert!(Instant::now() < deadline, "pool never filled");
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_valid(&pool.take().unwrap());
+    // The taken slot is refilled.
+    while pool.metrics().depth < 8 {
+        assert!(Instant::now() < deadline, "pool never refilled");
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(pool.metrics().hits, 1);
+}
+
+#[test]
+fn concurrent_takes_never_share_a_keypair() {
+    const THREADS: usize = 8;
+    const TAKES: usize = 32;
+    let pool =
+        Arc::new(KeypairPool::new(16, rand::thread_rng).unwrap());
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || {
+                (0..TAKES)
+                    .map(|_| pool.take().unwrap().public)
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let mut seen = HashSet::new();
+    for handle in handles {
+        for public in handle.join().unwrap() {
+            assert!(
+                seen.insert(public.to_vec()),
+                "keypair taken twice"
+            );
+        }
+    }
+    let metrics = pool.metrics();
+    assert_eq!(seen.len(), THREADS * TAKES);
+    assert_eq!(metrics.hits + metrics.misses, (THREADS * TAKES) as u64);
+}
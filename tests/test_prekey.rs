@@ -0,0 +1,179 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for signed prekey bundles.
+
+#![cfg(feature = "prekey")]
+
+use kyberlib::prekey::*;
+use kyberlib::*;
+
+// Deterministic RNG so the fixture can be reproduced.
+#[cfg(feature = "kyber1024")]
+struct CountingRng(u8);
+
+#[cfg(feature = "kyber1024")]
+impl RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kyber1024")]
+impl CryptoRng for CountingRng {}
+
+#[cfg(feature = "kyber1024")]
+const FIXTURE_IDENTITY: [u8; 32] = [0x55; 32];
+
+fn identity() -> SigningKey {
+    let mut seed = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    SigningKey::from_bytes(&seed)
+}
+
+#[test]
+fn bundles_verify_and_round_trip() {
+    let mut rng = rand::thread_rng();
+    let identity = identity();
+    let prekeys = generate_prekeys(&identity, 5, &mut rng).unwrap();
+    assert_eq!(prekeys.len(), 5);
+    let mut ids: Vec<u32> =
+        prekeys.iter().map(|p| p.bundle().id()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), 5);
+
+    for prekey in &prekeys {
+        let bundle =
+            PrekeyBundle::from_bytes(&prekey.bundle().to_bytes())
+                .unwrap();
+        assert_eq!(&bundle, prekey.bundle());
+        assert_eq!(bundle.kind(), PrekeyKind::OneTime);
+        verify_bundle(&identity.verifying_key(), &bundle).unwrap();
+
+        let (ct, ss) =
+            encapsulate(bundle.public_key(), &mut rng).unwrap();
+        assert_eq!(prekey.decapsulate(&ct).unwrap(), ss);
+    }
+}
+
+#[test]
+fn tampering_is_detected() {
+    let mut rng = rand::thread_rng();
+    let identity = identity();
+    let prekey =
+        generate_last_resort_prekey(&identity, &mut rng).unwrap();
+    let bytes = prekey.bundle().to_bytes();
+
+    // Flipping a bit of the key or the signature breaks verification.
+    for index in
+        [7, 7 + KYBER_PUBLIC_KEY_BYTES - 1, PREKEY_BUNDLE_BYTES - 1]
+    {
+        let mut tampered = bytes;
+        tampered[index] ^= 1;
+        let bundle = PrekeyBundle::from_bytes(&tampered).unwrap();
+        assert_eq!(
+            verify_bundle(&identity.verifying_key(), &bundle)
+                .unwrap_err(),
+            KyberLibError::InvalidSignature
+        );
+    }
+
+    // So does verifying under another identity.
+    assert_eq!(
+        verify_bundle(
+            &self::identity().verifying_key(),
+            prekey.bundle()
+        )
+        .unwrap_err(),
+        KyberLibError::InvalidSignature
+    );
+
+    // Unknown versions, kinds and key types do not parse.
+    for (index, value) in [(0, 2), (1, 0), (1, 3), (6, KEY_TYPE ^ 1)] {
+        let mut bad = bytes;
+        bad[index] = value;
+        assert_eq!(
+            PrekeyBundle::from_bytes(&bad).unwrap_err(),
+            KyberLibError::InvalidInput
+        );
+    }
+    assert_eq!(
+        PrekeyBundle::from_bytes(&bytes[1..]).unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+}
+
+#[test]
+fn store_applies_prekey_semantics() {
+    let mut rng = rand::thread_rng();
+    let identity = identity();
+    let mut store = PrekeyStore::new();
+    let one_time = generate_prekeys(&identity, 1, &mut rng).unwrap();
+    let one_time_id = one_time[0].bundle().id();
+    let one_time_pk = *one_time[0].bundle().public_key();
+    let last_resort =
+        generate_last_resort_prekey(&identity, &mut rng).unwrap();
+    let last_resort_id = last_resort.bundle().id();
+    let last_resort_pk = *last_resort.bundle().public_key();
+    store.insert(one_time.into_iter().next().unwrap());
+    store.insert(last_resort);
+    assert_eq!(store.bundles().len(), 2);
+
+    // A one-time prekey serves exactly one session.
+    let (ct, ss) = encapsulate(&one_time_pk, &mut rng).unwrap();
+    assert_eq!(store.decapsulate(one_time_id, &ct).unwrap(), ss);
+    assert_eq!(
+        store.decapsulate(one_time_id, &ct).unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+    assert_eq!(store.bundles().len(), 1);
+
+    // A last-resort prekey keeps serving.
+    for _ in 0..3 {
+        let (ct, ss) = encapsulate(&last_resort_pk, &mut rng).unwrap();
+        assert_eq!(store.decapsulate(last_resort_id, &ct).unwrap(), ss);
+    }
+    assert_eq!(store.bundles()[0].kind(), PrekeyKind::LastResort);
+}
+
+// Last-resort bundle generated from FIXTURE_IDENTITY with a counting RNG.
+// It must keep parsing and verifying in future releases; do not
+// regenerate it.
+#[cfg(feature = "kyber1024")]
+#[test]
+fn fixture_parses_and_verifies() {
+    let bytes =
+        include_bytes!("fixtures/prekey_bundle_v1_kyber1024.bin");
+    let identity = SigningKey::from_bytes(&FIXTURE_IDENTITY);
+    let bundle = PrekeyBundle::from_bytes(bytes).unwrap();
+    assert_eq!(bundle.kind(), PrekeyKind::LastResort);
+    assert_eq!(bundle.id(), u32::from_le_bytes([0, 1, 2, 3]));
+    verify_bundle(&identity.verifying_key(), &bundle).unwrap();
+
+    let rebuilt =
+        generate_last_resort_prekey(&identity, &mut CountingRng(0))
+            .unwrap();
+    assert_eq!(&rebuilt.bundle().to_bytes()[..], &bytes[..]);
+}
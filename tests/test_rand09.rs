@@ -0,0 +1,105 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for rand_core 0.9 RNGs through `Rand09`.
+
+#![cfg(feature = "rand09")]
+
+use kyberlib::rng::Rand09;
+use kyberlib::*;
+
+// rand_chacha 0.3 implements the rand_core 0.6 traits and 0.9 the 0.9
+// ones, with the same stream for the same seed.
+use rand_chacha::rand_core::SeedableRng as _;
+use rand_core_09::SeedableRng as _;
+
+#[test]
+fn rngs_of_both_versions_drive_the_api() {
+    let mut rng06 = rand_chacha::ChaCha20Rng::from_seed([1; 32]);
+    let mut rng09 =
+        Rand09(rand_chacha_09::ChaCha20Rng::from_seed([1; 32]));
+
+    let keys06 = keypair(&mut rng06).unwrap();
+    let keys09 = keypair(&mut rng09).unwrap();
+    assert_eq!(keys06, keys09);
+
+    let (ct, ss) = encapsulate(&keys06.public, &mut rng09).unwrap();
+    assert_eq!(decapsulate(&ct, &keys09.secret).unwrap(), ss);
+    let (ct, ss) = encapsulate(&keys09.public, &mut rng06).unwrap();
+    assert_eq!(decapsulate(&ct, &keys06.secret).unwrap(), ss);
+}
+
+// A fallible rand_core 0.9 source that always fails.
+struct Broken;
+
+#[derive(Debug)]
+struct Unavailable;
+
+impl core::fmt::Display for Unavailable {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.write_str("unavailable")
+    }
+}
+
+impl rand_core_09::TryRngCore for Broken {
+    type Error = Unavailable;
+
+    fn try_next_u32(&mut self) -> Result<u32, Unavailable> {
+        Err(Unavailable)
+    }
+
+    fn try_next_u64(&mut self) -> Result<u64, Unavailable> {
+        Err(Unavailable)
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        _: &mut [u8],
+    ) -> Result<(), Unavailable> {
+        Err(Unavailable)
+    }
+}
+
+impl rand_core_09::TryCryptoRng for Broken {}
+
+// A rand_core 0.6 RNG failing the same way.
+struct Broken06;
+
+impl RngCore for Broken06 {
+    fn next_u32(&mut self) -> u32 {
+        0
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        0
+    }
+
+    fn fill_bytes(&mut self, _: &mut [u8]) {}
+
+    fn try_fill_bytes(
+        &mut self,
+        _: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        Err(rand_core::Error::from(
+            core::num::NonZeroU32::new(rand_core::Error::CUSTOM_START)
+                .unwrap(),
+        ))
+    }
+}
+
+impl CryptoRng for Broken06 {}
+
+#[test]
+fn failures_of_both_versions_map_to_the_same_error() {
+    let failed = Some(KyberLibError::RandomBytesGeneration);
+    assert_eq!(keypair(&mut Rand09(Broken)).err(), failed);
+    assert_eq!(keypair(&mut Broken06).err(), failed);
+
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let pk = &keys.public;
+    assert_eq!(encapsulate(pk, &mut Rand09(Broken)).err(), failed);
+    assert_eq!(encapsulate(pk, &mut Broken06).err(), failed);
+}
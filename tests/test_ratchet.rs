@@ -0,0 +1,81 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the hash ratchet.
+
+#![cfg(feature = "ratchet")]
+
+use kyberlib::ratchet::*;
+use kyberlib::*;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn both_parties_derive_the_same_chain() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, alice_secret) =
+        encapsulate(&keys.public, &mut rng).unwrap();
+    let bob_secret = decapsulate(&ct, &keys.secret).unwrap();
+
+    let mut alice = Ratchet::new(&alice_secret, b"label");
+    let mut bob = Ratchet::new(&bob_secret, b"label");
+    let mut other = Ratchet::new(&bob_secret, b"other label");
+    for epoch in 0..16 {
+        assert_eq!(alice.epoch(), epoch);
+        let key = alice.next_key();
+        assert_eq!(bob.next_key(), key);
+        assert_ne!(other.next_key(), key);
+    }
+}
+
+#[test]
+fn advanced_state_does_not_rederive_old_keys() {
+    let secret: SharedSecret = [7; KYBER_SHARED_SECRET_BYTES];
+    let mut ratchet = Ratchet::new(&secret, b"label");
+    let old = ratchet.next_key();
+    let later: Vec<_> = (0..64).map(|_| ratchet.next_key()).collect();
+    assert!(later.iter().all(|key| *key != old));
+    assert_eq!(ratchet.skip_to(0), Err(KyberLibError::InvalidInput));
+    assert_eq!(ratchet.epoch(), 65);
+}
+
+#[test]
+fn skip_window_is_enforced() {
+    let secret: SharedSecret = [7; KYBER_SHARED_SECRET_BYTES];
+    let mut reference = Ratchet::new(&secret, b"label");
+    let mut ratchet = Ratchet::new(&secret, b"label");
+
+    assert_eq!(
+        ratchet.skip_to(MAX_SKIP + 1),
+        Err(KyberLibError::InvalidInput)
+    );
+    assert_eq!(ratchet.epoch(), 0);
+
+    reference.skip_to(MAX_SKIP).unwrap();
+    let bytes = reference.epoch_to_bytes();
+    ratchet.skip_to(epoch_from_bytes(&bytes).unwrap()).unwrap();
+    assert_eq!(ratchet.next_key(), reference.next_key());
+    assert_eq!(
+        epoch_from_bytes(&bytes[1..]),
+        Err(KyberLibError::InvalidLength)
+    );
+}
+
+// Pins the KDF chain; do not regenerate these values
+#[test]
+fn ratchet_vectors() {
+    let secret: SharedSecret = [0x42; KYBER_SHARED_SECRET_BYTES];
+    let mut ratchet = Ratchet::new(&secret, b"label");
+    assert_eq!(
+        hex(&ratchet.next_key()),
+        "dd392b192810abe86bd9c22f36337511530207afc9b1e8ea820987403b5bb245"
+    );
+    assert_eq!(
+        hex(&ratchet.next_key()),
+        "eb61752eddc3117bf20f1c17da75f532c8c0fc39196fadef6433a2581e133d02"
+    );
+    assert_eq!(ratchet.epoch_to_bytes(), 2u64.to_le_bytes());
+}
@@ -0,0 +1,102 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the ciphertext replay filter.
+
+#![cfg(feature = "replay")]
+
+use kyberlib::replay::*;
+use kyberlib::*;
+
+fn item(i: u32) -> [u8; 4] {
+    i.to_le_bytes()
+}
+
+#[test]
+fn exact_replay_is_detected() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (first, _) = encapsulate(&keys.public, &mut rng).unwrap();
+    let (second, _) = encapsulate(&keys.public, &mut rng).unwrap();
+
+    let mut storage = [0u64; 4 * 64];
+    let mut filter =
+        ReplayFilter::<4>::new(&mut storage, 60, 256, &mut rng)
+            .unwrap();
+    assert_eq!(filter.check_and_insert(&first, 0), Ok(()));
+    assert_eq!(filter.check_and_insert(&second, 1), Ok(()));
+    assert_eq!(filter.check_and_insert(&first, 2), Err(Replay));
+    assert_eq!(filter.check_and_insert(&second, 3), Err(Replay));
+}
+
+#[test]
+fn entries_expire_after_the_window() {
+    let mut rng = rand::thread_rng();
+    let mut storage = [0u64; 4 * 64];
+    // Generations of 60 / 3 = 20 time units
+    let mut filter =
+        ReplayFilter::<4>::new(&mut storage, 60, 256, &mut rng)
+            .unwrap();
+    assert_eq!(filter.check_and_insert(&item(1), 10), Ok(()));
+    for now in 10..=70 {
+        assert!(filter.contains(&item(1), now));
+    }
+    assert_eq!(filter.check_and_insert(&item(1), 70), Err(Replay));
+    assert_eq!(filter.check_and_insert(&item(1), 10 + 60 + 20), Ok(()));
+}
+
+#[test]
+fn full_generations_evict_the_oldest() {
+    let mut rng = rand::thread_rng();
+    let mut storage = [0u64; 2 * 64];
+    let mut filter =
+        ReplayFilter::<2>::new(&mut storage, 100, 4, &mut rng).unwrap();
+    for i in 0..8 {
+        assert_eq!(filter.check_and_insert(&item(i), 0), Ok(()));
+    }
+    // The ninth ciphertext closes the second generation and reuses the first.
+    assert_eq!(filter.check_and_insert(&item(8), 0), Ok(()));
+    assert!(!filter.contains(&item(0), 0));
+    assert!(filter.contains(&item(5), 0));
+    assert_eq!(filter.check_and_insert(&item(5), 0), Err(Replay));
+}
+
+#[test]
+fn false_positive_rate_is_bounded() {
+    let mut rng = rand::thread_rng();
+    // 8192 bits per generation at 16 bits per ciphertext gives k = 11 and
+    // a rate of (1 - e^(-11/16))^11, about 4.6e-4, for a full generation.
+    let mut storage = [0u64; 4 * 128];
+    let mut filter =
+        ReplayFilter::<4>::new(&mut storage, 60, 512, &mut rng)
+            .unwrap();
+    for i in 0..512 {
+        assert_eq!(filter.check_and_insert(&item(i), 0), Ok(()));
+    }
+    let probes = 20_000;
+    let false_positives = (1_000_000..1_000_000 + probes)
+        .filter(|&i| filter.contains(&item(i), 0))
+        .count();
+    assert!(false_positives < probes as usize / 500);
+}
+
+#[test]
+fn invalid_parameters_are_rejected() {
+    let mut rng = rand::thread_rng();
+    let mut storage = [0u64; 4];
+    assert_eq!(
+        ReplayFilter::<1>::new(&mut storage, 60, 4, &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    assert_eq!(
+        ReplayFilter::<4>::new(&mut storage, 0, 4, &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    assert_eq!(
+        ReplayFilter::<8>::new(&mut storage, 60, 4, &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+}
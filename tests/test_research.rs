@@ -0,0 +1,242 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the research primitives.
+
+#![cfg(feature = "research")]
+
+use kyberlib::params::{KYBER_N, KYBER_Q};
+use kyberlib::research::*;
+use rand::Rng;
+
+// Canonical representative in 0..q.
+fn modq(x: i16) -> i16 {
+    (x as i32).rem_euclid(KYBER_Q as i32) as i16
+}
+
+#[test]
+fn montgomery_round_trip_is_identity_mod_q() {
+    let mut rng = rand::thread_rng();
+    let mut coeffs = [0i16; KYBER_N];
+    for c in coeffs.iter_mut() {
+        *c = rng.gen_range(-(KYBER_Q as i16) + 1..KYBER_Q as i16);
+    }
+    let mut poly = Polynomial::from_coeffs(coeffs);
+    poly.to_montgomery();
+    poly.from_montgomery();
+    for (actual, expected) in poly.coeffs().iter().zip(coeffs.iter()) {
+        assert_eq!(modq(*actual), modq(*expected));
+        assert!(actual.unsigned_abs() < KYBER_Q as u16);
+    }
+}
+
+#[test]
+fn to_montgomery_multiplies_by_r() {
+    // R = 2^16 mod q = 2285
+    let mut poly = Polynomial::from_coeffs([1; KYBER_N]);
+    poly.to_montgomery();
+    assert!(poly.coeffs().iter().all(|&c| modq(c) == 2285));
+    poly.from_montgomery();
+    assert!(poly.coeffs().iter().all(|&c| modq(c) == 1));
+}
+
+#[test]
+fn inv_ntt_undoes_ntt_up_to_montgomery_factor() {
+    let mut rng = rand::thread_rng();
+    let mut coeffs = [0i16; KYBER_N];
+    for c in coeffs.iter_mut() {
+        *c = rng.gen_range(0..KYBER_Q as i16);
+    }
+    let mut poly = Polynomial::from_coeffs(coeffs);
+    poly.ntt();
+    poly.inv_ntt();
+    poly.from_montgomery();
+    for (actual, expected) in poly.coeffs().iter().zip(coeffs.iter()) {
+        assert_eq!(modq(*actual), *expected);
+    }
+}
+
+#[test]
+fn serialization_ignores_the_representative() {
+    let mut rng = rand::thread_rng();
+    let mut coeffs = [0i16; KYBER_N];
+    for c in coeffs.iter_mut() {
+        *c = rng.gen_range(1..KYBER_Q as i16);
+    }
+    let positive = Polynomial::from_coeffs(coeffs);
+    let negative =
+        Polynomial::from_coeffs(coeffs.map(|c| c - KYBER_Q as i16));
+    assert_eq!(positive.to_bytes(), negative.to_bytes());
+    assert_eq!(positive.compress(), negative.compress());
+}
+
+#[test]
+fn as_ref_exposes_the_coefficients() {
+    let mut rng = rand::thread_rng();
+    let mut coeffs = [0i16; KYBER_N];
+    for c in coeffs.iter_mut() {
+        *c = rng.gen_range(-(KYBER_Q as i16) + 1..KYBER_Q as i16);
+    }
+    let poly = Polynomial::from_coeffs(coeffs);
+    let slice: &[i16] = poly.as_ref();
+    assert_eq!(slice.len(), KYBER_N);
+    assert_eq!(slice.as_ptr(), poly.coeffs().as_ptr());
+
+    let mut expected = 0i32;
+    for &c in coeffs.iter() {
+        expected += c as i32;
+    }
+    assert_eq!(slice.iter().map(|&c| c as i32).sum::<i32>(), expected);
+}
+
+#[test]
+fn to_poly_string_uses_centered_coefficients() {
+    assert_eq!(Polynomial::new().to_poly_string(), "0");
+
+    let mut coeffs = [0i16; KYBER_N];
+    coeffs[0] = -3;
+    coeffs[1] = 1;
+    coeffs[2] = KYBER_Q as i16; // zero mod q, omitted
+    coeffs[3] = 1664;
+    coeffs[4] = 1665; // -1664 mod q
+    coeffs[255] = -(KYBER_Q as i16) + 12;
+    assert_eq!(
+        Polynomial::from_coeffs(coeffs).to_poly_string(),
+        "-3 + 1x + 1664x^3 - 1664x^4 + 12x^255"
+    );
+}
+
+#[test]
+fn polyvec_csubq_centers_into_zero_q() {
+    use kyberlib::params::KYBER_SECURITY_PARAMETER;
+    let q = KYBER_Q as i16;
+    let inputs = [q, q + 1, 2 * q - 1, -1, -q + 1, 0, q - 1];
+    let mut coeffs = [0i16; KYBER_N];
+    for (i, c) in coeffs.iter_mut().enumerate() {
+        *c = inputs[i % inputs.len()];
+    }
+    let mut v =
+        [Polynomial::from_coeffs(coeffs); KYBER_SECURITY_PARAMETER];
+    polyvec_csubq(&mut v);
+    for p in v.iter() {
+        for (actual, input) in p.coeffs().iter().zip(coeffs.iter()) {
+            assert!((0..q).contains(actual));
+            assert_eq!(*actual, modq(*input));
+        }
+    }
+}
+
+#[test]
+fn forced_failure_returns_the_implicit_rejection_key() {
+    use kyberlib::symmetric::{hash_h, kdf};
+    use kyberlib::*;
+
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+    let rejected =
+        decapsulate_force_failure(&ct, &keys.secret).unwrap();
+    assert_ne!(rejected, ss);
+    assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+
+    // KDF(z || H(ct)), with z the last KYBER_SYM_BYTES of the secret key.
+    let mut input = [0u8; 2 * KYBER_SYM_BYTES];
+    input[..KYBER_SYM_BYTES].copy_from_slice(
+        &keys.secret[KYBER_SECRET_KEY_BYTES - KYBER_SYM_BYTES..],
+    );
+    hash_h(&mut input[KYBER_SYM_BYTES..], &ct, KYBER_CIPHERTEXT_BYTES);
+    let mut expected = [0u8; KYBER_SHARED_SECRET_BYTES];
+    kdf(&mut expected, &input, 2 * KYBER_SYM_BYTES);
+    assert_eq!(rejected, expected);
+
+    assert_eq!(
+        decapsulate_force_failure(&ct[1..], &keys.secret),
+        Err(KyberLibError::InvalidInput)
+    );
+}
+
+#[test]
+fn decapsulate_debug_recovers_the_encapsulated_message() {
+    use kyberlib::*;
+
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, ss, m) =
+        encapsulate_export_m(&keys.public, &mut rng).unwrap();
+    assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+    assert_eq!(decapsulate_debug(&ct, &keys.secret).unwrap(), (ss, m));
+
+    // A tampered ciphertext still decodes, and the shared secret is the
+    // implicit rejection key.
+    let mut tampered = ct;
+    tampered[0] ^= 1;
+    let (rejected, _) =
+        decapsulate_debug(&tampered, &keys.secret).unwrap();
+    assert_eq!(rejected, decapsulate(&tampered, &keys.secret).unwrap());
+    assert_ne!(rejected, ss);
+
+    assert_eq!(
+        decapsulate_debug(&ct[1..], &keys.secret),
+        Err(KyberLibError::InvalidInput)
+    );
+    assert_eq!(
+        encapsulate_export_m(&keys.public[1..], &mut rng),
+        Err(KyberLibError::InvalidInput)
+    );
+}
+
+// The noise e of a key, recovered as t - A*s from the public and secret
+// key with the matrix sampled from `seed`, in canonical representatives.
+fn key_noise(
+    keys: &kyberlib::Keypair,
+    seed: &[u8],
+    transposed: bool,
+) -> Vec<i16> {
+    use kyberlib::params::{
+        KYBER_POLY_BYTES, KYBER_SECURITY_PARAMETER,
+    };
+    use std::convert::TryInto;
+
+    let poly = |bytes: &[u8], i: usize| {
+        let chunk = &bytes[i * KYBER_POLY_BYTES..][..KYBER_POLY_BYTES];
+        Polynomial::from_bytes(chunk.try_into().unwrap())
+    };
+    let a = gen_matrix(seed, transposed).unwrap();
+    let s: Vec<_> = (0..KYBER_SECURITY_PARAMETER)
+        .map(|i| poly(&keys.secret, i))
+        .collect();
+    let mut noise = Vec::new();
+    for (i, row) in a.iter().enumerate() {
+        let mut product = basemul_acc(row, &s).unwrap();
+        product.to_montgomery();
+        let t = poly(&keys.public, i);
+        let mut diff = [0i16; KYBER_N];
+        for (d, (t, p)) in
+            diff.iter_mut().zip(t.coeffs().iter().zip(product.coeffs()))
+        {
+            *d = modq(t - p);
+        }
+        let mut e = Polynomial::from_coeffs(diff);
+        e.inv_ntt();
+        e.from_montgomery();
+        noise.extend(e.coeffs().iter().map(|&c| modq(c)));
+    }
+    noise
+}
+
+#[test]
+fn matrix_seed_reproduces_the_key_generation_matrix() {
+    use kyberlib::params::KYBER_ETA1;
+    use kyberlib::{keypair, MatrixSeed};
+
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let seed = keys.public.matrix_seed();
+
+    // t = A*s + e holds with e drawn from the centered binomial
+    // distribution, so every coefficient is within eta1 of zero.
+    let eta = KYBER_ETA1 as i16;
+    let q = KYBER_Q as i16;
+    let small = |c: &i16| *c <= eta || *c >= q - eta;
+    assert!(key_noise(&keys, &seed, false).iter().all(small));
+    assert!(!key_noise(&keys, &seed, true).iter().all(small));
+}
@@ -1,11 +1,92 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the random number generation helpers.
+
 #[cfg(test)]
 mod tests {
 
-    use kyberlib::{rng::randombytes, KyberLibError};
-    use rand_core::OsRng;
+    use kyberlib::{
+        decapsulate, encapsulate, keypair,
+        rng::{
+            random_bytes_attempts, randombytes,
+            set_random_bytes_attempts, RetryRng,
+            DEFAULT_RANDOM_BYTES_ATTEMPTS,
+        },
+        KyberLibError,
+    };
+    use rand_core::{CryptoRng, Error, OsRng, RngCore};
+
+    // An entropy source failing a number of times before it recovers.
+    struct FlakyRng {
+        failures: usize,
+    }
+
+    impl RngCore for FlakyRng {
+        fn next_u32(&mut self) -> u32 {
+            OsRng.next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            OsRng.next_u64()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            OsRng.fill_bytes(dest)
+        }
+
+        fn try_fill_bytes(
+            &mut self,
+            dest: &mut [u8],
+        ) -> Result<(), Error> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(Error::new("entropy source not ready"));
+            }
+            OsRng.try_fill_bytes(dest)
+        }
+    }
+
+    impl CryptoRng for FlakyRng {}
+
+    #[test]
+    fn test_keypair_retries_a_flaky_rng() {
+        // Fails twice, the third attempt succeeds.
+        let mut rng = RetryRng(FlakyRng { failures: 2 });
+        let keys = keypair(&mut rng).unwrap();
+        let (ct, ss) = encapsulate(&keys.public, &mut OsRng).unwrap();
+        assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+    }
+
+    #[test]
+    fn test_caller_rngs_are_not_retried() {
+        assert!(matches!(
+            keypair(&mut FlakyRng { failures: 1 }),
+            Err(KyberLibError::RandomBytesGeneration)
+        ));
+    }
+
+    #[test]
+    fn test_randombytes_attempts_are_bounded_and_configurable() {
+        // The only test changing the process-wide setting.
+        assert_eq!(
+            random_bytes_attempts(),
+            DEFAULT_RANDOM_BYTES_ATTEMPTS
+        );
+        let mut buffer = [0u8; 32];
+        let mut rng = RetryRng(FlakyRng { failures: 3 });
+        let result = randombytes(&mut buffer, 32, &mut rng);
+        assert!(matches!(
+            result,
+            Err(KyberLibError::RandomBytesGeneration)
+        ));
+
+        set_random_bytes_attempts(4);
+        let mut rng = RetryRng(FlakyRng { failures: 3 });
+        let result = randombytes(&mut buffer, 32, &mut rng);
+        set_random_bytes_attempts(DEFAULT_RANDOM_BYTES_ATTEMPTS);
+        assert!(result.is_ok());
+    }
 
     #[test]
     fn test_randombytes() {
@@ -203,4 +284,38 @@ mod tests {
         // Check if the result is an InvalidLength error
         assert!(matches!(result, Err(KyberLibError::InvalidLength)));
     }
+
+    // The source is process-wide and registered once, so one test covers
+    // the whole lifecycle.
+    #[cfg(feature = "custom-entropy")]
+    #[test]
+    fn test_host_rng_draws_from_the_registered_source() {
+        use kyberlib::rng::{register_entropy_source, HostRng};
+
+        fn host_entropy(buf: &mut [u8]) -> Result<(), KyberLibError> {
+            OsRng
+                .try_fill_bytes(buf)
+                .map_err(|_| KyberLibError::RandomBytesGeneration)
+        }
+
+        fn other_entropy(buf: &mut [u8]) -> Result<(), KyberLibError> {
+            buf.fill(0);
+            Ok(())
+        }
+
+        assert!(matches!(
+            keypair(&mut HostRng),
+            Err(KyberLibError::RandomBytesGeneration)
+        ));
+
+        register_entropy_source(host_entropy).unwrap();
+        assert!(matches!(
+            register_entropy_source(other_entropy),
+            Err(KyberLibError::InvalidInput)
+        ));
+
+        let keys = keypair(&mut HostRng).unwrap();
+        let (ct, ss) = encapsulate(&keys.public, &mut HostRng).unwrap();
+        assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss);
+    }
 }
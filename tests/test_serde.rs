@@ -0,0 +1,141 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the serde support.
+
+#![cfg(feature = "serde")]
+
+use kyberlib::params::KYBER_INDCPA_SECRET_KEY_BYTES;
+use kyberlib::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Peer {
+    #[serde(with = "kyberlib::serialization")]
+    public: PublicKey,
+    ciphertext: AlignedCiphertext,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn json_holds_hex_strings() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, _) = encapsulate(&keys.public, &mut rng).unwrap();
+    let peer = Peer {
+        public: keys.public,
+        ciphertext: AlignedCiphertext(ct),
+    };
+
+    let json = serde_json::to_value(&peer).unwrap();
+    assert_eq!(json["public"], hex(&keys.public));
+    assert_eq!(json["ciphertext"], hex(&ct));
+    assert_eq!(serde_json::from_value::<Peer>(json).unwrap(), peer);
+
+    let json = serde_json::to_string(&keys).unwrap();
+    assert_eq!(json, format!("\"{}\"", hex(&keys.to_bytes())));
+    assert_eq!(serde_json::from_str::<Keypair>(&json).unwrap(), keys);
+    let upper = json.to_uppercase();
+    assert_eq!(serde_json::from_str::<Keypair>(&upper).unwrap(), keys);
+
+    let validated = keys.public.validated().unwrap();
+    let json = serde_json::to_string(&validated).unwrap();
+    assert_eq!(
+        serde_json::from_str::<ValidatedPublicKey>(&json).unwrap(),
+        validated
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Session {
+    #[serde(with = "kyberlib::serialization::secret")]
+    secret: SecretKey,
+    #[serde(with = "kyberlib::serialization::secret")]
+    shared: SharedSecret,
+}
+
+#[test]
+fn secret_fields_are_written_like_public_ones() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (_, shared) = encapsulate(&keys.public, &mut rng).unwrap();
+    let session = Session {
+        secret: keys.secret,
+        shared,
+    };
+
+    let json = serde_json::to_value(&session).unwrap();
+    assert_eq!(json["secret"], hex(&keys.secret));
+    assert_eq!(json["shared"], hex(&shared));
+    assert_eq!(
+        serde_json::from_value::<Session>(json).unwrap(),
+        session
+    );
+
+    let bytes = bincode::serialize(&session).unwrap();
+    assert_eq!(
+        bytes.len(),
+        8 + KYBER_SECRET_KEY_BYTES + 8 + KYBER_SHARED_SECRET_BYTES
+    );
+    assert_eq!(
+        bincode::deserialize::<Session>(&bytes).unwrap(),
+        session
+    );
+}
+
+#[test]
+fn bincode_holds_raw_bytes() {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    let (ct, _) = encapsulate(&keys.public, &mut rng).unwrap();
+    let peer = Peer {
+        public: keys.public,
+        ciphertext: AlignedCiphertext(ct),
+    };
+
+    // bincode prefixes each byte string with its length as a u64.
+    let bytes = bincode::serialize(&peer).unwrap();
+    assert_eq!(
+        bytes.len(),
+        8 + KYBER_PUBLIC_KEY_BYTES + 8 + KYBER_CIPHERTEXT_BYTES
+    );
+    assert_eq!(&bytes[8..8 + KYBER_PUBLIC_KEY_BYTES], &keys.public[..]);
+    assert_eq!(bincode::deserialize::<Peer>(&bytes).unwrap(), peer);
+
+    let bytes = bincode::serialize(&keys).unwrap();
+    assert_eq!(bytes.len(), 8 + VERSIONED_SECRET_KEY_BYTES);
+    assert_eq!(bincode::deserialize::<Keypair>(&bytes).unwrap(), keys);
+}
+
+#[test]
+fn malformed_input_is_rejected() {
+    let keys = keypair(&mut rand::thread_rng()).unwrap();
+    let json = serde_json::to_string(&keys).unwrap();
+
+    // One hex digit short, and a digit that is not hex.
+    let short = format!("\"{}\"", &json[1..json.len() - 2]);
+    assert!(serde_json::from_str::<Keypair>(&short).is_err());
+    let bad = json.replacen(&json[1..2], "g", 1);
+    let err = serde_json::from_str::<Keypair>(&bad).unwrap_err();
+    assert!(err.to_string().contains("invalid hex digit"));
+
+    // The embedded hash of the public key no longer matches.
+    let mut blob = keys.to_bytes();
+    blob[1 + KYBER_INDCPA_SECRET_KEY_BYTES] ^= 1;
+    let tampered = format!("\"{}\"", hex(&blob));
+    assert!(serde_json::from_str::<Keypair>(&tampered).is_err());
+
+    // A public key with a coefficient of q fails validation.
+    let mut public = keys.public;
+    public[0] = 0x01;
+    public[1] = 0xfd;
+    let json = format!("\"{}\"", hex(&public));
+    assert!(serde_json::from_str::<AlignedPublicKey>(&json).is_ok());
+    assert!(serde_json::from_str::<ValidatedPublicKey>(&json).is_err());
+
+    let bytes = bincode::serialize(&keys).unwrap();
+    assert!(bincode::deserialize::<Keypair>(&bytes[..100]).is_err());
+}
@@ -0,0 +1,163 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for the type-state handshake sessions.
+
+#![cfg(feature = "session")]
+
+use kyberlib::session::*;
+use kyberlib::*;
+
+#[test]
+fn full_handshake() {
+    let mut rng = rand::thread_rng();
+    let server_keys = keypair(&mut rng).unwrap();
+
+    let (hello, awaiting) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let hello = HelloMessage::from_bytes(&hello.to_bytes()).unwrap();
+    let (response, server) =
+        Responder::accept(&hello, &server_keys.secret, &mut rng)
+            .unwrap();
+    let response =
+        ResponseMessage::from_bytes(&response.to_bytes()).unwrap();
+    let client = awaiting.finish(&response).unwrap();
+
+    assert_eq!(client.sending_key(), server.receiving_key());
+    assert_eq!(client.receiving_key(), server.sending_key());
+}
+
+#[test]
+fn directional_keys_are_separated() {
+    let mut rng = rand::thread_rng();
+    let server_keys = keypair(&mut rng).unwrap();
+    let (hello, awaiting) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let (response, server) =
+        Responder::accept(&hello, &server_keys.secret, &mut rng)
+            .unwrap();
+    let client = awaiting.finish(&response).unwrap();
+
+    assert_ne!(client.sending_key(), client.receiving_key());
+    assert_ne!(server.sending_key(), server.receiving_key());
+
+    // A second handshake with the same static key yields fresh keys.
+    let (hello, awaiting) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let (response, _) =
+        Responder::accept(&hello, &server_keys.secret, &mut rng)
+            .unwrap();
+    let again = awaiting.finish(&response).unwrap();
+    assert_ne!(again.sending_key(), client.sending_key());
+}
+
+#[test]
+fn messages_are_not_interchangeable() {
+    let mut rng = rand::thread_rng();
+    let server_keys = keypair(&mut rng).unwrap();
+    let (hello, awaiting) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let (response, _) =
+        Responder::accept(&hello, &server_keys.secret, &mut rng)
+            .unwrap();
+
+    // Out-of-order delivery: each parser rejects the other's message.
+    assert_eq!(
+        ResponseMessage::from_bytes(&hello.to_bytes()).unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+    assert_eq!(
+        HelloMessage::from_bytes(&response.to_bytes()).unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+
+    // Wrong version and message type bytes.
+    let mut bytes = response.to_bytes();
+    bytes[0] = SESSION_VERSION + 1;
+    assert_eq!(
+        ResponseMessage::from_bytes(&bytes).unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    let mut bytes = response.to_bytes();
+    bytes[1] = 1;
+    assert_eq!(
+        ResponseMessage::from_bytes(&bytes).unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    assert!(awaiting.finish(&response).is_ok());
+}
+
+#[test]
+fn hello_with_non_canonical_key_is_rejected() {
+    let mut rng = rand::thread_rng();
+    let server_keys = keypair(&mut rng).unwrap();
+    let (hello, _) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let mut bytes = hello.to_bytes();
+    // Force the first coefficient of the ephemeral key to 4095.
+    bytes[2] = 0xff;
+    bytes[3] |= 0x0f;
+    assert_eq!(
+        HelloMessage::from_bytes(&bytes).unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+
+    let mut public = server_keys.public;
+    public[0] = 0xff;
+    public[1] |= 0x0f;
+    assert_eq!(
+        Initiator::new(&public, &mut rng).unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+}
+
+#[test]
+fn tampered_response_is_rejected() {
+    let mut rng = rand::thread_rng();
+    let server_keys = keypair(&mut rng).unwrap();
+    let (hello, awaiting) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let (response, _) =
+        Responder::accept(&hello, &server_keys.secret, &mut rng)
+            .unwrap();
+    let mut bytes = response.to_bytes();
+    bytes[10] ^= 0x01;
+    let evil = ResponseMessage::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        awaiting.finish(&evil).unwrap_err(),
+        KyberLibError::Decapsulation
+    );
+}
+
+#[test]
+fn response_from_another_handshake_is_rejected() {
+    let mut rng = rand::thread_rng();
+    let server_keys = keypair(&mut rng).unwrap();
+    let (_, awaiting) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let (other_hello, _) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let (other_response, _) =
+        Responder::accept(&other_hello, &server_keys.secret, &mut rng)
+            .unwrap();
+    assert_eq!(
+        awaiting.finish(&other_response).unwrap_err(),
+        KyberLibError::Decapsulation
+    );
+}
+
+#[test]
+fn hello_to_wrong_responder_fails_confirmation() {
+    let mut rng = rand::thread_rng();
+    let server_keys = keypair(&mut rng).unwrap();
+    let impostor_keys = keypair(&mut rng).unwrap();
+    let (hello, awaiting) =
+        Initiator::new(&server_keys.public, &mut rng).unwrap();
+    let (response, _) =
+        Responder::accept(&hello, &impostor_keys.secret, &mut rng)
+            .unwrap();
+    assert_eq!(
+        awaiting.finish(&response).unwrap_err(),
+        KyberLibError::Decapsulation
+    );
+}
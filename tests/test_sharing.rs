@@ -0,0 +1,141 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for Shamir secret sharing of secret keys.
+
+#![cfg(feature = "sharing")]
+
+use kyberlib::sharing::*;
+use kyberlib::*;
+use zeroize::Zeroize;
+
+// Derives a fresh key pair, returning it with its seed.
+fn seeded_keypair() -> (Keypair, [u8; SHARE_SEED_BYTES]) {
+    let mut seed = [0u8; SHARE_SEED_BYTES];
+    rand::thread_rng().fill_bytes(&mut seed);
+    (derive(&seed).unwrap(), seed)
+}
+
+#[test]
+fn recover_with_exactly_threshold_shares() {
+    let mut rng = rand::thread_rng();
+    let (keys, seed) = seeded_keypair();
+    let shares =
+        split_secret_key(&keys.secret, &seed, 3, 5, &mut rng).unwrap();
+    assert_eq!(shares.len(), 5);
+
+    // Every window of three shares recovers the key.
+    for window in shares.windows(3) {
+        assert_eq!(recover_secret_key(window).unwrap(), keys.secret);
+    }
+    let picked =
+        [shares[4].clone(), shares[0].clone(), shares[2].clone()];
+    assert_eq!(recover_secret_key(&picked).unwrap(), keys.secret);
+}
+
+#[test]
+fn recover_with_fewer_shares_fails() {
+    let mut rng = rand::thread_rng();
+    let (keys, seed) = seeded_keypair();
+    let shares =
+        split_secret_key(&keys.secret, &seed, 3, 5, &mut rng).unwrap();
+    assert_eq!(
+        recover_secret_key(&shares[..2]).unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    assert_eq!(
+        recover_secret_key(&[]).unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+}
+
+#[test]
+fn corrupted_share_is_detected() {
+    let mut rng = rand::thread_rng();
+    let (keys, seed) = seeded_keypair();
+    let shares =
+        split_secret_key(&keys.secret, &seed, 2, 3, &mut rng).unwrap();
+    let mut bytes = shares[1].to_bytes();
+    bytes[40] ^= 0x80;
+    assert_eq!(
+        Share::from_bytes(&bytes).unwrap_err(),
+        KyberLibError::InvalidShare
+    );
+    assert_eq!(
+        Share::from_bytes(&bytes[1..]).unwrap_err(),
+        KyberLibError::InvalidLength
+    );
+}
+
+#[test]
+fn mixing_shares_of_different_keys_fails() {
+    let mut rng = rand::thread_rng();
+    let (keys_a, seed_a) = seeded_keypair();
+    let (keys_b, seed_b) = seeded_keypair();
+    let a = split_secret_key(&keys_a.secret, &seed_a, 2, 3, &mut rng)
+        .unwrap();
+    let b = split_secret_key(&keys_b.secret, &seed_b, 2, 3, &mut rng)
+        .unwrap();
+    let mixed = [a[0].clone(), b[1].clone()];
+    assert_eq!(
+        recover_secret_key(&mixed).unwrap_err(),
+        KyberLibError::InvalidShare
+    );
+
+    // The same share twice does not count twice.
+    let repeated = [a[0].clone(), a[0].clone()];
+    assert_eq!(
+        recover_secret_key(&repeated).unwrap_err(),
+        KyberLibError::InvalidShare
+    );
+}
+
+#[test]
+fn shares_round_trip_through_bytes() {
+    let mut rng = rand::thread_rng();
+    let (keys, seed) = seeded_keypair();
+    let shares =
+        split_secret_key(&keys.secret, &seed, 2, 2, &mut rng).unwrap();
+    let parsed: Vec<Share> = shares
+        .iter()
+        .map(|s| Share::from_bytes(&s.to_bytes()).unwrap())
+        .collect();
+    assert_eq!(parsed, shares);
+    assert_eq!(parsed[1].index(), 2);
+    assert_eq!(parsed[1].threshold(), 2);
+    assert_eq!(recover_secret_key(&parsed).unwrap(), keys.secret);
+}
+
+#[test]
+fn split_rejects_invalid_arguments() {
+    let mut rng = rand::thread_rng();
+    let (keys, seed) = seeded_keypair();
+    assert_eq!(
+        split_secret_key(&keys.secret, &seed, 1, 3, &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    assert_eq!(
+        split_secret_key(&keys.secret, &seed, 4, 3, &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidInput
+    );
+    let (other, _) = seeded_keypair();
+    assert_eq!(
+        split_secret_key(&other.secret, &seed, 2, 3, &mut rng)
+            .unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+}
+
+#[test]
+fn share_zeroizes() {
+    let mut rng = rand::thread_rng();
+    let (keys, seed) = seeded_keypair();
+    let mut share =
+        split_secret_key(&keys.secret, &seed, 2, 2, &mut rng)
+            .unwrap()
+            .remove(0);
+    share.zeroize();
+    assert_eq!(share.to_bytes(), [0u8; SHARE_BYTES]);
+}
@@ -0,0 +1,190 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Stack high-water marks of the KEM operations.
+//!
+//! Each measurement is printed as a report line and checked against the
+//! ceiling in [`CEILINGS`] for its configuration, the backend that ran,
+//! the parameter set and the profile. The ceilings leave roughly a third
+//! of headroom over what was measured on x86_64 with Rust 1.95; the
+//! reference configuration is the default features, the small one adds
+//! `small`. A backend without ceilings is reported but not checked. The
+//! `levels` parameter sets are measured when that feature is on:
+//!
+//! ```shell
+//! cargo test --features stack-probe,levels --test test_stack_probe -- --nocapture
+//! cargo test --features stack-probe,levels,small --test test_stack_probe -- --nocapture
+//! cargo test --features stack-probe,levels,avx2 --test test_stack_probe -- --nocapture
+//! ```
+//!
+//! A failure means a change made an operation noticeably hungrier for
+//! stack, which matters on embedded targets; look for new large locals
+//! before raising a ceiling.
+
+#![cfg(feature = "stack-probe")]
+
+use kyberlib::acceleration::acceleration;
+use kyberlib::stack_probe::measure;
+use kyberlib::*;
+use rand::rngs::OsRng;
+
+const CONFIGURATION: &str = if cfg!(feature = "small") {
+    "small"
+} else {
+    "reference"
+};
+
+const PROFILE: &str = if cfg!(debug_assertions) {
+    "debug"
+} else {
+    "release"
+};
+
+/// Peak stack use allowed for each of `keypair`, `encapsulate` and
+/// `decapsulate`: configuration, backend, parameter set, then the debug
+/// and release ceilings in KiB.
+const CEILINGS: &[(&str, &str, &str, usize, usize)] = &[
+    ("reference", "scalar", "kyber1024", 60, 24),
+    ("reference", "scalar", "levels::Kyber512", 96, 52),
+    ("reference", "scalar", "levels::Kyber768", 96, 52),
+    ("reference", "scalar", "levels::Kyber1024", 104, 54),
+    ("reference", "avx2", "kyber1024", 92, 48),
+    ("reference", "avx2", "levels::Kyber512", 96, 52),
+    ("reference", "avx2", "levels::Kyber768", 96, 52),
+    ("reference", "avx2", "levels::Kyber1024", 104, 54),
+    ("reference", "avx512", "kyber1024", 92, 48),
+    ("reference", "avx512", "levels::Kyber512", 96, 52),
+    ("reference", "avx512", "levels::Kyber768", 96, 52),
+    ("reference", "avx512", "levels::Kyber1024", 104, 54),
+    ("small", "scalar", "kyber1024", 48, 24),
+    ("small", "scalar", "levels::Kyber512", 80, 52),
+    ("small", "scalar", "levels::Kyber768", 80, 52),
+    ("small", "scalar", "levels::Kyber1024", 88, 54),
+    ("small", "avx2", "kyber1024", 80, 48),
+    ("small", "avx2", "levels::Kyber512", 80, 52),
+    ("small", "avx2", "levels::Kyber768", 80, 52),
+    ("small", "avx2", "levels::Kyber1024", 88, 54),
+    ("small", "avx512", "kyber1024", 88, 48),
+    ("small", "avx512", "levels::Kyber512", 80, 52),
+    ("small", "avx512", "levels::Kyber768", 80, 52),
+    ("small", "avx512", "levels::Kyber1024", 88, 54),
+];
+
+fn ceiling(backend: &str, params: &str) -> Option<usize> {
+    let &(_, _, _, debug, release) =
+        CEILINGS.iter().find(|&&(configuration, b, name, _, _)| {
+            configuration == CONFIGURATION
+                && b == backend
+                && name == params
+        })?;
+    let kib = if cfg!(debug_assertions) {
+        debug
+    } else {
+        release
+    };
+    Some(kib * 1024)
+}
+
+fn check(params: &str, operation: &str, depth: usize) {
+    let backend = acceleration().name();
+    let ceiling = match ceiling(backend, params) {
+        Some(ceiling) => ceiling,
+        None => {
+            eprintln!(
+                "stack {} {} {} {} {}: {} bytes, no ceiling",
+                CONFIGURATION,
+                backend,
+                PROFILE,
+                params,
+                operation,
+                depth
+            );
+            return;
+        }
+    };
+    eprintln!(
+        "stack {} {} {} {} {}: {} bytes, ceiling {}",
+        CONFIGURATION,
+        backend,
+        PROFILE,
+        params,
+        operation,
+        depth,
+        ceiling
+    );
+    assert!(
+        depth <= ceiling,
+        "{} {} used {} bytes of stack, over the {} byte ceiling",
+        params,
+        operation,
+        depth,
+        ceiling
+    );
+}
+
+#[test]
+fn probe_sees_a_large_local_buffer() {
+    let ((), small) = measure(|| ());
+    let ((), large) = measure(|| {
+        let buf = [0u8; 16 * 1024];
+        let _ = core::hint::black_box(&buf);
+    });
+    assert!(small < 1024, "{}", small);
+    assert!(large >= 16 * 1024, "{}", large);
+}
+
+#[test]
+fn keypair_stays_under_ceiling() {
+    let (keys, depth) = measure(|| keypair(&mut OsRng));
+    assert!(keys.is_ok());
+    check("kyber1024", "keypair", depth);
+}
+
+#[test]
+fn encapsulate_and_decapsulate_stay_under_ceiling() {
+    let keys = keypair(&mut OsRng).unwrap();
+    let public = keys.public;
+    let (encapsulated, depth) =
+        measure(move || encapsulate(&public, &mut OsRng));
+    let (ct, ss) = encapsulated.unwrap();
+    check("kyber1024", "encapsulate", depth);
+
+    let (decapsulated, depth) =
+        measure(move || decapsulate(&ct, &keys.secret));
+    assert_eq!(decapsulated.unwrap(), ss);
+    check("kyber1024", "decapsulate", depth);
+}
+
+#[cfg(feature = "levels")]
+fn check_level<P>(params: &str)
+where
+    P: levels::Params + Send + 'static,
+    P::PublicKey: Send + 'static,
+    P::SecretKey: Send + 'static,
+    P::Ciphertext: Send + 'static,
+{
+    let (keys, depth) = measure(|| levels::keypair::<P, _>(&mut OsRng));
+    let keys = keys.unwrap();
+    check(params, "keypair", depth);
+
+    let public = keys.public;
+    let (encapsulated, depth) = measure(move || {
+        levels::encapsulate::<P, _>(public.as_ref(), &mut OsRng)
+    });
+    let (ct, ss) = encapsulated.unwrap();
+    check(params, "encapsulate", depth);
+
+    let (decapsulated, depth) = measure(move || {
+        levels::decapsulate::<P>(ct.as_ref(), keys.secret.as_ref())
+    });
+    assert_eq!(decapsulated.unwrap(), ss);
+    check(params, "decapsulate", depth);
+}
+
+#[cfg(feature = "levels")]
+#[test]
+fn levels_stay_under_ceiling() {
+    check_level::<levels::Kyber512>("levels::Kyber512");
+    check_level::<levels::Kyber768>("levels::Kyber768");
+    check_level::<levels::Kyber1024>("levels::Kyber1024");
+}
@@ -0,0 +1,73 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for deriving several keys from a shared secret.
+
+use kyberlib::subkeys::*;
+use kyberlib::*;
+
+fn shared_secret() -> SharedSecret {
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+    encapsulate(&keys.public, &mut rng).unwrap().1
+}
+
+#[test]
+fn subkeys_are_pairwise_distinct() {
+    let ss = shared_secret();
+    let keys = ss.split::<8>(b"test v1");
+    for (i, a) in keys.iter().enumerate() {
+        for b in &keys[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+}
+
+#[test]
+fn subkeys_are_deterministic() {
+    let ss = shared_secret();
+    assert_eq!(ss.split::<3>(b"test v1"), ss.split::<3>(b"test v1"));
+    assert_eq!(ss.derive_key(b"test v1"), ss.derive_key(b"test v1"));
+}
+
+#[test]
+fn single_split_is_derive_key() {
+    let ss = shared_secret();
+    assert_eq!(ss.split::<1>(b"test v1"), [ss.derive_key(b"test v1")]);
+}
+
+#[test]
+fn fewer_subkeys_are_a_prefix() {
+    let ss = shared_secret();
+    let three = ss.split::<3>(b"test v1");
+    let five = ss.split::<5>(b"test v1");
+    assert_eq!(three, five[..3]);
+}
+
+#[test]
+fn context_and_secret_separate_the_keys() {
+    let ss = shared_secret();
+    let key = ss.derive_key(b"test v1");
+    assert_ne!(key, ss.derive_key(b"test v2"));
+    assert_ne!(key, ss.derive_key(b""));
+    assert_ne!(key, shared_secret().derive_key(b"test v1"));
+    assert_ne!(key, ss);
+}
+
+// SHAKE256("kyberlib subkeys v1" || ss || le64(3) || "kat"), as
+// computed by Python's hashlib.
+#[test]
+fn known_answer() {
+    let keys = [0x42u8; KYBER_SHARED_SECRET_BYTES].split::<2>(b"kat");
+    let hex = |k: &[u8]| -> String {
+        k.iter().map(|b| format!("{:02x}", b)).collect()
+    };
+    assert_eq!(
+        hex(&keys[0]),
+        "f0cf45a5d5083cefd1d1f3574335cc1352c1cad65f78bcbb6dbba6b44039d172"
+    );
+    assert_eq!(
+        hex(&keys[1]),
+        "28d4f164a926b45f772550fcbfe167b939b6eaf4b6de5306013a2f547f712e7c"
+    );
+}
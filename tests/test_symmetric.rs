@@ -1,11 +1,16 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the symmetric primitives.
+
 #[cfg(test)]
 mod tests {
     // Import necessary items
     use kyberlib::{
-        symmetric::{hash_g, hash_h, kdf, prf},
+        symmetric::{
+            hash_g, hash_h, kdf, labeled_kdf, matrix_index_bytes, prf,
+            PrfReader,
+        },
         KYBER_SHARED_SECRET_BYTES,
     };
 
@@ -67,4 +72,66 @@ mod tests {
         // Assert that the output is not all zeros
         assert_ne!(out, [0u8; KYBER_SHARED_SECRET_BYTES]);
     }
+
+    // Test the labeled_kdf function
+    #[test]
+    fn test_labeled_kdf() {
+        let mut out = [0u8; 64];
+        labeled_kdf(&mut out, "kyberlib test v1", &[b"ab", b"c"]);
+
+        // Assert that the label and the input split separate the outputs
+        let mut other = [0u8; 64];
+        labeled_kdf(&mut other, "kyberlib test v2", &[b"ab", b"c"]);
+        assert_ne!(out, other);
+        labeled_kdf(&mut other, "kyberlib test v1", &[b"a", b"bc"]);
+        assert_ne!(out, other);
+
+        // Assert that shorter outputs are prefixes of longer ones
+        let mut short = [0u8; 32];
+        labeled_kdf(&mut short, "kyberlib test v1", &[b"ab", b"c"]);
+        assert_eq!(short, out[..32]);
+    }
+
+    // Test that the PrfReader stream matches the buffered prf output
+    #[test]
+    fn test_prf_reader_matches_prf() {
+        use kyberlib::params::KYBER_SYM_BYTES;
+        let key = [7u8; KYBER_SYM_BYTES];
+        let mut expected = [0u8; 200];
+        prf(&mut expected, 200, &key, 3);
+
+        // Assert that one read returns the first N bytes
+        let mut out = [0u8; 200];
+        PrfReader::new(&key, 3).squeeze(&mut out);
+        assert_eq!(out, expected);
+
+        // Assert that reads of uneven sizes continue the same stream
+        let mut reader = PrfReader::new(&key, 3);
+        let mut pieces = [0xffu8; 200];
+        for chunk in [1, 15, 48, 64, 72].iter().scan(0, |start, len| {
+            *start += len;
+            Some(*start - len..*start)
+        }) {
+            reader.squeeze(&mut pieces[chunk]);
+        }
+        assert_eq!(pieces, expected);
+
+        // Assert that another nonce gives another stream
+        PrfReader::new(&key, 4).squeeze(&mut out);
+        assert_ne!(out, expected);
+    }
+
+    // Pin the domain separation bytes of the public matrix: A uses
+    // (j, i) and A^T uses (i, j).
+    #[test]
+    fn test_matrix_index_bytes() {
+        assert_eq!(matrix_index_bytes(0, 0, false), [0, 0]);
+        assert_eq!(matrix_index_bytes(0, 1, false), [1, 0]);
+        assert_eq!(matrix_index_bytes(2, 1, false), [1, 2]);
+        assert_eq!(matrix_index_bytes(3, 0, false), [0, 3]);
+        assert_eq!(matrix_index_bytes(0, 0, true), [0, 0]);
+        assert_eq!(matrix_index_bytes(0, 1, true), [0, 1]);
+        assert_eq!(matrix_index_bytes(2, 1, true), [2, 1]);
+        assert_eq!(matrix_index_bytes(3, 0, true), [3, 0]);
+    }
 }
@@ -0,0 +1,73 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Tests for encapsulating to a public key validated once.
+
+use kyberlib::kem::public_key_checks;
+use kyberlib::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::sync::{Mutex, MutexGuard};
+
+// The check counter is process-wide, so the tests counting checks must
+// not run alongside others that validate keys.
+fn lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[test]
+fn validated_key_matches_validating_path() {
+    let _guard = lock();
+    let keys = derive(&[5u8; 64]).unwrap();
+    let peer = keys.public.validated().unwrap();
+    assert_eq!(peer.as_bytes(), &keys.public);
+
+    let expected =
+        encapsulate(&keys.public, &mut StdRng::from_seed([9u8; 32]))
+            .unwrap();
+    let before = public_key_checks();
+    let (ct, ss) =
+        encapsulate_validated(&peer, &mut StdRng::from_seed([9u8; 32]))
+            .unwrap();
+    assert_eq!(
+        public_key_checks(),
+        before,
+        "the key was checked again"
+    );
+    assert_eq!((ct, ss), expected);
+    assert_eq!(decapsulate(&ct, keys.expose_secret()).unwrap(), ss);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn validation_runs_once_per_validated_call() {
+    let _guard = lock();
+    let mut rng = rand::thread_rng();
+    let keys = keypair(&mut rng).unwrap();
+
+    let before = public_key_checks();
+    let _ = encapsulate(&keys.public, &mut rng).unwrap();
+    assert_eq!(public_key_checks(), before + 1);
+
+    let peer = keys.public.validated().unwrap();
+    assert_eq!(public_key_checks(), before + 2);
+    for _ in 0..4 {
+        let _ = encapsulate_validated(&peer, &mut rng).unwrap();
+    }
+    assert_eq!(public_key_checks(), before + 2);
+}
+
+#[test]
+fn invalid_keys_are_rejected() {
+    let _guard = lock();
+    let mut rng = rand::thread_rng();
+    // The first coefficient is 0xfff, above q.
+    let mut pk = keypair(&mut rng).unwrap().public;
+    pk[0] = 0xff;
+    pk[1] |= 0x0f;
+    assert_eq!(pk.validated(), Err(KyberLibError::InvalidKey));
+    assert_eq!(
+        encapsulate(&pk, &mut rng).unwrap_err(),
+        KyberLibError::InvalidKey
+    );
+}
@@ -1,10 +1,14 @@
 // Copyright © 2024 kyberlib. All rights reserved.
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+//! Tests for the WebAssembly bindings.
+
+#![cfg(feature = "wasm")]
+
 #[cfg(test)]
 mod tests {
     // Import necessary items
-    use kyberlib::wasm::{Kex, Keys, Params};
+    use kyberlib::wasm::{self, Kex, Keys, Params};
     use kyberlib::{decapsulate, encapsulate, keypair, params::*};
     use wasm_bindgen_test::*;
 
@@ -92,7 +96,8 @@ mod tests {
         };
 
         // Create a new Kex instance using the valid public key
-        let mut kex = Kex::new(keys.pubkey());
+        let mut kex = Kex::new(keys.pubkey())
+            .unwrap_or_else(|_| panic!("Failed to encapsulate"));
 
         // Test the ciphertext and sharedSecret getters
         assert_eq!(kex.ciphertext().len(), KYBER_CIPHERTEXT_BYTES);
@@ -139,18 +144,17 @@ mod tests {
         );
     }
 
-    // Test the Kex::new() method with an invalid public key size
+    // Test the Kex::new() method with an invalid public key size; a
+    // JsError can only be built on wasm targets
+    #[cfg(target_arch = "wasm32")]
     #[wasm_bindgen_test]
     fn test_kex_new_invalid_pubkey_size() {
         // Generate an invalid public key with incorrect size
         let invalid_pk =
             vec![0u8; KYBER_PUBLIC_KEY_BYTES - 1].into_boxed_slice();
 
-        // Call Kex::new() with the invalid public key and expect a panic
-        let _ = std::panic::catch_unwind(|| {
-            let _ = Kex::new(invalid_pk);
-        })
-        .unwrap_err();
+        // Call Kex::new() with the invalid public key and expect an error
+        assert!(Kex::new(invalid_pk).is_err());
     }
 
     // Test the decapsulate() function with mismatched ciphertext and secret key
@@ -163,7 +167,8 @@ mod tests {
         };
 
         // Encapsulate with the public key to get a valid ciphertext
-        let kex = Kex::new(keys.pubkey());
+        let kex = Kex::new(keys.pubkey())
+            .unwrap_or_else(|_| panic!("Failed to encapsulate"));
 
         // Generate a different key pair
         let different_keys = match Keys::new() {
@@ -187,7 +192,8 @@ mod tests {
         };
 
         // Encapsulate with the public key to get a valid ciphertext
-        let kex = Kex::new(keys.pubkey());
+        let kex = Kex::new(keys.pubkey())
+            .unwrap_or_else(|_| panic!("Failed to encapsulate"));
 
         // Call decapsulate() with the valid ciphertext and secret key
         let result = decapsulate(&kex.ciphertext(), &keys.secret());
@@ -222,7 +228,8 @@ mod tests {
         };
 
         // Encapsulate with the public key to get a valid ciphertext
-        let kex = Kex::new(keys.pubkey());
+        let kex = Kex::new(keys.pubkey())
+            .unwrap_or_else(|_| panic!("Failed to encapsulate"));
 
         // Create an invalid secret key with incorrect size
         let invalid_sk =
@@ -259,7 +266,8 @@ mod tests {
         };
 
         // Create a Kex instance
-        let mut kex = Kex::new(keys.pubkey());
+        let mut kex = Kex::new(keys.pubkey())
+            .unwrap_or_else(|_| panic!("Failed to encapsulate"));
 
         // Check if ciphertext() returns the expected ciphertext
         assert_eq!(kex.ciphertext().len(), KYBER_CIPHERTEXT_BYTES);
@@ -316,4 +324,76 @@ mod tests {
         let result = encapsulate(&keys.pubkey(), &mut MockRng);
         assert!(result.is_err());
     }
+
+    // Test the JavaScript-facing round trip through the Keys class
+    #[wasm_bindgen_test]
+    fn test_keys_round_trip() {
+        let keys = Keys::generate()
+            .unwrap_or_else(|_| panic!("Failed to generate keys"));
+        assert_eq!(keys.publicKey(), keys.pubkey());
+        assert_eq!(keys.secretKey(), keys.secret());
+
+        let kex = Keys::encapsulate(keys.publicKey())
+            .unwrap_or_else(|_| panic!("Failed to encapsulate"));
+        assert_eq!(kex.ciphertext().len(), KYBER_CIPHERTEXT_BYTES);
+        let ss = keys
+            .decapsulate(kex.ciphertext())
+            .unwrap_or_else(|_| panic!("Failed to decapsulate"));
+        assert_eq!(ss, kex.sharedSecret());
+
+        let ss = wasm::decapsulate(kex.ciphertext(), keys.secretKey())
+            .unwrap_or_else(|_| panic!("Failed to decapsulate"));
+        assert_eq!(ss, kex.sharedSecret());
+    }
+
+    // Returns the message of the exception thrown into JavaScript
+    #[cfg(target_arch = "wasm32")]
+    fn message(err: wasm_bindgen::JsError) -> String {
+        use wasm_bindgen::JsCast;
+        let value = wasm_bindgen::JsValue::from(err);
+        String::from(
+            value.dyn_into::<js_sys::Error>().unwrap().message(),
+        )
+    }
+
+    // Test that bad input lengths throw descriptive exceptions
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_keys_invalid_lengths() {
+        let keys = Keys::generate()
+            .unwrap_or_else(|_| panic!("Failed to generate keys"));
+
+        let err = Keys::encapsulate(vec![0u8; 32].into_boxed_slice())
+            .err()
+            .unwrap();
+        assert_eq!(
+            message(err),
+            format!(
+                "public key must be {} bytes, got 32",
+                KYBER_PUBLIC_KEY_BYTES
+            )
+        );
+
+        let err = keys
+            .decapsulate(vec![0u8; 1].into_boxed_slice())
+            .err()
+            .unwrap();
+        assert_eq!(
+            message(err),
+            format!(
+                "ciphertext must be {} bytes, got 1",
+                KYBER_CIPHERTEXT_BYTES
+            )
+        );
+
+        let ct = vec![0u8; KYBER_CIPHERTEXT_BYTES].into_boxed_slice();
+        let err = wasm::decapsulate(ct, Box::new([])).err().unwrap();
+        assert_eq!(
+            message(err),
+            format!(
+                "secret key must be {} bytes, got 0",
+                KYBER_SECRET_KEY_BYTES
+            )
+        );
+    }
 }
@@ -0,0 +1,7 @@
+// The first coefficient of this key is 0xfff, above q.
+static KEY: kyberlib::PublicKey =
+    kyberlib::include_pubkey!("../fixtures/public_key_corrupted_kyber1024.pk");
+
+fn main() {
+    let _ = KEY;
+}
@@ -0,0 +1,8 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! `uniffi-bindgen` for the kyberlib bindings, see `README.md`.
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
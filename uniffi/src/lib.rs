@@ -0,0 +1,356 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! UniFFI bindings for kyberlib.
+//!
+//! The interface is declared with UniFFI's proc-macros, so there is no UDL
+//! file: keypair generation, encapsulation, decapsulation and the password
+//! sealing of secret keys, over byte arrays (`ByteArray` in Kotlin, `Data`
+//! in Swift) and a flat error enum. There are no callbacks and no objects,
+//! so nothing crosses the boundary but values.
+//!
+//! ## Ownership
+//!
+//! Every byte array is copied across the boundary in a `RustBuffer`; the
+//! generated code frees each buffer once it has been read, so neither side
+//! ever frees memory the other allocated. Rust zeroes its copy of a secret
+//! key, shared secret or password before returning.
+//!
+//! The copies handed to Kotlin and Swift are ordinary managed arrays that
+//! the garbage collector may move or duplicate; they cannot be zeroized
+//! reliably. Copy a secret out once, keep it in as few places as possible
+//! and overwrite the array when it is no longer needed (`fill(0)` in
+//! Kotlin, `resetBytes(in:)` in Swift) as a best effort.
+
+use kyberlib::sealing::{
+    SealWithPassword, SealedSecretKey, SealingParams,
+};
+use kyberlib::{
+    KYBER_CIPHERTEXT_BYTES, KYBER_PUBLIC_KEY_BYTES,
+    KYBER_SECRET_KEY_BYTES, KYBER_SHARED_SECRET_BYTES,
+};
+use rand::rngs::OsRng;
+use std::convert::TryInto;
+use zeroize::Zeroize;
+
+uniffi::setup_scaffolding!();
+
+/// Errors raised by the bindings, one per `kyberlib::KyberLibError`
+/// variant. Kotlin sees them as `KyberException` subclasses, Swift as
+/// `KyberError` cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum KyberError {
+    /// An input is of the wrong length for this security level.
+    InvalidInput,
+    /// A secret and public key do not match.
+    InvalidKey,
+    /// The length of an input buffer is invalid.
+    InvalidLength,
+    /// A ciphertext could not be decapsulated.
+    Decapsulation,
+    /// The operating system failed to provide random bytes.
+    RandomBytesGeneration,
+    /// A sealed secret key could not be opened: the password is wrong or
+    /// the data was tampered with.
+    Unsealing,
+    /// A secret share is corrupted, repeated or belongs to another key.
+    InvalidShare,
+    /// A signature over a key does not verify.
+    InvalidSignature,
+}
+
+impl From<kyberlib::KyberLibError> for KyberError {
+    fn from(err: kyberlib::KyberLibError) -> Self {
+        use kyberlib::KyberLibError::*;
+        match err {
+            InvalidInput => KyberError::InvalidInput,
+            InvalidKey => KyberError::InvalidKey,
+            InvalidLength => KyberError::InvalidLength,
+            Decapsulation => KyberError::Decapsulation,
+            RandomBytesGeneration => KyberError::RandomBytesGeneration,
+            Unsealing => KyberError::Unsealing,
+            InvalidShare => KyberError::InvalidShare,
+            InvalidSignature => KyberError::InvalidSignature,
+        }
+    }
+}
+
+impl core::fmt::Display for KyberError {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        use kyberlib::KyberLibError as E;
+        let err = match self {
+            KyberError::InvalidInput => E::InvalidInput,
+            KyberError::InvalidKey => E::InvalidKey,
+            KyberError::InvalidLength => E::InvalidLength,
+            KyberError::Decapsulation => E::Decapsulation,
+            KyberError::RandomBytesGeneration => {
+                E::RandomBytesGeneration
+            }
+            KyberError::Unsealing => E::Unsealing,
+            KyberError::InvalidShare => E::InvalidShare,
+            KyberError::InvalidSignature => E::InvalidSignature,
+        };
+        core::fmt::Display::fmt(&err, f)
+    }
+}
+
+impl std::error::Error for KyberError {}
+
+/// A public and secret key.
+#[derive(Clone, uniffi::Record)]
+pub struct KeyPair {
+    /// The public key.
+    pub public_key: Vec<u8>,
+    /// The secret key.
+    pub secret_key: Vec<u8>,
+}
+
+impl core::fmt::Debug for KeyPair {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A ciphertext and the shared secret it encapsulates.
+#[derive(Clone, uniffi::Record)]
+pub struct Encapsulation {
+    /// The ciphertext to send to the holder of the secret key.
+    pub ciphertext: Vec<u8>,
+    /// The shared secret.
+    pub shared_secret: Vec<u8>,
+}
+
+impl core::fmt::Debug for Encapsulation {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        f.debug_struct("Encapsulation")
+            .field("ciphertext", &self.ciphertext)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Size in bytes of a public key.
+#[uniffi::export]
+pub fn public_key_bytes() -> u32 {
+    KYBER_PUBLIC_KEY_BYTES as u32
+}
+
+/// Size in bytes of a secret key.
+#[uniffi::export]
+pub fn secret_key_bytes() -> u32 {
+    KYBER_SECRET_KEY_BYTES as u32
+}
+
+/// Size in bytes of a ciphertext.
+#[uniffi::export]
+pub fn ciphertext_bytes() -> u32 {
+    KYBER_CIPHERTEXT_BYTES as u32
+}
+
+/// Size in bytes of a shared secret.
+#[uniffi::export]
+pub fn shared_secret_bytes() -> u32 {
+    KYBER_SHARED_SECRET_BYTES as u32
+}
+
+/// Generates a keypair from the operating system RNG.
+///
+/// # Errors
+///
+/// Returns `KyberError::RandomBytesGeneration` if the RNG fails.
+#[uniffi::export]
+pub fn keypair() -> Result<KeyPair, KyberError> {
+    let mut keys = kyberlib::keypair(&mut OsRng)?;
+    let pair = KeyPair {
+        public_key: keys.public.to_vec(),
+        secret_key: keys.secret.to_vec(),
+    };
+    keys.secret.zeroize();
+    Ok(pair)
+}
+
+/// Encapsulates a fresh shared secret to `public_key`.
+///
+/// # Errors
+///
+/// Returns `KyberError::InvalidInput` if the public key has the wrong
+/// length and `KyberError::RandomBytesGeneration` if the RNG fails.
+#[uniffi::export]
+pub fn encapsulate(
+    public_key: Vec<u8>,
+) -> Result<Encapsulation, KyberError> {
+    let (ct, mut ss) = kyberlib::encapsulate(&public_key, &mut OsRng)?;
+    let encapsulation = Encapsulation {
+        ciphertext: ct.to_vec(),
+        shared_secret: ss.to_vec(),
+    };
+    ss.zeroize();
+    Ok(encapsulation)
+}
+
+/// Decapsulates `ciphertext` with `secret_key`.
+///
+/// A ciphertext of the right length that does not decapsulate yields a
+/// pseudo-random shared secret rather than an error.
+///
+/// # Errors
+///
+/// Returns `KyberError::InvalidInput` if either input has the wrong
+/// length.
+#[uniffi::export]
+pub fn decapsulate(
+    ciphertext: Vec<u8>,
+    mut secret_key: Vec<u8>,
+) -> Result<Vec<u8>, KyberError> {
+    let result = kyberlib::decapsulate(&ciphertext, &secret_key);
+    secret_key.zeroize();
+    let mut ss = result?;
+    let shared_secret = ss.to_vec();
+    ss.zeroize();
+    Ok(shared_secret)
+}
+
+/// Seals `secret_key` under `password` with Argon2id and
+/// XChaCha20-Poly1305, returning the versioned blob described in
+/// `kyberlib::sealing`.
+///
+/// `m_cost` is in KiB; see `SealingParams` for the accepted ranges.
+///
+/// # Errors
+///
+/// Returns `KyberError::InvalidInput` if the secret key has the wrong
+/// length or the Argon2id parameters are rejected, and
+/// `KyberError::RandomBytesGeneration` if the RNG fails.
+#[uniffi::export]
+pub fn seal_secret_key(
+    mut secret_key: Vec<u8>,
+    mut password: Vec<u8>,
+    m_cost: u32,
+    t_cost: u32,
+    lanes: u32,
+) -> Result<Vec<u8>, KyberError> {
+    let result = seal(&secret_key, &password, m_cost, t_cost, lanes);
+    secret_key.zeroize();
+    password.zeroize();
+    result
+}
+
+fn seal(
+    secret_key: &[u8],
+    password: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    lanes: u32,
+) -> Result<Vec<u8>, KyberError> {
+    let params = SealingParams::new(m_cost, t_cost, lanes)?;
+    let mut secret: kyberlib::SecretKey = secret_key
+        .try_into()
+        .map_err(|_| KyberError::InvalidInput)?;
+    let sealed =
+        secret.seal_with_password(password, &params, &mut OsRng);
+    secret.zeroize();
+    Ok(sealed?.to_bytes().to_vec())
+}
+
+/// Opens a blob produced by [`seal_secret_key`] and returns the secret
+/// key.
+///
+/// # Errors
+///
+/// Returns `KyberError::Unsealing` if the password is wrong or the blob
+/// was tampered with, `KyberError::InvalidLength` if it has the wrong size
+/// and `KyberError::InvalidInput` if its header is not understood.
+#[uniffi::export]
+pub fn open_sealed_secret_key(
+    sealed: Vec<u8>,
+    mut password: Vec<u8>,
+) -> Result<Vec<u8>, KyberError> {
+    let result = SealedSecretKey::from_bytes(&sealed)
+        .and_then(|sealed| sealed.open(&password));
+    password.zeroize();
+    let mut secret = result?;
+    let secret_key = secret.to_vec();
+    secret.zeroize();
+    Ok(secret_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let keys = keypair().unwrap();
+        let encapsulation =
+            encapsulate(keys.public_key.clone()).unwrap();
+        assert_eq!(
+            decapsulate(encapsulation.ciphertext, keys.secret_key)
+                .unwrap(),
+            encapsulation.shared_secret
+        );
+    }
+
+    #[test]
+    fn sealing_round_trip() {
+        let keys = keypair().unwrap();
+        let sealed = seal_secret_key(
+            keys.secret_key.clone(),
+            b"hunter2".to_vec(),
+            64,
+            1,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            open_sealed_secret_key(sealed.clone(), b"hunter2".to_vec())
+                .unwrap(),
+            keys.secret_key
+        );
+        assert_eq!(
+            open_sealed_secret_key(sealed, b"wrong".to_vec())
+                .unwrap_err(),
+            KyberError::Unsealing
+        );
+    }
+
+    #[test]
+    fn wrong_lengths_are_rejected() {
+        assert_eq!(
+            encapsulate(vec![0; 3]).unwrap_err(),
+            KyberError::InvalidInput
+        );
+        assert_eq!(
+            decapsulate(vec![0; 3], vec![0; 3]).unwrap_err(),
+            KyberError::InvalidInput
+        );
+        assert_eq!(
+            seal_secret_key(vec![0; 3], b"pw".to_vec(), 64, 1, 1)
+                .unwrap_err(),
+            KyberError::InvalidInput
+        );
+        assert_eq!(
+            open_sealed_secret_key(vec![0; 3], b"pw".to_vec())
+                .unwrap_err(),
+            KyberError::InvalidLength
+        );
+    }
+
+    #[test]
+    fn errors_display_like_kyberlib() {
+        assert_eq!(
+            KyberError::Unsealing.to_string(),
+            kyberlib::KyberLibError::Unsealing.to_string()
+        );
+    }
+}
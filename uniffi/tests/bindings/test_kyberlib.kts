@@ -0,0 +1,27 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+import uniffi.kyberlib_uniffi.*
+
+val keys = keypair()
+assert(keys.publicKey.size == publicKeyBytes().toInt())
+assert(keys.secretKey.size == secretKeyBytes().toInt())
+
+val encapsulation = encapsulate(keys.publicKey)
+val sharedSecret = decapsulate(encapsulation.ciphertext, keys.secretKey)
+assert(sharedSecret.contentEquals(encapsulation.sharedSecret))
+
+val sealed = sealSecretKey(keys.secretKey, "hunter2".toByteArray(), 64u, 1u, 1u)
+assert(openSealedSecretKey(sealed, "hunter2".toByteArray()).contentEquals(keys.secretKey))
+
+try {
+    encapsulate(ByteArray(3))
+    throw RuntimeException("a short public key was accepted")
+} catch (e: KyberException.InvalidInput) {
+}
+
+try {
+    openSealedSecretKey(sealed, "wrong".toByteArray())
+    throw RuntimeException("a wrong password was accepted")
+} catch (e: KyberException.Unsealing) {
+}
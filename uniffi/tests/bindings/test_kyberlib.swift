@@ -0,0 +1,33 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+import Foundation
+import kyberlib_uniffi
+
+let keys = try! keypair()
+assert(keys.publicKey.count == Int(publicKeyBytes()))
+assert(keys.secretKey.count == Int(secretKeyBytes()))
+
+let encapsulation = try! encapsulate(publicKey: keys.publicKey)
+let sharedSecret = try! decapsulate(
+    ciphertext: encapsulation.ciphertext, secretKey: keys.secretKey)
+assert(sharedSecret == encapsulation.sharedSecret)
+
+let sealed = try! sealSecretKey(
+    secretKey: keys.secretKey, password: Data("hunter2".utf8),
+    mCost: 64, tCost: 1, lanes: 1)
+assert(
+    try! openSealedSecretKey(sealed: sealed, password: Data("hunter2".utf8))
+        == keys.secretKey)
+
+do {
+    _ = try encapsulate(publicKey: Data(count: 3))
+    fatalError("a short public key was accepted")
+} catch KyberError.InvalidInput {
+}
+
+do {
+    _ = try openSealedSecretKey(sealed: sealed, password: Data("wrong".utf8))
+    fatalError("a wrong password was accepted")
+} catch KyberError.Unsealing {
+}
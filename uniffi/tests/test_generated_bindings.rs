@@ -0,0 +1,11 @@
+// Copyright © 2024 kyberlib. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Runs the Kotlin and Swift smoke tests against freshly generated
+//! bindings. Needs `kotlinc` and the JNA jar on the `CLASSPATH` for
+//! Kotlin, and `swiftc` for Swift.
+
+uniffi::build_foreign_language_testcases!(
+    "tests/bindings/test_kyberlib.kts",
+    "tests/bindings/test_kyberlib.swift",
+);